@@ -0,0 +1,116 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr};
+
+/*  Description:
+    derive(FitsRow) implements rustronomy_fits::FitsRow for structs with named
+    fields. Every field is read from (and written to) the table column with
+    the same name, in upper case like FITS column names usually are. Columns
+    are looked up case-insensitively. Another column name can be given with
+    the fits attribute:
+
+      #[derive(FitsRow)]
+      struct Star {
+        #[fits(name = "RA_DEG")]
+        ra: f64,
+        name: String,
+      }
+
+    Field types have to implement rustronomy_fits::FitsField.
+*/
+
+#[proc_macro_derive(FitsRow, attributes(fits))]
+pub fn derive_fits_row(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  match impl_fits_row(&input) {
+    Ok(tokens) => tokens.into(),
+    Err(err) => err.to_compile_error().into(),
+  }
+}
+
+fn impl_fits_row(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+  //(1) Only structs with named fields map onto columns
+  let fields = match &input.data {
+    Data::Struct(data) => match &data.fields {
+      Fields::Named(fields) => &fields.named,
+      _ => return Err(syn::Error::new_spanned(input, "FitsRow needs a struct with named fields")),
+    },
+    _ => return Err(syn::Error::new_spanned(input, "FitsRow can only be derived for structs")),
+  };
+
+  //(2) Column label of every field
+  let mut idents = Vec::new();
+  let mut types = Vec::new();
+  let mut labels = Vec::new();
+  for field in fields {
+    let ident = field.ident.clone().unwrap();
+    let mut label = ident.to_string().trim_start_matches("r#").to_uppercase();
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("fits")) {
+      attr.parse_nested_meta(|meta| match meta.path.is_ident("name") {
+        true => {
+          label = meta.value()?.parse::<LitStr>()?.value();
+          Ok(())
+        }
+        false => Err(meta.error("unknown fits attribute, expected `name`")),
+      })?;
+    }
+    idents.push(ident);
+    types.push(field.ty.clone());
+    labels.push(label);
+  }
+  let indices = 0..idents.len();
+
+  //(3) The impl itself, with fields in declaration order
+  let name = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+  Ok(quote! {
+    impl #impl_generics ::rustronomy_fits::FitsRow for #name #ty_generics #where_clause {
+      fn get_col_labels() -> ::std::vec::Vec<&'static str> {
+        ::std::vec![#(#labels),*]
+      }
+
+      fn from_entries(
+        entries: ::std::vec::Vec<::rustronomy_fits::TableEntry>,
+      ) -> ::std::result::Result<Self, ::std::boxed::Box<
+        dyn ::std::error::Error + ::std::marker::Send + ::std::marker::Sync,
+      >> {
+        <Self as ::rustronomy_fits::FitsRow>::check_entries(&entries)?;
+        let mut entries = entries.into_iter();
+        ::std::result::Result::Ok(Self {
+          #(#idents: <#types as ::rustronomy_fits::FitsField>::from_entry(entries.next().unwrap())?,)*
+        })
+      }
+
+      fn to_entries(&self) -> ::std::vec::Vec<::rustronomy_fits::TableEntry> {
+        ::std::vec![#(::rustronomy_fits::FitsField::to_entry(&self.#idents)),*]
+      }
+
+      fn add_cols(
+        builder: ::rustronomy_fits::TableBuilder,
+        widths: &[usize],
+      ) -> ::rustronomy_fits::TableBuilder {
+        #(let builder = <#types as ::rustronomy_fits::FitsField>::add_col(builder, #labels, widths[#indices]);)*
+        builder
+      }
+    }
+  })
+}
@@ -0,0 +1,79 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Read -> calibrate -> write.
+
+    Converts the science image of a NICMOS exposure from counts to count rates
+    (dividing by EXPTIME), masks every pixel that is flagged in the data quality
+    (DQ) extension and writes the result atomically, with a HISTORY record
+    describing what was done.
+
+        cargo run --example calibrate_image [input.fits] [output.fits]
+*/
+
+use std::{env, error::Error, path::PathBuf};
+
+use ndarray::Zip;
+use rustronomy_fits::prelude::*;
+
+fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+  let mut args = env::args().skip(1);
+  let input = args.next().map(PathBuf::from).unwrap_or_else(|| {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/Hubble_NICMOS.fits")
+  });
+  let output =
+    args.next().map(PathBuf::from).unwrap_or_else(|| env::temp_dir().join("calibrated.fits"));
+
+  //(1) Read the file and the exposure time from the primary header
+  let mut fits = Fits::open(&input)?;
+  let exptime: f64 = fits.primary()?.get_header().get_value_as("EXPTIME")?;
+  println!("read {} ({} HDU's), EXPTIME = {exptime}s", input.display(), fits.get_num_hdus());
+
+  //(2) The data quality mask lives in its own extension
+  let dq = match fits.get_by_name("DQ", 1).and_then(|hdu| hdu.get_data()) {
+    Some(Extension::Image(img)) => img.as_array::<i16>()?.to_owned(),
+    _ => return Err("file has no DQ extension".into()),
+  };
+
+  //(3) Calibrate the science image in place
+  let sci_index = (0..fits.get_num_hdus())
+    .find(|&index| fits.get_hdu(index).unwrap().get_extname().as_deref() == Some("SCI"))
+    .ok_or("file has no SCI extension")?;
+  let sci_hdu = fits.get_hdu_mut(sci_index).unwrap();
+  let Some(Extension::Image(sci)) = sci_hdu.get_data_mut() else {
+    return Err("SCI extension has no image".into());
+  };
+  let sci = sci.as_array_mut::<f32>()?;
+  let mut masked = 0;
+  Zip::from(sci).and(&dq).for_each(|px, &flag| match flag {
+    0 => *px /= exptime as f32,
+    _ => {
+      *px = f32::NAN;
+      masked += 1;
+    }
+  });
+  sci_hdu.get_header_mut().append_history("Divided by EXPTIME and masked pixels with DQ != 0");
+  println!("calibrated SCI (HDU #{sci_index}), masked {masked} pixels");
+
+  //(4) Write the result. Atomic writes never leave a half-written file behind
+  fits.write_with(&output, &WriteOptions::new().atomic(true))?;
+  println!("wrote {}", output.display());
+  Ok(())
+}
@@ -0,0 +1,120 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Read table -> crossmatch -> write.
+
+    Reads the pointings of the four WFPC2 chips from the ASCII table extension
+    of a WFPC2 file, matches every pointing to the nearest object of a small
+    catalog and writes the matches as a new table. Encoding ASCII tables is not
+    supported yet, so the matches are written as CSV.
+
+        cargo run --example crossmatch_table [input.fits] [output.csv]
+*/
+
+use std::{env, error::Error, fs, path::PathBuf};
+
+use rustronomy_fits::{prelude::*, push_row};
+
+//A tiny catalog: name, RA and Dec (degrees, J2000)
+const CATALOG: [(&str, f64, f64); 4] = [
+  ("NGC4151", 182.6357, 39.4057),
+  ("M87", 187.7059, 12.3911),
+  ("M31", 10.6847, 41.2690),
+  ("M51", 202.4696, 47.1952),
+];
+
+fn separation(ra1: f64, dec1: f64, ra2: f64, dec2: f64) -> f64 {
+  //Angular separation in arcseconds (haversine formula)
+  let (ra1, dec1, ra2, dec2) =
+    (ra1.to_radians(), dec1.to_radians(), ra2.to_radians(), dec2.to_radians());
+  let hav = ((dec2 - dec1) / 2.0).sin().powi(2)
+    + dec1.cos() * dec2.cos() * ((ra2 - ra1) / 2.0).sin().powi(2);
+  (2.0 * hav.sqrt().asin()).to_degrees() * 3600.0
+}
+
+fn column(header: &Header, label: &str) -> Result<usize, Box<dyn Error + Send + Sync>> {
+  //Columns are named by the TTYPEn keywords (n = column index + 1)
+  let n_fields: usize = header.get_value_as("TFIELDS")?;
+  (1..=n_fields)
+    .find(|n| {
+      header
+        .get_value_as::<String>(&format!("TTYPE{n}"))
+        .is_ok_and(|ttype| ttype.trim_matches(|c: char| c == '\'' || c.is_whitespace()) == label)
+    })
+    .map(|n| n - 1)
+    .ok_or_else(|| format!("table has no column {label}").into())
+}
+
+fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+  let mut args = env::args().skip(1);
+  let input = args.next().map(PathBuf::from).unwrap_or_else(|| {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/Hubble_WFPC2_1.fits")
+  });
+  let output =
+    args.next().map(PathBuf::from).unwrap_or_else(|| env::temp_dir().join("crossmatch.csv"));
+
+  //(1) Read the pointings from the table
+  let fits = Fits::open(&input)?;
+  let (header, table) = (0..fits.get_num_hdus())
+    .find_map(|index| {
+      let hdu = fits.get_hdu(index)?;
+      match hdu.get_data() {
+        Some(Extension::AsciiTable(table)) => Some((hdu.get_header(), table)),
+        _ => None,
+      }
+    })
+    .ok_or("file has no table")?;
+  let (ra_col, dec_col) = (column(header, "CRVAL1")?, column(header, "CRVAL2")?);
+  let n_rows = table.get_shape().1;
+
+  //(2) Find the nearest catalog object for every pointing
+  let mut matches = TableBuilder::new()
+    .col_i64("CHIP")
+    .col_f64("RA")
+    .col_f64("DEC")
+    .col_str("OBJECT", 8)
+    .col_f64("SEP_ARCSEC");
+  for row in 0..n_rows {
+    let ra = f64::try_from(table.get_entry(ra_col, row)?)?;
+    let dec = f64::try_from(table.get_entry(dec_col, row)?)?;
+    let (name, sep) = CATALOG
+      .iter()
+      .map(|&(name, obj_ra, obj_dec)| (name, separation(ra, dec, obj_ra, obj_dec)))
+      .min_by(|a, b| a.1.total_cmp(&b.1))
+      .unwrap();
+    push_row!(matches, row as i64 + 1, ra, dec, name, sep)?;
+  }
+  let matches = matches.build();
+  println!("{matches}");
+
+  //(3) Write the matches
+  let (n_cols, n_rows) = matches.get_shape();
+  let columns: Vec<Vec<String>> =
+    (0..n_cols).filter_map(|col| matches.get_fmtd_column(col)).collect();
+  let mut csv =
+    (0..n_cols).filter_map(|col| matches.get_col_label(col)).collect::<Vec<_>>().join(",");
+  for row in 0..n_rows {
+    let fields: Vec<&str> = columns.iter().map(|col| col[row].trim()).collect();
+    csv += &format!("\n{}", fields.join(","));
+  }
+  fs::write(&output, csv + "\n")?;
+  println!("wrote {} matches to {}", n_rows, output.display());
+  Ok(())
+}
@@ -0,0 +1,132 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Skeleton of a (remote) cutout service.
+
+    Serves image sections of the FITS files in a directory over HTTP. Requests
+    use cfitsio's extended file name syntax, relative to the served directory:
+
+        GET /cutout?Hubble_NICMOS.fits[SCI][100:200,100:200]
+
+    The reply lists the shape and some statistics of the cutout. A real
+    service would stream the pixels (or a FITS file) back instead.
+
+        cargo run --example cutout_service [directory] [address]
+*/
+
+use std::{
+  env,
+  error::Error,
+  io::{BufRead, BufReader, Write},
+  net::{TcpListener, TcpStream},
+  path::{Component, Path, PathBuf},
+  thread,
+};
+
+use num_traits::ToPrimitive;
+use rustronomy_fits::{impl_typed_image_dispatch, prelude::*};
+
+fn percent_decode(text: &str) -> String {
+  //Just enough URL decoding for extended file names
+  let (bytes, mut out, mut idx) = (text.as_bytes(), Vec::new(), 0);
+  while idx < bytes.len() {
+    let hex = text.get(idx + 1..idx + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+    match (bytes[idx], hex) {
+      (b'%', Some(byte)) => {
+        out.push(byte);
+        idx += 3;
+      }
+      (byte, _) => {
+        out.push(byte);
+        idx += 1;
+      }
+    }
+  }
+  String::from_utf8_lossy(&out).to_string()
+}
+
+fn statistics<T: FitsPixel + ToPrimitive>(img: &Image<T>) -> (f64, f64, f64) {
+  //Minimum, maximum and mean of the finite pixels
+  let values: Vec<f64> =
+    img.get_data().iter().filter_map(|px| px.to_f64()).filter(|px| px.is_finite()).collect();
+  let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+  let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+  (min, max, values.iter().sum::<f64>() / values.len() as f64)
+}
+
+fn cutout(root: &Path, spec: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+  //(1) Only files inside the served directory may be read
+  let path = ExtendedPath::parse(spec)?;
+  if !path.get_path().components().all(|part| matches!(part, Component::Normal(_))) {
+    return Err("path has to be relative to the served directory".into());
+  }
+  let file = root.join(spec);
+
+  //(2) Read the section and summarise it
+  let img = Fits::read_section(&file.to_string_lossy())?;
+  let shape = impl_typed_image_dispatch!(&img, img => img.get_shape().clone());
+  let (min, max, mean) = impl_typed_image_dispatch!(&img, img => statistics(img));
+  Ok(format!(
+    "bitpix: {}\nshape: {shape:?}\nmin: {min}\nmax: {max}\nmean: {mean}\n",
+    img.get_bitpix()
+  ))
+}
+
+fn handle(root: &Path, mut stream: TcpStream) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let mut request = String::new();
+  BufReader::new(&stream).read_line(&mut request)?;
+
+  //Request line looks like: GET /cutout?<extended file name> HTTP/1.1
+  let target = request.split_whitespace().nth(1).unwrap_or_default();
+  let (status, body) = match target.strip_prefix("/cutout?") {
+    None => ("404 Not Found", String::from("usage: GET /cutout?file.fits[hdu][section]\n")),
+    Some(spec) => match cutout(root, &percent_decode(spec)) {
+      Ok(body) => ("200 OK", body),
+      Err(err) => ("400 Bad Request", format!("{err}\n")),
+    },
+  };
+  let reply = format!(
+    "HTTP/1.1 {status}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\r\n{body}",
+    body.len()
+  );
+  stream.write_all(reply.as_bytes())?;
+  Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+  let mut args = env::args().skip(1);
+  let root = args
+    .next()
+    .map(PathBuf::from)
+    .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources"));
+  let address = args.next().unwrap_or(String::from("127.0.0.1:8080"));
+
+  let listener = TcpListener::bind(&address)?;
+  println!("serving cutouts of {} on http://{address}/cutout?...", root.display());
+  for stream in listener.incoming() {
+    let (root, stream) = (root.clone(), stream?);
+    thread::spawn(move || {
+      if let Err(err) = handle(&root, stream) {
+        eprintln!("error while handling request: {err}");
+      }
+    });
+  }
+  Ok(())
+}
@@ -0,0 +1,274 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Encrypted-at-rest FITS files through the BlockRead/BlockWrite traits.
+
+    Files are encrypted with AES-256-GCM in chunks of CHUNK_BLOCKS FITS blocks,
+    so the reader can jump to any HDU without decrypting everything in front of
+    it. The layout of an encrypted file is:
+
+        magic (8 bytes) | nonce (12 bytes) | file key (48 bytes) | chunk 0 | ...
+
+    Chunks are not encrypted with the key itself, but with a random key that
+    is generated for every file. The file key is stored in the preamble,
+    encrypted with the real key under a random nonce. Chunk nonces only have
+    to be unique for one file key, so they are simply the chunk index.
+
+    Every chunk is the ciphertext of CHUNK_BLOCKS blocks followed by a 16 byte
+    tag, except for the final chunk which is always shorter (and may even be
+    empty). The authenticated data marks the final chunk, so chunks cannot be
+    reordered, and truncated files are detected.
+
+    The key is read from RSF_KEY (64 hex characters). Without it, a random key
+    is used for the duration of the example.
+
+        RSF_KEY=... cargo run --example encrypted_backend [input.fits] [output.enc]
+*/
+
+use std::{
+  env,
+  error::Error,
+  fs::File,
+  io::{BufWriter, Read, Seek, SeekFrom, Write},
+  path::{Path, PathBuf},
+};
+
+use aes_gcm::{
+  aead::{Aead, KeyInit, OsRng, Payload},
+  AeadCore, Aes256Gcm, Key, Nonce,
+};
+use rustronomy_fits::prelude::*;
+
+const BLOCK_SIZE: usize = 2880;
+const CHUNK_BLOCKS: usize = 16;
+const CHUNK_SIZE: usize = CHUNK_BLOCKS * BLOCK_SIZE; // = 46kB of plaintext
+const TAG_SIZE: usize = 16;
+const MAGIC: &[u8; 8] = b"RSFAEAD2";
+const NONCE_SIZE: usize = 12;
+const WRAPPED_KEY_SIZE: usize = 32 + TAG_SIZE;
+const PREAMBLE_SIZE: usize = MAGIC.len() + NONCE_SIZE + WRAPPED_KEY_SIZE;
+
+fn nonce(chunk: u64) -> Nonce<<Aes256Gcm as AeadCore>::NonceSize> {
+  let mut nonce = [0u8; NONCE_SIZE];
+  nonce[4..].copy_from_slice(&chunk.to_be_bytes());
+  Nonce::clone_from_slice(&nonce)
+}
+
+struct EncryptingWriter {
+  file: BufWriter<File>,
+  cipher: Aes256Gcm,
+  buffer: Vec<u8>,
+  chunk: u64,
+  finished: bool,
+}
+
+impl EncryptingWriter {
+  fn create(path: &Path, key: &Key<Aes256Gcm>) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    //Every file gets its own random key, stored encrypted with the real key
+    let file_key = Aes256Gcm::generate_key(&mut OsRng);
+    let wrap_nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let wrapped = Aes256Gcm::new(key)
+      .encrypt(&wrap_nonce, Payload { msg: &file_key, aad: MAGIC })
+      .map_err(|_| "could not encrypt the file key")?;
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(MAGIC)?;
+    file.write_all(&wrap_nonce)?;
+    file.write_all(&wrapped)?;
+    Ok(EncryptingWriter {
+      file,
+      cipher: Aes256Gcm::new(&file_key),
+      buffer: Vec::with_capacity(CHUNK_SIZE),
+      chunk: 0,
+      finished: false,
+    })
+  }
+
+  fn seal(&mut self, len: usize, last: bool) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let plain: Vec<u8> = self.buffer.drain(..len).collect();
+    let payload = Payload { msg: &plain, aad: &[last as u8] };
+    let sealed =
+      self.cipher.encrypt(&nonce(self.chunk), payload).map_err(|_| "could not encrypt chunk")?;
+    self.file.write_all(&sealed)?;
+    self.chunk += 1;
+    Ok(())
+  }
+}
+
+impl BlockWrite for EncryptingWriter {
+  fn write_blocks(&mut self, buffer: &[u8]) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    if self.finished {
+      return Err("encrypted file was already finished".into());
+    }
+    self.buffer.extend_from_slice(buffer);
+    while self.buffer.len() >= CHUNK_SIZE {
+      self.seal(CHUNK_SIZE, false)?;
+    }
+    Ok(buffer.len() / BLOCK_SIZE)
+  }
+
+  fn flush(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+    //The final chunk is always shorter than a full one
+    if !self.finished {
+      self.seal(self.buffer.len(), true)?;
+      self.finished = true;
+    }
+    Ok(self.file.flush()?)
+  }
+}
+
+struct DecryptingReader {
+  file: File,
+  cipher: Aes256Gcm,
+  n_chunks: usize,
+  n_blocks: usize,
+  block_index: usize,
+  cache: Option<(usize, Vec<u8>)>, //the last decrypted chunk
+}
+
+impl DecryptingReader {
+  fn open(path: &Path, key: &Key<Aes256Gcm>) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    //(1) Check the preamble and decrypt the file key
+    let mut file = File::open(path)?;
+    let mut preamble = [0u8; PREAMBLE_SIZE];
+    file.read_exact(&mut preamble).map_err(|_| "file is too short")?;
+    let (magic, rest) = preamble.split_at(MAGIC.len());
+    if magic != MAGIC {
+      return Err("not an encrypted FITS file".into());
+    }
+    let (wrap_nonce, wrapped) = rest.split_at(NONCE_SIZE);
+    let file_key = Aes256Gcm::new(key)
+      .decrypt(Nonce::from_slice(wrap_nonce), Payload { msg: wrapped, aad: MAGIC })
+      .map_err(|_| "the file key could not be decrypted (wrong key?)")?;
+
+    //(2) The size of the plaintext follows from the size of the file
+    let body = file.metadata()?.len() as usize - PREAMBLE_SIZE;
+    let (n_full, rest) = (body / (CHUNK_SIZE + TAG_SIZE), body % (CHUNK_SIZE + TAG_SIZE));
+    if rest < TAG_SIZE || !(n_full * CHUNK_SIZE + rest - TAG_SIZE).is_multiple_of(BLOCK_SIZE) {
+      return Err("encrypted file is truncated".into());
+    }
+    Ok(DecryptingReader {
+      file,
+      cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&file_key)),
+      n_chunks: n_full + 1,
+      n_blocks: (n_full * CHUNK_SIZE + rest - TAG_SIZE) / BLOCK_SIZE,
+      block_index: 0,
+      cache: None,
+    })
+  }
+
+  fn chunk(&mut self, index: usize) -> Result<&[u8], Box<dyn Error + Send + Sync>> {
+    if self.cache.as_ref().map(|(cached, _)| *cached) != Some(index) {
+      //Read and decrypt the chunk. Failures mean that the file was tampered with
+      let offset = PREAMBLE_SIZE + index * (CHUNK_SIZE + TAG_SIZE);
+      let last = index + 1 == self.n_chunks;
+      let mut sealed = Vec::with_capacity(CHUNK_SIZE + TAG_SIZE);
+      self.file.seek(SeekFrom::Start(offset as u64))?;
+      (&mut self.file).take((CHUNK_SIZE + TAG_SIZE) as u64).read_to_end(&mut sealed)?;
+      let payload = Payload { msg: &sealed, aad: &[last as u8] };
+      let plain = self
+        .cipher
+        .decrypt(&nonce(index as u64), payload)
+        .map_err(|_| format!("chunk {index} could not be decrypted"))?;
+      self.cache = Some((index, plain));
+    }
+    Ok(&self.cache.as_ref().unwrap().1)
+  }
+}
+
+impl BlockRead for DecryptingReader {
+  fn read_blocks(&mut self, buffer: &mut [u8]) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let n_blocks = buffer.len() / BLOCK_SIZE;
+    if n_blocks > self.n_blocks - self.block_index {
+      return Err("read past the end of the encrypted file".into());
+    }
+
+    //Copy the requested blocks chunk by chunk
+    let mut pos = self.block_index * BLOCK_SIZE;
+    for out in buffer.chunks_mut(BLOCK_SIZE) {
+      let (index, offset) = (pos / CHUNK_SIZE, pos % CHUNK_SIZE);
+      out.copy_from_slice(&self.chunk(index)?[offset..offset + BLOCK_SIZE]);
+      pos += BLOCK_SIZE;
+    }
+    self.block_index += n_blocks;
+    Ok(n_blocks)
+  }
+
+  fn skip_blocks(&mut self, n_blocks: usize) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    //Skipped blocks are not decrypted at all
+    if n_blocks > self.n_blocks - self.block_index {
+      return Err("skipped past the end of the encrypted file".into());
+    }
+    self.block_index += n_blocks;
+    Ok(n_blocks)
+  }
+
+  fn get_block_len(&self) -> usize {
+    self.n_blocks
+  }
+  fn get_block_index(&self) -> usize {
+    self.block_index
+  }
+}
+
+fn read_key() -> Result<Key<Aes256Gcm>, Box<dyn Error + Send + Sync>> {
+  let Ok(hex) = env::var("RSF_KEY") else {
+    println!("RSF_KEY is not set, using a random key");
+    return Ok(Aes256Gcm::generate_key(&mut OsRng));
+  };
+  if hex.len() != 64 {
+    return Err("RSF_KEY should be 64 hex characters (256 bits)".into());
+  }
+  let bytes = (0..32)
+    .map(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16))
+    .collect::<Result<Vec<u8>, _>>()?;
+  Ok(*Key::<Aes256Gcm>::from_slice(&bytes))
+}
+
+fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+  let mut args = env::args().skip(1);
+  let input = args.next().map(PathBuf::from).unwrap_or_else(|| {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/Hubble_NICMOS.fits")
+  });
+  let output =
+    args.next().map(PathBuf::from).unwrap_or_else(|| env::temp_dir().join("encrypted.fits.enc"));
+  let key = read_key()?;
+
+  //(1) Encrypt the file
+  let fits = Fits::open(&input)?;
+  let manifest: Vec<String> = fits.manifest().iter().map(|entry| entry.to_string()).collect();
+  fits.write_to(&mut EncryptingWriter::create(&output, &key)?, &WriteOptions::new())?;
+  println!("encrypted {} -> {}", input.display(), output.display());
+
+  //(2) Read it back, the HDU's should be identical
+  let decrypted =
+    Fits::read_from(&mut DecryptingReader::open(&output, &key)?, &ReadOptions::new())?;
+  for (entry, original) in decrypted.manifest().iter().zip(&manifest) {
+    assert_eq!(&entry.to_string(), original, "decrypted HDU differs from the original");
+    println!("{entry}");
+  }
+
+  //(3) Anything else but the right key is refused
+  let wrong_key = Aes256Gcm::generate_key(&mut OsRng);
+  match DecryptingReader::open(&output, &wrong_key) {
+    Ok(_) => return Err("file was decrypted with the wrong key".into()),
+    Err(err) => println!("wrong key: {err}"),
+  }
+  Ok(())
+}
@@ -0,0 +1,30 @@
+#ifndef RUSTRONOMY_FITS_H
+#define RUSTRONOMY_FITS_H
+
+/* Generated with cbindgen from src/capi.rs, do not edit by hand */
+
+#include <stddef.h>
+
+typedef struct RsfFits RsfFits;
+
+const char *rsf_last_error(void);
+
+RsfFits *rsf_fits_open(const char *path);
+
+void rsf_fits_free(RsfFits *fits);
+
+size_t rsf_fits_num_hdus(const RsfFits *fits);
+
+int rsf_fits_get_image(const RsfFits *fits,
+                       size_t hdu,
+                       const void **data,
+                       int *bitpix,
+                       size_t *shape,
+                       size_t max_naxis,
+                       size_t *naxis);
+
+char *rsf_fits_get_keyword(const RsfFits *fits, size_t hdu, const char *keyword);
+
+void rsf_string_free(char *string);
+
+#endif /* RUSTRONOMY_FITS_H */
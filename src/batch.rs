@@ -0,0 +1,165 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    A BatchReader opens many FITS files in parallel and hands each of them to
+    a user-supplied function. To keep thousands of large files from being
+    resident at the same time, a memory budget can be set: before a file is
+    opened, the raw size of its data units is reserved from the budget, and
+    the reservation is only returned once the function is done with the file.
+    Threads that would exceed the budget wait until enough memory is freed.
+*/
+
+use std::{
+  error::Error,
+  path::{Path, PathBuf},
+  sync::{Condvar, Mutex, PoisonError},
+};
+
+use rayon::prelude::*;
+
+use crate::{
+  fits::Fits, header_data_unit::HeaderDataUnit, raw::raw_io::RawFitsReader,
+  read_options::ReadOptions,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct BatchReader {
+  paths: Vec<PathBuf>,
+  opts: ReadOptions,
+  memory_budget: Option<usize>, //in bytes, None means unlimited
+}
+
+impl BatchReader {
+  pub fn new(paths: Vec<PathBuf>) -> Self {
+    BatchReader { paths, ..Self::default() }
+  }
+
+  pub fn read_options(mut self, opts: ReadOptions) -> Self {
+    self.opts = opts;
+    self
+  }
+
+  pub fn memory_budget(mut self, bytes: usize) -> Self {
+    /*  Maximum number of data unit bytes that may be resident at once. Files
+        whose data units are larger than the whole budget are still read, but
+        only when nothing else is.
+    */
+    self.memory_budget = Some(bytes);
+    self
+  }
+
+  pub fn get_paths(&self) -> &[PathBuf] {
+    &self.paths
+  }
+  pub fn get_read_options(&self) -> &ReadOptions {
+    &self.opts
+  }
+  pub fn get_memory_budget(&self) -> Option<usize> {
+    self.memory_budget
+  }
+
+  pub fn map<R, F>(&self, func: F) -> Vec<(PathBuf, Result<R, String>)>
+  where
+    R: Send,
+    F: Fn(&Path, Fits) -> Result<R, Box<dyn Error + Send + Sync>> + Sync,
+  {
+    /*  Opens every file and applies func to it, in parallel. The results are
+        returned in the order of the paths. Errors are not Send, so they are
+        turned into strings right away (like in HeaderCatalog::scan).
+    */
+    let budget = self.memory_budget.map(MemoryBudget::new);
+    self
+      .paths
+      .par_iter()
+      .map(|path| {
+        let result = self.map_file(path, budget.as_ref(), &func).map_err(|err| err.to_string());
+        (path.clone(), result)
+      })
+      .collect()
+  }
+
+  fn map_file<R, F>(
+    &self,
+    path: &Path,
+    budget: Option<&MemoryBudget>,
+    func: &F,
+  ) -> Result<R, Box<dyn Error + Send + Sync>>
+  where
+    F: Fn(&Path, Fits) -> Result<R, Box<dyn Error + Send + Sync>>,
+  {
+    //(1) Reserve memory for the data units, waiting for it if necessary
+    let _reservation = match budget {
+      Some(budget) => Some(budget.reserve(Self::data_size(path)?)),
+      None => None,
+    };
+
+    //(2) Only then read the file. The reservation is returned on drop
+    func(path, Fits::open_with(path, &self.opts)?)
+  }
+
+  fn data_size(path: &Path) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    //Raw size of all data units in the file, from the headers only
+    let mut reader = RawFitsReader::new(path)?;
+    let mut size = 0u64;
+    while reader.get_block_index() < reader.get_block_len() {
+      let header = HeaderDataUnit::decode_header_only(&mut reader)?;
+      size = size.saturating_add(HeaderDataUnit::data_byte_len(&header)?);
+    }
+    //Reservations are clamped to the budget anyway
+    Ok(usize::try_from(size).unwrap_or(usize::MAX))
+  }
+}
+
+struct MemoryBudget {
+  limit: usize,
+  used: Mutex<usize>,
+  freed: Condvar,
+}
+
+impl MemoryBudget {
+  fn new(limit: usize) -> Self {
+    MemoryBudget { limit, used: Mutex::new(0), freed: Condvar::new() }
+  }
+
+  fn reserve(&self, bytes: usize) -> Reservation<'_> {
+    //Oversized requests are clamped, so they can be granted on an idle budget
+    let bytes = bytes.min(self.limit);
+    let mut used = self.used.lock().unwrap_or_else(PoisonError::into_inner);
+    while *used + bytes > self.limit {
+      used = self.freed.wait(used).unwrap_or_else(PoisonError::into_inner);
+    }
+    *used += bytes;
+    Reservation { budget: self, bytes }
+  }
+}
+
+struct Reservation<'a> {
+  //Memory taken from a budget, which is returned when the reservation drops
+  budget: &'a MemoryBudget,
+  bytes: usize,
+}
+
+impl Drop for Reservation<'_> {
+  fn drop(&mut self) {
+    let mut used = self.budget.used.lock().unwrap_or_else(PoisonError::into_inner);
+    *used -= self.bytes;
+    self.budget.freed.notify_all();
+  }
+}
@@ -21,9 +21,9 @@ use std::fmt::{self, Display, Formatter};
 
 use crate::hdu_err::InvalidRecordValueError;
 
-const VALID_BITPIX_VALUES: [&'static str; 6] = ["8", "16", "32", "64", "-32", "-64"];
+const VALID_BITPIX_VALUES: [&str; 6] = ["8", "16", "32", "64", "-32", "-64"];
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Bitpix {
   Byte,
   Short,
@@ -49,7 +49,7 @@ impl Bitpix {
     }
   }
 
-  pub(crate) fn to_code(&self) -> isize {
+  pub(crate) fn to_code(self) -> isize {
     use Bitpix::*;
     match self {
       Byte => 8,
@@ -61,6 +61,22 @@ impl Bitpix {
     }
   }
 
+  pub fn is_float(&self) -> bool {
+    matches!(self, Bitpix::Spf | Bitpix::Dpf)
+  }
+
+  fn int_rank(&self) -> usize {
+    //Integer types ordered by width (floats rank above all of them)
+    use Bitpix::*;
+    match self {
+      Byte => 0,
+      Short => 1,
+      Int => 2,
+      Long => 3,
+      Spf | Dpf => 4,
+    }
+  }
+
   pub(crate) fn byte() -> Self {
     Self::Byte
   }
@@ -88,9 +104,96 @@ impl Display for Bitpix {
       Byte => write!(f, "u8"),
       Short => write!(f, "i16"),
       Int => write!(f, "i32"),
-      Long => write!(f, "i62"),
+      Long => write!(f, "i64"),
       Spf => write!(f, "f32"),
       Dpf => write!(f, "f64"),
     }
   }
 }
+
+pub fn promote(a: Bitpix, b: Bitpix) -> Bitpix {
+  /*  Pixel type of the result of combining images of type a and b (the rules
+      NumPy uses as well):
+      - integers are promoted to the widest of the two integer types,
+      - f32 is kept for u8 and i16, which it represents exactly. Combined with
+        i32 or i64 the result is f64, since f32 would lose precision,
+      - anything combined with f64 gives f64.
+  */
+  use Bitpix::*;
+  match (a, b) {
+    (Dpf, _) | (_, Dpf) => Dpf,
+    (Spf, other) | (other, Spf) if other.int_rank() <= Short.int_rank() || other == Spf => Spf,
+    (Spf, _) | (_, Spf) => Dpf,
+    (a, b) => match a.int_rank() >= b.int_rank() {
+      true => a,
+      false => b,
+    },
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PromotionRules {
+  /*  THIS STRUCT IS PART OF THE USER-FACING API
+      Promotion rules used by operations that combine images or produce
+      floating point results (like Stack, TypedImage::convolve and
+      TypedImage::reduce). The default rules are those of promote(), with
+      floating point results in double precision. Rules can be overridden for
+      specific pairs of types, later rules take precedence over earlier ones.
+  */
+  keep_f32: bool,
+  overrides: Vec<(Bitpix, Bitpix, Bitpix)>,
+}
+
+impl PromotionRules {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn keep_f32(mut self, keep: bool) -> Self {
+    /*  Keeps single precision results single precision: f32 is no longer
+        promoted to f64 when combined with integers, and floating point
+        results of f32 (and u8, i16, i32 and i64) images are f32.
+    */
+    self.keep_f32 = keep;
+    self
+  }
+
+  pub fn rule(mut self, a: Bitpix, b: Bitpix, result: Bitpix) -> Self {
+    //Overrides the result of combining a and b (in either order)
+    self.overrides.push((a, b, result));
+    self
+  }
+
+  pub fn get_keep_f32(&self) -> bool {
+    self.keep_f32
+  }
+  pub fn get_overrides(&self) -> &[(Bitpix, Bitpix, Bitpix)] {
+    &self.overrides
+  }
+
+  pub fn promote(&self, a: Bitpix, b: Bitpix) -> Bitpix {
+    //(1) Overrides take precedence, the last one wins
+    let found =
+      self.overrides.iter().rev().find(|(x, y, _)| (*x, *y) == (a, b) || (*y, *x) == (a, b));
+    if let Some((_, _, result)) = found {
+      return *result;
+    }
+
+    //(2) Then the default rules, possibly keeping f32
+    match (a, b) {
+      (Bitpix::Spf, other) | (other, Bitpix::Spf) if self.keep_f32 && other != Bitpix::Dpf => {
+        Bitpix::Spf
+      }
+      _ => promote(a, b),
+    }
+  }
+
+  pub fn float_result(&self, bitpix: Bitpix) -> Bitpix {
+    //Pixel type of floating point results (averages, convolutions...) of an
+    //image with this pixel type
+    match self.keep_f32 {
+      true => self.promote(bitpix, Bitpix::Spf),
+      false => self.promote(bitpix, Bitpix::Dpf),
+    }
+  }
+}
@@ -0,0 +1,234 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    C-compatible interface for embedding this crate in C/C++ reduction
+    packages (enabled with the capi feature). The crate is built as a static
+    library; a shared library can be built on demand with
+    `cargo rustc --release --features capi --crate-type cdylib`. The matching
+    header is include/rustronomy_fits.h, which can be regenerated with
+    `cbindgen --config cbindgen.toml`.
+
+    Files are opened as an opaque RsfFits handle that owns all decoded data.
+    Pointers handed out (image data, the last error message) stay valid as
+    long as the handle (or, for errors, until the next call on the same
+    thread). Strings returned by rsf_fits_get_keyword are owned by the caller
+    and must be released with rsf_string_free. Functions that fail return
+    NULL or -1 and store a message that can be read with rsf_last_error.
+*/
+
+use std::{
+  cell::RefCell,
+  error::Error,
+  ffi::{c_char, c_int, c_void, CStr, CString},
+  path::Path,
+  ptr,
+};
+
+use crate::{
+  extensions::Extension, fits::Fits, hdu_err::MissingDataErr, keyword_value::unquote,
+  section_err::HduNotFoundErr,
+};
+
+thread_local! {
+  static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+pub struct RsfFits {
+  fits: Fits,
+}
+
+fn set_error(err: Box<dyn Error + Send + Sync>) {
+  //Interior NUL bytes cannot be represented in a C string
+  let msg = CString::new(err.to_string().replace('\0', " ")).unwrap();
+  LAST_ERROR.with(|last| *last.borrow_mut() = Some(msg));
+}
+
+unsafe fn to_str<'a>(
+  ptr: *const c_char,
+  what: &str,
+) -> Result<&'a str, Box<dyn Error + Send + Sync>> {
+  if ptr.is_null() {
+    Err(format!("{what} is NULL"))?;
+  }
+  Ok(CStr::from_ptr(ptr).to_str()?)
+}
+
+unsafe fn to_fits<'a>(fits: *const RsfFits) -> Result<&'a Fits, Box<dyn Error + Send + Sync>> {
+  match fits.as_ref() {
+    Some(handle) => Ok(&handle.fits),
+    None => Err("FITS handle is NULL")?,
+  }
+}
+
+#[no_mangle]
+pub extern "C" fn rsf_last_error() -> *const c_char {
+  //Message of the last error on this thread, or NULL if there was none
+  LAST_ERROR.with(|last| match last.borrow().as_ref() {
+    Some(msg) => msg.as_ptr(),
+    None => ptr::null(),
+  })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rsf_fits_open(path: *const c_char) -> *mut RsfFits {
+  /*  Safety: path must be NULL or a valid NUL-terminated string. Returns NULL
+      if the file could not be read.
+  */
+  let open = || -> Result<Fits, Box<dyn Error + Send + Sync>> {
+    Fits::open(Path::new(to_str(path, "path")?))
+  };
+  match open() {
+    Ok(fits) => Box::into_raw(Box::new(RsfFits { fits })),
+    Err(err) => {
+      set_error(err);
+      ptr::null_mut()
+    }
+  }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rsf_fits_free(fits: *mut RsfFits) {
+  //Safety: fits must be NULL or a handle from rsf_fits_open, freed only once
+  if !fits.is_null() {
+    drop(Box::from_raw(fits));
+  }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rsf_fits_num_hdus(fits: *const RsfFits) -> usize {
+  //Safety: fits must be NULL or a live handle. Returns 0 for NULL
+  match fits.as_ref() {
+    Some(handle) => handle.fits.get_num_hdus(),
+    None => 0,
+  }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rsf_fits_get_image(
+  fits: *const RsfFits,
+  hdu: usize,
+  data: *mut *const c_void,
+  bitpix: *mut c_int,
+  shape: *mut usize,
+  max_naxis: usize,
+  naxis: *mut usize,
+) -> c_int {
+  /*  Safety: fits must be a live handle, data, bitpix and naxis must be valid
+      pointers and shape must point to at least max_naxis elements.
+
+      Returns a pointer to the pixels of the image in the given HDU, which are
+      stored in file order (NAXIS1 runs fastest) as native-endian values of
+      the type given by bitpix. The pointer is valid as long as the handle.
+      shape receives NAXIS1, NAXIS2, ... and naxis the number of axes.
+  */
+  let get_image = || -> Result<(), Box<dyn Error + Send + Sync>> {
+    //(1) Find the image
+    let fits = to_fits(fits)?;
+    let hdu_ref = match fits.get_hdu(hdu) {
+      Some(hdu_ref) => hdu_ref,
+      None => Err(HduNotFoundErr::new(hdu.to_string()))?,
+    };
+    let img = match hdu_ref.get_data() {
+      Some(Extension::Image(img)) => img,
+      _ => Err(MissingDataErr::new("an image"))?,
+    };
+
+    //(2) Check that the caller can receive the shape
+    let img_shape = crate::impl_typed_image_dispatch!(img, img => img.get_shape().clone());
+    if img_shape.len() > max_naxis {
+      Err(format!("image has {} axes, but shape only holds {max_naxis}", img_shape.len()))?;
+    }
+    if data.is_null() || bitpix.is_null() || naxis.is_null() || shape.is_null() {
+      Err("output pointer is NULL")?;
+    }
+
+    //(3) The pixels are only handed out if they are contiguous in file order
+    let img_data = crate::impl_typed_image_dispatch!(img, img => {
+      let pixels = img.get_data();
+      match pixels.t().is_standard_layout() {
+        true => Some(pixels.as_ptr() as *const c_void),
+        false => None,
+      }
+    });
+    let img_data = match img_data {
+      Some(img_data) => img_data,
+      None => Err("image data is not contiguous in file order")?,
+    };
+
+    //(4) Write the outputs
+    *data = img_data;
+    *bitpix = img.get_bitpix().to_code() as c_int;
+    *naxis = img_shape.len();
+    for (idx, len) in img_shape.iter().enumerate() {
+      *shape.add(idx) = *len;
+    }
+    Ok(())
+  };
+
+  match get_image() {
+    Ok(()) => 0,
+    Err(err) => {
+      set_error(err);
+      -1
+    }
+  }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rsf_fits_get_keyword(
+  fits: *const RsfFits,
+  hdu: usize,
+  keyword: *const c_char,
+) -> *mut c_char {
+  /*  Safety: fits must be a live handle and keyword a valid NUL-terminated
+      string. Returns the value of the keyword (string values without quotes)
+      as a new string, to be released with rsf_string_free, or NULL if the
+      HDU or the keyword does not exist.
+  */
+  let get_keyword = || -> Result<CString, Box<dyn Error + Send + Sync>> {
+    let fits = to_fits(fits)?;
+    let keyword = to_str(keyword, "keyword")?;
+    let header = match fits.get_hdu(hdu) {
+      Some(hdu_ref) => hdu_ref.get_header(),
+      None => Err(HduNotFoundErr::new(hdu.to_string()))?,
+    };
+    let value = match header.get_value(keyword) {
+      Some(value) => unquote(value).unwrap_or_else(|| value.trim().to_string()),
+      None => Err(format!("keyword {keyword} not found in HDU {hdu}"))?,
+    };
+    Ok(CString::new(value.replace('\0', " "))?)
+  };
+
+  match get_keyword() {
+    Ok(value) => value.into_raw(),
+    Err(err) => {
+      set_error(err);
+      ptr::null_mut()
+    }
+  }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rsf_string_free(string: *mut c_char) {
+  //Safety: string must be NULL or a string returned by this library
+  if !string.is_null() {
+    drop(CString::from_raw(string));
+  }
+}
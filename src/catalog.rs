@@ -0,0 +1,172 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    A HeaderCatalog is a small "file database": it scans a directory tree for
+    FITS files and records the values of selected keywords for every HDU.
+    Only the headers are read, data units are skipped, so scanning thousands
+    of files is cheap.
+*/
+
+use std::{
+  error::Error,
+  fs,
+  path::{Path, PathBuf},
+};
+
+use indexmap::IndexMap;
+use rayon::prelude::*;
+
+use crate::{
+  header_data_unit::HeaderDataUnit, io_err::FitsIoErr, keyword_value::unquote,
+  raw::raw_io::RawFitsReader,
+};
+
+//File extensions that are recognised as FITS files (case insensitive)
+const FITS_EXTENSIONS: [&str; 4] = ["fits", "fit", "fts", "fz"];
+
+#[derive(Debug, Clone)]
+pub struct CatalogEntry {
+  /*  THIS STRUCT IS PART OF THE USER-FACING API
+      One entry per HDU. String values are stored without their quotes, other
+      values as they appear in the header. Keywords that are missing from the
+      header are left out.
+  */
+  path: PathBuf,
+  hdu: usize,
+  values: IndexMap<String, String>,
+}
+
+impl CatalogEntry {
+  pub fn get_path(&self) -> &Path {
+    &self.path
+  }
+  pub fn get_hdu(&self) -> usize {
+    self.hdu
+  }
+  pub fn get_value(&self, keyword: &str) -> Option<&str> {
+    self.values.get(keyword).map(String::as_str)
+  }
+  pub fn get_values(&self) -> &IndexMap<String, String> {
+    &self.values
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HeaderCatalog {
+  keywords: Vec<String>,
+  entries: Vec<CatalogEntry>,
+  failed: Vec<(PathBuf, String)>,
+}
+
+impl HeaderCatalog {
+  //Keywords that observers usually want to search on
+  pub const DEFAULT_KEYWORDS: [&'static str; 4] = ["OBJECT", "DATE-OBS", "FILTER", "EXPTIME"];
+
+  pub fn scan(dir: &Path, keywords: &[&str]) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    /*  Scans dir (recursively) for FITS files. Files that cannot be read are
+        not fatal: they are listed in get_failed() together with the error.
+    */
+
+    //(1) Find all FITS files, in a predictable order
+    let mut paths = Vec::new();
+    Self::find_files(dir, &mut paths)?;
+    paths.sort();
+
+    //(2) Read the headers of all files in parallel (errors are not Send, so
+    //they are turned into strings right away)
+    let results: Vec<_> = paths
+      .into_par_iter()
+      .map(|path| (Self::scan_file(&path, keywords).map_err(|err| err.to_string()), path))
+      .collect();
+
+    //(3) Sort out the good from the bad
+    let mut catalog = HeaderCatalog {
+      keywords: keywords.iter().map(|kw| kw.to_string()).collect(),
+      ..Self::default()
+    };
+    for (result, path) in results {
+      match result {
+        Ok(mut entries) => catalog.entries.append(&mut entries),
+        Err(err) => catalog.failed.push((path, err)),
+      }
+    }
+    Ok(catalog)
+  }
+
+  pub fn get_keywords(&self) -> &[String] {
+    &self.keywords
+  }
+  pub fn get_entries(&self) -> &[CatalogEntry] {
+    &self.entries
+  }
+  pub fn get_failed(&self) -> &[(PathBuf, String)] {
+    &self.failed
+  }
+
+  pub fn find(&self, keyword: &str, value: &str) -> Vec<&CatalogEntry> {
+    //All HDU's where the keyword has exactly this value
+    self.filter(keyword, |found| found == value)
+  }
+
+  pub fn filter(&self, keyword: &str, pred: impl Fn(&str) -> bool) -> Vec<&CatalogEntry> {
+    //All HDU's where the keyword is present and its value matches pred
+    self.entries.iter().filter(|entry| entry.get_value(keyword).is_some_and(&pred)).collect()
+  }
+
+  fn find_files(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<(), FitsIoErr> {
+    let entries = fs::read_dir(dir).map_err(|err| FitsIoErr::new(dir, "read directory", err))?;
+    for entry in entries {
+      let path = entry.map_err(|err| FitsIoErr::new(dir, "read directory", err))?.path();
+      if path.is_dir() {
+        Self::find_files(&path, paths)?;
+      } else if Self::is_fits_file(&path) {
+        paths.push(path);
+      }
+    }
+    Ok(())
+  }
+
+  fn is_fits_file(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+      Some(ext) => FITS_EXTENSIONS.iter().any(|fits_ext| ext.eq_ignore_ascii_case(fits_ext)),
+      None => false,
+    }
+  }
+
+  fn scan_file(
+    path: &Path,
+    keywords: &[&str],
+  ) -> Result<Vec<CatalogEntry>, Box<dyn Error + Send + Sync>> {
+    let mut reader = RawFitsReader::new(path)?;
+    let mut entries = Vec::new();
+    while reader.get_block_index() < reader.get_block_len() {
+      let header = HeaderDataUnit::decode_header_only(&mut reader)?;
+      let values = keywords
+        .iter()
+        .filter_map(|&kw| {
+          let raw = header.get_value(kw)?;
+          Some((kw.to_string(), unquote(raw).unwrap_or_else(|| raw.trim().to_string())))
+        })
+        .collect();
+      entries.push(CatalogEntry { path: path.to_path_buf(), hdu: entries.len(), values });
+    }
+    Ok(entries)
+  }
+}
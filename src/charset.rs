@@ -0,0 +1,131 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Header records may only contain printable ASCII characters (0x20 to 0x7E).
+    Files with Latin-1 (or UTF-8) text in their headers are common in the wild
+    though, for example in observer names. The charset policy determines if
+    such headers are refused, or repaired by replacing the illegal bytes.
+*/
+
+use crate::{
+  header_err::IllegalCharErr,
+  validation::{Diagnostic, Severity},
+};
+
+//Name of the (pseudo) validation profile of charset diagnostics
+const PROFILE: &str = "charset";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CharsetPolicy {
+  /*  THIS ENUM IS PART OF THE USER-FACING API
+      Strict refuses headers with illegal characters. Replace substitutes
+      every illegal byte (or character, when writing) with the given byte,
+      which should be printable ASCII itself. If it is not, '?' is used.
+  */
+  #[default]
+  Strict,
+  Replace(u8),
+}
+
+impl CharsetPolicy {
+  fn replacement(&self) -> Option<char> {
+    match self {
+      CharsetPolicy::Strict => None,
+      CharsetPolicy::Replace(byte) if is_legal(*byte) => Some(*byte as char),
+      CharsetPolicy::Replace(_) => Some('?'),
+    }
+  }
+}
+
+pub(crate) fn is_legal(byte: u8) -> bool {
+  (0x20..=0x7E).contains(&byte)
+}
+
+pub(crate) fn card_text(card: &[u8]) -> String {
+  //Shows the card as Latin-1 (the most likely culprit), escaping control chars
+  card
+    .iter()
+    .map(|&byte| match byte {
+      0x20..=0x7E | 0xA0..=0xFF => (byte as char).to_string(),
+      _ => format!("\\x{byte:02X}"),
+    })
+    .collect::<String>()
+    .trim_end()
+    .to_string()
+}
+
+pub(crate) fn repair_card(
+  card: &mut [u8],
+  policy: CharsetPolicy,
+) -> Result<Option<Diagnostic>, IllegalCharErr> {
+  //(1) Cards with only legal bytes are fine as-is
+  let Some(first) = card.iter().position(|&byte| !is_legal(byte)) else {
+    return Ok(None);
+  };
+  let original = card_text(card);
+  let Some(replacement) = policy.replacement() else {
+    return Err(IllegalCharErr::new(original, first + 1, card[first]));
+  };
+
+  //(2) Replace all illegal bytes, reporting the first one
+  let byte = card[first];
+  let mut count = 0;
+  for illegal in card.iter_mut().filter(|byte| !is_legal(**byte)) {
+    *illegal = replacement as u8;
+    count += 1;
+  }
+  let keyword = String::from_utf8_lossy(&card[..8]).trim().to_string();
+  let message = format!(
+    "replaced {count} illegal byte(s) with '{replacement}' (first was 0x{byte:02X} in column {})",
+    first + 1
+  );
+  Ok(Some(Diagnostic::new(PROFILE, 0, &keyword, &original, message, Severity::Warning)))
+}
+
+pub(crate) fn repair_text(
+  keyword: &str,
+  text: &mut String,
+  policy: CharsetPolicy,
+) -> Option<Diagnostic> {
+  /*  Checks text that is about to be written to a header record. With the
+      strict policy an Error diagnostic is returned and the text is left
+      alone, otherwise illegal characters are replaced.
+  */
+  let (column, ch) =
+    text.chars().enumerate().find(|(_, ch)| !ch.is_ascii() || !is_legal(*ch as u8))?;
+  let original = text.clone();
+  let (message, severity) = match policy.replacement() {
+    None => (format!("illegal character {ch:?} at position {}", column + 1), Severity::Error),
+    Some(replacement) => {
+      *text = text
+        .chars()
+        .map(|ch| match ch.is_ascii() && is_legal(ch as u8) {
+          true => ch,
+          false => replacement,
+        })
+        .collect();
+      (
+        format!("replaced illegal character(s) with '{replacement}' (first was {ch:?})"),
+        Severity::Warning,
+      )
+    }
+  };
+  Some(Diagnostic::new(PROFILE, 0, keyword, &original, message, severity))
+}
@@ -0,0 +1,194 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Implementation of the FITS checksum convention (FITS standard, appendix J).
+    The DATASUM keyword holds the 32-bit ones' complement sum of the data unit
+    (as a decimal string) and the CHECKSUM keyword is chosen such that the sum
+    of the whole HDU (header + data) equals negative zero (all bits set). It
+    is stored as a 16-character ASCII encoding of the complement of the sum.
+*/
+
+use std::{
+  fs::{File, OpenOptions},
+  io::{Read, Seek, SeekFrom, Write},
+  path::Path,
+};
+
+use crate::{fits_index::HduLayout, io_err::FitsIoErr, raw::raw_io};
+
+const BLOCK_SIZE: usize = crate::BLOCK_SIZE;
+//Number of blocks that are summed in one go when reading data units
+const BLOCKS_PER_READ: usize = 128;
+//Value of CHECKSUM while the checksum is being computed
+pub(crate) const CHECKSUM_PLACEHOLDER: &str = "'0000000000000000'";
+
+pub(crate) fn add(a: u32, b: u32) -> u32 {
+  //Ones' complement addition: carries wrap around
+  let (sum, carry) = a.overflowing_add(b);
+  sum + carry as u32
+}
+
+pub(crate) fn sum_bytes(bytes: &[u8], init: u32) -> u32 {
+  //FITS blocks are always a multiple of 4 bytes long
+  let mut hi = (init >> 16) as u64;
+  let mut lo = (init & 0xffff) as u64;
+  for word in bytes.chunks_exact(4) {
+    hi += u16::from_be_bytes([word[0], word[1]]) as u64;
+    lo += u16::from_be_bytes([word[2], word[3]]) as u64;
+  }
+
+  //Fold the carries back in until they are gone
+  loop {
+    let (hi_carry, lo_carry) = (hi >> 16, lo >> 16);
+    if hi_carry == 0 && lo_carry == 0 {
+      break;
+    }
+    hi = (hi & 0xffff) + lo_carry;
+    lo = (lo & 0xffff) + hi_carry;
+  }
+  ((hi as u32) << 16) | lo as u32
+}
+
+pub(crate) fn encode(sum: u32) -> String {
+  //Encodes the complement of sum as 16 ASCII characters (alphanumerics only)
+  const EXCLUDE: [u8; 13] = *b":;<=>?@[\\]^_`";
+  let value = !sum;
+  let mut ascii = [0u8; 16];
+  for byte_idx in 0..4 {
+    //(1) Spread every byte over four characters
+    let byte = (value >> (24 - 8 * byte_idx)) & 0xff;
+    let (quotient, remainder) = ((byte / 4) as u8 + b'0', (byte % 4) as u8);
+    let mut chars = [quotient; 4];
+    chars[0] += remainder;
+
+    //(2) Shift away from punctuation, keeping the sum of the pairs equal
+    loop {
+      let mut shifted = false;
+      for pair in [0, 2] {
+        if EXCLUDE.contains(&chars[pair]) || EXCLUDE.contains(&chars[pair + 1]) {
+          chars[pair] += 1;
+          chars[pair + 1] -= 1;
+          shifted = true;
+        }
+      }
+      if !shifted {
+        break;
+      }
+    }
+    for (idx, ch) in chars.into_iter().enumerate() {
+      ascii[4 * idx + byte_idx] = ch;
+    }
+  }
+
+  //(3) The encoded string is rotated by one character
+  (0..16).map(|idx| ascii[(idx + 15) % 16] as char).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct HduSums {
+  pub(crate) header: u32,
+  pub(crate) data: u32,
+}
+
+impl HduSums {
+  pub(crate) fn total(&self) -> u32 {
+    add(self.header, self.data)
+  }
+}
+
+pub(crate) fn hdu_sums(
+  file: &mut File,
+  path: &Path,
+  layout: &HduLayout,
+) -> Result<(HduSums, Vec<u8>), FitsIoErr> {
+  //Returns the sums of an HDU, along with the raw bytes of its header
+  let mut header = vec![0u8; layout.get_header_blocks() * BLOCK_SIZE];
+  let offset = raw_io::block_offset(layout.get_start_block());
+  let io_err = |err| FitsIoErr::new(path, format!("read HDU at offset {offset}"), err);
+  file.seek(SeekFrom::Start(offset)).map_err(io_err)?;
+  file.read_exact(&mut header).map_err(io_err)?;
+
+  //Data units can be large, so they are summed in parts
+  let mut data = 0;
+  let mut buf = vec![0u8; BLOCKS_PER_READ * BLOCK_SIZE];
+  let mut blocks_left = layout.get_data_blocks();
+  while blocks_left > 0 {
+    let n_blocks = blocks_left.min(BLOCKS_PER_READ);
+    file.read_exact(&mut buf[..n_blocks * BLOCK_SIZE]).map_err(io_err)?;
+    data = sum_bytes(&buf[..n_blocks * BLOCK_SIZE], data);
+    blocks_left -= n_blocks;
+  }
+
+  Ok((HduSums { header: sum_bytes(&header, 0), data }, header))
+}
+
+pub(crate) fn find_card(header: &[u8], keyword: &str) -> Option<usize> {
+  //Index of the (first) record with this keyword in a raw header
+  let key = format!("{keyword:<8}=");
+  header.chunks_exact(80).position(|card| card.starts_with(key.as_bytes()))
+}
+
+pub(crate) fn fixed_card(keyword: &str, value: &str, comment: &str) -> [u8; 80] {
+  let mut card = [b' '; 80];
+  let text = format!("{keyword:<8}= {value:<20} / {comment}");
+  card[..text.len().min(80)].copy_from_slice(&text.as_bytes()[..text.len().min(80)]);
+  card
+}
+
+pub(crate) fn write_checksums(path: &Path, layouts: &[HduLayout]) -> Result<(), FitsIoErr> {
+  /*  Fills in the CHECKSUM and DATASUM records of every HDU in a file. The
+      records have to be present already (with CHECKSUM set to the
+      placeholder), so that the header does not change size. HDU's without
+      these records are left alone.
+  */
+  let mut file = OpenOptions::new()
+    .read(true)
+    .write(true)
+    .open(path)
+    .map_err(|err| FitsIoErr::new(path, "open file for writing checksums", err))?;
+
+  for layout in layouts {
+    let (sums, mut header) = hdu_sums(&mut file, path, layout)?;
+    let (Some(checksum), Some(datasum)) =
+      (find_card(&header, "CHECKSUM"), find_card(&header, "DATASUM"))
+    else {
+      continue;
+    };
+
+    //(1) DATASUM goes first, since it is included in the checksum
+    let datasum_card = fixed_card("DATASUM", &format!("'{}'", sums.data), "data unit checksum");
+    header[datasum * 80..(datasum + 1) * 80].copy_from_slice(&datasum_card);
+    let placeholder = fixed_card("CHECKSUM", CHECKSUM_PLACEHOLDER, "HDU checksum");
+    header[checksum * 80..(checksum + 1) * 80].copy_from_slice(&placeholder);
+
+    //(2) Then the checksum of the header (with placeholder) plus data
+    let total = add(sum_bytes(&header, 0), sums.data);
+    let checksum_card = fixed_card("CHECKSUM", &format!("'{}'", encode(total)), "HDU checksum");
+    header[checksum * 80..(checksum + 1) * 80].copy_from_slice(&checksum_card);
+
+    //(3) Write the updated header back
+    let offset = raw_io::block_offset(layout.get_start_block());
+    let io_err = |err| FitsIoErr::new(path, format!("write checksums at offset {offset}"), err);
+    file.seek(SeekFrom::Start(offset)).map_err(io_err)?;
+    file.write_all(&header).map_err(io_err)?;
+  }
+
+  file.flush().map_err(|err| FitsIoErr::new(path, "flush file", err))
+}
@@ -0,0 +1,177 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Spectral products alternate between two representations: a 1-D image and
+    a numeric column of a table. These funcs convert one into the other. The
+    WCS of the image axis (CRPIX1, CRVAL1...) is carried over to the column
+    WCS keywords of the table column (TCRPXn, TCRVLn...) and back, as is the
+    unit of the values (BUNIT and TUNITn).
+*/
+
+use std::error::Error;
+
+use ndarray::Array;
+
+use crate::{
+  extensions::{
+    image::{Image, TypedImage},
+    table::{AsciiTable, TableBuilder, TableEntry},
+  },
+  hdu_err::MissingDataErr,
+  header::Header,
+  keyword_value::quote,
+};
+
+//Image keyword and the prefix of the corresponding column keyword
+const WCS_KEYWORDS: [(&str, &str); 6] = [
+  ("CRPIX1", "TCRPX"),
+  ("CRVAL1", "TCRVL"),
+  ("CDELT1", "TCDLT"),
+  ("CTYPE1", "TCTYP"),
+  ("CUNIT1", "TCUNI"),
+  ("BUNIT", "TUNIT"),
+];
+
+fn new_header(records: &[(&str, String)]) -> Header {
+  //Header that starts with the given (mandatory) records
+  let mut header = Header::new();
+  for (index, (keyword, value)) in records.iter().enumerate() {
+    header.set_record_at(index, keyword, value);
+  }
+  header
+}
+
+fn copy_record(from: &Header, from_key: &str, to: &mut Header, to_key: &str) {
+  if let Some(value) = from.get_value(from_key) {
+    to.put_record(to_key, value.clone(), from.get_comment(from_key).cloned());
+  }
+}
+
+pub(crate) fn column_to_image(
+  header: &Header,
+  table: &AsciiTable,
+  col: usize,
+) -> Result<(Header, TypedImage), Box<dyn Error + Send + Sync>> {
+  //(1) Gather the column. Integer columns become 64 bit integer images,
+  //    float columns (which may contain integers as well) become f64 images
+  let entries =
+    (0..table.get_shape().1).map(|row| table.get_entry(col, row)).collect::<Result<Vec<_>, _>>()?;
+  let is_int = entries.iter().all(|entry| matches!(entry, TableEntry::Int(_)));
+  if entries.is_empty() {
+    return Err(Box::new(MissingDataErr::new("a non-empty table column")));
+  }
+  let img = match is_int {
+    true => TypedImage::I64Img(Image::new(
+      Array::from_iter(entries.iter().filter_map(|entry| i64::try_from(entry.clone()).ok()))
+        .into_dyn(),
+    )),
+    false => {
+      let values = entries
+        .iter()
+        .map(|entry| match entry {
+          TableEntry::Int(int) => Ok(*int as f64),
+          TableEntry::Float(float) => Ok(*float),
+          TableEntry::Text(_) | TableEntry::Complex(..) | TableEntry::Array(_) => {
+            Err(MissingDataErr::new("a numeric table column"))
+          }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+      TypedImage::DpfImg(Image::new(Array::from(values).into_dyn()))
+    }
+  };
+
+  //(2) The header of the image extension, named after the column
+  let bitpix = if is_int { "64" } else { "-64" };
+  let mut img_header = new_header(&[
+    ("XTENSION", String::from("'IMAGE   '")),
+    ("BITPIX", bitpix.to_string()),
+    ("NAXIS", String::from("1")),
+    ("NAXIS1", entries.len().to_string()),
+    ("PCOUNT", String::from("0")),
+    ("GCOUNT", String::from("1")),
+  ]);
+  let n = col + 1;
+  copy_record(header, &format!("TTYPE{n}"), &mut img_header, "EXTNAME");
+  for (img_key, col_prefix) in WCS_KEYWORDS {
+    copy_record(header, &format!("{col_prefix}{n}"), &mut img_header, img_key);
+  }
+
+  Ok((img_header, img))
+}
+
+pub(crate) fn image_to_column(
+  header: &Header,
+  img: &TypedImage,
+  label: &str,
+) -> Result<(Header, AsciiTable), Box<dyn Error + Send + Sync>> {
+  //(1) Only 1-D images can be turned into a column
+  let shape = crate::impl_typed_image_dispatch!(img, img => img.get_shape().clone());
+  if shape.len() != 1 {
+    return Err(Box::new(MissingDataErr::new("a 1-D image")));
+  }
+  let entries: Vec<TableEntry> = match img {
+    TypedImage::ByteImg(img) => {
+      img.get_data().iter().map(|&px| TableEntry::Int(px as i64)).collect()
+    }
+    TypedImage::I16Img(img) => {
+      img.get_data().iter().map(|&px| TableEntry::Int(px as i64)).collect()
+    }
+    TypedImage::I32Img(img) => {
+      img.get_data().iter().map(|&px| TableEntry::Int(px as i64)).collect()
+    }
+    TypedImage::I64Img(img) => img.get_data().iter().map(|&px| TableEntry::Int(px)).collect(),
+    TypedImage::SpfImg(img) => {
+      img.get_data().iter().map(|&px| TableEntry::Float(px as f64)).collect()
+    }
+    TypedImage::DpfImg(img) => img.get_data().iter().map(|&px| TableEntry::Float(px)).collect(),
+  };
+
+  //(2) Build a table with a single column
+  let is_int = matches!(entries.first(), Some(TableEntry::Int(_)));
+  let (mut builder, tform, width) = match is_int {
+    true => (TableBuilder::new().col_i64(label), "I20", 20),
+    false => (TableBuilder::new().col_f64(label), "D25.17", 25),
+  };
+  let n_rows = entries.len();
+  for entry in entries {
+    builder.push_row(vec![entry])?;
+  }
+
+  //(3) The header of the table extension, with the WCS of the image axis
+  let mut tbl_header = new_header(&[
+    ("XTENSION", String::from("'TABLE   '")),
+    ("BITPIX", String::from("8")),
+    ("NAXIS", String::from("2")),
+    ("NAXIS1", width.to_string()),
+    ("NAXIS2", n_rows.to_string()),
+    ("PCOUNT", String::from("0")),
+    ("GCOUNT", String::from("1")),
+    ("TFIELDS", String::from("1")),
+    ("TTYPE1", quote(label)),
+    ("TBCOL1", String::from("1")),
+    ("TFORM1", quote(tform)),
+  ]);
+  copy_record(header, "EXTNAME", &mut tbl_header, "EXTNAME");
+  for (img_key, col_prefix) in WCS_KEYWORDS {
+    copy_record(header, img_key, &mut tbl_header, &format!("{col_prefix}1"));
+  }
+
+  Ok((tbl_header, builder.build()))
+}
@@ -0,0 +1,245 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Catalogs list RA and Dec either in degrees or as sexagesimal strings
+    ('12:34:56.78', '-01:02:03.4'). These funcs add a column with the other
+    representation to a table, leaving the original column in place.
+
+    The unit of the source column is taken from TUNITn. Sexagesimal columns
+    are in hours with TUNITn = 'h:m:s' (or 'hms', 'hourangle', 'h') and in
+    degrees with 'd:m:s' (or 'dms', 'deg'). Numeric columns may be in 'deg'
+    (the default when TUNITn is missing), 'rad', 'arcmin', 'arcsec' or
+    'hourangle'. The unit of the new column is recorded in its TUNITn.
+
+    Entries that cannot be converted (blank or malformed strings, NaN) become
+    NaN or blank strings in the new column.
+*/
+
+use std::error::Error;
+
+use crate::{
+  extensions::{
+    table::{AsciiTable, TableBuilder, TableEntry},
+    Extension,
+  },
+  hdu_err::{InvalidRecordValueError, MissingDataErr},
+  header::Header,
+  header_data_unit::HeaderDataUnit,
+  keyword_value::{quote, unquote, Sexagesimal},
+};
+
+const HOUR_UNITS: [&str; 4] = ["h:m:s", "hms", "hourangle", "h"];
+const DEGREE_UNITS: [&str; 3] = ["d:m:s", "dms", "deg"];
+const SEXAGESIMAL_UNITS: [&str; 7] = ["h:m:s", "hms", "hourangle", "h", "d:m:s", "dms", "deg"];
+const ANGLE_UNITS: [&str; 7] = ["deg", "rad", "arcmin", "arcsec", "hourangle", "h", "degree"];
+
+//Column formats of the new columns
+const DEGREE_TFORM: (&str, usize) = ("D25.17", 25);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SexagesimalUnit {
+  //  THIS ENUM IS PART OF THE USER-FACING API
+  Hours,   //right ascension, '12:34:56.78'
+  Degrees, //declination, '-01:02:03.4'
+}
+
+impl SexagesimalUnit {
+  fn tunit(&self) -> &'static str {
+    match self {
+      SexagesimalUnit::Hours => "h:m:s",
+      SexagesimalUnit::Degrees => "d:m:s",
+    }
+  }
+
+  pub(crate) fn degrees_per_unit(&self) -> f64 {
+    match self {
+      SexagesimalUnit::Hours => 15.0,
+      SexagesimalUnit::Degrees => 1.0,
+    }
+  }
+}
+
+fn tunit(header: &Header, col: usize) -> Option<String> {
+  header
+    .get_value(&format!("TUNIT{}", col + 1))
+    .and_then(|raw| unquote(raw))
+    .map(|unit| unit.trim().to_lowercase())
+    .filter(|unit| !unit.is_empty())
+}
+
+pub(crate) fn sexagesimal_to_degrees(
+  header: &Header,
+  table: &AsciiTable,
+  col: usize,
+  label: &str,
+) -> Result<(Header, AsciiTable), Box<dyn Error + Send + Sync>> {
+  //(1) Hours or degrees, according to TUNITn
+  let keyword = format!("TUNIT{}", col + 1);
+  let unit = match tunit(header, col) {
+    Some(unit) if HOUR_UNITS.contains(&unit.as_str()) => SexagesimalUnit::Hours,
+    Some(unit) if DEGREE_UNITS.contains(&unit.as_str()) => SexagesimalUnit::Degrees,
+    Some(unit) => Err(InvalidRecordValueError::new(&keyword, &unit, &SEXAGESIMAL_UNITS))?,
+    None => Err(InvalidRecordValueError::new(&keyword, "(missing)", &SEXAGESIMAL_UNITS))?,
+  };
+
+  //(2) Parse the entries
+  let mut builder = TableBuilder::new().col_f64(label);
+  for row in 0..table.get_shape().1 {
+    let degrees = match table.get_entry(col, row)? {
+      TableEntry::Text(text) => text.parse::<Sexagesimal>().map(|value| value.to_decimal()),
+      _ => return Err(Box::new(MissingDataErr::new("a text column"))),
+    };
+    builder.push_row(vec![TableEntry::Float(
+      degrees.map(|value| value * unit.degrees_per_unit()).unwrap_or(f64::NAN),
+    )])?;
+  }
+  Ok(append_column(header, table, builder.build(), label, DEGREE_TFORM, "deg"))
+}
+
+pub(crate) fn degrees_to_sexagesimal(
+  header: &Header,
+  table: &AsciiTable,
+  col: usize,
+  label: &str,
+  unit: SexagesimalUnit,
+  decimals: usize,
+) -> Result<(Header, AsciiTable), Box<dyn Error + Send + Sync>> {
+  //(1) Scale factor to degrees, according to TUNITn
+  let keyword = format!("TUNIT{}", col + 1);
+  let to_degrees = match tunit(header, col).as_deref() {
+    None | Some("deg") | Some("degree") => 1.0,
+    Some("rad") => 180.0 / std::f64::consts::PI,
+    Some("arcmin") => 1.0 / 60.0,
+    Some("arcsec") => 1.0 / 3600.0,
+    Some("hourangle") | Some("h") => 15.0,
+    Some(other) => Err(InvalidRecordValueError::new(&keyword, other, &ANGLE_UNITS))?,
+  };
+
+  //(2) Format the entries. Declinations always get a sign
+  let width = match unit {
+    SexagesimalUnit::Hours => 9,
+    SexagesimalUnit::Degrees => 10,
+  } + decimals
+    + (decimals > 0) as usize;
+  let mut builder = TableBuilder::new().col_str(label, width);
+  for row in 0..table.get_shape().1 {
+    let degrees = match table.get_entry(col, row)? {
+      TableEntry::Float(float) => float * to_degrees,
+      TableEntry::Int(int) => int as f64 * to_degrees,
+      TableEntry::Text(_) | TableEntry::Complex(..) | TableEntry::Array(_) => {
+        return Err(Box::new(MissingDataErr::new("a numeric column")))
+      }
+    };
+    let text = match degrees.is_finite() {
+      true => format_sexagesimal(degrees / unit.degrees_per_unit(), unit, decimals),
+      false => String::new(),
+    };
+    builder.push_row(vec![TableEntry::Text(text)])?;
+  }
+  Ok(append_column(
+    header,
+    table,
+    builder.build(),
+    label,
+    (&format!("A{width}"), width),
+    unit.tunit(),
+  ))
+}
+
+impl HeaderDataUnit {
+  /*
+      Coordinate columns of catalogs, in degrees or as sexagesimal strings.
+      These funcs return a copy of the HDU with a column added that holds
+      the other representation.
+  */
+  pub fn sexagesimal_to_degrees(
+    &self,
+    col: usize,
+    label: &str,
+  ) -> Result<HeaderDataUnit, Box<dyn Error + Send + Sync>> {
+    let Some(Extension::AsciiTable(table)) = self.get_data() else {
+      return Err(Box::new(MissingDataErr::new("a table")));
+    };
+    let (header, table) = sexagesimal_to_degrees(self.get_header(), table, col, label)?;
+    Ok(Self::from_parts(header, Some(Extension::AsciiTable(table))))
+  }
+
+  pub fn degrees_to_sexagesimal(
+    &self,
+    col: usize,
+    label: &str,
+    unit: SexagesimalUnit,
+    decimals: usize,
+  ) -> Result<HeaderDataUnit, Box<dyn Error + Send + Sync>> {
+    let Some(Extension::AsciiTable(table)) = self.get_data() else {
+      return Err(Box::new(MissingDataErr::new("a table")));
+    };
+    let (header, table) =
+      degrees_to_sexagesimal(self.get_header(), table, col, label, unit, decimals)?;
+    Ok(Self::from_parts(header, Some(Extension::AsciiTable(table))))
+  }
+}
+
+fn format_sexagesimal(value: f64, unit: SexagesimalUnit, decimals: usize) -> String {
+  //Rounds to the last decimal of the seconds first, so that 59.9999 seconds
+  //carries over into the minutes instead of being printed as 60.000
+  let scale = 10f64.powi(decimals as i32);
+  let total = (value.abs() * 3600.0 * scale).round() / scale;
+  let units = (total / 3600.0).floor();
+  let minutes = ((total - units * 3600.0) / 60.0).floor();
+  let seconds = total - units * 3600.0 - minutes * 60.0;
+  let seconds_width = 2 + decimals + (decimals > 0) as usize;
+  let sign = match (unit, value < 0.0 && total > 0.0) {
+    (_, true) => "-",
+    (SexagesimalUnit::Degrees, false) => "+",
+    (SexagesimalUnit::Hours, false) => "",
+  };
+  format!("{sign}{units:02}:{minutes:02}:{seconds:0seconds_width$.decimals$}")
+}
+
+fn append_column(
+  header: &Header,
+  table: &AsciiTable,
+  column: AsciiTable,
+  label: &str,
+  (tform, width): (&str, usize),
+  unit: &str,
+) -> (Header, AsciiTable) {
+  //The new column follows the last one, separated by a space
+  let mut header = header.clone();
+  let mut table = table.clone();
+  table.append_columns(column);
+  let n = table.get_shape().0;
+  let row_len: usize = header.get_value_as("NAXIS1").unwrap_or(0);
+  for (keyword, value) in [
+    (String::from("NAXIS1"), (row_len + 1 + width).to_string()),
+    (String::from("TFIELDS"), n.to_string()),
+    (format!("TTYPE{n}"), quote(label)),
+    (format!("TBCOL{n}"), (row_len + 2).to_string()),
+    (format!("TFORM{n}"), quote(tform)),
+    (format!("TUNIT{n}"), quote(unit)),
+  ] {
+    let comment = header.get_comment(&keyword).cloned();
+    header.put_record(&keyword, value, comment);
+  }
+  header.remove_record("CHECKSUM");
+  header.remove_record("DATASUM");
+  (header, table)
+}
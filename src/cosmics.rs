@@ -0,0 +1,383 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::error::Error;
+
+use ndarray::{Array, IxDyn, ShapeBuilder};
+use rayon::prelude::*;
+
+use crate::{
+  extensions::{
+    image::{Image, TypedImage},
+    Extension,
+  },
+  hdu_err::MissingDataErr,
+  header_data_unit::HeaderDataUnit,
+};
+
+/*
+    Cosmic ray detection with the L.A.Cosmic algorithm (van Dokkum 2001, PASP
+    113, 1420). Cosmic rays have sharper edges than anything that went through
+    the optics, so they stand out in the Laplacian of the image:
+
+    (1) the Laplacian is taken of the image subsampled by a factor 2, negative
+        values are clipped and the result is binned back (L+)
+    (2) L+ is divided by the expected noise (from the gain and read noise),
+        and large scale structure is removed with a 5x5 median filter (S')
+    (3) pixels with S' > sig_clip are candidates, unless the fine structure
+        of the image (median-filtered at 3x3 minus 7x7) explains them. This
+        is what keeps the cores of stars from being flagged (obj_lim)
+    (4) the neighbours of cosmic rays are added when their S' exceeds sig_clip,
+        and their neighbours in turn when it exceeds sig_frac * sig_clip
+
+    Detected pixels are replaced by the median of the good pixels in the 5x5
+    box around them, and the detection is repeated on the cleaned image until
+    no new cosmic rays are found (or max_iterations is reached).
+
+    Pixel values are in ADU, the gain in electrons per ADU and the read noise in
+    electrons. Like convolution, the detection works on the first two axes: the
+    planes of a cube are treated as separate exposures.
+*/
+
+//Header keywords for the gain and read noise, see Cosmics::detect_hdu
+const GAIN_KEYWORD: &str = "GAIN";
+const READ_NOISE_KEYWORD: &str = "RDNOISE";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cosmics {
+  gain: Option<f64>,
+  read_noise: Option<f64>,
+  sig_clip: f64,
+  sig_frac: f64,
+  obj_lim: f64,
+  sat_level: Option<f64>,
+  max_iterations: usize,
+}
+
+impl Default for Cosmics {
+  fn default() -> Self {
+    Cosmics {
+      gain: None,
+      read_noise: None,
+      sig_clip: 4.5,
+      sig_frac: 0.3,
+      obj_lim: 5.0,
+      sat_level: None,
+      max_iterations: 4,
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct CosmicsResult {
+  /*  THIS STRUCT IS PART OF THE USER-FACING API
+      The mask has the shape of the image and is true for cosmic ray pixels.
+      The cleaned image is always a double precision image.
+  */
+  mask: Array<bool, IxDyn>,
+  cleaned: TypedImage,
+  n_iterations: usize,
+}
+
+impl CosmicsResult {
+  pub fn get_mask(&self) -> &Array<bool, IxDyn> {
+    &self.mask
+  }
+  pub fn get_cleaned(&self) -> &TypedImage {
+    &self.cleaned
+  }
+  pub fn get_n_cosmics(&self) -> usize {
+    //Number of flagged pixels (not of separate cosmic rays)
+    self.mask.iter().filter(|&&bad| bad).count()
+  }
+  pub fn get_n_iterations(&self) -> usize {
+    self.n_iterations
+  }
+  pub fn to_parts(self) -> (Array<bool, IxDyn>, TypedImage) {
+    (self.mask, self.cleaned)
+  }
+}
+
+impl Cosmics {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn gain(mut self, gain: f64) -> Self {
+    //Defaults to the GAIN keyword for HDU's, and to 1.0 otherwise
+    self.gain = Some(gain);
+    self
+  }
+
+  pub fn read_noise(mut self, read_noise: f64) -> Self {
+    //Defaults to the RDNOISE keyword for HDU's, and to 6.5 otherwise
+    self.read_noise = Some(read_noise);
+    self
+  }
+
+  pub fn sig_clip(mut self, sig_clip: f64) -> Self {
+    //Detection limit for cosmic rays, in standard deviations of the noise
+    self.sig_clip = sig_clip;
+    self
+  }
+
+  pub fn sig_frac(mut self, sig_frac: f64) -> Self {
+    //Fraction of sig_clip used for the outer ring of neighbouring pixels
+    self.sig_frac = sig_frac;
+    self
+  }
+
+  pub fn obj_lim(mut self, obj_lim: f64) -> Self {
+    //Minimum contrast between the Laplacian and the fine structure image.
+    //Lower this for undersampled data, where stars are sharp as well
+    self.obj_lim = obj_lim;
+    self
+  }
+
+  pub fn sat_level(mut self, sat_level: f64) -> Self {
+    //Pixels at or above this level (saturated stars) are never flagged
+    self.sat_level = Some(sat_level);
+    self
+  }
+
+  pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+    self.max_iterations = max_iterations.max(1);
+    self
+  }
+
+  pub fn get_gain(&self) -> Option<f64> {
+    self.gain
+  }
+  pub fn get_read_noise(&self) -> Option<f64> {
+    self.read_noise
+  }
+  pub fn get_sig_clip(&self) -> f64 {
+    self.sig_clip
+  }
+  pub fn get_sig_frac(&self) -> f64 {
+    self.sig_frac
+  }
+  pub fn get_obj_lim(&self) -> f64 {
+    self.obj_lim
+  }
+  pub fn get_sat_level(&self) -> Option<f64> {
+    self.sat_level
+  }
+  pub fn get_max_iterations(&self) -> usize {
+    self.max_iterations
+  }
+
+  pub fn detect(&self, img: &TypedImage) -> CosmicsResult {
+    //(1) Flatten the image in the Fortran layout. 1-D images are a single row
+    let data = img.to_f64_array();
+    let shape = data.shape().to_vec();
+    let (nx, ny) = (shape.first().copied().unwrap_or(1), shape.get(1).copied().unwrap_or(1));
+    let mut pixels: Vec<f64> = data.t().iter().copied().collect();
+    let mut mask = vec![false; pixels.len()];
+
+    //(2) Detect and clean the planes one by one
+    let plane_len = (nx * ny).max(1);
+    let mut n_iterations = 0;
+    for (plane, mask) in pixels.chunks_mut(plane_len).zip(mask.chunks_mut(plane_len)) {
+      n_iterations = n_iterations.max(self.detect_plane(plane, mask, nx, ny));
+    }
+
+    //(R) back into the shape of the image
+    CosmicsResult {
+      mask: Array::from_shape_vec(shape.clone().f(), mask).unwrap(),
+      cleaned: TypedImage::DpfImg(Image::new(Array::from_shape_vec(shape.f(), pixels).unwrap())),
+      n_iterations,
+    }
+  }
+
+  pub fn detect_hdu(
+    &self,
+    hdu: &HeaderDataUnit,
+  ) -> Result<CosmicsResult, Box<dyn Error + Send + Sync>> {
+    //Like detect, but the gain and read noise default to the header keywords
+    let Some(Extension::Image(img)) = hdu.get_data() else {
+      return Err(Box::new(MissingDataErr::new("an image")));
+    };
+    let header = hdu.get_header();
+    let mut cosmics = *self;
+    if cosmics.gain.is_none() && header.get_value(GAIN_KEYWORD).is_some() {
+      cosmics.gain = Some(header.get_value_as(GAIN_KEYWORD)?);
+    }
+    if cosmics.read_noise.is_none() && header.get_value(READ_NOISE_KEYWORD).is_some() {
+      cosmics.read_noise = Some(header.get_value_as(READ_NOISE_KEYWORD)?);
+    }
+    Ok(cosmics.detect(img))
+  }
+
+  fn detect_plane(&self, plane: &mut [f64], mask: &mut [bool], nx: usize, ny: usize) -> usize {
+    //Returns the number of iterations that were run
+    let gain = self.gain.unwrap_or(1.0);
+    let read_noise = self.read_noise.unwrap_or(6.5);
+    for iteration in 1..=self.max_iterations {
+      //(1) Laplacian edges in units of the noise, without large scale structure
+      let lplus = laplacian_plus(plane, nx, ny);
+      let m5 = median_filter(plane, nx, ny, 5);
+      let sig: Vec<f64> = lplus
+        .iter()
+        .zip(&m5)
+        .map(|(lplus, m5)| {
+          let noise = (gain * m5.max(1e-4) + read_noise * read_noise).sqrt() / gain;
+          lplus / (2.0 * noise)
+        })
+        .collect();
+      let sig_median = median_filter(&sig, nx, ny, 5);
+      let sig: Vec<f64> = sig.iter().zip(&sig_median).map(|(s, m)| s - m).collect();
+
+      //(2) Candidates that are not explained by the fine structure of objects
+      let m3 = median_filter(plane, nx, ny, 3);
+      let m37 = median_filter(&m3, nx, ny, 7);
+      let saturated = |i: usize| self.sat_level.is_some_and(|level| plane[i] >= level);
+      let candidates: Vec<bool> = (0..plane.len())
+        .into_par_iter()
+        .map(|i| {
+          let fine = (m3[i] - m37[i]).max(0.01);
+          sig[i] > self.sig_clip && lplus[i] / fine > self.obj_lim && !saturated(i)
+        })
+        .collect();
+
+      //(3) Grow the cosmic rays into their neighbours, in two steps
+      let grown = dilate(&candidates, nx, ny);
+      let grown: Vec<bool> = (0..plane.len()).map(|i| grown[i] && sig[i] > self.sig_clip).collect();
+      let grown = dilate(&grown, nx, ny);
+      let limit = self.sig_frac * self.sig_clip;
+      let grown: Vec<bool> =
+        (0..plane.len()).map(|i| grown[i] && sig[i] > limit && !saturated(i)).collect();
+
+      //(4) Clean the new cosmic rays, and stop once there are none
+      let mut n_new = 0;
+      for (bad, new) in mask.iter_mut().zip(grown) {
+        if new && !*bad {
+          *bad = true;
+          n_new += 1;
+        }
+      }
+      if n_new == 0 {
+        return iteration;
+      }
+      clean(plane, mask, nx, ny);
+    }
+    self.max_iterations
+  }
+}
+
+fn laplacian_plus(plane: &[f64], nx: usize, ny: usize) -> Vec<f64> {
+  /*  On the image subsampled by 2, three of the four neighbours of a subpixel
+      lie in the same (constant) pixel, so the Laplacian of subpixel (a, b) is
+      (v - h_a) + (v - w_b): h and w are the neighbours of the pixel on the a
+      side along NAXIS1 and on the b side along NAXIS2. Pixels outside the
+      image repeat the edge pixels.
+  */
+  let mut out = vec![0.0; plane.len()];
+  out.par_chunks_mut(nx.max(1)).enumerate().for_each(|(y, out_row)| {
+    let at = |x: usize, y: usize| plane[x + nx * y];
+    for (x, out_px) in out_row.iter_mut().enumerate() {
+      let v = at(x, y);
+      let h = [at(x.saturating_sub(1), y), at((x + 1).min(nx - 1), y)];
+      let w = [at(x, y.saturating_sub(1)), at(x, (y + 1).min(ny - 1))];
+      let sum: f64 = h.iter().flat_map(|h| w.iter().map(move |w| (2.0 * v - h - w).max(0.0))).sum();
+      *out_px = sum / 4.0;
+    }
+  });
+  out
+}
+
+fn median_filter(plane: &[f64], nx: usize, ny: usize, size: usize) -> Vec<f64> {
+  //Median of the size x size box around each pixel (cut off at the edges)
+  let mut out = vec![0.0; plane.len()];
+  out.par_chunks_mut(nx.max(1)).enumerate().for_each(|(y, out_row)| {
+    let mut window = Vec::with_capacity(size * size);
+    for (x, out_px) in out_row.iter_mut().enumerate() {
+      box_values(plane, None, (nx, ny), (x, y), size / 2, &mut window);
+      *out_px = median(&mut window);
+    }
+  });
+  out
+}
+
+fn box_values(
+  plane: &[f64],
+  mask: Option<&[bool]>,
+  (nx, ny): (usize, usize),
+  (x, y): (usize, usize),
+  half: usize,
+  window: &mut Vec<f64>,
+) {
+  //Collects the pixels in the box, leaving out NaN and masked pixels
+  window.clear();
+  for wy in y.saturating_sub(half)..(y + half + 1).min(ny) {
+    for wx in x.saturating_sub(half)..(x + half + 1).min(nx) {
+      let i = wx + nx * wy;
+      if mask.is_none_or(|mask| !mask[i]) && !plane[i].is_nan() {
+        window.push(plane[i]);
+      }
+    }
+  }
+}
+
+fn median(values: &mut [f64]) -> f64 {
+  if values.is_empty() {
+    return f64::NAN;
+  }
+  let mid = values.len() / 2;
+  let (_, &mut upper, _) = values.select_nth_unstable_by(mid, f64::total_cmp);
+  if values.len() % 2 == 1 {
+    return upper;
+  }
+  let lower = values[..mid].iter().copied().fold(f64::NEG_INFINITY, f64::max);
+  0.5 * (lower + upper)
+}
+
+fn dilate(mask: &[bool], nx: usize, ny: usize) -> Vec<bool> {
+  //Adds the 8 neighbours of every pixel in the mask
+  (0..mask.len())
+    .into_par_iter()
+    .map(|i| {
+      let (x, y) = (i % nx, i / nx);
+      (y.saturating_sub(1)..(y + 2).min(ny))
+        .any(|wy| (x.saturating_sub(1)..(x + 2).min(nx)).any(|wx| mask[wx + nx * wy]))
+    })
+    .collect()
+}
+
+fn clean(plane: &mut [f64], mask: &[bool], nx: usize, ny: usize) {
+  //Replaces the masked pixels by the median of the good pixels around them,
+  //widening the box for pixels in large groups of cosmic rays
+  let todo: Vec<usize> = (0..plane.len()).filter(|&i| mask[i]).collect();
+  let fills: Vec<(usize, f64)> = todo
+    .into_par_iter()
+    .filter_map(|i| {
+      let mut window = Vec::with_capacity(25);
+      for half in 2..=nx.max(ny) {
+        box_values(plane, Some(mask), (nx, ny), (i % nx, i / nx), half, &mut window);
+        if !window.is_empty() {
+          return Some((i, median(&mut window)));
+        }
+      }
+      None
+    })
+    .collect();
+  for (i, fill) in fills {
+    plane[i] = fill;
+  }
+}
@@ -0,0 +1,108 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    A keyword should appear only once in a header (commentary records aside),
+    but files with repeated keywords exist, often with conflicting values. The
+    duplicate policy determines which record is kept when such a header is
+    read:
+      - FirstWins keeps the first record, LastWins the last one (in the
+        position of the first one)
+      - KeepAllAsList keeps the first record, and the values of all records
+        are available through Header::get_all_values. Only the kept record is
+        written back to a file though
+      - Error refuses the header
+
+    Every repeated keyword is reported with a diagnostic, which can be
+    obtained with Header::get_duplicates or Fits::duplicate_keywords.
+*/
+
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+
+use crate::{
+  header_err::DuplicateKeywordErr,
+  raw::keyword_record::KeywordRecord,
+  validation::{Diagnostic, Severity},
+};
+
+//Name of the (pseudo) validation profile of duplicate keyword diagnostics
+const PROFILE: &str = "duplicates";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+  //  THIS ENUM IS PART OF THE USER-FACING API
+  FirstWins,
+  #[default]
+  LastWins,
+  KeepAllAsList,
+  Error,
+}
+
+pub(crate) type Records = IndexMap<Arc<String>, KeywordRecord>;
+pub(crate) type ValueLists = IndexMap<Arc<String>, Vec<String>>;
+
+pub(crate) fn resolve(
+  records: Vec<KeywordRecord>,
+  policy: DuplicatePolicy,
+) -> Result<(Records, ValueLists, Vec<Diagnostic>), DuplicateKeywordErr> {
+  //(1) Group the records by keyword, in order of first appearance
+  let mut grouped: IndexMap<Arc<String>, Vec<KeywordRecord>> = IndexMap::new();
+  for record in records {
+    grouped.entry(record.keyword.clone()).or_default().push(record);
+  }
+
+  //(2) Pick a record for every keyword, reporting the repeated ones
+  let (mut kept, mut lists, mut diagnostics) = (IndexMap::new(), IndexMap::new(), Vec::new());
+  for (keyword, mut group) in grouped {
+    if group.len() > 1 {
+      let values: Vec<String> =
+        group.iter().map(|record| record.value.clone().unwrap_or_default()).collect();
+      if policy == DuplicatePolicy::Error {
+        return Err(DuplicateKeywordErr::new(&keyword, values));
+      }
+      diagnostics.push(diagnostic(&keyword, &values, policy));
+      if policy == DuplicatePolicy::KeepAllAsList {
+        lists.insert(keyword.clone(), values);
+      }
+    }
+    let record = match policy {
+      DuplicatePolicy::LastWins => group.pop(),
+      _ => group.into_iter().next(),
+    };
+    kept.insert(keyword, record.unwrap());
+  }
+  Ok((kept, lists, diagnostics))
+}
+
+fn diagnostic(keyword: &str, values: &[String], policy: DuplicatePolicy) -> Diagnostic {
+  //Repeated records with the same value are harmless, conflicting ones are not
+  let conflicting = values.iter().any(|value| value.trim() != values[0].trim());
+  let kept = match policy {
+    DuplicatePolicy::LastWins => "kept the last one",
+    DuplicatePolicy::KeepAllAsList => "kept the first one, all values are listed",
+    _ => "kept the first one",
+  };
+  let message = match conflicting {
+    true => format!("{} records with conflicting values, {kept}", values.len()),
+    false => format!("{} records with the same value, {kept}", values.len()),
+  };
+  Diagnostic::new(PROFILE, 0, keyword, &values.join(", "), message, Severity::Warning)
+}
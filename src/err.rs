@@ -29,5 +29,7 @@ pub mod header_err;
 pub mod img_err;
 pub mod io_err;
 pub mod keyword_err;
+pub mod section_err;
 pub mod tbl_err;
 pub mod tbl_fmt_err;
+pub mod validation_err;
@@ -20,7 +20,6 @@
 use std::{
   error::Error,
   fmt::{self, Display, Formatter},
-  ops::Not,
 };
 
 #[derive(Debug)]
@@ -80,7 +79,7 @@ impl InvalidRecordValueError {
     InvalidRecordValueError {
       keyword: String::from(keyword),
       invalid_value: String::from(invalid_value),
-      allowed_values: allowed_values,
+      allowed_values,
     }
   }
 }
@@ -100,6 +99,164 @@ impl Display for NotImplementedErr {
 
 impl NotImplementedErr {
   pub fn new(xtnsion: String) -> Self {
-    NotImplementedErr { xtnsion: xtnsion }
+    NotImplementedErr { xtnsion }
+  }
+}
+
+#[derive(Debug)]
+pub struct NoDataSourceErr {}
+//This error may be thrown when unloading or (re)loading the data of an HDU.
+//It signifies that the HDU was not read from a file, so its data cannot be
+//read (again) once it has been dropped.
+
+impl Error for NoDataSourceErr {}
+impl Display for NoDataSourceErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "Error while (re)loading HDU data: the HDU was not read from a file")
+  }
+}
+
+impl NoDataSourceErr {
+  pub(crate) fn new() -> Self {
+    NoDataSourceErr {}
+  }
+}
+
+#[derive(Debug)]
+pub struct MissingDataErr {
+  //thrown by the convenience accessors of Fits (primary_image etc.) when the
+  //file does not contain the requested kind of data
+  kind: &'static str,
+}
+
+impl Error for MissingDataErr {}
+impl Display for MissingDataErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "FITS file does not contain {} (or its data was unloaded)", self.kind)
+  }
+}
+
+impl MissingDataErr {
+  pub(crate) fn new(kind: &'static str) -> Self {
+    MissingDataErr { kind }
+  }
+}
+
+#[derive(Debug)]
+pub struct InvalidOrderErr {
+  //thrown by Fits::reorder if the new order is not a permutation of the HDU's
+  order: Vec<usize>,
+  n_hdus: usize,
+}
+
+impl Error for InvalidOrderErr {}
+impl Display for InvalidOrderErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "Error while reordering HDU's: {:?} is not a permutation of the {} HDU's in the file",
+      self.order, self.n_hdus
+    )
+  }
+}
+
+impl InvalidOrderErr {
+  pub(crate) fn new(order: &[usize], n_hdus: usize) -> Self {
+    InvalidOrderErr { order: order.to_vec(), n_hdus }
+  }
+}
+
+#[derive(Debug)]
+pub struct RemoveHduErr {
+  //thrown by Fits::remove_hdu_in_place for HDU's that cannot be removed
+  index: usize,
+  n_hdus: usize,
+}
+
+impl Error for RemoveHduErr {}
+impl Display for RemoveHduErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self.index {
+      0 => write!(
+        f,
+        "Error while removing HDU: the primary HDU cannot be removed in place, since the next HDU would have to be rewritten as a primary HDU"
+      ),
+      _ => write!(
+        f,
+        "Error while removing HDU: there is no HDU #{} in a file with {} HDU's",
+        self.index, self.n_hdus
+      ),
+    }
+  }
+}
+
+impl RemoveHduErr {
+  pub(crate) fn new(index: usize, n_hdus: usize) -> Self {
+    RemoveHduErr { index, n_hdus }
+  }
+  pub fn get_index(&self) -> usize {
+    self.index
+  }
+}
+
+#[derive(Debug)]
+pub struct EncodeHduErr {
+  /*
+      Thrown when the data of an HDU could not be encoded. Data units are
+      encoded on separate threads (see WriteOptions::encode_memory), the
+      original error is kept as the source of this one.
+  */
+  index: usize,
+  source: Box<dyn Error + Send + Sync>,
+}
+
+impl Error for EncodeHduErr {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    Some(self.source.as_ref())
+  }
+}
+impl Display for EncodeHduErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "Error while encoding the data of HDU #{}: {}", self.index, self.source)
+  }
+}
+
+impl EncodeHduErr {
+  pub(crate) fn new(index: usize, source: Box<dyn Error + Send + Sync>) -> Self {
+    EncodeHduErr { index, source }
+  }
+  pub fn get_index(&self) -> usize {
+    self.index
+  }
+}
+
+#[derive(Debug)]
+pub struct DecodeHduErr {
+  /*
+      Thrown when the data of an HDU could not be decoded. The data units of
+      a file are decoded on separate threads (see HeaderDataUnit::decode_hdus),
+      the original error is kept as the source of this one.
+  */
+  index: usize,
+  source: Box<dyn Error + Send + Sync>,
+}
+
+impl Error for DecodeHduErr {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    Some(self.source.as_ref())
+  }
+}
+impl Display for DecodeHduErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "Error while decoding the data of HDU #{}: {}", self.index, self.source)
+  }
+}
+
+impl DecodeHduErr {
+  pub(crate) fn new(index: usize, source: Box<dyn Error + Send + Sync>) -> Self {
+    DecodeHduErr { index, source }
+  }
+  pub fn get_index(&self) -> usize {
+    self.index
   }
 }
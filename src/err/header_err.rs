@@ -19,7 +19,6 @@
 use std::{
   error::Error,
   fmt::{self, Display, Formatter},
-  str::Utf8Error,
 };
 
 use crate::keyword_err::KeywordRecordBufferErr;
@@ -34,7 +33,7 @@ pub struct HeaderBlockBufferErr {
 }
 
 //List of possible messages:
-pub const BUFFER_LEN: &'static str = "header buffer was not exactly 1 FITS block (2880b) long";
+pub const BUFFER_LEN: &str = "header buffer was not exactly 1 FITS block (2880b) long";
 
 impl Error for HeaderBlockBufferErr {}
 impl Display for HeaderBlockBufferErr {
@@ -54,3 +53,112 @@ impl HeaderBlockBufferErr {
     Self { msg: msg.to_string() }
   }
 }
+
+#[derive(Debug)]
+pub struct IllegalCharErr {
+  /*
+      This error may be thrown when reading or writing a header with the
+      strict charset policy. Header records may only contain printable ASCII
+      characters (0x20 to 0x7E).
+  */
+  card: String,
+  column: usize,
+  byte: u8,
+}
+
+impl Error for IllegalCharErr {}
+impl Display for IllegalCharErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "illegal byte 0x{:02X} in column {} of header record \"{}\" (only printable ASCII is allowed)",
+      self.byte, self.column, self.card
+    )
+  }
+}
+
+impl IllegalCharErr {
+  pub(crate) fn new(card: String, column: usize, byte: u8) -> Self {
+    IllegalCharErr { card, column, byte }
+  }
+
+  pub fn get_card(&self) -> &str {
+    &self.card
+  }
+  pub fn get_column(&self) -> usize {
+    self.column
+  }
+  pub fn get_byte(&self) -> u8 {
+    self.byte
+  }
+}
+
+#[derive(Debug)]
+pub struct TextRecordErr {
+  /*
+      This error may be thrown when reading a (detached) header from text. It
+      signifies that a line of the text was too long to be a header record.
+  */
+  line: usize,
+  len: usize,
+}
+
+impl Error for TextRecordErr {}
+impl Display for TextRecordErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "line {} of text header is {} chars long, header records are at most 80 chars",
+      self.line, self.len
+    )
+  }
+}
+
+impl TextRecordErr {
+  pub(crate) fn new(line: usize, len: usize) -> Self {
+    TextRecordErr { line, len }
+  }
+
+  pub fn get_line(&self) -> usize {
+    self.line
+  }
+  pub fn get_len(&self) -> usize {
+    self.len
+  }
+}
+
+#[derive(Debug)]
+pub struct DuplicateKeywordErr {
+  /*
+      This error may be thrown when reading a header with the Error duplicate
+      policy. It signifies that a keyword appears more than once.
+  */
+  keyword: String,
+  values: Vec<String>,
+}
+
+impl Error for DuplicateKeywordErr {}
+impl Display for DuplicateKeywordErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "keyword {} appears {} times in the header (values: {})",
+      self.keyword,
+      self.values.len(),
+      self.values.join(", ")
+    )
+  }
+}
+
+impl DuplicateKeywordErr {
+  pub(crate) fn new(keyword: &str, values: Vec<String>) -> Self {
+    DuplicateKeywordErr { keyword: keyword.to_string(), values }
+  }
+
+  pub fn get_keyword(&self) -> &str {
+    &self.keyword
+  }
+  pub fn get_values(&self) -> &[String] {
+    &self.values
+  }
+}
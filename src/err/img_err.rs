@@ -47,7 +47,10 @@ impl Display for WrongImgTypeErr {
 
 impl WrongImgTypeErr {
   pub(crate) fn new(img: &TypedImage, wrong_type: Bitpix) -> Self {
-    WrongImgTypeErr { img_type: img.bpx(), wrong_type: wrong_type }
+    Self::from_bitpix(img.bpx(), wrong_type)
+  }
+  pub(crate) fn from_bitpix(img_type: Bitpix, wrong_type: Bitpix) -> Self {
+    WrongImgTypeErr { img_type, wrong_type }
   }
 }
 
@@ -66,3 +69,434 @@ impl InvalidMemLayout {
     InvalidMemLayout {}
   }
 }
+
+#[derive(Debug)]
+pub struct ComplexShapeErr {
+  /*
+      This error may be thrown when accessing a complex-valued image. Complex
+      images must have a first axis (NAXIS1) of length 2 containing the real
+      and imaginary parts of each entry.
+  */
+  shape: Vec<usize>,
+}
+
+impl Error for ComplexShapeErr {}
+impl Display for ComplexShapeErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "Tried to access image with shape {:?} as a complex image. Complex images must have a first axis (NAXIS1) of length 2",
+      self.shape
+    )
+  }
+}
+
+impl ComplexShapeErr {
+  pub(crate) fn new(shape: &[usize]) -> Self {
+    ComplexShapeErr { shape: shape.to_vec() }
+  }
+}
+
+#[derive(Debug)]
+pub struct CutoutRangeErr {
+  /*
+      This error may be thrown when reading a cutout of an image. It signifies
+      that the requested cutout does not lie completely within the image.
+  */
+  start: Vec<usize>,
+  cut_shape: Vec<usize>,
+  img_shape: Vec<usize>,
+}
+
+impl Error for CutoutRangeErr {}
+impl Display for CutoutRangeErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "Cutout with shape {:?} starting at {:?} does not fit in image with shape {:?}",
+      self.cut_shape, self.start, self.img_shape
+    )
+  }
+}
+
+impl CutoutRangeErr {
+  pub(crate) fn new(start: &[usize], cut_shape: &[usize], img_shape: &[usize]) -> Self {
+    CutoutRangeErr {
+      start: start.to_vec(),
+      cut_shape: cut_shape.to_vec(),
+      img_shape: img_shape.to_vec(),
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct InvalidAxesErr {
+  /*
+      This error may be thrown when flipping or transposing an image. It
+      signifies that the axis does not exist, or that the axes are not a
+      permutation of the axes of the image.
+  */
+  axes: Vec<usize>,
+  naxis: usize,
+}
+
+impl Error for InvalidAxesErr {}
+impl Display for InvalidAxesErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "Axes {:?} are not valid for an image with {} axes. Axes are 0-based and transpositions need a permutation of all axes",
+      self.axes, self.naxis
+    )
+  }
+}
+
+impl InvalidAxesErr {
+  pub(crate) fn new(axes: &[usize], naxis: usize) -> Self {
+    InvalidAxesErr { axes: axes.to_vec(), naxis }
+  }
+}
+
+#[derive(Debug)]
+pub struct RowShapeErr {
+  /*
+      This error may be thrown when building or streaming an image row by row.
+      Rows run along the first axis (NAXIS1) and are given in file order. The
+      error records the row that did not fit: its length, or None if there
+      were fewer rows than the shape of the image requires.
+  */
+  shape: Vec<usize>,
+  row: usize,
+  len: Option<usize>,
+}
+
+impl Error for RowShapeErr {}
+impl Display for RowShapeErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    let n_rows: usize = self.shape.iter().skip(1).product();
+    match self.len {
+      None => write!(
+        f,
+        "Error while building image: got {} rows, but an image with shape {:?} has {} rows",
+        self.row, self.shape, n_rows
+      ),
+      Some(_) if self.row >= n_rows => write!(
+        f,
+        "Error while building image: got more than {} rows for an image with shape {:?}",
+        n_rows, self.shape
+      ),
+      Some(len) => write!(
+        f,
+        "Error while building image: row {} has {} pixels, but rows of an image with shape {:?} have {}",
+        self.row,
+        len,
+        self.shape,
+        self.shape.first().copied().unwrap_or(1)
+      ),
+    }
+  }
+}
+
+impl RowShapeErr {
+  pub(crate) fn new(shape: &[usize], row: usize, len: Option<usize>) -> Self {
+    RowShapeErr { shape: shape.to_vec(), row, len }
+  }
+
+  pub fn get_shape(&self) -> &[usize] {
+    &self.shape
+  }
+  pub fn get_row(&self) -> usize {
+    self.row
+  }
+  pub fn get_len(&self) -> Option<usize> {
+    self.len
+  }
+}
+
+#[derive(Debug)]
+pub struct SkyPositionErr {
+  /*
+      This error may be thrown when cutting a sky position out of an image. It
+      signifies that the position (in degrees) does not lie on the image, or
+      not even on the side of the sky that the projection of the image covers.
+  */
+  ra: f64,
+  dec: f64,
+}
+
+impl Error for SkyPositionErr {}
+impl Display for SkyPositionErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "Sky position (RA = {}, Dec = {}) does not lie on the image", self.ra, self.dec)
+  }
+}
+
+impl SkyPositionErr {
+  pub(crate) fn new(ra: f64, dec: f64) -> Self {
+    SkyPositionErr { ra, dec }
+  }
+
+  pub fn get_ra(&self) -> f64 {
+    self.ra
+  }
+  pub fn get_dec(&self) -> f64 {
+    self.dec
+  }
+}
+
+#[derive(Debug)]
+pub struct MaskShapeErr {
+  /*
+      This error may be thrown when inpainting an image. It signifies that the
+      bad pixel mask does not have the same shape as the image.
+  */
+  img_shape: Vec<usize>,
+  mask_shape: Vec<usize>,
+}
+
+impl Error for MaskShapeErr {}
+impl Display for MaskShapeErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "Mask with shape {:?} does not match image with shape {:?}",
+      self.mask_shape, self.img_shape
+    )
+  }
+}
+
+impl MaskShapeErr {
+  pub(crate) fn new(img_shape: &[usize], mask_shape: &[usize]) -> Self {
+    MaskShapeErr { img_shape: img_shape.to_vec(), mask_shape: mask_shape.to_vec() }
+  }
+
+  pub fn get_img_shape(&self) -> &[usize] {
+    &self.img_shape
+  }
+  pub fn get_mask_shape(&self) -> &[usize] {
+    &self.mask_shape
+  }
+}
+
+#[derive(Debug)]
+pub struct StackShapeErr {
+  /*
+      This error may be thrown when stacking images. It signifies that a frame
+      (or its weight map) does not have the same shape as the frames before it.
+      Frames are counted from 0.
+  */
+  frame: usize,
+  expected: Vec<usize>,
+  found: Vec<usize>,
+}
+
+impl Error for StackShapeErr {}
+impl Display for StackShapeErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "Frame {} (or its weight map) has shape {:?}, but the stack has shape {:?}",
+      self.frame, self.found, self.expected
+    )
+  }
+}
+
+impl StackShapeErr {
+  pub(crate) fn new(frame: usize, expected: &[usize], found: &[usize]) -> Self {
+    StackShapeErr { frame, expected: expected.to_vec(), found: found.to_vec() }
+  }
+
+  pub fn get_frame(&self) -> usize {
+    self.frame
+  }
+  pub fn get_expected(&self) -> &[usize] {
+    &self.expected
+  }
+  pub fn get_found(&self) -> &[usize] {
+    &self.found
+  }
+}
+
+#[derive(Debug)]
+pub struct SpectrumShapeErr {
+  /*
+      This error may be thrown when transforming a spectrum back into an
+      image (see fft.rs). The image may only be smaller than the spectrum
+      along the first two axes (cropping the padding), its other axes must
+      be the same.
+  */
+  spectrum: Vec<usize>,
+  image: Vec<usize>,
+}
+
+impl Error for SpectrumShapeErr {}
+impl Display for SpectrumShapeErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "A spectrum with shape {:?} cannot be transformed into an image with shape {:?}",
+      self.spectrum, self.image
+    )
+  }
+}
+
+impl SpectrumShapeErr {
+  #[cfg(feature = "fft")]
+  pub(crate) fn new(spectrum: &[usize], image: &[usize]) -> Self {
+    SpectrumShapeErr { spectrum: spectrum.to_vec(), image: image.to_vec() }
+  }
+
+  pub fn get_spectrum_shape(&self) -> &[usize] {
+    &self.spectrum
+  }
+  pub fn get_image_shape(&self) -> &[usize] {
+    &self.image
+  }
+}
+
+#[derive(Debug)]
+pub struct PsfGridErr {
+  /*
+      This error may be thrown when reading a gridded PSF (see psf.rs). The
+      positions of the PSF's must form a complete rectangular grid with one
+      position for every plane of the cube.
+  */
+  n_planes: usize,
+  n_positions: usize,
+  grid: (usize, usize),
+}
+
+impl Error for PsfGridErr {}
+impl Display for PsfGridErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "PSF cube has {} planes, but {} positions on a {} x {} grid",
+      self.n_planes, self.n_positions, self.grid.0, self.grid.1
+    )
+  }
+}
+
+impl PsfGridErr {
+  pub(crate) fn new(n_planes: usize, n_positions: usize, grid: (usize, usize)) -> Self {
+    PsfGridErr { n_planes, n_positions, grid }
+  }
+
+  pub fn get_num_planes(&self) -> usize {
+    self.n_planes
+  }
+  pub fn get_num_positions(&self) -> usize {
+    self.n_positions
+  }
+  pub fn get_grid(&self) -> (usize, usize) {
+    self.grid
+  }
+}
+
+#[derive(Debug)]
+pub struct DiffShapeErr {
+  /*
+      This error may be thrown when comparing two images (see image_diff.rs).
+      Only images with the same shape can be compared pixel by pixel.
+  */
+  shape_a: Vec<usize>,
+  shape_b: Vec<usize>,
+}
+
+impl Error for DiffShapeErr {}
+impl Display for DiffShapeErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "Cannot compare image with shape {:?} to image with shape {:?}",
+      self.shape_a, self.shape_b
+    )
+  }
+}
+
+impl DiffShapeErr {
+  pub(crate) fn new(shape_a: &[usize], shape_b: &[usize]) -> Self {
+    DiffShapeErr { shape_a: shape_a.to_vec(), shape_b: shape_b.to_vec() }
+  }
+
+  pub fn get_shape_a(&self) -> &[usize] {
+    &self.shape_a
+  }
+  pub fn get_shape_b(&self) -> &[usize] {
+    &self.shape_b
+  }
+}
+
+#[derive(Debug)]
+pub struct DataUnitLenErr {
+  /*
+      This error may be thrown when viewing a raw data unit as an image (see
+      TypedImage::view_u8_raw). The data unit has to hold all pixels.
+  */
+  len: usize,
+  n_pixels: usize,
+}
+
+impl Error for DataUnitLenErr {}
+impl Display for DataUnitLenErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "Data unit of {} bytes is too short for {} pixels", self.len, self.n_pixels)
+  }
+}
+
+impl DataUnitLenErr {
+  pub(crate) fn new(len: usize, n_pixels: usize) -> Self {
+    DataUnitLenErr { len, n_pixels }
+  }
+
+  pub fn get_len(&self) -> usize {
+    self.len
+  }
+  pub fn get_num_pixels(&self) -> usize {
+    self.n_pixels
+  }
+}
+
+#[derive(Debug)]
+pub struct TextureShapeErr {
+  /*
+      This error may be thrown when uploading an image to the GPU (see
+      TypedImage::to_wgpu_texture). Textures have one to three axes, none of
+      which may be empty or longer than the limit of the device (max_len,
+      which is None if the number of axes is the problem).
+  */
+  shape: Vec<usize>,
+  max_len: Option<u32>,
+}
+
+impl Error for TextureShapeErr {}
+impl Display for TextureShapeErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self.max_len {
+      Some(max_len) => write!(
+        f,
+        "Image with shape {:?} cannot be uploaded as a texture: axes must hold 1 to {max_len} pixels",
+        self.shape
+      ),
+      None => write!(
+        f,
+        "Image with shape {:?} cannot be uploaded as a texture: textures have 1 to 3 axes",
+        self.shape
+      ),
+    }
+  }
+}
+
+impl TextureShapeErr {
+  #[cfg(feature = "gpu")]
+  pub(crate) fn new(shape: &[usize], max_len: Option<u32>) -> Self {
+    TextureShapeErr { shape: shape.to_vec(), max_len }
+  }
+
+  pub fn get_shape(&self) -> &[usize] {
+    &self.shape
+  }
+  pub fn get_max_len(&self) -> Option<u32> {
+    self.max_len
+  }
+}
@@ -20,6 +20,8 @@
 use std::{
   error::Error,
   fmt::{self, Display, Formatter},
+  io,
+  path::{Path, PathBuf},
 };
 
 #[derive(Debug)]
@@ -33,12 +35,17 @@ pub struct InvalidFitsFileErr {
 }
 
 //Possible messages
-pub(crate) const FILE_BLOCK_DIV: &'static str =
+pub(crate) const FILE_BLOCK_DIV: &str =
   "tried to open file with a length not equal to an integer multiple of FITS blocks";
-pub(crate) const BUF_BLOCK_DIV: &'static str =
-  "supplied buffer not an integer multiple of FITS blocks";
-pub(crate) const FILE_END: &'static str = "tried to read more FITS blocks than the file contains";
-pub(crate) const CORRUPTED: &'static str = "tried to access corrupted data";
+pub(crate) const BUF_BLOCK_DIV: &str = "supplied buffer not an integer multiple of FITS blocks";
+pub(crate) const FILE_END: &str = "tried to read more FITS blocks than the file contains";
+pub(crate) const CORRUPTED: &str = "tried to access corrupted data";
+pub(crate) const TOO_LARGE: &str =
+  "file has more FITS blocks than can be addressed on this platform";
+pub(crate) const DATA_TOO_LARGE: &str =
+  "size of the data unit given by the header does not fit in 64 bits";
+pub(crate) const DATA_NOT_ADDRESSABLE: &str =
+  "data unit is too large to be loaded into memory on this platform";
 
 impl Error for InvalidFitsFileErr {}
 impl Display for InvalidFitsFileErr {
@@ -49,6 +56,118 @@ impl Display for InvalidFitsFileErr {
 
 impl InvalidFitsFileErr {
   pub(crate) fn new(msg: &'static str) -> Self {
-    InvalidFitsFileErr { msg: msg }
+    InvalidFitsFileErr { msg }
+  }
+}
+
+#[derive(Debug)]
+pub struct FileLockedErr {
+  /*
+      This error may be thrown when writing a FITS file with a LockPolicy that
+      does not wait for other processes to release their lock on the file.
+  */
+  path: String,
+}
+
+impl Error for FileLockedErr {}
+impl Display for FileLockedErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "Error while accessing FITS file: {} is locked by another process", self.path)
+  }
+}
+
+impl FileLockedErr {
+  pub(crate) fn new(path: &Path) -> Self {
+    FileLockedErr { path: path.display().to_string() }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TruncatedFileErr {
+  /*
+      This error is returned alongside the HDU's that could be recovered when
+      opening a truncated FITS file with Fits::open_partial. It records which
+      HDU was cut off and how many bytes of its data unit were expected and
+      actually present. If the header of the HDU itself was cut off, the HDU
+      is left out entirely and both byte counts are zero.
+  */
+  hdu: usize,
+  expected: u64,
+  got: u64,
+}
+
+impl Error for TruncatedFileErr {}
+impl Display for TruncatedFileErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self.expected {
+      0 => write!(f, "Error while accessing FITS file: header of HDU #{} is truncated", self.hdu),
+      _ => write!(
+        f,
+        "Error while accessing FITS file: data of HDU #{} is truncated (expected {} bytes, got {})",
+        self.hdu, self.expected, self.got
+      ),
+    }
+  }
+}
+
+impl TruncatedFileErr {
+  pub(crate) fn new(hdu: usize, expected: u64, got: u64) -> Self {
+    TruncatedFileErr { hdu, expected, got }
+  }
+
+  pub fn get_hdu(&self) -> usize {
+    self.hdu
+  }
+  pub fn get_expected(&self) -> u64 {
+    self.expected
+  }
+  pub fn get_got(&self) -> u64 {
+    self.got
+  }
+}
+
+#[derive(Debug)]
+pub struct FitsIoErr {
+  /*
+      This error wraps the std::io::Error of a failed read, write, seek etc.
+      on a FITS file. It records the path of the file and the operation that
+      failed (e.g. "read FITS block 12"), so that failures can be traced back
+      to a specific file when many files are processed at once.
+  */
+  path: PathBuf,
+  op: String,
+  source: io::Error,
+}
+
+impl Error for FitsIoErr {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    Some(&self.source)
+  }
+}
+impl Display for FitsIoErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "Error while accessing FITS file {}: could not {}: {}",
+      self.path.display(),
+      self.op,
+      self.source
+    )
+  }
+}
+
+impl FitsIoErr {
+  pub(crate) fn new(path: &Path, op: impl Into<String>, source: io::Error) -> Self {
+    FitsIoErr { path: path.to_path_buf(), op: op.into(), source }
+  }
+
+  pub fn get_path(&self) -> &Path {
+    &self.path
+  }
+  pub fn get_op(&self) -> &str {
+    &self.op
+  }
+  pub fn get_kind(&self) -> io::ErrorKind {
+    self.source.kind()
   }
 }
@@ -33,8 +33,9 @@ pub struct KeywordRecordBufferErr {
 }
 
 //List of possible messages:
-pub const BUFFER_LEN: &'static str = "Keyword record buffer was not exactly 80 bytes long";
-pub const ILLEGAL_CHAR: &'static str = "Keyword record contains illegal characters";
+pub const BUFFER_LEN: &str = "Keyword record buffer was not exactly 80 bytes long";
+pub const ILLEGAL_CHAR: &str = "Keyword record contains illegal characters";
+pub const RECORD_LEN: &str = "Keyword record does not fit in 80 bytes";
 
 impl Error for KeywordRecordBufferErr {}
 impl Display for KeywordRecordBufferErr {
@@ -51,7 +52,7 @@ impl From<Utf8Error> for KeywordRecordBufferErr {
 
 impl KeywordRecordBufferErr {
   pub fn new(msg: &'static str) -> Self {
-    Self { msg: msg }
+    Self { msg }
   }
 }
 
@@ -78,3 +79,70 @@ impl ProtectedKeywordErr {
     ProtectedKeywordErr { keyword: kw }
   }
 }
+
+#[derive(Debug)]
+pub struct StructuralKeywordErr {
+  /*
+    This error may be thrown when an instance tries to set one of the keywords
+    that describe the layout of the data unit (SIMPLE, BITPIX, NAXISn etc.).
+    These are derived from the data when the HDU is written. Setting them by
+    hand would only produce headers that do not match their data.
+  */
+  keyword: String,
+}
+
+impl Error for StructuralKeywordErr {}
+impl Display for StructuralKeywordErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "Cannot set ({}) directly, since it is a structural keyword: {}",
+      self.keyword,
+      self.get_guidance()
+    )
+  }
+}
+
+impl StructuralKeywordErr {
+  pub(crate) fn new(keyword: &str) -> Self {
+    StructuralKeywordErr { keyword: keyword.to_string() }
+  }
+
+  pub fn get_keyword(&self) -> &str {
+    &self.keyword
+  }
+  pub fn get_guidance(&self) -> &'static str {
+    match self.keyword.as_str() {
+      "SIMPLE" | "XTENSION" => {
+        "it follows from the position of the HDU in the file (primary HDU or extension), \
+        use Fits::reorder or Fits::push_hdu to move it instead"
+      }
+      "BITPIX" => "it follows from the pixel type of the image, convert the image instead",
+      "PCOUNT" | "GCOUNT" => "it follows from the layout of the data unit",
+      _ => "it follows from the shape of the data, reshape or replace the data instead",
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct InvalidValueErr {
+  /*
+    This error may be thrown when a keyword value cannot be parsed by a
+    KeywordValue implementation.
+  */
+  value: String,
+  expected: &'static str,
+}
+
+impl Error for InvalidValueErr {}
+impl Display for InvalidValueErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "Keyword value ({}) is not a valid {}", self.value, self.expected)
+  }
+}
+
+impl InvalidValueErr {
+  pub fn new(value: &str, expected: &'static str) -> Self {
+    InvalidValueErr { value: value.to_string(), expected }
+  }
+}
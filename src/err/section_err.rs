@@ -0,0 +1,100 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{
+  error::Error,
+  fmt::{self, Display, Formatter},
+};
+
+#[derive(Debug)]
+pub struct SectionSyntaxErr {
+  /*
+      This error may be thrown when parsing an image section ("1:100,*") or an
+      extended file name ("file.fits[SCI,2][1:100,*]").
+  */
+  input: String,
+  reason: String,
+}
+
+impl Error for SectionSyntaxErr {}
+impl Display for SectionSyntaxErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "Could not parse section \"{}\": {}", self.input, self.reason)
+  }
+}
+
+impl SectionSyntaxErr {
+  pub(crate) fn new(input: &str, reason: impl Into<String>) -> Self {
+    SectionSyntaxErr { input: input.to_string(), reason: reason.into() }
+  }
+
+  pub fn get_input(&self) -> &str {
+    &self.input
+  }
+  pub fn get_reason(&self) -> &str {
+    &self.reason
+  }
+}
+
+#[derive(Debug)]
+pub struct SectionRangeErr {
+  /*
+      This error may be thrown when reading an image section. It signifies
+      that the section does not fit the image: it has the wrong number of
+      axes, or one of its (1-based) ranges lies outside the image.
+  */
+  section: String,
+  shape: Vec<usize>,
+}
+
+impl Error for SectionRangeErr {}
+impl Display for SectionRangeErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "Section [{}] does not fit in image with shape {:?} (FITS pixels are numbered from 1)",
+      self.section, self.shape
+    )
+  }
+}
+
+impl SectionRangeErr {
+  pub(crate) fn new(section: String, shape: &[usize]) -> Self {
+    SectionRangeErr { section, shape: shape.to_vec() }
+  }
+}
+
+#[derive(Debug)]
+pub struct HduNotFoundErr {
+  //thrown when the HDU named in an extended file name does not exist
+  selector: String,
+}
+
+impl Error for HduNotFoundErr {}
+impl Display for HduNotFoundErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "FITS file does not contain an image HDU matching [{}]", self.selector)
+  }
+}
+
+impl HduNotFoundErr {
+  pub(crate) fn new(selector: String) -> Self {
+    HduNotFoundErr { selector }
+  }
+}
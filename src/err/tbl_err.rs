@@ -58,8 +58,12 @@ impl IndexOutOfRangeErr {
       tbl_shape: (Some(tbl.get_shape().0), tbl.get_shape().1),
     }
   }
+  pub(crate) fn from_shape(index: (usize, usize), shape: (usize, usize)) -> Self {
+    IndexOutOfRangeErr { index: (Some(index.0), index.1), tbl_shape: (Some(shape.0), shape.1) }
+  }
+  #[allow(dead_code)]
   pub(crate) fn from_idx(index: (Option<usize>, usize), shape: (Option<usize>, usize)) -> Self {
-    IndexOutOfRangeErr { index: index, tbl_shape: shape }
+    IndexOutOfRangeErr { index, tbl_shape: shape }
   }
 }
 
@@ -81,8 +85,8 @@ impl Display for ShapeMisMatchErr {
 }
 
 impl ShapeMisMatchErr {
-  pub(crate) fn new(row: &Vec<TableEntry>, tbl: &AsciiTable) -> Self {
-    ShapeMisMatchErr { row_len: row.len(), col_len: tbl.get_shape().0 }
+  pub(crate) fn from_len(row_len: usize, col_len: usize) -> Self {
+    ShapeMisMatchErr { row_len, col_len }
   }
 }
 
@@ -101,7 +105,7 @@ impl Display for TypeMisMatchErr {
 
 impl TypeMisMatchErr {
   pub(crate) fn new(tbl_type: TableEntry, wrong_type: &TableEntry) -> Self {
-    TypeMisMatchErr { wrong_type: wrong_type.clone(), tbl_type: tbl_type }
+    TypeMisMatchErr { wrong_type: wrong_type.clone(), tbl_type }
   }
 }
 
@@ -134,3 +138,225 @@ impl From<IndexOutOfRangeErr> for TblDecodeErr {
     TblDecodeErr { msg: format!("{err}") }
   }
 }
+
+#[derive(Debug)]
+pub struct FieldWidthErr {
+  label: String,
+  width: usize,
+  got: usize,
+}
+
+impl Error for FieldWidthErr {}
+impl Display for FieldWidthErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "cannot add a string of {} characters to column '{}' with a width of {} characters",
+      self.got, self.label, self.width
+    )
+  }
+}
+
+impl FieldWidthErr {
+  pub(crate) fn new(label: &str, width: usize, got: usize) -> Self {
+    FieldWidthErr { label: label.to_string(), width, got }
+  }
+}
+
+#[derive(Debug)]
+pub struct EntryConversionErr {
+  entry: TableEntry,
+  target: &'static str,
+}
+
+impl Error for EntryConversionErr {}
+impl Display for EntryConversionErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "cannot convert table entry {} to {}", self.entry, self.target)
+  }
+}
+
+impl EntryConversionErr {
+  pub(crate) fn new(entry: TableEntry, target: &'static str) -> Self {
+    EntryConversionErr { entry, target }
+  }
+  pub fn get_entry(&self) -> &TableEntry {
+    &self.entry
+  }
+}
+
+#[derive(Debug)]
+pub struct MissingColumnErr {
+  names: Vec<String>,
+}
+
+impl Error for MissingColumnErr {}
+impl Display for MissingColumnErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "table has no column named {}", self.names.join(" or "))
+  }
+}
+
+impl MissingColumnErr {
+  pub(crate) fn new(names: &[&str]) -> Self {
+    MissingColumnErr { names: names.iter().map(|name| name.to_string()).collect() }
+  }
+  pub fn get_names(&self) -> &[String] {
+    &self.names
+  }
+}
+
+#[derive(Debug)]
+pub struct RowLayoutErr {
+  //thrown when the fields of a binary table do not add up to its row length
+  row_len: usize,
+  fields_len: usize,
+}
+
+impl Error for RowLayoutErr {}
+impl Display for RowLayoutErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "binary table rows are {} bytes long (NAXIS1), but the fields (TFORMn) take up {} bytes",
+      self.row_len, self.fields_len
+    )
+  }
+}
+
+impl RowLayoutErr {
+  pub(crate) fn new(row_len: usize, fields_len: usize) -> Self {
+    RowLayoutErr { row_len, fields_len }
+  }
+}
+
+#[derive(Debug)]
+pub struct HeapRangeErr {
+  /*
+    Thrown when the descriptor of a variable-length array in a binary table
+    points outside of the heap.
+  */
+  col: usize,
+  row: usize,
+  range: (usize, usize),
+  heap_len: usize,
+}
+
+impl Error for HeapRangeErr {}
+impl Display for HeapRangeErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "the variable-length array in (col, row) ({},{}) takes up heap bytes {}..{}, but the heap is only {} bytes long",
+      self.col, self.row, self.range.0, self.range.1, self.heap_len
+    )
+  }
+}
+
+impl HeapRangeErr {
+  pub(crate) fn new(index: (usize, usize), range: (usize, usize), heap_len: usize) -> Self {
+    HeapRangeErr { col: index.0, row: index.1, range, heap_len }
+  }
+  pub fn get_index(&self) -> (usize, usize) {
+    (self.col, self.row)
+  }
+}
+
+#[derive(Debug)]
+pub struct DescriptorOverflowErr {
+  /*
+    Thrown when writing a binary table with a variable-length array whose
+    length or heap offset does not fit in the 32-bit integers of a P
+    descriptor. Such columns need a Q descriptor (TFORMn = 'rQt').
+  */
+  col: usize,
+  row: usize,
+  len: usize,
+  offset: usize,
+}
+
+impl Error for DescriptorOverflowErr {}
+impl Display for DescriptorOverflowErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "the variable-length array in (col, row) ({},{}) has {} elements at heap offset {}, which does not fit in a P descriptor (use Q instead)",
+      self.col, self.row, self.len, self.offset
+    )
+  }
+}
+
+impl DescriptorOverflowErr {
+  pub(crate) fn new(index: (usize, usize), len: usize, offset: usize) -> Self {
+    DescriptorOverflowErr { col: index.0, row: index.1, len, offset }
+  }
+  pub fn get_index(&self) -> (usize, usize) {
+    (self.col, self.row)
+  }
+}
+
+#[derive(Debug)]
+pub struct FieldOverflowErr {
+  /*
+    Thrown when writing an ASCII table with an entry that does not fit in the
+    width of its column, as given by the TFORMn keyword of the column.
+  */
+  col: usize,
+  row: usize,
+  tform: String,
+  got: usize,
+}
+
+impl Error for FieldOverflowErr {}
+impl Display for FieldOverflowErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "the entry in (col, row) ({},{}) takes up {} characters, which does not fit in format {}",
+      self.col, self.row, self.got, self.tform
+    )
+  }
+}
+
+impl FieldOverflowErr {
+  pub(crate) fn new(index: (usize, usize), tform: &str, got: usize) -> Self {
+    FieldOverflowErr { col: index.0, row: index.1, tform: tform.to_string(), got }
+  }
+  pub fn get_index(&self) -> (usize, usize) {
+    (self.col, self.row)
+  }
+  pub fn get_tform(&self) -> &str {
+    &self.tform
+  }
+}
+
+#[derive(Debug)]
+pub struct TileDecodeErr {
+  /*
+    Thrown when a tile of a compressed binary table (ZTABLE = T) could not be
+    decompressed.
+  */
+  col: usize,
+  tile: usize,
+  message: String,
+}
+
+impl Error for TileDecodeErr {}
+impl Display for TileDecodeErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "Error while decompressing tile {} of column {} of a compressed table: {}",
+      self.tile, self.col, self.message
+    )
+  }
+}
+
+impl TileDecodeErr {
+  pub(crate) fn new(index: (usize, usize), message: String) -> Self {
+    TileDecodeErr { col: index.0, tile: index.1, message }
+  }
+  pub fn get_index(&self) -> (usize, usize) {
+    (self.col, self.tile)
+  }
+}
@@ -21,6 +21,7 @@ use std::{
   error::Error,
   fmt::{self, Display, Formatter},
   num::{ParseFloatError, ParseIntError},
+  str::Utf8Error,
 };
 
 use crate::raw::table_entry_format::TableEntryFormat;
@@ -68,7 +69,7 @@ impl Display for FieldSizeMisMatch {
 }
 
 impl FieldSizeMisMatch {
-  pub(crate) fn new(fmt: &TableEntryFormat, field: &str) -> Self {
+  pub(crate) fn new(fmt: &TableEntryFormat, field: &[u8]) -> Self {
     FieldSizeMisMatch { buf_size: field.len(), fmt_field_size: fmt.get_field_width() }
   }
 }
@@ -79,12 +80,20 @@ pub enum ParseError {
   ParseIntError(ParseIntError),
   ParseFloatError(ParseFloatError),
   InvalidFFCode(InvalidFFCode),
+  Utf8Error(Utf8Error),
 }
 
 impl Error for ParseError {}
 impl Display for ParseError {
   fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-    write!(f, "Error while parsing table entry: '{}'", self)
+    use ParseError::*;
+    match self {
+      FieldSizeMisMatch(err) => write!(f, "Error while parsing table entry: '{err}'"),
+      ParseIntError(err) => write!(f, "Error while parsing table entry: '{err}'"),
+      ParseFloatError(err) => write!(f, "Error while parsing table entry: '{err}'"),
+      InvalidFFCode(err) => write!(f, "Error while parsing table entry: '{err}'"),
+      Utf8Error(err) => write!(f, "Error while parsing table entry: '{err}'"),
+    }
   }
 }
 
@@ -106,6 +115,12 @@ impl From<ParseFloatError> for ParseError {
   }
 }
 
+impl From<Utf8Error> for ParseError {
+  fn from(err: Utf8Error) -> Self {
+    ParseError::Utf8Error(err)
+  }
+}
+
 impl From<InvalidFFCode> for ParseError {
   fn from(err: InvalidFFCode) -> Self {
     ParseError::InvalidFFCode(err)
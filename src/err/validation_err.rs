@@ -0,0 +1,55 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{
+  error::Error,
+  fmt::{self, Display, Formatter},
+};
+
+use crate::validation::Diagnostic;
+
+#[derive(Debug)]
+pub struct ValidationErr {
+  /*
+      This error may be thrown when writing a FITS file with validation
+      profiles. It contains all diagnostics with the Error severity.
+  */
+  diagnostics: Vec<Diagnostic>,
+}
+
+impl Error for ValidationErr {}
+impl Display for ValidationErr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    writeln!(f, "FITS file failed validation with {} error(s):", self.diagnostics.len())?;
+    for diagnostic in &self.diagnostics {
+      writeln!(f, "  {diagnostic}")?;
+    }
+    Ok(())
+  }
+}
+
+impl ValidationErr {
+  pub(crate) fn new(diagnostics: Vec<Diagnostic>) -> Self {
+    ValidationErr { diagnostics }
+  }
+
+  pub fn get_diagnostics(&self) -> &[Diagnostic] {
+    &self.diagnostics
+  }
+}
@@ -0,0 +1,90 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Export of HDU's to text formats read by other tools: tables to IPAC tables
+    and (with the votable feature) to VOTable XML, for use in VO tools, and
+    source catalogs to DS9 region files. See table_export.rs and
+    ds9_regions.rs for the formats.
+*/
+
+use std::{error::Error, fs, path::Path};
+
+use crate::{
+  extensions::Extension, hdu_err::MissingDataErr, header_data_unit::HeaderDataUnit,
+  io_err::FitsIoErr,
+};
+
+//Module structure
+mod ds9_regions;
+mod table_export;
+
+//re-exports for readability
+pub use ds9_regions::RegionOptions;
+
+impl HeaderDataUnit {
+  pub fn to_ipac(&self) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let Some(Extension::AsciiTable(table)) = self.get_data() else {
+      return Err(Box::new(MissingDataErr::new("a table")));
+    };
+    table_export::to_ipac(self.get_header(), table)
+  }
+
+  pub fn write_ipac(&self, path: &Path) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let text = self.to_ipac()?;
+    fs::write(path, text).map_err(|err| FitsIoErr::new(path, "write IPAC table", err))?;
+    Ok(())
+  }
+
+  pub fn to_ds9_regions(
+    &self,
+    opts: &RegionOptions,
+  ) -> Result<String, Box<dyn Error + Send + Sync>> {
+    /* Catalog rows as DS9 regions */
+    let Some(Extension::AsciiTable(table)) = self.get_data() else {
+      return Err(Box::new(MissingDataErr::new("a table")));
+    };
+    ds9_regions::to_ds9_regions(self.get_header(), table, opts)
+  }
+
+  pub fn write_ds9_regions(
+    &self,
+    path: &Path,
+    opts: &RegionOptions,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let text = self.to_ds9_regions(opts)?;
+    fs::write(path, text).map_err(|err| FitsIoErr::new(path, "write DS9 regions", err))?;
+    Ok(())
+  }
+
+  #[cfg(feature = "votable")]
+  pub fn to_votable(&self) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let Some(Extension::AsciiTable(table)) = self.get_data() else {
+      return Err(Box::new(MissingDataErr::new("a table")));
+    };
+    table_export::to_votable(self.get_header(), table)
+  }
+
+  #[cfg(feature = "votable")]
+  pub fn write_votable(&self, path: &Path) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let text = self.to_votable()?;
+    fs::write(path, text).map_err(|err| FitsIoErr::new(path, "write VOTable", err))?;
+    Ok(())
+  }
+}
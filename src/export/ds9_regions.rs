@@ -0,0 +1,215 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::error::Error;
+
+use crate::{
+  extensions::table::{AsciiTable, TableEntry},
+  header::Header,
+  keyword_value::unquote,
+  tbl_err::MissingColumnErr,
+};
+
+/*
+    Export of source catalogs (such as those from SExtractor) as DS9 region
+    files, for overlaying the sources on an image. Every row becomes an
+    ellipse, or a circle when no shape columns are used. The positions are
+    taken to be FITS pixel coordinates (the center of the first pixel is at
+    1, 1), which is what SExtractor writes and what DS9 calls image
+    coordinates. THETA is in degrees, counter-clockwise from the x axis.
+
+    Rows with a missing (NaN) position or shape are skipped.
+*/
+
+#[derive(Debug, Clone)]
+pub struct RegionOptions {
+  x: String,
+  y: String,
+  shape: Option<[String; 3]>, //A, B and THETA columns
+  label: Option<String>,
+  scale: f64,
+  radius: f64,
+  color: String,
+}
+
+impl Default for RegionOptions {
+  fn default() -> Self {
+    //The column names of SExtractor
+    RegionOptions {
+      x: String::from("X_IMAGE"),
+      y: String::from("Y_IMAGE"),
+      shape: Some([String::from("A_IMAGE"), String::from("B_IMAGE"), String::from("THETA_IMAGE")]),
+      label: None,
+      scale: 1.0,
+      radius: 3.0,
+      color: String::from("green"),
+    }
+  }
+}
+
+impl RegionOptions {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn position(mut self, x: &str, y: &str) -> Self {
+    self.x = x.to_string();
+    self.y = y.to_string();
+    self
+  }
+
+  pub fn ellipse(mut self, a: &str, b: &str, theta: &str) -> Self {
+    //Semi-major and semi-minor axis columns (in pixels), and the angle
+    self.shape = Some([a.to_string(), b.to_string(), theta.to_string()]);
+    self
+  }
+
+  pub fn circle(mut self, radius: f64) -> Self {
+    //Circles of a fixed radius (in pixels) instead of ellipses
+    self.shape = None;
+    self.radius = radius;
+    self
+  }
+
+  pub fn label(mut self, column: &str) -> Self {
+    //Column whose value is shown next to each region
+    self.label = Some(column.to_string());
+    self
+  }
+
+  pub fn scale(mut self, scale: f64) -> Self {
+    //Factor for the axes of the ellipses, such as the Kron factor
+    self.scale = scale;
+    self
+  }
+
+  pub fn color(mut self, color: &str) -> Self {
+    self.color = color.to_string();
+    self
+  }
+
+  pub fn get_position(&self) -> (&str, &str) {
+    (&self.x, &self.y)
+  }
+  pub fn get_ellipse(&self) -> Option<&[String; 3]> {
+    self.shape.as_ref()
+  }
+  pub fn get_radius(&self) -> f64 {
+    self.radius
+  }
+  pub fn get_label(&self) -> Option<&str> {
+    self.label.as_deref()
+  }
+  pub fn get_scale(&self) -> f64 {
+    self.scale
+  }
+  pub fn get_color(&self) -> &str {
+    &self.color
+  }
+}
+
+fn find_column(header: &Header, table: &AsciiTable, name: &str) -> Result<usize, MissingColumnErr> {
+  //Column names (TTYPEn) are case insensitive
+  (0..table.get_shape().0)
+    .find(|col| {
+      header
+        .get_value(&format!("TTYPE{}", col + 1))
+        .and_then(|raw| unquote(raw))
+        .is_some_and(|ttype| ttype.trim().eq_ignore_ascii_case(name))
+    })
+    .ok_or_else(|| MissingColumnErr::new(&[name]))
+}
+
+fn float_column(
+  header: &Header,
+  table: &AsciiTable,
+  name: &str,
+) -> Result<Vec<f64>, Box<dyn Error + Send + Sync>> {
+  let col = find_column(header, table, name)?;
+  (0..table.get_shape().1).map(|row| table.get_float(col, row)).collect()
+}
+
+pub(crate) fn to_ds9_regions(
+  header: &Header,
+  table: &AsciiTable,
+  opts: &RegionOptions,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+  //(1) Collect the columns
+  let (x, y) = (float_column(header, table, &opts.x)?, float_column(header, table, &opts.y)?);
+  let shape = match &opts.shape {
+    Some(cols) => Some(
+      cols
+        .iter()
+        .map(|col| float_column(header, table, col))
+        .collect::<Result<Vec<Vec<f64>>, Box<dyn Error + Send + Sync>>>()?,
+    ),
+    None => None,
+  };
+  let labels = match &opts.label {
+    Some(name) => {
+      let col = find_column(header, table, name)?;
+      let labels = (0..table.get_shape().1).map(|row| {
+        Ok(match table.get_entry(col, row)? {
+          TableEntry::Text(text) => text.trim().to_string(),
+          TableEntry::Int(int) => int.to_string(),
+          TableEntry::Float(float) => float.to_string(),
+          TableEntry::Complex(..) | TableEntry::Array(_) => {
+            unreachable!("ASCII tables only have scalar entries")
+          }
+        })
+      });
+      Some(labels.collect::<Result<Vec<String>, Box<dyn Error + Send + Sync>>>()?)
+    }
+    None => None,
+  };
+
+  //(2) Preamble. The coordinate system applies to all regions below it
+  let mut out = String::from("# Region file format: DS9 version 4.1\n");
+  if let Some(extname) = header.get_value("EXTNAME").and_then(|raw| unquote(raw)) {
+    out.push_str(&format!("# Sources from {extname}\n"));
+  }
+  out.push_str(&format!("global color={}\nimage\n", opts.color));
+
+  //(3) One region per row
+  for row in 0..x.len() {
+    let region = match &shape {
+      Some(shape) => {
+        let (a, b, theta) = (shape[0][row] * opts.scale, shape[1][row] * opts.scale, shape[2][row]);
+        if ![x[row], y[row], a, b, theta].iter().all(|val| val.is_finite()) {
+          continue;
+        }
+        format!("ellipse({},{},{a},{b},{theta})", x[row], y[row])
+      }
+      None => {
+        if !x[row].is_finite() || !y[row].is_finite() {
+          continue;
+        }
+        format!("circle({},{},{})", x[row], y[row], opts.radius)
+      }
+    };
+    out.push_str(&region);
+
+    //Braces delimit the text in DS9, so they cannot be part of it
+    if let Some(labels) = &labels {
+      out.push_str(&format!(" # text={{{}}}", labels[row].replace(['{', '}'], "")));
+    }
+    out.push('\n');
+  }
+  Ok(out)
+}
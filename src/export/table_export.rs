@@ -0,0 +1,216 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Export of tables to the text formats that VO tools (TOPCAT, Aladin, the
+    IRSA services) read: IPAC tables and, with the votable feature, VOTable
+    XML (TABLEDATA serialization). Column names come from TTYPEn (or the
+    column labels) and units from TUNITn. Integer columns are exported as 64 bit integers, float
+    columns as doubles (NaN becomes null) and text columns as strings. Null
+    entries (see TNULLn) are exported as nulls too.
+*/
+
+use std::error::Error;
+
+use crate::{
+  extensions::table::{AsciiTable, TableEntry},
+  header::Header,
+  keyword_value::unquote,
+  raw::table_entry_format::TableEntryFormat,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+  Int,
+  Float,
+  Text,
+}
+
+#[derive(Debug, Clone)]
+struct Column {
+  name: String,
+  unit: Option<String>,
+  kind: Kind,
+  values: Vec<Option<String>>, //None for null values
+}
+
+fn columns(
+  header: &Header,
+  table: &AsciiTable,
+) -> Result<Vec<Column>, Box<dyn Error + Send + Sync>> {
+  let (n_cols, n_rows) = table.get_shape();
+  let mut columns = Vec::with_capacity(n_cols);
+  for col in 0..n_cols {
+    let n = col + 1;
+    let kind = match table.get_col_fmt(col) {
+      Some(TableEntryFormat::Int(_)) => Kind::Int,
+      Some(TableEntryFormat::Float(_)) => Kind::Float,
+      _ => Kind::Text,
+    };
+    let values = (0..n_rows)
+      .map(|row| {
+        Ok(match table.get_entry(col, row)? {
+          _ if table.is_null(col, row) => None,
+          TableEntry::Float(float) if float.is_nan() => None,
+          TableEntry::Float(float) => Some(format!("{float:?}")),
+          TableEntry::Int(int) => Some(int.to_string()),
+          TableEntry::Text(text) => Some(text.trim_end().to_string()),
+          TableEntry::Complex(..) | TableEntry::Array(_) => {
+            unreachable!("ASCII tables only have scalar entries")
+          }
+        })
+      })
+      .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()?;
+    let name = header
+      .get_value(&format!("TTYPE{n}"))
+      .and_then(|raw| unquote(raw))
+      .or_else(|| table.get_col_label(col).map(|label| unquote(label).unwrap_or(label.to_string())))
+      .map(|name| name.trim().to_string())
+      .filter(|name| !name.is_empty())
+      .unwrap_or(format!("col{n}"));
+    let unit = header.get_value(&format!("TUNIT{n}")).and_then(|raw| unquote(raw));
+    columns.push(Column { name, unit: unit.filter(|unit| !unit.is_empty()), kind, values });
+  }
+  Ok(columns)
+}
+
+/*
+    IPAC tables have up to four header lines, delimited by |: the column
+    names, types, units and null values. Values are aligned in the columns
+    between the delimiters. Keywords of the table are written as \KEY = value.
+*/
+pub(crate) fn to_ipac(
+  header: &Header,
+  table: &AsciiTable,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+  const NULL: &str = "null";
+  let columns = columns(header, table)?;
+
+  //(1) Keywords. IPAC names cannot contain spaces
+  let mut out = String::from("\\fixlen = T\n");
+  if let Some(extname) = header.get_value("EXTNAME").and_then(|raw| unquote(raw)) {
+    out.push_str(&format!("\\EXTNAME = '{extname}'\n"));
+  }
+  let names: Vec<String> = columns.iter().map(|col| col.name.replace(' ', "_")).collect();
+  let types: Vec<&str> = columns
+    .iter()
+    .map(|col| match col.kind {
+      Kind::Int => "long",
+      Kind::Float => "double",
+      Kind::Text => "char",
+    })
+    .collect();
+  let units: Vec<&str> = columns.iter().map(|col| col.unit.as_deref().unwrap_or("")).collect();
+
+  //(2) Every column is as wide as its widest field
+  let widths: Vec<usize> = (0..columns.len())
+    .map(|col| {
+      let values = columns[col].values.iter().map(|value| value.as_deref().unwrap_or(NULL).len());
+      [names[col].len(), types[col].len(), units[col].len(), NULL.len()]
+        .into_iter()
+        .chain(values)
+        .max()
+        .unwrap_or(0)
+    })
+    .collect();
+
+  //(3) Header lines and data lines
+  let header_line = |fields: &[&str]| {
+    let line: String =
+      fields.iter().zip(&widths).map(|(field, width)| format!("|{field:<width$}")).collect();
+    format!("{line}|\n")
+  };
+  let names: Vec<&str> = names.iter().map(String::as_str).collect();
+  out.push_str(&header_line(&names));
+  out.push_str(&header_line(&types));
+  if units.iter().any(|unit| !unit.is_empty()) {
+    out.push_str(&header_line(&units));
+  }
+  out.push_str(&header_line(&vec![NULL; columns.len()]));
+  for row in 0..table.get_shape().1 {
+    let line: String = columns
+      .iter()
+      .zip(&widths)
+      .map(|(col, width)| {
+        let value = col.values[row].as_deref().unwrap_or(NULL);
+        match col.kind {
+          Kind::Text => format!(" {value:<width$}"),
+          _ => format!(" {value:>width$}"),
+        }
+      })
+      .collect();
+    out.push_str(&format!("{line} \n"));
+  }
+  Ok(out)
+}
+
+#[cfg(feature = "votable")]
+pub(crate) fn to_votable(
+  header: &Header,
+  table: &AsciiTable,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+  let columns = columns(header, table)?;
+  let name = header.get_value("EXTNAME").and_then(|raw| unquote(raw));
+
+  //(1) Table metadata
+  let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+  out.push_str("<VOTABLE version=\"1.4\" xmlns=\"http://www.ivoa.net/xml/VOTable/v1.3\">\n");
+  out.push_str("  <RESOURCE>\n");
+  match name {
+    Some(name) => out.push_str(&format!("    <TABLE name=\"{}\">\n", xml_escape(&name))),
+    None => out.push_str("    <TABLE>\n"),
+  }
+  for col in &columns {
+    let datatype = match col.kind {
+      Kind::Int => "datatype=\"long\"",
+      Kind::Float => "datatype=\"double\"",
+      Kind::Text => "datatype=\"char\" arraysize=\"*\"",
+    };
+    let unit = match &col.unit {
+      Some(unit) => format!(" unit=\"{}\"", xml_escape(unit)),
+      None => String::new(),
+    };
+    out.push_str(&format!("      <FIELD name=\"{}\" {datatype}{unit}/>\n", xml_escape(&col.name)));
+  }
+
+  //(2) The rows. Null values are empty cells
+  out.push_str("      <DATA>\n        <TABLEDATA>\n");
+  for row in 0..table.get_shape().1 {
+    out.push_str("          <TR>");
+    for col in &columns {
+      match &col.values[row] {
+        Some(value) => out.push_str(&format!("<TD>{}</TD>", xml_escape(value))),
+        None => out.push_str("<TD/>"),
+      }
+    }
+    out.push_str("</TR>\n");
+  }
+  out.push_str("        </TABLEDATA>\n      </DATA>\n    </TABLE>\n  </RESOURCE>\n</VOTABLE>\n");
+  Ok(out)
+}
+
+#[cfg(feature = "votable")]
+fn xml_escape(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+    .replace('\'', "&apos;")
+}
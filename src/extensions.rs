@@ -23,13 +23,14 @@ use std::{
 };
 
 use crate::{
+  header::Header,
   io_err::{self, InvalidFitsFileErr as IFFErr},
-  raw::{raw_io::RawFitsWriter, BlockSized},
+  raw::{block_io::BlockWrite, BlockSized},
 };
 
 use self::{
   image::{ImgParser, TypedImage},
-  table::{AsciiTable, AsciiTblParser},
+  table::{AsciiTable, AsciiTblParser, BinTable, BinTblParser},
 };
 
 //FITS standard-conforming extensions
@@ -47,6 +48,7 @@ pub enum Extension {
   Corrupted,
   Image(TypedImage),
   AsciiTable(AsciiTable),
+  BinTable(BinTable),
 }
 
 impl BlockSized for Extension {
@@ -56,6 +58,7 @@ impl BlockSized for Extension {
       Corrupted => 0, //corrupted data is disregarded
       Image(img) => img.get_block_len(),
       AsciiTable(tbl) => tbl.get_block_len(),
+      BinTable(tbl) => tbl.get_block_len(),
     }
   }
 }
@@ -67,17 +70,37 @@ impl Display for Extension {
       Corrupted => write!(f, "(CORRUPTED_DATA)"),
       Image(img) => write!(f, "{}", img.xprint()),
       AsciiTable(tbl) => write!(f, "{}", tbl.xprint()),
+      BinTable(tbl) => write!(f, "{}", tbl.xprint()),
     }
   }
 }
 
 impl Extension {
-  pub(crate) fn write_to_buffer(self, writer: &mut RawFitsWriter) -> Result<(), Box<dyn Error>> {
+  pub(crate) fn get_memory_usage(&self) -> usize {
+    //Approximate number of bytes the decoded data takes up in memory
+    use Extension::*;
+    match &self {
+      Corrupted => 0,
+      Image(img) => img.get_memory_usage(),
+      AsciiTable(tbl) => tbl.get_memory_usage(),
+      BinTable(tbl) => tbl.get_memory_usage(),
+    }
+  }
+
+  pub(crate) fn write_to_buffer(
+    self,
+    writer: &mut dyn BlockWrite,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
     use Extension::*;
     match self {
-      Corrupted => return Err(Box::new(IFFErr::new(io_err::CORRUPTED))),
+      Corrupted => Err(Box::new(IFFErr::new(io_err::CORRUPTED))),
       Image(img) => ImgParser::encode_img(img, writer),
-      AsciiTable(tbl) => AsciiTblParser::encode_tbl(tbl, writer),
+      AsciiTable(tbl) => {
+        //Without the header of the HDU, the fields are packed
+        let layout = AsciiTblParser::encode_layout(&tbl, &mut Header::new());
+        AsciiTblParser::encode_tbl(tbl, layout, writer)
+      }
+      BinTable(tbl) => BinTblParser::encode_tbl(tbl, writer),
     }
   }
 }
@@ -18,11 +18,33 @@
 */
 
 //Module structure
+mod compact_image;
+mod convolution;
+#[cfg(feature = "fft")]
+mod fft;
+mod fits_pixel;
 mod generic_image;
+#[cfg(feature = "gpu")]
+mod gpu;
 mod image_parser;
+mod inpaint;
+#[cfg(feature = "mmap")]
+#[allow(unsafe_code)]
+mod mapped_pixels;
+mod ops;
+mod reduction;
 mod typed_image;
 
 //re-exports for readability
+pub use compact_image::CompactImage;
+pub use convolution::{boxcar_kernel, gaussian_kernel, Boundary};
+#[cfg(feature = "fft")]
+pub use fft::{FftNorm, FftOptions, FftPadding};
+pub use fits_pixel::FitsPixel;
 pub use generic_image::Image;
 pub(crate) use image_parser::ImgParser;
+pub use inpaint::InpaintMethod;
+#[cfg(feature = "mmap")]
+pub(crate) use mapped_pixels::MappedPixels;
+pub use reduction::Reduction;
 pub use typed_image::TypedImage;
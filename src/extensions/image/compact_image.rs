@@ -0,0 +1,250 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{
+  collections::{hash_map::DefaultHasher, HashMap},
+  hash::Hasher,
+  mem::size_of,
+};
+
+use ndarray::{Array, IxDyn, ShapeBuilder};
+use num_traits::ToPrimitive;
+
+use crate::bitpix::Bitpix;
+
+use super::{generic_image::Image, typed_image::TypedImage, FitsPixel};
+
+/*
+    Large calibration frames (flats, masks, bad pixel maps) often consist of
+    huge constant regions. A CompactImage stores the pixels (in the order they
+    have in a FITS file) in tiles of a fixed number of pixels:
+
+    - constant tiles are stored as a single pixel,
+    - other tiles are stored once, even if the same tile occurs many times.
+
+    This keeps many such frames in memory at a fraction of the cost of a
+    TypedImage, for example while stacking them one by one (Stack::add_compact).
+    The conversion is lossless, except that constant NaN tiles all get the NaN
+    payload of their first pixel.
+*/
+
+//Default number of pixels in a tile
+const DEFAULT_TILE_LEN: usize = 4096;
+
+#[derive(Debug, Clone)]
+enum Tile<T> {
+  Constant(T),
+  Chunk(usize), //index into the stored chunks
+}
+
+#[derive(Debug, Clone)]
+struct Tiles<T> {
+  tiles: Vec<Tile<T>>,
+  chunks: Vec<Vec<T>>,
+}
+
+#[derive(Debug, Clone)]
+enum CompactData {
+  Byte(Tiles<u8>),
+  Short(Tiles<i16>),
+  Int(Tiles<i32>),
+  Long(Tiles<i64>),
+  Spf(Tiles<f32>),
+  Dpf(Tiles<f64>),
+}
+
+#[derive(Debug, Clone)]
+pub struct CompactImage {
+  shape: Vec<usize>,
+  tile_len: usize,
+  data: CompactData,
+}
+
+fn same<T: FitsPixel + ToPrimitive>(a: T, b: T) -> bool {
+  //Pixels are the same if they are equal (with the same sign, for -0.0) or
+  //both NaN
+  let (x, y) = (a.to_f64(), b.to_f64());
+  match a == b {
+    true => x.map(f64::is_sign_negative) == y.map(f64::is_sign_negative),
+    false => x.is_some_and(f64::is_nan) && y.is_some_and(f64::is_nan),
+  }
+}
+
+fn chunk_hash<T: FitsPixel + ToPrimitive>(chunk: &[T]) -> u64 {
+  //Same chunks have the same hash (all NaN's hash alike)
+  let mut hasher = DefaultHasher::new();
+  for px in chunk {
+    match px.to_f64() {
+      Some(px) if px.is_nan() => hasher.write_u64(f64::NAN.to_bits()),
+      Some(px) => hasher.write_u64(px.to_bits()),
+      None => hasher.write_u8(0),
+    }
+  }
+  hasher.finish()
+}
+
+impl<T: FitsPixel + ToPrimitive> Tiles<T> {
+  fn from_pixels(pixels: &[T], tile_len: usize) -> Self {
+    let mut tiles = Tiles { tiles: Vec::new(), chunks: Vec::new() };
+    let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+    for chunk in pixels.chunks(tile_len) {
+      //(1) Constant tiles only keep their first pixel
+      if chunk.iter().all(|&px| same(px, chunk[0])) {
+        tiles.tiles.push(Tile::Constant(chunk[0]));
+        continue;
+      }
+
+      //(2) Other tiles are only stored if they were not seen before
+      let candidates = seen.entry(chunk_hash(chunk)).or_default();
+      let found = candidates.iter().copied().find(|&idx| {
+        let stored = &tiles.chunks[idx];
+        stored.len() == chunk.len() && stored.iter().zip(chunk).all(|(&a, &b)| same(a, b))
+      });
+      let idx = found.unwrap_or_else(|| {
+        tiles.chunks.push(chunk.to_vec());
+        candidates.push(tiles.chunks.len() - 1);
+        tiles.chunks.len() - 1
+      });
+      tiles.tiles.push(Tile::Chunk(idx));
+    }
+    tiles
+  }
+
+  fn to_pixels(&self, n_pixels: usize, tile_len: usize) -> Vec<T> {
+    let mut pixels = Vec::with_capacity(n_pixels);
+    for tile in &self.tiles {
+      match tile {
+        Tile::Constant(px) => {
+          let len = tile_len.min(n_pixels - pixels.len());
+          pixels.extend(std::iter::repeat_n(*px, len));
+        }
+        Tile::Chunk(idx) => pixels.extend_from_slice(&self.chunks[*idx]),
+      }
+    }
+    pixels
+  }
+
+  fn memory_usage(&self) -> usize {
+    self.tiles.len() * size_of::<Tile<T>>()
+      + self
+        .chunks
+        .iter()
+        .map(|chunk| chunk.len() * size_of::<T>() + size_of::<Vec<T>>())
+        .sum::<usize>()
+  }
+
+  fn n_constant(&self) -> usize {
+    self.tiles.iter().filter(|tile| matches!(tile, Tile::Constant(_))).count()
+  }
+}
+
+macro_rules! compact_dispatch {
+  //Six-way match over the variants of CompactData, binding the tiles
+  ($data:expr, $tiles:ident => $body:expr) => {
+    match $data {
+      CompactData::Byte($tiles) => $body,
+      CompactData::Short($tiles) => $body,
+      CompactData::Int($tiles) => $body,
+      CompactData::Long($tiles) => $body,
+      CompactData::Spf($tiles) => $body,
+      CompactData::Dpf($tiles) => $body,
+    }
+  };
+}
+
+impl CompactImage {
+  pub fn from_image(img: &TypedImage) -> Self {
+    Self::from_image_tiled(img, DEFAULT_TILE_LEN)
+  }
+
+  pub fn from_image_tiled(img: &TypedImage, tile_len: usize) -> Self {
+    //Compacts the image using tiles of tile_len pixels (at least one)
+    fn flat<T: FitsPixel>(img: &Image<T>) -> Vec<T> {
+      //Pixels in FITS (Fortran) order
+      img.get_data().t().iter().copied().collect()
+    }
+    let tile_len = tile_len.max(1);
+    let shape = crate::impl_typed_image_dispatch!(img, inner => inner.get_shape().clone());
+    let data = match img {
+      TypedImage::ByteImg(img) => CompactData::Byte(Tiles::from_pixels(&flat(img), tile_len)),
+      TypedImage::I16Img(img) => CompactData::Short(Tiles::from_pixels(&flat(img), tile_len)),
+      TypedImage::I32Img(img) => CompactData::Int(Tiles::from_pixels(&flat(img), tile_len)),
+      TypedImage::I64Img(img) => CompactData::Long(Tiles::from_pixels(&flat(img), tile_len)),
+      TypedImage::SpfImg(img) => CompactData::Spf(Tiles::from_pixels(&flat(img), tile_len)),
+      TypedImage::DpfImg(img) => CompactData::Dpf(Tiles::from_pixels(&flat(img), tile_len)),
+    };
+    CompactImage { shape, tile_len, data }
+  }
+
+  pub fn to_image(&self) -> TypedImage {
+    fn image<T: FitsPixel + ToPrimitive>(tiles: &Tiles<T>, compact: &CompactImage) -> TypedImage {
+      let pixels = tiles.to_pixels(compact.get_n_pixels(), compact.tile_len);
+      let data = Array::<T, IxDyn>::from_shape_vec(compact.shape.clone().f(), pixels).unwrap();
+      Image::new(data).into()
+    }
+    compact_dispatch!(&self.data, tiles => image(tiles, self))
+  }
+
+  pub fn get_bitpix(&self) -> Bitpix {
+    match self.data {
+      CompactData::Byte(_) => Bitpix::Byte,
+      CompactData::Short(_) => Bitpix::Short,
+      CompactData::Int(_) => Bitpix::Int,
+      CompactData::Long(_) => Bitpix::Long,
+      CompactData::Spf(_) => Bitpix::Spf,
+      CompactData::Dpf(_) => Bitpix::Dpf,
+    }
+  }
+  pub fn get_shape(&self) -> &[usize] {
+    &self.shape
+  }
+  pub fn get_n_pixels(&self) -> usize {
+    self.shape.iter().product()
+  }
+  pub fn get_tile_len(&self) -> usize {
+    self.tile_len
+  }
+  pub fn get_num_tiles(&self) -> usize {
+    compact_dispatch!(&self.data, tiles => tiles.tiles.len())
+  }
+  pub fn get_num_constant_tiles(&self) -> usize {
+    compact_dispatch!(&self.data, tiles => tiles.n_constant())
+  }
+  pub fn get_num_stored_tiles(&self) -> usize {
+    //Distinct non-constant tiles, which are the only ones stored in full
+    compact_dispatch!(&self.data, tiles => tiles.chunks.len())
+  }
+
+  pub fn get_memory_usage(&self) -> usize {
+    //Bytes used by the pixels (compare with the size of the full image)
+    compact_dispatch!(&self.data, tiles => tiles.memory_usage())
+  }
+}
+
+impl From<&TypedImage> for CompactImage {
+  fn from(img: &TypedImage) -> Self {
+    Self::from_image(img)
+  }
+}
+
+impl From<&CompactImage> for TypedImage {
+  fn from(img: &CompactImage) -> Self {
+    img.to_image()
+  }
+}
@@ -0,0 +1,125 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use ndarray::{Array, Array2, ArrayView2, ArrayViewD, IxDyn, ShapeBuilder};
+use rayon::prelude::*;
+
+/*
+    2-D convolution of images with a kernel. The kernel is applied to the
+    first two axes of the image (NAXIS1 and NAXIS2), every plane of a cube is
+    convolved separately. Axis 0 of the kernel runs along NAXIS1 and its center
+    is the element at (rows / 2, cols / 2). This is a true convolution, so a
+    single bright pixel turns into (a scaled copy of) the kernel itself.
+
+    Pixels outside the image are taken from the Boundary. NaN pixels are not
+    treated specially, they spread over the footprint of the kernel.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Boundary {
+  /*  THIS ENUM IS PART OF THE USER-FACING API
+      Fill uses a constant for all pixels outside the image, Extend repeats the
+      edge pixels, Wrap treats the image as periodic and Mirror reflects it in
+      its edges (d c b a | a b c d | d c b a).
+  */
+  Fill(f64),
+  Extend,
+  Wrap,
+  Mirror,
+}
+
+impl Boundary {
+  fn index(&self, index: isize, len: usize) -> Option<usize> {
+    //Index of the image pixel to use, or None for the fill value
+    let len = len as isize;
+    if (0..len).contains(&index) {
+      return Some(index as usize);
+    }
+    match self {
+      Boundary::Fill(_) => None,
+      Boundary::Extend => Some(index.clamp(0, len - 1) as usize),
+      Boundary::Wrap => Some(index.rem_euclid(len) as usize),
+      Boundary::Mirror => {
+        let folded = index.rem_euclid(2 * len);
+        Some(if folded < len { folded } else { 2 * len - folded - 1 } as usize)
+      }
+    }
+  }
+}
+
+pub fn gaussian_kernel(sigma: f64) -> Array2<f64> {
+  //Normalized gaussian, cut off at 4 sigma. Sigma is given in pixels
+  if sigma <= 0.0 {
+    return Array2::ones((1, 1));
+  }
+  let radius = (4.0 * sigma).ceil() as isize;
+  let size = (2 * radius + 1) as usize;
+  let kernel = Array2::from_shape_fn((size, size), |(i, j)| {
+    let (x, y) = ((i as isize - radius) as f64, (j as isize - radius) as f64);
+    (-(x * x + y * y) / (2.0 * sigma * sigma)).exp()
+  });
+  let sum = kernel.sum();
+  kernel / sum
+}
+
+pub fn boxcar_kernel(width: usize) -> Array2<f64> {
+  //Normalized width x width box (running mean)
+  let width = width.max(1);
+  Array2::from_elem((width, width), 1.0 / (width * width) as f64)
+}
+
+pub(crate) fn convolve(
+  data: ArrayViewD<f64>,
+  kernel: ArrayView2<f64>,
+  boundary: Boundary,
+) -> Array<f64, IxDyn> {
+  //(1) Flatten the image in the Fortran layout. 1-D images are a single row
+  let shape = data.shape().to_vec();
+  let (nx, ny) = (shape.first().copied().unwrap_or(1), shape.get(1).copied().unwrap_or(1));
+  let pixels: Vec<f64> = data.t().iter().copied().collect();
+  let (kx, ky) = kernel.dim();
+  let (cx, cy) = ((kx / 2) as isize, (ky / 2) as isize);
+  let fill = match boundary {
+    Boundary::Fill(value) => value,
+    _ => 0.0,
+  };
+
+  //(2) Convolve the rows of all planes in parallel
+  let mut out = vec![0.0; pixels.len()];
+  out.par_chunks_mut(nx.max(1)).enumerate().for_each(|(row, out_row)| {
+    let (plane, y) = (row / ny, row % ny);
+    let plane = &pixels[plane * nx * ny..(plane + 1) * nx * ny];
+    for (x, out_px) in out_row.iter_mut().enumerate() {
+      let mut sum = 0.0;
+      for ((i, j), &weight) in kernel.indexed_iter() {
+        let src_x = boundary.index(x as isize + cx - i as isize, nx);
+        let src_y = boundary.index(y as isize + cy - j as isize, ny);
+        sum += weight
+          * match (src_x, src_y) {
+            (Some(src_x), Some(src_y)) => plane[src_x + nx * src_y],
+            _ => fill,
+          };
+      }
+      *out_px = sum;
+    }
+  });
+
+  //(R) back into the shape of the image
+  Array::from_shape_vec(shape.f(), out).unwrap()
+}
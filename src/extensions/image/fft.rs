@@ -0,0 +1,185 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use ndarray::{Array, ArrayViewD, IxDyn, ShapeBuilder};
+use num_complex::Complex;
+use rayon::prelude::*;
+use rustfft::{FftDirection, FftPlanner};
+
+/*
+    2-D discrete Fourier transforms of images (enabled with the fft feature),
+    as a building block for matched filtering and deconvolution. Like the
+    convolution, the transform is applied to the first two axes of the image
+    (NAXIS1 and NAXIS2), and every plane of a cube is transformed separately.
+
+    The spectrum is not shifted: the zero frequency is the first element, as
+    in numpy.fft. The rows and columns are transformed by rustfft, which is
+    fastest for lengths with only small prime factors, so padding to a power
+    of two is still worthwhile for repeated transforms. Padding, cropping and
+    normalization are done here.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FftNorm {
+  /*  THIS ENUM IS PART OF THE USER-FACING API
+      Scaling of the transforms, with the same meaning as in numpy.fft.
+      Backward leaves the forward transform unscaled and divides the inverse
+      by the number of pixels, Forward does the opposite and Ortho scales both
+      by the square root of the number of pixels (preserving the power).
+  */
+  #[default]
+  Backward,
+  Ortho,
+  Forward,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FftPadding {
+  /*  THIS ENUM IS PART OF THE USER-FACING API
+      Zero padding of the first two axes before the forward transform. The
+      zeros are added after the last pixel. To pads to (at least) the given
+      lengths, for instance the image plus kernel size minus one to get a
+      linear rather than circular convolution.
+  */
+  #[default]
+  None,
+  PowerOfTwo,
+  To(usize, usize),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FftOptions {
+  padding: FftPadding,
+  norm: FftNorm,
+}
+
+impl FftOptions {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn padding(mut self, padding: FftPadding) -> Self {
+    self.padding = padding;
+    self
+  }
+
+  pub fn norm(mut self, norm: FftNorm) -> Self {
+    self.norm = norm;
+    self
+  }
+
+  pub fn get_padding(&self) -> FftPadding {
+    self.padding
+  }
+  pub fn get_norm(&self) -> FftNorm {
+    self.norm
+  }
+
+  pub(crate) fn padded_len(&self, len: usize, axis: usize) -> usize {
+    match self.padding {
+      FftPadding::None => len,
+      FftPadding::PowerOfTwo => len.next_power_of_two(),
+      FftPadding::To(n1, n2) => len.max([n1, n2][axis]),
+    }
+  }
+
+  fn scale(&self, n_pixels: usize, inverse: bool) -> f64 {
+    match (self.norm, inverse) {
+      (FftNorm::Backward, false) | (FftNorm::Forward, true) => 1.0,
+      (FftNorm::Backward, true) | (FftNorm::Forward, false) => 1.0 / n_pixels as f64,
+      (FftNorm::Ortho, _) => 1.0 / (n_pixels as f64).sqrt(),
+    }
+  }
+}
+
+pub(crate) fn plane_dims(shape: &[usize]) -> (usize, usize) {
+  //Lengths of the first two axes. 1-D images are a single row
+  (shape.first().copied().unwrap_or(1), shape.get(1).copied().unwrap_or(1))
+}
+
+fn fft_lanes(buf: &mut [Complex<f64>], len: usize, planner: &mut FftPlanner<f64>, inverse: bool) {
+  //Transforms consecutive lanes of the given length, in parallel
+  if len < 2 {
+    return;
+  }
+  let direction = if inverse { FftDirection::Inverse } else { FftDirection::Forward };
+  let fft = planner.plan_fft(len, direction);
+  let scratch = || vec![Complex::new(0.0, 0.0); fft.get_inplace_scratch_len()];
+  buf.par_chunks_mut(len).for_each_init(scratch, |scratch, lane| {
+    fft.process_with_scratch(lane, scratch);
+  });
+}
+
+pub(crate) fn transform(
+  data: ArrayViewD<Complex<f64>>,
+  shape: &[usize],
+  opts: &FftOptions,
+  inverse: bool,
+) -> Array<Complex<f64>, IxDyn> {
+  /*  Transforms the first two axes of data into an array of the given shape.
+      The input is zero padded (or cropped) to that shape before a forward
+      transform, and the output is cropped after an inverse one.
+  */
+  let (d1, d2) = plane_dims(data.shape());
+  let (n1, n2) = plane_dims(shape);
+  let (p1, p2) = if inverse { (d1, d2) } else { (n1, n2) };
+  let n_planes = data.len() / (d1 * d2).max(1);
+  let pixels: Vec<Complex<f64>> = data.t().iter().copied().collect();
+
+  //(1) Copy the planes into buffers of the transform size
+  let mut buf = vec![Complex::new(0.0, 0.0); p1 * p2 * n_planes];
+  for plane in 0..n_planes {
+    for y in 0..d2.min(p2) {
+      for x in 0..d1.min(p1) {
+        buf[x + p1 * (y + p2 * plane)] = pixels[x + d1 * (y + d2 * plane)];
+      }
+    }
+  }
+
+  //(2) Transform the rows (along NAXIS1), which are contiguous
+  let mut planner = FftPlanner::new();
+  fft_lanes(&mut buf, p1, &mut planner, inverse);
+
+  //(3) Transform the columns, after transposing every plane
+  let mut cols = vec![Complex::new(0.0, 0.0); buf.len()];
+  for plane in 0..n_planes {
+    let offset = plane * p1 * p2;
+    for y in 0..p2 {
+      for x in 0..p1 {
+        cols[offset + y + p2 * x] = buf[offset + x + p1 * y];
+      }
+    }
+  }
+  fft_lanes(&mut cols, p2, &mut planner, inverse);
+
+  //(4) Back to the Fortran layout, cropping and scaling on the way
+  let scale = opts.scale(p1 * p2, inverse);
+  let (o1, o2) = (n1.min(p1), n2.min(p2));
+  let mut out = vec![Complex::new(0.0, 0.0); n1 * n2 * n_planes];
+  for plane in 0..n_planes {
+    for y in 0..o2 {
+      for x in 0..o1 {
+        out[x + n1 * (y + n2 * plane)] = cols[plane * p1 * p2 + y + p2 * x] * scale;
+      }
+    }
+  }
+
+  //(R) in the requested shape
+  Array::from_shape_vec(shape.to_vec().f(), out).unwrap()
+}
@@ -0,0 +1,102 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::fmt::{Debug, Display};
+
+use num_traits::Num;
+use rustronomy_core::data_type_traits::io_utils::{Decode, Encode};
+
+use crate::bitpix::Bitpix;
+
+use super::{generic_image::Image, typed_image::TypedImage};
+
+pub trait FitsPixel:
+  Debug + Num + Sized + Decode + Encode + Display + Clone + Copy + Send + Sync + 'static
+{
+  /*  THIS TRAIT IS PART OF THE USER-FACING API
+      FitsPixel is implemented for every type that can be stored in a FITS
+      image (u8, i16, i32, i64, f32 and f64). Code that is generic over
+      FitsPixel works for all variants of TypedImage.
+
+      The trait cannot be implemented outside this crate, since the FITS
+      standard fixes the set of valid pixel types.
+  */
+
+  //BITPIX value of images with this pixel type
+  const BITPIX: Bitpix;
+
+  //(big-endian) byte conversions, as used in FITS files
+  fn from_fits_bytes(bytes: &[u8]) -> Self {
+    Decode::from_bytes(bytes)
+  }
+  fn to_fits_bytes(&self) -> Vec<u8> {
+    Encode::to_bytes(self)
+  }
+
+  //Conversions between the generic Image<T> and the TypedImage enum
+  fn into_typed(img: Image<Self>) -> TypedImage;
+  fn from_typed(img: TypedImage) -> Option<Image<Self>>;
+  fn typed_ref(img: &TypedImage) -> Option<&Image<Self>>;
+  fn typed_mut(img: &mut TypedImage) -> Option<&mut Image<Self>>;
+
+  #[doc(hidden)]
+  fn sealed(_: private::Sealed);
+}
+
+mod private {
+  pub struct Sealed;
+}
+
+macro_rules! impl_fits_pixel {
+  ($t:ty, $bpx:ident, $var:ident) => {
+    impl FitsPixel for $t {
+      const BITPIX: Bitpix = Bitpix::$bpx;
+
+      fn into_typed(img: Image<Self>) -> TypedImage {
+        TypedImage::$var(img)
+      }
+      fn from_typed(img: TypedImage) -> Option<Image<Self>> {
+        match img {
+          TypedImage::$var(img) => Some(img),
+          _ => None,
+        }
+      }
+      fn typed_ref(img: &TypedImage) -> Option<&Image<Self>> {
+        match img {
+          TypedImage::$var(img) => Some(img),
+          _ => None,
+        }
+      }
+      fn typed_mut(img: &mut TypedImage) -> Option<&mut Image<Self>> {
+        match img {
+          TypedImage::$var(img) => Some(img),
+          _ => None,
+        }
+      }
+      fn sealed(_: private::Sealed) {}
+    }
+  };
+}
+
+impl_fits_pixel!(u8, Byte, ByteImg);
+impl_fits_pixel!(i16, Short, I16Img);
+impl_fits_pixel!(i32, Int, I32Img);
+impl_fits_pixel!(i64, Long, I64Img);
+impl_fits_pixel!(f32, Spf, SpfImg);
+impl_fits_pixel!(f64, Dpf, DpfImg);
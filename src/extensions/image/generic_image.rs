@@ -17,32 +17,50 @@
     along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
 */
 
-use std::fmt::{Debug, Display};
+use std::hash::{Hash, Hasher};
+#[cfg(feature = "mmap")]
+use std::sync::OnceLock;
 
-use ndarray::{Array, IxDyn};
-use num_traits::Num;
-use rustronomy_core::data_type_traits::io_utils::{Decode, Encode};
+use ndarray::{ArcArray, Array, ArrayViewD, Axis, IxDyn, ShapeBuilder, Slice};
 
-use crate::raw::BlockSized;
+use crate::{
+  img_err::{InvalidAxesErr, RowShapeErr},
+  raw::BlockSized,
+};
+
+#[cfg(feature = "mmap")]
+use super::mapped_pixels::MappedPixels;
+use super::FitsPixel;
+
+#[derive(Debug, Clone)]
+enum Storage<T>
+where
+  T: FitsPixel,
+{
+  //Pixels in memory, or in a memory map of the file (see mapped_pixels.rs)
+  Memory(ArcArray<T, IxDyn>),
+  #[cfg(feature = "mmap")]
+  Mapped(MappedPixels, OnceLock<ArcArray<T, IxDyn>>), //decoded on first use
+}
 
 #[derive(Debug, Clone)]
 pub struct Image<T>
 where
-  T: Debug + Num + Sized + Decode + Encode + Display + Clone,
+  T: FitsPixel,
 {
-  /*  THIS STRUCT IS NOT PART OF THE USER-FACING API
-      None of the implementations or fields of this struct are public.
-      Users interface with Images through the TypedImage enum and its impleme-
-      ntations.
+  /*  THIS STRUCT IS PART OF THE USER-FACING API
+      Users usually interface with Images through the TypedImage enum, but
+      code that is generic over the pixel type (see FitsPixel) can work with
+      Image<T> directly.
   */
   shape: Vec<usize>,
-  data: Array<T, IxDyn>,
+  data: Storage<T>, //copy-on-write, so clones share the data
   block_size: usize,
 }
 
 impl<T> BlockSized for Image<T>
 where
-  T: Debug + Num + Sized + Decode + Encode + Display + Clone,
+  T: FitsPixel,
 {
   fn get_block_len(&self) -> usize {
     self.block_size
@@ -51,31 +69,208 @@ where
 
 impl<T> Image<T>
 where
-  T: Debug + Num + Sized + Decode + Encode + Display + Clone,
+  T: FitsPixel,
 {
   /*
       PUBLIC API
   */
   pub fn new(array: Array<T, IxDyn>) -> Self {
-    todo!()
+    //FITS images are stored in whole blocks of 2880 bytes
+    let size = (array.len() * std::mem::size_of::<T>()).div_ceil(crate::BLOCK_SIZE);
+    Image {
+      shape: array.shape().to_vec(),
+      data: Storage::Memory(array.into_shared()),
+      block_size: size,
+    }
+  }
+
+  pub fn from_row_iter<I, R>(shape: &[usize], rows: I) -> Result<Self, RowShapeErr>
+  where
+    I: IntoIterator<Item = R>,
+    R: AsRef<[T]>,
+  {
+    /*  Builds an image from its rows: runs of pixels along the first axis
+        (NAXIS1), in the order in which they are stored in a FITS file. The
+        rows are copied straight into the (Fortran layout) storage of the
+        image, so no intermediate copy of the whole image is made.
+    */
+    let row_len = shape.first().copied().unwrap_or(1);
+    let n_rows: usize = shape.iter().skip(1).product();
+    let mut data = Vec::with_capacity(row_len * n_rows);
+    let mut n_read = 0;
+    for row in rows {
+      let row = row.as_ref();
+      if n_read == n_rows || row.len() != row_len {
+        return Err(RowShapeErr::new(shape, n_read, Some(row.len())));
+      }
+      data.extend_from_slice(row);
+      n_read += 1;
+    }
+    if n_read != n_rows {
+      return Err(RowShapeErr::new(shape, n_read, None));
+    }
+
+    //The length was checked above, so this cannot fail
+    Ok(Self::new(Array::from_shape_vec(shape.f(), data).unwrap()))
+  }
+
+  pub fn get_data(&self) -> &ArcArray<T, IxDyn> {
+    //The pixels of mapped images are decoded the first time they are needed
+    match &self.data {
+      Storage::Memory(data) => data,
+      #[cfg(feature = "mmap")]
+      Storage::Mapped(map, data) => data.get_or_init(|| map.decode(&self.shape).into_shared()),
+    }
+  }
+  pub fn get_data_mut(&mut self) -> &mut ArcArray<T, IxDyn> {
+    //Mutating the returned array copies the data if it is shared. Mapped
+    //images are decoded into memory first
+    #[cfg(feature = "mmap")]
+    if self.is_mapped() {
+      self.data = Storage::Memory(self.get_data().clone());
+    }
+    match &mut self.data {
+      Storage::Memory(data) => data,
+      #[cfg(feature = "mmap")]
+      Storage::Mapped(..) => unreachable!("mapped images were decoded above"),
+    }
+  }
+  pub fn get_data_owned(self) -> Array<T, IxDyn> {
+    //Only copies the data if it is shared with another image
+    match self.data {
+      Storage::Memory(data) => data.into_owned(),
+      #[cfg(feature = "mmap")]
+      Storage::Mapped(map, data) => match data.into_inner() {
+        Some(data) => data.into_owned(),
+        None => map.decode(&self.shape),
+      },
+    }
+  }
+  pub fn view(&self) -> ArrayViewD<'_, T> {
+    //Mapped pixels are viewed in place if their byte order and alignment allow
+    //it (see mapped_pixels.rs), without decoding them
+    #[cfg(feature = "mmap")]
+    if let Storage::Mapped(map, _) = &self.data {
+      if let Some(view) = map.view(&self.shape) {
+        return view;
+      }
+    }
+    self.get_data().view()
+  }
+  pub fn is_mapped(&self) -> bool {
+    match self.data {
+      Storage::Memory(_) => false,
+      #[cfg(feature = "mmap")]
+      Storage::Mapped(..) => true,
+    }
+  }
+  pub fn get_shape(&self) -> &Vec<usize> {
+    &self.shape
+  }
+
+  pub fn deep_clone(&self) -> Self {
+    //Unlike clone(), which shares the data, this copies it right away
+    Image {
+      shape: self.shape.clone(),
+      data: Storage::Memory(self.view().to_owned().into_shared()),
+      block_size: self.block_size,
+    }
+  }
+
+  pub fn shares_data_with(&self, other: &Self) -> bool {
+    self.view().as_ptr() == other.view().as_ptr()
+  }
+
+  pub fn flip(&mut self, axis: usize) -> Result<(), InvalidAxesErr> {
+    //Reverses the order of the pixels along the (0-based) axis
+    if axis >= self.shape.len() {
+      return Err(InvalidAxesErr::new(&[axis], self.shape.len()));
+    }
+    let mut view = self.view();
+    view.invert_axis(Axis(axis));
+    self.data = Storage::Memory(Self::to_fortran(view));
+    Ok(())
+  }
+
+  pub fn transpose(&mut self, axes: &[usize]) -> Result<(), InvalidAxesErr> {
+    //New axis k is old axis axes[k], like ndarray's permuted_axes
+    let mut sorted = axes.to_vec();
+    sorted.sort_unstable();
+    if sorted != (0..self.shape.len()).collect::<Vec<_>>() {
+      return Err(InvalidAxesErr::new(axes, self.shape.len()));
+    }
+    let data = Self::to_fortran(self.view().permuted_axes(axes));
+    self.shape = data.shape().to_vec();
+    self.data = Storage::Memory(data);
+    Ok(())
   }
 
   /*
       INTERNAL CODE
   */
+  fn to_fortran(view: ndarray::ArrayViewD<T>) -> ArcArray<T, IxDyn> {
+    //Images are kept in the Fortran layout, which is how they are written
+    let shape = view.shape().to_vec();
+    let flat = view.t().iter().copied().collect();
+    Array::from_shape_vec(shape.f(), flat).unwrap().into_shared()
+  }
+
+  pub(crate) fn subsample(&mut self, steps: &[isize]) {
+    //Keeps every step-th pixel along each axis, negative steps start at the end
+    let view = self.view();
+    let data =
+      Self::to_fortran(view.slice_each_axis(|axis| Slice::new(0, None, steps[axis.axis.index()])));
+    self.shape = data.shape().to_vec();
+    self.block_size = (data.len() * std::mem::size_of::<T>()).div_ceil(crate::BLOCK_SIZE);
+    self.data = Storage::Memory(data);
+  }
+
+  pub(crate) fn cutout(&self, start: &[usize], shape: &[usize]) -> Self {
+    //Copy of the box with the given start and shape (which has to fit)
+    let view = self.view();
+    let data = Self::to_fortran(view.slice_each_axis(|axis| {
+      let (first, len) = (start[axis.axis.index()], shape[axis.axis.index()]);
+      Slice::from(first..first + len)
+    }));
+    let block_size = (data.len() * std::mem::size_of::<T>()).div_ceil(crate::BLOCK_SIZE);
+    Image { shape: shape.to_vec(), data: Storage::Memory(data), block_size }
+  }
+
   pub(crate) fn new_sized(shape: Vec<usize>, array: Array<T, IxDyn>, size: usize) -> Self {
-    Image { shape: shape, data: array, block_size: size }
+    Image { shape, data: Storage::Memory(array.into_shared()), block_size: size }
   }
 
-  //Getters
-  pub(crate) fn get_data(&self) -> &Array<T, IxDyn> {
-    &self.data
+  #[cfg(feature = "mmap")]
+  pub(crate) fn new_mapped(shape: Vec<usize>, map: MappedPixels) -> Self {
+    let size = map.as_bytes().len().div_ceil(crate::BLOCK_SIZE);
+    Image { shape, data: Storage::Mapped(map, OnceLock::new()), block_size: size }
   }
-  pub(crate) fn get_data_owned(self) -> Array<T, IxDyn> {
-    self.data
+
+  pub(crate) fn get_memory_usage(&self) -> usize {
+    //Data shared with other images is counted for every image. Mapped pixels
+    //only take up memory once they were decoded
+    let len = match &self.data {
+      Storage::Memory(data) => data.len(),
+      #[cfg(feature = "mmap")]
+      Storage::Mapped(_, data) => data.get().map_or(0, |data| data.len()),
+    };
+    len * std::mem::size_of::<T>()
   }
-  pub(crate) fn get_shape(&self) -> &Vec<usize> {
-    &self.shape
+
+  pub(crate) fn hash_data(&self, hasher: &mut impl Hasher) {
+    //Hashes the pixels as they would be written to a FITS file
+    self.shape.hash(hasher);
+    self.view().iter().for_each(|px| hasher.write(&px.to_fits_bytes()));
+  }
+
+  pub(crate) fn same_data(&self, other: &Self) -> bool {
+    //Bitwise comparison, so NaN's with the same bit pattern are equal
+    self.shape == other.shape
+      && self
+        .view()
+        .iter()
+        .zip(other.view().iter())
+        .all(|(a, b)| a.to_fits_bytes() == b.to_fits_bytes())
   }
 
   pub(crate) fn pretty_print_shape(&self) -> String {
@@ -84,6 +279,6 @@ where
       rsp += format!("{ax},").as_str();
     }
     rsp.pop(); //remove last comma
-    String::from(rsp + ")")
+    rsp + ")"
   }
 }
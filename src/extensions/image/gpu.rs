@@ -0,0 +1,103 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Uploading images to the GPU as wgpu textures (enabled with the gpu
+    feature), for display or for processing in compute shaders. Images with
+    one to three axes become 1-D, 2-D or 3-D textures, with NAXIS1 as the
+    width, NAXIS2 as the height and NAXIS3 as the depth.
+
+    Textures have no 64-bit formats, so the pixels are converted on the way:
+      - u8, i16 and i32 images keep their values (R8Uint, R16Sint, R32Sint)
+      - f32 images are uploaded as they are (R32Float)
+      - i64 and f64 images are converted to f32 (R32Float), losing precision
+    BZERO and BSCALE are not applied, as for the image itself.
+*/
+
+use wgpu::{
+  Device, Extent3d, Queue, TexelCopyBufferLayout, Texture, TextureDescriptor, TextureDimension,
+  TextureFormat, TextureUsages,
+};
+
+use crate::{bitpix::Bitpix, img_err::TextureShapeErr};
+
+use super::TypedImage;
+
+pub(crate) fn texture_format(bitpix: Bitpix) -> TextureFormat {
+  match bitpix {
+    Bitpix::Byte => TextureFormat::R8Uint,
+    Bitpix::Short => TextureFormat::R16Sint,
+    Bitpix::Int => TextureFormat::R32Sint,
+    Bitpix::Long | Bitpix::Spf | Bitpix::Dpf => TextureFormat::R32Float,
+  }
+}
+
+fn texels(img: &TypedImage) -> Vec<u8> {
+  //Pixels in file order (NAXIS1 runs fastest), as little-endian texels
+  use TypedImage::*;
+  match img {
+    ByteImg(img) => img.get_data().t().iter().copied().collect(),
+    I16Img(img) => img.get_data().t().iter().flat_map(|px| px.to_le_bytes()).collect(),
+    I32Img(img) => img.get_data().t().iter().flat_map(|px| px.to_le_bytes()).collect(),
+    I64Img(img) => img.get_data().t().iter().flat_map(|&px| (px as f32).to_le_bytes()).collect(),
+    SpfImg(img) => img.get_data().t().iter().flat_map(|px| px.to_le_bytes()).collect(),
+    DpfImg(img) => img.get_data().t().iter().flat_map(|&px| (px as f32).to_le_bytes()).collect(),
+  }
+}
+
+pub(crate) fn upload(
+  img: &TypedImage,
+  device: &Device,
+  queue: &Queue,
+) -> Result<Texture, TextureShapeErr> {
+  //(1) The texture has to fit within the limits of the device
+  let shape = crate::impl_typed_image_dispatch!(img, img => img.get_shape().clone());
+  let limits = device.limits();
+  let (dimension, max_len) = match shape.len() {
+    1 => (TextureDimension::D1, limits.max_texture_dimension_1d),
+    2 => (TextureDimension::D2, limits.max_texture_dimension_2d),
+    3 => (TextureDimension::D3, limits.max_texture_dimension_3d),
+    _ => return Err(TextureShapeErr::new(&shape, None)),
+  };
+  if shape.iter().any(|&len| len == 0 || len > max_len as usize) {
+    return Err(TextureShapeErr::new(&shape, Some(max_len)));
+  }
+  let len = |axis: usize| shape.get(axis).map_or(1, |&len| len as u32);
+  let size = Extent3d { width: len(0), height: len(1), depth_or_array_layers: len(2) };
+
+  //(2) Create the texture and copy the converted pixels into it
+  let format = texture_format(img.bpx());
+  let texture = device.create_texture(&TextureDescriptor {
+    label: None,
+    size,
+    mip_level_count: 1,
+    sample_count: 1,
+    dimension,
+    format,
+    usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::COPY_SRC,
+    view_formats: &[],
+  });
+  let layout = TexelCopyBufferLayout {
+    offset: 0,
+    bytes_per_row: Some(size.width * format.block_copy_size(None).unwrap()),
+    rows_per_image: Some(size.height),
+  };
+  queue.write_texture(texture.as_image_copy(), &texels(img), layout, size);
+  Ok(texture)
+}
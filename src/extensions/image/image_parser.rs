@@ -24,28 +24,26 @@ const BLOCK_SIZE: usize = crate::BLOCK_SIZE; // = 2880B
 const MAX_BLOCKS_IN_BUF: usize = 128; // = 369kB
 const MIN_BLOCKS_IN_BUF: usize = 1; // = 3kB
 
-use std::{
-  error::Error,
-  fmt::{Debug, Display},
-  mem::size_of,
-};
-
-//Rustronomy Imports
-use rustronomy_core::data_type_traits::io_utils::{Decode, Encode};
+use std::{error::Error, mem::size_of};
 
 //External Imports
-use ndarray::{Array, ShapeBuilder};
-use num_traits::Num;
+use ndarray::{Array, ShapeBuilder, Slice};
 use rayon::prelude::*;
 
 use crate::{
   bitpix::Bitpix,
   img_err::InvalidMemLayout as IMLErr,
-  raw::raw_io::{RawFitsReader, RawFitsWriter},
+  raw::{
+    block_io::{BlockRead, BlockWrite},
+    raw_io,
+  },
   Extension,
 };
 
-use super::{generic_image::Image, typed_image::TypedImage};
+#[cfg(feature = "half")]
+use crate::{img_err::WrongImgTypeErr as WITErr, raw::BlockSized};
+
+use super::{generic_image::Image, typed_image::TypedImage, FitsPixel};
 
 /*
     THIS IS NOT PART OF THE USER-FACING API
@@ -57,15 +55,15 @@ pub(crate) struct ImgParser {}
 impl ImgParser {
   //Public decoder for parsing images
   pub(crate) fn decode_img(
-    reader: &mut RawFitsReader,
-    shape: &Vec<usize>,
+    reader: &mut dyn BlockRead,
+    shape: &[usize],
     bitpix: Bitpix,
-  ) -> Result<Extension, Box<dyn Error>> {
+  ) -> Result<Extension, Box<dyn Error + Send + Sync>> {
     use Bitpix::*;
     use TypedImage::*;
 
     Ok(Extension::Image(match bitpix {
-      Byte => ByteImg(Self::decode_helper::<u8>(reader, shape)?),
+      Byte => ByteImg(Self::decode_bytes(reader, shape)?),
       Short => I16Img(Self::decode_helper::<i16>(reader, shape)?),
       Int => I32Img(Self::decode_helper::<i32>(reader, shape)?),
       Long => I64Img(Self::decode_helper::<i64>(reader, shape)?),
@@ -74,12 +72,54 @@ impl ImgParser {
     }))
   }
 
+  //Converts half-precision images (BITPIX = 16 plus a marker keyword), which
+  //are decoded like any other 16 bit image, to the f32 images they represent
+  #[cfg(feature = "half")]
+  pub(crate) fn half_from_bits(bits: TypedImage) -> Result<TypedImage, WITErr> {
+    //(1) The raw bits are stored just like a regular BITPIX = 16 image
+    let bits = match bits {
+      TypedImage::I16Img(bits) => bits,
+      other => return Err(WITErr::new(&other, Bitpix::short())),
+    };
+    let (shape, size) = (bits.get_shape().clone(), bits.get_block_len());
+
+    //(2) Reinterpret the bits as f16 and widen them to f32
+    let data = bits.get_data_owned().mapv(|val| half::f16::from_bits(val as u16).to_f32());
+
+    //(R) half-precision images are exposed as f32 images, which still take up
+    //as many blocks as the 16 bit data on disk
+    Ok(TypedImage::SpfImg(Image::new_sized(shape, data, size)))
+  }
+
+  fn decode_bytes(
+    reader: &mut dyn BlockRead,
+    shape: &[usize],
+  ) -> Result<Image<u8>, Box<dyn Error + Send + Sync>> {
+    /*  Bytes do not have to be converted (their endianness does not matter),
+        so the blocks are read straight into the vector underpinning the
+        ndarray, in chunks of the usual buffer size (see calc_buf_size). This
+        way the data is never held in memory twice.
+    */
+    let n_entries = shape.iter().product::<usize>();
+    let total_blocks = n_entries.div_ceil(BLOCK_SIZE);
+    let (buf_size, _) = Self::calc_buf_size(total_blocks);
+    let mut flat = vec![0u8; total_blocks * BLOCK_SIZE];
+    for chunk in flat.chunks_mut(buf_size) {
+      reader.read_blocks(chunk)?;
+    }
+
+    //Cut off the padding of the last block, the layout is column-major again
+    flat.truncate(n_entries);
+    let img_data = Array::from_shape_vec(shape.to_vec().f(), flat)?;
+    Ok(Image::new_sized(shape.to_vec(), img_data, total_blocks))
+  }
+
   fn decode_helper<T>(
-    reader: &mut RawFitsReader,
-    shape: &Vec<usize>,
-  ) -> Result<Image<T>, Box<dyn Error>>
+    reader: &mut dyn BlockRead,
+    shape: &[usize],
+  ) -> Result<Image<T>, Box<dyn Error + Send + Sync>>
   where
-    T: Debug + Num + Sized + Decode + Encode + Display + Clone + Send,
+    T: FitsPixel,
   {
     /*  (1)
         To create a ndarray we need to provide an underlying data structure.
@@ -88,7 +128,7 @@ impl ImgParser {
         the number of entries in this vector by folding the array shape.
     */
     let entry_size = size_of::<T>();
-    let n_entries = (&shape).iter().fold(1, |prod, &x| prod * x);
+    let n_entries = shape.iter().product::<usize>();
     let byte_size = n_entries * entry_size;
     let total_blocks = (byte_size as f64 / BLOCK_SIZE as f64).ceil() as usize;
 
@@ -146,37 +186,313 @@ impl ImgParser {
         row-major like C and Rust. Hence we have to call the .f() function
         on the shape of the array to tell ndarray that we have a Fortran array.
     */
-    let img_data = Array::from_shape_vec(shape.clone().f(), flat)?;
+    let img_data = Array::from_shape_vec(shape.to_vec().f(), flat)?;
+
+    // (R) return an Image struct
+    Ok(Image::<T>::new_sized(shape.to_vec(), img_data, total_blocks))
+  }
+
+  //Decoder for images that were cut off by the end of the file. Also returns
+  //the number of entries that were actually present
+  pub(crate) fn decode_partial_img(
+    reader: &mut dyn BlockRead,
+    shape: &[usize],
+    bitpix: Bitpix,
+  ) -> Result<(Extension, usize), Box<dyn Error + Send + Sync>> {
+    use Bitpix::*;
+    use TypedImage::*;
+
+    let (img, valid_len) = match bitpix {
+      Byte => Self::partial_helper::<u8>(reader, shape).map(|(img, n)| (ByteImg(img), n))?,
+      Short => Self::partial_helper::<i16>(reader, shape).map(|(img, n)| (I16Img(img), n))?,
+      Int => Self::partial_helper::<i32>(reader, shape).map(|(img, n)| (I32Img(img), n))?,
+      Long => Self::partial_helper::<i64>(reader, shape).map(|(img, n)| (I64Img(img), n))?,
+      Spf => Self::partial_helper::<f32>(reader, shape).map(|(img, n)| (SpfImg(img), n))?,
+      Dpf => Self::partial_helper::<f64>(reader, shape).map(|(img, n)| (DpfImg(img), n))?,
+    };
+    Ok((Extension::Image(img), valid_len))
+  }
+
+  fn partial_helper<T>(
+    reader: &mut dyn BlockRead,
+    shape: &[usize],
+  ) -> Result<(Image<T>, usize), Box<dyn Error + Send + Sync>>
+  where
+    T: FitsPixel,
+  {
+    //(1) Read whatever is left of the file (the last block is zero-padded)
+    let entry_size = size_of::<T>();
+    let n_entries = shape.iter().product::<usize>();
+    let total_blocks = (n_entries * entry_size).div_ceil(BLOCK_SIZE);
+    let valid_len = (reader.get_bytes_left() / entry_size as u64).min(n_entries as u64) as usize;
+    let mut buf = vec![0u8; (reader.get_block_len() - reader.get_block_index()) * BLOCK_SIZE];
+    reader.read_blocks(&mut buf)?;
+
+    //(2) Decode the entries we have and set the missing ones to zero
+    let mut flat: Vec<T> =
+      buf.par_chunks(entry_size).take(valid_len).map(|val| T::from_bytes(val)).collect();
+    flat.resize(n_entries, T::zero());
+
+    //(3) Images are in the Fortran memory-layout (see decode_helper)
+    let img_data = Array::from_shape_vec(shape.to_vec().f(), flat)?;
+
+    //(R) the image and the number of valid entries
+    Ok((Image::<T>::new_sized(shape.to_vec(), img_data, total_blocks), valid_len))
+  }
+
+  //Decoder for reading cutouts of images. The bytes of the data unit are
+  //obtained from fetch(byte_offset, n_bytes), so they may come from a cache
+  pub(crate) fn decode_cutout(
+    fetch: &mut dyn FnMut(u64, usize) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>>,
+    shape: &[usize],
+    bitpix: Bitpix,
+    start: &[usize],
+    cut_shape: &[usize],
+  ) -> Result<Extension, Box<dyn Error + Send + Sync>> {
+    use Bitpix::*;
+    use TypedImage::*;
+
+    Ok(Extension::Image(match bitpix {
+      Byte => ByteImg(Self::cutout_helper::<u8>(fetch, shape, start, cut_shape)?),
+      Short => I16Img(Self::cutout_helper::<i16>(fetch, shape, start, cut_shape)?),
+      Int => I32Img(Self::cutout_helper::<i32>(fetch, shape, start, cut_shape)?),
+      Long => I64Img(Self::cutout_helper::<i64>(fetch, shape, start, cut_shape)?),
+      Spf => SpfImg(Self::cutout_helper::<f32>(fetch, shape, start, cut_shape)?),
+      Dpf => DpfImg(Self::cutout_helper::<f64>(fetch, shape, start, cut_shape)?),
+    }))
+  }
+
+  fn cutout_helper<T>(
+    fetch: &mut dyn FnMut(u64, usize) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>>,
+    shape: &[usize],
+    start: &[usize],
+    cut_shape: &[usize],
+  ) -> Result<Image<T>, Box<dyn Error + Send + Sync>>
+  where
+    T: FitsPixel,
+  {
+    //(1) The first axis is contiguous on disk (see preview_helper)
+    let entry_size = size_of::<T>();
+    let n_entries = cut_shape.iter().product::<usize>();
+    let mut flat: Vec<T> = Vec::with_capacity(n_entries);
+    let outer_shape = &cut_shape[1..];
+    let mut outer_idx = vec![0usize; outer_shape.len()];
+
+    //Empty cutouts don't need any data
+    if n_entries == 0 {
+      return Ok(Image::<T>::new_sized(cut_shape.to_vec(), Array::zeros(cut_shape.f()), 0));
+    }
+
+    //(2) Fetch the part of every row that lies inside of the cutout
+    'rows: loop {
+      let row_index = outer_idx
+        .iter()
+        .zip(&start[1..])
+        .zip(&shape[1..])
+        .rev()
+        .fold(0u64, |idx, ((&i, &offset), &ax)| idx * ax as u64 + (offset + i) as u64);
+      let offset = (row_index * shape[0] as u64 + start[0] as u64) * entry_size as u64;
+      let raw = fetch(offset, cut_shape[0] * entry_size)?;
+      flat.extend(raw.chunks_exact(entry_size).map(|val| T::from_bytes(val)));
+
+      //Move on to the next row (first outer axis runs fastest)
+      for (i, &ax) in outer_idx.iter_mut().zip(outer_shape) {
+        *i += 1;
+        if *i < ax {
+          continue 'rows;
+        }
+        *i = 0;
+      }
+      break;
+    }
+
+    //(3) Cutouts are in the Fortran memory-layout as well
+    let blocks = (n_entries * entry_size).div_ceil(BLOCK_SIZE);
+    let img_data = Array::from_shape_vec(cut_shape.to_vec().f(), flat)?;
+
+    // (R) return an Image struct
+    Ok(Image::<T>::new_sized(cut_shape.to_vec(), img_data, blocks))
+  }
+
+  //Decoder for reading downsampled previews of images
+  pub(crate) fn decode_preview(
+    reader: &mut dyn BlockRead,
+    shape: &[usize],
+    bitpix: Bitpix,
+    max_dim: usize,
+  ) -> Result<Extension, Box<dyn Error + Send + Sync>> {
+    use Bitpix::*;
+    use TypedImage::*;
+
+    Ok(Extension::Image(match bitpix {
+      Byte => ByteImg(Self::preview_helper::<u8>(reader, shape, max_dim)?),
+      Short => I16Img(Self::preview_helper::<i16>(reader, shape, max_dim)?),
+      Int => I32Img(Self::preview_helper::<i32>(reader, shape, max_dim)?),
+      Long => I64Img(Self::preview_helper::<i64>(reader, shape, max_dim)?),
+      Spf => SpfImg(Self::preview_helper::<f32>(reader, shape, max_dim)?),
+      Dpf => DpfImg(Self::preview_helper::<f64>(reader, shape, max_dim)?),
+    }))
+  }
+
+  //Previews of images that are already in memory (see preview_helper)
+  pub(crate) fn preview_of(img: &TypedImage, max_dim: usize) -> TypedImage {
+    crate::impl_typed_image_dispatch!(img, img => {
+      let stride = Self::preview_stride(img.get_shape(), max_dim) as isize;
+      let view = img.get_data().slice_each_axis(|_| Slice::new(0, None, stride));
+      //Same (Fortran) memory layout as previews read from disk
+      let flat = view.t().iter().cloned().collect();
+      FitsPixel::into_typed(Image::new(Array::from_shape_vec(view.raw_dim().f(), flat).unwrap()))
+    })
+  }
+
+  fn preview_stride(shape: &[usize], max_dim: usize) -> usize {
+    //A single stride for all axes, so the preview keeps the aspect ratio
+    let max_dim = max_dim.max(1);
+    shape.iter().fold(1, |max, &ax| max.max(ax.div_ceil(max_dim)))
+  }
+
+  fn preview_helper<T>(
+    reader: &mut dyn BlockRead,
+    shape: &[usize],
+    max_dim: usize,
+  ) -> Result<Image<T>, Box<dyn Error + Send + Sync>>
+  where
+    T: FitsPixel,
+  {
+    /*  (1)
+        We pick a single stride for all axes (this preserves the aspect ratio
+        of the image) such that no axis of the preview is longer than max_dim.
+    */
+    let entry_size = size_of::<T>();
+    //Only the preview has to fit in memory, so offsets in the data unit are u64
+    let n_bytes = shape.iter().fold(entry_size as u64, |n, &ax| n * ax as u64);
+    let total_blocks = raw_io::block_count(n_bytes)?;
+
+    let stride = Self::preview_stride(shape, max_dim);
+    let preview_shape: Vec<usize> = shape.iter().map(|&ax| ax.div_ceil(stride)).collect();
+
+    //Empty images have nothing to preview
+    if n_bytes == 0 {
+      reader.skip_blocks(total_blocks)?;
+      return Ok(Image::<T>::new_sized(preview_shape.clone(), Array::zeros(preview_shape.f()), 0));
+    }
+
+    /*  (2)
+        FITS images are stored in column-major order, so the first axis is
+        contiguous on disk. We call such a contiguous run of values a row. We
+        only need every stride-th row along each of the remaining axes, so the
+        FITS blocks that only contain unwanted rows can be skipped entirely.
+    */
+    let row_len = shape[0];
+    let row_bytes = row_len * entry_size;
+    let outer_shape = &shape[1..];
+    let mut outer_idx = vec![0usize; outer_shape.len()];
+
+    let mut flat: Vec<T> = Vec::with_capacity(preview_shape.iter().product());
+    let mut cursor = 0usize; //index of the next data block in the file
+    let mut cached: Option<(usize, Vec<u8>)> = None; //last block we read
+
+    'rows: loop {
+      //(2a) Calculate the position of this row in the data unit
+      let row_index = outer_idx
+        .iter()
+        .zip(outer_shape)
+        .rev()
+        .fold(0u64, |idx, (&i, &ax)| idx * ax as u64 + i as u64);
+      let start = row_index * row_bytes as u64;
+      let end = start + row_bytes as u64 - 1;
+      let (first_block, last_block) =
+        ((start / BLOCK_SIZE as u64) as usize, (end / BLOCK_SIZE as u64) as usize);
+
+      //(2b) Re-use the previous block if this row starts inside of it
+      let mut raw = Vec::with_capacity((last_block - first_block + 1) * BLOCK_SIZE);
+      let mut next_block = first_block;
+      if let Some((idx, block)) = &cached {
+        if *idx == first_block {
+          raw.extend_from_slice(block);
+          next_block += 1;
+        }
+      }
+
+      //(2c) Skip ahead to the first block we do not have yet, and read the rest
+      if next_block <= last_block {
+        reader.skip_blocks(next_block - cursor)?;
+        let mut buf = vec![0u8; (last_block - next_block + 1) * BLOCK_SIZE];
+        reader.read_blocks(&mut buf)?;
+        cursor = last_block + 1;
+        cached = Some((last_block, buf[buf.len() - BLOCK_SIZE..].to_vec()));
+        raw.append(&mut buf);
+      }
+
+      //(2d) Decode every stride-th value of the row
+      let offset = (start - raw_io::block_offset(first_block)) as usize;
+      let row = &raw[offset..offset + row_bytes];
+      flat.extend(row.chunks_exact(entry_size).step_by(stride).map(|val| T::from_bytes(val)));
+
+      //(2e) Move on to the next wanted row (first outer axis runs fastest)
+      for (i, &ax) in outer_idx.iter_mut().zip(outer_shape) {
+        *i += stride;
+        if *i < ax {
+          continue 'rows;
+        }
+        *i = 0;
+      }
+      break;
+    }
+
+    //(3) Skip the remainder of the data unit so the next HDU can be read
+    reader.skip_blocks(total_blocks - cursor)?;
+
+    /*  (4)
+        The preview was collected in the Fortran memory-layout as well, so we
+        have to tell ndarray about that (see decode_helper)
+    */
+    let preview_blocks = (flat.len() * entry_size).div_ceil(BLOCK_SIZE);
+    let img_data = Array::from_shape_vec(preview_shape.clone().f(), flat)?;
 
     // (R) return an Image struct
-    Ok(Image::<T>::new_sized(shape.clone(), img_data, total_blocks))
+    Ok(Image::<T>::new_sized(preview_shape, img_data, preview_blocks))
   }
 
   //Encoder for parsing Images. Consumes the image it encodes
   pub(crate) fn encode_img(
     typed_img: TypedImage,
-    writer: &mut RawFitsWriter,
-  ) -> Result<(), Box<dyn Error>> {
+    writer: &mut dyn BlockWrite,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
     //This function only matches the typed image and calls the appropriate
     //helper function
-    use TypedImage::*;
-
-    match typed_img {
-      ByteImg(img) => Self::encode_helper(img, writer)?,
-      I16Img(img) => Self::encode_helper(img, writer)?,
-      I32Img(img) => Self::encode_helper(img, writer)?,
-      I64Img(img) => Self::encode_helper(img, writer)?,
-      SpfImg(img) => Self::encode_helper(img, writer)?,
-      DpfImg(img) => Self::encode_helper(img, writer)?,
-    }
+    crate::impl_typed_image_dispatch!(typed_img, img => Self::encode_helper(img, writer)?);
 
     //(R) this went ok
     Ok(())
   }
 
-  fn encode_helper<T>(img: Image<T>, writer: &mut RawFitsWriter) -> Result<(), Box<dyn Error>>
+  //Encoder for half-precision images. Consumes the image it encodes
+  #[cfg(feature = "half")]
+  pub(crate) fn encode_half_img(
+    typed_img: TypedImage,
+    writer: &mut dyn BlockWrite,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    //(1) Half-precision images are always exposed as f32 images
+    let img = match typed_img {
+      TypedImage::SpfImg(img) => img,
+      other => return Err(Box::new(WITErr::new(&other, Bitpix::spf()))),
+    };
+
+    //(2) Quantize the f32 values to f16 and store their bits as i16
+    let bits = img.get_data_owned().mapv(|val| half::f16::from_f32(val).to_bits() as i16);
+
+    //(R) write the bits like a regular BITPIX = 16 image (Image::new computes
+    //the block length of the 16 bit data, not that of the f32 image)
+    Self::encode_helper(Image::new(bits), writer)
+  }
+
+  fn encode_helper<T>(
+    img: Image<T>,
+    writer: &mut dyn BlockWrite,
+  ) -> Result<(), Box<dyn Error + Send + Sync>>
   where
-    T: Debug + Num + Sized + Decode + Encode + Display + Clone,
+    T: FitsPixel,
   {
     /*  (1)
         ndarray preserves the internal memory-layout that was used to create
@@ -204,12 +520,9 @@ impl ImgParser {
             Data is either discontinuous, OR in Fortran layout. In the
             second case we want to perform a no-op copy!
         */
-        match img.get_data().as_slice_memory_order() {
-          None => {
-            //Data is NOT continuous, return error!
-            return Err(Box::new(IMLErr::new()));
-          }
-          _ => {} //Data IS continuous, continue!
+        if img.get_data().as_slice_memory_order().is_none() {
+          //Data is NOT continuous, return error!
+          return Err(Box::new(IMLErr::new()));
         }
 
         //No-op return the underlying data, since it's already in the
@@ -245,9 +558,8 @@ impl ImgParser {
         writer.write_blocks(&buffer)?;
         buffer.clear();
       }
-      match raw.pop() {
-        Some(val) => val.fill_buf(&mut buffer),
-        None => {} //loop will break after this
+      if let Some(val) = raw.pop() {
+        val.fill_buf(&mut buffer);
       }
     }
 
@@ -300,7 +612,7 @@ impl ImgParser {
       }
 
       //If a larger buffer works, use it!
-      if total_blocks % i == 0 {
+      if total_blocks.is_multiple_of(i) {
         n_buf_blocks = i;
       }
     }
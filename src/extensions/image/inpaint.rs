@@ -0,0 +1,196 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use ndarray::{Array, ArrayViewD, ShapeBuilder};
+use num_traits::{NumCast, ToPrimitive};
+use rayon::prelude::*;
+
+use crate::{bitpix::Bitpix, img_err::MaskShapeErr};
+
+use super::{generic_image::Image, FitsPixel};
+
+/*
+    Inpainting replaces bad pixels (true in the mask) with values derived from
+    the good pixels around them. Like convolution, this works on the first two
+    axes of the image: every plane of a cube is filled separately.
+
+    NearestGood copies the closest good pixel (ties go to the first one found
+    in the file). Bilinear interpolates linearly between
+    the closest good pixels along the row and along the column of a bad pixel
+    and averages the two. Bad pixels with good pixels on only one side get the
+    value of that pixel, and pixels without good pixels in their row and column
+    fall back to NearestGood. Planes without any good pixels are left as they
+    are. Interpolated values are rounded for integer images.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InpaintMethod {
+  //  THIS ENUM IS PART OF THE USER-FACING API
+  NearestGood,
+  Bilinear,
+}
+
+pub(crate) fn inpaint<T: FitsPixel + ToPrimitive + NumCast>(
+  img: &Image<T>,
+  mask: ArrayViewD<bool>,
+  method: InpaintMethod,
+) -> Result<Image<T>, MaskShapeErr> {
+  //(1) Flatten the image and mask in the Fortran layout
+  let shape = img.get_shape().clone();
+  if mask.shape() != shape.as_slice() {
+    return Err(MaskShapeErr::new(&shape, mask.shape()));
+  }
+  let (nx, ny) = (shape.first().copied().unwrap_or(1), shape.get(1).copied().unwrap_or(1));
+  let mut pixels: Vec<T> = img.get_data().t().iter().copied().collect();
+  let bad: Vec<bool> = mask.t().iter().copied().collect();
+
+  //(2) Fill the planes one by one. The passes within a plane are parallel
+  let plane_len = (nx * ny).max(1);
+  for (plane, bad) in pixels.chunks_mut(plane_len).zip(bad.chunks(plane_len)) {
+    if !bad.contains(&false) {
+      continue;
+    }
+    match method {
+      InpaintMethod::NearestGood => nearest_good(plane, bad, nx, ny),
+      InpaintMethod::Bilinear => bilinear(plane, bad, nx, ny),
+    }
+  }
+
+  //(R) back into the shape of the image
+  Ok(Image::new(Array::from_shape_vec(shape.f(), pixels).unwrap()))
+}
+
+fn nearest_good<T: Copy>(plane: &mut [T], bad: &[bool], nx: usize, ny: usize) {
+  /*  Dijkstra search outwards from all good pixels at once. Steps cost 2 along
+      the axes and 3 diagonally (the 2-3 chamfer distance, which is close to
+      the euclidean distance). source[i] is the good pixel that pixel i copies.
+  */
+  let mut source: Vec<Option<usize>> =
+    bad.iter().enumerate().map(|(i, &bad)| (!bad).then_some(i)).collect();
+  let mut dist: Vec<u32> = bad.iter().map(|&bad| if bad { u32::MAX } else { 0 }).collect();
+  let mut queue: BinaryHeap<Reverse<(u32, usize)>> =
+    (0..plane.len()).filter(|&i| !bad[i]).map(|i| Reverse((0, i))).collect();
+  while let Some(Reverse((d, i))) = queue.pop() {
+    if d > dist[i] {
+      continue;
+    }
+    let (x, y) = ((i % nx) as isize, (i / nx) as isize);
+    for (dx, dy) in [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)] {
+      let (nbx, nby) = (x + dx, y + dy);
+      if nbx < 0 || nby < 0 || nbx >= nx as isize || nby >= ny as isize {
+        continue;
+      }
+      let neighbour = nbx as usize + nx * nby as usize;
+      let step = if dx == 0 || dy == 0 { 2 } else { 3 };
+      if d + step < dist[neighbour] {
+        dist[neighbour] = d + step;
+        source[neighbour] = source[i];
+        queue.push(Reverse((d + step, neighbour)));
+      }
+    }
+  }
+
+  for (i, source) in source.into_iter().enumerate() {
+    if let Some(source) = source.filter(|_| bad[i]) {
+      plane[i] = plane[source];
+    }
+  }
+}
+
+fn bilinear<T: FitsPixel + ToPrimitive + NumCast>(
+  plane: &mut [T],
+  bad: &[bool],
+  nx: usize,
+  ny: usize,
+) {
+  let value = |i: usize| plane[i].to_f64().unwrap_or(f64::NAN);
+
+  //(1) Interpolate along the rows (contiguous) and the columns (strided)
+  let rows: Vec<Option<f64>> = (0..ny)
+    .into_par_iter()
+    .flat_map_iter(|y| interpolate_line(&value, bad, y * nx, 1, nx))
+    .collect();
+  let columns: Vec<Vec<Option<f64>>> =
+    (0..nx).into_par_iter().map(|x| interpolate_line(&value, bad, x, nx, ny)).collect();
+
+  //(2) Average the two, and fall back to the nearest good pixel
+  let is_float = matches!(T::BITPIX, Bitpix::Spf | Bitpix::Dpf);
+  let mut unfilled = Vec::new();
+  for i in (0..plane.len()).filter(|&i| bad[i]) {
+    let filled = match (rows[i], columns[i % nx][i / nx]) {
+      (Some(row), Some(col)) => 0.5 * (row + col),
+      (Some(row), None) => row,
+      (None, Some(col)) => col,
+      (None, None) => {
+        unfilled.push(i);
+        continue;
+      }
+    };
+    let filled = if is_float { filled } else { filled.round() };
+    if let Some(filled) = T::from(filled) {
+      plane[i] = filled;
+    }
+  }
+  if !unfilled.is_empty() {
+    //Only these pixels are still bad, the interpolated ones count as good
+    let mut still_bad = vec![false; plane.len()];
+    unfilled.into_iter().for_each(|i| still_bad[i] = true);
+    nearest_good(plane, &still_bad, nx, ny);
+  }
+}
+
+fn interpolate_line(
+  value: &(impl Fn(usize) -> f64 + Sync),
+  bad: &[bool],
+  start: usize,
+  step: usize,
+  len: usize,
+) -> Vec<Option<f64>> {
+  //Linear interpolation between the good pixels of a row or column
+  let index = |k: usize| start + k * step;
+  let mut next_good = vec![None; len];
+  let mut next = None;
+  for k in (0..len).rev() {
+    if !bad[index(k)] {
+      next = Some(k);
+    }
+    next_good[k] = next;
+  }
+
+  let mut out = vec![None; len];
+  let mut last = None;
+  for k in 0..len {
+    if !bad[index(k)] {
+      last = Some(k);
+      continue;
+    }
+    out[k] = match (last, next_good[k]) {
+      (Some(a), Some(b)) => {
+        let t = (k - a) as f64 / (b - a) as f64;
+        Some((1.0 - t) * value(index(a)) + t * value(index(b)))
+      }
+      (Some(a), None) => Some(value(index(a))),
+      (None, Some(b)) => Some(value(index(b))),
+      (None, None) => None,
+    };
+  }
+  out
+}
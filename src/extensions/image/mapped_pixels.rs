@@ -0,0 +1,112 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{error::Error, fs::File, mem::size_of, path::Path, slice, sync::Arc};
+
+use memmap2::{Mmap, MmapOptions};
+use ndarray::{Array, ArrayViewD, IxDyn, ShapeBuilder};
+
+use crate::io_err::{self, FitsIoErr, InvalidFitsFileErr, TruncatedFileErr};
+
+use super::FitsPixel;
+
+#[derive(Debug, Clone)]
+pub(crate) struct MappedPixels {
+  /*  NOT PART OF USER-FACING API
+      Storage of an Image<T> whose data unit is mapped into memory instead of
+      read into a Vec (see FitsIndex::mmap_image). The operating system pages
+      the data in when it is accessed, and can drop those pages again under
+      memory pressure, so survey-scale mosaics can be processed without
+      holding them in RAM (twice).
+
+      FITS pixels are stored big-endian, so the map can only be viewed as an
+      ndarray directly if the pixels have the byte order of this machine:
+      bytes always, the other types only on big-endian machines. Otherwise
+      Image::get_data decodes the pixels once they are accessed.
+
+      The map is read-only. Like every memory map, it is only valid as long
+      as no other process truncates the file while it is in use.
+  */
+  map: Option<Arc<Mmap>>, //empty images are not mapped, clones share the map
+}
+
+impl MappedPixels {
+  pub(crate) fn map(
+    path: &Path,
+    hdu: usize,
+    offset: u64,
+    n_bytes: u64,
+  ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    //(1) Only the pixels are mapped, the padding of the last block is not
+    let len = usize::try_from(n_bytes)
+      .map_err(|_| InvalidFitsFileErr::new(io_err::DATA_NOT_ADDRESSABLE))?;
+
+    //(2) Accessing a map beyond the end of the file crashes the program
+    let file = File::open(path).map_err(|err| FitsIoErr::new(path, "open file", err))?;
+    let meta = file.metadata().map_err(|err| FitsIoErr::new(path, "read file metadata", err))?;
+    let got = meta.len().saturating_sub(offset);
+    if got < n_bytes {
+      return Err(Box::new(TruncatedFileErr::new(hdu, n_bytes, got)));
+    }
+    if len == 0 {
+      return Ok(MappedPixels { map: None });
+    }
+
+    /*  (3)
+        Mapping a file is unsafe because the map is not protected against
+        changes to the file by other processes. We only ever read from the
+        map, and its length was checked against the file above.
+    */
+    let map = unsafe { MmapOptions::new().offset(offset).len(len).map(&file) }
+      .map_err(|err| FitsIoErr::new(path, format!("map data unit at offset {offset}"), err))?;
+    Ok(MappedPixels { map: Some(Arc::new(map)) })
+  }
+
+  pub(crate) fn as_bytes(&self) -> &[u8] {
+    //Raw (big-endian, column-major) pixels of the image, without padding
+    match &self.map {
+      Some(map) => map,
+      None => &[],
+    }
+  }
+
+  pub(crate) fn view<T: FitsPixel>(&self, shape: &[usize]) -> Option<ArrayViewD<'_, T>> {
+    //The pixels in place, if they can be used without decoding them
+    let bytes = self.as_bytes();
+    let native = size_of::<T>() == 1 || cfg!(target_endian = "big");
+    if !native || bytes.as_ptr().align_offset(std::mem::align_of::<T>()) != 0 {
+      return None;
+    }
+
+    /*  The bytes are aligned for T and hold big-endian pixels on a big-endian
+        machine (or bytes), so they are valid T's. The length of the map was
+        checked against the shape of the image when it was mapped.
+    */
+    let pixels =
+      unsafe { slice::from_raw_parts(bytes.as_ptr().cast::<T>(), bytes.len() / size_of::<T>()) };
+    ArrayViewD::from_shape(IxDyn(shape).f(), pixels).ok()
+  }
+
+  pub(crate) fn decode<T: FitsPixel>(&self, shape: &[usize]) -> Array<T, IxDyn> {
+    //Copies the pixels into memory, in the Fortran layout of every image
+    let size = size_of::<T>();
+    let flat = self.as_bytes().chunks_exact(size).map(T::from_fits_bytes).collect();
+    Array::from_shape_vec(shape.f(), flat).unwrap()
+  }
+}
@@ -0,0 +1,130 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Image operations on whole HDU's. These wrap the operations of TypedImage
+    and keep the header of the HDU in line with the new data: NAXISn, the
+    (primary) WCS keywords and the keywords that described the stored pixels.
+    Axes are 0-based.
+*/
+
+use std::error::Error;
+
+#[cfg(feature = "fft")]
+use ndarray::{Array, IxDyn};
+#[cfg(feature = "fft")]
+use num_complex::Complex;
+
+#[cfg(feature = "fft")]
+use super::FftOptions;
+use super::Reduction;
+use crate::{
+  bitpix::PromotionRules,
+  extensions::Extension,
+  hdu_err::MissingDataErr,
+  header_data_unit::{HeaderDataUnit, HALF_FLOAT_MARKER},
+  wcs,
+};
+
+impl HeaderDataUnit {
+  /*
+      Orientation helpers. These flip or transpose the image of this HDU and
+      update the NAXISn and (primary) WCS keywords of the header accordingly,
+      such that every pixel keeps its world coordinates.
+  */
+  pub fn flip(&mut self, axis: usize) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let Some(Extension::Image(img)) = self.get_data_mut() else {
+      return Err(Box::new(MissingDataErr::new("an image")));
+    };
+    img.flip(axis)?;
+    let len = crate::impl_typed_image_dispatch!(img, img => img.get_shape()[axis]);
+    wcs::flip_axis(self.get_header_mut(), axis, len);
+    Ok(())
+  }
+
+  pub fn transpose(&mut self, axes: &[usize]) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let Some(Extension::Image(img)) = self.get_data_mut() else {
+      return Err(Box::new(MissingDataErr::new("an image")));
+    };
+    img.transpose(axes)?;
+    wcs::permute_axes(self.get_header_mut(), axes);
+    Ok(())
+  }
+
+  #[cfg(feature = "fft")]
+  pub fn fft2(
+    &self,
+    opts: &FftOptions,
+  ) -> Result<Array<Complex<f64>, IxDyn>, Box<dyn Error + Send + Sync>> {
+    /* Spectrum of the image, see TypedImage::fft2 and TypedImage::ifft2 */
+    let Some(Extension::Image(img)) = self.get_data() else {
+      return Err(Box::new(MissingDataErr::new("an image")));
+    };
+    Ok(img.fft2(opts))
+  }
+
+  pub fn reduce(
+    &self,
+    axis: usize,
+    reduction: Reduction,
+    rules: &PromotionRules,
+  ) -> Result<HeaderDataUnit, Box<dyn Error + Send + Sync>> {
+    /*  Collapses the image along the axis, see reduction.rs. The new HDU
+        holds a floating point image (of the float_result of the rules) with
+        one axis less, and its header describes the remaining axes. The
+        original HDU is left as it is.
+    */
+    let Some(Extension::Image(img)) = self.get_data() else {
+      return Err(Box::new(MissingDataErr::new("an image")));
+    };
+    let reduced = img.reduce(axis, reduction, rules)?;
+    let shape = crate::impl_typed_image_dispatch!(&reduced, img => img.get_shape().clone());
+
+    /* (1) Structural keywords: the pixel type and the axes */
+    let mut header = self.get_header().clone();
+    wcs::remove_axis(&mut header, axis);
+    for (keyword, value) in
+      [("BITPIX", reduced.get_bitpix().to_code().to_string()), ("NAXIS", shape.len().to_string())]
+    {
+      header.put_record(keyword, value, header.get_comment(keyword).cloned());
+    }
+
+    /* (2) Keywords that described the stored pixels no longer apply */
+    for keyword in ["BLANK", HALF_FLOAT_MARKER, "CHECKSUM", "DATASUM"] {
+      header.remove_record(keyword);
+    }
+    header.append_history(&format!("Reduced axis {} ({reduction})", axis + 1));
+
+    Ok(Self::from_parts(header, Some(Extension::Image(reduced))))
+  }
+
+  pub fn sky_cutout_range(
+    &self,
+    ra_deg: f64,
+    dec_deg: f64,
+    size_arcmin: f64,
+  ) -> Result<(Vec<usize>, Vec<usize>), Box<dyn Error + Send + Sync>> {
+    /*  Start and shape (in pixels) of the square box of size_arcmin centered
+        on the sky position, clipped to the image. See wcs.rs for the
+        supported projections.
+    */
+    let (axes, _) = Self::img_layout(self.get_header())?;
+    wcs::sky_box(self.get_header(), &axes, ra_deg, dec_deg, size_arcmin)
+  }
+}
@@ -0,0 +1,102 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::fmt::Display;
+
+use ndarray::{Array, ArrayView1, ArrayViewD, Axis, IxDyn, ShapeBuilder};
+use rayon::prelude::*;
+
+/*
+    Reductions collapse an image along one of its axes, for example to turn a
+    spectral cube into a moment-0 map or to compute a profile cut. The NaN
+    variants skip NaN pixels (blanked data), and only give NaN when all pixels
+    along the axis are NaN. Sum and Max propagate NaN pixels.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reduction {
+  //  THIS ENUM IS PART OF THE USER-FACING API
+  NanMean,
+  NanMedian,
+  Sum,
+  Max,
+}
+
+impl Display for Reduction {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let name = match self {
+      Reduction::NanMean => "nan-mean",
+      Reduction::NanMedian => "nan-median",
+      Reduction::Sum => "sum",
+      Reduction::Max => "max",
+    };
+    write!(f, "{name}")
+  }
+}
+
+impl Reduction {
+  fn apply(&self, lane: ArrayView1<f64>) -> f64 {
+    let valid = lane.iter().copied().filter(|px| !px.is_nan());
+    match self {
+      Reduction::Sum => lane.sum(),
+      Reduction::Max => lane.iter().copied().fold(f64::NEG_INFINITY, |max, px| {
+        if max.is_nan() || px.is_nan() {
+          f64::NAN
+        } else {
+          max.max(px)
+        }
+      }),
+      Reduction::NanMean => {
+        let (sum, count) = valid.fold((0.0, 0usize), |(sum, count), px| (sum + px, count + 1));
+        if count == 0 {
+          f64::NAN
+        } else {
+          sum / count as f64
+        }
+      }
+      Reduction::NanMedian => {
+        let mut pixels: Vec<f64> = valid.collect();
+        pixels.sort_by(f64::total_cmp);
+        let mid = pixels.len() / 2;
+        match pixels.len() {
+          0 => f64::NAN,
+          len if len % 2 == 0 => 0.5 * (pixels[mid - 1] + pixels[mid]),
+          _ => pixels[mid],
+        }
+      }
+    }
+  }
+}
+
+pub(crate) fn reduce(
+  data: ArrayViewD<f64>,
+  axis: usize,
+  reduction: Reduction,
+) -> Array<f64, IxDyn> {
+  //The axis must exist, this is checked by the caller
+  let mut shape = data.shape().to_vec();
+  shape.remove(axis);
+  let transposed = data.t();
+  let lanes: Vec<ArrayView1<f64>> =
+    transposed.lanes(Axis(data.ndim() - 1 - axis)).into_iter().collect();
+  let out: Vec<f64> = lanes.into_par_iter().map(|lane| reduction.apply(lane)).collect();
+
+  //The lanes of the transposed view come in the Fortran order of the image
+  Array::from_shape_vec(shape.f(), out).unwrap()
+}
@@ -17,18 +17,40 @@
     along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
 */
 
-use std::{
-  error::Error,
-  fmt::{Display, Write},
-};
+use std::{collections::hash_map::DefaultHasher, error::Error, fmt::Display, hash::Hasher};
 
-use ndarray::{Array, IxDyn};
+use ndarray::{ArcArray, Array, ArrayView2, ArrayViewD, Axis, IxDyn, ShapeBuilder, Zip};
+use num_complex::Complex;
+use num_traits::ToPrimitive;
 
 use crate::{
-  bitpix::Bitpix, extensions::ExtensionPrint, img_err::WrongImgTypeErr as WITErr, raw::BlockSized,
+  bitpix::{Bitpix, PromotionRules},
+  extensions::ExtensionPrint,
+  img_err::{
+    ComplexShapeErr, DataUnitLenErr, InvalidAxesErr, MaskShapeErr, WrongImgTypeErr as WITErr,
+  },
+  raw::BlockSized,
+  stats::ImageStats,
 };
 
-use super::generic_image::Image;
+#[cfg(feature = "fft")]
+use super::fft::{self, FftOptions};
+#[cfg(feature = "gpu")]
+use super::gpu;
+#[cfg(feature = "mmap")]
+use super::mapped_pixels::MappedPixels;
+#[cfg(feature = "fft")]
+use crate::img_err::SpectrumShapeErr;
+#[cfg(feature = "gpu")]
+use crate::img_err::TextureShapeErr;
+
+use super::{
+  convolution::{self, Boundary},
+  generic_image::Image,
+  inpaint::{self, InpaintMethod},
+  reduction::{self, Reduction},
+  FitsPixel,
+};
 
 #[derive(Debug, Clone)]
 pub enum TypedImage {
@@ -43,77 +65,84 @@ pub enum TypedImage {
   DpfImg(Image<f64>),
 }
 
-impl BlockSized for TypedImage {
-  fn get_block_len(&self) -> usize {
-    use TypedImage::*;
-    match self {
-      ByteImg(var) => var.get_block_len(),
-      I16Img(var) => var.get_block_len(),
-      I32Img(var) => var.get_block_len(),
-      I64Img(var) => var.get_block_len(),
-      SpfImg(var) => var.get_block_len(),
-      DpfImg(var) => var.get_block_len(),
+#[macro_export]
+macro_rules! impl_typed_image_dispatch {
+  /*  THIS MACRO IS PART OF THE USER-FACING API
+      Generates the six-way match over the variants of TypedImage. It can be
+      used in two ways:
+
+      (1) as an expression, binding the Image<T> inside the TypedImage:
+            impl_typed_image_dispatch!(typed_img, img => img.get_shape().len())
+
+      (2) to implement a trait for TypedImage that is already implemented for
+          Image<T>, by forwarding every listed method to the inner image:
+            impl_typed_image_dispatch!(impl MyTrait {
+              fn name(&self, arg: usize) -> String;
+              fn name_mut(&mut self);
+            });
+  */
+  (@methods) => {};
+  (@methods fn $name:ident(&self $(, $arg:ident: $ty:ty)* $(,)?) $(-> $ret:ty)?; $($rest:tt)*) => {
+    fn $name(&self $(, $arg: $ty)*) $(-> $ret)? {
+      $crate::impl_typed_image_dispatch!(self, img => img.$name($($arg),*))
     }
-  }
+    $crate::impl_typed_image_dispatch!(@methods $($rest)*);
+  };
+  (@methods fn $name:ident(&mut self $(, $arg:ident: $ty:ty)* $(,)?) $(-> $ret:ty)?; $($rest:tt)*) => {
+    fn $name(&mut self $(, $arg: $ty)*) $(-> $ret)? {
+      $crate::impl_typed_image_dispatch!(self, img => img.$name($($arg),*))
+    }
+    $crate::impl_typed_image_dispatch!(@methods $($rest)*);
+  };
+  (impl $tr:path { $($items:tt)* }) => {
+    impl $tr for $crate::TypedImage {
+      $crate::impl_typed_image_dispatch!(@methods $($items)*);
+    }
+  };
+  ($typed:expr, $img:ident => $body:expr) => {
+    match $typed {
+      $crate::TypedImage::ByteImg($img) => $body,
+      $crate::TypedImage::I16Img($img) => $body,
+      $crate::TypedImage::I32Img($img) => $body,
+      $crate::TypedImage::I64Img($img) => $body,
+      $crate::TypedImage::SpfImg($img) => $body,
+      $crate::TypedImage::DpfImg($img) => $body,
+    }
+  };
 }
 
+crate::impl_typed_image_dispatch!(impl BlockSized {
+  fn get_block_len(&self) -> usize;
+});
+
 impl ExtensionPrint for TypedImage {
   fn xprint(&self) -> String {
-    use TypedImage::*;
-    let mut f = String::from("(IMAGE) - ");
-
-    match self {
-      ByteImg(img) => write!(
-        f,
-        "datatype: u8, shape: {}, size: {}",
-        img.pretty_print_shape(),
-        img.get_block_len()
-      ),
-      I16Img(img) => write!(
-        f,
-        "datatype: i16, shape: {}, size: {}",
-        img.pretty_print_shape(),
-        img.get_block_len()
-      ),
-      I32Img(img) => write!(
-        f,
-        "datatype: i32, shape: {}, size: {}",
-        img.pretty_print_shape(),
-        img.get_block_len()
-      ),
-      I64Img(img) => write!(
-        f,
-        "datatype: i64, shape: {}, size: {}",
-        img.pretty_print_shape(),
-        img.get_block_len()
-      ),
-      SpfImg(img) => write!(
-        f,
-        "datatype: f32, shape: {}, size: {}",
-        img.pretty_print_shape(),
-        img.get_block_len()
-      ),
-      DpfImg(img) => write!(
-        f,
-        "datatype: f64, shape: {}, size: {}",
-        img.pretty_print_shape(),
-        img.get_block_len()
-      ),
-    }
-    .unwrap();
-
-    return f;
+    crate::impl_typed_image_dispatch!(self, img => format!(
+      "(IMAGE) - datatype: {}, shape: {}, size: {}",
+      self.bpx(),
+      img.pretty_print_shape(),
+      img.get_block_len()
+    ))
   }
 }
 
 impl Display for TypedImage {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    //TODO: make pretty display for image!
-    todo!()
+    write!(f, "{}", self.xprint())
+  }
+}
+
+impl<T: FitsPixel> From<Image<T>> for TypedImage {
+  fn from(img: Image<T>) -> Self {
+    T::into_typed(img)
   }
 }
 
 impl TypedImage {
+  pub(crate) fn get_memory_usage(&self) -> usize {
+    crate::impl_typed_image_dispatch!(self, img => img.get_memory_usage())
+  }
+
   pub(crate) fn bpx(&self) -> Bitpix {
     use Bitpix::*;
     use TypedImage::*;
@@ -128,87 +157,438 @@ impl TypedImage {
     }
   }
 
-  pub fn as_u8_array(&self) -> Result<&Array<u8, IxDyn>, Box<dyn Error>> {
+  pub fn get_bitpix(&self) -> Bitpix {
+    self.bpx()
+  }
+
+  pub fn deep_clone(&self) -> Self {
+    //Copies the data right away, keeping its pixel type (see Image::deep_clone)
+    crate::impl_typed_image_dispatch!(self, img => img.deep_clone().into())
+  }
+
+  pub fn flip(&mut self, axis: usize) -> Result<(), InvalidAxesErr> {
+    //Only flips the data, HeaderDataUnit::flip also updates the WCS keywords
+    crate::impl_typed_image_dispatch!(self, img => img.flip(axis))
+  }
+
+  pub fn transpose(&mut self, axes: &[usize]) -> Result<(), InvalidAxesErr> {
+    //Only transposes the data, see HeaderDataUnit::transpose
+    crate::impl_typed_image_dispatch!(self, img => img.transpose(axes))
+  }
+
+  pub(crate) fn cutout(&self, start: &[usize], shape: &[usize]) -> Self {
+    crate::impl_typed_image_dispatch!(self, img => img.cutout(start, shape).into())
+  }
+
+  pub fn convolve(
+    &self,
+    kernel: ArrayView2<f64>,
+    boundary: Boundary,
+    rules: &PromotionRules,
+  ) -> TypedImage {
+    //Convolution of the image with a 2-D kernel (see convolution.rs). The
+    //pixel type of the result is the float_result of the rules (f64 by default)
+    let data = self.to_f64_array();
+    let bitpix = rules.float_result(self.bpx());
+    Self::from_f64_array(convolution::convolve(data.view(), kernel, boundary), bitpix)
+  }
+
+  #[cfg(feature = "gpu")]
+  pub fn to_wgpu_texture(
+    &self,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+  ) -> Result<wgpu::Texture, TextureShapeErr> {
+    //Uploads the pixels as a 1-D to 3-D texture of the format given by
+    //wgpu_texture_format, converting them on the way (see gpu.rs)
+    gpu::upload(self, device, queue)
+  }
+
+  #[cfg(feature = "gpu")]
+  pub fn wgpu_texture_format(&self) -> wgpu::TextureFormat {
+    gpu::texture_format(self.bpx())
+  }
+
+  #[cfg(feature = "fft")]
+  pub fn fft2(&self, opts: &FftOptions) -> Array<Complex<f64>, IxDyn> {
+    //Fourier transform of the first two axes, after padding (see fft.rs)
+    let data = self.to_f64_array().mapv(|px| Complex::new(px, 0.0));
+    let mut shape = data.shape().to_vec();
+    for (axis, len) in shape.iter_mut().take(2).enumerate() {
+      *len = opts.padded_len(*len, axis);
+    }
+    fft::transform(data.view(), &shape, opts, false)
+  }
+
+  #[cfg(feature = "fft")]
+  pub fn ifft2(
+    spectrum: ArrayViewD<Complex<f64>>,
+    shape: &[usize],
+    opts: &FftOptions,
+  ) -> Result<TypedImage, SpectrumShapeErr> {
+    //Inverse of fft2, giving the real part cropped to the shape of the
+    //original image. Only the normalization of the options is used
+    let valid = shape.len() == spectrum.ndim()
+      && shape.iter().zip(spectrum.shape()).enumerate().all(
+        |(axis, (len, spectrum_len))| match axis < 2 {
+          true => len <= spectrum_len,
+          false => len == spectrum_len,
+        },
+      );
+    if !valid {
+      return Err(SpectrumShapeErr::new(spectrum.shape(), shape));
+    }
+    let image = fft::transform(spectrum, shape, opts, true);
+    Ok(TypedImage::DpfImg(Image::new(image.mapv(|px| px.re))))
+  }
+
+  pub fn reduce(
+    &self,
+    axis: usize,
+    reduction: Reduction,
+    rules: &PromotionRules,
+  ) -> Result<TypedImage, InvalidAxesErr> {
+    //Collapses the (0-based) axis into an image with one axis less, with the
+    //float_result pixel type of the rules (f64 by default).
+    //HeaderDataUnit::reduce also updates the header
+    let shape = crate::impl_typed_image_dispatch!(self, img => img.get_shape().clone());
+    if axis >= shape.len() || shape.len() < 2 {
+      return Err(InvalidAxesErr::new(&[axis], shape.len()));
+    }
+    let data = self.to_f64_array();
+    let reduced = reduction::reduce(data.view(), axis, reduction);
+    let bitpix = rules.float_result(self.bpx());
+    Ok(Self::from_f64_array(reduced, bitpix))
+  }
+
+  pub fn stats(&self) -> ImageStats {
+    //Overflow-safe summary statistics, see stats.rs
+    ImageStats::of_image(self)
+  }
+
+  pub fn inpaint(
+    &self,
+    mask: ArrayViewD<bool>,
+    method: InpaintMethod,
+  ) -> Result<TypedImage, MaskShapeErr> {
+    //Fills the pixels that are true in the mask (see inpaint.rs). The pixel
+    //type of the image is kept
+    crate::impl_typed_image_dispatch!(self, img => Ok(inpaint::inpaint(img, mask, method)?.into()))
+  }
+
+  pub(crate) fn to_f64_array(&self) -> Array<f64, IxDyn> {
+    fn to_f64<T: FitsPixel + ToPrimitive>(img: &Image<T>) -> Array<f64, IxDyn> {
+      img.get_data().mapv(|px| px.to_f64().unwrap_or(f64::NAN))
+    }
+    crate::impl_typed_image_dispatch!(self, img => to_f64(img))
+  }
+
+  pub fn to_bitpix(&self, bitpix: Bitpix) -> TypedImage {
+    //The image with another pixel type, for example one given by
+    //PromotionRules. Integers are rounded and saturate, NaN becomes 0
+    match bitpix == self.bpx() {
+      true => self.clone(),
+      false => Self::from_f64_array(self.to_f64_array(), bitpix),
+    }
+  }
+
+  pub(crate) fn from_f64_array(data: Array<f64, IxDyn>, bitpix: Bitpix) -> Self {
+    match bitpix {
+      Bitpix::Byte => Image::new(data.mapv(|px| px.round() as u8)).into(),
+      Bitpix::Short => Image::new(data.mapv(|px| px.round() as i16)).into(),
+      Bitpix::Int => Image::new(data.mapv(|px| px.round() as i32)).into(),
+      Bitpix::Long => Image::new(data.mapv(|px| px.round() as i64)).into(),
+      Bitpix::Spf => Image::new(data.mapv(|px| px as f32)).into(),
+      Bitpix::Dpf => Image::new(data).into(),
+    }
+  }
+
+  #[cfg(feature = "mmap")]
+  pub(crate) fn new_mapped(shape: Vec<usize>, bitpix: Bitpix, map: MappedPixels) -> Self {
+    match bitpix {
+      Bitpix::Byte => Image::<u8>::new_mapped(shape, map).into(),
+      Bitpix::Short => Image::<i16>::new_mapped(shape, map).into(),
+      Bitpix::Int => Image::<i32>::new_mapped(shape, map).into(),
+      Bitpix::Long => Image::<i64>::new_mapped(shape, map).into(),
+      Bitpix::Spf => Image::<f32>::new_mapped(shape, map).into(),
+      Bitpix::Dpf => Image::<f64>::new_mapped(shape, map).into(),
+    }
+  }
+
+  pub fn is_mapped(&self) -> bool {
+    //Whether the pixels are in a memory map of the file (see FitsIndex::mmap_image)
+    crate::impl_typed_image_dispatch!(self, img => img.is_mapped())
+  }
+
+  pub fn shares_data_with(&self, other: &TypedImage) -> bool {
+    fn shares<T: FitsPixel>(img: &Image<T>, other: &TypedImage) -> bool {
+      T::typed_ref(other).is_some_and(|other| img.shares_data_with(other))
+    }
+    crate::impl_typed_image_dispatch!(self, img => shares(img, other))
+  }
+
+  pub(crate) fn subsample(&mut self, steps: &[isize]) {
+    crate::impl_typed_image_dispatch!(self, img => img.subsample(steps))
+  }
+
+  pub(crate) fn digest(&self) -> u64 {
+    //Images with different pixel types never have the same digest
+    let mut hasher = DefaultHasher::new();
+    hasher.write(self.bpx().to_string().as_bytes());
+    crate::impl_typed_image_dispatch!(self, img => img.hash_data(&mut hasher));
+    hasher.finish()
+  }
+
+  pub(crate) fn same_data(&self, other: &TypedImage) -> bool {
+    fn same<T: FitsPixel>(img: &Image<T>, other: &TypedImage) -> bool {
+      T::typed_ref(other).is_some_and(|other| img.same_data(other))
+    }
+    crate::impl_typed_image_dispatch!(self, img => same(img, other))
+  }
+
+  /*
+      Generic versions of the as_*_array funcs below. The pixel type T has to
+      match the variant of the image, no conversions are performed.
+  */
+  pub fn as_array<T: FitsPixel>(
+    &self,
+  ) -> Result<&ArcArray<T, IxDyn>, Box<dyn Error + Send + Sync>> {
+    match T::typed_ref(self) {
+      Some(img) => Ok(img.get_data()),
+      None => Err(Box::new(WITErr::new(self, T::BITPIX))),
+    }
+  }
+
+  pub fn as_array_mut<T: FitsPixel>(
+    &mut self,
+  ) -> Result<&mut ArcArray<T, IxDyn>, Box<dyn Error + Send + Sync>> {
+    if T::typed_ref(self).is_none() {
+      return Err(Box::new(WITErr::new(self, T::BITPIX)));
+    }
+    Ok(T::typed_mut(self).unwrap().get_data_mut())
+  }
+
+  pub fn as_owned_array<T: FitsPixel>(
+    self,
+  ) -> Result<Array<T, IxDyn>, Box<dyn Error + Send + Sync>> {
+    if T::typed_ref(&self).is_none() {
+      return Err(Box::new(WITErr::new(&self, T::BITPIX)));
+    }
+    Ok(T::from_typed(self).unwrap().get_data_owned())
+  }
+
+  /*
+      Byte images (BITPIX = 8) are used for masks and flags, which can be big.
+      view_u8 borrows the pixels of such an image without copying them, and
+      view_u8_raw does the same for a data unit that is already in memory (a
+      buffer or a memory map of the file), without decoding it at all. Bytes
+      are stored as-is, so only the (column-major) layout has to be applied.
+      The padding at the end of the data unit may be included in data_unit.
+  */
+  pub fn view_u8(&self) -> Result<ArrayViewD<'_, u8>, Box<dyn Error + Send + Sync>> {
+    match &self {
+      Self::ByteImg(img) => Ok(img.view()),
+      &var => Err(Box::new(WITErr::new(var, Bitpix::byte()))),
+    }
+  }
+
+  pub fn view_u8_raw<'a>(
+    data_unit: &'a [u8],
+    shape: &[usize],
+  ) -> Result<ArrayViewD<'a, u8>, Box<dyn Error + Send + Sync>> {
+    let n_entries = shape.iter().product::<usize>();
+    match data_unit.get(..n_entries) {
+      Some(pixels) => Ok(ArrayViewD::from_shape(IxDyn(shape).f(), pixels)?),
+      None => Err(Box::new(DataUnitLenErr::new(data_unit.len(), n_entries))),
+    }
+  }
+
+  pub fn as_u8_array(&self) -> Result<&ArcArray<u8, IxDyn>, Box<dyn Error + Send + Sync>> {
     match &self {
       Self::ByteImg(img) => Ok(img.get_data()),
       &var => Err(Box::new(WITErr::new(var, Bitpix::byte()))),
     }
   }
 
-  pub fn as_i16_array(&self) -> Result<&Array<i16, IxDyn>, Box<dyn Error>> {
+  pub fn as_i16_array(&self) -> Result<&ArcArray<i16, IxDyn>, Box<dyn Error + Send + Sync>> {
     match &self {
       Self::I16Img(img) => Ok(img.get_data()),
       &var => Err(Box::new(WITErr::new(var, Bitpix::short()))),
     }
   }
 
-  pub fn as_i32_array(&self) -> Result<&Array<i32, IxDyn>, Box<dyn Error>> {
+  pub fn as_i32_array(&self) -> Result<&ArcArray<i32, IxDyn>, Box<dyn Error + Send + Sync>> {
     match &self {
       Self::I32Img(img) => Ok(img.get_data()),
       &var => Err(Box::new(WITErr::new(var, Bitpix::int()))),
     }
   }
 
-  pub fn as_i64_array(&self) -> Result<&Array<i64, IxDyn>, Box<dyn Error>> {
+  pub fn as_i64_array(&self) -> Result<&ArcArray<i64, IxDyn>, Box<dyn Error + Send + Sync>> {
     match &self {
       Self::I64Img(img) => Ok(img.get_data()),
       &var => Err(Box::new(WITErr::new(var, Bitpix::long()))),
     }
   }
 
-  pub fn as_f32_array(&self) -> Result<&Array<f32, IxDyn>, Box<dyn Error>> {
+  pub fn as_f32_array(&self) -> Result<&ArcArray<f32, IxDyn>, Box<dyn Error + Send + Sync>> {
     match &self {
       Self::SpfImg(img) => Ok(img.get_data()),
       &var => Err(Box::new(WITErr::new(var, Bitpix::spf()))),
     }
   }
 
-  pub fn as_f64_array(&self) -> Result<&Array<f64, IxDyn>, Box<dyn Error>> {
+  pub fn as_f64_array(&self) -> Result<&ArcArray<f64, IxDyn>, Box<dyn Error + Send + Sync>> {
     match &self {
       Self::DpfImg(img) => Ok(img.get_data()),
       &var => Err(Box::new(WITErr::new(var, Bitpix::dpf()))),
     }
   }
 
-  pub fn as_owned_u8_array(self) -> Result<Array<u8, IxDyn>, Box<dyn Error>> {
+  /*
+      Image data is shared between clones of an image. Mutating the array
+      returned by the following funcs copies the data if it is shared.
+  */
+  pub fn as_u8_array_mut(
+    &mut self,
+  ) -> Result<&mut ArcArray<u8, IxDyn>, Box<dyn Error + Send + Sync>> {
+    match self {
+      Self::ByteImg(img) => Ok(img.get_data_mut()),
+      var => Err(Box::new(WITErr::new(var, Bitpix::byte()))),
+    }
+  }
+
+  pub fn as_i16_array_mut(
+    &mut self,
+  ) -> Result<&mut ArcArray<i16, IxDyn>, Box<dyn Error + Send + Sync>> {
+    match self {
+      Self::I16Img(img) => Ok(img.get_data_mut()),
+      var => Err(Box::new(WITErr::new(var, Bitpix::short()))),
+    }
+  }
+
+  pub fn as_i32_array_mut(
+    &mut self,
+  ) -> Result<&mut ArcArray<i32, IxDyn>, Box<dyn Error + Send + Sync>> {
+    match self {
+      Self::I32Img(img) => Ok(img.get_data_mut()),
+      var => Err(Box::new(WITErr::new(var, Bitpix::int()))),
+    }
+  }
+
+  pub fn as_i64_array_mut(
+    &mut self,
+  ) -> Result<&mut ArcArray<i64, IxDyn>, Box<dyn Error + Send + Sync>> {
+    match self {
+      Self::I64Img(img) => Ok(img.get_data_mut()),
+      var => Err(Box::new(WITErr::new(var, Bitpix::long()))),
+    }
+  }
+
+  pub fn as_f32_array_mut(
+    &mut self,
+  ) -> Result<&mut ArcArray<f32, IxDyn>, Box<dyn Error + Send + Sync>> {
+    match self {
+      Self::SpfImg(img) => Ok(img.get_data_mut()),
+      var => Err(Box::new(WITErr::new(var, Bitpix::spf()))),
+    }
+  }
+
+  pub fn as_f64_array_mut(
+    &mut self,
+  ) -> Result<&mut ArcArray<f64, IxDyn>, Box<dyn Error + Send + Sync>> {
+    match self {
+      Self::DpfImg(img) => Ok(img.get_data_mut()),
+      var => Err(Box::new(WITErr::new(var, Bitpix::dpf()))),
+    }
+  }
+
+  pub fn as_owned_u8_array(self) -> Result<Array<u8, IxDyn>, Box<dyn Error + Send + Sync>> {
     match self {
       Self::ByteImg(img) => Ok(img.get_data_owned()),
       var => Err(Box::new(WITErr::new(&var, Bitpix::byte()))),
     }
   }
 
-  pub fn as_owned_i16_array(self) -> Result<Array<i16, IxDyn>, Box<dyn Error>> {
+  pub fn as_owned_i16_array(self) -> Result<Array<i16, IxDyn>, Box<dyn Error + Send + Sync>> {
     match self {
       Self::I16Img(img) => Ok(img.get_data_owned()),
       var => Err(Box::new(WITErr::new(&var, Bitpix::short()))),
     }
   }
 
-  pub fn as_owned_i32_array(self) -> Result<Array<i32, IxDyn>, Box<dyn Error>> {
+  pub fn as_owned_i32_array(self) -> Result<Array<i32, IxDyn>, Box<dyn Error + Send + Sync>> {
     match self {
       Self::I32Img(img) => Ok(img.get_data_owned()),
       var => Err(Box::new(WITErr::new(&var, Bitpix::int()))),
     }
   }
 
-  pub fn as_owned_i64_array(self) -> Result<Array<i64, IxDyn>, Box<dyn Error>> {
+  pub fn as_owned_i64_array(self) -> Result<Array<i64, IxDyn>, Box<dyn Error + Send + Sync>> {
     match self {
       Self::I64Img(img) => Ok(img.get_data_owned()),
       var => Err(Box::new(WITErr::new(&var, Bitpix::long()))),
     }
   }
 
-  pub fn as_owned_f32_array(self) -> Result<Array<f32, IxDyn>, Box<dyn Error>> {
+  pub fn as_owned_f32_array(self) -> Result<Array<f32, IxDyn>, Box<dyn Error + Send + Sync>> {
     match self {
       Self::SpfImg(img) => Ok(img.get_data_owned()),
       var => Err(Box::new(WITErr::new(&var, Bitpix::spf()))),
     }
   }
 
-  pub fn as_owned_f64_array(self) -> Result<Array<f64, IxDyn>, Box<dyn Error>> {
+  pub fn as_owned_f64_array(self) -> Result<Array<f64, IxDyn>, Box<dyn Error + Send + Sync>> {
     match self {
       Self::DpfImg(img) => Ok(img.get_data_owned()),
       var => Err(Box::new(WITErr::new(&var, Bitpix::dpf()))),
     }
   }
+
+  /*
+      Complex-valued images are stored as f32 or f64 images with an extra
+      first axis (NAXIS1) of length 2, so that the real and imaginary part of
+      each entry sit next to each other in the data unit. The complex arrays
+      do not have this axis. See HeaderDataUnit::is_complex() for the marker
+      keyword.
+  */
+  pub fn as_owned_c32_array(
+    self,
+  ) -> Result<Array<Complex<f32>, IxDyn>, Box<dyn Error + Send + Sync>> {
+    Ok(Self::to_complex(self.as_owned_f32_array()?)?)
+  }
+
+  pub fn as_owned_c64_array(
+    self,
+  ) -> Result<Array<Complex<f64>, IxDyn>, Box<dyn Error + Send + Sync>> {
+    Ok(Self::to_complex(self.as_owned_f64_array()?)?)
+  }
+
+  pub fn from_c32_array(array: ArrayViewD<Complex<f32>>) -> Self {
+    Self::from(Self::from_complex(array))
+  }
+
+  pub fn from_c64_array(array: ArrayViewD<Complex<f64>>) -> Self {
+    Self::from(Self::from_complex(array))
+  }
+
+  fn from_complex<T: FitsPixel>(array: ArrayViewD<Complex<T>>) -> Image<T> {
+    //Interleave the real and imaginary parts along a new NAXIS1 (Fortran order)
+    let shape: Vec<usize> = std::iter::once(2).chain(array.shape().iter().copied()).collect();
+    let flat = array.t().iter().flat_map(|px| [px.re, px.im]).collect();
+    Image::new(Array::from_shape_vec(shape.f(), flat).unwrap())
+  }
+
+  fn to_complex<T: Clone>(
+    array: Array<T, IxDyn>,
+  ) -> Result<Array<Complex<T>, IxDyn>, ComplexShapeErr> {
+    //(1) The first axis must contain the real and imaginary parts
+    if array.ndim() < 2 || array.shape()[0] != 2 {
+      return Err(ComplexShapeErr::new(array.shape()));
+    }
+
+    //(2) Zip the real and imaginary parts together
+    let (re, im) = (array.index_axis(Axis(0), 0), array.index_axis(Axis(0), 1));
+    Ok(Zip::from(&re).and(&im).map_collect(|re, im| Complex::new(re.clone(), im.clone())))
+  }
 }
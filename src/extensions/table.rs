@@ -18,13 +18,24 @@
 */
 
 //Module Structure
+mod ascii_num;
 pub mod ascii_table;
 pub(crate) mod ascii_tbl_parser;
 pub mod bin_table;
+pub(crate) mod bin_tbl_parser;
 pub mod column;
+pub(crate) mod compressed_table;
+pub mod fits_row;
+pub mod table_builder;
 pub mod table_entry;
+pub mod validity;
 
 //Re-exports for readability
 pub use ascii_table::AsciiTable;
 pub(crate) use ascii_tbl_parser::AsciiTblParser;
+pub use bin_table::{BinFormat, BinTable, BinType};
+pub(crate) use bin_tbl_parser::BinTblParser;
+pub use fits_row::{FitsField, FitsRow};
+pub use table_builder::TableBuilder;
 pub use table_entry::TableEntry;
+pub use validity::Validity;
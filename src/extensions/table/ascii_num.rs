@@ -0,0 +1,181 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Fast number parsers for the fields of ASCII tables. The fields are parsed
+    straight from the raw bytes, so we don't have to validate them as UTF-8
+    first. The parsers only handle the common cases (plain integers and
+    decimal floats with an optional E or D exponent) and return None for anything
+    else, in which case the caller should fall back to str::parse.
+*/
+
+//Largest integer that can be represented exactly by an f64
+const MAX_EXACT_MANTISSA: u64 = 1 << 53;
+//Powers of ten that can be represented exactly by an f64
+const EXACT_POWERS: [f64; 23] = [
+  1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16, 1e17,
+  1e18, 1e19, 1e20, 1e21, 1e22,
+];
+
+fn trim(raw: &[u8]) -> &[u8] {
+  let start = raw.iter().position(|&b| b != b' ').unwrap_or(raw.len());
+  let end = raw.iter().rposition(|&b| b != b' ').map_or(start, |end| end + 1);
+  &raw[start..end]
+}
+
+fn split_sign(raw: &[u8]) -> (bool, &[u8]) {
+  match raw.first() {
+    Some(b'-') => (true, &raw[1..]),
+    Some(b'+') => (false, &raw[1..]),
+    _ => (false, raw),
+  }
+}
+
+pub(crate) fn parse_int(raw: &[u8]) -> Option<i64> {
+  let (negative, digits) = split_sign(trim(raw));
+  if digits.is_empty() {
+    return None;
+  }
+
+  //Accumulate negatively, so i64::MIN can be parsed as well
+  let mut value = 0i64;
+  for &byte in digits {
+    if !byte.is_ascii_digit() {
+      return None;
+    }
+    value = value.checked_mul(10)?.checked_sub((byte - b'0') as i64)?;
+  }
+  match negative {
+    true => Some(value),
+    false => value.checked_neg(),
+  }
+}
+
+pub(crate) fn parse_float(raw: &[u8]) -> Option<f64> {
+  let (negative, rest) = split_sign(trim(raw));
+
+  //(1) Mantissa digits, with an optional decimal point
+  let (mut mantissa, mut n_digits, mut frac_digits, mut seen_point) = (0u64, 0usize, 0i32, false);
+  let mut idx = 0;
+  while idx < rest.len() {
+    match rest[idx] {
+      b'0'..=b'9' => {
+        //More than 19 digits might overflow the mantissa
+        if n_digits == 19 {
+          return None;
+        }
+        mantissa = mantissa * 10 + (rest[idx] - b'0') as u64;
+        n_digits += 1;
+        frac_digits += seen_point as i32;
+      }
+      b'.' if !seen_point => seen_point = true,
+      _ => break,
+    }
+    idx += 1;
+  }
+  if n_digits == 0 {
+    return None;
+  }
+
+  //(2) Optional exponent
+  let mut exponent = 0i32;
+  if idx < rest.len() {
+    if !matches!(rest[idx], b'E' | b'e' | b'D' | b'd') {
+      return None;
+    }
+    exponent = parse_int(&rest[idx + 1..])?.try_into().ok()?;
+  }
+
+  //(3) Only exact mantissas and powers of ten give correctly rounded results
+  let power = exponent.checked_sub(frac_digits)?;
+  if mantissa > MAX_EXACT_MANTISSA || power.unsigned_abs() as usize >= EXACT_POWERS.len() {
+    return None;
+  }
+  let value = match power < 0 {
+    true => mantissa as f64 / EXACT_POWERS[power.unsigned_abs() as usize],
+    false => mantissa as f64 * EXACT_POWERS[power as usize],
+  };
+  Some(if negative { -value } else { value })
+}
+
+pub(crate) fn is_overflow(raw: &[u8]) -> bool {
+  //Fortran fills fields that are too narrow for their value with asterisks
+  let raw = trim(raw);
+  !raw.is_empty() && raw.iter().all(|&byte| byte == b'*')
+}
+
+pub(crate) fn repair_number(raw: &[u8]) -> Option<(String, Vec<&'static str>)> {
+  /*  Rewrites a malformed number into something str::parse accepts, listing
+      what was changed. Returns None if the field cannot be repaired without
+      guessing (a minus sign in an odd position for example).
+  */
+  let mut notes = Vec::new();
+
+  //(1) Blanks inside a number are ignored, like Fortran's BN edit descriptor
+  let mut bytes: Vec<u8> = trim(raw).to_vec();
+  if bytes.contains(&b' ') {
+    bytes.retain(|&byte| byte != b' ');
+    notes.push("removed embedded blanks");
+  }
+
+  //(2) Strip stray characters around the number
+  let is_start = |byte: &u8| byte.is_ascii_digit() || matches!(byte, b'+' | b'-' | b'.');
+  let is_end = |byte: &u8| byte.is_ascii_digit() || *byte == b'.';
+  let start = bytes.iter().position(is_start)?;
+  let end = bytes.iter().rposition(is_end)? + 1;
+  if start >= end {
+    return None;
+  }
+  if start > 0 || end < bytes.len() {
+    notes.push("removed stray characters");
+  }
+
+  //(3) Rebuild the number, fixing exponents and signs along the way
+  let mut repaired = String::with_capacity(end - start);
+  let mut has_exponent = false;
+  for (idx, &byte) in bytes[start..end].iter().enumerate() {
+    let prev = repaired.chars().last();
+    let next_is_digit = bytes.get(start + idx + 1).is_some_and(|next| next.is_ascii_digit());
+    match byte {
+      b'0'..=b'9' | b'.' => repaired.push(byte as char),
+      b'E' | b'e' | b'D' | b'd' if !has_exponent => {
+        if matches!(byte, b'D' | b'd') {
+          notes.push("converted D exponent");
+        }
+        has_exponent = true;
+        repaired.push('E');
+      }
+      b'+' | b'-' => match prev {
+        None | Some('E') => repaired.push(byte as char),
+        //1.5-03 is Fortran for 1.5E-03
+        Some('0'..='9' | '.') if !has_exponent && next_is_digit => {
+          notes.push("added missing exponent letter");
+          has_exponent = true;
+          repaired.push('E');
+          repaired.push(byte as char);
+        }
+        _ if byte == b'+' => notes.push("removed stray '+' sign"),
+        _ => return None,
+      },
+      _ => return None,
+    }
+  }
+  notes.dedup();
+  Some((repaired, notes))
+}
@@ -26,10 +26,10 @@ use crate::{
   extensions::ExtensionPrint,
   raw::{table_entry_format::TableEntryFormat, BlockSized},
   tbl_err::IndexOutOfRangeErr,
-  tbl_err::ShapeMisMatchErr,
+  validation::Diagnostic,
 };
 
-use super::{column::AsciiCol, TableEntry};
+use super::{column::AsciiCol, fits_row, FitsRow, TableEntry, Validity};
 
 /*  Description:
     This is the abstracted user-facing api for tables. The
@@ -38,6 +38,7 @@ use super::{column::AsciiCol, TableEntry};
 pub struct AsciiTable {
   cols: Vec<Box<dyn AsciiCol>>,
   block_size: Option<usize>,
+  repairs: Vec<Diagnostic>, //fields that were repaired when reading
 }
 
 impl BlockSized for AsciiTable {
@@ -47,7 +48,8 @@ impl BlockSized for AsciiTable {
       None => {
         //We have to calculate the size of the table manually, as it is
         //not currently known (this is the case for user-created tables)
-        todo!()
+        let row_len: usize = self.get_tbl_fmt().iter().map(|fmt| fmt.get_field_width()).sum();
+        (row_len * self.max_col_len()).div_ceil(crate::BLOCK_SIZE)
       }
     }
   }
@@ -76,7 +78,7 @@ impl ExtensionPrint for AsciiTable {
     format!(
       "(TABLE) - #columns: {}, #rows: {}, size: {}",
       self.cols.len(),
-      match self.cols.get(0) {
+      match self.cols.first() {
         None => 0,
         Some(col_ref) => col_ref.len(),
       },
@@ -111,51 +113,141 @@ impl AsciiTable {
     (self.cols.len(), self.max_col_len())
   }
 
-  pub fn get_fmtd_column(&self, col: usize) -> Option<Vec<String>> {
+  pub fn get_col_label(&self, col: usize) -> Option<&str> {
     match self.cols.get(col) {
       None => None,
-      Some(column) => Some(column.to_ascii_vec()),
+      Some(column) => column.get_col_label(),
+    }
+  }
+
+  pub fn get_col_index(&self, label: &str) -> Option<usize> {
+    //Column labels are compared case-insensitively, as in the standard
+    self.cols.iter().position(|col| {
+      col.get_col_label().is_some_and(|name| name.trim().eq_ignore_ascii_case(label.trim()))
+    })
+  }
+
+  /*
+      Rows can be read as (and tables made from) structs that implement
+      FitsRow, see fits_row.rs. Fields are matched to columns by label.
+  */
+  pub fn rows_as<R: FitsRow>(&self) -> Result<Vec<R>, Box<dyn Error + Send + Sync>> {
+    fits_row::read_rows(
+      self.get_shape().1,
+      |label| self.get_col_index(label),
+      |col, row| self.get_entry(col, row),
+    )
+  }
+
+  pub fn from_rows<R: FitsRow>(rows: &[R]) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    Ok(fits_row::build_rows(rows)?.build())
+  }
+
+  pub fn get_fmtd_column(&self, col: usize) -> Option<Vec<String>> {
+    self.cols.get(col).map(|column| column.to_ascii_vec())
+  }
+
+  /*
+      Null entries hold a placeholder (0, NaN or an empty string), so use the
+      validity bitmap of a column to tell them apart from actual values.
+      Columns without nulls have no bitmap.
+  */
+  pub fn get_col_validity(&self, col: usize) -> Option<&Validity> {
+    self.cols.get(col)?.validity()
+  }
+
+  pub fn get_col_null(&self, col: usize) -> Option<&str> {
+    //TNULLn string of the column, which nulls are written as
+    self.cols.get(col)?.get_null()
+  }
+
+  pub fn is_null(&self, col: usize, row: usize) -> bool {
+    self.get_col_validity(col).is_some_and(|validity| validity.is_null(row))
+  }
+
+  pub fn get_field_repairs(&self) -> &[Diagnostic] {
+    //Fields that were repaired (or nulled) with FieldTolerance::Repair
+    &self.repairs
+  }
+
+  /*
+      String columns can be interned (dictionary encoded) to save memory, see
+      StringInterning. Interned columns behave like any other column, these
+      funcs are only needed to intern columns after reading or to look at the
+      dictionary itself.
+  */
+  pub fn intern_column(&mut self, col: usize) -> bool {
+    //Returns if the column is interned now (only string columns can be)
+    let Some(column) = self.cols.get_mut(col) else {
+      return false;
+    };
+    if column.dictionary().is_none() {
+      match column.to_interned() {
+        Some(interned) => *column = interned,
+        None => return false,
+      }
     }
+    true
+  }
+
+  pub fn get_col_dictionary(&self, col: usize) -> Option<&[String]> {
+    //Distinct values of an interned column
+    self.cols.get(col)?.dictionary()
+  }
+
+  pub fn get_str(&self, col: usize, row: usize) -> Option<&str> {
+    //Entry of a string column, without copying it
+    self.cols.get(col)?.get_str(row)
   }
 
   /*
       INTERNAL FUNCS
   */
 
-  pub(crate) fn get_col_fmt(&self, col: usize) -> Option<TableEntryFormat> {
-    match self.cols.get(col) {
-      None => None,
-      Some(column) => Some(column.get_col_fmt()),
+  pub(crate) fn get_float(
+    &self,
+    col: usize,
+    row: usize,
+  ) -> Result<f64, Box<dyn Error + Send + Sync>> {
+    //Numeric entry as a float, where nulls become NaN
+    let entry = self.get_entry(col, row)?;
+    match self.is_null(col, row) {
+      true => Ok(f64::NAN),
+      false => Ok(f64::try_from(entry)?),
     }
   }
 
+  pub(crate) fn get_col_fmt(&self, col: usize) -> Option<TableEntryFormat> {
+    self.cols.get(col).map(|column| column.get_col_fmt())
+  }
+
   pub(crate) fn get_tbl_fmt(&self) -> Vec<TableEntryFormat> {
     self.cols.iter().map(|col| col.get_col_fmt()).collect()
   }
 
   pub(crate) fn new_sized(cols: Vec<Box<dyn AsciiCol>>, size: usize) -> Self {
     //creates new table with known blocksize
-    AsciiTable { cols: cols, block_size: Some(size) }
+    AsciiTable { cols, block_size: Some(size), repairs: Vec::new() }
   }
 
-  pub(crate) fn add_row(&mut self, row: Vec<TableEntry>) -> Result<(), Box<dyn Error>> {
-    //Adds row to table
-    if row.len() != self.cols.len() {
-      return Err(Box::new(ShapeMisMatchErr::new(&row, &self)));
-    }
+  pub(crate) fn with_repairs(mut self, repairs: Vec<Diagnostic>) -> Self {
+    self.repairs = repairs;
+    self
+  }
 
-    //Add row to the table
-    for (index, entry) in row.into_iter().enumerate() {
-      self.cols[index].push_entry(entry)?;
-    }
+  pub(crate) fn new_unsized(cols: Vec<Box<dyn AsciiCol>>) -> Self {
+    //creates new table whose blocksize is calculated on demand
+    AsciiTable { cols, block_size: None, repairs: Vec::new() }
+  }
 
-    //(R) ok
-    Ok(())
+  pub(crate) fn append_columns(&mut self, other: AsciiTable) {
+    //Adds the columns of another table, which must have as many rows
+    self.cols.extend(other.cols);
+    self.block_size = None;
   }
 
-  pub(crate) fn destroy(self) -> Vec<Vec<String>> {
-    //destructs table into columns of strings
-    self.cols.into_iter().map(|val| val.to_ascii_vec()).collect()
+  pub(crate) fn get_memory_usage(&self) -> usize {
+    self.cols.iter().map(|col| col.get_memory_usage()).sum()
   }
 
   pub(crate) fn max_col_len(&self) -> usize {
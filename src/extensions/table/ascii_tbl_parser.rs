@@ -20,36 +20,202 @@
 //Get block size from root
 const BLOCK_SIZE: usize = crate::BLOCK_SIZE; // = 2880B
 
-use std::{
-  error::Error,
-  num::ParseIntError,
-  str::{self, Utf8Error},
-};
+use std::{error::Error, num::ParseIntError};
 
 use crate::{
-  extensions::{table::column::AsciiCol, Extension},
+  extensions::{
+    table::column::{self, AsciiCol},
+    Extension,
+  },
+  header::Header,
+  keyword_value::{quote, unquote},
   raw::{
-    raw_io::{RawFitsReader, RawFitsWriter},
+    block_io::{BlockRead, BlockWrite},
     table_entry_format::TableEntryFormat,
   },
+  read_options::{FieldTolerance, ReadOptions, TableStrategy},
+  tbl_err::FieldOverflowErr,
   tbl_fmt_err::{InvalidFFCode, ParseError},
+  validation::{Diagnostic, Severity},
 };
 
-use super::{column::Column, AsciiTable, TableEntry};
+use super::{ascii_num, column::Column, AsciiTable, TableEntry, Validity};
 
 use rayon::prelude::*;
 
+//Number of rows decoded by a single rayon task
+const ROWS_PER_CHUNK: usize = 4096;
+//Rows at least this long are considered wide (for TableStrategy::Auto)
+const WIDE_ROW_CHARS: usize = 512;
+//Name of the (pseudo) validation profile of field repair diagnostics
+const PROFILE: &str = "table";
+
+enum Values {
+  /*
+      Typed buffer that the fields of a single column are decoded into. The
+      entries pushed into a buffer always have the right type, since they were
+      decoded using the format of the column.
+  */
+  Text(Vec<String>),
+  Int(Vec<i64>),
+  Float(Vec<f64>),
+}
+
+struct FieldRepair {
+  //A field that was repaired (or nulled) with FieldTolerance::Repair
+  row: usize,
+  raw: String,
+  message: String,
+  severity: Severity,
+}
+
+struct ColumnBuffer {
+  //Decoded entries of a column, and which of them are null (see TNULLn)
+  values: Values,
+  validity: Validity,
+  repairs: Vec<FieldRepair>,
+}
+
+impl ColumnBuffer {
+  fn with_capacity(fmt: &TableEntryFormat, capacity: usize) -> Result<Self, InvalidFFCode> {
+    let values = match fmt {
+      TableEntryFormat::Char(_) => Values::Text(Vec::with_capacity(capacity)),
+      TableEntryFormat::Int(_) => Values::Int(Vec::with_capacity(capacity)),
+      TableEntryFormat::Float(_) => Values::Float(Vec::with_capacity(capacity)),
+      TableEntryFormat::Invalid(invld) => return Err(InvalidFFCode::new(invld.clone())),
+    };
+    let validity = Validity::with_capacity(capacity);
+    Ok(ColumnBuffer { values, validity, repairs: Vec::new() })
+  }
+
+  fn push_field(
+    &mut self,
+    field: &[u8],
+    fmt: &TableEntryFormat,
+    null: Option<&[u8]>,
+    tolerance: FieldTolerance,
+  ) -> Result<(), ParseError> {
+    //(1) Fields that match the TNULLn string are null
+    if null.is_some_and(|null| field.trim_ascii() == null) {
+      self.push_null();
+      return Ok(());
+    }
+
+    //(2) With FieldTolerance::Repair, overflowed and unreadable fields
+    //    become null entries instead of errors
+    let decoded = match TableEntry::from_bytes_with(field, fmt, tolerance) {
+      Err(_) if tolerance == FieldTolerance::Repair && ascii_num::is_overflow(field) => {
+        self.push_repair(
+          field,
+          String::from("field overflowed, stored as null"),
+          Severity::Warning,
+        );
+        self.push_null();
+        return Ok(());
+      }
+      Err(err) if tolerance == FieldTolerance::Repair => {
+        let message = format!("unreadable field ({err}), stored as null");
+        self.push_repair(field, message, Severity::Error);
+        self.push_null();
+        return Ok(());
+      }
+      decoded => decoded?,
+    };
+
+    //(3) Valid entries, which may have been repaired
+    let (entry, repair) = decoded;
+    if let Some(repair) = repair {
+      self.push_repair(field, format!("{repair}, read as {entry}"), Severity::Warning);
+    }
+    self.validity.push(true);
+    match (&mut self.values, entry) {
+      (Values::Text(buf), TableEntry::Text(txt)) => buf.push(txt),
+      (Values::Int(buf), TableEntry::Int(num)) => buf.push(num),
+      (Values::Float(buf), TableEntry::Float(num)) => buf.push(num),
+      _ => unreachable!("entry was decoded with the format of another column"),
+    }
+    Ok(())
+  }
+
+  fn push_null(&mut self) {
+    //Null entries get a placeholder value
+    self.validity.push(false);
+    match &mut self.values {
+      Values::Text(buf) => buf.push(String::new()),
+      Values::Int(buf) => buf.push(0),
+      Values::Float(buf) => buf.push(f64::NAN),
+    }
+  }
+
+  fn push_repair(&mut self, field: &[u8], message: String, severity: Severity) {
+    //The repair applies to the entry that is about to be pushed
+    let raw = String::from_utf8_lossy(field).trim().to_string();
+    let row = self.validity.len();
+    self.repairs.push(FieldRepair { row, raw, message, severity });
+  }
+
+  fn append(&mut self, other: Self) {
+    //Rows of the repairs are relative to the start of the buffer
+    let offset = self.validity.len();
+    self.repairs.extend(
+      other.repairs.into_iter().map(|repair| FieldRepair { row: repair.row + offset, ..repair }),
+    );
+    self.validity.append(&other.validity);
+    match (&mut self.values, other.values) {
+      (Values::Text(buf), Values::Text(mut other)) => buf.append(&mut other),
+      (Values::Int(buf), Values::Int(mut other)) => buf.append(&mut other),
+      (Values::Float(buf), Values::Float(mut other)) => buf.append(&mut other),
+      _ => unreachable!("chunks were decoded with the same formats"),
+    }
+  }
+
+  fn take_repairs(&mut self, col: usize) -> Vec<Diagnostic> {
+    //Repairs as diagnostics, with the TFORMn keyword of the column
+    let keyword = format!("TFORM{}", col + 1);
+    std::mem::take(&mut self.repairs)
+      .into_iter()
+      .map(|repair| {
+        let message = format!("row {}: {}", repair.row + 1, repair.message);
+        Diagnostic::new(PROFILE, 0, &keyword, &repair.raw, message, repair.severity)
+      })
+      .collect()
+  }
+
+  fn into_column(self, label: Option<String>, null: Option<String>) -> Box<dyn AsciiCol> {
+    let validity = self.validity;
+    match self.values {
+      Values::Text(buf) => Box::new(Column::with_nulls(label, buf, validity, null)),
+      Values::Int(buf) => Box::new(Column::with_nulls(label, buf, validity, null)),
+      Values::Float(buf) => Box::new(Column::with_nulls(label, buf, validity, null)),
+    }
+  }
+}
+
+struct Fields<'a> {
+  //Layout of the fields in a row: their ranges, formats and null strings
+  ranges: &'a [(usize, usize)],
+  fmts: &'a [TableEntryFormat],
+  nulls: &'a [Option<&'a [u8]>],
+  tolerance: FieldTolerance,
+}
+
+impl Fields<'_> {
+  fn get(&self, col: usize) -> ((usize, usize), &TableEntryFormat, Option<&[u8]>) {
+    (self.ranges[col], &self.fmts[col], self.nulls[col])
+  }
+}
+
 pub struct AsciiTblParser {}
 impl AsciiTblParser {
   pub(crate) fn decode_tbl(
-    reader: &mut RawFitsReader,
-    chars_in_row: usize,               //#ASCII characters in a (raw) row
-    rows_in_file: usize,               //#raw rows in the table
-    fields_in_row: usize,              //#fields in each row
-    row_index_col_start: Vec<usize>,   //row index where each column starts
-    field_format: Vec<String>,         //data format (incl length) of each field
-    field_labels: Option<Vec<String>>, //field labels
-  ) -> Result<Extension, Box<dyn Error>> {
+    reader: &mut dyn BlockRead,
+    chars_in_row: usize,             //#ASCII characters in a (raw) row
+    rows_in_file: usize,             //#raw rows in the table
+    row_index_col_start: Vec<usize>, //row index where each column starts
+    field_format: Vec<(String, Option<String>)>, //data format (incl length) and TNULLn of each field
+    field_labels: Option<Vec<String>>,           //field labels
+    opts: &ReadOptions,                          //table strategy and field tolerance
+  ) -> Result<Extension, Box<dyn Error + Send + Sync>> {
     /*  (1)
         Tables are usually pretty small compared to images. Hence it's
         probably ok to read the whole table in one go. We should be careful
@@ -57,7 +223,7 @@ impl AsciiTblParser {
     */
     let byte_size = chars_in_row * rows_in_file;
     let mut num_blocks = byte_size / BLOCK_SIZE;
-    if byte_size % BLOCK_SIZE != 0 {
+    if !byte_size.is_multiple_of(BLOCK_SIZE) {
       num_blocks += 1;
     } //leftover block
 
@@ -69,168 +235,369 @@ impl AsciiTblParser {
         Next we have to figure out how the fields in each row are encoded.
         This information is contained within the field_format vec.
         Specifically, we want to know how long (in chars) each field in a row
-        is and we want to set-up the typed table.
+        is and what type of column it ends up in.
     */
+    let (field_format, field_nulls): (Vec<String>, Vec<Option<String>>) =
+      field_format.into_iter().unzip();
     let fmts = field_format
       .iter()
       .map(|f| TableEntryFormat::from_fortran_format_code(f))
       .collect::<Result<Vec<TableEntryFormat>, ParseIntError>>()?;
 
-    //(2a) Turn the formats into an vec of field sizes
-    let field_lengs: Vec<usize> = fmts.iter().map(|fmt| fmt.get_field_width()).collect();
-
-    //(2b) Turn the formats into a typed table
-    let mut tbl = Self::setup_table(&fmts, field_labels, num_blocks)?;
+    //(2a) Turn the formats into a vec of field ranges within a row
+    let fields: Vec<(usize, usize)> = row_index_col_start
+      .iter()
+      .zip(&fmts)
+      .map(|(&start, fmt)| (start, start + fmt.get_field_width()))
+      .collect();
 
     /*  (3)
-        We may now divide the total raw file into row-sized chunks and process
-        each row in a parallel fashion using rayon.
-
-        Steps:
-            1.  slice whole_table into row-sized chunks
-            2.  slice row_sized chunks into field-sized chunks. Note that
-                not all fields have the same size in bytes.
-            3.  convert vector of byte slices to a vector of table-entries
-            4.  add this formatted row to the formatted table
-
-        Btw, 1 char = 1 byte in ASCII encoding
+        Since we read in blocks of 2880 bytes, we might've read too much (the
+        last block may be padded), so we only look at the actual rows. These
+        are decoded in parallel (see decode_rows and decode_cols) straight
+        into typed column buffers. Btw, 1 char = 1 byte in ASCII encoding
     */
+    let raw_rows = &whole_table[..byte_size];
+    let column_parallel = match opts.get_table_strategy() {
+      TableStrategy::RowParallel => false,
+      TableStrategy::ColumnParallel => true,
+      TableStrategy::Auto => {
+        //Wide tables with enough columns to keep all threads busy
+        chars_in_row >= WIDE_ROW_CHARS && fmts.len() >= rayon::current_num_threads()
+      }
+    };
+    let nulls: Vec<Option<&[u8]>> =
+      field_nulls.iter().map(|null| null.as_ref().map(|null| null.trim().as_bytes())).collect();
+    let tolerance = opts.get_field_tolerance();
+    let fields = Fields { ranges: &fields, fmts: &fmts, nulls: &nulls, tolerance };
+    let bufs = match column_parallel {
+      true => Self::decode_cols(raw_rows, chars_in_row, rows_in_file, &fields)?,
+      false => Self::decode_rows(raw_rows, chars_in_row, rows_in_file, &fields)?,
+    };
+
+    //(3a) and turn the buffers into labeled columns, keeping their repairs
+    let mut repairs = Vec::new();
+    let cols = bufs
+      .into_iter()
+      .zip(field_nulls)
+      .enumerate()
+      .map(|(i, (mut buf, null))| {
+        repairs.append(&mut buf.take_repairs(i));
+        let col = buf.into_column(field_labels.as_ref().map(|labels| labels[i].clone()), null);
+        column::intern_column(col, opts.get_string_interning())
+      })
+      .collect();
 
-    //(3a) first we split the rows in the table (steps 1 and 2)
-    let split_rows_err = whole_table
-      .par_chunks_exact(chars_in_row)
-      .map(|raw| Self::split_row(raw, &row_index_col_start, &field_lengs))
-      .collect::<Vec<Result<Vec<&str>, Utf8Error>>>();
+    //(R) return the filled table
+    let tbl = AsciiTable::new_sized(cols, num_blocks).with_repairs(repairs);
+    Ok(Extension::AsciiTable(tbl))
+  }
 
-    //Because of the limitations of rayon we must unpack the errors sequentially
-    let mut split_rows: Vec<Vec<&str>> =
-      split_rows_err.into_iter().collect::<Result<Vec<Vec<&str>>, Utf8Error>>()?;
+  fn decode_rows(
+    raw_rows: &[u8],
+    chars_in_row: usize,
+    rows_in_file: usize,
+    fields: &Fields,
+  ) -> Result<Vec<ColumnBuffer>, ParseError> {
+    /*  (1)
+        Divide the raw table into chunks of rows and decode each chunk in a
+        parallel fashion using rayon. Every chunk is decoded straight into
+        typed column buffers, so we don't allocate anything per row.
+    */
+    let chunks = match chars_in_row {
+      0 => Vec::new(),
+      _ => raw_rows
+        .par_chunks(ROWS_PER_CHUNK * chars_in_row)
+        .map(|chunk| Self::decode_chunk(chunk, chars_in_row, fields))
+        .collect::<Result<Vec<Vec<ColumnBuffer>>, ParseError>>()?,
+    };
+
+    //(2) glue the chunks back together, in order
+    let mut bufs = fields
+      .fmts
+      .iter()
+      .map(|fmt| ColumnBuffer::with_capacity(fmt, rows_in_file))
+      .collect::<Result<Vec<ColumnBuffer>, InvalidFFCode>>()?;
+    for chunk in chunks {
+      for (buf, part) in bufs.iter_mut().zip(chunk) {
+        buf.append(part);
+      }
+    }
 
-    //Since we read in blocks of 2880 bytes, we might've read too much (some
-    //rows may just contain zeroes). We fix this by throwing some rows away.
-    split_rows.resize(rows_in_file, vec!["ERROR"]);
+    //(R) the decoded columns
+    Ok(bufs)
+  }
 
-    //(3b) and then we decode the rows (step 3)
-    let fmtd_rows_err = split_rows
+  fn decode_cols(
+    raw_rows: &[u8],
+    chars_in_row: usize,
+    rows_in_file: usize,
+    fields: &Fields,
+  ) -> Result<Vec<ColumnBuffer>, ParseError> {
+    //Every column is decoded by a single rayon task, scanning all rows
+    (0..fields.fmts.len())
       .into_par_iter()
-      .map(|field_vec| {
-        field_vec
-          .into_iter()
-          .enumerate()
-          .map(|(i, st)| TableEntry::from_parts(st, &fmts[i]))
-          .collect::<Result<Vec<TableEntry>, ParseError>>()
+      .map(|col| {
+        let ((start, end), fmt, null) = fields.get(col);
+        let mut buf = ColumnBuffer::with_capacity(fmt, rows_in_file)?;
+        if chars_in_row > 0 {
+          for row in raw_rows.chunks_exact(chars_in_row) {
+            buf.push_field(&row[start..end], fmt, null, fields.tolerance)?;
+          }
+        }
+        Ok(buf)
       })
-      .collect::<Vec<Result<Vec<TableEntry>, ParseError>>>();
-
-    //Because of the limitations of rayon we must unpack the errors sequentially
-    let fmtd_rows =
-      fmtd_rows_err.into_iter().collect::<Result<Vec<Vec<TableEntry>>, ParseError>>()?;
+      .collect()
+  }
 
-    //(3c) fill the table with the formatted rows (step 4)
-    for row in fmtd_rows {
-      tbl.add_row(row)?;
+  fn decode_chunk(
+    chunk: &[u8],
+    chars_in_row: usize,
+    fields: &Fields,
+  ) -> Result<Vec<ColumnBuffer>, ParseError> {
+    //(1) Set-up a typed buffer for every column
+    let n_rows = chunk.len() / chars_in_row;
+    let mut bufs = fields
+      .fmts
+      .iter()
+      .map(|fmt| ColumnBuffer::with_capacity(fmt, n_rows))
+      .collect::<Result<Vec<ColumnBuffer>, InvalidFFCode>>()?;
+
+    //(2) Parse the fields of every row straight into the buffers
+    for row in chunk.chunks_exact(chars_in_row) {
+      for (col, buf) in bufs.iter_mut().enumerate() {
+        let ((start, end), fmt, null) = fields.get(col);
+        buf.push_field(&row[start..end], fmt, null, fields.tolerance)?;
+      }
     }
 
-    //(R) return the filled table
-    Ok(Extension::AsciiTable(tbl))
+    //(R) the decoded chunk
+    Ok(bufs)
   }
 
-  fn setup_table(
-    fmts: &Vec<TableEntryFormat>,
-    labels: Option<Vec<String>>,
-    size: usize,
-  ) -> Result<AsciiTable, InvalidFFCode> {
-    //(1) Use the column formats to set-up typed columns
-    let mut cols = Vec::<Box<dyn AsciiCol>>::new();
-    for i in 0..fmts.len() {
-      match &fmts[i] {
-        TableEntryFormat::Char(_) => {
-          let label = match &labels {
-            None => None,
-            Some(vec) => Some(vec[i].clone()),
-          };
-          cols.push(Box::new(Column::<String>::new(label)));
-        }
-        TableEntryFormat::Int(_) => {
-          let label = match &labels {
-            None => None,
-            Some(vec) => Some(vec[i].clone()),
-          };
-          cols.push(Box::new(Column::<i64>::new(label)));
-        }
-        TableEntryFormat::Float(_) => {
-          let label = match &labels {
-            None => None,
-            Some(vec) => Some(vec[i].clone()),
-          };
-          cols.push(Box::new(Column::<f64>::new(label)));
-        }
-        TableEntryFormat::Invalid(invld) => {
-          return Err(InvalidFFCode::new(invld.clone()));
-        }
+  pub(crate) fn encode_layout(tbl: &AsciiTable, header: &mut Header) -> AsciiLayout {
+    /*  Note:
+        The layout of the rows is fixed before the header of the HDU is
+        written, and the NAXIS1, NAXIS2, TFIELDS, TBCOLn and TFORMn keywords
+        of the header are set to match it. The fields are formatted anew, so
+        any CHECKSUM and DATASUM of the HDU no longer apply.
+
+        Note: (some definitions)
+        column WIDTH is the number of ascii characters needed to encode a
+        single entry in the column (w in the Fortran format code).
+        column LENGTH is the number of entries in a column.
+    */
+
+    /*  (1)
+        Columns keep their TFORMn format if it suits the type of the column.
+        Columns without one (or with an unsuitable one) get a format that is
+        wide enough for all their entries.
+    */
+    let formats: Vec<FieldFormat> = tbl
+      .get_tbl_fmt()
+      .iter()
+      .enumerate()
+      .map(|(i, fmt)| {
+        header
+          .get_value(&format!("TFORM{}", i + 1))
+          .and_then(|raw| FieldFormat::parse(&unquote(raw).unwrap_or(raw.clone())))
+          .filter(|format| format.suits(fmt))
+          .unwrap_or_else(|| FieldFormat::of_column(fmt))
+      })
+      .collect();
+
+    /*  (2)
+        Fields stay where their TBCOLn keywords put them, unless a keyword is
+        missing or the fields would overlap. Then all fields are packed,
+        separated by a space (the row is just long enough for them).
+    */
+    let starts: Option<Vec<usize>> = (1..=formats.len())
+      .map(|n| header.get_value_as::<usize>(&format!("TBCOL{n}")).ok()?.checked_sub(1))
+      .collect();
+    let (fields, min_row_len): (Vec<(usize, FieldFormat)>, usize) = match starts {
+      Some(starts) if !Self::overlaps(&starts, &formats) => {
+        (starts.into_iter().zip(formats).collect(), header.get_value_as("NAXIS1").unwrap_or(0))
+      }
+      _ => {
+        let mut start = 0;
+        let fields = formats
+          .into_iter()
+          .map(|format| {
+            start += format.width + 1;
+            (start - format.width - 1, format)
+          })
+          .collect();
+        (fields, 0)
       }
+    };
+    let row_len =
+      fields.iter().map(|(start, format)| start + format.width).fold(min_row_len, usize::max);
+
+    //(3) Describe the layout in the header
+    let (n_cols, n_rows) = tbl.get_shape();
+    let mut records = vec![
+      (String::from("NAXIS1"), row_len.to_string()),
+      (String::from("NAXIS2"), n_rows.to_string()),
+      (String::from("TFIELDS"), n_cols.to_string()),
+    ];
+    for (i, (start, format)) in fields.iter().enumerate() {
+      records.push((format!("TBCOL{}", i + 1), (start + 1).to_string()));
+      records.push((format!("TFORM{}", i + 1), quote(&format.to_tform())));
+    }
+    for (keyword, value) in records {
+      let comment = header.get_comment(&keyword).cloned();
+      header.put_record(&keyword, value, comment);
     }
+    header.remove_record("CHECKSUM");
+    header.remove_record("DATASUM");
 
-    //(R) yeet the columns in an (empty) table
-    Ok(AsciiTable::new_sized(cols, size))
+    //(R) the layout, which encode_tbl needs to write the rows
+    AsciiLayout { row_len, fields }
   }
 
-  fn split_row<'a>(
-    raw: &'a [u8],
-    field_start: &'a Vec<usize>,
-    field_len: &'a Vec<usize>,
-  ) -> Result<Vec<&'a str>, Utf8Error> {
-    let mut result = Vec::<&str>::new();
-    for i in 0..field_start.len() {
-      result.push(str::from_utf8(&raw[field_start[i]..(field_start[i] + field_len[i])])?);
-    }
-    Ok(result)
+  fn overlaps(starts: &[usize], formats: &[FieldFormat]) -> bool {
+    let mut ranges: Vec<(usize, usize)> =
+      starts.iter().zip(formats).map(|(&start, format)| (start, start + format.width)).collect();
+    ranges.sort_unstable();
+    ranges.windows(2).any(|pair| pair[0].1 > pair[1].0)
   }
 
   pub(crate) fn encode_tbl(
     tbl: AsciiTable,
-    writer: &mut RawFitsWriter,
-  ) -> Result<(), Box<dyn Error>> {
+    layout: AsciiLayout,
+    writer: &mut dyn BlockWrite,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
     /*  Note:
-        This parser assumes that certain necessary keywords to decode a HDU
-        containing a table have already been set while encoding the header
-        of the HDU. The values of other table keywords are determined by this
-        func, with the function returning their appropriate values.
-
-        Note:
         This function takes ownership of the table, so we can do with it
         whatever we want without worrying about race conditions.
-
-        Note: (some definitions)
-        column WIDTH is the number of ascii characters needed to encode a
-        single entry in the column.
-        column LENGTH is the number of entries in a column.
     */
 
     /*  (1)
-        We start by getting basic info about the table: how many columns
-        does it have, what are their formats, etc...
+        The table is written in one go, like it is read. Everything that is
+        not part of a field is a space, including the padding of the last
+        block (as the standard requires for ASCII tables).
     */
-    let tbl_fmts = (&tbl).get_tbl_fmt(); //IN ORDER!
-    let tbl_len = (&tbl).max_col_len();
-    let mut cols: Vec<Vec<String>> = tbl.destroy(); //IN ORDER
+    let AsciiLayout { row_len, fields } = layout;
+    let byte_size = row_len * tbl.get_shape().1;
+    let mut whole_table = vec![b' '; byte_size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE];
 
     /*  (2)
-        All columns in a FITS table should have the same length. Therefore,
-        we need to add empty entries to each column that is smaller than the
-        longest column.
+        Rows are formatted in parallel. All columns should have the same
+        length, but the fields of columns that are shorter than the table are
+        left blank. Entries that do not fit in their field are an error (a
+        Fortran program would print asterisks instead).
     */
-    cols.iter_mut().for_each(|col| {
-      while col.len() < tbl_len {
-        col.push(String::from(""));
-      }
-    });
+    if row_len > 0 {
+      whole_table[..byte_size].par_chunks_mut(row_len).enumerate().try_for_each(
+        |(row, raw_row)| {
+          for (col, (start, format)) in fields.iter().enumerate() {
+            let field = match tbl.is_null(col, row) {
+              true => format.justify(tbl.get_col_null(col).unwrap_or("")),
+              false => match tbl.get_entry(col, row) {
+                Ok(entry) => format.format(&entry),
+                Err(_) => continue,
+              },
+            };
+            if field.len() > format.width {
+              return Err(FieldOverflowErr::new((col, row), &format.to_tform(), field.len()));
+            }
+            raw_row[*start..start + field.len()].copy_from_slice(field.as_bytes());
+          }
+          Ok(())
+        },
+      )?;
+    }
 
-    /*  (3)
-        Furthermore, all rows in a single column must take up the same width
-        in ascii characters. This means that we must extend the co
-    */
+    //(R) write the table
+    writer.write_blocks(&whole_table)?;
+    Ok(())
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FieldFormat {
+  //Fortran format code of a field: A, I, F, E or D, with w and d
+  code: char,
+  width: usize,
+  decimals: usize,
+}
+
+impl FieldFormat {
+  fn parse(tform: &str) -> Option<Self> {
+    let tform = tform.trim();
+    let code = tform.chars().next()?.to_ascii_uppercase();
+    let (width, decimals) = match tform.get(1..)?.split_once('.') {
+      Some((width, decimals)) => (width.parse().ok()?, Some(decimals.parse().ok()?)),
+      None => (tform.get(1..)?.parse().ok()?, None),
+    };
+    let decimals = match (code, decimals) {
+      ('A' | 'I', None) => 0,
+      ('F' | 'E' | 'D', Some(decimals)) => decimals,
+      _ => return None,
+    };
+    match width {
+      0 => None,
+      _ => Some(FieldFormat { code, width, decimals }),
+    }
+  }
 
-    todo!()
+  fn of_column(fmt: &TableEntryFormat) -> Self {
+    //Format that fits all entries of a column (see AsciiCol::get_col_fmt)
+    let (code, width, decimals) = match *fmt {
+      TableEntryFormat::Char(width) => ('A', width, 0),
+      TableEntryFormat::Int(width) => ('I', width, 0),
+      //Fortran exponents take up to two more characters (E+05 vs e5)
+      TableEntryFormat::Float((width, decimals)) => ('E', width + 2, decimals),
+      TableEntryFormat::Invalid(ref code) => ('A', code.len(), 0),
+    };
+    FieldFormat { code, width: width.max(1), decimals }
   }
+
+  fn suits(&self, fmt: &TableEntryFormat) -> bool {
+    use TableEntryFormat::*;
+    matches!((self.code, fmt), ('A', Char(_)) | ('I', Int(_)) | ('F' | 'E' | 'D', Float(_)))
+  }
+
+  fn to_tform(self) -> String {
+    match self.code {
+      'A' | 'I' => format!("{}{}", self.code, self.width),
+      code => format!("{code}{}.{}", self.width, self.decimals),
+    }
+  }
+
+  fn justify(&self, text: &str) -> String {
+    //Strings are left-justified in their field, numbers right-justified
+    match self.code {
+      'A' => format!("{text:0$}", self.width),
+      _ => format!("{text:>0$}", self.width),
+    }
+  }
+
+  fn format(&self, entry: &TableEntry) -> String {
+    let decimals = self.decimals;
+    self.justify(&match entry {
+      TableEntry::Text(text) => text.clone(),
+      TableEntry::Int(int) => int.to_string(),
+      TableEntry::Float(float) if float.is_finite() && self.code == 'F' => {
+        format!("{float:.decimals$}")
+      }
+      TableEntry::Float(float) if float.is_finite() => {
+        //Fortran notation: 1.500E+03 rather than 1.500e3
+        let rust = format!("{float:.decimals$e}");
+        let (mantissa, exponent) = rust.split_once('e').unwrap();
+        let exponent: i32 = exponent.parse().unwrap();
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{mantissa}{}{sign}{:02}", self.code, exponent.unsigned_abs())
+      }
+      TableEntry::Float(float) => float.to_string(),
+      _ => unreachable!("ASCII tables only have scalar entries"),
+    })
+  }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct AsciiLayout {
+  //Length of a row and the (0-based) start and format of every field
+  row_len: usize,
+  fields: Vec<(usize, FieldFormat)>,
 }
@@ -16,3 +16,802 @@
     You should have received a copy of the GNU General Public License
     along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
 */
+
+use std::{
+  error::Error,
+  fmt::{self, Display, Formatter},
+  str,
+};
+
+use crate::{
+  extensions::ExtensionPrint,
+  raw::{table_entry_format::TableEntryFormat, BlockSized},
+  tbl_err::{FieldOverflowErr, IndexOutOfRangeErr, ShapeMisMatchErr},
+  tbl_fmt_err::InvalidFFCode,
+};
+
+use super::{
+  bin_tbl_parser::BinField,
+  column::{AsciiCol, Column},
+  fits_row, AsciiTable, FitsRow, TableEntry, Validity,
+};
+
+/*  Description:
+    Binary tables (XTENSION = 'BINTABLE') store every field in its binary
+    (big endian) representation. A field holds a fixed number of elements of
+    one type, given by the TFORMn code of its column (rT, e.g. 16E for an
+    array of 16 floats), or a descriptor of a variable-length array that is
+    stored in the heap after the table (rPt(emax) or rQt(emax)).
+
+    BinTable gives access to the entries in the same way AsciiTable does.
+    Fields with a single element (and no descriptor) are returned as scalar
+    entries, all others as TableEntry::Array. Logicals are returned as T/F
+    text, like TableEntry::from(bool), and bits as integers. TSCALn and TZEROn
+    are applied when an entry is read, so unsigned integers stored with the
+    usual offsets (TZEROn = 32768 etc.) come out as plain integers.
+
+    Only columns with scalar fields have a validity bitmap. Their entries are
+    null if they match TNULLn (integers), are NaN (reals) or are 0 (logicals).
+
+    Tile-compressed tables (ZTABLE = T) are binary tables as well: every
+    column of a tile of rows is stored as one compressed variable-length
+    array in the heap (ZCTYPn gives the algorithm, ZFORMn, ZTILELEN and
+    ZNAXISn the original layout). They are decompressed when they are read,
+    see compressed_table.rs, and written back uncompressed.
+
+    Conversions between the two table representations live here as well:
+      - AsciiTable::to_bintable(), which maps Aw/Iw/Fw.d/Ew.d/Dw.d columns to
+        wA/K/D columns (ASCII tables hold i64 and f64 entries)
+      - BinTable::to_ascii(tforms), with the TFORMn of every column given by
+        the caller
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinType {
+  /*  THIS ENUM IS PART OF THE USER-FACING API
+      Element types of binary table columns (the t in a TFORMn code)
+  */
+  Logical,       //L
+  Bit,           //X
+  Byte,          //B (unsigned)
+  Short,         //I
+  Int,           //J
+  Long,          //K
+  Char,          //A
+  Float,         //E
+  Double,        //D
+  Complex,       //C (pair of floats)
+  DoubleComplex, //M (pair of doubles)
+}
+
+impl BinType {
+  pub fn from_code(code: char) -> Option<Self> {
+    use BinType::*;
+    Some(match code {
+      'L' => Logical,
+      'X' => Bit,
+      'B' => Byte,
+      'I' => Short,
+      'J' => Int,
+      'K' => Long,
+      'A' => Char,
+      'E' => Float,
+      'D' => Double,
+      'C' => Complex,
+      'M' => DoubleComplex,
+      _ => return None,
+    })
+  }
+
+  pub fn get_code(&self) -> char {
+    use BinType::*;
+    match self {
+      Logical => 'L',
+      Bit => 'X',
+      Byte => 'B',
+      Short => 'I',
+      Int => 'J',
+      Long => 'K',
+      Char => 'A',
+      Float => 'E',
+      Double => 'D',
+      Complex => 'C',
+      DoubleComplex => 'M',
+    }
+  }
+
+  pub(crate) fn unit_size(&self) -> usize {
+    //Bytes per stored unit (a byte of 8 bits for X, a pair for C and M)
+    use BinType::*;
+    match self {
+      Logical | Bit | Byte | Char => 1,
+      Short => 2,
+      Int | Float => 4,
+      Long | Double | Complex => 8,
+      DoubleComplex => 16,
+    }
+  }
+
+  pub(crate) fn units(&self, n_elements: usize) -> usize {
+    //Number of stored units that hold n elements
+    match self {
+      BinType::Bit => n_elements.div_ceil(8),
+      _ => n_elements,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Descriptor {
+  //Descriptors of variable-length arrays, with 32 (P) or 64 bit (Q) integers
+  P,
+  Q,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BinFormat {
+  /*  THIS STRUCT IS PART OF THE USER-FACING API
+      Parsed TFORMn code of a binary table column: rT for fixed-length fields
+      and rPt(emax) or rQt(emax) for variable-length arrays (where r is 0 or 1)
+  */
+  repeat: usize,
+  kind: BinType,
+  descriptor: Option<Descriptor>,
+  max_len: Option<usize>,
+}
+
+impl BinFormat {
+  pub fn parse(tform: &str) -> Result<Self, InvalidFFCode> {
+    let err = || InvalidFFCode::new(tform.to_string());
+
+    //(1) Optional repeat count, which defaults to 1
+    let code = tform.trim();
+    let digits = code.find(|c: char| !c.is_ascii_digit()).ok_or_else(err)?;
+    let repeat = match digits {
+      0 => 1,
+      _ => code[..digits].parse().map_err(|_| err())?,
+    };
+
+    //(2) Element type, or a descriptor followed by the element type
+    let mut chars = code[digits..].chars();
+    let descriptor = match chars.next() {
+      Some('P') => Some(Descriptor::P),
+      Some('Q') => Some(Descriptor::Q),
+      _ => None,
+    };
+    let kind = match descriptor {
+      Some(_) => chars.next(),
+      None => code[digits..].chars().next(),
+    };
+    let kind = kind.and_then(BinType::from_code).ok_or_else(err)?;
+    if descriptor.is_some() && repeat > 1 {
+      return Err(err());
+    }
+    if Self::field_width(repeat, kind, descriptor).is_none() {
+      return Err(err()); //the field would not fit in memory
+    }
+
+    //(3) Variable-length arrays may give their maximum length as (emax).
+    //    Anything else after the type is ignored, as the standard allows
+    let rest = chars.as_str().trim();
+    let max_len = match (descriptor, rest.strip_prefix('(')) {
+      (Some(_), Some(rest)) => {
+        Some(rest.strip_suffix(')').and_then(|emax| emax.trim().parse().ok()).ok_or_else(err)?)
+      }
+      _ => None,
+    };
+    Ok(BinFormat { repeat, kind, descriptor, max_len })
+  }
+
+  pub fn get_repeat(&self) -> usize {
+    self.repeat
+  }
+  pub fn get_type(&self) -> BinType {
+    self.kind
+  }
+  pub fn is_variable(&self) -> bool {
+    self.descriptor.is_some()
+  }
+  pub fn get_max_len(&self) -> Option<usize> {
+    self.max_len
+  }
+
+  pub(crate) fn get_descriptor(&self) -> Option<Descriptor> {
+    self.descriptor
+  }
+
+  pub(crate) fn is_scalar(&self) -> bool {
+    self.repeat == 1 && self.descriptor.is_none()
+  }
+
+  pub(crate) fn get_field_width(&self) -> usize {
+    //Bytes that a field of this format takes up in a row. Formats are parsed,
+    //and parse refuses widths that overflow
+    Self::field_width(self.repeat, self.kind, self.descriptor).unwrap_or(usize::MAX)
+  }
+
+  fn field_width(repeat: usize, kind: BinType, descriptor: Option<Descriptor>) -> Option<usize> {
+    match descriptor {
+      Some(Descriptor::P) => repeat.checked_mul(8),
+      Some(Descriptor::Q) => repeat.checked_mul(16),
+      None => kind.units(repeat).checked_mul(kind.unit_size()),
+    }
+  }
+}
+
+impl Display for BinFormat {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    let code = self.kind.get_code();
+    match (self.descriptor, self.max_len) {
+      (None, _) => write!(f, "{}{code}", self.repeat),
+      (Some(desc), Some(max)) => write!(f, "{}{desc:?}{code}({max})", self.repeat),
+      (Some(desc), None) => write!(f, "{}{desc:?}{code}", self.repeat),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum BinData {
+  /*
+      Elements of a column, in row order. Logicals, bits, bytes and characters
+      are kept as raw bytes, all other types are decoded to native numbers.
+  */
+  Bytes(Vec<u8>),
+  Short(Vec<i16>),
+  Int(Vec<i32>),
+  Long(Vec<i64>),
+  Float(Vec<f32>),
+  Double(Vec<f64>),
+  Complex(Vec<(f32, f32)>),
+  DoubleComplex(Vec<(f64, f64)>),
+}
+
+macro_rules! extend_be {
+  ($buf:expr, $bytes:expr, $t:ty) => {
+    $buf.extend(
+      $bytes
+        .chunks_exact(std::mem::size_of::<$t>())
+        .map(|b| <$t>::from_be_bytes(b.try_into().unwrap())),
+    )
+  };
+}
+
+macro_rules! write_be {
+  ($values:expr, $out:expr, $size:expr) => {
+    for (value, chunk) in $values.iter().zip($out.chunks_exact_mut($size)) {
+      chunk.copy_from_slice(&value.to_be_bytes())
+    }
+  };
+}
+
+impl BinData {
+  pub(crate) fn with_capacity(kind: BinType, units: usize) -> Self {
+    use BinType::*;
+    match kind {
+      Logical | Bit | Byte | Char => BinData::Bytes(Vec::with_capacity(units)),
+      Short => BinData::Short(Vec::with_capacity(units)),
+      Int => BinData::Int(Vec::with_capacity(units)),
+      Long => BinData::Long(Vec::with_capacity(units)),
+      Float => BinData::Float(Vec::with_capacity(units)),
+      Double => BinData::Double(Vec::with_capacity(units)),
+      Complex => BinData::Complex(Vec::with_capacity(units)),
+      DoubleComplex => BinData::DoubleComplex(Vec::with_capacity(units)),
+    }
+  }
+
+  pub(crate) fn extend_from_be(&mut self, bytes: &[u8]) {
+    //Appends the big endian units in bytes
+    match self {
+      BinData::Bytes(buf) => buf.extend_from_slice(bytes),
+      BinData::Short(buf) => extend_be!(buf, bytes, i16),
+      BinData::Int(buf) => extend_be!(buf, bytes, i32),
+      BinData::Long(buf) => extend_be!(buf, bytes, i64),
+      BinData::Float(buf) => extend_be!(buf, bytes, f32),
+      BinData::Double(buf) => extend_be!(buf, bytes, f64),
+      BinData::Complex(buf) => buf.extend(bytes.chunks_exact(8).map(|b| {
+        (
+          f32::from_be_bytes(b[..4].try_into().unwrap()),
+          f32::from_be_bytes(b[4..].try_into().unwrap()),
+        )
+      })),
+      BinData::DoubleComplex(buf) => buf.extend(bytes.chunks_exact(16).map(|b| {
+        (
+          f64::from_be_bytes(b[..8].try_into().unwrap()),
+          f64::from_be_bytes(b[8..].try_into().unwrap()),
+        )
+      })),
+    }
+  }
+
+  pub(crate) fn write_be(&self, start: usize, units: usize, out: &mut [u8]) {
+    //Writes units starting at start to out, as big endian bytes
+    let range = start..start + units;
+    match self {
+      BinData::Bytes(buf) => out.copy_from_slice(&buf[range]),
+      BinData::Short(buf) => write_be!(buf[range], out, 2),
+      BinData::Int(buf) => write_be!(buf[range], out, 4),
+      BinData::Long(buf) => write_be!(buf[range], out, 8),
+      BinData::Float(buf) => write_be!(buf[range], out, 4),
+      BinData::Double(buf) => write_be!(buf[range], out, 8),
+      BinData::Complex(buf) => {
+        for ((re, im), chunk) in buf[range].iter().zip(out.chunks_exact_mut(8)) {
+          chunk[..4].copy_from_slice(&re.to_be_bytes());
+          chunk[4..].copy_from_slice(&im.to_be_bytes());
+        }
+      }
+      BinData::DoubleComplex(buf) => {
+        for ((re, im), chunk) in buf[range].iter().zip(out.chunks_exact_mut(16)) {
+          chunk[..8].copy_from_slice(&re.to_be_bytes());
+          chunk[8..].copy_from_slice(&im.to_be_bytes());
+        }
+      }
+    }
+  }
+
+  pub(crate) fn len(&self) -> usize {
+    match self {
+      BinData::Bytes(buf) => buf.len(),
+      BinData::Short(buf) => buf.len(),
+      BinData::Int(buf) => buf.len(),
+      BinData::Long(buf) => buf.len(),
+      BinData::Float(buf) => buf.len(),
+      BinData::Double(buf) => buf.len(),
+      BinData::Complex(buf) => buf.len(),
+      BinData::DoubleComplex(buf) => buf.len(),
+    }
+  }
+
+  pub(crate) fn is_null(&self, kind: BinType, index: usize, tnull: Option<i64>) -> bool {
+    //Whether the (scalar) element at index is null
+    let matches = |value: i64| tnull == Some(value);
+    match self {
+      BinData::Bytes(buf) if kind == BinType::Logical => buf[index] == 0,
+      BinData::Bytes(buf) if kind == BinType::Byte => matches(buf[index] as i64),
+      BinData::Bytes(_) => false,
+      BinData::Short(buf) => matches(buf[index] as i64),
+      BinData::Int(buf) => matches(buf[index] as i64),
+      BinData::Long(buf) => matches(buf[index]),
+      BinData::Float(buf) => buf[index].is_nan(),
+      BinData::Double(buf) => buf[index].is_nan(),
+      BinData::Complex(buf) => buf[index].0.is_nan() || buf[index].1.is_nan(),
+      BinData::DoubleComplex(buf) => buf[index].0.is_nan() || buf[index].1.is_nan(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HeapArray {
+  //Variable-length array of a row: first unit in the column data, number of
+  //elements and the offset of the array in the heap
+  pub(crate) start: usize,
+  pub(crate) len: usize,
+  pub(crate) offset: usize,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct BinColumn {
+  pub(crate) field: BinField,
+  pub(crate) data: BinData,
+  pub(crate) heap: Vec<HeapArray>, //only for variable-length arrays
+  pub(crate) validity: Option<Validity>,
+}
+
+impl BinColumn {
+  pub(crate) fn row_span(&self, row: usize) -> (usize, usize) {
+    //First unit and number of elements of a row
+    let format = &self.field.format;
+    match format.is_variable() {
+      true => self.heap.get(row).map_or((0, 0), |array| (array.start, array.len)),
+      false => (row * format.get_type().units(format.get_repeat()), format.get_repeat()),
+    }
+  }
+
+  fn int_entry(&self, value: i64) -> TableEntry {
+    //Integers with an integral offset stay integers, other scalings do not
+    match self.field.scaling {
+      None => TableEntry::Int(value),
+      Some((1.0, zero)) if zero.fract() == 0.0 && zero.abs() < i64::MAX as f64 => {
+        match value.checked_add(zero as i64) {
+          Some(value) => TableEntry::Int(value),
+          None => TableEntry::Float(value as f64 + zero),
+        }
+      }
+      Some((scale, zero)) => TableEntry::Float(zero + scale * value as f64),
+    }
+  }
+
+  fn float_entry(&self, value: f64) -> TableEntry {
+    match self.field.scaling {
+      None => TableEntry::Float(value),
+      Some((scale, zero)) => TableEntry::Float(zero + scale * value),
+    }
+  }
+
+  fn complex_entry(&self, re: f64, im: f64) -> TableEntry {
+    //The offset only applies to the real part
+    match self.field.scaling {
+      None => TableEntry::Complex(re, im),
+      Some((scale, zero)) => TableEntry::Complex(zero + scale * re, scale * im),
+    }
+  }
+
+  fn element(&self, start: usize, index: usize) -> TableEntry {
+    //Entry of a single (non-character) element
+    let kind = self.field.format.get_type();
+    match &self.data {
+      BinData::Bytes(buf) if kind == BinType::Bit => {
+        TableEntry::Int(((buf[start + index / 8] >> (7 - index % 8)) & 1) as i64)
+      }
+      BinData::Bytes(buf) if kind == BinType::Logical => match buf[start + index] {
+        b'T' => TableEntry::from(true),
+        b'F' => TableEntry::from(false),
+        _ => TableEntry::txt(),
+      },
+      BinData::Bytes(buf) => self.int_entry(buf[start + index] as i64),
+      BinData::Short(buf) => self.int_entry(buf[start + index] as i64),
+      BinData::Int(buf) => self.int_entry(buf[start + index] as i64),
+      BinData::Long(buf) => self.int_entry(buf[start + index]),
+      BinData::Float(buf) => self.float_entry(buf[start + index] as f64),
+      BinData::Double(buf) => self.float_entry(buf[start + index]),
+      BinData::Complex(buf) => {
+        self.complex_entry(buf[start + index].0 as f64, buf[start + index].1 as f64)
+      }
+      BinData::DoubleComplex(buf) => self.complex_entry(buf[start + index].0, buf[start + index].1),
+    }
+  }
+
+  fn get_str(&self, row: usize) -> Option<&str> {
+    //Strings end at the first NUL, trailing blanks are not significant
+    let (BinType::Char, BinData::Bytes(buf)) = (self.field.format.get_type(), &self.data) else {
+      return None;
+    };
+    let (start, len) = self.row_span(row);
+    let raw = &buf[start..start + len];
+    let raw = raw.iter().position(|&byte| byte == 0).map_or(raw, |end| &raw[..end]);
+    str::from_utf8(raw).ok().map(str::trim_end)
+  }
+
+  fn get_entry(&self, row: usize) -> TableEntry {
+    if let Some(text) = self.get_str(row) {
+      return TableEntry::from(text);
+    }
+    let (start, len) = self.row_span(row);
+    match self.field.format.get_type() {
+      //Invalid UTF-8 is replaced, rather than refused
+      BinType::Char => match &self.data {
+        BinData::Bytes(buf) => {
+          TableEntry::from(String::from_utf8_lossy(&buf[start..start + len]).trim_end())
+        }
+        _ => unreachable!("character columns are stored as bytes"),
+      },
+      _ if self.field.format.is_scalar() => self.element(start, 0),
+      _ => TableEntry::Array((0..len).map(|index| self.element(start, index)).collect()),
+    }
+  }
+
+  fn get_memory_usage(&self) -> usize {
+    let kind = self.field.format.get_type();
+    let validity = self.validity.as_ref().map_or(0, |validity| validity.as_bytes().len());
+    self.data.len() * kind.unit_size()
+      + self.heap.len() * std::mem::size_of::<HeapArray>()
+      + validity
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct BinTable {
+  cols: Vec<BinColumn>,
+  n_rows: usize,
+  row_len: usize,    //NAXIS1
+  heap_start: usize, //THEAP
+  heap_len: usize,   //PCOUNT (including the gap between table and heap)
+  block_size: usize,
+}
+
+impl BlockSized for BinTable {
+  fn get_block_len(&self) -> usize {
+    self.block_size
+  }
+}
+
+impl Display for BinTable {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    writeln!(
+      f,
+      ">=============================<|FITS Binary Table|>============================="
+    )?;
+    writeln!(f, ">Table Layout:")?;
+    for (index, col) in self.cols.iter().enumerate() {
+      let label = col.field.label.as_deref().unwrap_or("(unlabeled)");
+      writeln!(f, ">  col#{index:03} - {label} [{}]", col.field.format)?
+    }
+    writeln!(
+      f,
+      ">==============================================================================="
+    )?;
+    Ok(())
+  }
+}
+
+impl ExtensionPrint for BinTable {
+  fn xprint(&self) -> String {
+    format!(
+      "(BINTABLE) - #columns: {}, #rows: {}, size: {}",
+      self.cols.len(),
+      self.n_rows,
+      self.get_block_len()
+    )
+  }
+}
+
+impl BinTable {
+  /*
+      PUBLIC API
+  */
+
+  pub fn get_entry(&self, col: usize, row: usize) -> Result<TableEntry, IndexOutOfRangeErr> {
+    //returns an entry in the table, if it exists
+    match self.cols.get(col) {
+      Some(column) if row < self.n_rows => Ok(column.get_entry(row)),
+      _ => Err(IndexOutOfRangeErr::from_shape((col, row), self.get_shape())),
+    }
+  }
+
+  pub fn get_shape(&self) -> (usize, usize) {
+    //returns shape (columns, rows) of table
+    (self.cols.len(), self.n_rows)
+  }
+
+  pub fn get_col_label(&self, col: usize) -> Option<&str> {
+    self.cols.get(col)?.field.label.as_deref()
+  }
+
+  pub fn get_col_unit(&self, col: usize) -> Option<&str> {
+    //TUNITn of the column
+    self.cols.get(col)?.field.unit.as_deref()
+  }
+
+  pub fn get_col_format(&self, col: usize) -> Option<BinFormat> {
+    Some(self.cols.get(col)?.field.format)
+  }
+
+  pub fn get_col_dims(&self, col: usize) -> Option<&[usize]> {
+    //TDIMn of the column, the shape of the array in each field
+    self.cols.get(col)?.field.dims.as_deref()
+  }
+
+  pub fn get_col_index(&self, label: &str) -> Option<usize> {
+    //Column labels are compared case-insensitively, as in the standard
+    self.cols.iter().position(|col| {
+      col.field.label.as_deref().is_some_and(|name| name.eq_ignore_ascii_case(label.trim()))
+    })
+  }
+
+  pub fn rows_as<R: FitsRow>(&self) -> Result<Vec<R>, Box<dyn Error + Send + Sync>> {
+    //Rows as structs, see fits_row.rs
+    fits_row::read_rows(
+      self.n_rows,
+      |label| self.get_col_index(label),
+      |col, row| self.get_entry(col, row),
+    )
+  }
+
+  /*
+      Null entries keep the value they were stored with, so use the validity
+      bitmap of a column to tell them apart from actual values. Only columns
+      with scalar fields (and nulls) have a bitmap.
+  */
+  pub fn get_col_validity(&self, col: usize) -> Option<&Validity> {
+    self.cols.get(col)?.validity.as_ref()
+  }
+
+  pub fn get_col_null(&self, col: usize) -> Option<i64> {
+    //TNULLn of an integer column
+    self.cols.get(col)?.field.null
+  }
+
+  pub fn is_null(&self, col: usize, row: usize) -> bool {
+    self.get_col_validity(col).is_some_and(|validity| validity.is_null(row))
+  }
+
+  pub fn get_str(&self, col: usize, row: usize) -> Option<&str> {
+    //Entry of a character column, without copying it
+    match row < self.n_rows {
+      true => self.cols.get(col)?.get_str(row),
+      false => None,
+    }
+  }
+
+  pub fn get_heap_len(&self) -> usize {
+    //Size of the heap (and the gap in front of it) in bytes, see PCOUNT
+    self.heap_len
+  }
+
+  pub fn to_ascii(&self, tforms: &[&str]) -> Result<AsciiTable, Box<dyn Error + Send + Sync>> {
+    /*  Converts the table to an ASCII table, with the format of every column
+        (Aw, Iw, Fw.d, Ew.d or Dw.d) given by the caller. Only character
+        columns and columns with scalar fields can be converted. Text and
+        integers have to fit in the width of their format. Nulls stay null,
+        integer columns keep their TNULLn.
+    */
+    //(1) Every column needs a format
+    let (n_cols, n_rows) = self.get_shape();
+    if tforms.len() != n_cols {
+      return Err(Box::new(ShapeMisMatchErr::from_len(tforms.len(), n_cols)));
+    }
+
+    //(2) Convert the columns one by one
+    let mut cols: Vec<Box<dyn AsciiCol>> = Vec::with_capacity(n_cols);
+    for (col, &tform) in tforms.iter().enumerate() {
+      let label = self.get_col_label(col).map(str::to_string);
+      let fits = |row: usize, got: usize, width: usize| match got > width {
+        true => Err(FieldOverflowErr::new((col, row), tform, got)),
+        false => Ok(()),
+      };
+      let mut validity = Validity::with_capacity(n_rows);
+      let format = match tform.trim().is_empty() {
+        true => None,
+        false => TableEntryFormat::from_fortran_format_code(tform).ok(),
+      };
+      let column: Box<dyn AsciiCol> = match format {
+        Some(TableEntryFormat::Char(width)) => {
+          let mut texts = Vec::with_capacity(n_rows);
+          for row in 0..n_rows {
+            let txt = String::try_from(self.get_entry(col, row)?)?;
+            fits(row, txt.chars().count(), width)?;
+            texts.push(format!("{txt:width$}"));
+            validity.push(true);
+          }
+          Box::new(Column::with_nulls(label, texts, validity, None))
+        }
+        Some(TableEntryFormat::Int(width)) => {
+          let mut values = Vec::with_capacity(n_rows);
+          for row in 0..n_rows {
+            let null = self.is_null(col, row);
+            let value = match null {
+              true => 0,
+              false => i64::try_from(self.get_entry(col, row)?)?,
+            };
+            fits(row, value.to_string().len(), width)?;
+            values.push(value);
+            validity.push(!null);
+          }
+          let null = self.get_col_null(col).map(|null| null.to_string());
+          Box::new(Column::with_nulls(label, values, validity, null))
+        }
+        Some(TableEntryFormat::Float(_)) => {
+          let mut values = Vec::with_capacity(n_rows);
+          for row in 0..n_rows {
+            let null = self.is_null(col, row);
+            values.push(match null {
+              true => f64::NAN,
+              false => f64::try_from(self.get_entry(col, row)?)?,
+            });
+            validity.push(!null);
+          }
+          Box::new(Column::with_nulls(label, values, validity, None))
+        }
+        _ => return Err(Box::new(InvalidFFCode::new(tform.to_string()))),
+      };
+      cols.push(column);
+    }
+
+    //(R) the converted table, its block size is calculated on demand
+    Ok(AsciiTable::new_unsized(cols))
+  }
+
+  /*
+      INTERNAL FUNCS
+  */
+
+  pub(crate) fn new_sized(
+    cols: Vec<BinColumn>,
+    n_rows: usize,
+    row_len: usize,
+    heap: (usize, usize),
+    size: usize,
+  ) -> Self {
+    BinTable { cols, n_rows, row_len, heap_start: heap.0, heap_len: heap.1, block_size: size }
+  }
+
+  pub(crate) fn get_cols(&self) -> &[BinColumn] {
+    &self.cols
+  }
+
+  pub(crate) fn get_row_len(&self) -> usize {
+    self.row_len
+  }
+
+  pub(crate) fn get_heap_start(&self) -> usize {
+    self.heap_start
+  }
+
+  pub(crate) fn get_memory_usage(&self) -> usize {
+    self.cols.iter().map(|col| col.get_memory_usage()).sum()
+  }
+}
+
+impl AsciiTable {
+  pub fn to_bintable(&self) -> Result<BinTable, Box<dyn Error + Send + Sync>> {
+    /*  Converts the table to a binary table. Text columns become wA columns
+        as wide as their widest entry, integers 64-bit (K) and floats double
+        precision (D) columns. Nulls in numeric columns stay null, integer
+        columns get a TNULLn for them. Binary tables cannot mark text as null,
+        so null text becomes an empty string.
+    */
+    let (n_cols, n_rows) = self.get_shape();
+    let mut cols = Vec::with_capacity(n_cols);
+    for col in 0..n_cols {
+      //(1) Collect the entries in a field of the matching type
+      let is_null = |row: usize| self.is_null(col, row);
+      let entries = (0..n_rows).map(|row| self.get_entry(col, row));
+      let (tform, data, null) = match self.get_col_fmt(col) {
+        Some(TableEntryFormat::Char(width)) => {
+          let mut bytes = Vec::with_capacity(n_rows * width);
+          for (row, entry) in entries.enumerate() {
+            let mut field = match is_null(row) {
+              true => Vec::new(),
+              false => String::try_from(entry?)?.into_bytes(),
+            };
+            field.resize(width, b' ');
+            bytes.extend(field);
+          }
+          (format!("{width}A"), BinData::Bytes(bytes), None)
+        }
+        Some(TableEntryFormat::Int(_)) => {
+          let mut values = Vec::with_capacity(n_rows);
+          for entry in entries {
+            values.push(i64::try_from(entry?)?);
+          }
+          //Nulls are stored as the TNULLn of the ASCII column if that is an
+          //integer that no valid entry has
+          let tnull = self
+            .get_col_null(col)
+            .and_then(|null| null.trim().parse::<i64>().ok())
+            .filter(|tnull| !(0..n_rows).any(|row| !is_null(row) && values[row] == *tnull))
+            .unwrap_or(i64::MIN);
+          for (row, value) in values.iter_mut().enumerate() {
+            if is_null(row) {
+              *value = tnull;
+            }
+          }
+          let null = self.get_col_validity(col).map(|_| tnull);
+          (String::from("K"), BinData::Long(values), null)
+        }
+        Some(TableEntryFormat::Float(_)) => {
+          let mut values = Vec::with_capacity(n_rows);
+          for (row, entry) in entries.enumerate() {
+            values.push(match is_null(row) {
+              true => f64::NAN,
+              false => f64::try_from(entry?)?,
+            });
+          }
+          (String::from("D"), BinData::Double(values), None)
+        }
+        _ => unreachable!("ASCII table columns hold text, integers or floats"),
+      };
+
+      //(2) Scalar fields get a validity bitmap, like they do when decoded
+      let format = BinFormat::parse(&tform)?;
+      let validity = format
+        .is_scalar()
+        .then(|| {
+          let mut validity = Validity::with_capacity(n_rows);
+          for row in 0..n_rows {
+            validity.push(!data.is_null(format.get_type(), row, null));
+          }
+          validity
+        })
+        .filter(|validity| validity.null_count() > 0);
+      let label = self.get_col_label(col).map(str::to_string);
+      let field = BinField { format, label, unit: None, null, scaling: None, dims: None };
+      cols.push(BinColumn { field, data, heap: Vec::new(), validity });
+    }
+
+    //(R) the converted table, without a heap
+    let row_len: usize = cols.iter().map(|col| col.field.format.get_field_width()).sum();
+    let byte_size = row_len * n_rows;
+    let size = byte_size.div_ceil(crate::BLOCK_SIZE);
+    Ok(BinTable::new_sized(cols, n_rows, row_len, (byte_size, 0), size))
+  }
+}
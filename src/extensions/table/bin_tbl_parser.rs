@@ -15,4 +15,221 @@
 
     You should have received a copy of the GNU General Public License
     along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
-*/
\ No newline at end of file
+*/
+
+//Get block size from root
+const BLOCK_SIZE: usize = crate::BLOCK_SIZE; // = 2880B
+
+use std::error::Error;
+
+use rayon::prelude::*;
+
+use crate::{
+  extensions::Extension,
+  raw::block_io::{BlockRead, BlockWrite},
+  tbl_err::{DescriptorOverflowErr, HeapRangeErr, RowLayoutErr},
+};
+
+use super::{
+  bin_table::{BinColumn, BinData, BinFormat, Descriptor, HeapArray},
+  BinTable, Validity,
+};
+
+#[derive(Debug, Clone)]
+pub(crate) struct BinField {
+  //Description of a column, from its TFORMn, TTYPEn, TUNITn etc. keywords
+  pub(crate) format: BinFormat,
+  pub(crate) label: Option<String>,
+  pub(crate) unit: Option<String>,
+  pub(crate) null: Option<i64>,
+  pub(crate) scaling: Option<(f64, f64)>, //TSCALn and TZEROn, unless 1 and 0
+  pub(crate) dims: Option<Vec<usize>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BinLayout {
+  pub(crate) row_len: usize,    //NAXIS1, #bytes in a row
+  pub(crate) n_rows: usize,     //NAXIS2
+  pub(crate) heap_start: usize, //THEAP, offset of the heap in the data unit
+  pub(crate) heap_len: usize,   //PCOUNT, #bytes after the rows
+}
+
+pub struct BinTblParser {}
+impl BinTblParser {
+  pub(crate) fn decode_tbl(
+    reader: &mut dyn BlockRead,
+    layout: BinLayout,
+    fields: Vec<BinField>,
+  ) -> Result<Extension, Box<dyn Error + Send + Sync>> {
+    //(1) The fields have to fill the rows exactly
+    let fields_len = fields
+      .iter()
+      .try_fold(0usize, |len, field| len.checked_add(field.format.get_field_width()))
+      .unwrap_or(usize::MAX);
+    if fields_len != layout.row_len {
+      return Err(Box::new(RowLayoutErr::new(layout.row_len, fields_len)));
+    }
+
+    //(2) Read the rows and the heap in one go
+    let byte_size = layout.row_len * layout.n_rows + layout.heap_len;
+    let num_blocks = byte_size.div_ceil(BLOCK_SIZE);
+    let mut whole_table = vec![0u8; num_blocks * BLOCK_SIZE];
+    reader.read_blocks(&mut whole_table)?;
+    let raw = &whole_table[..byte_size];
+
+    /*  (3)
+        Every column is decoded by a single rayon task, which picks its field
+        out of every row. Variable-length arrays are copied from the heap into
+        the column, so the column data always holds all of its elements.
+    */
+    let starts: Vec<usize> = fields
+      .iter()
+      .scan(0, |start, field| {
+        *start += field.format.get_field_width();
+        Some(*start - field.format.get_field_width())
+      })
+      .collect();
+    let cols = fields
+      .into_par_iter()
+      .zip(starts)
+      .enumerate()
+      .map(|(col, (field, start))| Self::decode_col(raw, &layout, (col, start), field))
+      .collect::<Result<Vec<BinColumn>, Box<dyn Error + Send + Sync>>>()?;
+
+    //(R) return the filled table
+    let heap = (layout.heap_start, layout.heap_len);
+    let tbl = BinTable::new_sized(cols, layout.n_rows, layout.row_len, heap, num_blocks);
+    Ok(Extension::BinTable(tbl))
+  }
+
+  fn decode_col(
+    raw: &[u8],
+    layout: &BinLayout,
+    (col, start): (usize, usize), //index of the column and its offset in a row
+    field: BinField,
+  ) -> Result<BinColumn, Box<dyn Error + Send + Sync>> {
+    let format = field.format;
+    let (kind, width) = (format.get_type(), format.get_field_width());
+    let capacity = match format.is_variable() {
+      true => Some(0),
+      false => layout.n_rows.checked_mul(kind.units(format.get_repeat())),
+    };
+    let capacity = capacity.ok_or_else(|| RowLayoutErr::new(layout.row_len, width))?;
+    let mut data = BinData::with_capacity(kind, capacity);
+    let mut heap = Vec::new();
+    let mut validity = format.is_scalar().then(|| Validity::with_capacity(layout.n_rows));
+
+    for row in 0..layout.n_rows {
+      let field_start = row * layout.row_len + start;
+      let bytes = &raw[field_start..field_start + width];
+      match format.get_descriptor() {
+        None => data.extend_from_be(bytes),
+        Some(descriptor) => {
+          //The array has to lie within the heap
+          let (len, offset) = Self::read_descriptor(descriptor, bytes);
+          let heap_len = raw.len().saturating_sub(layout.heap_start);
+          let n_bytes = kind.units(len).checked_mul(kind.unit_size());
+          let end = n_bytes.and_then(|n_bytes| offset.checked_add(n_bytes));
+          let Some(end) = end.filter(|&end| end <= heap_len) else {
+            let range = (offset, end.unwrap_or(usize::MAX));
+            return Err(Box::new(HeapRangeErr::new((col, row), range, heap_len)));
+          };
+          heap.push(HeapArray { start: data.len(), len, offset });
+          data.extend_from_be(&raw[layout.heap_start + offset..layout.heap_start + end]);
+        }
+      }
+      if let Some(validity) = &mut validity {
+        validity.push(!data.is_null(kind, data.len() - 1, field.null));
+      }
+    }
+
+    let validity = validity.filter(|validity| validity.null_count() > 0);
+    Ok(BinColumn { field, data, heap, validity })
+  }
+
+  fn read_descriptor(descriptor: Descriptor, bytes: &[u8]) -> (usize, usize) {
+    //Number of elements and heap offset. Negative values cannot be valid, so
+    //they are made too large to fit in the heap
+    let to_usize = |value: i64| usize::try_from(value).unwrap_or(usize::MAX);
+    match (descriptor, bytes.len()) {
+      (_, 0) => (0, 0), //0P: no array at all
+      (Descriptor::P, _) => (
+        to_usize(i32::from_be_bytes(bytes[..4].try_into().unwrap()) as i64),
+        to_usize(i32::from_be_bytes(bytes[4..8].try_into().unwrap()) as i64),
+      ),
+      (Descriptor::Q, _) => (
+        to_usize(i64::from_be_bytes(bytes[..8].try_into().unwrap())),
+        to_usize(i64::from_be_bytes(bytes[8..16].try_into().unwrap())),
+      ),
+    }
+  }
+
+  fn write_descriptor(
+    descriptor: Descriptor,
+    array: &HeapArray,
+    field: &mut [u8],
+    index: (usize, usize),
+  ) -> Result<(), DescriptorOverflowErr> {
+    //Arrays that do not fit in a P descriptor are refused rather than truncated
+    let overflow = || DescriptorOverflowErr::new(index, array.len, array.offset);
+    match (descriptor, field.len()) {
+      (_, 0) => {}
+      (Descriptor::P, _) => {
+        let len = i32::try_from(array.len).map_err(|_| overflow())?;
+        let offset = i32::try_from(array.offset).map_err(|_| overflow())?;
+        field[..4].copy_from_slice(&len.to_be_bytes());
+        field[4..8].copy_from_slice(&offset.to_be_bytes());
+      }
+      (Descriptor::Q, _) => {
+        field[..8].copy_from_slice(&(array.len as i64).to_be_bytes());
+        field[8..16].copy_from_slice(&(array.offset as i64).to_be_bytes());
+      }
+    }
+    Ok(())
+  }
+
+  pub(crate) fn encode_tbl(
+    tbl: BinTable,
+    writer: &mut dyn BlockWrite,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    /*  Note:
+        Binary tables cannot be modified, so the header of the HDU still
+        describes the table. The rows and the heap are written back in the
+        layout they were read with (arrays keep their heap offsets), so the
+        NAXIS1, PCOUNT and THEAP keywords stay valid.
+    */
+    let (row_len, n_rows) = (tbl.get_row_len(), tbl.get_shape().1);
+    let byte_size = row_len * n_rows + tbl.get_heap_len();
+    let mut whole_table = vec![0u8; byte_size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE];
+
+    let mut start = 0;
+    for (col_idx, col) in tbl.get_cols().iter().enumerate() {
+      let format = col.field.format;
+      let (kind, width) = (format.get_type(), format.get_field_width());
+      for row in 0..n_rows {
+        let field_start = row * row_len + start;
+        let field = &mut whole_table[field_start..field_start + width];
+        let Some(descriptor) = format.get_descriptor() else {
+          let (first, len) = col.row_span(row);
+          col.data.write_be(first, kind.units(len), field);
+          continue;
+        };
+
+        //Variable-length arrays go back to their place in the heap
+        let array = &col.heap[row];
+        Self::write_descriptor(descriptor, array, field, (col_idx, row))?;
+        let begin = tbl.get_heap_start() + array.offset;
+        let n_bytes = kind.units(array.len) * kind.unit_size();
+        col.data.write_be(
+          array.start,
+          kind.units(array.len),
+          &mut whole_table[begin..begin + n_bytes],
+        );
+      }
+      start += width;
+    }
+
+    writer.write_blocks(&whole_table)?;
+    Ok(())
+  }
+}
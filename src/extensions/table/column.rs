@@ -16,17 +16,17 @@
     You should have received a copy of the GNU General Public License
     along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
 */
-use std::fmt::Debug;
+use std::{collections::HashMap, fmt::Debug, mem::size_of};
 
 use dyn_clone::{clone_trait_object, DynClone};
 use rayon::prelude::*;
 
 use crate::{
-  raw::table_entry_format::TableEntryFormat,
-  tbl_err::{IndexOutOfRangeErr, TblDecodeErr, TypeMisMatchErr},
+  raw::table_entry_format::TableEntryFormat, read_options::StringInterning,
+  tbl_err::TypeMisMatchErr,
 };
 
-use super::TableEntry;
+use super::{TableEntry, Validity};
 
 /*  Fixed number of digits after comma
     This value is fixed by the maximum number of digits in the mantissa of a
@@ -35,7 +35,11 @@ use super::TableEntry;
 */
 const DIGITS_AFTER_COMMA: usize = 15;
 
-pub(crate) trait AsciiCol: Debug + DynClone {
+//With StringInterning::Repeated, values have to repeat this many times on
+//average before a column is interned
+const MIN_REPEATS: usize = 4;
+
+pub(crate) trait AsciiCol: Debug + DynClone + Send + Sync {
   /*  PUBLIC API
       End-users will recieve a Table struct containing boxed columns. They
       may modify the entries in each column, or remove/replace/reorder columns.
@@ -44,19 +48,21 @@ pub(crate) trait AsciiCol: Debug + DynClone {
       columns is defined in this trait.
   */
 
-  //Funcs for modifying/adding/removing entries in the column
+  //Funcs for adding and reading entries in the column
   fn push_entry(&mut self, entry: TableEntry) -> Result<(), TypeMisMatchErr>;
-  fn pop_entry(&mut self) -> Option<TableEntry>;
-  fn set_entry(&mut self, entry: TableEntry, index: usize) -> Result<(), TblDecodeErr>;
   fn get_entry(&self, index: usize) -> Option<TableEntry>;
-  fn remove_entry(&mut self, index: usize) -> Option<TableEntry>;
 
   //Other funcs
   fn len(&self) -> usize;
   fn get_col_label(&self) -> Option<&str>;
   fn get_col_fmt(&self) -> TableEntryFormat;
+  fn get_memory_usage(&self) -> usize;
   fn pretty_print(&self) -> String;
 
+  //Nullable columns have a validity bitmap and the TNULLn string for nulls
+  fn validity(&self) -> Option<&Validity>;
+  fn get_null(&self) -> Option<&str>;
+
   /*  PRIVATE FUNCS
       These funcs are used for decoding and encoding columns. Not to be used
       by the end user
@@ -64,6 +70,19 @@ pub(crate) trait AsciiCol: Debug + DynClone {
 
   //Funcs for properly encoding/decoding
   fn to_ascii_vec(&self) -> Vec<String>;
+
+  /*  String columns can be interned (dictionary encoded), see DictColumn.
+      These funcs only do something for string columns
+  */
+  fn get_str(&self, _index: usize) -> Option<&str> {
+    None
+  }
+  fn dictionary(&self) -> Option<&[String]> {
+    None
+  }
+  fn to_interned(&self) -> Option<Box<dyn AsciiCol>> {
+    None
+  }
 }
 
 //This macro makes Col a clonable trait object
@@ -78,59 +97,73 @@ pub(crate) struct Column<T> {
       Fortran-formatted strings when the table is opened.
 
       Columns may be labeled as per the FITS standard.
+
+      Null entries are not marked with a sentinel value. Instead, nullable
+      columns keep a validity bitmap next to the entries (like Arrow does),
+      and the entries themselves hold a placeholder (0, NaN or ""). Columns
+      without any nulls have no bitmap at all.
   */
   label: Option<String>,
   container: Vec<T>,
+  validity: Option<Validity>,
+  null: Option<String>, //TNULLn string that nulls are written as
 }
 
 impl<T> Column<T> {
   pub(crate) fn new(label: Option<String>) -> Self {
-    Column { label: label, container: Vec::new() }
+    Column { label, container: Vec::new(), validity: None, null: None }
   }
-}
 
-impl AsciiCol for Column<String> {
-  fn push_entry(&mut self, entry: TableEntry) -> Result<(), TypeMisMatchErr> {
-    match entry {
-      TableEntry::Text(txt) => Ok(self.container.push(txt)),
-      other => Err(TypeMisMatchErr::new(TableEntry::txt(), &other)),
+  pub(crate) fn with_nulls(
+    label: Option<String>,
+    container: Vec<T>,
+    validity: Validity,
+    null: Option<String>,
+  ) -> Self {
+    let validity = Some(validity).filter(|validity| validity.null_count() > 0);
+    Column { label, container, validity, null }
+  }
+
+  fn null_text(&self, index: usize) -> Option<&str> {
+    //Text that a null entry is encoded as, None for valid entries
+    match &self.validity {
+      Some(validity) if validity.is_null(index) => Some(self.null.as_deref().unwrap_or("")),
+      _ => None,
     }
   }
 
-  fn pop_entry(&mut self) -> Option<TableEntry> {
-    match self.container.pop() {
-      Some(val) => Some(TableEntry::Text(val)),
-      None => None,
+  fn null_width(&self) -> usize {
+    match &self.validity {
+      Some(_) => self.null.as_ref().map_or(0, |null| null.len()),
+      None => 0,
+    }
+  }
+
+  fn push_valid(&mut self) {
+    if let Some(validity) = &mut self.validity {
+      validity.push(true);
     }
   }
 
-  fn set_entry(&mut self, entry: TableEntry, index: usize) -> Result<(), TblDecodeErr> {
+  fn validity_usage(&self) -> usize {
+    self.validity.as_ref().map_or(0, |validity| validity.as_bytes().len())
+  }
+}
+
+impl AsciiCol for Column<String> {
+  fn push_entry(&mut self, entry: TableEntry) -> Result<(), TypeMisMatchErr> {
     match entry {
       TableEntry::Text(txt) => {
-        if self.container.len() >= index {
-          Err(IndexOutOfRangeErr::from_idx((None, index), (None, self.container.len())).into())
-        } else {
-          self.container[index] = txt;
-          Ok(())
-        }
+        self.container.push(txt);
+        self.push_valid();
+        Ok(())
       }
-      other => Err(TypeMisMatchErr::new(TableEntry::txt(), &other).into()),
+      other => Err(TypeMisMatchErr::new(TableEntry::txt(), &other)),
     }
   }
 
   fn get_entry(&self, index: usize) -> Option<TableEntry> {
-    match self.container.get(index) {
-      Some(txt) => Some(TableEntry::Text(txt.to_string())),
-      None => None,
-    }
-  }
-
-  fn remove_entry(&mut self, index: usize) -> Option<TableEntry> {
-    if self.container.len() >= index {
-      None
-    } else {
-      Some(TableEntry::Text(self.container.remove(index)))
-    }
+    self.container.get(index).map(|txt| TableEntry::Text(txt.to_string()))
   }
 
   fn len(&self) -> usize {
@@ -138,7 +171,15 @@ impl AsciiCol for Column<String> {
   }
 
   fn to_ascii_vec(&self) -> Vec<String> {
-    self.container.par_iter().map(|primitive| primitive.to_string()).collect()
+    self
+      .container
+      .par_iter()
+      .enumerate()
+      .map(|(index, primitive)| match self.null_text(index) {
+        Some(null) => null.to_string(),
+        None => primitive.to_string(),
+      })
+      .collect()
   }
 
   fn get_col_label(&self) -> Option<&str> {
@@ -150,12 +191,21 @@ impl AsciiCol for Column<String> {
 
   fn get_col_fmt(&self) -> TableEntryFormat {
     //(1) Find the entry with the largest width, use it as return val
-    let width = self.container.iter().fold(0, |acc, entry| acc.max(entry.len()));
+    let width = self.container.iter().fold(self.null_width(), |acc, entry| acc.max(entry.len()));
 
     //(R) return a Char tblfmt with specified width
     TableEntryFormat::Char(width)
   }
 
+  fn get_memory_usage(&self) -> usize {
+    //Strings also own a heap allocation
+    self
+      .container
+      .iter()
+      .fold(self.container.len() * size_of::<String>(), |sum, txt| sum + txt.capacity())
+      + self.validity_usage()
+  }
+
   fn pretty_print(&self) -> String {
     format!(
       "label: {}, dtype: string",
@@ -165,50 +215,38 @@ impl AsciiCol for Column<String> {
       }
     )
   }
-}
 
-impl AsciiCol for Column<i64> {
-  fn push_entry(&mut self, entry: TableEntry) -> Result<(), TypeMisMatchErr> {
-    match entry {
-      TableEntry::Int(num) => Ok(self.container.push(num)),
-      other => Err(TypeMisMatchErr::new(TableEntry::int(), &other)),
-    }
+  fn validity(&self) -> Option<&Validity> {
+    self.validity.as_ref()
   }
 
-  fn pop_entry(&mut self) -> Option<TableEntry> {
-    match self.container.pop() {
-      Some(val) => Some(TableEntry::Int(val)),
-      None => None,
-    }
+  fn get_null(&self) -> Option<&str> {
+    self.null.as_deref()
+  }
+
+  fn get_str(&self, index: usize) -> Option<&str> {
+    self.container.get(index).map(String::as_str)
   }
 
-  fn set_entry(&mut self, entry: TableEntry, index: usize) -> Result<(), TblDecodeErr> {
+  fn to_interned(&self) -> Option<Box<dyn AsciiCol>> {
+    Some(Box::new(DictColumn::from_column(self)))
+  }
+}
+
+impl AsciiCol for Column<i64> {
+  fn push_entry(&mut self, entry: TableEntry) -> Result<(), TypeMisMatchErr> {
     match entry {
       TableEntry::Int(num) => {
-        if self.container.len() >= index {
-          Err(IndexOutOfRangeErr::from_idx((None, index), (None, self.container.len())).into())
-        } else {
-          self.container[index] = num;
-          Ok(())
-        }
+        self.container.push(num);
+        self.push_valid();
+        Ok(())
       }
-      other => Err(TypeMisMatchErr::new(TableEntry::int(), &other))?,
+      other => Err(TypeMisMatchErr::new(TableEntry::int(), &other)),
     }
   }
 
   fn get_entry(&self, index: usize) -> Option<TableEntry> {
-    match self.container.get(index) {
-      Some(num) => Some(TableEntry::Int(*num)),
-      None => None,
-    }
-  }
-
-  fn remove_entry(&mut self, index: usize) -> Option<TableEntry> {
-    if self.container.len() >= index {
-      None
-    } else {
-      Some(TableEntry::Int(self.container.remove(index)))
-    }
+    self.container.get(index).map(|num| TableEntry::Int(*num))
   }
 
   fn len(&self) -> usize {
@@ -216,7 +254,15 @@ impl AsciiCol for Column<i64> {
   }
 
   fn to_ascii_vec(&self) -> Vec<String> {
-    self.container.par_iter().map(|primitive| primitive.to_string()).collect()
+    self
+      .container
+      .par_iter()
+      .enumerate()
+      .map(|(index, primitive)| match self.null_text(index) {
+        Some(null) => null.to_string(),
+        None => primitive.to_string(),
+      })
+      .collect()
   }
 
   fn get_col_label(&self) -> Option<&str> {
@@ -227,11 +273,16 @@ impl AsciiCol for Column<i64> {
   }
 
   fn get_col_fmt(&self) -> TableEntryFormat {
-    //(1) get the largest value, it'll be the longest
-    let width = self.container.iter().fold(0, |acc, entry| acc.max(entry.abs() as usize));
+    //(1) get the number of digits of the longest value
+    let width =
+      self.container.iter().fold(1, |acc, entry| acc.max(entry.unsigned_abs().to_string().len()));
+
+    //(R) return width + 1 character for the sign of the integer (nulls must fit too)
+    TableEntryFormat::Int((width + 1).max(self.null_width()))
+  }
 
-    //(R) return width + 1 character for the sign of the integer
-    TableEntryFormat::Int(width + 1)
+  fn get_memory_usage(&self) -> usize {
+    self.container.len() * size_of::<i64>() + self.validity_usage()
   }
 
   fn pretty_print(&self) -> String {
@@ -243,50 +294,30 @@ impl AsciiCol for Column<i64> {
       }
     )
   }
-}
 
-impl AsciiCol for Column<f64> {
-  fn push_entry(&mut self, entry: TableEntry) -> Result<(), TypeMisMatchErr> {
-    match entry {
-      TableEntry::Float(num) => Ok(self.container.push(num)),
-      other => Err(TypeMisMatchErr::new(TableEntry::float(), &other)),
-    }
+  fn validity(&self) -> Option<&Validity> {
+    self.validity.as_ref()
   }
 
-  fn pop_entry(&mut self) -> Option<TableEntry> {
-    match self.container.pop() {
-      Some(val) => Some(TableEntry::Float(val)),
-      None => None,
-    }
+  fn get_null(&self) -> Option<&str> {
+    self.null.as_deref()
   }
+}
 
-  fn set_entry(&mut self, entry: TableEntry, index: usize) -> Result<(), TblDecodeErr> {
+impl AsciiCol for Column<f64> {
+  fn push_entry(&mut self, entry: TableEntry) -> Result<(), TypeMisMatchErr> {
     match entry {
       TableEntry::Float(num) => {
-        if self.container.len() >= index {
-          Err(IndexOutOfRangeErr::from_idx((None, index), (None, self.container.len())).into())
-        } else {
-          self.container[index] = num;
-          Ok(())
-        }
+        self.container.push(num);
+        self.push_valid();
+        Ok(())
       }
-      other => Err(TypeMisMatchErr::new(TableEntry::float(), &other))?,
+      other => Err(TypeMisMatchErr::new(TableEntry::float(), &other)),
     }
   }
 
   fn get_entry(&self, index: usize) -> Option<TableEntry> {
-    match self.container.get(index) {
-      Some(num) => Some(TableEntry::Float(*num)),
-      None => None,
-    }
-  }
-
-  fn remove_entry(&mut self, index: usize) -> Option<TableEntry> {
-    if self.container.len() >= index {
-      None
-    } else {
-      Some(TableEntry::Float(self.container.remove(index)))
-    }
+    self.container.get(index).map(|num| TableEntry::Float(*num))
   }
 
   fn len(&self) -> usize {
@@ -297,7 +328,11 @@ impl AsciiCol for Column<f64> {
     self
       .container
       .par_iter()
-      .map(|primitive| format!("{primitive:.0$e}", DIGITS_AFTER_COMMA))
+      .enumerate()
+      .map(|(index, primitive)| match self.null_text(index) {
+        Some(null) => null.to_string(),
+        None => format!("{primitive:.0$e}", DIGITS_AFTER_COMMA),
+      })
       .collect()
   }
 
@@ -310,13 +345,20 @@ impl AsciiCol for Column<f64> {
 
   fn get_col_fmt(&self) -> TableEntryFormat {
     //(1) Find the largest number -> it defines the width
-    let largest = self.container.iter().fold(0.0f64, |acc, entry| acc.max(entry.abs()));
+    let largest = self.container.iter().fold(0.0f64, |acc, entry| match entry.is_nan() {
+      true => acc,
+      false => acc.max(entry.abs()),
+    });
 
     //(R) width is width of largest number plus one for the sign
-    let width = format!("{largest:.0$e}", DIGITS_AFTER_COMMA).len() + 1;
+    let width = (format!("{largest:.0$e}", DIGITS_AFTER_COMMA).len() + 1).max(self.null_width());
     TableEntryFormat::Float((width, DIGITS_AFTER_COMMA))
   }
 
+  fn get_memory_usage(&self) -> usize {
+    self.container.len() * size_of::<f64>() + self.validity_usage()
+  }
+
   fn pretty_print(&self) -> String {
     format!(
       "label: {}, dtype: float",
@@ -326,4 +368,163 @@ impl AsciiCol for Column<f64> {
       }
     )
   }
+
+  fn validity(&self) -> Option<&Validity> {
+    self.validity.as_ref()
+  }
+
+  fn get_null(&self) -> Option<&str> {
+    self.null.as_deref()
+  }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct DictColumn {
+  /*
+      Interned (dictionary encoded) string column. Columns with heavily
+      repeated values, like filter names or flags, take up much less memory
+      when every distinct value is stored once and the entries are small codes
+      into the dictionary of values. The label, validity bitmap and null
+      string are kept by the column of codes.
+
+      Values that are no longer used (after set_entry or remove_entry) stay in
+      the dictionary until the column is dropped.
+  */
+  codes: Column<u32>,
+  values: Vec<String>,
+  index: HashMap<String, u32>,
+}
+
+impl DictColumn {
+  pub(crate) fn from_column(column: &Column<String>) -> Self {
+    let codes = Column {
+      label: column.label.clone(),
+      container: Vec::with_capacity(column.container.len()),
+      validity: column.validity.clone(),
+      null: column.null.clone(),
+    };
+    let mut dict = DictColumn { codes, values: Vec::new(), index: HashMap::new() };
+    for txt in &column.container {
+      let code = dict.code_of(txt);
+      dict.codes.container.push(code);
+    }
+    dict
+  }
+
+  fn code_of(&mut self, txt: &str) -> u32 {
+    //Code of the value, adding it to the dictionary if it is new
+    if let Some(&code) = self.index.get(txt) {
+      return code;
+    }
+    let code = self.values.len() as u32;
+    self.values.push(txt.to_string());
+    self.index.insert(txt.to_string(), code);
+    code
+  }
+
+  fn value(&self, code: u32) -> &str {
+    &self.values[code as usize]
+  }
+}
+
+impl AsciiCol for DictColumn {
+  fn push_entry(&mut self, entry: TableEntry) -> Result<(), TypeMisMatchErr> {
+    match entry {
+      TableEntry::Text(txt) => {
+        let code = self.code_of(&txt);
+        self.codes.container.push(code);
+        self.codes.push_valid();
+        Ok(())
+      }
+      other => Err(TypeMisMatchErr::new(TableEntry::txt(), &other)),
+    }
+  }
+
+  fn get_entry(&self, index: usize) -> Option<TableEntry> {
+    self.get_str(index).map(TableEntry::from)
+  }
+
+  fn len(&self) -> usize {
+    self.codes.container.len()
+  }
+
+  fn to_ascii_vec(&self) -> Vec<String> {
+    self
+      .codes
+      .container
+      .par_iter()
+      .enumerate()
+      .map(|(index, &code)| match self.codes.null_text(index) {
+        Some(null) => null.to_string(),
+        None => self.value(code).to_string(),
+      })
+      .collect()
+  }
+
+  fn get_col_label(&self) -> Option<&str> {
+    self.codes.label.as_deref()
+  }
+
+  fn get_col_fmt(&self) -> TableEntryFormat {
+    //Same as for plain string columns: the width of the widest entry
+    let width = self
+      .codes
+      .container
+      .iter()
+      .fold(self.codes.null_width(), |acc, &code| acc.max(self.value(code).len()));
+    TableEntryFormat::Char(width)
+  }
+
+  fn get_memory_usage(&self) -> usize {
+    //Every value is stored twice: in the dictionary and as key of the index
+    let values = self.values.iter().fold(0, |sum, txt| sum + txt.capacity());
+    let per_value = 2 * size_of::<String>() + size_of::<u32>();
+    self.codes.container.len() * size_of::<u32>()
+      + self.values.len() * per_value
+      + 2 * values
+      + self.codes.validity_usage()
+  }
+
+  fn pretty_print(&self) -> String {
+    format!(
+      "label: {}, dtype: string (interned, {} distinct values)",
+      self.get_col_label().unwrap_or("(no label)"),
+      self.values.len()
+    )
+  }
+
+  fn validity(&self) -> Option<&Validity> {
+    self.codes.validity.as_ref()
+  }
+
+  fn get_null(&self) -> Option<&str> {
+    self.codes.null.as_deref()
+  }
+
+  fn get_str(&self, index: usize) -> Option<&str> {
+    self.codes.container.get(index).map(|&code| self.value(code))
+  }
+
+  fn dictionary(&self) -> Option<&[String]> {
+    Some(&self.values)
+  }
+}
+
+pub(crate) fn intern_column(
+  col: Box<dyn AsciiCol>,
+  interning: StringInterning,
+) -> Box<dyn AsciiCol> {
+  //Interns a string column if the policy asks for it, other columns are kept
+  if interning == StringInterning::Off {
+    return col;
+  }
+  match col.to_interned() {
+    Some(interned) if interning == StringInterning::Always => interned,
+    Some(interned)
+      if interned.dictionary().map_or(0, |dict| dict.len()) * MIN_REPEATS <= col.len() =>
+    {
+      interned
+    }
+    _ => col,
+  }
 }
@@ -0,0 +1,261 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//Get block size from root
+const BLOCK_SIZE: usize = crate::BLOCK_SIZE; // = 2880B
+
+//Number of values that share a Rice coding parameter
+const RICE_BLOCK: usize = 32;
+
+use std::{error::Error, io::Read};
+
+use flate2::read::GzDecoder;
+
+use crate::{header::Header, tbl_err::TileDecodeErr};
+
+use super::{bin_table::BinData, bin_tbl_parser::BinField, BinTable, BinType};
+
+/*  Description:
+    Tile-compressed binary tables (ZTABLE = T) group their rows in tiles of
+    ZTILELEN rows. Every column of a tile is compressed on its own and stored
+    as a variable-length byte array in the heap, so the stored table has a row
+    per tile and a descriptor (1PB or 1QB) per column. ZFORMn, ZNAXIS1 and
+    ZNAXIS2 describe the original table, ZCTYPn gives the algorithm of every
+    column:
+      - GZIP_1: the big endian values of the column, gzipped
+      - GZIP_2: the same, but with the bytes of the values shuffled before
+        compression (all most significant bytes first, then the next etc.)
+      - RICE_1: Rice coding, only for B, I and J columns
+
+    The tiles are decompressed back into rows, which are then decoded by the
+    regular binary table parser. Variable-length array columns are compressed
+    differently, and are not supported.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TileCompression {
+  Gzip1,
+  Gzip2,
+  Rice1,
+}
+
+impl TileCompression {
+  pub(crate) fn from_zctype(zctype: &str) -> Option<Self> {
+    match zctype {
+      "GZIP_1" => Some(Self::Gzip1),
+      "GZIP_2" => Some(Self::Gzip2),
+      "RICE_1" => Some(Self::Rice1),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ZTableLayout {
+  pub(crate) row_len: usize,  //ZNAXIS1, #bytes in an original row
+  pub(crate) n_rows: usize,   //ZNAXIS2, #original rows
+  pub(crate) tile_len: usize, //ZTILELEN, #rows in a tile (but the last)
+}
+
+pub(crate) fn decompress_rows(
+  tiles: &BinTable,
+  layout: &ZTableLayout,
+  fields: &[BinField],
+  algorithms: &[TileCompression],
+) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+  //(1) The original rows, as they would have been stored without compression
+  let n_tiles = layout.n_rows.div_ceil(layout.tile_len);
+  let mut rows = vec![0u8; (layout.row_len * layout.n_rows).div_ceil(BLOCK_SIZE) * BLOCK_SIZE];
+  if tiles.get_shape().1 != n_tiles {
+    let message = format!("expected {n_tiles} tiles, found {}", tiles.get_shape().1);
+    return Err(Box::new(TileDecodeErr::new((0, 0), message)));
+  }
+
+  //(2) Decompress every column of every tile, and put its fields in the rows
+  let mut start = 0;
+  for (col, (field, &algorithm)) in fields.iter().zip(algorithms).enumerate() {
+    let width = field.format.get_field_width();
+    let column = &tiles.get_cols()[col];
+    for tile in 0..n_tiles {
+      let first_row = tile * layout.tile_len;
+      let n_rows = layout.tile_len.min(layout.n_rows - first_row);
+      let (offset, len) = column.row_span(tile);
+      let compressed = match &column.data {
+        BinData::Bytes(buf) => &buf[offset..offset + len],
+        _ => {
+          let message = String::from("compressed tiles have to be stored as byte arrays");
+          return Err(Box::new(TileDecodeErr::new((col, tile), message)));
+        }
+      };
+      let fields = decompress_tile(compressed, algorithm, field.format.get_type(), n_rows * width)
+        .map_err(|message| TileDecodeErr::new((col, tile), message))?;
+      for (row, field) in fields.chunks_exact(width.max(1)).enumerate() {
+        let at = (first_row + row) * layout.row_len + start;
+        rows[at..at + width].copy_from_slice(field);
+      }
+    }
+    start += width;
+  }
+
+  //(R) the rows, padded to whole blocks
+  Ok(rows)
+}
+
+fn decompress_tile(
+  compressed: &[u8],
+  algorithm: TileCompression,
+  kind: BinType,
+  len: usize,
+) -> Result<Vec<u8>, String> {
+  //Decompressed fields of a column in a tile, which have to be len bytes long
+  let value_size = match kind {
+    BinType::Complex | BinType::DoubleComplex => kind.unit_size() / 2,
+    _ => kind.unit_size(),
+  };
+  let fields = match algorithm {
+    TileCompression::Gzip1 => gunzip(compressed)?,
+    TileCompression::Gzip2 => unshuffle(&gunzip(compressed)?, value_size),
+    TileCompression::Rice1 => match kind {
+      BinType::Byte | BinType::Short | BinType::Int => {
+        rice_decode(compressed, value_size, len / value_size)?
+      }
+      _ => {
+        return Err(format!("RICE_1 only applies to B, I and J columns, not {}", kind.get_code()))
+      }
+    },
+  };
+  match fields.len() == len {
+    true => Ok(fields),
+    false => Err(format!("decompressed to {} bytes instead of {len}", fields.len())),
+  }
+}
+
+fn gunzip(compressed: &[u8]) -> Result<Vec<u8>, String> {
+  let mut fields = Vec::new();
+  GzDecoder::new(compressed).read_to_end(&mut fields).map_err(|err| err.to_string())?;
+  Ok(fields)
+}
+
+fn unshuffle(shuffled: &[u8], value_size: usize) -> Vec<u8> {
+  //GZIP_2 stores the first byte of every value, then the second etc.
+  let n_values = shuffled.len() / value_size;
+  let mut fields = vec![0u8; shuffled.len()];
+  for (byte, plane) in shuffled.chunks_exact(n_values.max(1)).take(value_size).enumerate() {
+    for (value, &part) in plane.iter().enumerate() {
+      fields[value * value_size + byte] = part;
+    }
+  }
+  fields
+}
+
+struct BitReader<'a> {
+  bytes: &'a [u8],
+  pos: usize, //index of the next bit
+}
+
+impl BitReader<'_> {
+  fn bit(&mut self) -> Result<u32, String> {
+    let byte = self.bytes.get(self.pos / 8).ok_or("Rice coded data ended early")?;
+    let bit = (byte >> (7 - self.pos % 8)) & 1;
+    self.pos += 1;
+    Ok(bit as u32)
+  }
+
+  fn read(&mut self, n_bits: u32) -> Result<u32, String> {
+    (0..n_bits).try_fold(0u32, |value, _| Ok((value << 1) | self.bit()?))
+  }
+
+  fn count_zeros(&mut self) -> Result<u32, String> {
+    //Length of a unary code: the number of 0 bits before the next 1 bit
+    let mut zeros = 0;
+    while self.bit()? == 0 {
+      zeros += 1;
+    }
+    Ok(zeros)
+  }
+}
+
+fn rice_decode(compressed: &[u8], value_size: usize, n_values: usize) -> Result<Vec<u8>, String> {
+  /*  Rice coded data starts with the first value. Every block of 32 values
+      after that starts with the coding parameter fs (fsbits bits, plus one).
+      fs = 0 means all differences in the block are zero, fs = fsmax that they
+      are stored as plain bbits numbers. Otherwise every difference is stored
+      as a unary code for its high bits, followed by its low fs bits. The
+      differences between consecutive values are mapped to positive numbers
+      (0, -1, 1, -2... become 0, 1, 2, 3...) before coding.
+  */
+  let (fsbits, fsmax, bbits) = match value_size {
+    1 => (3, 6, 8),
+    2 => (4, 14, 16),
+    _ => (5, 25, 32),
+  };
+  let mask = u32::MAX >> (32 - bbits);
+  let mut fields = Vec::with_capacity(n_values * value_size);
+  if n_values == 0 {
+    return Ok(fields);
+  }
+  let Some(first) = compressed.get(..value_size) else {
+    return Err(String::from("Rice coded data ended early"));
+  };
+  let mut last = first.iter().fold(0u32, |value, &byte| (value << 8) | byte as u32);
+  let mut bits = BitReader { bytes: &compressed[value_size..], pos: 0 };
+
+  for block in (0..n_values).step_by(RICE_BLOCK) {
+    let fs = bits.read(fsbits)?;
+    for _ in block..(block + RICE_BLOCK).min(n_values) {
+      let diff = match fs {
+        0 => 0,
+        fs if fs - 1 == fsmax => bits.read(bbits)?,
+        fs => (bits.count_zeros()?.wrapping_shl(fs - 1)) | bits.read(fs - 1)?,
+      };
+      let diff = match diff & 1 {
+        0 => diff >> 1,
+        _ => !(diff >> 1),
+      };
+      last = last.wrapping_add(diff) & mask;
+      fields.extend_from_slice(&last.to_be_bytes()[4 - value_size..]);
+    }
+  }
+  Ok(fields)
+}
+
+pub(crate) fn decompress_header(header: &mut Header) {
+  /*  Once a compressed table is decoded, the HDU holds the original table.
+      When it is written, the header has to describe that table instead of
+      the compressed tiles: the Z keywords take the place of the keywords they
+      were saved from. The heap of the original table is not kept.
+  */
+  let n_fields: usize = header.get_value_as("TFIELDS").unwrap_or(0);
+  for n in 1..=n_fields {
+    if let Some(zform) = header.remove_record(&format!("ZFORM{n}")) {
+      header.put_record(&format!("TFORM{n}"), zform.value.unwrap_or_default(), zform.comment);
+    }
+    header.remove_record(&format!("ZCTYP{n}"));
+  }
+  for (keyword, zkeyword) in [("NAXIS1", "ZNAXIS1"), ("NAXIS2", "ZNAXIS2")] {
+    if let Some(zrecord) = header.remove_record(zkeyword) {
+      header.put_record(keyword, zrecord.value.unwrap_or_default(), zrecord.comment);
+    }
+  }
+  header.put_record("PCOUNT", String::from("0"), header.get_comment("PCOUNT").cloned());
+  let obsolete = ["ZTABLE", "ZTILELEN", "ZPCOUNT", "ZTHEAP", "ZCHECKSUM", "ZDATASUM", "THEAP"];
+  for keyword in obsolete.into_iter().chain(["CHECKSUM", "DATASUM"]) {
+    header.remove_record(keyword);
+  }
+}
@@ -0,0 +1,177 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::error::Error;
+
+use crate::tbl_err::{EntryConversionErr, IndexOutOfRangeErr, MissingColumnErr, ShapeMisMatchErr};
+
+use super::{TableBuilder, TableEntry};
+
+/*  Description:
+    FitsRow maps the rows of a table to a user struct, column by column. It is
+    usually derived (derive(FitsRow), with the derive feature), in which case
+    every field is read from the column with the same name:
+
+      #[derive(FitsRow)]
+      struct Star { ra: f64, dec: f64, name: String }
+
+      let stars: Vec<Star> = table.rows_as()?;
+      let table = AsciiTable::from_rows(&stars)?;
+
+    The fields themselves are converted with FitsField, which is implemented
+    for the integer and float types, String and bool.
+*/
+
+pub trait FitsRow: Sized {
+  /*  THIS TRAIT IS PART OF THE USER-FACING API
+      Column labels of the fields, in order. from_entries and to_entries use
+      the same order, and add_cols gets the width of every text column.
+  */
+  fn get_col_labels() -> Vec<&'static str>;
+  fn from_entries(entries: Vec<TableEntry>) -> Result<Self, Box<dyn Error + Send + Sync>>;
+  fn to_entries(&self) -> Vec<TableEntry>;
+  fn add_cols(builder: TableBuilder, widths: &[usize]) -> TableBuilder;
+
+  fn check_entries(entries: &[TableEntry]) -> Result<(), ShapeMisMatchErr> {
+    //There has to be exactly one entry per field
+    let n_cols = Self::get_col_labels().len();
+    match entries.len() == n_cols {
+      true => Ok(()),
+      false => Err(ShapeMisMatchErr::from_len(entries.len(), n_cols)),
+    }
+  }
+}
+
+pub trait FitsField: Sized {
+  /*  THIS TRAIT IS PART OF THE USER-FACING API
+      Conversion of a single field. add_col adds a column that can hold the
+      field to a TableBuilder (width is only used for text columns).
+  */
+  fn from_entry(entry: TableEntry) -> Result<Self, Box<dyn Error + Send + Sync>>;
+  fn to_entry(&self) -> TableEntry;
+  fn add_col(builder: TableBuilder, label: &str, width: usize) -> TableBuilder;
+}
+
+macro_rules! impl_int_field {
+  ($($int:ty),*) => {$(
+    impl FitsField for $int {
+      fn from_entry(entry: TableEntry) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        //Integers that do not fit the field are refused, not truncated
+        let num = i64::try_from(entry.clone())?;
+        Ok(<$int>::try_from(num).map_err(|_| EntryConversionErr::new(entry, stringify!($int)))?)
+      }
+      fn to_entry(&self) -> TableEntry {
+        TableEntry::Int(i64::from(*self))
+      }
+      fn add_col(builder: TableBuilder, label: &str, _width: usize) -> TableBuilder {
+        builder.col_i64(label)
+      }
+    }
+  )*};
+}
+impl_int_field!(u8, i16, u16, i32, u32, i64);
+
+impl FitsField for f64 {
+  fn from_entry(entry: TableEntry) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    Ok(f64::try_from(entry)?)
+  }
+  fn to_entry(&self) -> TableEntry {
+    TableEntry::Float(*self)
+  }
+  fn add_col(builder: TableBuilder, label: &str, _width: usize) -> TableBuilder {
+    builder.col_f64(label)
+  }
+}
+
+impl FitsField for f32 {
+  fn from_entry(entry: TableEntry) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    Ok(f64::try_from(entry)? as f32)
+  }
+  fn to_entry(&self) -> TableEntry {
+    TableEntry::from(*self)
+  }
+  fn add_col(builder: TableBuilder, label: &str, _width: usize) -> TableBuilder {
+    builder.col_f64(label)
+  }
+}
+
+impl FitsField for String {
+  fn from_entry(entry: TableEntry) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    //Text fields are padded with spaces in the file
+    Ok(String::try_from(entry)?.trim_end().to_string())
+  }
+  fn to_entry(&self) -> TableEntry {
+    TableEntry::Text(self.clone())
+  }
+  fn add_col(builder: TableBuilder, label: &str, width: usize) -> TableBuilder {
+    builder.col_str(label, width)
+  }
+}
+
+impl FitsField for bool {
+  fn from_entry(entry: TableEntry) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    Ok(bool::try_from(entry)?)
+  }
+  fn to_entry(&self) -> TableEntry {
+    TableEntry::from(*self)
+  }
+  fn add_col(builder: TableBuilder, label: &str, _width: usize) -> TableBuilder {
+    builder.col_str(label, 1)
+  }
+}
+
+pub(crate) fn read_rows<R: FitsRow>(
+  n_rows: usize,
+  col_index: impl Fn(&str) -> Option<usize>,
+  get_entry: impl Fn(usize, usize) -> Result<TableEntry, IndexOutOfRangeErr>,
+) -> Result<Vec<R>, Box<dyn Error + Send + Sync>> {
+  //(1) Find the column of every field
+  let cols = R::get_col_labels()
+    .into_iter()
+    .map(|label| col_index(label).ok_or_else(|| MissingColumnErr::new(&[label])))
+    .collect::<Result<Vec<usize>, MissingColumnErr>>()?;
+
+  //(2) and convert the rows one by one
+  (0..n_rows)
+    .map(|row| {
+      let entries = cols.iter().map(|&col| get_entry(col, row)).collect::<Result<_, _>>()?;
+      R::from_entries(entries)
+    })
+    .collect()
+}
+
+pub(crate) fn build_rows<R: FitsRow>(
+  rows: &[R],
+) -> Result<TableBuilder, Box<dyn Error + Send + Sync>> {
+  //Text columns are as wide as their longest entry
+  let entries: Vec<Vec<TableEntry>> = rows.iter().map(FitsRow::to_entries).collect();
+  let mut widths = vec![1; R::get_col_labels().len()];
+  for row in &entries {
+    for (width, entry) in widths.iter_mut().zip(row) {
+      if let TableEntry::Text(txt) = entry {
+        *width = (*width).max(txt.chars().count());
+      }
+    }
+  }
+  let mut builder = R::add_cols(TableBuilder::new(), &widths);
+  for row in entries {
+    builder.push_row(row)?;
+  }
+  Ok(builder)
+}
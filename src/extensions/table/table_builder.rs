@@ -0,0 +1,137 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::error::Error;
+
+use crate::tbl_err::{FieldWidthErr, ShapeMisMatchErr, TypeMisMatchErr};
+
+use super::{
+  column::{AsciiCol, Column},
+  AsciiTable, TableEntry,
+};
+
+/*  Description:
+    TableBuilder is the user-facing way to create an AsciiTable from scratch.
+    Columns are declared first (in order), after which rows can be added with
+    push_row or the push_row! macro:
+
+      let mut builder = TableBuilder::new().col_f64("FLUX").col_str("NAME", 8);
+      push_row!(builder, 1.5, "M31")?;
+      let table = builder.build();
+
+    Text columns have a fixed width. Shorter strings are padded with spaces
+    (like they would be in a FITS file), longer strings are rejected.
+*/
+
+#[derive(Debug, Clone)]
+enum ColKind {
+  Text(usize),
+  Int,
+  Float,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TableBuilder {
+  labels: Vec<String>,
+  kinds: Vec<ColKind>,
+  cols: Vec<Box<dyn AsciiCol>>,
+}
+
+impl TableBuilder {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn col_i64(self, label: &str) -> Self {
+    self.add_col(label, ColKind::Int, Box::new(Column::<i64>::new(Some(label.to_string()))))
+  }
+
+  pub fn col_f64(self, label: &str) -> Self {
+    self.add_col(label, ColKind::Float, Box::new(Column::<f64>::new(Some(label.to_string()))))
+  }
+
+  pub fn col_str(self, label: &str, width: usize) -> Self {
+    self.add_col(
+      label,
+      ColKind::Text(width),
+      Box::new(Column::<String>::new(Some(label.to_string()))),
+    )
+  }
+
+  pub fn push_row(&mut self, row: Vec<TableEntry>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    //(1) Check the whole row before adding anything, so that a bad row does
+    //    not leave the columns with different lengths
+    if row.len() != self.cols.len() {
+      return Err(Box::new(ShapeMisMatchErr::from_len(row.len(), self.cols.len())));
+    }
+    let mut checked = Vec::with_capacity(row.len());
+    for ((entry, kind), label) in row.into_iter().zip(&self.kinds).zip(&self.labels) {
+      checked.push(match (kind, entry) {
+        (ColKind::Text(width), TableEntry::Text(txt)) => {
+          let len = txt.chars().count();
+          if len > *width {
+            return Err(Box::new(FieldWidthErr::new(label, *width, len)));
+          }
+          TableEntry::Text(format!("{txt:0$}", *width))
+        }
+        (ColKind::Int, entry @ TableEntry::Int(_)) => entry,
+        (ColKind::Float, entry @ TableEntry::Float(_)) => entry,
+        (ColKind::Text(_), other) => Err(TypeMisMatchErr::new(TableEntry::txt(), &other))?,
+        (ColKind::Int, other) => Err(TypeMisMatchErr::new(TableEntry::int(), &other))?,
+        (ColKind::Float, other) => Err(TypeMisMatchErr::new(TableEntry::float(), &other))?,
+      });
+    }
+
+    //(2) Add the row to the table
+    for (col, entry) in self.cols.iter_mut().zip(checked) {
+      col.push_entry(entry)?;
+    }
+    Ok(())
+  }
+
+  pub fn get_num_rows(&self) -> usize {
+    match self.cols.first() {
+      None => 0,
+      Some(col) => col.len(),
+    }
+  }
+
+  pub fn build(self) -> AsciiTable {
+    AsciiTable::new_unsized(self.cols)
+  }
+
+  fn add_col(mut self, label: &str, kind: ColKind, col: Box<dyn AsciiCol>) -> Self {
+    //Columns can only be added to an empty table
+    assert!(self.get_num_rows() == 0, "cannot add column '{label}' after rows have been added");
+    self.labels.push(label.to_string());
+    self.kinds.push(kind);
+    self.cols.push(col);
+    self
+  }
+}
+
+#[macro_export]
+macro_rules! push_row {
+  /*  Adds a row to a TableBuilder, converting every value into a TableEntry:
+        push_row!(builder, 1.5, 42, "M31")
+  */
+  ($builder:expr, $($val:expr),* $(,)?) => {
+    $builder.push_row(vec![$($crate::TableEntry::from($val)),*])
+  };
+}
@@ -18,20 +18,27 @@
 */
 
 use std::{
-  error::Error,
   fmt::{self, Display, Formatter},
+  str,
 };
 
 use crate::{
   raw::table_entry_format::TableEntryFormat,
+  read_options::FieldTolerance,
+  tbl_err::EntryConversionErr,
   tbl_fmt_err::{FieldSizeMisMatch, InvalidFFCode, ParseError},
 };
 
+use super::ascii_num;
+
 #[derive(Debug, Clone)]
 pub enum TableEntry {
   Text(String),
   Int(i64),
   Float(f64),
+  //Only binary tables have complex entries and fields with several elements
+  Complex(f64, f64),
+  Array(Vec<TableEntry>),
 }
 
 impl Display for TableEntry {
@@ -44,26 +51,175 @@ impl Display for TableEntry {
         Text(txt) => format!("{txt} (string)"),
         Int(num) => format!("{num} (int)"),
         Float(num) => format!("{num} (float)"),
+        Complex(re, im) => format!("({re}, {im}) (complex)"),
+        Array(entries) => format!("{} entries (array)", entries.len()),
       }
     )
   }
 }
 
+impl From<i64> for TableEntry {
+  fn from(num: i64) -> Self {
+    Self::Int(num)
+  }
+}
+
+impl From<f64> for TableEntry {
+  fn from(num: f64) -> Self {
+    Self::Float(num)
+  }
+}
+
+impl From<String> for TableEntry {
+  fn from(txt: String) -> Self {
+    Self::Text(txt)
+  }
+}
+
+impl From<&str> for TableEntry {
+  fn from(txt: &str) -> Self {
+    Self::Text(txt.to_string())
+  }
+}
+
+impl From<i32> for TableEntry {
+  fn from(num: i32) -> Self {
+    Self::Int(num as i64)
+  }
+}
+
+impl From<f32> for TableEntry {
+  fn from(num: f32) -> Self {
+    Self::Float(num as f64)
+  }
+}
+
+impl From<bool> for TableEntry {
+  fn from(val: bool) -> Self {
+    //FITS logicals are written as T or F
+    Self::Text(String::from(if val { "T" } else { "F" }))
+  }
+}
+
+impl TryFrom<TableEntry> for i64 {
+  type Error = EntryConversionErr;
+  fn try_from(entry: TableEntry) -> Result<Self, Self::Error> {
+    match entry {
+      TableEntry::Int(num) => Ok(num),
+      other => Err(EntryConversionErr::new(other, "i64")),
+    }
+  }
+}
+
+impl TryFrom<TableEntry> for f64 {
+  type Error = EntryConversionErr;
+  fn try_from(entry: TableEntry) -> Result<Self, Self::Error> {
+    //Integers are promoted, like they would be in a FITS reader
+    match entry {
+      TableEntry::Float(num) => Ok(num),
+      TableEntry::Int(num) => Ok(num as f64),
+      other => Err(EntryConversionErr::new(other, "f64")),
+    }
+  }
+}
+
+impl TryFrom<TableEntry> for String {
+  type Error = EntryConversionErr;
+  fn try_from(entry: TableEntry) -> Result<Self, Self::Error> {
+    match entry {
+      TableEntry::Text(txt) => Ok(txt),
+      other => Err(EntryConversionErr::new(other, "String")),
+    }
+  }
+}
+
+impl TryFrom<TableEntry> for bool {
+  type Error = EntryConversionErr;
+  fn try_from(entry: TableEntry) -> Result<Self, Self::Error> {
+    //ASCII tables have no logical type, so we accept T/F strings and 1/0
+    match &entry {
+      TableEntry::Text(txt) if txt.trim() == "T" => Ok(true),
+      TableEntry::Text(txt) if txt.trim() == "F" => Ok(false),
+      TableEntry::Int(1) => Ok(true),
+      TableEntry::Int(0) => Ok(false),
+      _ => Err(EntryConversionErr::new(entry, "bool")),
+    }
+  }
+}
+
 impl TableEntry {
-  pub(crate) fn from_parts(raw_field: &str, format: &TableEntryFormat) -> Result<Self, ParseError> {
+  pub(crate) fn from_bytes_with(
+    raw_field: &[u8],
+    format: &TableEntryFormat,
+    tolerance: FieldTolerance,
+  ) -> Result<(Self, Option<String>), ParseError> {
+    /*  Like from_bytes, but with FieldTolerance::Repair numeric fields that
+        are not valid FITS numbers are repaired if possible. The entry is
+        returned together with a description of the repairs, if any. Fields
+        that cannot be repaired still result in the original error.
+    */
+    let err = match Self::from_bytes(raw_field, format) {
+      Ok(entry) => return Ok((entry, None)),
+      Err(err) => err,
+    };
+    if tolerance == FieldTolerance::Strict {
+      return Err(err);
+    }
+
+    //(1) Text fields can only fail on invalid UTF-8
+    use TableEntryFormat::*;
+    if let Char(_) = format {
+      let txt = String::from_utf8_lossy(raw_field).to_string();
+      return Ok((Self::Text(txt), Some(String::from("replaced invalid UTF-8"))));
+    }
+
+    //(2) Numbers are rewritten into something str::parse can handle
+    let Some((repaired, mut notes)) = ascii_num::repair_number(raw_field) else {
+      return Err(err);
+    };
+    let entry = match format {
+      Int(_) => match (str::parse::<i64>(&repaired), str::parse::<f64>(&repaired)) {
+        (Ok(num), _) => Self::Int(num),
+        //Integers are sometimes written with an exponent (1.0D+03)
+        (_, Ok(num)) if num.fract() == 0.0 && num.abs() < i64::MAX as f64 => {
+          notes.push("read integer written as float");
+          Self::Int(num as i64)
+        }
+        _ => return Err(err),
+      },
+      Float(_) => match str::parse::<f64>(&repaired) {
+        Ok(num) if num.is_finite() => Self::Float(num),
+        _ => return Err(err),
+      },
+      _ => return Err(err),
+    };
+    Ok((entry, Some(notes.join(", "))))
+  }
+
+  pub(crate) fn from_bytes(
+    raw_field: &[u8],
+    format: &TableEntryFormat,
+  ) -> Result<Self, ParseError> {
     //(1) Check if the field is as long as was specified in the format
     if format.get_field_width() != raw_field.len() {
       return Err(FieldSizeMisMatch::new(format, raw_field).into());
     }
 
-    //(2) Match the format (and don't forget to strip spaces of the numeric
-    //    variants before parsing them!)
+    //(2) Match the format. Numbers are parsed straight from the bytes, and
+    //    only fall back to str::parse (mostly for its error) if that fails
     use TableEntryFormat::*;
 
     Ok(match format {
-      Char(_) => Self::Text(String::from(raw_field)),
-      Int(_) => Self::Int(str::parse(raw_field.trim())?),
-      Float(_) => Self::Float(str::parse(raw_field.trim())?),
+      Char(_) => Self::Text(String::from(str::from_utf8(raw_field)?)),
+      Int(_) => Self::Int(match ascii_num::parse_int(raw_field) {
+        Some(num) => num,
+        None => str::parse(str::from_utf8(raw_field)?.trim())?,
+      }),
+      Float(_) => Self::Float(match ascii_num::parse_float(raw_field) {
+        Some(num) => num,
+        //Fortran writes double precision exponents with a D
+        None => str::parse(&str::from_utf8(raw_field)?.trim().replace(['D', 'd'], "E"))?,
+      }),
       Invalid(invalid_format) => {
         return Err(InvalidFFCode::new(invalid_format.to_string()).into());
       }
@@ -76,6 +232,8 @@ impl TableEntry {
       Text(_) => String::from("(string)"),
       Int(_) => String::from("(int)"),
       Float(_) => String::from("(float)"),
+      Complex(..) => String::from("(complex)"),
+      Array(_) => String::from("(array)"),
     }
   }
 
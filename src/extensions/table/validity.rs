@@ -0,0 +1,93 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*  Description:
+    Validity bitmap of a nullable table column. Bit i is set if entry i of
+    the column holds a value, and cleared if it is null. Bits are packed
+    least-significant bit first, like the validity buffers of Apache Arrow,
+    so the bitmap can be handed to Arrow as-is.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validity {
+  bits: Vec<u8>,
+  len: usize,
+}
+
+impl Validity {
+  /*
+      PUBLIC API
+  */
+
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  pub fn is_valid(&self, index: usize) -> bool {
+    //Entries outside of the bitmap do not exist, so they are not valid either
+    index < self.len && self.bits[index / 8] & (1 << (index % 8)) != 0
+  }
+
+  pub fn is_null(&self, index: usize) -> bool {
+    index < self.len && !self.is_valid(index)
+  }
+
+  pub fn null_count(&self) -> usize {
+    self.len - self.bits.iter().map(|byte| byte.count_ones() as usize).sum::<usize>()
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+    (0..self.len).map(|index| self.is_valid(index))
+  }
+
+  pub fn as_bytes(&self) -> &[u8] {
+    //Unused bits in the last byte are always cleared
+    &self.bits
+  }
+
+  /*
+      INTERNAL FUNCS
+  */
+
+  pub(crate) fn with_capacity(capacity: usize) -> Self {
+    Validity { bits: Vec::with_capacity(capacity.div_ceil(8)), len: 0 }
+  }
+
+  pub(crate) fn push(&mut self, valid: bool) {
+    if self.len.is_multiple_of(8) {
+      self.bits.push(0);
+    }
+    self.len += 1;
+    self.set(self.len - 1, valid);
+  }
+
+  pub(crate) fn set(&mut self, index: usize, valid: bool) {
+    match valid {
+      true => self.bits[index / 8] |= 1 << (index % 8),
+      false => self.bits[index / 8] &= !(1 << (index % 8)),
+    }
+  }
+
+  pub(crate) fn append(&mut self, other: &Validity) {
+    other.iter().for_each(|valid| self.push(valid));
+  }
+}
@@ -23,17 +23,35 @@
 
 use core::fmt;
 use std::{
+  collections::HashMap,
   error::Error,
   fmt::{Display, Formatter},
-  path::Path,
+  fs::{self, File},
+  io::{Read, Write},
+  path::{Path, PathBuf},
+  sync::atomic::{AtomicUsize, Ordering},
 };
 
 use crate::{
+  extensions::{image::TypedImage, Extension},
+  fits_index::FitsIndex,
+  hdu_err::{InvalidOrderErr, MissingDataErr, RemoveHduErr},
   header_data_unit::HeaderDataUnit,
+  io_err::{FitsIoErr, TruncatedFileErr},
+  manifest::{self, ManifestEntry},
   raw::{
-    raw_io::{RawFitsReader, RawFitsWriter},
+    block_io::{BlockRead, BlockWrite},
+    raw_io::{self, LockPolicy, RawFitsReader, RawFitsWriter},
+    stream_io::{StreamReader, StreamWriter},
     BlockSized,
   },
+  read_options::ReadOptions,
+  repack::{self, RepackReport},
+  section::ExtendedPath,
+  section_err::HduNotFoundErr,
+  validation::{Diagnostic, Severity, ValidationProfile},
+  validation_err::ValidationErr,
+  write_options::{ExtendPolicy, FitsStandard, WriteOptions},
 };
 
 #[derive(Debug, Clone)]
@@ -42,41 +60,763 @@ pub struct Fits {
 }
 
 impl Fits {
-  pub fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+  pub fn open(path: &Path) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    Self::open_with(path, &ReadOptions::default())
+  }
+
+  pub fn open_with(path: &Path, opts: &ReadOptions) -> Result<Self, Box<dyn Error + Send + Sync>> {
     //(1) Construct a RawFitsReader
     let mut reader = RawFitsReader::new(path)?;
 
-    //(2) Read HDU's from the fits file until it is empty
+    //(2) Read all HDU's from the fits file
+    let hdus = HeaderDataUnit::decode_hdus(&mut reader, opts)?;
+
+    //File is empty, we don't need the reader anymore!
+    // (3) return the completed file
+    Ok(Fits { hdus })
+  }
+
+  pub fn open_lazy(path: &Path) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    Self::open_lazy_with(path, &ReadOptions::default())
+  }
+
+  pub fn open_lazy_with(
+    path: &Path,
+    opts: &ReadOptions,
+  ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    /*  Reads all headers, but none of the data. The data units are left on
+        disk (HeaderDataUnit::is_loaded returns false) until load_data is
+        called on their HDU, which seeks straight to them. Any HDU filter in
+        opts is ignored, since nothing is loaded anyway.
+    */
+    Self::open_with(path, &opts.clone().hdu_filter(|_| false))
+  }
+
+  pub fn read_from(
+    reader: &mut dyn BlockRead,
+    opts: &ReadOptions,
+  ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    /*  Reads a FITS file from an alternative backend (see BlockRead). The HDU's
+        are decoded one after the other, and their data cannot be unloaded
+        since there is no file to reload it from.
+    */
     let mut hdus = Vec::new();
     while reader.get_block_index() < reader.get_block_len() {
-      hdus.push(HeaderDataUnit::decode_hdu(&mut reader)?)
+      hdus.push(HeaderDataUnit::decode_hdu_from(reader, opts)?);
     }
+    Ok(Fits { hdus })
+  }
 
-    //File is empty, we don't need the reader anymore!
-    // (3) return the completed file
-    Ok(Fits { hdus: hdus })
+  pub fn from_stream<R: Read>(stream: R) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    Self::from_stream_with(stream, &ReadOptions::default())
+  }
+
+  pub fn from_stream_with<R: Read>(
+    stream: R,
+    opts: &ReadOptions,
+  ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    /*  Reads a FITS file from a non-seekable stream (a pipe, stdin, a socket),
+        strictly in order. Like with read_from, the data of the HDU's cannot
+        be unloaded (or loaded lazily), since the stream cannot be read again.
+        Wrap unbuffered streams in a BufReader for speed.
+    */
+    let mut reader = StreamReader::new(stream);
+    let mut hdus = Vec::new();
+    while !reader.is_finished()? {
+      hdus.push(HeaderDataUnit::decode_hdu_from(&mut reader, opts)?);
+    }
+    Ok(Fits { hdus })
+  }
+
+  pub fn open_partial(
+    path: &Path,
+    lenient: bool,
+  ) -> Result<(Self, Option<TruncatedFileErr>), Box<dyn Error + Send + Sync>> {
+    /*
+        Opens a FITS file that may have been truncated (during a transfer, for
+        example). All HDU's up to the point where the file was cut off are
+        returned, together with an error describing the truncation (if any).
+        The header of a truncated HDU is kept. In lenient mode, truncated
+        images are kept as well, see HeaderDataUnit::get_valid_len()
+    */
+
+    //(1) Construct a RawFitsReader that accepts incomplete FITS blocks
+    let mut reader = RawFitsReader::new_lenient(path)?;
+
+    //(2) Read HDU's until the file is empty or we run into the truncation
+    let mut hdus = Vec::new();
+    while reader.get_block_index() < reader.get_block_len() {
+      let (hdu, truncated) = HeaderDataUnit::decode_hdu_partial(&mut reader, hdus.len(), lenient)?;
+      hdus.extend(hdu);
+      if truncated.is_some() {
+        return Ok((Fits { hdus }, truncated));
+      }
+    }
+
+    //(R) the file was complete after all
+    Ok((Fits { hdus }, None))
+  }
+
+  pub fn open_detached(
+    header_path: &Path,
+    data_path: &Path,
+  ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    //A detached header and its raw data file are read as a single HDU
+    let mut fits = Fits { hdus: vec![HeaderDataUnit::open_detached(header_path, data_path)?] };
+    fits.fix_structure();
+    Ok(fits)
+  }
+
+  pub fn open_indexed(path: &Path) -> Result<FitsIndex, Box<dyn Error + Send + Sync>> {
+    /*
+        Opens the FITS file using its sidecar index (see FitsIndex), such that
+        individual HDU's can be read without scanning the whole file. Falls
+        back to scanning the headers if there is no (valid) sidecar.
+    */
+    FitsIndex::open(path)
+  }
+
+  pub fn read_preview(
+    path: &Path,
+    index: usize,
+    max_dim: usize,
+  ) -> Result<HeaderDataUnit, Box<dyn Error + Send + Sync>> {
+    /*
+        Reads a downsampled preview of the image in the HDU with the given
+        index. No axis of the preview is longer than max_dim. Rows that are
+        not part of the preview are skipped on disk where possible, so this is
+        much cheaper than opening the whole file for large images. The header
+        of the returned HDU is left untouched (it still describes the full
+        image!)
+    */
+
+    //(1) Construct a RawFitsReader
+    let mut reader = RawFitsReader::new(path)?;
+
+    //(2) Skip the HDU's in front of the one we want
+    for _ in 0..index {
+      HeaderDataUnit::skip_hdu(&mut reader)?;
+    }
+
+    //(3) Read the preview
+    HeaderDataUnit::decode_hdu_preview(&mut reader, max_dim)
+  }
+
+  pub fn read_section(spec: &str) -> Result<TypedImage, Box<dyn Error + Send + Sync>> {
+    /*  Reads (part of) an image given as an extended file name, such as
+        "file.fits[SCI,2][100:200,300:400]". See Section for the syntax.
+    */
+    let spec = ExtendedPath::parse(spec)?;
+    let index = FitsIndex::open(spec.get_path())?;
+    index.read_section(spec.get_hdu(), spec.get_section())
+  }
+
+  pub fn write(self, path: &Path) -> Result<(), Box<dyn Error + Send + Sync>> {
+    self.write_with_lock(path, LockPolicy::NoLock)
   }
 
-  pub fn write(self, path: &Path) -> Result<(), Box<dyn Error>> {
-    //(1) Construct a RawFitsWriter
-    let mut writer = RawFitsWriter::new(path)?;
+  pub fn write_with_lock(
+    self,
+    path: &Path,
+    policy: LockPolicy,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    self.write_with(path, &WriteOptions::new().lock(policy))
+  }
+
+  pub fn write_with(
+    mut self,
+    path: &Path,
+    opts: &WriteOptions,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    self.prepare_write(opts)?;
+
+    if !opts.get_atomic() {
+      //(1) Construct a RawFitsWriter, holding a lock on the file if requested
+      let mut writer = RawFitsWriter::new_with_options(path, opts)?;
+
+      //(2) Write all HDU's to this thing
+      return self.write_hdus(&mut writer, opts);
+    }
+
+    /*  (1)
+        Atomic writes go to a temporary file in the same directory as the
+        target, such that the final rename cannot cross filesystems. The lock
+        (if any) is taken on a separate lock file and held until the rename is
+        done. Locking the target itself would not work: the rename replaces it,
+        after which a second writer could lock the new file while we still
+        hold the lock on the old one. The lock file is left in place, removing
+        it would open up the same race.
+    */
+    let _lock = match opts.get_lock() {
+      LockPolicy::NoLock => None,
+      policy => Some(RawFitsWriter::open_locked(&Self::lock_path(path), policy)?),
+    };
+    let tmp_path = Self::tmp_path(path);
+
+    //(2) Write everything to the temporary file, and remove it if we fail
+    let written =
+      RawFitsWriter::new_with_options(&tmp_path, &opts.clone().lock(LockPolicy::NoLock))
+        .and_then(|mut writer| self.write_hdus(&mut writer, opts))
+        .and_then(|()| {
+          fs::rename(&tmp_path, path)
+            .map_err(|err| FitsIoErr::new(path, "rename temporary file to target", err).into())
+        });
+    if let Err(err) = written {
+      let _ = fs::remove_file(&tmp_path);
+      return Err(err);
+    }
+
+    //(3) Sync the directory, so the rename itself is persisted as well. This
+    //is not possible on all platforms, so failures are ignored
+    if opts.get_fsync() {
+      if let Some(dir) = path.parent().and_then(|dir| File::open(dir).ok()) {
+        let _ = dir.sync_all();
+      }
+    }
+
+    //(R) done
+    Ok(())
+  }
+
+  fn prepare_write(&mut self, opts: &WriteOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    //EXTEND may be forced to reproduce legacy files
+    if let Some(primary) = self.hdus.first_mut().filter(|hdu| hdu.is_primary()) {
+      match opts.get_extend() {
+        ExtendPolicy::Keep => {}
+        ExtendPolicy::True => primary.set_extend(true),
+        ExtendPolicy::False => primary.set_extend(false),
+        ExtendPolicy::Omit => {
+          primary.get_header_mut().remove_record("EXTEND");
+        }
+      }
+    }
+
+    //Files that do not conform to the targeted standard are not written at all
+    let mut errors = self.check_standard(opts.get_standard());
+    for (index, hdu) in self.hdus.iter_mut().enumerate() {
+      let diagnostics = hdu.get_header_mut().repair_charset(opts.get_header_charset());
+      errors.extend(
+        diagnostics
+          .into_iter()
+          .filter(|diagnostic| diagnostic.get_severity() == Severity::Error)
+          .map(|diagnostic| diagnostic.with_hdu(index)),
+      );
+    }
+    if !errors.is_empty() {
+      return Err(Box::new(ValidationErr::new(errors)));
+    }
+    Ok(())
+  }
 
-    //(2) Write all HDU's to this thing
-    for hdu in self.hdus {
-      hdu.encode_hdu(&mut writer)?;
+  pub fn write_to(
+    mut self,
+    writer: &mut dyn BlockWrite,
+    opts: &WriteOptions,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    /*  Writes the file to an alternative backend (see BlockWrite). Options that
+        only apply to regular files (locking, atomic writes, fsync etc.) are
+        ignored.
+    */
+    self.prepare_write(opts)?;
+    HeaderDataUnit::encode_hdus(self.hdus, writer, opts.get_encode_memory())?;
+    writer.flush()
+  }
+
+  pub fn write_stream<W: Write>(
+    self,
+    sink: W,
+    opts: &WriteOptions,
+  ) -> Result<W, Box<dyn Error + Send + Sync>> {
+    /*  Writes the file to a non-seekable sink (a pipe, stdout, an HTTP body).
+        Every HDU is encoded with its final header before anything is written,
+        so nothing has to be fixed up afterwards. The sink is returned once
+        everything was written (and flushed).
+    */
+    let mut writer = StreamWriter::new(sink);
+    self.write_to(&mut writer, opts)?;
+    Ok(writer.into_inner())
+  }
+
+  fn write_hdus(
+    self,
+    writer: &mut RawFitsWriter,
+    opts: &WriteOptions,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    //(1) Write all HDU's to the writer, reserving space for them if requested
+    if opts.get_preallocate() {
+      writer.preallocate(self.get_block_len())?;
     }
+    HeaderDataUnit::encode_hdus(self.hdus, writer, opts.get_encode_memory())?;
 
-    //(3) Flush writer and close the file
+    //(2) Flush writer (and sync if requested) before the file is closed
     writer.flush()?;
+    if opts.get_fsync() {
+      writer.sync()?;
+    }
 
     //(R) done
     Ok(())
   }
 
+  fn tmp_path(path: &Path) -> PathBuf {
+    //Hidden file next to the target, unique per process and per call
+    static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let count = TMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(".{name}.{}.{count}.tmp", std::process::id()))
+  }
+
+  fn lock_path(path: &Path) -> PathBuf {
+    //Hidden file next to the target, shared by all writers of the target
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    path.with_file_name(format!(".{name}.lock"))
+  }
+
+  pub fn concat<P: AsRef<Path>>(
+    paths: &[P],
+    out: &Path,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    /*  Merges FITS files into a single multi-extension file. The primary HDU of
+        the first file stays the primary HDU, the primary HDU's of the other
+        files are turned into IMAGE extensions.
+    */
+    let mut hdus = Vec::new();
+    for path in paths {
+      let mut fits = Self::open(path.as_ref())?;
+      if !hdus.is_empty() {
+        fits.hdus.iter_mut().for_each(HeaderDataUnit::demote_primary);
+      }
+      hdus.append(&mut fits.hdus);
+    }
+
+    //The primary HDU has to announce the extensions
+    if hdus.len() > 1 {
+      hdus[0].announce_extensions();
+    }
+    Fits { hdus }.write(out)
+  }
+
+  pub fn split(path: &Path, out_dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error + Send + Sync>> {
+    /*  Writes every extension of a FITS file to its own file in out_dir, named
+        <stem>_<index>.fits. IMAGE extensions become the primary HDU of their
+        file, other extensions get an empty primary HDU in front of them. The
+        primary HDU is only written (as <stem>_0.fits) if it contains data.
+    */
+    let fits = Self::open(path)?;
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+
+    let mut written = Vec::new();
+    for (index, mut hdu) in fits.hdus.into_iter().enumerate() {
+      if index == 0 && hdu.get_data().is_none() {
+        continue;
+      }
+      let hdus = match hdu.promote_to_primary() {
+        true => vec![hdu],
+        false => vec![HeaderDataUnit::empty_primary(), hdu],
+      };
+      let out = out_dir.join(format!("{stem}_{index}.fits"));
+      Fits { hdus }.write(&out)?;
+      written.push(out);
+    }
+
+    //(R) paths of the files we wrote
+    Ok(written)
+  }
+
+  pub fn repack(path: &Path, out: &Path) -> Result<RepackReport, Box<dyn Error + Send + Sync>> {
+    /*  Rewrites a FITS file in canonical form (standard keyword formatting,
+        normalised padding) with freshly computed CHECKSUM and DATASUM records.
+        The report lists everything that was wrong with the original file.
+    */
+    repack::repack(path, out)
+  }
+
+  pub fn remove_hdu_in_place(
+    path: &Path,
+    index: usize,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    Self::remove_hdu_in_place_with(path, index, &WriteOptions::new())
+  }
+
+  pub fn remove_hdu_in_place_with(
+    path: &Path,
+    index: usize,
+    opts: &WriteOptions,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    /*  Removes an extension from a FITS file on disk without reading or
+        rewriting the other HDU's: the HDU's after it are moved forward and
+        the file is truncated (see WriteOptions::zero_fill). Only the lock,
+        fsync and zero_fill options are used. The primary HDU cannot be
+        removed this way. An index sidecar of the file is updated as well.
+    */
+    //(1) Find the HDU (holding the lock, so the file cannot change under us).
+    //Opening it for reading first makes sure we do not create a new file
+    RawFitsReader::new(path)?;
+    let mut out = RawFitsWriter::open_locked(path, opts.get_lock())?;
+    let index_before = FitsIndex::build(path)?;
+    let n_hdus = index_before.get_num_hdus();
+    let layout = match index_before.get_layout(index) {
+      Some(layout) if index > 0 => *layout,
+      _ => return Err(Box::new(RemoveHduErr::new(index, n_hdus))),
+    };
+
+    //(2) Cut it out
+    let start = raw_io::block_offset(layout.get_start_block());
+    let n_blocks = layout.get_header_blocks() + layout.get_data_blocks();
+    let end = start + raw_io::block_offset(n_blocks);
+    RawFitsWriter::cut_range(&mut out, path, start, end, opts.get_zero_fill())?;
+    if opts.get_fsync() {
+      out.sync_all().map_err(|err| FitsIoErr::new(path, "sync file", err))?;
+    }
+
+    //(3) An outdated sidecar would be ignored, but it might as well be right
+    if FitsIndex::sidecar_path(path).exists() {
+      FitsIndex::build(path)?.write_sidecar()?;
+    }
+    Ok(())
+  }
+
+  pub fn reorder(&mut self, order: &[usize]) -> Result<(), InvalidOrderErr> {
+    /*  Reorders the HDU's: the new i-th HDU is the old order[i]-th HDU. If a
+        different HDU ends up in front, it is promoted to the primary HDU (and
+        the old primary HDU is turned into an IMAGE extension). Extensions that
+        cannot be stored in the primary HDU get an empty primary HDU in front.
+    */
+
+    //(1) The order has to be a permutation of all HDU's
+    let mut seen = vec![false; self.hdus.len()];
+    for &index in order {
+      match seen.get_mut(index) {
+        Some(seen @ false) => *seen = true,
+        _ => return Err(InvalidOrderErr::new(order, self.hdus.len())),
+      }
+    }
+    if order.len() != self.hdus.len() {
+      return Err(InvalidOrderErr::new(order, self.hdus.len()));
+    }
+
+    //(2) Move the HDU's around
+    let mut old: Vec<Option<HeaderDataUnit>> = self.hdus.drain(..).map(Some).collect();
+    self.hdus = order.iter().map(|&index| old[index].take().unwrap()).collect();
+
+    //(3) Fix the structural keywords
+    self.fix_structure();
+    Ok(())
+  }
+
+  pub fn sort_by_extname(&mut self) {
+    /*  Sorts the extensions by EXTNAME (and EXTVER). The primary HDU stays in
+        front, extensions without EXTNAME go last. The sort is stable.
+    */
+    if self.hdus.len() < 2 {
+      return;
+    }
+    let key = |hdu: &HeaderDataUnit| (hdu.extname().is_none(), hdu.extname(), hdu.extver());
+    self.hdus[1..].sort_by_key(key);
+    self.fix_structure();
+  }
+
+  fn fix_structure(&mut self) {
+    //(1) Only the first HDU may be a primary HDU
+    self.hdus.iter_mut().skip(1).for_each(HeaderDataUnit::demote_primary);
+    if let Some(first) = self.hdus.first_mut() {
+      if !first.promote_to_primary() {
+        self.hdus.insert(0, HeaderDataUnit::empty_primary());
+      }
+    }
+    if self.hdus.len() > 1 {
+      self.hdus[0].announce_extensions();
+    }
+
+    /*  (2)
+        HDU's are referred to by EXTNAME and EXTVER, which have to be unique
+        together. HDU's with a duplicated EXTNAME + EXTVER combination (these
+        references were ambiguous anyway) are renumbered in file order. Other
+        EXTVER's are left alone, so references to them remain valid.
+    */
+    let mut taken = std::collections::HashSet::new();
+    let mut duplicates = Vec::new();
+    for (index, hdu) in self.hdus.iter().enumerate() {
+      if let Some(name) = hdu.extname() {
+        if !taken.insert((name, hdu.extver().unwrap_or(1))) {
+          duplicates.push(index);
+        }
+      }
+    }
+    for index in duplicates {
+      let name = self.hdus[index].extname();
+      let mut extver = 1;
+      while taken.contains(&(name.clone().unwrap(), extver)) {
+        extver += 1;
+      }
+      taken.insert((name.unwrap(), extver));
+      self.hdus[index].set_extver(extver);
+    }
+  }
+
+  pub fn validate(&self, profiles: &[ValidationProfile]) -> Vec<Diagnostic> {
+    //Runs all validation profiles on the headers of all HDU's
+    let mut diagnostics = Vec::new();
+    for (index, hdu) in self.hdus.iter().enumerate() {
+      for profile in profiles {
+        diagnostics.append(&mut profile.validate(index, hdu.get_header()));
+      }
+    }
+    diagnostics
+  }
+
+  pub fn check_standard(&self, standard: FitsStandard) -> Vec<Diagnostic> {
+    //Lists everything that keeps the file from conforming to this standard
+    let mut diagnostics = Vec::new();
+    for (index, hdu) in self.hdus.iter().enumerate() {
+      diagnostics.append(&mut standard.check(index, hdu.get_header()));
+    }
+    diagnostics
+  }
+
+  pub fn charset_repairs(&self) -> Vec<Diagnostic> {
+    //Illegal header characters that were replaced when the file was read
+    let mut repairs = Vec::new();
+    for (index, hdu) in self.hdus.iter().enumerate() {
+      let header_repairs = hdu.get_header().get_charset_repairs().iter().cloned();
+      repairs.extend(header_repairs.map(|diagnostic| diagnostic.with_hdu(index)));
+    }
+    repairs
+  }
+
+  pub fn table_repairs(&self) -> Vec<Diagnostic> {
+    //ASCII table fields that were repaired when the file was read
+    let mut repairs = Vec::new();
+    for (index, hdu) in self.hdus.iter().enumerate() {
+      if let Some(Extension::AsciiTable(tbl)) = hdu.get_data() {
+        let table_repairs = tbl.get_field_repairs().iter().cloned();
+        repairs.extend(table_repairs.map(|diagnostic| diagnostic.with_hdu(index)));
+      }
+    }
+    repairs
+  }
+
+  pub fn duplicate_keywords(&self) -> Vec<Diagnostic> {
+    //Keywords that appeared more than once in a header when the file was read
+    let mut duplicates = Vec::new();
+    for (index, hdu) in self.hdus.iter().enumerate() {
+      let header_duplicates = hdu.get_header().get_duplicates().iter().cloned();
+      duplicates.extend(header_duplicates.map(|diagnostic| diagnostic.with_hdu(index)));
+    }
+    duplicates
+  }
+
+  pub fn open_validated(
+    path: &Path,
+    profiles: &[ValidationProfile],
+  ) -> Result<(Self, Vec<Diagnostic>), Box<dyn Error + Send + Sync>> {
+    //Diagnostics are returned alongside the file, even if there are errors
+    let fits = Self::open(path)?;
+    let diagnostics = fits.validate(profiles);
+    Ok((fits, diagnostics))
+  }
+
+  pub fn write_validated(
+    self,
+    path: &Path,
+    profiles: &[ValidationProfile],
+  ) -> Result<Vec<Diagnostic>, Box<dyn Error + Send + Sync>> {
+    //(1) Validate the file before touching the disk
+    let (errors, warnings): (Vec<Diagnostic>, Vec<Diagnostic>) = self
+      .validate(profiles)
+      .into_iter()
+      .partition(|diagnostic| diagnostic.get_severity() == Severity::Error);
+
+    //(2) Refuse to write files with errors
+    if !errors.is_empty() {
+      return Err(Box::new(ValidationErr::new(errors)));
+    }
+    self.write(path)?;
+
+    //(R) the remaining warnings
+    Ok(warnings)
+  }
+
   pub fn get_hdu(&self, index: usize) -> Option<&HeaderDataUnit> {
     self.hdus.get(index)
   }
 
+  pub fn get_hdu_mut(&mut self, index: usize) -> Option<&mut HeaderDataUnit> {
+    self.hdus.get_mut(index)
+  }
+
+  pub fn primary(&self) -> Result<&HeaderDataUnit, MissingDataErr> {
+    self.hdus.first().ok_or(MissingDataErr::new("a primary HDU"))
+  }
+
+  pub fn cutout_sky(
+    &self,
+    hdu: usize,
+    ra_deg: f64,
+    dec_deg: f64,
+    size_arcmin: f64,
+  ) -> Result<Fits, Box<dyn Error + Send + Sync>> {
+    /*  Cuts the square box of size_arcmin centered on the sky position out of
+        the image in the HDU, and returns it as a new FITS file with the WCS
+        adjusted to the cutout. Boxes that stick out of the image are clipped.
+        FitsIndex::cutout_sky does the same, reading only the box from disk.
+    */
+    let source = self.get_hdu(hdu).ok_or_else(|| HduNotFoundErr::new(hdu.to_string()))?;
+    let Some(Extension::Image(img)) = source.get_data() else {
+      return Err(Box::new(MissingDataErr::new("an image")));
+    };
+    let (start, shape) = source.sky_cutout_range(ra_deg, dec_deg, size_arcmin)?;
+    let note = Self::cutout_note(ra_deg, dec_deg, size_arcmin, &start);
+    let hdu = HeaderDataUnit::sky_cutout(
+      source.get_header(),
+      &start,
+      img.cutout(&start, &shape),
+      &shape,
+      &note,
+    );
+    Ok(Fits { hdus: vec![hdu] })
+  }
+
+  pub(crate) fn cutout_note(
+    ra_deg: f64,
+    dec_deg: f64,
+    size_arcmin: f64,
+    start: &[usize],
+  ) -> String {
+    let origin: Vec<String> = start.iter().map(|index| (index + 1).to_string()).collect();
+    format!(
+      "Cutout of {size_arcmin}' around RA = {ra_deg}, Dec = {dec_deg} from pixel ({})",
+      origin.join(",")
+    )
+  }
+
+  pub(crate) fn from_hdus(hdus: Vec<HeaderDataUnit>) -> Self {
+    Fits { hdus }
+  }
+
+  pub fn primary_image(&self) -> Result<&TypedImage, MissingDataErr> {
+    /*  Returns the main image of the file. This is the image in the primary
+        HDU if it has one. Many files (HST, JWST...) leave the primary HDU
+        empty and store their images in extensions, in which case the first
+        image extension is returned instead.
+    */
+    self
+      .hdus
+      .iter()
+      .find_map(|hdu| match hdu.get_data() {
+        Some(Extension::Image(img)) => Some(img),
+        _ => None,
+      })
+      .ok_or(MissingDataErr::new("an image"))
+  }
+
+  pub fn first_table(&self) -> Result<&Extension, MissingDataErr> {
+    //Returns the data of the first ASCII or binary table extension
+    self
+      .hdus
+      .iter()
+      .find_map(|hdu| match hdu.get_data() {
+        Some(tbl @ (Extension::AsciiTable(_) | Extension::BinTable(_))) => Some(tbl),
+        _ => None,
+      })
+      .ok_or(MissingDataErr::new("a table"))
+  }
+
+  pub fn manifest(&self) -> Vec<ManifestEntry> {
+    //Header and data digests of every HDU, for change detection
+    self
+      .hdus
+      .iter()
+      .enumerate()
+      .map(|(index, hdu)| {
+        let data_digest = hdu.get_data().and_then(manifest::data_digest);
+        ManifestEntry::new(index, hdu.get_extname(), hdu.get_header().digest(), data_digest)
+      })
+      .collect()
+  }
+
+  pub fn memory_usage(&self) -> Vec<usize> {
+    //Approximate number of bytes of decoded data held by each HDU
+    self.hdus.iter().map(|hdu| hdu.get_memory_usage()).collect()
+  }
+
+  pub fn get_num_hdus(&self) -> usize {
+    self.hdus.len()
+  }
+
+  pub fn get_by_name(&self, extname: &str, extver: i64) -> Option<&HeaderDataUnit> {
+    self
+      .hdus
+      .iter()
+      .find(|hdu| hdu.extname().as_deref() == Some(extname) && hdu.get_extver() == extver)
+  }
+
+  pub fn get_all_by_name(&self, extname: &str) -> Vec<&HeaderDataUnit> {
+    //All extensions with this EXTNAME, ordered by EXTVER
+    let mut found: Vec<_> =
+      self.hdus.iter().filter(|hdu| hdu.extname().as_deref() == Some(extname)).collect();
+    found.sort_by_key(|hdu| hdu.get_extver());
+    found
+  }
+
+  pub fn push_hdu(&mut self, mut hdu: HeaderDataUnit) {
+    /*  Appends an HDU to the file. If an extension with the same EXTNAME is
+        already present, the new HDU gets the next EXTVER (unless its own
+        EXTVER was still free). The new HDU is turned into an extension if it
+        is not the first HDU of the file.
+    */
+    if let Some(name) = hdu.extname() {
+      let taken: Vec<i64> =
+        self.get_all_by_name(&name).iter().map(|hdu| hdu.get_extver()).collect();
+      if let Some(max) = taken.iter().max() {
+        if hdu.extver().is_none() || taken.contains(&hdu.get_extver()) {
+          hdu.set_extver(max + 1);
+        }
+      }
+    }
+    self.hdus.push(hdu);
+    self.fix_structure();
+  }
+
+  pub fn resolve_duplicates(&mut self) {
+    //Renumbers extensions that share both EXTNAME and EXTVER (see fix_structure)
+    self.fix_structure();
+  }
+
+  pub fn dedup_data(&mut self) -> usize {
+    /*  Finds images with identical data (same BITPIX, shape and pixels) and
+        lets them share a single copy of that data. This saves memory when the
+        same image (e.g. a calibration frame) appears in multiple HDU's.
+        Returns the number of data units that now share an earlier one's data.
+    */
+    let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+    let mut deduped = 0;
+    for index in 0..self.hdus.len() {
+      let Some(Extension::Image(img)) = self.hdus[index].get_data() else {
+        continue;
+      };
+
+      //(1) Digests can collide, so candidates are compared before sharing
+      let candidates = seen.entry(img.digest()).or_default();
+      let original = candidates.iter().find_map(|&other| match self.hdus[other].get_data() {
+        Some(Extension::Image(other)) if other.same_data(img) => Some(other.clone()),
+        _ => None,
+      });
+
+      //(2) Replace the data with a (cheap) clone of the original
+      match original {
+        None => candidates.push(index),
+        Some(original) => {
+          if let Some(Extension::Image(img)) = self.hdus[index].get_data_mut() {
+            *img = original;
+          }
+          deduped += 1;
+        }
+      }
+    }
+    deduped
+  }
+
   pub fn remove_hdu(&mut self, index: usize) -> Option<HeaderDataUnit> {
     if self.hdus.len() < index {
       return None;
@@ -87,7 +827,7 @@ impl Fits {
 
 impl BlockSized for Fits {
   fn get_block_len(&self) -> usize {
-    (&self.hdus).iter().fold(0, |sum, hdu| sum + hdu.get_block_len())
+    self.hdus.iter().fold(0, |sum, hdu| sum + hdu.get_block_len())
   }
 }
 
@@ -100,7 +840,7 @@ impl Display for Fits {
     writeln!(f, ">Total File size in FITS blocks: {}", self.get_block_len())?;
     writeln!(f, ">Number of Header-Data-Units: {}", self.hdus.len())?;
     writeln!(f, ">Contents:")?;
-    for (index, hdu) in (&self.hdus).iter().enumerate() {
+    for (index, hdu) in self.hdus.iter().enumerate() {
       writeln!(f, ">-------------------------------------------------------------------------------\n>  [HDU #{}]", index)?;
       writeln!(f, ">  Total HDU size in FITS blocks: {}", hdu.get_block_len())?;
       writeln!(f, ">    {}", hdu.pretty_print_header())?;
@@ -0,0 +1,438 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    The FitsIndex contains the layout of all HDU's in a FITS file. It can be
+    stored in a small sidecar file next to the FITS file, such that reopening a
+    file with thousands of extensions does not require scanning every header.
+*/
+
+use std::{
+  error::Error,
+  fmt::{self, Display, Formatter},
+  fs,
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex, MutexGuard},
+  time::UNIX_EPOCH,
+};
+
+#[cfg(feature = "mmap")]
+use crate::{
+  extensions::image::MappedPixels,
+  io_err::{self, InvalidFitsFileErr},
+};
+use crate::{
+  extensions::{
+    image::{ImgParser, TypedImage},
+    Extension,
+  },
+  hdu_err::InvalidRecordValueError,
+  header::Header,
+  header_data_unit::HeaderDataUnit,
+  img_err::CutoutRangeErr,
+  io_err::FitsIoErr,
+  keyword_value::unquote,
+  raw::raw_io::{self, RawFitsReader},
+  read_options::ReadOptions,
+  section::{HduSelector, Section},
+  section_err::HduNotFoundErr,
+  tile_cache::TileCache,
+  wcs, Fits,
+};
+
+//Sidecar files are named after the FITS file, with this extension appended
+const SIDECAR_EXT: &str = "toc";
+//First line of every sidecar file
+const SIDECAR_MAGIC: &str = "RUSTRONOMY-FITS TOC 2";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HduLayout {
+  start_block: usize,
+  header_blocks: usize,
+  data_blocks: usize,
+}
+
+impl HduLayout {
+  pub(crate) fn new(start_block: usize, header_blocks: usize, data_blocks: usize) -> Self {
+    HduLayout { start_block, header_blocks, data_blocks }
+  }
+
+  pub fn get_start_block(&self) -> usize {
+    self.start_block
+  }
+  pub fn get_header_blocks(&self) -> usize {
+    self.header_blocks
+  }
+  pub fn get_data_blocks(&self) -> usize {
+    self.data_blocks
+  }
+}
+
+/*
+    Length and modification time of a file. Files that were rewritten since
+    their layout was recorded (almost always) have a different stamp, so
+    layouts with an outdated stamp are not used to seek through the file.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FileStamp {
+  len: u64,
+  mtime_ns: u128, //0 if the platform does not record modification times
+}
+
+impl FileStamp {
+  pub(crate) fn of(path: &Path) -> Result<Self, FitsIoErr> {
+    let meta = fs::metadata(path).map_err(|err| FitsIoErr::new(path, "read file metadata", err))?;
+    let mtime_ns = meta
+      .modified()
+      .ok()
+      .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+      .map_or(0, |time| time.as_nanos());
+    Ok(FileStamp { len: meta.len(), mtime_ns })
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct FitsIndex {
+  path: PathBuf,
+  file_blocks: usize,
+  stamp: FileStamp,
+  hdus: Vec<HduLayout>,
+  cache: Option<Arc<Mutex<TileCache>>>, //shared between clones of the index
+}
+
+impl FitsIndex {
+  /*
+      PUBLIC API
+  */
+
+  pub fn build(path: &Path) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    //(1) Construct a RawFitsReader
+    let mut reader = RawFitsReader::new(path)?;
+
+    //(2) Skip through the file HDU by HDU, only decoding the headers
+    let mut hdus = Vec::new();
+    while reader.get_block_index() < reader.get_block_len() {
+      let start_block = reader.get_block_index();
+      let (header_blocks, data_blocks) = HeaderDataUnit::skip_hdu(&mut reader)?;
+      hdus.push(HduLayout { start_block, header_blocks, data_blocks });
+    }
+
+    Ok(FitsIndex {
+      path: path.to_path_buf(),
+      file_blocks: reader.get_block_len(),
+      stamp: FileStamp::of(path)?,
+      hdus,
+      cache: None,
+    })
+  }
+
+  pub fn open(path: &Path) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    /*
+        Uses the sidecar file if there is one and it still matches the FITS
+        file: both the length and the modification time of the file must be
+        the same as when the sidecar was written. Missing, corrupted or
+        outdated sidecars are silently ignored and the index is built by
+        scanning the file instead.
+    */
+    let stamp = FileStamp::of(path)?;
+    match Self::read_sidecar(path) {
+      Some(index) if index.stamp == stamp => Ok(index),
+      _ => Self::build(path),
+    }
+  }
+
+  pub fn write_sidecar(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let FileStamp { len, mtime_ns } = self.stamp;
+    let mut out = format!("{SIDECAR_MAGIC}\n{} {len} {mtime_ns}\n", self.file_blocks);
+    for hdu in &self.hdus {
+      out += &format!("{} {} {}\n", hdu.start_block, hdu.header_blocks, hdu.data_blocks);
+    }
+    let sidecar = Self::sidecar_path(&self.path);
+    fs::write(&sidecar, out).map_err(|err| FitsIoErr::new(&sidecar, "write index sidecar", err))?;
+    Ok(())
+  }
+
+  pub fn read_hdu(
+    &self,
+    index: usize,
+  ) -> Result<Option<HeaderDataUnit>, Box<dyn Error + Send + Sync>> {
+    let layout = match self.hdus.get(index) {
+      None => return Ok(None),
+      Some(layout) => layout,
+    };
+
+    //Jump straight to the start of the HDU and decode it
+    let mut reader = RawFitsReader::new(&self.path)?;
+    reader.skip_blocks(layout.start_block)?;
+    Ok(Some(HeaderDataUnit::decode_hdu(&mut reader, &ReadOptions::default())?))
+  }
+
+  pub fn read_header(&self, index: usize) -> Result<Option<Header>, Box<dyn Error + Send + Sync>> {
+    //Only decodes the header of the HDU, its data is not read
+    let layout = match self.hdus.get(index) {
+      None => return Ok(None),
+      Some(layout) => layout,
+    };
+    let mut reader = RawFitsReader::new(&self.path)?;
+    reader.seek_block(layout.start_block)?;
+    Ok(Some(Header::decode_header(&mut reader)?))
+  }
+
+  pub fn read_section(
+    &self,
+    hdu: Option<&HduSelector>,
+    section: Option<&Section>,
+  ) -> Result<TypedImage, Box<dyn Error + Send + Sync>> {
+    /*
+        Reads a section of an image (see Section for the syntax). Without HDU
+        selector, the first HDU with an image is used. Without section, the
+        whole image is read. Only the bounding box of the section is read from
+        disk, pixels are skipped (or reversed) afterwards.
+    */
+    //(1) Find the HDU and the shape of its image
+    let index = self.find_image_hdu(hdu)?;
+    let header = self.read_header(index)?.unwrap();
+    let (axes, _) = HeaderDataUnit::img_layout(&header)?;
+
+    //(2) Read the bounding box of the section
+    let ranges = match section {
+      None => axes.iter().map(|&len| (0, len, 1)).collect(),
+      Some(section) => section.resolve(&axes)?,
+    };
+    let start: Vec<usize> = ranges.iter().map(|range| range.0).collect();
+    let shape: Vec<usize> = ranges.iter().map(|range| range.1).collect();
+    let mut img = match self.read_cutout(index, &start, &shape)? {
+      Some(Extension::Image(img)) => img,
+      _ => return Err(Box::new(HduNotFoundErr::new(index.to_string()))),
+    };
+
+    //(3) Apply the steps
+    let steps: Vec<isize> = ranges.iter().map(|range| range.2).collect();
+    if steps.iter().any(|&step| step != 1) {
+      img.subsample(&steps);
+    }
+    Ok(img)
+  }
+
+  fn find_image_hdu(
+    &self,
+    selector: Option<&HduSelector>,
+  ) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    //Index of the HDU selected by the selector (which has to hold an image)
+    let not_found = || {
+      let selector = selector.map(|selector| selector.to_string()).unwrap_or(String::from("*"));
+      Box::new(HduNotFoundErr::new(selector))
+    };
+    for index in 0..self.hdus.len() {
+      let header = self.read_header(index)?.unwrap();
+      let is_img = HeaderDataUnit::is_plain_img(&header)
+        && header.get_value_as::<usize>("NAXIS").unwrap_or(0) > 0;
+      let extname = header.get_value("EXTNAME").and_then(|raw| unquote(raw));
+      let extver = header.get_value_as::<i64>("EXTVER").unwrap_or(1);
+      let selected = match selector {
+        None => is_img,
+        Some(HduSelector::Index(selected)) => *selected == index,
+        Some(HduSelector::Name { extname: name, extver: version }) => {
+          extname.is_some_and(|extname| extname.eq_ignore_ascii_case(name))
+            && version.is_none_or(|version| version == extver)
+        }
+      };
+      match (selected, is_img) {
+        (true, true) => return Ok(index),
+        (true, false) if selector.is_some() => return Err(not_found()),
+        _ => {}
+      }
+    }
+    Err(not_found())
+  }
+
+  pub fn read_cutout(
+    &self,
+    index: usize,
+    start: &[usize],
+    shape: &[usize],
+  ) -> Result<Option<Extension>, Box<dyn Error + Send + Sync>> {
+    /*
+        Reads the part of the image in the HDU with the given index that starts
+        at the (0-based) pixel start and has the given shape. Only the FITS
+        blocks containing the cutout are read. If the index has a TileCache,
+        those blocks are read from the cache whenever possible.
+    */
+    let layout = match self.hdus.get(index) {
+      None => return Ok(None),
+      Some(layout) => layout,
+    };
+
+    //(1) Decode the header to find out what the image looks like
+    let mut reader = RawFitsReader::new(&self.path)?;
+    reader.seek_block(layout.start_block)?;
+    let header = Header::decode_header(&mut reader)?;
+    if !HeaderDataUnit::is_plain_img(&header) {
+      let xtension = header.get_value("XTENSION").cloned().unwrap_or_default();
+      return Err(Box::new(InvalidRecordValueError::new("XTENSION", &xtension, &["'IMAGE   '"])));
+    }
+    let (axes, bitpix) = HeaderDataUnit::img_layout(&header)?;
+
+    //(2) The cutout has to lie within the image
+    let fits = start.len() == axes.len()
+      && shape.len() == axes.len()
+      && (0..axes.len()).all(|i| start[i] + shape[i] <= axes[i]);
+    if !fits {
+      return Err(Box::new(CutoutRangeErr::new(start, shape, &axes)));
+    }
+
+    //(3) Read the required bytes from the cache, or straight from the file
+    let data = (layout.start_block + layout.header_blocks, layout.data_blocks);
+    let mut cache = self.get_tile_cache();
+    let mut fetch = |offset: u64, len: usize| -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+      match cache.as_mut() {
+        Some(cache) => cache.read_bytes(&mut reader, index, data, offset, len),
+        None => {
+          let block_size = crate::BLOCK_SIZE as u64;
+          let first_block = (offset / block_size) as usize;
+          let last_block = ((offset + len as u64 - 1) / block_size) as usize;
+          let mut buf = vec![0u8; (last_block - first_block + 1) * crate::BLOCK_SIZE];
+          reader.seek_block(data.0 + first_block)?;
+          reader.read_blocks(&mut buf)?;
+          let skip = (offset - raw_io::block_offset(first_block)) as usize;
+          Ok(buf[skip..skip + len].to_vec())
+        }
+      }
+    };
+    Ok(Some(ImgParser::decode_cutout(&mut fetch, &axes, bitpix, start, shape)?))
+  }
+
+  pub fn cutout_sky(
+    &self,
+    index: usize,
+    ra_deg: f64,
+    dec_deg: f64,
+    size_arcmin: f64,
+  ) -> Result<Fits, Box<dyn Error + Send + Sync>> {
+    //Like Fits::cutout_sky, but only the FITS blocks of the box are read
+    let header = self.read_header(index)?.ok_or_else(|| HduNotFoundErr::new(index.to_string()))?;
+    let (axes, _) = HeaderDataUnit::img_layout(&header)?;
+    let (start, shape) = wcs::sky_box(&header, &axes, ra_deg, dec_deg, size_arcmin)?;
+    let img = match self.read_cutout(index, &start, &shape)? {
+      Some(Extension::Image(img)) => img,
+      _ => return Err(Box::new(HduNotFoundErr::new(index.to_string()))),
+    };
+    let note = Fits::cutout_note(ra_deg, dec_deg, size_arcmin, &start);
+    Ok(Fits::from_hdus(vec![HeaderDataUnit::sky_cutout(&header, &start, img, &shape, &note)]))
+  }
+
+  #[cfg(feature = "mmap")]
+  pub fn mmap_image(
+    &self,
+    index: usize,
+  ) -> Result<Option<TypedImage>, Box<dyn Error + Send + Sync>> {
+    //Like read_hdu, but the data unit of the image is mapped into memory rather
+    //than read from the file (see mapped_pixels.rs)
+    let header = match self.read_header(index)? {
+      None => return Ok(None),
+      Some(header) => header,
+    };
+    if !HeaderDataUnit::is_plain_img(&header) {
+      let xtension = header.get_value("XTENSION").cloned().unwrap_or_default();
+      return Err(Box::new(InvalidRecordValueError::new("XTENSION", &xtension, &["'IMAGE   '"])));
+    }
+    let (axes, bitpix) = HeaderDataUnit::img_layout(&header)?;
+    let layout = self.hdus[index];
+    let offset = raw_io::block_offset(layout.start_block + layout.header_blocks);
+    let n_bytes = axes
+      .iter()
+      .try_fold(bitpix.to_code().unsigned_abs() as u64 / 8, |n, &ax| n.checked_mul(ax as u64))
+      .ok_or_else(|| InvalidFitsFileErr::new(io_err::DATA_TOO_LARGE))?;
+    let map = MappedPixels::map(&self.path, index, offset, n_bytes)?;
+    Ok(Some(TypedImage::new_mapped(axes, bitpix, map)))
+  }
+
+  pub fn with_tile_cache(mut self, cache: TileCache) -> Self {
+    self.cache = Some(Arc::new(Mutex::new(cache)));
+    self
+  }
+
+  pub fn get_tile_cache(&self) -> Option<MutexGuard<'_, TileCache>> {
+    //A poisoned cache is still perfectly usable
+    self.cache.as_ref().map(|cache| cache.lock().unwrap_or_else(|err| err.into_inner()))
+  }
+
+  pub fn get_layout(&self, index: usize) -> Option<&HduLayout> {
+    self.hdus.get(index)
+  }
+
+  pub fn get_num_hdus(&self) -> usize {
+    self.hdus.len()
+  }
+
+  pub(crate) fn get_layouts(&self) -> &[HduLayout] {
+    &self.hdus
+  }
+
+  pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(SIDECAR_EXT);
+    PathBuf::from(name)
+  }
+
+  /*
+      INTERNAL FUNCS
+  */
+
+  fn read_sidecar(path: &Path) -> Option<Self> {
+    let text = fs::read_to_string(Self::sidecar_path(path)).ok()?;
+    let mut lines = text.lines();
+    if lines.next()? != SIDECAR_MAGIC {
+      return None;
+    }
+    let mut stamp = lines.next()?.split_whitespace();
+    let file_blocks = stamp.next()?.parse().ok()?;
+    let stamp =
+      FileStamp { len: stamp.next()?.parse().ok()?, mtime_ns: stamp.next()?.parse().ok()? };
+
+    let mut hdus = Vec::new();
+    for line in lines {
+      let fields =
+        line.split_whitespace().map(str::parse).collect::<Result<Vec<usize>, _>>().ok()?;
+      match fields[..] {
+        [start_block, header_blocks, data_blocks] => {
+          hdus.push(HduLayout { start_block, header_blocks, data_blocks })
+        }
+        _ => return None,
+      }
+    }
+
+    Some(FitsIndex { path: path.to_path_buf(), file_blocks, stamp, hdus, cache: None })
+  }
+}
+
+impl Display for FitsIndex {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    writeln!(f, ">FITS index of {} ({} blocks)", self.path.display(), self.file_blocks)?;
+    for (index, hdu) in self.hdus.iter().enumerate() {
+      writeln!(
+        f,
+        ">  [HDU #{index}] start: {}, header: {}, data: {}",
+        hdu.start_block, hdu.header_blocks, hdu.data_blocks
+      )?;
+    }
+    Ok(())
+  }
+}
@@ -18,26 +18,56 @@
 */
 
 use std::{
+  collections::HashMap,
   error::Error,
   fmt::{self, Display},
-  rc::Rc,
   str::FromStr,
+  sync::Arc,
 };
 
 use chrono::{Datelike, Utc};
 use indexmap::IndexMap;
 
 use crate::{
+  charset::{self, CharsetPolicy},
+  coord_columns::SexagesimalUnit,
+  duplicates::{self, DuplicatePolicy, ValueLists},
   hdu_err::MissingRecordError,
+  header_err::TextRecordErr,
+  hierarch::HierarchNode,
+  keyword_err::StructuralKeywordErr,
+  keyword_value::{KeywordValue, MetaValue},
+  manifest, obs_keywords,
   raw::{
+    block_io::{BlockRead, BlockWrite},
     header_block::HeaderBlock,
-    keyword_record::KeywordRecord,
-    raw_io::{RawFitsReader, RawFitsWriter},
+    keyword_record::{KeywordRecord, HIERARCH},
     BlockSized,
   },
+  validation::Diagnostic,
 };
 
 const BLOCK_SIZE: usize = crate::BLOCK_SIZE;
+//Max. length of the text in a single COMMENT or HISTORY record
+const COMMENTARY_LEN: usize = 72;
+
+#[derive(Debug, Clone)]
+struct Commentary {
+  /*
+      A COMMENT, HISTORY or blank-keyword record. Commentary is kept in its
+      place by the keyword of the valued record that it follows (None if it
+      comes before all of them), so it stays there when records are added.
+  */
+  keyword: Arc<String>,
+  text: String,
+  after: Option<Arc<String>>,
+}
+
+enum Card<'a> {
+  //A record of the header in file order, see Header::cards
+  Record(&'a KeywordRecord),
+  Commentary(&'a Commentary),
+}
 
 /*
     Public version of the header is a Simple IndexMap with a wrapper around it
@@ -45,12 +75,31 @@ const BLOCK_SIZE: usize = crate::BLOCK_SIZE;
 */
 #[derive(Debug, Clone)]
 pub struct Header {
-  records: IndexMap<Rc<String>, KeywordRecord>,
+  records: IndexMap<Arc<String>, KeywordRecord>,
+  commentary: Vec<Commentary>, //COMMENT, HISTORY and blank records, in order
   block_len: usize,
+  repairs: Vec<Diagnostic>, //illegal characters that were replaced when reading
+  value_lists: ValueLists,  //all values of repeated keywords (KeepAllAsList)
+  duplicates: Vec<Diagnostic>, //repeated keywords found when reading
 }
 
 impl Header {
-  pub fn decode_header(raw: &mut RawFitsReader) -> Result<Self, Box<dyn Error>> {
+  pub fn decode_header(raw: &mut dyn BlockRead) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    Self::decode_header_with(raw, CharsetPolicy::Strict)
+  }
+
+  pub fn decode_header_with(
+    raw: &mut dyn BlockRead,
+    charset: CharsetPolicy,
+  ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    Self::decode_header_opts(raw, charset, DuplicatePolicy::default())
+  }
+
+  pub(crate) fn decode_header_opts(
+    raw: &mut dyn BlockRead,
+    charset: CharsetPolicy,
+    duplicates: DuplicatePolicy,
+  ) -> Result<Self, Box<dyn Error + Send + Sync>> {
     /*  Setup:
         We'll keep reading headerblocks (= FITS blocks) until we encounter
         the END keyword. We'll also have to keep track of the block size of
@@ -59,11 +108,12 @@ impl Header {
     let (mut hbs, mut end) = (Vec::<HeaderBlock>::new(), false);
     let mut hb_buf = vec![0u8; 2880]; //1 FITS block
     let mut block_len = 0usize;
+    let mut repairs = Vec::new();
 
     while !end {
       //Read the next headerblock (2880 bytes) and decode it!
       block_len += raw.read_blocks(&mut hb_buf)?;
-      let (hb, finished) = HeaderBlock::decode_from_bytes(&hb_buf)?;
+      let (hb, finished) = Self::decode_block(&mut hb_buf, charset, &mut repairs)?;
 
       //Append the keywords that we found
       hbs.push(hb);
@@ -72,62 +122,148 @@ impl Header {
       end = finished;
     }
 
-    Ok(Self::from_parts(hbs, block_len)?)
+    let mut header = Self::from_parts(hbs, block_len, duplicates)?;
+    header.repairs = repairs;
+    Ok(header)
+  }
+
+  /*
+      Detached headers are stored as plain text next to a raw data file, with
+      one record per line (or as one long line of 80 character records). They
+      are used by some (radio) archives for data that has no FITS structure.
+  */
+  pub fn from_text(text: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    //(1) Split the text into records
+    let lines: Vec<&[u8]> = match text.contains('\n') {
+      true => text.lines().map(str::as_bytes).collect(),
+      false => text.as_bytes().chunks(80).collect(),
+    };
+
+    //(2) Pad the records to 80 chars, up to and including the END record
+    let is_end = |card: &[u8]| card.starts_with(b"END") && card[3..].iter().all(|&b| b == b' ');
+    let mut buf = Vec::new();
+    for (line_no, line) in lines.into_iter().enumerate() {
+      if line.len() > 80 {
+        return Err(Box::new(TextRecordErr::new(line_no + 1, line.len())));
+      }
+      let start = buf.len();
+      buf.extend_from_slice(line);
+      buf.resize(start + 80, b' ');
+      if is_end(&buf[start..]) {
+        break;
+      }
+    }
+    if !buf.rchunks(80).next().is_some_and(is_end) {
+      buf.extend(format!("{:<80}", "END").bytes());
+    }
+    buf.resize(buf.len().div_ceil(BLOCK_SIZE) * BLOCK_SIZE, b' ');
+
+    //(3) Decode the blocks like we would when reading a FITS file
+    let (mut hbs, mut repairs) = (Vec::new(), Vec::new());
+    for block in buf.chunks_exact_mut(BLOCK_SIZE) {
+      hbs.push(Self::decode_block(block, CharsetPolicy::Strict, &mut repairs)?.0);
+    }
+    Self::from_parts(hbs, buf.len() / BLOCK_SIZE, DuplicatePolicy::default())
+  }
+
+  pub fn to_text(&self) -> Result<String, Box<dyn Error + Send + Sync>> {
+    //The records as they would be written to a FITS file, one per line
+    let buf = self.encode_records()?;
+    Ok(
+      buf
+        .chunks(80)
+        .map(|card| format!("{}\n", String::from_utf8_lossy(card).trim_end()))
+        .collect(),
+    )
+  }
+
+  fn decode_block(
+    block: &mut [u8],
+    charset: CharsetPolicy,
+    repairs: &mut Vec<Diagnostic>,
+  ) -> Result<(HeaderBlock, bool), Box<dyn Error + Send + Sync>> {
+    //Check the records for illegal characters (up to the END record)
+    for card in block.chunks_exact_mut(80) {
+      if card.starts_with(b"END     ") {
+        break;
+      }
+      repairs.extend(charset::repair_card(card, charset)?);
+    }
+    Ok(HeaderBlock::decode_from_bytes(block)?)
   }
 
-  fn from_parts(hbs: Vec<HeaderBlock>, block_len: usize) -> Result<Self, Box<dyn Error>> {
+  fn from_parts(
+    hbs: Vec<HeaderBlock>,
+    block_len: usize,
+    duplicates: DuplicatePolicy,
+  ) -> Result<Self, Box<dyn Error + Send + Sync>> {
     //Parse the Keywordrecords to plain Key-Data pairs
-    let mut parsed_map: IndexMap<Rc<String>, KeywordRecord> = IndexMap::new();
+    let mut parsed: Vec<KeywordRecord> = Vec::new();
 
-    //Keep track of the last keyword for multi-keyword strings
-    let mut last_keyword = String::from("");
+    //Commentary records are kept separately, since they may be repeated
+    let mut commentary = Vec::new();
 
     for hb in hbs {
       for unparsed_record in hb.records {
         //Deal with multi-line strings
         match unparsed_record.keyword.as_str() {
+          "COMMENT" | "HISTORY" | "" => {
+            //Records with a blank keyword are commentary used for layout only
+            commentary.push(Commentary {
+              keyword: unparsed_record.keyword,
+              text: unparsed_record.comment.unwrap_or_default(),
+              after: parsed.last().map(|record| record.keyword.clone()),
+            });
+            continue;
+          }
           "CONTINUE" => {
-            //This record actually belongs to the previous keyword!
-            //Should never panic... hopefully
-            let last_parsed = parsed_map.get_mut(&last_keyword).unwrap();
-
-            //(1) remove the trailing {'&} from the previous record's
-            //value
-            last_parsed.value.as_mut().unwrap().pop();
-            last_parsed.value.as_mut().unwrap().pop();
-
-            //(2) append the continued value
-            last_parsed.value.as_mut().unwrap().push_str(unparsed_record.value.unwrap().as_str());
-
-            //(3) do not append keyword-record pair as separate entry
+            /*  This record actually belongs to the previous keyword! The
+                previous value ends with &' and the continued value starts
+                with a quote, both are removed when joining them.
+            */
+            let continued = unparsed_record.value.unwrap_or_default();
+            let last_parsed = parsed.last_mut();
+            if let Some(value) = last_parsed.and_then(|record| record.value.as_mut()) {
+              if value.ends_with("&'") && continued.starts_with('\'') {
+                value.truncate(value.len() - 2);
+                value.push_str(&continued[1..]);
+              }
+            }
+            if let (Some(comment), Some(record)) = (unparsed_record.comment, parsed.last_mut()) {
+              record.comment = Some(comment);
+            }
+
+            //do not append keyword-record pair as separate entry
             continue;
           }
           _ => {} //do nothing
         }
 
-        //update last keyword
-        last_keyword = (*unparsed_record.keyword).clone();
-
         //and add our beautiful string
-        parsed_map.insert(unparsed_record.keyword.clone(), unparsed_record);
+        parsed.push(unparsed_record);
       }
     }
 
-    Ok(Header { records: parsed_map, block_len: block_len })
+    //Repeated keywords are resolved according to the duplicate policy
+    let (records, value_lists, diagnostics) = duplicates::resolve(parsed, duplicates)?;
+
+    Ok(Header {
+      records,
+      commentary,
+      block_len,
+      repairs: Vec::new(),
+      value_lists,
+      duplicates: diagnostics,
+    })
   }
 
-  pub fn encode_header(self, writer: &mut RawFitsWriter) -> Result<(), Box<dyn Error>> {
+  pub fn encode_header(
+    self,
+    writer: &mut dyn BlockWrite,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
     //Buffer to write whole header in one go.
     //Also keeps track of number of bytes we wrote to the header!
-    let mut buf = Vec::new();
-
-    for (_, record) in self.records {
-      record.encode_fill_buff(&mut buf)?;
-    }
-
-    //We musn't forget to add an END keyword!
-    KeywordRecord { keyword: Rc::new(String::from("END     ")), value: None, comment: None }
-      .encode_fill_buff(&mut buf)?;
+    let mut buf = self.encode_records()?;
 
     //make sure that the size of the whole header is an integer multiple
     //of the block size. Btw we fill it with spaces not zeroes
@@ -142,19 +278,71 @@ impl Header {
     Ok(())
   }
 
+  fn encode_records(&self) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let mut buf = Vec::new();
+    for card in self.cards() {
+      match card {
+        Card::Record(record) => record.clone().encode_fill_buff(&mut buf)?,
+        Card::Commentary(com) => {
+          KeywordRecord::encode_commentary(&com.keyword, &com.text, &mut buf)
+        }
+      }
+    }
+
+    //We musn't forget to add an END keyword!
+    KeywordRecord { keyword: Arc::new(String::from("END     ")), value: None, comment: None }
+      .encode_fill_buff(&mut buf)?;
+    Ok(buf)
+  }
+
+  fn cards(&self) -> Vec<Card<'_>> {
+    /*  All records in file order: each valued record is followed by the
+        commentary that came after it. Commentary of records that were removed
+        goes at the end.
+    */
+    let (mut first, mut orphans) = (Vec::new(), Vec::new());
+    let mut anchored: HashMap<&str, Vec<&Commentary>> = HashMap::new();
+    for com in &self.commentary {
+      match &com.after {
+        None => first.push(com),
+        Some(keyword) if self.records.contains_key(keyword) => {
+          anchored.entry(keyword.as_str()).or_default().push(com)
+        }
+        Some(_) => orphans.push(com),
+      }
+    }
+
+    let mut cards: Vec<Card> = first.into_iter().map(Card::Commentary).collect();
+    for (keyword, record) in &self.records {
+      cards.push(Card::Record(record));
+      let after = anchored.remove(keyword.as_str()).unwrap_or_default();
+      cards.extend(after.into_iter().map(Card::Commentary));
+    }
+    cards.extend(orphans.into_iter().map(Card::Commentary));
+    cards
+  }
+
   pub(crate) fn new() -> Self {
     /*
         Creates new empty header.
         pub(crate) since the end user should only create empty HDU's, not
         bare headers!
     */
-    let mut header = Header {
-      records: IndexMap::new(),
-      block_len: 0, //contains nothing
-    };
+    let mut header = Self::empty();
     //we modified the header, so we should indicate that!
     header.update_last_modified();
-    return header;
+    header
+  }
+
+  fn empty() -> Self {
+    Header {
+      records: IndexMap::new(),
+      commentary: Vec::new(),
+      block_len: 0, //contains nothing
+      repairs: Vec::new(),
+      value_lists: IndexMap::new(),
+      duplicates: Vec::new(),
+    }
   }
 
   /*
@@ -172,6 +360,35 @@ impl Header {
     }
   }
 
+  pub fn get_charset_repairs(&self) -> &[Diagnostic] {
+    //Illegal characters that were replaced when this header was read
+    &self.repairs
+  }
+
+  pub fn get_duplicates(&self) -> &[Diagnostic] {
+    //Keywords that appeared more than once when this header was read
+    &self.duplicates
+  }
+
+  pub fn get_all_values(&self, keyword: &str) -> Vec<&String> {
+    //All values of a keyword, which only differs from get_value for repeated
+    //keywords read with the KeepAllAsList duplicate policy
+    match self.value_lists.get(&keyword.to_string()) {
+      Some(values) => values.iter().collect(),
+      None => self.get_value(keyword).into_iter().collect(),
+    }
+  }
+
+  pub fn keywords(&self) -> impl Iterator<Item = &str> {
+    //Keywords of all valued records, in order (without COMMENT and HISTORY)
+    self.records.keys().map(|keyword| keyword.as_str())
+  }
+
+  pub fn digest(&self) -> u64 {
+    //Stable digest of the normalized records, see manifest.rs
+    manifest::header_digest(self)
+  }
+
   pub fn get_comment(&self, keyword: &str) -> Option<&String> {
     match self.records.get(&keyword.to_string()) {
       Some(record) => record.comment.as_ref(),
@@ -179,6 +396,49 @@ impl Header {
     }
   }
 
+  /*
+      COMMENT and HISTORY records are not part of the keyword map, since they
+      may occur many times in a single header. Their text can be read and
+      appended to (in order) using the following funcs. Text that is too long
+      for a single record is split over multiple records. Appended text goes
+      after the records that are in the header at that moment.
+  */
+  pub fn comments(&self) -> impl Iterator<Item = &str> {
+    self.commentary_of("COMMENT")
+  }
+
+  pub fn history(&self) -> impl Iterator<Item = &str> {
+    self.commentary_of("HISTORY")
+  }
+
+  pub fn append_comment(&mut self, text: &str) {
+    self.append_commentary("COMMENT", text)
+  }
+
+  pub fn append_history(&mut self, text: &str) {
+    self.append_commentary("HISTORY", text)
+  }
+
+  fn commentary_of(&self, keyword: &'static str) -> impl Iterator<Item = &str> {
+    self.commentary.iter().filter(move |com| *com.keyword == keyword).map(|com| com.text.as_str())
+  }
+
+  fn append_commentary(&mut self, keyword: &str, text: &str) {
+    let keyword = Arc::new(keyword.to_string());
+    let after = self.records.keys().last().cloned();
+    let chars: Vec<char> = text.chars().collect();
+    let mut texts: Vec<String> =
+      chars.chunks(COMMENTARY_LEN).map(|chunk| chunk.iter().collect()).collect();
+    if texts.is_empty() {
+      texts.push(String::new());
+    }
+    self.commentary.extend(texts.into_iter().map(|text| Commentary {
+      keyword: keyword.clone(),
+      text,
+      after: after.clone(),
+    }));
+  }
+
   pub(crate) fn update_last_modified(&mut self) {
     /*
         This function modifies the DATE keyword in the primary header which
@@ -197,7 +457,7 @@ impl Header {
     );
 
     //create the keyword record and update the internal IndexMap
-    let key = Rc::new(String::from("DATE"));
+    let key = Arc::new(String::from("DATE"));
     let date = KeywordRecord::from_string(
       key.clone(),
       now_fmtd,
@@ -207,20 +467,228 @@ impl Header {
   }
 
   //Helper function for parsing keyword records
-  pub fn get_value_as<T>(&self, keyword: &str) -> Result<T, Box<dyn Error>>
+  pub fn get_value_as<T>(&self, keyword: &str) -> Result<T, Box<dyn Error + Send + Sync>>
   where
     T: FromStr,
-    <T as FromStr>::Err: 'static + Error,
+    <T as FromStr>::Err: 'static + Error + Send + Sync,
   {
-    match self.get_value(&keyword.to_string()) {
+    match self.get_value(keyword) {
       None => Err(MissingRecordError::new(keyword))?,
       Some(val) => Ok(str::parse::<T>(val)?),
     }
   }
 
+  //Helper functions for keyword values with a custom syntax
+  pub fn get_value_with<T: KeywordValue>(
+    &self,
+    keyword: &str,
+  ) -> Result<T, Box<dyn Error + Send + Sync>> {
+    match self.get_value(keyword) {
+      None => Err(MissingRecordError::new(keyword))?,
+      Some(val) => T::parse_value(val),
+    }
+  }
+
+  /*
+      Angles that may be written as degrees or as sexagesimal strings, see
+      obs_keywords.rs. The unit only applies to sexagesimal values. The
+      pointing is read from RA and DEC, or else from OBJCTRA and OBJCTDEC.
+  */
+  pub fn get_angle_deg(
+    &self,
+    keyword: &str,
+    unit: SexagesimalUnit,
+  ) -> Result<f64, Box<dyn Error + Send + Sync>> {
+    obs_keywords::get_angle(self, keyword, unit)
+  }
+  pub fn get_ra_deg(&self) -> Result<f64, Box<dyn Error + Send + Sync>> {
+    obs_keywords::get_ra(self)
+  }
+  pub fn get_dec_deg(&self) -> Result<f64, Box<dyn Error + Send + Sync>> {
+    obs_keywords::get_dec(self)
+  }
+
+  pub fn set_value_with<T: KeywordValue>(
+    &mut self,
+    keyword: &str,
+    value: &T,
+    comment: Option<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    //Structural keywords follow from the data, so they may not be set at all
+    let normalized = keyword.trim().to_ascii_uppercase();
+    if KeywordRecord::is_structural(&normalized) {
+      return Err(Box::new(StructuralKeywordErr::new(&normalized)));
+    }
+
+    //Protected keywords may not be set by the user
+    let record = KeywordRecord::new(keyword, Some(value.format_value()), comment)?;
+    self.records.insert(record.keyword.clone(), record);
+    Ok(())
+  }
+
+  /*
+      Keywords that follow the ESO HIERARCH convention can be accessed as a
+      tree. The root level of the tree has to be specified (usually ESO). If
+      there are no HIERARCH keywords with this root, the tree is empty.
+  */
+  pub fn hierarch(&self, root: &str) -> HierarchNode {
+    let prefix = format!("{HIERARCH} {root} ");
+    let mut tree = HierarchNode::default();
+    for (keyword, record) in &self.records {
+      if let Some(path) = keyword.strip_prefix(&prefix) {
+        let levels: Vec<&str> = path.split(' ').collect();
+        tree.insert(&levels, record.value.clone(), record.comment.clone());
+      }
+    }
+    tree
+  }
+
+  pub fn set_hierarch(
+    &mut self,
+    path: &str,
+    value: String,
+    comment: Option<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    //Path includes the root, with the levels separated by spaces
+    let keyword = Arc::new(KeywordRecord::hierarch_keyword(path));
+    let record = KeywordRecord { keyword: keyword.clone(), value: Some(value), comment };
+
+    //Make sure the record can actually be written
+    record.clone().encode_fill_buff(&mut Vec::new())?;
+    self.records.insert(keyword, record);
+    Ok(())
+  }
+
+  /*
+      All records as typed key-value pairs, in file order. COMMENT and HISTORY
+      records are included as strings, blank-keyword records and record
+      comments are not. from_metadata_pairs does the inverse, so a header can
+      be dumped to a log or database and rebuilt.
+  */
+  pub fn metadata_pairs(&self) -> Vec<(String, MetaValue)> {
+    let pair = |card| match card {
+      Card::Record(record) => {
+        Some(((*record.keyword).clone(), MetaValue::from_raw(record.value.as_deref())))
+      }
+      Card::Commentary(com) if com.keyword.is_empty() => None,
+      Card::Commentary(com) => Some(((*com.keyword).clone(), MetaValue::String(com.text.clone()))),
+    };
+    self.cards().into_iter().filter_map(pair).collect()
+  }
+
+  pub fn from_metadata_pairs(
+    pairs: &[(String, MetaValue)],
+  ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    let mut header = Self::empty();
+    for (keyword, value) in pairs {
+      match (keyword.as_str(), value) {
+        ("COMMENT", MetaValue::String(text)) => header.append_comment(text),
+        ("HISTORY", MetaValue::String(text)) => header.append_history(text),
+        _ => {
+          //Make sure the record can actually be written
+          let key = Arc::new(keyword.clone());
+          let record =
+            KeywordRecord { keyword: key.clone(), value: value.to_raw()?, comment: None };
+          record.clone().encode_fill_buff(&mut Vec::new())?;
+          header.records.insert(key, record);
+        }
+      }
+    }
+    Ok(header)
+  }
+
   pub fn get_num_records(&self) -> usize {
     self.records.len()
   }
+
+  /*
+      Structural records (SIMPLE, XTENSION etc.) are protected, so they cannot
+      be set with set_value_with. These funcs are used internally to restructure
+      headers, for example when an HDU is promoted to the primary HDU.
+  */
+  pub(crate) fn set_record_at(&mut self, index: usize, keyword: &str, value: &str) {
+    //Inserts the record (or replaces its value) and moves it to the index
+    let key = Arc::new(keyword.to_string());
+    let comment = self.get_comment(keyword).cloned();
+    let record = KeywordRecord::from_string(key.clone(), value.to_string(), comment);
+    let (old_index, _) = self.records.insert_full(key, record);
+    self.records.move_index(old_index, index.min(self.records.len() - 1));
+  }
+
+  pub(crate) fn put_record(&mut self, keyword: &str, value: String, comment: Option<String>) {
+    //Replaces the record in place (comment included), or appends it
+    let key = Arc::new(keyword.to_string());
+    self.records.insert(key.clone(), KeywordRecord::from_string(key, value, comment));
+  }
+
+  pub(crate) fn rename_record(&mut self, keyword: &str, new_keyword: &str) {
+    //Gives a record a new keyword, keeping its position, value and comment
+    let Some((index, _, record)) = self.records.shift_remove_full(&keyword.to_string()) else {
+      return;
+    };
+    let key = Arc::new(new_keyword.to_string());
+    self.move_commentary(keyword, Some(key.clone()));
+    let record =
+      KeywordRecord::from_string(key.clone(), record.value.unwrap_or_default(), record.comment);
+    let (old_index, _) = self.records.insert_full(key, record);
+    self.records.move_index(old_index, index.min(self.records.len() - 1));
+  }
+
+  pub(crate) fn merge_records(&mut self, other: &Header) {
+    //Copies the non-structural records of another header (commentary included)
+    for (keyword, record) in &other.records {
+      if !KeywordRecord::is_structural(keyword) {
+        self.records.insert(keyword.clone(), record.clone());
+      }
+    }
+    self.commentary.extend(other.commentary.iter().cloned());
+  }
+
+  pub(crate) fn remove_record(&mut self, keyword: &str) -> Option<KeywordRecord> {
+    //Commentary that followed the record now follows the record before it
+    let (index, _, record) = self.records.shift_remove_full(&keyword.to_string())?;
+    let previous = index.checked_sub(1).and_then(|index| self.records.get_index(index));
+    self.move_commentary(keyword, previous.map(|(key, _)| key.clone()));
+    Some(record)
+  }
+
+  fn move_commentary(&mut self, keyword: &str, after: Option<Arc<String>>) {
+    //Re-anchors the commentary that followed a record
+    for com in &mut self.commentary {
+      if com.after.as_ref().is_some_and(|key| key.as_str() == keyword) {
+        com.after = after.clone();
+      }
+    }
+  }
+
+  pub(crate) fn repair_charset(&mut self, charset: CharsetPolicy) -> Vec<Diagnostic> {
+    //Checks (and with a lenient policy, fixes) the text of all records
+    let mut diagnostics = Vec::new();
+    let mut records = IndexMap::new();
+    for (keyword, mut record) in std::mem::take(&mut self.records) {
+      let mut key = (*keyword).clone();
+      diagnostics.extend(charset::repair_text(&key.clone(), &mut key, charset));
+      for text in [&mut record.value, &mut record.comment].into_iter().flatten() {
+        diagnostics.extend(charset::repair_text(&key, text, charset));
+      }
+      let key = Arc::new(key);
+      if *key != *keyword {
+        self.move_commentary(&keyword, Some(key.clone()));
+      }
+      record.keyword = key.clone();
+      records.insert(key, record);
+    }
+    self.records = records;
+
+    for com in &mut self.commentary {
+      diagnostics.extend(charset::repair_text(&com.keyword, &mut com.text, charset));
+    }
+    diagnostics
+  }
+
+  pub(crate) fn index_of(&self, keyword: &str) -> Option<usize> {
+    self.records.get_index_of(&keyword.to_string())
+  }
 }
 
 impl BlockSized for Header {
@@ -236,10 +704,12 @@ impl Display for Header {
       ">================================<|FITS Header|>================================"
     )?;
     writeln!(f, ">Size in FITS blocks: {}", self.block_len)?;
-    for (_, record) in &self.records {
-      writeln!(f, ">  {record}")?;
-      //Not flushing the formatter causes issues with the ordering of the
-      //keyword records!
+    for card in self.cards() {
+      match card {
+        Card::Record(record) => writeln!(f, ">  {record}")?,
+        Card::Commentary(com) if com.keyword.is_empty() => {} //layout only
+        Card::Commentary(com) => writeln!(f, ">  [{}] {}", com.keyword, com.text)?,
+      }
     }
     writeln!(
       f,
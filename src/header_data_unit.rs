@@ -18,25 +18,76 @@
 */
 
 use core::fmt;
-use std::{borrow::Cow, error::Error, fmt::Display};
+use std::{any::Any, borrow::Cow, error::Error, fmt::Display, fs, path::Path, sync::Arc};
+
+use ndarray::{Array, ArrayViewD, IxDyn};
+use num_complex::Complex;
+use rayon::prelude::*;
 
 use crate::{
   bitpix::Bitpix,
-  extensions::{image::ImgParser, table::AsciiTblParser, Extension},
+  column_image,
+  extensions::{
+    image::{ImgParser, TypedImage},
+    table::{
+      ascii_tbl_parser::AsciiLayout,
+      bin_tbl_parser::{BinField, BinLayout},
+      compressed_table::{self, TileCompression, ZTableLayout},
+      AsciiTblParser, BinFormat, BinTblParser,
+    },
+    Extension,
+  },
+  fits_index::HduLayout,
   hdu_err::*,
   header::Header,
+  io_err::{self, FitsIoErr, InvalidFitsFileErr, TruncatedFileErr},
+  keyword_value::{unquote, MetaValue},
   raw::{
-    raw_io::{RawFitsReader, RawFitsWriter},
+    block_io::{BlockRead, BlockWrite},
+    raw_io::{self, RawFitsReader, RawFitsWriter},
+    stream_io::{StreamReader, StreamWriter},
     BlockSized,
   },
+  read_options::ReadOptions,
+  user_data::UserData,
+  wcs,
 };
 
-const VALID_EXTENSION_NAMES: [&'static str; 3] = ["'IMAGE   '", "'TABLE   '", "'BINTABLE'"];
+//Marker keyword for images with an interleaved real/imaginary NAXIS1
+pub(crate) const COMPLEX_MARKER: &str = "COMPLEX";
+//Marker keyword for BITPIX = 16 images that contain half-precision floats
+pub(crate) const HALF_FLOAT_MARKER: &str = "HALFPREC";
+const VALID_EXTENSION_NAMES: [&str; 3] = ["'IMAGE   '", "'TABLE   '", "'BINTABLE'"];
 
 #[derive(Debug, Clone)]
 pub struct HeaderDataUnit {
   header: Header,
   data: Option<Extension>,
+  valid_len: Option<usize>,   //number of valid entries in truncated images
+  source: Option<DataSource>, //where the data can be (re)loaded from
+  reload_opts: Option<Arc<ReadOptions>>, //options the data is (re)loaded with
+  unloaded: bool,
+  user_data: UserData, //in-memory only, never written
+}
+
+#[derive(Debug, Clone)]
+pub enum DataSource {
+  /*  THIS ENUM IS PART OF THE USER-FACING API
+      Where the data of an HDU is stored. Data is either stored in a FITS file,
+      right after the header of the HDU, or in a raw data file described by a
+      detached (text) header. Raw data files contain just the data unit,
+      without FITS block padding.
+  */
+  Embedded(Arc<Path>, HduLayout),
+  Detached(Arc<Path>),
+}
+
+impl DataSource {
+  pub fn get_path(&self) -> &Path {
+    match self {
+      Self::Embedded(path, _) | Self::Detached(path) => path,
+    }
+  }
 }
 
 impl HeaderDataUnit {
@@ -44,11 +95,141 @@ impl HeaderDataUnit {
       INTERNAL CODE
   */
 
-  pub(crate) fn decode_hdu(raw: &mut RawFitsReader) -> Result<Self, Box<dyn Error>> {
-    //(1) Read the header
-    let header = Header::decode_header(raw)?;
+  pub(crate) fn decode_hdu(
+    raw: &mut RawFitsReader,
+    opts: &ReadOptions,
+  ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    /* (1) Read the header and the data that belongs to it */
+    let start_block = raw.get_block_index();
+    let mut hdu = Self::decode_hdu_from(raw, opts)?;
+
+    /* (2) Remember where the data came from, so it can be reloaded */
+    let layout =
+      HduLayout::new(start_block, hdu.header.get_block_len(), Self::data_block_len(&hdu.header)?);
+    hdu.source = Some(DataSource::Embedded(raw.get_path().clone(), layout));
+    hdu.reload_opts = Some(Arc::new(opts.without_filter()));
+    Ok(hdu)
+  }
+
+  pub(crate) fn decode_hdu_from(
+    raw: &mut dyn BlockRead,
+    opts: &ReadOptions,
+  ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    /* Decodes an HDU from any backend. These HDU's have no data source */
+    let header = Self::decode_header_with(raw, opts)?;
+    Self::check_conforming(&header, opts.get_lenient())?;
+    if !opts.loads_data(&header) {
+      raw.skip_blocks(Self::data_block_len(&header)?)?;
+      return Ok(Self::skipped(header));
+    }
+    Self::decode_data(raw, header, opts)
+  }
+
+  fn skipped(header: Header) -> Self {
+    /* HDU whose data was skipped by the HDU filter of the read options */
+    let mut hdu = Self::from_parts(header, None);
+    hdu.unloaded = true;
+    hdu
+  }
+
+  fn decode_header_with(
+    raw: &mut dyn BlockRead,
+    opts: &ReadOptions,
+  ) -> Result<Header, Box<dyn Error + Send + Sync>> {
+    let mut header =
+      Header::decode_header_opts(raw, opts.get_header_charset(), opts.get_duplicates())?;
+    if let Some(aliases) = opts.get_keyword_aliases() {
+      aliases.apply(&mut header);
+    }
+    Ok(header)
+  }
 
-    //(2) Read data, if there is any
+  pub(crate) fn decode_hdus(
+    raw: &mut RawFitsReader,
+    opts: &ReadOptions,
+  ) -> Result<Vec<Self>, Box<dyn Error + Send + Sync>> {
+    /*
+        Decodes all HDU's in the file. The headers are read first, which tells
+        us where every data unit starts. The data units are independent of each
+        other, so they are decoded in parallel.
+    */
+
+    /* (1) Scan the headers, skipping over the data units */
+    let mut headers = Vec::new();
+    while raw.get_block_index() < raw.get_block_len() {
+      let start_block = raw.get_block_index();
+      let header = Self::decode_header_with(raw, opts)?;
+      Self::check_conforming(&header, opts.get_lenient())?;
+      let layout =
+        HduLayout::new(start_block, header.get_block_len(), Self::data_block_len(&header)?);
+      raw.skip_blocks(layout.get_data_blocks())?;
+      let load = opts.loads_data(&header);
+      headers.push((header, layout, load));
+    }
+
+    /*  (2)
+        Decode the data units with the headers read above. Every worker opens
+        the file once, when it first needs it, and seeks straight to the data
+        units it decodes.
+    */
+    let path = raw.get_path().clone();
+    let data: Vec<Result<Option<Extension>, Box<dyn Error + Send + Sync>>> = headers
+      .par_iter()
+      .map_init(
+        || None,
+        |reader: &mut Option<RawFitsReader>, (header, layout, load)| {
+          if !load {
+            return Ok(None); /* skipped by the HDU filter */
+          }
+          let reader = match reader {
+            Some(reader) => reader,
+            None => reader.insert(RawFitsReader::new(&path)?),
+          };
+          reader.seek_block(layout.get_start_block() + layout.get_header_blocks())?;
+          Self::decode_extension(reader, header, opts)
+        },
+      )
+      .collect();
+
+    /* (3) Combine the headers with their data */
+    let reload_opts = Arc::new(opts.without_filter());
+    let mut hdus = Vec::with_capacity(headers.len());
+    for (index, ((header, layout, load), data)) in headers.into_iter().zip(data).enumerate() {
+      let data = data.map_err(|source| DecodeHduErr::new(index, source))?;
+      let mut hdu = match load {
+        true => HeaderDataUnit::from_parts(header, data),
+        false => Self::skipped(header),
+      };
+      hdu.source = Some(DataSource::Embedded(path.clone(), layout));
+      hdu.reload_opts = Some(reload_opts.clone());
+      hdus.push(hdu);
+    }
+
+    /* (R) all HDU's, in file order */
+    Ok(hdus)
+  }
+
+  fn decode_data(
+    raw: &mut dyn BlockRead,
+    header: Header,
+    opts: &ReadOptions,
+  ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    let extension = Self::decode_extension(raw, &header, opts)?;
+    Ok(HeaderDataUnit::from_parts(header, extension))
+  }
+
+  fn decode_extension(
+    raw: &mut dyn BlockRead,
+    header: &Header,
+    opts: &ReadOptions,
+  ) -> Result<Option<Extension>, Box<dyn Error + Send + Sync>> {
+    /*  Headers that describe impossibly large data units are refused up front,
+        so the parsers below can compute the size of the data unit in a usize
+    */
+    usize::try_from(Self::data_byte_len(header)?)
+      .map_err(|_| InvalidFitsFileErr::new(io_err::DATA_NOT_ADDRESSABLE))?;
+
+    /* Read data, if there is any */
     let extension = match &header.get_value("XTENSION") {
       None => {
         /*  (2a)
@@ -58,12 +239,13 @@ impl HeaderDataUnit {
             keyword set to zero.
         */
         if header.get_value_as::<usize>("NAXIS")? == 0 {
-          //For now I'll just return None rather than implement random
-          //groups
+          /*  For now I'll just return None rather than implement random
+              groups
+          */
           None
         } else {
-          //Image
-          Some(Self::read_img(raw, &header)?)
+          /* Image */
+          Some(Self::read_img(raw, header)?)
         }
       }
       Some(extension_type) => {
@@ -72,19 +254,84 @@ impl HeaderDataUnit {
             hdu.
         */
         match extension_type.as_str() {
-          "'IMAGE   '" => Some(Self::read_img(raw, &header)?),
-          _kw @ "'TABLE   '" => Some(Self::read_table(raw, &header)?),
-          kw @ "'BINTABLE'" => Err(Self::not_impl(kw))?,
+          /* Image extensions may be empty as well */
+          "'IMAGE   '" if header.get_value_as::<usize>("NAXIS")? == 0 => None,
+          "'IMAGE   '" => Some(Self::read_img(raw, header)?),
+          _kw @ "'TABLE   '" => Some(Self::read_table(raw, header, opts)?),
+          "'BINTABLE'" => Some(Self::read_bintable(raw, header)?),
           kw => Err(InvalidRecordValueError::new("XTENSION", kw, &VALID_EXTENSION_NAMES))?,
         }
       }
     };
 
-    //(3) return complete HDU
-    Ok(HeaderDataUnit { header: header, data: extension })
+    /* return the data */
+    Ok(extension)
+  }
+
+  pub(crate) fn decode_hdu_partial(
+    raw: &mut dyn BlockRead,
+    index: usize,
+    lenient: bool,
+  ) -> Result<(Option<Self>, Option<TruncatedFileErr>), Box<dyn Error + Send + Sync>> {
+    /*
+        Decodes an HDU from a file that may be truncated. If the data unit is
+        cut off, the HDU is returned without data together with a truncation
+        error. In lenient mode, truncated images are returned anyway: the
+        missing entries are set to zero and the number of entries that were
+        actually read is stored as the validity length of the HDU.
+    */
+
+    /* (1) Read the header. Running out of file here means the header is cut off */
+    let header = match Header::decode_header(raw) {
+      Ok(header) => header,
+      Err(_) if raw.get_bytes_left() == 0 => {
+        return Ok((None, Some(TruncatedFileErr::new(index, 0, 0))))
+      }
+      Err(err) => return Err(err),
+    };
+    Self::check_conforming(&header, lenient)?;
+
+    /* (2) Complete data units are decoded as usual */
+    let (expected, got) = (Self::data_byte_len(&header)?, raw.get_bytes_left());
+    if got >= expected {
+      return Ok((Some(Self::decode_data(raw, header, &ReadOptions::default())?), None));
+    }
+
+    /* (3) Salvage what we can from the truncated data unit */
+    let err = TruncatedFileErr::new(index, expected, got);
+    let is_img = Self::is_plain_img(&header) || Self::is_half_img(&header);
+    let (data, valid_len) = match lenient && is_img {
+      true => {
+        let (axes, bitpix) = Self::img_layout(&header)?;
+        let (img, valid_len) = ImgParser::decode_partial_img(raw, &axes, bitpix)?;
+        (Some(Self::finish_img(&header, img)?), Some(valid_len))
+      }
+      false => {
+        raw.skip_blocks(raw.get_block_len() - raw.get_block_index())?;
+        (None, None)
+      }
+    };
+
+    /* (R) the partial HDU and the truncation error */
+    let mut hdu = HeaderDataUnit::from_parts(header, data);
+    hdu.valid_len = valid_len;
+    Ok((Some(hdu), Some(err)))
   }
 
-  fn read_table(raw: &mut RawFitsReader, header: &Header) -> Result<Extension, Box<dyn Error>> {
+  pub(crate) fn is_plain_img(header: &Header) -> bool {
+    /* Images that do not need any conversion after decoding */
+    let is_img = match header.get_value("XTENSION") {
+      None => header.get_value_as::<usize>("NAXIS").map(|naxis| naxis > 0).unwrap_or(false),
+      Some(xt) => xt.as_str() == "'IMAGE   '",
+    };
+    is_img && !Self::is_half_img(header)
+  }
+
+  fn read_table(
+    raw: &mut dyn BlockRead,
+    header: &Header,
+    opts: &ReadOptions,
+  ) -> Result<Extension, Box<dyn Error + Send + Sync>> {
     /*
         To parse a table we need to know the following keywords:
             TFIELDS => #fields in a row
@@ -101,12 +348,12 @@ impl HeaderDataUnit {
         We obtain these values from the header
     */
 
-    //(1) check that the mandatory keywords have been set properly
+    /* (1) check that the mandatory keywords have been set properly */
     let naxis: usize = header.get_value_as("NAXIS")?;
     let bitpix: isize = header.get_value_as("BITPIX")?;
     let pcount: usize = header.get_value_as("PCOUNT")?;
     let gcount: usize = header.get_value_as("GCOUNT")?;
-    //Here come the if statements :c
+    /* Here come the if statements :c */
     if naxis != 2 {
       Err(InvalidRecordValueError::new("NAXIS", &format!("{naxis}"), &["2"]))?
     }
@@ -120,7 +367,7 @@ impl HeaderDataUnit {
       Err(InvalidRecordValueError::new("GCOUNT", &format!("{gcount}"), &["1"]))?
     }
 
-    //(2) Obtain the keywords required for decoding the header
+    /* (2) Obtain the keywords required for decoding the header */
     let nfields: usize = header.get_value_as("TFIELDS")?;
     let row_len: usize = header.get_value_as("NAXIS1")?;
     let nrows: usize = header.get_value_as("NAXIS2")?;
@@ -128,15 +375,21 @@ impl HeaderDataUnit {
     let mut row_index_col_start: Vec<usize> = Vec::new();
     for i in 1..=nfields {
       row_index_col_start.push(
-        //We have to substract 1 since FITS indices start at 1 rather
-        //than 0
+        /*  We have to substract 1 since FITS indices start at 1 rather
+            than 0
+        */
         header.get_value_as::<usize>(&format!("TBCOL{i}"))? - 1,
       );
     }
 
-    let mut field_format: Vec<String> = Vec::new();
+    /* Fields that match their TNULLn string are null */
+    let mut field_format: Vec<(String, Option<String>)> = Vec::new();
     for i in 1..=nfields {
-      field_format.push(header.get_value_as(&format!("TFORM{i}"))?)
+      let tnull = header.get_value(&format!("TNULL{i}"));
+      field_format.push((
+        header.get_value_as(&format!("TFORM{i}"))?,
+        tnull.map(|raw| unquote(raw).unwrap_or(raw.clone())),
+      ))
     }
 
     let labels = match header.get_value("TTYPE1") {
@@ -153,67 +406,624 @@ impl HeaderDataUnit {
           tmp.push(header.get_value_as(&format!("TTYPE{i}"))?);
         }
         Some(
-          //Before we return, we query keywords we've found so far
+          /* Before we return, we query keywords we've found so far */
           tmp
             .into_iter()
             .map(|mut ttype_keyword| {
-              //We still have to strip the keyword of its annoying
-              //{'keyword   '} syntax
+              /*  We still have to strip the keyword of its annoying
+                  {'keyword   '} syntax
+              */
               ttype_keyword.remove(0);
               ttype_keyword.pop();
-              header.get_value_as(ttype_keyword.trim())
+              /*  Most tables just name their columns in TTYPE{i}, in which
+                  case there is no keyword to follow
+              */
+              match header.get_value(ttype_keyword.trim()) {
+                Some(_) => header.get_value_as(ttype_keyword.trim()),
+                None => Ok(ttype_keyword.trim().to_string()),
+              }
             })
-            .collect::<Result<Vec<String>, Box<dyn Error>>>()?,
+            .collect::<Result<Vec<String>, Box<dyn Error + Send + Sync>>>()?,
         )
       }
     };
 
-    //(3) Decode the image using the table parser
+    /* (3) Decode the image using the table parser */
     let tbl = AsciiTblParser::decode_tbl(
       raw,
       row_len,
       nrows,
-      nfields,
       row_index_col_start,
       field_format,
       labels,
+      opts,
     )?;
 
-    //(R) return the completed table
+    /* (R) return the completed table */
     Ok(tbl)
   }
 
-  fn read_img(raw: &mut RawFitsReader, header: &Header) -> Result<Extension, Box<dyn Error>> {
-    //Let's start by getting the number of axes from the NAXIS keyword
+  fn read_img(
+    raw: &mut dyn BlockRead,
+    header: &Header,
+  ) -> Result<Extension, Box<dyn Error + Send + Sync>> {
+    let (axes, bitpix) = Self::img_layout(header)?;
+
+    /* Now do the actual decoding of the image: */
+    Self::finish_img(header, ImgParser::decode_img(raw, &axes, bitpix)?)
+  }
+
+  fn finish_img(
+    header: &Header,
+    img: Extension,
+  ) -> Result<Extension, Box<dyn Error + Send + Sync>> {
+    /*  Half-precision floats are stored as 16 bit integers (see is_half_img).
+        Without the half feature they are left as they are
+    */
+    match (img, Self::is_half_img(header)) {
+      #[cfg(feature = "half")]
+      (Extension::Image(bits), true) => Ok(Extension::Image(ImgParser::half_from_bits(bits)?)),
+      (img, _) => Ok(img),
+    }
+  }
+
+  fn read_bintable(
+    raw: &mut dyn BlockRead,
+    header: &Header,
+  ) -> Result<Extension, Box<dyn Error + Send + Sync>> {
+    /*
+        Binary tables need the following keywords:
+            TFIELDS => #fields in a row
+            NAXIS1 => #bytes in a row
+            NAXIS2 => #rows in the table
+            PCOUNT => #bytes after the rows (the heap, see THEAP)
+            TFORM{i} => data format of field i
+        and optionally TTYPE{i}, TUNIT{i}, TNULL{i}, TSCAL{i}, TZERO{i} and
+        TDIM{i}. NAXIS has to be 2, BITPIX 8 and GCOUNT 1. Tile-compressed
+        tables (ZTABLE = T) describe their original fields with ZFORM{i}
+        instead, see compressed_table.rs.
+    */
+
+    /* (1) check that the mandatory keywords have been set properly */
+    let naxis: usize = header.get_value_as("NAXIS")?;
+    let bitpix: isize = header.get_value_as("BITPIX")?;
+    let gcount: usize = header.get_value_as("GCOUNT")?;
+    if naxis != 2 {
+      Err(InvalidRecordValueError::new("NAXIS", &format!("{naxis}"), &["2"]))?
+    }
+    if bitpix != 8 {
+      Err(InvalidRecordValueError::new("BITPIX", &format!("{bitpix}"), &["8"]))?
+    }
+    if gcount != 1 {
+      Err(InvalidRecordValueError::new("GCOUNT", &format!("{gcount}"), &["1"]))?
+    }
+
+    /*  (2) Layout of the rows and the heap, which starts right after the rows
+        unless THEAP says otherwise. Either way it lies within the PCOUNT bytes
+        that follow the rows
+    */
+    let nfields: usize = header.get_value_as("TFIELDS")?;
+    let row_len: usize = header.get_value_as("NAXIS1")?;
+    let nrows: usize = header.get_value_as("NAXIS2")?;
+    let pcount: usize = header.get_value_as("PCOUNT")?;
+    let rows_len = row_len
+      .checked_mul(nrows)
+      .filter(|rows_len| rows_len.checked_add(pcount).is_some())
+      .ok_or_else(|| InvalidFitsFileErr::new(io_err::DATA_TOO_LARGE))?;
+    let heap_start = match header.get_value("THEAP") {
+      None => rows_len,
+      Some(_) => header.get_value_as("THEAP")?,
+    };
+    if !(rows_len..=rows_len + pcount).contains(&heap_start) {
+      Err(InvalidRecordValueError::new(
+        "THEAP",
+        &format!("{heap_start}"),
+        &["NAXIS1 * NAXIS2 up to NAXIS1 * NAXIS2 + PCOUNT"],
+      ))?
+    }
+    let layout = BinLayout { row_len, n_rows: nrows, heap_start, heap_len: pcount };
+
+    /* (3) Describe the fields */
+    let compressed = header.get_value("ZTABLE").is_some_and(|val| val == "T");
+    let form = if compressed { "ZFORM" } else { "TFORM" };
+    let mut fields = Vec::with_capacity(nfields);
+    for i in 1..=nfields {
+      let text = |keyword: &str| {
+        let raw = header.get_value(&format!("{keyword}{i}"))?;
+        Some(unquote(raw).unwrap_or(raw.clone()).trim().to_string()).filter(|txt| !txt.is_empty())
+      };
+      let number = |keyword: &str| match header.get_value(&format!("{keyword}{i}")) {
+        None => Ok(None),
+        Some(_) => header.get_value_as::<f64>(&format!("{keyword}{i}")).map(Some),
+      };
+      let tform = text(form).ok_or(MissingRecordError::new(&format!("{form}{i}")))?;
+      let scaling = (number("TSCAL")?.unwrap_or(1.0), number("TZERO")?.unwrap_or(0.0));
+      let null = match header.get_value(&format!("TNULL{i}")) {
+        None => None,
+        Some(_) => Some(header.get_value_as::<i64>(&format!("TNULL{i}"))?),
+      };
+      /* TDIMn looks like '(2,3)' */
+      let dims = text("TDIM").and_then(|dims| {
+        let dims = dims.strip_prefix('(')?.strip_suffix(')')?;
+        dims.split(',').map(|len| len.trim().parse().ok()).collect()
+      });
+      fields.push(BinField {
+        format: BinFormat::parse(&tform)?,
+        label: text("TTYPE"),
+        unit: text("TUNIT"),
+        null,
+        scaling: Some(scaling).filter(|&scaling| scaling != (1.0, 0.0)),
+        dims,
+      });
+    }
+
+    /* (4) Decode the table using the binary table parser */
+    match compressed {
+      true => Self::read_compressed_bintable(raw, header, layout, fields),
+      false => BinTblParser::decode_tbl(raw, layout, fields),
+    }
+  }
+
+  fn read_compressed_bintable(
+    raw: &mut dyn BlockRead,
+    header: &Header,
+    layout: BinLayout,
+    fields: Vec<BinField>,
+  ) -> Result<Extension, Box<dyn Error + Send + Sync>> {
+    /* (1) The stored table has a row per tile, with a byte array per column */
+    let mut tile_fields = Vec::with_capacity(fields.len());
+    let mut algorithms = Vec::with_capacity(fields.len());
+    for (i, field) in (1..).zip(&fields) {
+      let text = |keyword: &str| {
+        let raw = header.get_value(&format!("{keyword}{i}"))?;
+        Some(unquote(raw).unwrap_or(raw.clone()).trim().to_string())
+      };
+      let tform = text("TFORM").ok_or(MissingRecordError::new(&format!("TFORM{i}")))?;
+      let zctype = text("ZCTYP").ok_or(MissingRecordError::new(&format!("ZCTYP{i}")))?;
+      let Some(algorithm) = TileCompression::from_zctype(&zctype) else {
+        return Err(Self::not_impl(&format!("'BINTABLE' (compressed with ZCTYP{i} = '{zctype}')")));
+      };
+      if field.format.is_variable() {
+        return Err(Self::not_impl("'BINTABLE' (compressed variable-length array columns)"));
+      }
+      let format = BinFormat::parse(&tform)?;
+      tile_fields.push(BinField {
+        format,
+        label: None,
+        unit: None,
+        null: None,
+        scaling: None,
+        dims: None,
+      });
+      algorithms.push(algorithm);
+    }
+    let Extension::BinTable(tiles) = BinTblParser::decode_tbl(raw, layout, tile_fields)? else {
+      unreachable!("the binary table parser returns binary tables");
+    };
+
+    /* (2) Decompress the tiles back into the original rows */
+    let tile_len: usize = header.get_value_as("ZTILELEN")?;
+    if tile_len == 0 {
+      Err(InvalidRecordValueError::new("ZTILELEN", "0", &["a positive number of rows"]))?
+    }
+    let (row_len, n_rows): (usize, usize) =
+      (header.get_value_as("ZNAXIS1")?, header.get_value_as("ZNAXIS2")?);
+    let rows_len =
+      row_len.checked_mul(n_rows).ok_or_else(|| InvalidFitsFileErr::new(io_err::DATA_TOO_LARGE))?;
+    let z_layout = ZTableLayout { row_len, n_rows, tile_len };
+    let rows = compressed_table::decompress_rows(&tiles, &z_layout, &fields, &algorithms)?;
+
+    /* (R) Decode the original table from its rows */
+    let layout = BinLayout { row_len, n_rows, heap_start: rows_len, heap_len: 0 };
+    BinTblParser::decode_tbl(&mut StreamReader::new(rows.as_slice()), layout, fields)
+  }
+
+  pub(crate) fn img_layout(
+    header: &Header,
+  ) -> Result<(Vec<usize>, Bitpix), Box<dyn Error + Send + Sync>> {
+    /* Let's start by getting the number of axes from the NAXIS keyword */
     let naxis: usize = header.get_value_as("NAXIS")?;
 
-    //Axis sizes are encoded in the NAXIS{i} keywords
+    /* Axis sizes are encoded in the NAXIS{i} keywords */
     let mut axes: Vec<usize> = Vec::new();
     for i in 1..=naxis {
       axes.push(header.get_value_as(&format!("NAXIS{i}"))?);
     }
 
-    //Datatype is encoded in the BITPIX keyword
+    /* Datatype is encoded in the BITPIX keyword */
     let bitpix = Bitpix::from_code(&header.get_value_as("BITPIX")?)?;
 
-    //Now do the actual decoding of the image:
-    Ok(ImgParser::decode_img(raw, &axes, bitpix)?)
+    Ok((axes, bitpix))
   }
 
-  pub(crate) fn encode_hdu(self, writer: &mut RawFitsWriter) -> Result<(), Box<dyn Error>> {
-    //(1) Write header
-    self.header.encode_header(writer)?;
+  fn is_half_img(header: &Header) -> bool {
+    /* The marker is only meaningful for 16 bit images */
+    header.get_value("BITPIX").map(|bpx| bpx.as_str()) == Some("16")
+      && header.get_value(HALF_FLOAT_MARKER).map(|val| val.as_str()) == Some("T")
+  }
 
-    //(2) If we have data, write the data
-    match self.data {
-      Some(data) => data.write_to_buffer(writer)?,
-      _ => {} //no data, do nothing
+  pub(crate) fn decode_hdu_preview(
+    raw: &mut dyn BlockRead,
+    max_dim: usize,
+  ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    /* (1) Read the header, only images can be previewed */
+    let header = Header::decode_header(raw)?;
+    Self::check_previewable(&header)?;
+
+    /* (2) Read a downsampled version of the image, if there is one */
+    let data = match header.get_value_as::<usize>("NAXIS")? {
+      0 => None,
+      _ => {
+        let (axes, bitpix) = Self::img_layout(&header)?;
+        Some(Self::finish_img(&header, ImgParser::decode_preview(raw, &axes, bitpix, max_dim)?)?)
+      }
+    };
+
+    /* (R) return HDU with the preview as its data */
+    Ok(HeaderDataUnit::from_parts(header, data))
+  }
+
+  fn check_previewable(header: &Header) -> Result<(), InvalidRecordValueError> {
+    match header.get_value("XTENSION") {
+      None => Ok(()),
+      Some(xt) if xt.as_str() == "'IMAGE   '" => Ok(()),
+      Some(xt) => Err(InvalidRecordValueError::new("XTENSION", xt, &["'IMAGE   '"])),
+    }
+  }
+
+  pub(crate) fn skip_hdu(
+    raw: &mut dyn BlockRead,
+  ) -> Result<(usize, usize), Box<dyn Error + Send + Sync>> {
+    /* Only the header has to be decoded to find out how large the data is */
+    let header = Self::decode_header_only(raw)?;
+
+    /* (R) the size of the header and data in FITS blocks */
+    Ok((header.get_block_len(), Self::data_block_len(&header)?))
+  }
+
+  pub(crate) fn decode_header_only(
+    raw: &mut dyn BlockRead,
+  ) -> Result<Header, Box<dyn Error + Send + Sync>> {
+    /* Decodes the header and skips over the data that belongs to it */
+    let header = Header::decode_header(raw)?;
+    raw.skip_blocks(Self::data_block_len(&header)?)?;
+    Ok(header)
+  }
+
+  fn data_block_len(header: &Header) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    Ok(raw_io::block_count(Self::data_byte_len(header)?)?)
+  }
+
+  pub(crate) fn data_byte_len(header: &Header) -> Result<u64, Box<dyn Error + Send + Sync>> {
+    /*
+        The size of the data unit in bits is given by the FITS standard as:
+            |BITPIX| * GCOUNT * (PCOUNT + NAXIS1 * ... * NAXISm)
+        Random groups set NAXIS1 to zero, in which case it is left out of the
+        product. PCOUNT and GCOUNT default to 0 and 1 respectively. Data units
+        can be larger than 4GB, so this is computed in u64 (also on 32 bit
+        platforms). Headers that describe even larger data units are refused.
+    */
+    let naxis: usize = header.get_value_as("NAXIS")?;
+    if naxis == 0 {
+      return Ok(0);
     }
+    let bitpix: isize = header.get_value_as("BITPIX")?;
+    let pcount: u64 = header.get_value_as("PCOUNT").unwrap_or(0);
+    let gcount: u64 = header.get_value_as("GCOUNT").unwrap_or(1);
 
-    //(R) ok
+    let mut n_entries = Some(1u64);
+    for i in 1..=naxis {
+      let ax: u64 = header.get_value_as(&format!("NAXIS{i}"))?;
+      if !(i == 1 && ax == 0 && header.get_value("GROUPS").is_some()) {
+        n_entries = n_entries.and_then(|n| n.checked_mul(ax));
+      }
+    }
+
+    n_entries
+      .and_then(|n| n.checked_add(pcount))
+      .and_then(|n| n.checked_mul(gcount))
+      .and_then(|n| n.checked_mul(bitpix.unsigned_abs() as u64 / 8))
+      .ok_or_else(|| InvalidFitsFileErr::new(io_err::DATA_TOO_LARGE).into())
+  }
+
+  pub(crate) fn from_parts(header: Header, data: Option<Extension>) -> Self {
+    HeaderDataUnit {
+      header,
+      data,
+      valid_len: None,
+      source: None,
+      reload_opts: None,
+      unloaded: false,
+      user_data: UserData::default(),
+    }
+  }
+
+  pub(crate) fn encode_hdus(
+    hdus: Vec<Self>,
+    writer: &mut dyn BlockWrite,
+    memory: usize,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    /*
+        Converting the data to its on-disk representation is CPU-bound, and
+        the data units do not depend on each other. Consecutive HDU's are
+        therefore collected in batches of at most memory bytes (of encoded
+        data), which are encoded to separate buffers in parallel and then
+        written in order. HDU's larger than memory are written directly.
+    */
+    let mut batch = Vec::new();
+    let memory = memory as u64;
+    let mut batch_bytes = 0;
+    for (index, hdu) in hdus.into_iter().enumerate() {
+      let (header, data) = hdu.into_encode_parts()?;
+      let bytes = raw_io::block_offset(Self::data_block_len(&header)?);
+      if batch_bytes + bytes > memory {
+        Self::encode_batch(std::mem::take(&mut batch), writer)?;
+        batch_bytes = 0;
+      }
+      if bytes > memory {
+        header.encode_header(writer)?;
+        data.encode(writer)?;
+        continue;
+      }
+      batch.push((index, header, data));
+      batch_bytes += bytes;
+    }
+    Self::encode_batch(batch, writer)
+  }
+
+  fn encode_batch(
+    batch: Vec<(usize, Header, EncodeData)>,
+    writer: &mut dyn BlockWrite,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    /*  (1) Encode the data units. The headers stay on this thread, they are
+        written in order below
+    */
+    let (headers, data): (Vec<_>, Vec<_>) =
+      batch.into_iter().map(|(index, header, data)| ((index, header), data)).unzip();
+    let buffers: Vec<Result<Vec<u8>, Box<dyn Error + Send + Sync>>> = data
+      .into_par_iter()
+      .map(|data| {
+        let mut buffer = StreamWriter::new(Vec::new());
+        data.encode(&mut buffer)?;
+        Ok(buffer.into_inner())
+      })
+      .collect();
+
+    /* (2) Write everything in order */
+    for ((index, header), buffer) in headers.into_iter().zip(buffers) {
+      let buffer = buffer.map_err(|source| EncodeHduErr::new(index, source))?;
+      header.encode_header(writer)?;
+      if !buffer.is_empty() {
+        writer.write_blocks(&buffer)?;
+      }
+    }
     Ok(())
   }
 
+  fn into_encode_parts(mut self) -> Result<(Header, EncodeData), Box<dyn Error + Send + Sync>> {
+    /*  Unloaded data has to be read again before we can write it. The layout
+        of ASCII tables goes into the header, so it is fixed here
+    */
+    self.load_data()?;
+    self.sync_structure();
+    let layout = match &self.data {
+      Some(Extension::AsciiTable(tbl)) => {
+        Some(AsciiTblParser::encode_layout(tbl, &mut self.header))
+      }
+      /* Compressed tables are written decompressed, like they were decoded */
+      Some(Extension::BinTable(_))
+        if self.header.get_value("ZTABLE").is_some_and(|val| val == "T") =>
+      {
+        compressed_table::decompress_header(&mut self.header);
+        None
+      }
+      _ => None,
+    };
+    let data = EncodeData {
+      #[cfg(feature = "half")]
+      is_half: Self::is_half_img(&self.header),
+      data: self.data,
+      layout,
+    };
+    Ok((self.header, data))
+  }
+
+  fn sync_structure(&mut self) {
+    /*
+        The data of an HDU may have been replaced since its header was made, so
+        BITPIX, NAXIS and NAXISn of images are taken from the image itself. In
+        the standard order, right after SIMPLE or XTENSION. Half-precision
+        images keep BITPIX = 16 (see is_half_img). Extensions always need
+        PCOUNT and GCOUNT, which follow the last NAXISn record.
+    */
+    if let Some(Extension::Image(img)) = &self.data {
+      let shape = crate::impl_typed_image_dispatch!(img, img => img.get_shape().clone());
+      let bitpix = match Self::is_half_img(&self.header) && matches!(img, TypedImage::SpfImg(_)) {
+        true => Bitpix::Short,
+        false => img.get_bitpix(),
+      };
+      let unchanged = match Self::img_layout(&self.header) {
+        Ok((axes, old_bitpix)) => axes == shape && old_bitpix == bitpix,
+        Err(_) => false,
+      };
+      if !unchanged {
+        let old_naxis: usize = self.header.get_value_as("NAXIS").unwrap_or(0);
+        for i in shape.len() + 1..=old_naxis {
+          self.header.remove_record(&format!("NAXIS{i}"));
+        }
+        let mut records = vec![
+          (String::from("BITPIX"), bitpix.to_code().to_string()),
+          (String::from("NAXIS"), shape.len().to_string()),
+        ];
+        records.extend(
+          shape.iter().enumerate().map(|(i, len)| (format!("NAXIS{}", i + 1), len.to_string())),
+        );
+        for (index, (keyword, value)) in records.iter().enumerate() {
+          self.header.set_record_at(index + 1, keyword, value);
+        }
+
+        /* Checksums of the old data no longer apply */
+        self.header.remove_record("CHECKSUM");
+        self.header.remove_record("DATASUM");
+      }
+    }
+
+    if !self.is_primary() {
+      let last_axis = self.last_axis_index();
+      if self.header.index_of("PCOUNT").is_none() {
+        self.header.set_record_at(last_axis + 1, "PCOUNT", "0");
+      }
+      if self.header.index_of("GCOUNT").is_none() {
+        self.header.set_record_at(last_axis + 2, "GCOUNT", "1");
+      }
+    }
+  }
+
+  pub(crate) fn empty_primary() -> Self {
+    /* Primary HDU without data, in front of extensions */
+    let mut header = Header::new();
+    for (index, (keyword, value)) in
+      [("SIMPLE", "T"), ("BITPIX", "8"), ("NAXIS", "0"), ("EXTEND", "T")].into_iter().enumerate()
+    {
+      header.set_record_at(index, keyword, value);
+    }
+    Self::from_parts(header, None)
+  }
+
+  pub(crate) fn from_image(template: Option<&Header>, img: TypedImage) -> Self {
+    /* Primary HDU for an image, with the non-structural records of template */
+    let shape = crate::impl_typed_image_dispatch!(&img, img => img.get_shape().clone());
+    let mut records = vec![
+      (String::from("SIMPLE"), String::from("T")),
+      (String::from("BITPIX"), img.get_bitpix().to_code().to_string()),
+      (String::from("NAXIS"), shape.len().to_string()),
+    ];
+    records.extend(
+      shape.iter().enumerate().map(|(i, len)| (format!("NAXIS{}", i + 1), len.to_string())),
+    );
+    let mut header = Header::new();
+    for (index, (keyword, value)) in records.iter().enumerate() {
+      header.set_record_at(index, keyword, value);
+    }
+    if let Some(template) = template {
+      header.merge_records(template);
+    }
+    Self::from_parts(header, Some(Extension::Image(img)))
+  }
+
+  pub(crate) fn sky_cutout(
+    header: &Header,
+    start: &[usize],
+    img: TypedImage,
+    shape: &[usize],
+    note: &str,
+  ) -> Self {
+    /* Standalone primary HDU with a cutout of the image described by header */
+    let mut header = header.clone();
+    wcs::shift_origin(&mut header, start);
+    for (axis, len) in shape.iter().enumerate() {
+      let keyword = format!("NAXIS{}", axis + 1);
+      header.put_record(&keyword, len.to_string(), header.get_comment(&keyword).cloned());
+    }
+
+    /* Checksums of the original no longer apply */
+    header.remove_record("CHECKSUM");
+    header.remove_record("DATASUM");
+    header.append_history(note);
+
+    let mut hdu = Self::from_parts(header, Some(Extension::Image(img)));
+    hdu.promote_to_primary();
+    hdu
+  }
+
+  pub(crate) fn is_primary(&self) -> bool {
+    self.header.get_value("SIMPLE").is_some()
+  }
+
+  pub(crate) fn announce_extensions(&mut self) {
+    self.set_extend(true)
+  }
+
+  pub(crate) fn set_extend(&mut self, extend: bool) {
+    /* Sets EXTEND in a primary HDU, right after the last NAXISn record */
+    let index = match self.header.index_of("EXTEND") {
+      Some(index) => index,
+      None => self.last_axis_index() + 1,
+    };
+    self.header.set_record_at(index, "EXTEND", if extend { "T" } else { "F" });
+  }
+
+  pub(crate) fn check_conforming(
+    header: &Header,
+    lenient: bool,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    /* Primary HDU's have to be SIMPLE = T, or SIMPLE = F when reading leniently */
+    let allowed: &'static [&str] = if lenient { &["T", "F"] } else { &["T"] };
+    match header.get_value("SIMPLE") {
+      Some(simple) if !allowed.contains(&simple.as_str()) => {
+        Err(Box::new(InvalidRecordValueError::new("SIMPLE", simple, allowed)))
+      }
+      _ => Ok(()),
+    }
+  }
+
+  fn last_axis_index(&self) -> usize {
+    let naxis = self.header.get_value("NAXIS").cloned().unwrap_or_default();
+    match self.header.index_of(&format!("NAXIS{}", naxis.trim())) {
+      Some(index) => index,
+      None => self.header.index_of("NAXIS").unwrap_or(2),
+    }
+  }
+
+  pub(crate) fn demote_primary(&mut self) {
+    /* Turns a primary HDU into an IMAGE extension. Extensions are left alone */
+    if !self.is_primary() {
+      return;
+    }
+    self.header.remove_record("SIMPLE");
+    self.header.remove_record("EXTEND");
+    self.header.set_record_at(0, "XTENSION", "'IMAGE   '");
+
+    /* PCOUNT and GCOUNT follow the last NAXISn record */
+    let last_axis = self.last_axis_index();
+    self.header.set_record_at(last_axis + 1, "PCOUNT", "0");
+    self.header.set_record_at(last_axis + 2, "GCOUNT", "1");
+  }
+
+  pub(crate) fn promote_to_primary(&mut self) -> bool {
+    /*  Turns an IMAGE extension into a primary HDU. Returns false for other
+        extensions, which cannot be stored in the primary HDU
+    */
+    match self.header.get_value("XTENSION").map(String::as_str) {
+      None => return true,
+      Some("'IMAGE   '") => {}
+      Some(_) => return false,
+    }
+    self.header.remove_record("XTENSION");
+    self.header.remove_record("PCOUNT");
+    self.header.remove_record("GCOUNT");
+    self.header.set_record_at(0, "SIMPLE", "T");
+    true
+  }
+
+  pub(crate) fn extname(&self) -> Option<String> {
+    self.header.get_value("EXTNAME").and_then(|val| unquote(val))
+  }
+
+  pub(crate) fn extver(&self) -> Option<i64> {
+    self.header.get_value_as("EXTVER").ok()
+  }
+
+  pub(crate) fn set_extver(&mut self, extver: i64) {
+    /* EXTVER goes right after EXTNAME if it is not there yet */
+    let index = match (self.header.index_of("EXTVER"), self.header.index_of("EXTNAME")) {
+      (Some(index), _) => index,
+      (None, Some(index)) => index + 1,
+      (None, None) => self.header.get_num_records(),
+    };
+    self.header.set_record_at(index, "EXTVER", &extver.to_string());
+  }
+
   fn not_impl(keyword: &str) -> Box<NotImplementedErr> {
     Box::new(NotImplementedErr::new(keyword.to_string()))
   }
@@ -226,9 +1036,334 @@ impl HeaderDataUnit {
   pub fn get_header(&self) -> &Header {
     &self.header
   }
+  pub fn get_header_mut(&mut self) -> &mut Header {
+    &mut self.header
+  }
   pub fn get_data(&self) -> Option<&Extension> {
     self.data.as_ref()
   }
+  pub fn get_data_mut(&mut self) -> Option<&mut Extension> {
+    self.data.as_mut()
+  }
+
+  pub fn deep_clone(&self) -> Self {
+    /*  clone() is cheap, since image data is shared between the clones (and
+        only copied once one of them is mutated). deep_clone() copies image
+        data right away, keeping its pixel type. Tables are always copied.
+    */
+    let mut hdu = self.clone();
+    if let Some(Extension::Image(img)) = &self.data {
+      hdu.data = Some(Extension::Image(img.deep_clone()));
+    }
+    hdu
+  }
+
+  /*
+      User data. Any (cloneable) value can be attached to an HDU, at most one
+      per type. User data is carried along when the HDU is cloned, but it is
+      ignored when the HDU is written and lost when it is split into parts.
+  */
+  pub fn set_ext<T: Any + Clone>(&mut self, value: T) -> Option<T> {
+    self.user_data.insert(value)
+  }
+  pub fn get_ext<T: Any>(&self) -> Option<&T> {
+    self.user_data.get()
+  }
+  pub fn get_ext_mut<T: Any>(&mut self) -> Option<&mut T> {
+    self.user_data.get_mut()
+  }
+  pub fn remove_ext<T: Any>(&mut self) -> Option<T> {
+    self.user_data.remove()
+  }
+  pub fn has_ext<T: Any>(&self) -> bool {
+    self.user_data.get::<T>().is_some()
+  }
+  pub fn clear_ext(&mut self) {
+    self.user_data.clear()
+  }
+  pub fn get_num_ext(&self) -> usize {
+    self.user_data.len()
+  }
+
+  /*
+      Spectral products are stored either as a 1-D image or as a numeric table
+      column. These funcs convert between the two, the original HDU is left
+      as it is. See column_image.rs for the keywords that are carried over.
+  */
+  pub fn column_to_image(
+    &self,
+    col: usize,
+  ) -> Result<HeaderDataUnit, Box<dyn Error + Send + Sync>> {
+    let Some(Extension::AsciiTable(table)) = &self.data else {
+      return Err(Box::new(MissingDataErr::new("a table")));
+    };
+    let (header, img) = column_image::column_to_image(&self.header, table, col)?;
+    Ok(Self::from_parts(header, Some(Extension::Image(img))))
+  }
+
+  pub fn image_to_column(
+    &self,
+    label: &str,
+  ) -> Result<HeaderDataUnit, Box<dyn Error + Send + Sync>> {
+    let Some(Extension::Image(img)) = &self.data else {
+      return Err(Box::new(MissingDataErr::new("an image")));
+    };
+    let (header, table) = column_image::image_to_column(&self.header, img, label)?;
+    Ok(Self::from_parts(header, Some(Extension::AsciiTable(table))))
+  }
+
+  /*
+      Header as typed key-value pairs, see Header::metadata_pairs. HDU's can
+      only be rebuilt from pairs that do not describe a data unit.
+  */
+  pub fn metadata_pairs(&self) -> Vec<(String, MetaValue)> {
+    self.header.metadata_pairs()
+  }
+
+  pub fn from_metadata_pairs(
+    pairs: &[(String, MetaValue)],
+  ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    let header = Header::from_metadata_pairs(pairs)?;
+    Self::check_conforming(&header, false)?;
+    if Self::data_byte_len(&header)? > 0 {
+      return Err(Box::new(MissingDataErr::new("the data described by its header")));
+    }
+    Ok(Self::from_parts(header, None))
+  }
+
+  //Primary HDU's with SIMPLE = F (only accepted by lenient reads) do not
+  //conform to the FITS standard. Extensions always do
+  pub fn is_conforming(&self) -> bool {
+    self.header.get_value("SIMPLE").map(|val| val.as_str()) != Some("F")
+  }
+
+  //Complex images are marked with COMPLEX = T and store the real and imaginary
+  //parts interleaved along a leading NAXIS1 of length 2. Their data can be
+  //accessed with TypedImage::as_owned_c32_array() or as_owned_c64_array()
+  pub fn is_complex(&self) -> bool {
+    self.header.get_value(COMPLEX_MARKER).map(|val| val.as_str()) == Some("T")
+  }
+
+  /*
+      Complex images are only decoded as such when the header carries the
+      COMPLEX = T marker. Plain f32/f64 images with a NAXIS1 of 2 are not
+      complex-valued, and cannot be accessed through these methods.
+  */
+  pub fn get_c32_array(&self) -> Result<Array<Complex<f32>, IxDyn>, Box<dyn Error + Send + Sync>> {
+    self.complex_img()?.clone().as_owned_c32_array()
+  }
+
+  pub fn get_c64_array(&self) -> Result<Array<Complex<f64>, IxDyn>, Box<dyn Error + Send + Sync>> {
+    self.complex_img()?.clone().as_owned_c64_array()
+  }
+
+  fn complex_img(&self) -> Result<&TypedImage, Box<dyn Error + Send + Sync>> {
+    if !self.is_complex() {
+      return Err(Box::new(MissingRecordError::new(COMPLEX_MARKER)));
+    }
+    match &self.data {
+      Some(Extension::Image(img)) => Ok(img),
+      _ => Err(Box::new(MissingDataErr::new("a complex image"))),
+    }
+  }
+
+  pub fn from_c32_array(array: ArrayViewD<Complex<f32>>) -> Self {
+    Self::from_complex(TypedImage::from_c32_array(array))
+  }
+
+  pub fn from_c64_array(array: ArrayViewD<Complex<f64>>) -> Self {
+    Self::from_complex(TypedImage::from_c64_array(array))
+  }
+
+  fn from_complex(img: TypedImage) -> Self {
+    /* Primary HDU for the interleaved image, with the marker keyword */
+    let mut hdu = Self::from_image(None, img);
+    hdu.header.put_record(COMPLEX_MARKER, String::from("T"), None);
+    hdu
+  }
+
+  //Extensions are identified by EXTNAME, EXTVER and EXTLEVEL. EXTVER and
+  //EXTLEVEL default to 1 when they are missing
+  pub fn get_extname(&self) -> Option<String> {
+    self.extname()
+  }
+  pub fn get_extver(&self) -> i64 {
+    self.extver().unwrap_or(1)
+  }
+  pub fn get_extlevel(&self) -> i64 {
+    self.header.get_value_as("EXTLEVEL").unwrap_or(1)
+  }
+
+  /*
+      The layout of the data unit as described by the header. These do not
+      touch the data, so they also work for HDU's whose data was unloaded (or
+      that were only indexed), for planning memory use or displaying a file.
+  */
+  pub fn get_shape(&self) -> Result<Vec<usize>, Box<dyn Error + Send + Sync>> {
+    /* NAXISn, in the same order as the shape of the image. Empty if NAXIS = 0 */
+    let naxis: usize = self.header.get_value_as("NAXIS")?;
+    (1..=naxis).map(|i| self.header.get_value_as(&format!("NAXIS{i}"))).collect()
+  }
+
+  pub fn get_bitpix(&self) -> Result<Bitpix, Box<dyn Error + Send + Sync>> {
+    Ok(Bitpix::from_code(&self.header.get_value_as("BITPIX")?)?)
+  }
+
+  pub fn get_estimated_data_size(&self) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    /*  Bytes in the data unit (without padding). Images take up exactly this
+        much memory when loaded, tables take up more
+    */
+    let bytes = Self::data_byte_len(&self.header)?;
+    Ok(usize::try_from(bytes).map_err(|_| InvalidFitsFileErr::new(io_err::TOO_LARGE))?)
+  }
+
+  //Images read leniently from a truncated file only contain this many valid
+  //entries (in Fortran order), the rest were set to zero. None if complete
+  pub fn get_valid_len(&self) -> Option<usize> {
+    self.valid_len
+  }
+
+  /*
+      HDU's that were read from a file remember where their data is stored.
+      Their data can be dropped to save memory with unload(), and read again
+      with load_data(), using the read options the file was opened with. The
+      header is always kept in memory. Files opened with Fits::open_lazy start
+      out with all of their data unloaded.
+  */
+  pub fn unload(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+    /* Refuse to drop data that we cannot read again */
+    if self.source.is_none() {
+      return Err(Box::new(NoDataSourceErr::new()));
+    }
+    self.data = None;
+    self.unloaded = true;
+    Ok(())
+  }
+
+  pub fn load_data(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if !self.unloaded {
+      return Ok(()); /* data is already in memory */
+    }
+    let opts = match &self.reload_opts {
+      Some(opts) => opts.as_ref().clone(),
+      None => ReadOptions::new().lenient(true),
+    };
+    self.data = match &self.source {
+      None => return Err(Box::new(NoDataSourceErr::new())),
+      Some(DataSource::Embedded(path, layout)) => {
+        /* Jump straight to the start of the HDU and decode it again */
+        let mut reader = RawFitsReader::new(path)?;
+        reader.skip_blocks(layout.get_start_block())?;
+        Self::decode_hdu(&mut reader, &opts)?.data
+      }
+      Some(DataSource::Detached(path)) => {
+        /* Raw data files are not padded, but they may not be too short either */
+        let mut reader = RawFitsReader::new_lenient(path)?;
+        let (expected, got) = (Self::data_byte_len(&self.header)?, reader.get_bytes_left());
+        if got < expected {
+          return Err(Box::new(TruncatedFileErr::new(0, expected, got)));
+        }
+        Self::decode_data(&mut reader, self.header.clone(), &opts)?.data
+      }
+    };
+    self.unloaded = false;
+    Ok(())
+  }
+
+  pub fn read_preview(
+    &self,
+    max_dim: usize,
+  ) -> Result<Option<Extension>, Box<dyn Error + Send + Sync>> {
+    /*
+        Downsampled copy of the image in this HDU, no axis of which is longer
+        than max_dim. Images that are in memory are simply strided. Unloaded
+        images are read from their data source, skipping the FITS blocks that
+        only contain rows that are not part of the preview (see
+        ImgParser::decode_preview). None if the HDU has no image data.
+    */
+    Self::check_previewable(&self.header)?;
+    if self.header.get_value_as::<usize>("NAXIS")? == 0 {
+      return Ok(None);
+    }
+    match (&self.data, &self.source) {
+      (Some(Extension::Image(img)), _) => {
+        Ok(Some(Extension::Image(ImgParser::preview_of(img, max_dim))))
+      }
+      (Some(_), _) | (None, None) => Ok(None),
+      (None, Some(source)) => {
+        let mut reader = match source {
+          DataSource::Embedded(path, layout) => {
+            let mut reader = RawFitsReader::new(path)?;
+            reader.seek_block(layout.get_start_block() + layout.get_header_blocks())?;
+            reader
+          }
+          DataSource::Detached(path) => RawFitsReader::new_lenient(path)?,
+        };
+        let (axes, bitpix) = Self::img_layout(&self.header)?;
+        let preview = ImgParser::decode_preview(&mut reader, &axes, bitpix, max_dim)?;
+        Ok(Some(Self::finish_img(&self.header, preview)?))
+      }
+    }
+  }
+
+  pub fn get_data_source(&self) -> Option<&DataSource> {
+    self.source.as_ref()
+  }
+
+  /*
+      Detached headers describe a raw data file: a data unit without FITS
+      block padding and without a header of its own. The header is stored
+      separately, as text (see Header::from_text).
+  */
+  pub fn open_detached(
+    header_path: &Path,
+    data_path: &Path,
+  ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    /* (1) Read the text header */
+    let text = fs::read(header_path)
+      .map_err(|err| FitsIoErr::new(header_path, "read detached header", err))?;
+    let header = Header::from_text(&String::from_utf8_lossy(&text))?;
+
+    /* (2) Attach the raw data file and read it */
+    let mut hdu = Self::from_parts(header, None);
+    hdu.source = Some(DataSource::Detached(Arc::from(data_path)));
+    hdu.unloaded = true;
+    hdu.load_data()?;
+    Ok(hdu)
+  }
+
+  pub fn write_detached(
+    &self,
+    header_path: &Path,
+    data_path: &Path,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    /* (1) Write the header as text */
+    fs::write(header_path, self.header.to_text()?)
+      .map_err(|err| FitsIoErr::new(header_path, "write detached header", err))?;
+
+    /* (2) Write the data unit without its block padding */
+    let (_, data) = self.clone().into_encode_parts()?;
+    let mut writer = RawFitsWriter::new(data_path)?;
+    data.encode(&mut writer)?;
+    writer.flush_to_len(Self::data_byte_len(&self.header)?)?;
+    Ok(())
+  }
+
+  pub fn is_loaded(&self) -> bool {
+    !self.unloaded
+  }
+
+  pub fn get_layout(&self) -> Option<&HduLayout> {
+    match &self.source {
+      Some(DataSource::Embedded(_, layout)) => Some(layout),
+      _ => None,
+    }
+  }
+
+  //Approximate number of bytes the decoded data takes up in memory
+  pub fn get_memory_usage(&self) -> usize {
+    self.data.as_ref().map_or(0, |data| data.get_memory_usage())
+  }
 
   //Destructs HDU into parts
   pub fn to_parts(self) -> (Header, Option<Extension>) {
@@ -245,6 +1380,7 @@ impl HeaderDataUnit {
 
   pub fn pretty_print_data(&self) -> String {
     let data_string: Cow<str> = match &self.data {
+      None if self.unloaded => "(NOT_LOADED)".into(),
       None => "(NO_DATA)".into(),
       Some(data) => format!("{data}").into(),
     };
@@ -256,7 +1392,13 @@ impl BlockSized for HeaderDataUnit {
   fn get_block_len(&self) -> usize {
     self.header.get_block_len()
       + match &self.data {
+        None if self.unloaded => Self::data_block_len(&self.header).unwrap_or(0),
         None => 0,
+        /* Half-precision images are f32 images in memory, but 16 bit on disk */
+        Some(Extension::Image(img)) if Self::is_half_img(&self.header) => {
+          let n_pixels = crate::impl_typed_image_dispatch!(img, img => img.get_data().len());
+          (n_pixels * 2).div_ceil(crate::BLOCK_SIZE)
+        }
         Some(data) => data.get_block_len(),
       }
   }
@@ -269,3 +1411,29 @@ impl Display for HeaderDataUnit {
     Ok(())
   }
 }
+
+struct EncodeData {
+  //Data unit that is ready to be encoded, independent of its header
+  data: Option<Extension>,
+  #[cfg(feature = "half")]
+  is_half: bool,
+  layout: Option<AsciiLayout>, //rows of ASCII tables, see encode_layout
+}
+
+impl EncodeData {
+  fn encode(self, writer: &mut dyn BlockWrite) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match self.data {
+      /* Half-precision images were decoded to f32 and have to be quantized */
+      #[cfg(feature = "half")]
+      Some(Extension::Image(img)) if self.is_half => ImgParser::encode_half_img(img, writer)?,
+      Some(Extension::AsciiTable(tbl)) if self.layout.is_some() => {
+        AsciiTblParser::encode_tbl(tbl, self.layout.unwrap(), writer)?
+      }
+      Some(data) => data.write_to_buffer(writer)?,
+      _ => {} /* no data, do nothing */
+    }
+
+    /* (R) ok */
+    Ok(())
+  }
+}
@@ -0,0 +1,122 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    The ESO HIERARCH convention allows keywords with multiple levels, such as
+    HIERARCH ESO DET CHIP1 GAIN. Headers with such keywords can be browsed as a
+    tree of HierarchNodes, where every level of the keyword is a node.
+*/
+
+use std::{error::Error, ops::Index, str::FromStr};
+
+use indexmap::IndexMap;
+
+use crate::{hdu_err::MissingRecordError, keyword_value::KeywordValue};
+
+#[derive(Debug, Clone, Default)]
+pub struct HierarchNode {
+  /*  THIS STRUCT IS PART OF THE USER-FACING API
+      Nodes are snapshots of the header they were created from. Use
+      Header::set_hierarch to modify the header itself.
+  */
+  value: Option<String>,
+  comment: Option<String>,
+  children: IndexMap<String, HierarchNode>,
+}
+
+impl HierarchNode {
+  /*
+      PUBLIC API
+  */
+
+  pub fn get(&self, level: &str) -> Option<&HierarchNode> {
+    self.children.get(level)
+  }
+
+  pub fn get_path(&self, path: &str) -> Option<&HierarchNode> {
+    //Path with the levels separated by spaces, like "DET CHIP1 GAIN"
+    path.split_whitespace().try_fold(self, |node, level| node.get(level))
+  }
+
+  pub fn get_value(&self) -> Option<&String> {
+    self.value.as_ref()
+  }
+
+  pub fn get_comment(&self) -> Option<&String> {
+    self.comment.as_ref()
+  }
+
+  pub fn get_value_as<T>(&self) -> Result<T, Box<dyn Error + Send + Sync>>
+  where
+    T: FromStr,
+    <T as FromStr>::Err: 'static + Error + Send + Sync,
+  {
+    match &self.value {
+      None => Err(MissingRecordError::new("HIERARCH"))?,
+      Some(val) => Ok(str::parse::<T>(val)?),
+    }
+  }
+
+  pub fn get_value_with<T: KeywordValue>(&self) -> Result<T, Box<dyn Error + Send + Sync>> {
+    match &self.value {
+      None => Err(MissingRecordError::new("HIERARCH"))?,
+      Some(val) => T::parse_value(val),
+    }
+  }
+
+  pub fn children(&self) -> impl Iterator<Item = (&str, &HierarchNode)> {
+    self.children.iter().map(|(level, node)| (level.as_str(), node))
+  }
+
+  pub fn is_leaf(&self) -> bool {
+    self.children.is_empty()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.value.is_none() && self.children.is_empty()
+  }
+
+  /*
+      INTERNAL CODE
+  */
+
+  pub(crate) fn insert(&mut self, levels: &[&str], value: Option<String>, comment: Option<String>) {
+    match levels.split_first() {
+      None => {
+        self.value = value;
+        self.comment = comment;
+      }
+      Some((level, rest)) => {
+        self.children.entry(level.to_string()).or_default().insert(rest, value, comment)
+      }
+    }
+  }
+}
+
+impl Index<&str> for HierarchNode {
+  type Output = HierarchNode;
+
+  fn index(&self, level: &str) -> &HierarchNode {
+    //Like IndexMap, indexing with a missing level panics
+    match self.get(level) {
+      Some(node) => node,
+      None => panic!("HIERARCH level {level} does not exist"),
+    }
+  }
+}
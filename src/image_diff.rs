@@ -0,0 +1,209 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::error::Error;
+
+use ndarray::{Dimension, Zip};
+
+use crate::{
+  bitpix::{promote, Bitpix},
+  extensions::image::{Image, TypedImage},
+  header::Header,
+  header_data_unit::HeaderDataUnit,
+  img_err::DiffShapeErr,
+  keyword_value::quote,
+};
+
+/*
+    Pixel by pixel comparison of two images, for checking the output of a
+    changed pipeline against reference output. Pixels differ when they are
+    outside the tolerance. NaN pixels match NaN pixels, and infinities match
+    infinities of the same sign; a NaN opposite a number always differs.
+
+    The deviation of a pixel is |a - b|. The maximum and mean deviation are
+    taken over the pixels that are not NaN in either image, whether or not
+    they are within the tolerance. When pixels differ, the report holds the
+    difference image a - b (as f64, NaN where only one of the pixels is NaN),
+    which can be written out as an image extension with difference_hdu().
+*/
+
+//EXTNAME of the difference image, see ImageDiffReport::difference_hdu
+const DIFF_EXTNAME: &str = "DIFF";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tolerance {
+  /*  THIS ENUM IS PART OF THE USER-FACING API
+      Absolute allows |a - b| <= eps, Relative allows |a - b| <= eps times the
+      largest of |a| and |b|. Ulps allows pixels that are at most n floating
+      point numbers apart. These are counted in the pixel type the two images
+      promote to: f32 steps for f32 images, f64 steps when an f64 image is
+      involved, and steps of 1 for integer images.
+  */
+  Absolute(f64),
+  Relative(f64),
+  Ulps(u64),
+}
+
+impl Tolerance {
+  fn allows(&self, a: f64, b: f64, bitpix: Bitpix) -> bool {
+    if a == b || (a.is_nan() && b.is_nan()) {
+      return true;
+    }
+    let deviation = (a - b).abs();
+    match *self {
+      Tolerance::Absolute(eps) => deviation <= eps,
+      Tolerance::Relative(eps) => deviation <= eps * a.abs().max(b.abs()),
+      Tolerance::Ulps(n) => ulps(a, b, bitpix).is_some_and(|ulps| ulps <= n),
+    }
+  }
+}
+
+fn ulps(a: f64, b: f64, bitpix: Bitpix) -> Option<u64> {
+  //Number of representable values between a and b, None if either is NaN
+  if a.is_nan() || b.is_nan() {
+    return None;
+  }
+  //Float bits, mapped so that the integers are ordered like the floats
+  let ordered = |bits: i64, min: i64| if bits < 0 { min - bits } else { bits };
+  Some(match bitpix {
+    Bitpix::Spf => {
+      let (a, b) = ((a as f32).to_bits() as i32, (b as f32).to_bits() as i32);
+      let min = i32::MIN as i64;
+      ordered(a as i64, min).abs_diff(ordered(b as i64, min))
+    }
+    Bitpix::Dpf => {
+      let (a, b) = (a.to_bits() as i64, b.to_bits() as i64);
+      (ordered(a, i64::MIN) as i128 - ordered(b, i64::MIN) as i128).unsigned_abs() as u64
+    }
+    _ => (a - b).abs() as u64,
+  })
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageDiffReport {
+  /*  THIS STRUCT IS PART OF THE USER-FACING API
+      The index of the largest deviation is 0-based, in the order of the axes
+      of the image (NAXIS1 first).
+  */
+  tolerance: Tolerance,
+  n_pixels: usize,
+  n_differing: usize,
+  max_deviation: f64,
+  max_index: Option<Vec<usize>>,
+  mean_deviation: f64,
+  difference: Option<TypedImage>,
+}
+
+pub fn compare_images(
+  a: &TypedImage,
+  b: &TypedImage,
+  tolerance: Tolerance,
+) -> Result<ImageDiffReport, Box<dyn Error + Send + Sync>> {
+  //(1) Both images as f64, the ulps are counted in the promoted pixel type
+  let (pixels_a, pixels_b) = (a.to_f64_array(), b.to_f64_array());
+  if pixels_a.shape() != pixels_b.shape() {
+    return Err(Box::new(DiffShapeErr::new(pixels_a.shape(), pixels_b.shape())));
+  }
+  let bitpix = promote(a.get_bitpix(), b.get_bitpix());
+
+  //(2) Count the differing pixels and the deviations of the others
+  let (mut n_differing, mut n_compared, mut sum) = (0usize, 0usize, 0.0);
+  let (mut max_deviation, mut max_index) = (f64::NAN, None);
+  for ((index, &a), &b) in pixels_a.indexed_iter().zip(pixels_b.iter()) {
+    if !tolerance.allows(a, b, bitpix) {
+      n_differing += 1;
+    }
+    if a.is_nan() || b.is_nan() {
+      continue;
+    }
+    let deviation = if a == b { 0.0 } else { (a - b).abs() };
+    n_compared += 1;
+    sum += deviation;
+    if max_index.is_none() || deviation > max_deviation {
+      (max_deviation, max_index) = (deviation, Some(index.slice().to_vec()));
+    }
+  }
+
+  //(3) Difference image, only when there is a difference to show
+  let difference = match n_differing {
+    0 => None,
+    _ => {
+      let diff = Zip::from(&pixels_a).and(&pixels_b).map_collect(|&a, &b| match a == b {
+        true => 0.0,
+        false => a - b,
+      });
+      Some(TypedImage::DpfImg(Image::new(diff)))
+    }
+  };
+
+  Ok(ImageDiffReport {
+    tolerance,
+    n_pixels: pixels_a.len(),
+    n_differing,
+    max_deviation,
+    max_index,
+    mean_deviation: if n_compared == 0 { f64::NAN } else { sum / n_compared as f64 },
+    difference,
+  })
+}
+
+impl ImageDiffReport {
+  pub fn is_within_tolerance(&self) -> bool {
+    self.n_differing == 0
+  }
+  pub fn get_tolerance(&self) -> Tolerance {
+    self.tolerance
+  }
+  pub fn get_n_pixels(&self) -> usize {
+    self.n_pixels
+  }
+  pub fn get_n_differing(&self) -> usize {
+    //Pixels outside the tolerance
+    self.n_differing
+  }
+  pub fn get_max_deviation(&self) -> f64 {
+    //NaN if every pixel is NaN in either image
+    self.max_deviation
+  }
+  pub fn get_max_index(&self) -> Option<&[usize]> {
+    self.max_index.as_deref()
+  }
+  pub fn get_mean_deviation(&self) -> f64 {
+    self.mean_deviation
+  }
+  pub fn get_difference(&self) -> Option<&TypedImage> {
+    self.difference.as_ref()
+  }
+
+  pub fn difference_hdu(&self, template: Option<&Header>) -> Option<HeaderDataUnit> {
+    /*  The difference image as an HDU named DIFF, with the non-structural
+        records of template (e.g. the header of the reference image, so the
+        WCS carries over). None if no pixels differ.
+    */
+    let difference = self.difference.clone()?;
+    let mut hdu = HeaderDataUnit::from_image(template, difference);
+    let header = hdu.get_header_mut();
+    header.put_record("EXTNAME", quote(DIFF_EXTNAME), None);
+    header.append_history(&format!(
+      "Difference of two images, {} of {} pixels outside {:?}",
+      self.n_differing, self.n_pixels, self.tolerance
+    ));
+    Some(hdu)
+  }
+}
@@ -0,0 +1,327 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    An ImageStreamWriter writes a FITS file containing a single (primary)
+    image row by row, without ever holding the whole image in memory. This
+    lets arrays flow straight from a generator (a simulation, for example) to
+    disk. Rows run along the first axis (NAXIS1) and are written in file
+    order, see Image::from_row_iter for the in-memory equivalent.
+
+    The header is written when the stream is created. The file is complete
+    once finish() has been called, which checks that all rows were written
+    and pads the data unit to a whole number of FITS blocks. Since the shape
+    (and with it the whole header) is known up front, the image can also be
+    written to a non-seekable sink, such as a pipe or an HTTP response body
+    (see to_stream).
+
+    An ImageStreamReader reads an image HDU row by row in the same way. The
+    data unit is checksummed as it passes, so if the header has a DATASUM
+    record, the integrity of the data is verified without a separate read.
+    The verification happens in finish(), which reads the rest of the data
+    unit (if any) and returns a warning diagnostic if the sums do not match.
+*/
+
+use std::{error::Error, io::Write, marker::PhantomData, path::Path};
+
+use crate::{
+  checksum,
+  extensions::image::FitsPixel,
+  fits_index::FitsIndex,
+  hdu_err::MissingDataErr,
+  header::Header,
+  header_data_unit::HeaderDataUnit,
+  img_err::{RowShapeErr, WrongImgTypeErr},
+  io_err::{self, InvalidFitsFileErr},
+  keyword_value::unquote,
+  raw::{
+    block_io::BlockWrite,
+    raw_io::{RawFitsReader, RawFitsWriter},
+    stream_io::StreamWriter,
+  },
+  section_err::HduNotFoundErr,
+  validation::{Diagnostic, Severity},
+};
+
+const BLOCK_SIZE: usize = crate::BLOCK_SIZE;
+//Number of FITS blocks that are buffered before they are written (or read)
+const BUFFER_BLOCKS: usize = 64;
+//Name of the (pseudo) validation profile of checksum diagnostics
+const PROFILE: &str = "checksum";
+
+#[derive(Debug)]
+pub struct ImageStreamWriter<T: FitsPixel, W: BlockWrite = RawFitsWriter> {
+  /*  THIS STRUCT IS PART OF THE USER-FACING API
+   */
+  writer: W,
+  shape: Vec<usize>,
+  rows_written: usize,
+  buffer: Vec<u8>,
+  pixel: PhantomData<T>,
+}
+
+impl<T: FitsPixel> ImageStreamWriter<T> {
+  pub fn create(path: &Path, shape: &[usize]) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    Self::create_with_header(path, shape, None)
+  }
+
+  pub fn create_with_header(
+    path: &Path,
+    shape: &[usize],
+    header: Option<&Header>,
+  ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    //(1) Build the primary header
+    let primary = Self::primary_header(shape, header);
+
+    //(2) Write it, the data follows row by row
+    let mut writer = RawFitsWriter::new(path)?;
+    primary.encode_header(&mut writer)?;
+    Ok(ImageStreamWriter {
+      writer,
+      shape: shape.to_vec(),
+      rows_written: 0,
+      buffer: Vec::with_capacity(BUFFER_BLOCKS * BLOCK_SIZE),
+      pixel: PhantomData,
+    })
+  }
+}
+
+impl<T: FitsPixel, W: BlockWrite> ImageStreamWriter<T, W> {
+  fn primary_header(shape: &[usize], header: Option<&Header>) -> Header {
+    /*  The structural records follow from the pixel type and the shape, the
+        other records (if any) are copied from the given header.
+    */
+    let mut primary = Header::new();
+    let mut records = vec![
+      (String::from("SIMPLE"), String::from("T")),
+      (String::from("BITPIX"), T::BITPIX.to_code().to_string()),
+      (String::from("NAXIS"), shape.len().to_string()),
+    ];
+    records.extend(
+      shape.iter().enumerate().map(|(i, len)| (format!("NAXIS{}", i + 1), len.to_string())),
+    );
+    for (index, (keyword, value)) in records.iter().enumerate() {
+      primary.set_record_at(index, keyword, value);
+    }
+    if let Some(header) = header {
+      primary.merge_records(header);
+    }
+    primary
+  }
+}
+
+impl<T: FitsPixel, S: Write> ImageStreamWriter<T, StreamWriter<S>> {
+  pub fn to_stream(
+    sink: S,
+    shape: &[usize],
+    header: Option<&Header>,
+  ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    //Like create_with_header, but writes to a non-seekable sink
+    let mut writer = StreamWriter::new(sink);
+    Self::primary_header(shape, header).encode_header(&mut writer)?;
+    Ok(ImageStreamWriter {
+      writer,
+      shape: shape.to_vec(),
+      rows_written: 0,
+      buffer: Vec::with_capacity(BUFFER_BLOCKS * BLOCK_SIZE),
+      pixel: PhantomData,
+    })
+  }
+
+  pub fn finish_stream(mut self) -> Result<S, Box<dyn Error + Send + Sync>> {
+    //Like finish, but hands back the sink
+    self.write_tail()?;
+    Ok(self.writer.into_inner())
+  }
+}
+
+impl<T: FitsPixel, W: BlockWrite> ImageStreamWriter<T, W> {
+  pub fn write_row(&mut self, row: &[T]) -> Result<(), Box<dyn Error + Send + Sync>> {
+    //(1) The row has to fit in the image
+    let row_len = self.shape.first().copied().unwrap_or(1);
+    if self.rows_written == self.get_num_rows() || row.len() != row_len {
+      return Err(Box::new(RowShapeErr::new(&self.shape, self.rows_written, Some(row.len()))));
+    }
+
+    //(2) Encode it and write all complete blocks once the buffer is full
+    row.iter().for_each(|&px| px.fill_buf(&mut self.buffer));
+    self.rows_written += 1;
+    if self.buffer.len() >= BUFFER_BLOCKS * BLOCK_SIZE {
+      let n_full = self.buffer.len() / BLOCK_SIZE * BLOCK_SIZE;
+      self.writer.write_blocks(&self.buffer[..n_full])?;
+      self.buffer.drain(..n_full);
+    }
+    Ok(())
+  }
+
+  pub fn write_rows<I, R>(&mut self, rows: I) -> Result<(), Box<dyn Error + Send + Sync>>
+  where
+    I: IntoIterator<Item = R>,
+    R: AsRef<[T]>,
+  {
+    rows.into_iter().try_for_each(|row| self.write_row(row.as_ref()))
+  }
+
+  pub fn finish(mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+    self.write_tail()
+  }
+
+  fn write_tail(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+    //(1) All rows have to be there
+    if self.rows_written != self.get_num_rows() {
+      return Err(Box::new(RowShapeErr::new(&self.shape, self.rows_written, None)));
+    }
+
+    //(2) Pad the last block with zeroes and write it
+    self.buffer.resize(self.buffer.len().div_ceil(BLOCK_SIZE) * BLOCK_SIZE, 0);
+    self.writer.write_blocks(&self.buffer)?;
+    self.buffer.clear();
+    self.writer.flush()
+  }
+
+  pub fn get_shape(&self) -> &[usize] {
+    &self.shape
+  }
+  pub fn get_num_rows(&self) -> usize {
+    self.shape.iter().skip(1).product()
+  }
+  pub fn get_rows_written(&self) -> usize {
+    self.rows_written
+  }
+}
+
+#[derive(Debug)]
+pub struct ImageStreamReader<T: FitsPixel> {
+  /*  THIS STRUCT IS PART OF THE USER-FACING API
+   */
+  reader: RawFitsReader,
+  hdu: usize,
+  header: Header,
+  shape: Vec<usize>,
+  rows_read: usize,
+  blocks_left: usize, //blocks of the data unit that were not read yet
+  buffer: Vec<u8>,    //bytes that were read, but not decoded yet
+  datasum: u32,
+  pixel: PhantomData<T>,
+}
+
+impl<T: FitsPixel> ImageStreamReader<T> {
+  pub fn open(path: &Path) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    Self::open_hdu(path, 0)
+  }
+
+  pub fn open_hdu(path: &Path, hdu: usize) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    //(1) Find the HDU and decode its header
+    let index = FitsIndex::build(path)?;
+    let (Some(layout), Some(header)) = (index.get_layout(hdu), index.read_header(hdu)?) else {
+      return Err(Box::new(HduNotFoundErr::new(hdu.to_string())));
+    };
+
+    //(2) It has to be an image of the right type
+    let (shape, bitpix) = HeaderDataUnit::img_layout(&header)?;
+    if shape.is_empty() || header.get_value("XTENSION").is_some_and(|kw| kw != "'IMAGE   '") {
+      return Err(Box::new(MissingDataErr::new("an image")));
+    }
+    if bitpix.to_code() != T::BITPIX.to_code() {
+      return Err(Box::new(WrongImgTypeErr::from_bitpix(bitpix, T::BITPIX)));
+    }
+
+    //(3) Move to the start of the data unit
+    let mut reader = RawFitsReader::new(path)?;
+    reader.seek_block(layout.get_start_block() + layout.get_header_blocks())?;
+    Ok(ImageStreamReader {
+      reader,
+      hdu,
+      header,
+      shape,
+      rows_read: 0,
+      blocks_left: layout.get_data_blocks(),
+      buffer: Vec::with_capacity(BUFFER_BLOCKS * BLOCK_SIZE),
+      datasum: 0,
+      pixel: PhantomData,
+    })
+  }
+
+  pub fn read_row(&mut self) -> Result<Option<Vec<T>>, Box<dyn Error + Send + Sync>> {
+    //Returns None once all rows were read
+    if self.rows_read == self.get_num_rows() {
+      return Ok(None);
+    }
+
+    //(1) Make sure the whole row is in the buffer
+    let px_size = T::BITPIX.to_code().unsigned_abs() / 8;
+    let row_bytes = self.shape[0] * px_size;
+    while self.buffer.len() < row_bytes {
+      if self.blocks_left == 0 {
+        return Err(Box::new(InvalidFitsFileErr::new(io_err::FILE_END)));
+      }
+      self.read_blocks()?;
+    }
+
+    //(2) Decode it
+    let row = self.buffer[..row_bytes].chunks_exact(px_size).map(T::from_fits_bytes).collect();
+    self.buffer.drain(..row_bytes);
+    self.rows_read += 1;
+    Ok(Some(row))
+  }
+
+  fn read_blocks(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+    //Reads (and sums) the next blocks of the data unit into the buffer
+    let n_blocks = self.blocks_left.min(BUFFER_BLOCKS);
+    let start = self.buffer.len();
+    self.buffer.resize(start + n_blocks * BLOCK_SIZE, 0);
+    self.reader.read_blocks(&mut self.buffer[start..])?;
+    self.datasum = checksum::sum_bytes(&self.buffer[start..], self.datasum);
+    self.blocks_left -= n_blocks;
+    Ok(())
+  }
+
+  pub fn finish(mut self) -> Result<Vec<Diagnostic>, Box<dyn Error + Send + Sync>> {
+    /*  Reads the rest of the data unit and checks it against the DATASUM
+        record of the header. A mismatch is returned as a warning, since the
+        rows that were read were decoded just fine.
+    */
+    while self.blocks_left > 0 {
+      self.buffer.clear();
+      self.read_blocks()?;
+    }
+    let Some(raw) = self.header.get_value("DATASUM") else {
+      return Ok(Vec::new());
+    };
+    let expected = unquote(raw).unwrap_or(raw.clone());
+    if expected.trim().parse::<u32>().ok() == Some(self.datasum) {
+      return Ok(Vec::new());
+    }
+    let message = format!("data unit sums to {}, which does not match DATASUM", self.datasum);
+    Ok(vec![Diagnostic::new(PROFILE, self.hdu, "DATASUM", raw, message, Severity::Warning)])
+  }
+
+  pub fn get_header(&self) -> &Header {
+    &self.header
+  }
+  pub fn get_shape(&self) -> &[usize] {
+    &self.shape
+  }
+  pub fn get_num_rows(&self) -> usize {
+    self.shape.iter().skip(1).product()
+  }
+  pub fn get_rows_read(&self) -> usize {
+    self.rows_read
+  }
+}
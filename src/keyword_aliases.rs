@@ -0,0 +1,119 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Not every observatory spells its keywords the way the FITS standard (or
+    the conventions that grew around it) does. Old files use EPOCH instead
+    of EQUINOX, some instruments write EXPOSURE instead of EXPTIME, and
+    European archives sometimes use translated keywords. KeywordAliases maps
+    such aliases to a canonical keyword, so that the rest of the code only
+    has to look for one name.
+
+    Aliases are applied when reading a file with ReadOptions::keyword_aliases,
+    or to any header with KeywordAliases::apply. An alias is only renamed if
+    the header does not contain the canonical keyword already, in which case
+    the alias is kept as-is. Renamed records keep their position, value and
+    comment. Structural keywords (SIMPLE, BITPIX, NAXISn...) are never
+    renamed, nor renamed to.
+*/
+
+use indexmap::IndexMap;
+
+use crate::{header::Header, raw::keyword_record::KeywordRecord};
+
+//The default aliases (alias, canonical keyword)
+const DEFAULT_ALIASES: [(&str, &str); 15] = [
+  ("EPOCH", "EQUINOX"),
+  ("RADECSYS", "RADESYS"),
+  ("EXPOSURE", "EXPTIME"),
+  ("EXP_TIME", "EXPTIME"),
+  ("ITIME", "EXPTIME"),
+  ("DATE_OBS", "DATE-OBS"),
+  ("OBJNAME", "OBJECT"),
+  ("TELESCOPE", "TELESCOP"),
+  ("INSTRUMENT", "INSTRUME"),
+  ("READNOIS", "RDNOISE"),
+  ("RON", "RDNOISE"),
+  ("EGAIN", "GAIN"),
+  ("SECZ", "AIRMASS"),
+  ("OBJET", "OBJECT"),
+  ("OBJEKT", "OBJECT"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeywordAliases {
+  //alias -> canonical keyword, applied in insertion order
+  aliases: IndexMap<String, String>,
+}
+
+impl Default for KeywordAliases {
+  fn default() -> Self {
+    DEFAULT_ALIASES
+      .iter()
+      .fold(Self::empty(), |aliases, (alias, canonical)| aliases.alias(alias, canonical))
+  }
+}
+
+impl KeywordAliases {
+  pub fn new() -> Self {
+    //The default alias set
+    Self::default()
+  }
+
+  pub fn empty() -> Self {
+    KeywordAliases { aliases: IndexMap::new() }
+  }
+
+  pub fn alias(mut self, alias: &str, canonical: &str) -> Self {
+    //Adds an alias, or replaces the canonical keyword of an existing one
+    self.aliases.insert(alias.trim().to_uppercase(), canonical.trim().to_uppercase());
+    self
+  }
+
+  pub fn without(mut self, alias: &str) -> Self {
+    //Removes an alias (for example one of the defaults)
+    self.aliases.shift_remove(&alias.trim().to_uppercase());
+    self
+  }
+
+  pub fn get_canonical(&self, alias: &str) -> Option<&str> {
+    self.aliases.get(&alias.trim().to_uppercase()).map(String::as_str)
+  }
+
+  pub fn aliases(&self) -> impl Iterator<Item = (&str, &str)> {
+    self.aliases.iter().map(|(alias, canonical)| (alias.as_str(), canonical.as_str()))
+  }
+
+  pub fn apply(&self, header: &mut Header) -> Vec<String> {
+    //Renames the aliases found in the header, returns the renamed aliases
+    let mut renamed = Vec::new();
+    for (alias, canonical) in &self.aliases {
+      if KeywordRecord::is_structural(alias)
+        || KeywordRecord::is_structural(canonical)
+        || header.get_record(alias).is_none()
+        || header.get_record(canonical).is_some()
+      {
+        continue;
+      }
+      header.rename_record(alias, canonical);
+      renamed.push(alias.clone());
+    }
+    renamed
+  }
+}
@@ -0,0 +1,247 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Some observatories use keyword value syntaxes that are not part of the FITS
+    standard, such as sexagesimal coordinates ('12:34:56.7'). Types that
+    implement KeywordValue can be read from and written to a header with
+    Header::get_value_with and Header::set_value_with, without having to touch
+    the header parser.
+*/
+
+use std::{
+  error::Error,
+  fmt::{self, Display, Formatter},
+  str::FromStr,
+};
+
+use crate::keyword_err::InvalidValueErr;
+
+pub trait KeywordValue: Sized {
+  /*
+      The raw value is passed exactly as it appears in the header, so string
+      values still include their quotes (see unquote and quote).
+  */
+  fn parse_value(raw: &str) -> Result<Self, Box<dyn Error + Send + Sync>>;
+  fn format_value(&self) -> String;
+}
+
+pub fn unquote(raw: &str) -> Option<String> {
+  //Strips the quotes from a FITS string value, un-escaping inner quotes
+  let raw = raw.trim();
+  let inner = raw.strip_prefix('\'')?.strip_suffix('\'')?;
+  Some(inner.trim_end().replace("''", "'"))
+}
+
+pub fn quote(value: &str) -> String {
+  //Formats a string as a FITS string value (escaping inner quotes)
+  format!("'{}'", value.replace('\'', "''"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sexagesimal {
+  /*
+      Sexagesimal value such as '12:34:56.7' (hours or degrees, minutes and
+      seconds). The sign applies to the value as a whole, so '-00:30:00' is
+      stored as negative with zero units.
+  */
+  negative: bool,
+  units: u32,
+  minutes: u32,
+  seconds: f64,
+}
+
+impl Sexagesimal {
+  pub fn new(negative: bool, units: u32, minutes: u32, seconds: f64) -> Self {
+    Sexagesimal { negative, units, minutes, seconds }
+  }
+
+  pub fn from_decimal(value: f64) -> Self {
+    let abs = value.abs();
+    let (units, rest) = (abs.trunc(), abs.fract() * 60.0);
+    let (minutes, seconds) = (rest.trunc(), rest.fract() * 60.0);
+    Self::new(value < 0.0, units as u32, minutes as u32, seconds)
+  }
+
+  pub fn to_decimal(&self) -> f64 {
+    let abs = self.units as f64 + self.minutes as f64 / 60.0 + self.seconds / 3600.0;
+    match self.negative {
+      true => -abs,
+      false => abs,
+    }
+  }
+
+  pub fn is_negative(&self) -> bool {
+    self.negative
+  }
+  pub fn get_units(&self) -> u32 {
+    self.units
+  }
+  pub fn get_minutes(&self) -> u32 {
+    self.minutes
+  }
+  pub fn get_seconds(&self) -> f64 {
+    self.seconds
+  }
+}
+
+impl FromStr for Sexagesimal {
+  type Err = InvalidValueErr;
+
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    //Text without quotes, such as the entries of a table column
+    let invalid = || InvalidValueErr::new(text, "sexagesimal value");
+
+    //(1) The sign is optional
+    let trimmed = text.trim();
+    let (negative, trimmed) = match trimmed.strip_prefix('-') {
+      Some(rest) => (true, rest),
+      None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+
+    //(2) Fields may be separated by colons or spaces
+    let fields: Vec<&str> = trimmed.split([':', ' ']).filter(|field| !field.is_empty()).collect();
+    match fields[..] {
+      [units, minutes, seconds] => Ok(Sexagesimal::new(
+        negative,
+        units.parse().map_err(|_| invalid())?,
+        minutes.parse().map_err(|_| invalid())?,
+        seconds.parse().map_err(|_| invalid())?,
+      )),
+      _ => Err(invalid()),
+    }
+  }
+}
+
+impl KeywordValue for Sexagesimal {
+  fn parse_value(raw: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    //Sexagesimal values are stored as strings
+    let text = unquote(raw).ok_or_else(|| InvalidValueErr::new(raw, "sexagesimal value"))?;
+    Ok(text.parse::<Sexagesimal>().map_err(|_| InvalidValueErr::new(raw, "sexagesimal value"))?)
+  }
+
+  fn format_value(&self) -> String {
+    quote(&format!("{self}"))
+  }
+}
+
+impl Display for Sexagesimal {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    let sign = if self.negative { "-" } else { "" };
+    let pad = if self.seconds < 10.0 { "0" } else { "" };
+    write!(f, "{sign}{:02}:{:02}:{pad}{}", self.units, self.minutes, self.seconds)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetaValue {
+  /*  THIS ENUM IS PART OF THE USER-FACING API
+      Typed value of a header record, see Header::metadata_pairs. Values that
+      do not follow the FITS syntax are kept as (unquoted) strings.
+  */
+  Logical(bool),
+  Integer(i64),
+  Float(f64),
+  Complex(f64, f64),
+  String(String),
+  Undefined,
+}
+
+impl MetaValue {
+  pub(crate) fn from_raw(raw: Option<&str>) -> Self {
+    let Some(raw) = raw.map(str::trim).filter(|raw| !raw.is_empty()) else {
+      return MetaValue::Undefined;
+    };
+    if let Some(text) = unquote(raw) {
+      return MetaValue::String(text);
+    }
+    match raw {
+      "T" => return MetaValue::Logical(true),
+      "F" => return MetaValue::Logical(false),
+      _ => {}
+    }
+    if let Ok(int) = raw.parse::<i64>() {
+      return MetaValue::Integer(int);
+    }
+    if let Some(float) = parse_float(raw) {
+      return MetaValue::Float(float);
+    }
+    let parts = raw.strip_prefix('(').and_then(|rest| rest.strip_suffix(')'));
+    if let Some((re, im)) = parts.and_then(|parts| parts.split_once(',')) {
+      if let (Some(re), Some(im)) = (parse_float(re), parse_float(im)) {
+        return MetaValue::Complex(re, im);
+      }
+    }
+    MetaValue::String(raw.to_string())
+  }
+
+  pub(crate) fn to_raw(&self) -> Result<Option<String>, InvalidValueErr> {
+    //FITS has no syntax for non-finite reals
+    let check = |value: f64| match value.is_finite() {
+      true => Ok(format_float(value)),
+      false => Err(InvalidValueErr::new(&value.to_string(), "finite real")),
+    };
+    Ok(match self {
+      MetaValue::Logical(true) => Some(String::from("T")),
+      MetaValue::Logical(false) => Some(String::from("F")),
+      MetaValue::Integer(int) => Some(int.to_string()),
+      MetaValue::Float(float) => Some(check(*float)?),
+      MetaValue::Complex(re, im) => Some(format!("({}, {})", check(*re)?, check(*im)?)),
+      MetaValue::String(text) => Some(quote(text)),
+      MetaValue::Undefined => None,
+    })
+  }
+}
+
+impl KeywordValue for MetaValue {
+  fn parse_value(raw: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    Ok(MetaValue::from_raw(Some(raw)))
+  }
+
+  fn format_value(&self) -> String {
+    match self {
+      MetaValue::Float(float) => format_float(*float),
+      MetaValue::Complex(re, im) => format!("({}, {})", format_float(*re), format_float(*im)),
+      other => other.to_raw().ok().flatten().unwrap_or_default(),
+    }
+  }
+}
+
+impl Display for MetaValue {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      MetaValue::Logical(value) => write!(f, "{value}"),
+      MetaValue::Integer(value) => write!(f, "{value}"),
+      MetaValue::Float(value) => write!(f, "{value}"),
+      MetaValue::Complex(re, im) => write!(f, "({re}, {im})"),
+      MetaValue::String(text) => write!(f, "{text}"),
+      MetaValue::Undefined => Ok(()),
+    }
+  }
+}
+
+pub(crate) fn parse_float(raw: &str) -> Option<f64> {
+  //FITS allows D as exponent for double precision reals (but no inf or nan)
+  raw.trim().replace(['D', 'd'], "E").parse().ok().filter(|value: &f64| value.is_finite())
+}
+
+pub(crate) fn format_float(value: f64) -> String {
+  //FITS wants an uppercase exponent, and reals should contain a period
+  format!("{value:?}").replace('e', "E")
+}
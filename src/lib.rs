@@ -17,30 +17,170 @@
     along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
 */
 
+/*  Unsafe code is forbidden outright, unless the C API or memory maps are
+    compiled in. Even then it is only allowed in capi.rs and mapped_pixels.rs,
+    so the parsing core stays free of unsafe code in every build.
+*/
+#![cfg_attr(not(any(feature = "capi", feature = "mmap")), forbid(unsafe_code))]
+#![cfg_attr(any(feature = "capi", feature = "mmap"), deny(unsafe_code))]
+
 //Module structure
+mod batch;
 mod bitpix;
+#[cfg(feature = "capi")]
+#[allow(unsafe_code)]
+mod capi;
+mod catalog;
+mod charset;
+mod checksum;
+mod column_image;
+mod coord_columns;
+mod cosmics;
+mod duplicates;
 mod err;
+mod export;
 mod extensions;
 mod fits;
+mod fits_index;
 mod header;
 mod header_data_unit;
+mod hierarch;
+mod image_diff;
+mod image_stream;
+mod keyword_aliases;
+mod keyword_value;
+mod light_curve;
+mod manifest;
+mod obs_keywords;
+mod pixel_coords;
+mod provenance;
+mod psf;
+#[cfg(feature = "python")]
+mod python;
 mod raw;
+mod read_options;
+mod repack;
+mod schema;
+mod section;
+mod stack;
+mod stats;
+mod tile_cache;
+mod user_data;
+mod validation;
+mod wcs;
+mod write_options;
+mod zscale;
 
 //Constants defined by the FITS standard
 pub(crate) const BLOCK_SIZE: usize = 2880;
 
 //Public api re-exports
+pub use batch::BatchReader;
+pub use bitpix::{promote, Bitpix, PromotionRules};
+pub use catalog::{CatalogEntry, HeaderCatalog};
+pub use charset::CharsetPolicy;
+pub use coord_columns::SexagesimalUnit;
+pub use cosmics::{Cosmics, CosmicsResult};
+pub use duplicates::DuplicatePolicy;
 pub use err::*;
+pub use export::RegionOptions;
+pub use extensions::image::{
+  boxcar_kernel, gaussian_kernel, Boundary, CompactImage, FitsPixel, Image, InpaintMethod,
+  Reduction, TypedImage,
+};
+#[cfg(feature = "fft")]
+pub use extensions::image::{FftNorm, FftOptions, FftPadding};
+pub use extensions::table::{
+  AsciiTable, BinFormat, BinTable, BinType, FitsField, FitsRow, TableBuilder, TableEntry, Validity,
+};
 pub use extensions::Extension;
 pub use fits::Fits;
+pub use fits_index::{FitsIndex, HduLayout};
 pub use header::Header;
-pub use header_data_unit::HeaderDataUnit;
+pub use header_data_unit::{DataSource, HeaderDataUnit};
+pub use hierarch::HierarchNode;
+pub use image_diff::{compare_images, ImageDiffReport, Tolerance};
+pub use image_stream::{ImageStreamReader, ImageStreamWriter};
+pub use keyword_aliases::KeywordAliases;
+pub use keyword_value::{quote, unquote, KeywordValue, MetaValue, Sexagesimal};
+pub use light_curve::LightCurve;
+pub use manifest::ManifestEntry;
+pub use obs_keywords::{ObservationTime, SkyFrame, TimeScale};
+pub use pixel_coords::{
+  containing_index, containing_indices, fits_to_index, index_to_fits, mirror_fits,
+};
+pub use provenance::{ProvenanceGraph, ProvenanceNode};
+pub use psf::Psf;
+#[cfg(feature = "python")]
+pub use python::{register_python_module, PyFits, PyHdu};
+pub use raw::block_io::{BlockRead, BlockWrite};
+pub use raw::raw_io::LockPolicy;
+pub use raw::stream_io::StreamWriter;
+pub use read_options::{FieldTolerance, ReadOptions, StringInterning, TableStrategy};
+pub use repack::RepackReport;
+#[cfg(feature = "derive")]
+pub use rustronomy_fits_derive::FitsRow;
+pub use schema::{ColumnType, DataKind, HduSchema, Schema, SchemaReport, Violation, ViolationKind};
+pub use section::{AxisRange, ExtendedPath, HduSelector, Section};
+pub use stack::Stack;
+pub use stats::ImageStats;
+pub use tile_cache::TileCache;
+pub use validation::{Diagnostic, Severity, ValidationProfile};
+pub use wcs::CelestialWcs;
+pub use write_options::{ExtendPolicy, FitsStandard, WriteOptions};
+pub use zscale::{percentile_limits, zscale, ZScale};
 
 //prelude (kinda pointless rn but whatev)
 pub mod prelude {
+  pub use crate::batch::BatchReader;
+  pub use crate::bitpix::{promote, Bitpix, PromotionRules};
+  pub use crate::catalog::{CatalogEntry, HeaderCatalog};
+  pub use crate::charset::CharsetPolicy;
+  pub use crate::coord_columns::SexagesimalUnit;
+  pub use crate::cosmics::{Cosmics, CosmicsResult};
+  pub use crate::duplicates::DuplicatePolicy;
   pub use crate::err::*;
+  pub use crate::export::RegionOptions;
+  pub use crate::extensions::image::{
+    Boundary, CompactImage, FitsPixel, Image, InpaintMethod, Reduction, TypedImage,
+  };
+  #[cfg(feature = "fft")]
+  pub use crate::extensions::image::{FftNorm, FftOptions, FftPadding};
+  pub use crate::extensions::table::{
+    AsciiTable, BinFormat, BinTable, BinType, FitsField, FitsRow, TableBuilder, TableEntry,
+    Validity,
+  };
   pub use crate::extensions::Extension;
   pub use crate::fits::Fits;
+  pub use crate::fits_index::{FitsIndex, HduLayout};
   pub use crate::header::Header;
-  pub use crate::header_data_unit::HeaderDataUnit;
+  pub use crate::header_data_unit::{DataSource, HeaderDataUnit};
+  pub use crate::hierarch::HierarchNode;
+  pub use crate::image_diff::{compare_images, ImageDiffReport, Tolerance};
+  pub use crate::image_stream::{ImageStreamReader, ImageStreamWriter};
+  pub use crate::keyword_aliases::KeywordAliases;
+  pub use crate::keyword_value::{KeywordValue, MetaValue, Sexagesimal};
+  pub use crate::light_curve::LightCurve;
+  pub use crate::manifest::ManifestEntry;
+  pub use crate::obs_keywords::{ObservationTime, SkyFrame, TimeScale};
+  pub use crate::provenance::{ProvenanceGraph, ProvenanceNode};
+  pub use crate::psf::Psf;
+  pub use crate::raw::block_io::{BlockRead, BlockWrite};
+  pub use crate::raw::raw_io::LockPolicy;
+  pub use crate::raw::stream_io::StreamWriter;
+  pub use crate::read_options::{FieldTolerance, ReadOptions, StringInterning, TableStrategy};
+  pub use crate::repack::RepackReport;
+  pub use crate::schema::{
+    ColumnType, DataKind, HduSchema, Schema, SchemaReport, Violation, ViolationKind,
+  };
+  pub use crate::section::{AxisRange, ExtendedPath, HduSelector, Section};
+  pub use crate::stack::Stack;
+  pub use crate::stats::ImageStats;
+  pub use crate::tile_cache::TileCache;
+  pub use crate::validation::{Diagnostic, Severity, ValidationProfile};
+  pub use crate::wcs::CelestialWcs;
+  pub use crate::write_options::{ExtendPolicy, FitsStandard, WriteOptions};
+  pub use crate::zscale::ZScale;
+  #[cfg(feature = "derive")]
+  pub use rustronomy_fits_derive::FitsRow;
 }
@@ -0,0 +1,238 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{error::Error, ops::Range};
+
+use ndarray::Array1;
+
+use crate::{
+  extensions::{table::AsciiTable, Extension},
+  hdu_err::{InvalidRecordValueError, MissingDataErr},
+  header::Header,
+  header_data_unit::HeaderDataUnit,
+  keyword_value::unquote,
+  tbl_err::MissingColumnErr,
+};
+
+/*
+    Light curves are stored as tables with a TIME column, a flux column and
+    (usually) a column with the uncertainty of the flux. Kepler and TESS call
+    the flux columns SAP_FLUX and PDCSAP_FLUX, other missions just FLUX, with
+    the uncertainties in {flux}_ERR. For now this only reads light curves in
+    ASCII tables, not the binary tables (see BinTable) those missions use.
+
+    The TIME column holds the time relative to a reference time, given by
+    BJDREFI + BJDREFF (or BJDREF, or the MJDREF/JDREF variants) plus TIMEZERO.
+    LightCurve returns the absolute time in days (TIMEUNIT may be 'd' or 's').
+    Rows without a time are dropped and the rows are sorted in time. Rows
+    without a flux are kept (as NaN), since they do mark the cadence.
+
+    Gaps are found by comparing the time between consecutive rows with the
+    cadence of the light curve, which is the median time between rows.
+*/
+
+//Flux columns that are tried in order by from_hdu
+const FLUX_COLUMNS: [&str; 3] = ["FLUX", "PDCSAP_FLUX", "SAP_FLUX"];
+//Reference time keywords, tried in order (integer and fractional part)
+const TIME_REFERENCES: [(&str, &str, &str); 3] = [
+  ("BJDREFI", "BJDREFF", "BJDREF"),
+  ("MJDREFI", "MJDREFF", "MJDREF"),
+  ("JDREFI", "JDREFF", "JDREF"),
+];
+const TIME_UNITS: [&str; 2] = ["d", "s"];
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+#[derive(Debug, Clone)]
+pub struct LightCurve {
+  time: Array1<f64>,
+  flux: Array1<f64>,
+  flux_err: Option<Array1<f64>>,
+  flux_column: String,
+  time_ref: f64,
+  timesys: String,
+}
+
+impl LightCurve {
+  pub fn from_hdu(hdu: &HeaderDataUnit) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    //Uses the first flux column that is present
+    let (header, table) = Self::table(hdu)?;
+    let flux = FLUX_COLUMNS
+      .iter()
+      .find(|name| find_column(header, table, name).is_some())
+      .ok_or(MissingColumnErr::new(&FLUX_COLUMNS))?;
+    Self::from_hdu_with(hdu, flux)
+  }
+
+  pub fn from_hdu_with(
+    hdu: &HeaderDataUnit,
+    flux_column: &str,
+  ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    let (header, table) = Self::table(hdu)?;
+
+    //(1) Find the columns
+    let time_col = find_column(header, table, "TIME").ok_or(MissingColumnErr::new(&["TIME"]))?;
+    let flux_col =
+      find_column(header, table, flux_column).ok_or(MissingColumnErr::new(&[flux_column]))?;
+    let err_col = find_column(header, table, &format!("{flux_column}_ERR"));
+
+    //(2) Time reference and unit
+    let scale = match header.get_value("TIMEUNIT").and_then(|raw| unquote(raw)).as_deref() {
+      None | Some("d") => 1.0,
+      Some("s") => 1.0 / SECONDS_PER_DAY,
+      Some(other) => Err(InvalidRecordValueError::new("TIMEUNIT", other, &TIME_UNITS))?,
+    };
+    let time_ref = time_reference(header, scale)?;
+    let timesys = header
+      .get_value("TIMESYS")
+      .and_then(|raw| unquote(raw))
+      .map(|sys| sys.trim().to_string())
+      .unwrap_or(String::from("UTC"));
+
+    //(3) Read the rows that have a time, in time order
+    let n_rows = table.get_shape().1;
+    let mut rows = Vec::with_capacity(n_rows);
+    for row in 0..n_rows {
+      let time = table.get_float(time_col, row)? * scale + time_ref;
+      if !time.is_finite() {
+        continue;
+      }
+      let flux = table.get_float(flux_col, row)?;
+      let err = match err_col {
+        Some(col) => table.get_float(col, row)?,
+        None => f64::NAN,
+      };
+      rows.push((time, flux, err));
+    }
+    rows.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    Ok(LightCurve {
+      time: rows.iter().map(|row| row.0).collect(),
+      flux: rows.iter().map(|row| row.1).collect(),
+      flux_err: err_col.map(|_| rows.iter().map(|row| row.2).collect()),
+      flux_column: flux_column.to_string(),
+      time_ref,
+      timesys,
+    })
+  }
+
+  fn table(hdu: &HeaderDataUnit) -> Result<(&Header, &AsciiTable), MissingDataErr> {
+    match hdu.get_data() {
+      Some(Extension::AsciiTable(table)) => Ok((hdu.get_header(), table)),
+      _ => Err(MissingDataErr::new("a table")),
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.time.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.time.is_empty()
+  }
+
+  pub fn get_cadence(&self) -> Option<f64> {
+    //Median time between consecutive rows, if there are at least two
+    let mut steps: Vec<f64> = self.time.windows(2).into_iter().map(|w| w[1] - w[0]).collect();
+    if steps.is_empty() {
+      return None;
+    }
+    let mid = steps.len() / 2;
+    Some(*steps.select_nth_unstable_by(mid, f64::total_cmp).1)
+  }
+
+  pub fn segments(&self, factor: f64) -> Vec<Range<usize>> {
+    /*  Splits the light curve into contiguous segments (ranges of rows). A
+        new segment starts wherever the time between two rows exceeds factor
+        times the cadence
+    */
+    let Some(cadence) = self.get_cadence() else {
+      return std::iter::once(0..self.len()).collect();
+    };
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for row in 1..self.len() {
+      if self.time[row] - self.time[row - 1] > factor * cadence {
+        segments.push(start..row);
+        start = row;
+      }
+    }
+    segments.push(start..self.len());
+    segments
+  }
+
+  pub fn gaps(&self, factor: f64) -> Vec<(f64, f64)> {
+    //The gaps between the segments, as (last time before, first time after)
+    self
+      .segments(factor)
+      .windows(2)
+      .map(|pair| (self.time[pair[0].end - 1], self.time[pair[1].start]))
+      .collect()
+  }
+
+  pub fn get_time(&self) -> &Array1<f64> {
+    &self.time
+  }
+  pub fn get_flux(&self) -> &Array1<f64> {
+    &self.flux
+  }
+  pub fn get_flux_err(&self) -> Option<&Array1<f64>> {
+    self.flux_err.as_ref()
+  }
+  pub fn get_flux_column(&self) -> &str {
+    &self.flux_column
+  }
+  pub fn get_time_ref(&self) -> f64 {
+    self.time_ref
+  }
+  pub fn get_timesys(&self) -> &str {
+    &self.timesys
+  }
+}
+
+fn find_column(header: &Header, table: &AsciiTable, name: &str) -> Option<usize> {
+  //Column names (TTYPEn) are case insensitive
+  (0..table.get_shape().0).find(|col| {
+    header
+      .get_value(&format!("TTYPE{}", col + 1))
+      .and_then(|raw| unquote(raw))
+      .is_some_and(|ttype| ttype.trim().eq_ignore_ascii_case(name))
+  })
+}
+
+fn time_reference(header: &Header, scale: f64) -> Result<f64, Box<dyn Error + Send + Sync>> {
+  //(1) The first reference that is present, split or not
+  let mut time_ref = 0.0;
+  for (int, frac, whole) in TIME_REFERENCES {
+    if header.get_record(int).is_some() || header.get_record(frac).is_some() {
+      time_ref = header.get_value_as::<f64>(int).unwrap_or(0.0)
+        + header.get_value_as::<f64>(frac).unwrap_or(0.0);
+      break;
+    }
+    if header.get_record(whole).is_some() {
+      time_ref = header.get_value_as::<f64>(whole)?;
+      break;
+    }
+  }
+
+  //(2) TIMEZERO is a further offset (in TIMEUNIT)
+  match header.get_record("TIMEZERO") {
+    Some(_) => Ok(time_ref + header.get_value_as::<f64>("TIMEZERO")? * scale),
+    None => Ok(time_ref),
+  }
+}
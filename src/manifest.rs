@@ -0,0 +1,179 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Digests for change detection: caching layers on top of large archives have
+    to know whether an HDU changed without comparing it byte by byte. The
+    digests are stable (they do not depend on the platform, the Rust version
+    or the way a header happens to be formatted), so they may be stored and
+    compared later. They are not cryptographic hashes.
+
+    Header digests are computed over normalized records: the keyword, the
+    value without padding (and without trailing blanks in strings) and the
+    comment. Records that change whenever anything changes (CHECKSUM and
+    DATASUM) are left out. The order of the records does count.
+*/
+
+use std::{
+  fmt::{self, Display, Formatter},
+  hash::Hasher,
+};
+
+use crate::{
+  extensions::{image::FitsPixel, Extension},
+  header::Header,
+  keyword_value::{quote, unquote},
+};
+
+//Records that are left out of header digests
+const VOLATILE_KEYWORDS: [&str; 2] = ["CHECKSUM", "DATASUM"];
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Fnv64(u64);
+
+impl Default for Fnv64 {
+  fn default() -> Self {
+    Fnv64(0xcbf2_9ce4_8422_2325)
+  }
+}
+
+impl Hasher for Fnv64 {
+  //64 bit FNV-1a, which (unlike DefaultHasher) is stable
+  fn write(&mut self, bytes: &[u8]) {
+    for &byte in bytes {
+      self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+    }
+  }
+  fn finish(&self) -> u64 {
+    self.0
+  }
+}
+
+fn normalize_value(value: &str) -> String {
+  match unquote(value) {
+    Some(text) => quote(text.trim_end()),
+    None => value.trim().to_string(),
+  }
+}
+
+pub(crate) fn header_digest(header: &Header) -> u64 {
+  let mut hasher = Fnv64::default();
+  for keyword in header.keywords().filter(|kw| !VOLATILE_KEYWORDS.contains(kw)) {
+    let value = header.get_value(keyword).map_or(String::new(), |val| normalize_value(val));
+    let comment = header.get_comment(keyword).map_or("", |comment| comment.trim());
+    hasher.write(format!("{keyword}\0{value}\0{comment}\n").as_bytes());
+  }
+  for text in header.comments() {
+    hasher.write(format!("COMMENT\0{}\n", text.trim_end()).as_bytes());
+  }
+  for text in header.history() {
+    hasher.write(format!("HISTORY\0{}\n", text.trim_end()).as_bytes());
+  }
+  hasher.finish()
+}
+
+pub(crate) fn data_digest(data: &Extension) -> Option<u64> {
+  let mut hasher = Fnv64::default();
+  match data {
+    Extension::Corrupted => return None,
+    Extension::Image(img) => {
+      //Pixels are hashed in the order (and byte format) of a FITS file
+      hasher.write(format!("IMAGE\0{}\0", img.bpx()).as_bytes());
+      crate::impl_typed_image_dispatch!(img, img => {
+        img.get_shape().iter().for_each(|&len| hasher.write(&(len as u64).to_be_bytes()));
+        img.get_data().t().iter().for_each(|px| hasher.write(&px.to_fits_bytes()));
+      });
+    }
+    Extension::AsciiTable(tbl) => {
+      //Entries are hashed as formatted text, column by column
+      hasher.write(b"TABLE\0");
+      for col in 0..tbl.get_shape().0 {
+        hasher.write(format!("{}\0", tbl.get_col_label(col).unwrap_or_default()).as_bytes());
+        for entry in tbl.get_fmtd_column(col).unwrap_or_default() {
+          hasher.write(format!("{entry}\n").as_bytes());
+        }
+      }
+    }
+    Extension::BinTable(tbl) => {
+      //Entries are hashed in their debug format, column by column
+      hasher.write(b"BINTABLE\0");
+      let (n_cols, n_rows) = tbl.get_shape();
+      for col in 0..n_cols {
+        hasher.write(format!("{}\0", tbl.get_col_label(col).unwrap_or_default()).as_bytes());
+        for row in 0..n_rows {
+          hasher.write(format!("{:?}\n", tbl.get_entry(col, row).ok()?).as_bytes());
+        }
+      }
+    }
+  }
+  Some(hasher.finish())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+  /*  THIS STRUCT IS PART OF THE USER-FACING API
+      Digests of the header and data of a single HDU. The data digest is None
+      if the HDU has no (readable) data in memory: no data unit, unloaded or
+      corrupted data.
+  */
+  hdu: usize,
+  extname: Option<String>,
+  header_digest: u64,
+  data_digest: Option<u64>,
+}
+
+impl ManifestEntry {
+  pub(crate) fn new(
+    hdu: usize,
+    extname: Option<String>,
+    header_digest: u64,
+    data_digest: Option<u64>,
+  ) -> Self {
+    ManifestEntry { hdu, extname, header_digest, data_digest }
+  }
+
+  pub fn get_hdu(&self) -> usize {
+    self.hdu
+  }
+  pub fn get_extname(&self) -> Option<&str> {
+    self.extname.as_deref()
+  }
+  pub fn get_header_digest(&self) -> u64 {
+    self.header_digest
+  }
+  pub fn get_data_digest(&self) -> Option<u64> {
+    self.data_digest
+  }
+}
+
+impl Display for ManifestEntry {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "#{} {:<8} header {:016x}",
+      self.hdu,
+      self.extname.as_deref().unwrap_or("-"),
+      self.header_digest
+    )?;
+    match self.data_digest {
+      None => write!(f, " data -"),
+      Some(digest) => write!(f, " data {digest:016x}"),
+    }
+  }
+}
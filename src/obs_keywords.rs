@@ -0,0 +1,329 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    The time and coordinate frame of an observation are described by several
+    keywords that have to agree with each other: DATE-OBS and MJD-OBS give the
+    same instant in the time scale given by TIMESYS, and EQUINOX only has a
+    meaning for the FK4 and FK5 frames given by RADESYS. Setting them one by
+    one makes it easy to write combinations that contradict each other (an
+    MJD-OBS that was rounded differently than DATE-OBS, or an EQUINOX of 1950
+    with RADESYS = 'FK5'). ObservationTime and SkyFrame write all keywords of
+    their block at once, derived from a single typed value.
+*/
+
+use std::error::Error;
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+
+use crate::{
+  coord_columns::SexagesimalUnit,
+  hdu_err::MissingRecordError,
+  header::Header,
+  keyword_err::InvalidValueErr,
+  keyword_value::{parse_float, unquote, MetaValue, Sexagesimal},
+};
+
+const SECONDS_PER_DAY: f64 = 86400.0;
+//Keywords for the pointing of the telescope, in order of precedence
+const RA_KEYWORDS: [&str; 2] = ["RA", "OBJCTRA"];
+const DEC_KEYWORDS: [&str; 2] = ["DEC", "OBJCTDEC"];
+//Formats accepted for DATE-OBS, besides a plain date (yyyy-mm-dd)
+const DATE_FORMATS: [&str; 2] = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S"];
+
+fn mjd_epoch() -> NaiveDateTime {
+  //MJD 0 is 1858-11-17T00:00:00
+  NaiveDate::from_ymd_opt(1858, 11, 17).unwrap().and_hms_opt(0, 0, 0).unwrap()
+}
+
+fn get_string(header: &Header, keyword: &str) -> Option<String> {
+  header.get_value(keyword).and_then(|raw| unquote(raw)).map(|text| text.trim().to_string())
+}
+
+fn set(header: &mut Header, keyword: &str, value: MetaValue, comment: &str) {
+  //The keywords set here are neither structural nor protected
+  header.set_value_with(keyword, &value, Some(comment.to_string())).unwrap()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeScale {
+  /*  THIS ENUM IS PART OF THE USER-FACING API
+      Time scales for TIMESYS, as listed in the FITS standard (section 9.2.1)
+  */
+  #[default]
+  Utc,
+  Tai,
+  Tt,
+  Tdb,
+  Tcg,
+  Tcb,
+  Gps,
+  Ut1,
+  Local,
+}
+
+impl TimeScale {
+  pub fn get_name(&self) -> &'static str {
+    match self {
+      TimeScale::Utc => "UTC",
+      TimeScale::Tai => "TAI",
+      TimeScale::Tt => "TT",
+      TimeScale::Tdb => "TDB",
+      TimeScale::Tcg => "TCG",
+      TimeScale::Tcb => "TCB",
+      TimeScale::Gps => "GPS",
+      TimeScale::Ut1 => "UT1",
+      TimeScale::Local => "LOCAL",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    //TDT and ET are deprecated names of TT, GMT and UT() were used for UTC
+    Some(match name.trim().to_ascii_uppercase().as_str() {
+      "UTC" | "GMT" | "UT()" => TimeScale::Utc,
+      "TAI" | "IAT" => TimeScale::Tai,
+      "TT" | "TDT" | "ET" => TimeScale::Tt,
+      "TDB" => TimeScale::Tdb,
+      "TCG" => TimeScale::Tcg,
+      "TCB" => TimeScale::Tcb,
+      "GPS" => TimeScale::Gps,
+      "UT1" => TimeScale::Ut1,
+      "LOCAL" => TimeScale::Local,
+      _ => return None,
+    })
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ObservationTime {
+  /*
+      Start of an observation. DATE-OBS is written with millisecond precision
+      and MJD-OBS is computed from the same (rounded) instant, so the two
+      always agree.
+  */
+  time: NaiveDateTime,
+  scale: TimeScale,
+}
+
+impl ObservationTime {
+  pub fn new(time: NaiveDateTime, scale: TimeScale) -> Self {
+    //Rounded to whole milliseconds, which is what DATE-OBS can hold
+    let delta = time - mjd_epoch();
+    let millis =
+      delta.num_microseconds().map_or(delta.num_milliseconds(), |us| (us + 500).div_euclid(1000));
+    ObservationTime { time: mjd_epoch() + Duration::milliseconds(millis), scale }
+  }
+
+  pub fn from_mjd(mjd: f64, scale: TimeScale) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    let millis = (mjd * SECONDS_PER_DAY * 1000.0).round();
+    if !millis.is_finite() || millis.abs() > i64::MAX as f64 {
+      return Err(InvalidValueErr::new(&mjd.to_string(), "modified julian date"))?;
+    }
+    let time = mjd_epoch()
+      .checked_add_signed(Duration::milliseconds(millis as i64))
+      .ok_or(InvalidValueErr::new(&mjd.to_string(), "modified julian date"))?;
+    Ok(ObservationTime { time, scale })
+  }
+
+  pub fn from_header(header: &Header) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    /*  Reads DATE-OBS, or MJD-OBS if there is no DATE-OBS. TIMESYS defaults
+        to UTC, as in the standard
+    */
+    let scale = match get_string(header, "TIMESYS") {
+      None => TimeScale::Utc,
+      Some(name) => {
+        TimeScale::from_name(&name).ok_or(InvalidValueErr::new(&name, "time scale (TIMESYS)"))?
+      }
+    };
+    if let Some(date) = get_string(header, "DATE-OBS") {
+      let time = DATE_FORMATS
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(&date, fmt).ok())
+        .or_else(|| NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0))
+        .ok_or(InvalidValueErr::new(&date, "ISO-8601 date (DATE-OBS)"))?;
+      return Ok(Self::new(time, scale));
+    }
+    Self::from_mjd(header.get_value_as::<f64>("MJD-OBS")?, scale)
+  }
+
+  pub fn write_to(&self, header: &mut Header) {
+    //Sets DATE-OBS, MJD-OBS and TIMESYS
+    set(header, "DATE-OBS", MetaValue::String(self.get_iso()), "start of observation");
+    set(header, "MJD-OBS", MetaValue::Float(self.get_mjd()), "[d] start of observation");
+    set(header, "TIMESYS", MetaValue::String(self.scale.get_name().to_string()), "time scale");
+  }
+
+  pub fn get_time(&self) -> NaiveDateTime {
+    self.time
+  }
+  pub fn get_scale(&self) -> TimeScale {
+    self.scale
+  }
+  pub fn get_mjd(&self) -> f64 {
+    (self.time - mjd_epoch()).num_milliseconds() as f64 / (SECONDS_PER_DAY * 1000.0)
+  }
+  pub fn get_iso(&self) -> String {
+    //DATE-OBS format (yyyy-mm-ddThh:mm:ss.sss)
+    self.time.format("%Y-%m-%dT%H:%M:%S%.3f").to_string()
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SkyFrame {
+  /*  THIS ENUM IS PART OF THE USER-FACING API
+      Celestial reference frames for RADESYS. Only the FK4 and FK5 frames have
+      an equinox (a Besselian year for FK4, a Julian year for FK5). GAPPT is
+      tied to the date of the observation rather than to an equinox.
+  */
+  #[default]
+  Icrs,
+  Fk5(f64),
+  Fk4(f64),
+  Fk4NoE(f64),
+  Gappt,
+}
+
+impl SkyFrame {
+  pub fn fk5() -> Self {
+    //FK5 at the standard equinox J2000
+    SkyFrame::Fk5(2000.0)
+  }
+  pub fn fk4() -> Self {
+    //FK4 at the standard equinox B1950
+    SkyFrame::Fk4(1950.0)
+  }
+
+  pub fn get_name(&self) -> &'static str {
+    match self {
+      SkyFrame::Icrs => "ICRS",
+      SkyFrame::Fk5(_) => "FK5",
+      SkyFrame::Fk4(_) => "FK4",
+      SkyFrame::Fk4NoE(_) => "FK4-NO-E",
+      SkyFrame::Gappt => "GAPPT",
+    }
+  }
+
+  pub fn get_equinox(&self) -> Option<f64> {
+    match self {
+      SkyFrame::Fk5(equinox) | SkyFrame::Fk4(equinox) | SkyFrame::Fk4NoE(equinox) => Some(*equinox),
+      SkyFrame::Icrs | SkyFrame::Gappt => None,
+    }
+  }
+
+  pub fn from_header(header: &Header) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    /*  Without RADESYS the standard defaults to FK4 for equinoxes before 1984,
+        FK5 for later equinoxes and ICRS if there is no EQUINOX either. EQUINOX
+        defaults to 1950 for FK4 and 2000 for FK5
+    */
+    let equinox = match header.get_value("EQUINOX") {
+      None => None,
+      Some(_) => Some(header.get_value_as::<f64>("EQUINOX")?),
+    };
+    let Some(name) = get_string(header, "RADESYS") else {
+      return Ok(match equinox {
+        None => SkyFrame::Icrs,
+        Some(equinox) if equinox < 1984.0 => SkyFrame::Fk4(equinox),
+        Some(equinox) => SkyFrame::Fk5(equinox),
+      });
+    };
+    Ok(match name.to_ascii_uppercase().as_str() {
+      "ICRS" => SkyFrame::Icrs,
+      "FK5" => SkyFrame::Fk5(equinox.unwrap_or(2000.0)),
+      "FK4" => SkyFrame::Fk4(equinox.unwrap_or(1950.0)),
+      "FK4-NO-E" => SkyFrame::Fk4NoE(equinox.unwrap_or(1950.0)),
+      "GAPPT" => SkyFrame::Gappt,
+      _ => Err(InvalidValueErr::new(&name, "reference frame (RADESYS)"))?,
+    })
+  }
+
+  pub fn write_to(&self, header: &mut Header) -> Result<(), Box<dyn Error + Send + Sync>> {
+    /*  Sets RADESYS and EQUINOX. EQUINOX is removed for frames without an
+        equinox, so an old value cannot contradict the new frame
+    */
+    let equinox = self.get_equinox();
+    if let Some(equinox) = equinox.filter(|equinox| !equinox.is_finite() || *equinox <= 0.0) {
+      return Err(InvalidValueErr::new(&equinox.to_string(), "equinox"))?;
+    }
+    set(
+      header,
+      "RADESYS",
+      MetaValue::String(self.get_name().to_string()),
+      "celestial reference frame",
+    );
+    match equinox {
+      Some(equinox) => set(header, "EQUINOX", MetaValue::Float(equinox), "[yr] equinox of frame"),
+      None => {
+        header.remove_record("EQUINOX");
+      }
+    }
+    Ok(())
+  }
+}
+
+/*
+    The pointing of an observation is written as a number of degrees by some
+    instruments (RA = 150.25) and as a sexagesimal string by others (OBJCTRA =
+    '10 01 00.0'). Sexagesimal right ascensions are in hours, declinations in
+    degrees. These funcs read both forms and always return degrees.
+*/
+
+pub(crate) fn get_angle(
+  header: &Header,
+  keyword: &str,
+  unit: SexagesimalUnit,
+) -> Result<f64, Box<dyn Error + Send + Sync>> {
+  let raw = header.get_value(keyword).ok_or_else(|| MissingRecordError::new(keyword))?;
+  //Numbers are degrees, also when they were written as a string
+  let text = unquote(raw).unwrap_or(raw.clone());
+  if let Some(degrees) = parse_float(&text) {
+    return Ok(degrees);
+  }
+  let sexagesimal: Sexagesimal =
+    text.parse().map_err(|_| InvalidValueErr::new(raw, "angle in degrees or sexagesimal"))?;
+  Ok(sexagesimal.to_decimal() * unit.degrees_per_unit())
+}
+
+fn get_pointing(
+  header: &Header,
+  keywords: &[&str],
+  unit: SexagesimalUnit,
+) -> Result<(f64, String), Box<dyn Error + Send + Sync>> {
+  //The first of the keywords that is present, with the keyword itself
+  match keywords.iter().find(|keyword| header.get_value(keyword).is_some()) {
+    Some(keyword) => Ok((get_angle(header, keyword, unit)?, keyword.to_string())),
+    None => Err(Box::new(MissingRecordError::new(keywords[0]))),
+  }
+}
+
+pub(crate) fn get_ra(header: &Header) -> Result<f64, Box<dyn Error + Send + Sync>> {
+  //Right ascension in [0, 360)
+  let (ra, _) = get_pointing(header, &RA_KEYWORDS, SexagesimalUnit::Hours)?;
+  Ok(ra.rem_euclid(360.0))
+}
+
+pub(crate) fn get_dec(header: &Header) -> Result<f64, Box<dyn Error + Send + Sync>> {
+  //Declination in [-90, 90]
+  let (dec, keyword) = get_pointing(header, &DEC_KEYWORDS, SexagesimalUnit::Degrees)?;
+  match (-90.0..=90.0).contains(&dec) {
+    true => Ok(dec),
+    false => {
+      Err(Box::new(InvalidValueErr::new(header.get_value(&keyword).unwrap(), "declination")))
+    }
+  }
+}
@@ -0,0 +1,65 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    FITS and ndarray number pixels differently. In FITS (and therefore in the
+    WCS keywords, such as CRPIXn) the first pixel is pixel 1 and its center
+    lies at coordinate 1.0, so it covers [0.5, 1.5). Arrays are indexed from
+    0. Getting this wrong by one (or by half) a pixel is very easy, so all
+    conversions between the two conventions go through these funcs:
+
+        fits_to_index(1.0) == 0.0       index_to_fits(0.0) == 1.0
+        containing_index(1.49, n) == Some(0)
+        containing_index(1.5, n) == Some(1)
+        containing_index(0.4, n) == None
+
+    Fractional coordinates are allowed in both conventions, fits_to_index and
+    index_to_fits simply shift them by one.
+*/
+
+pub fn fits_to_index(pixel: f64) -> f64 {
+  pixel - 1.0
+}
+
+pub fn index_to_fits(index: f64) -> f64 {
+  index + 1.0
+}
+
+pub fn containing_index(pixel: f64, len: usize) -> Option<usize> {
+  //Index of the array element that covers the FITS coordinate along an axis
+  //of length len, if any. Pixels cover [center - 0.5, center + 0.5)
+  let index = (fits_to_index(pixel) + 0.5).floor();
+  match index >= 0.0 && index < len as f64 {
+    true => Some(index as usize),
+    false => None,
+  }
+}
+
+pub fn containing_indices(pixel: &[f64], shape: &[usize]) -> Option<Vec<usize>> {
+  //Same as containing_index, for all axes of an image at once
+  if pixel.len() != shape.len() {
+    return None;
+  }
+  pixel.iter().zip(shape).map(|(&px, &len)| containing_index(px, len)).collect()
+}
+
+pub fn mirror_fits(pixel: f64, len: usize) -> f64 {
+  //FITS coordinate of the same point after the axis was flipped
+  index_to_fits(len as f64 - 1.0 - fits_to_index(pixel))
+}
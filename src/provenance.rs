@@ -0,0 +1,240 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{
+  collections::HashMap,
+  error::Error,
+  fmt::{self, Display, Formatter},
+  path::{Path, PathBuf},
+};
+
+use crate::{
+  fits_index::FitsIndex,
+  header::Header,
+  header_data_unit::HeaderDataUnit,
+  keyword_value::{quote, unquote},
+  section::{ExtendedPath, HduSelector},
+};
+
+/*
+    Provenance (lineage) of HDU's. The inputs an HDU was made from are recorded
+    as a series of PROVn keywords (n = 1, 2, ...), each holding the extended
+    file name of one input (see ExtendedPath), for example:
+
+        PROV1   = 'bias/master_bias.fits[0]'
+        PROV2   = 'raw/sci_0017.fits[SCI,1]'
+
+    An input without HDU selector stands for all HDU's of the file. Relative
+    paths are relative to the directory of the file that records them, so a
+    tree of reduced files can be moved around as a whole.
+
+    ProvenanceGraph::trace follows the PROVn records from file to file and
+    builds the lineage graph. Only headers are read. Inputs that cannot be
+    found (raw data that was archived elsewhere, for example) end up in the
+    graph as nodes that were not found, rather than as errors.
+*/
+
+//Keyword prefix of the provenance records
+const PROV_KEYWORD: &str = "PROV";
+
+pub(crate) fn get_provenance(
+  header: &Header,
+) -> Result<Vec<ExtendedPath>, Box<dyn Error + Send + Sync>> {
+  //Inputs recorded in the header, in order. The series ends at the first gap
+  let mut inputs = Vec::new();
+  for n in 1.. {
+    let Some(raw) = header.get_value(&format!("{PROV_KEYWORD}{n}")) else {
+      break;
+    };
+    inputs.push(ExtendedPath::parse(&unquote(raw).unwrap_or(raw.clone()))?);
+  }
+  Ok(inputs)
+}
+
+pub(crate) fn add_provenance(
+  header: &mut Header,
+  inputs: &[&str],
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  //(1) Inputs have to be valid extended file names, recorded ones are skipped
+  let mut recorded = get_provenance(header)?;
+  let mut new_inputs = Vec::new();
+  for input in inputs {
+    let parsed = ExtendedPath::parse(input)?;
+    if !recorded.contains(&parsed) {
+      recorded.push(parsed);
+      new_inputs.push(input.trim());
+    }
+  }
+
+  //(2) The new records continue the series
+  let first = recorded.len() - new_inputs.len() + 1;
+  for (n, input) in (first..).zip(new_inputs) {
+    header.put_record(&format!("{PROV_KEYWORD}{n}"), quote(input), None);
+  }
+  Ok(())
+}
+
+impl HeaderDataUnit {
+  /*
+      Inputs the HDU was made from, recorded as a series of PROVn keywords
+      holding extended file names.
+  */
+  pub fn add_provenance(&mut self, inputs: &[&str]) -> Result<(), Box<dyn Error + Send + Sync>> {
+    add_provenance(self.get_header_mut(), inputs)
+  }
+
+  pub fn get_provenance(&self) -> Result<Vec<ExtendedPath>, Box<dyn Error + Send + Sync>> {
+    get_provenance(self.get_header())
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProvenanceNode {
+  /*  THIS STRUCT IS PART OF THE USER-FACING API
+      An HDU (or with hdu None, all HDU's of a file) in the lineage graph.
+      Paths are resolved relative to the file that recorded them.
+  */
+  path: PathBuf,
+  hdu: Option<HduSelector>,
+  found: bool,
+}
+
+impl ProvenanceNode {
+  pub fn get_path(&self) -> &Path {
+    &self.path
+  }
+  pub fn get_hdu(&self) -> Option<&HduSelector> {
+    self.hdu.as_ref()
+  }
+  pub fn is_found(&self) -> bool {
+    //False if the file (or the HDU in it) could not be read
+    self.found
+  }
+}
+
+impl Display for ProvenanceNode {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.path.display())?;
+    match &self.hdu {
+      None => Ok(()),
+      Some(hdu) => write!(f, "[{hdu}]"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProvenanceGraph {
+  //Edges point from an HDU to one of its inputs. Node 0 is the traced HDU
+  nodes: Vec<ProvenanceNode>,
+  edges: Vec<(usize, usize)>,
+}
+
+impl ProvenanceGraph {
+  pub fn trace(spec: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    /*  Lineage of the HDU given as an extended file name. Without HDU selector,
+        the provenance of all HDU's of the file is traced.
+    */
+    let spec = ExtendedPath::parse(spec)?;
+    let mut graph = ProvenanceGraph::default();
+    let mut seen = HashMap::new();
+    graph.visit(spec.get_path().to_path_buf(), spec.get_hdu().cloned(), &mut seen)?;
+    Ok(graph)
+  }
+
+  fn visit(
+    &mut self,
+    path: PathBuf,
+    hdu: Option<HduSelector>,
+    seen: &mut HashMap<(PathBuf, Option<HduSelector>), usize>,
+  ) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    //(1) Every HDU is visited once, which also ends cycles
+    let path = path.canonicalize().unwrap_or(path);
+    let key = (path.clone(), hdu.clone());
+    if let Some(&index) = seen.get(&key) {
+      return Ok(index);
+    }
+    let headers = Self::read_headers(&path, hdu.as_ref());
+    let index = self.nodes.len();
+    self.nodes.push(ProvenanceNode { path: path.clone(), hdu, found: headers.is_some() });
+    seen.insert(key, index);
+
+    //(2) Inputs of the HDU(s), relative to the directory of this file
+    let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let mut inputs: Vec<ExtendedPath> = Vec::new();
+    for header in headers.unwrap_or_default() {
+      for input in get_provenance(&header)? {
+        if !inputs.contains(&input) {
+          inputs.push(input);
+        }
+      }
+    }
+    for input in inputs {
+      let input_index = self.visit(dir.join(input.get_path()), input.get_hdu().cloned(), seen)?;
+      self.edges.push((index, input_index));
+    }
+    Ok(index)
+  }
+
+  fn read_headers(path: &Path, hdu: Option<&HduSelector>) -> Option<Vec<Header>> {
+    //Headers of the selected HDU(s), None if they cannot be read
+    let index = FitsIndex::open(path).ok()?;
+    let headers = (0..index.get_num_hdus())
+      .map(|i| index.read_header(i).ok().flatten().map(|header| (i, header)))
+      .collect::<Option<Vec<(usize, Header)>>>()?;
+    let selected: Vec<Header> = headers
+      .into_iter()
+      .filter(|(i, header)| match hdu {
+        None => true,
+        Some(HduSelector::Index(index)) => index == i,
+        Some(HduSelector::Name { extname, extver }) => {
+          let name = header.get_value("EXTNAME").and_then(|raw| unquote(raw));
+          name.is_some_and(|name| name.trim().eq_ignore_ascii_case(extname))
+            && extver
+              .is_none_or(|extver| header.get_value_as::<i64>("EXTVER").unwrap_or(1) == extver)
+        }
+      })
+      .map(|(_, header)| header)
+      .collect();
+    match selected.is_empty() {
+      true => None,
+      false => Some(selected),
+    }
+  }
+
+  pub fn get_nodes(&self) -> &[ProvenanceNode] {
+    &self.nodes
+  }
+  pub fn get_edges(&self) -> &[(usize, usize)] {
+    &self.edges
+  }
+  pub fn get_inputs(&self, node: usize) -> Vec<usize> {
+    self.edges.iter().filter(|edge| edge.0 == node).map(|edge| edge.1).collect()
+  }
+  pub fn get_outputs(&self, node: usize) -> Vec<usize> {
+    //HDU's that node is an input of, in the order of the nodes
+    let mut outputs: Vec<usize> =
+      self.edges.iter().filter(|edge| edge.1 == node).map(|edge| edge.0).collect();
+    outputs.sort_unstable();
+    outputs
+  }
+  pub fn get_sources(&self) -> Vec<usize> {
+    //Nodes without recorded inputs (the raw data, or inputs that were not found)
+    (0..self.nodes.len()).filter(|&node| self.edges.iter().all(|edge| edge.0 != node)).collect()
+  }
+}
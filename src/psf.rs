@@ -0,0 +1,210 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::error::Error;
+
+use ndarray::{Array2, Axis, Ix2, Ix3};
+
+use crate::{
+  extensions::Extension,
+  hdu_err::{InvalidRecordValueError, MissingDataErr},
+  header::Header,
+  header_data_unit::HeaderDataUnit,
+  img_err::PsfGridErr,
+  keyword_value::unquote,
+};
+
+/*
+    Point spread functions stored in image HDU's, in one of three forms:
+
+    - a single 2-D image: the PSF is the same everywhere.
+    - a cube of PSF's on a grid of positions, with the position of plane i
+      (counting from 0) in DET_YX{i} = '(y, x)' as written by WebbPSF and
+      read by photutils. The PSF between the grid positions is interpolated
+      bilinearly, and outside the grid the nearest edge is used.
+    - a cube holding the polynomial components of a PSFEx model, described
+      by the POLZERO, POLSCAL and POLDEG1 keywords. The PSF at (x, y) is the
+      sum of the components times the terms 1, dx, dx², ..., dy, dx dy, ...
+      with dx = (x - POLZERO1) / POLSCAL1 and dy likewise. PSFEx itself
+      writes these components to a binary table (a single 1-row array
+      column), which is not supported here yet; this form is for the same
+      model in an image cube.
+
+    Positions are in the pixel convention of the file (0-based for WebbPSF
+    grids, 1-based for PSFEx). The PSF is returned as it is stored, without
+    normalizing it again after interpolation.
+*/
+
+const GRID_KEYWORD: &str = "DET_YX";
+
+#[derive(Debug, Clone)]
+enum PsfModel {
+  Constant(Array2<f64>),
+  Grid { xs: Vec<f64>, ys: Vec<f64>, planes: Vec<Array2<f64>> }, //plane of (ix, iy) at ix + nx * iy
+  Polynomial { zero: [f64; 2], scale: [f64; 2], degree: usize, terms: Vec<Array2<f64>> },
+}
+
+#[derive(Debug, Clone)]
+pub struct Psf {
+  model: PsfModel,
+  shape: [usize; 2],
+}
+
+fn grid_position(
+  header: &Header,
+  plane: usize,
+) -> Option<Result<(f64, f64), Box<dyn Error + Send + Sync>>> {
+  //Positions are written as '(y, x)'
+  let keyword = format!("{GRID_KEYWORD}{plane}");
+  let raw = header.get_value(&keyword)?;
+  let invalid = || -> Box<dyn Error + Send + Sync> {
+    Box::new(InvalidRecordValueError::new(&keyword, raw, &["'(y, x)'"]))
+  };
+  let text = unquote(raw).unwrap_or(raw.to_string());
+  let inner = text.trim().trim_start_matches('(').trim_end_matches(')');
+  let parsed = match inner.split_once(',') {
+    Some((y, x)) => x.trim().parse::<f64>().ok().zip(y.trim().parse::<f64>().ok()),
+    None => None,
+  };
+  Some(parsed.ok_or_else(invalid))
+}
+
+fn unique_sorted(values: impl Iterator<Item = f64>) -> Vec<f64> {
+  let mut values: Vec<f64> = values.collect();
+  values.sort_by(f64::total_cmp);
+  values.dedup();
+  values
+}
+
+fn bracket(grid: &[f64], value: f64) -> (usize, usize, f64) {
+  //Grid points around the value and the weight of the second one. Values
+  //outside the grid use the nearest edge
+  if grid.len() == 1 || value <= grid[0] {
+    return (0, 0, 0.0);
+  }
+  let last = grid.len() - 1;
+  if value >= grid[last] {
+    return (last, last, 0.0);
+  }
+  let hi = grid.partition_point(|&point| point <= value);
+  (hi - 1, hi, (value - grid[hi - 1]) / (grid[hi] - grid[hi - 1]))
+}
+
+impl Psf {
+  pub fn from_hdu(hdu: &HeaderDataUnit) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    //(1) The PSF is an image (or a cube of them)
+    let Some(Extension::Image(img)) = hdu.get_data() else {
+      return Err(Box::new(MissingDataErr::new("an image")));
+    };
+    let data = img.to_f64_array();
+    let header = hdu.get_header();
+    if data.ndim() == 2 {
+      let psf = data.into_dimensionality::<Ix2>()?;
+      return Ok(Psf { shape: [psf.nrows(), psf.ncols()], model: PsfModel::Constant(psf) });
+    }
+    if data.ndim() != 3 {
+      Err(InvalidRecordValueError::new("NAXIS", &data.ndim().to_string(), &["2", "3"]))?;
+    }
+    let cube = data.into_dimensionality::<Ix3>()?;
+    let shape = [cube.shape()[0], cube.shape()[1]];
+    let planes: Vec<Array2<f64>> = cube.axis_iter(Axis(2)).map(|plane| plane.to_owned()).collect();
+
+    //(2) Polynomial (PSFEx) models
+    if header.get_record("POLDEG1").is_some() {
+      let degree: usize = header.get_value_as("POLDEG1")?;
+      let n_terms = (degree + 1) * (degree + 2) / 2;
+      if n_terms != planes.len() {
+        let allowed = &["a degree d with (d + 1)(d + 2) / 2 = NAXIS3"];
+        Err(InvalidRecordValueError::new("POLDEG1", &degree.to_string(), allowed))?;
+      }
+      let zero = [header.get_value_as("POLZERO1")?, header.get_value_as("POLZERO2")?];
+      let scale = [header.get_value_as("POLSCAL1")?, header.get_value_as("POLSCAL2")?];
+      return Ok(Psf { shape, model: PsfModel::Polynomial { zero, scale, degree, terms: planes } });
+    }
+
+    //(3) Gridded models: every plane needs a position on a complete grid
+    let positions = (0..planes.len())
+      .map_while(|plane| grid_position(header, plane))
+      .collect::<Result<Vec<(f64, f64)>, Box<dyn Error + Send + Sync>>>()?;
+    let xs = unique_sorted(positions.iter().map(|&(x, _)| x));
+    let ys = unique_sorted(positions.iter().map(|&(_, y)| y));
+    if positions.len() != planes.len() || xs.len() * ys.len() != planes.len() {
+      return Err(Box::new(PsfGridErr::new(planes.len(), positions.len(), (xs.len(), ys.len()))));
+    }
+    let mut grid = vec![None; planes.len()];
+    for (plane, &(x, y)) in planes.into_iter().zip(&positions) {
+      let ix = xs.partition_point(|&grid_x| grid_x < x);
+      let iy = ys.partition_point(|&grid_y| grid_y < y);
+      grid[ix + xs.len() * iy] = Some(plane);
+    }
+    let planes: Option<Vec<Array2<f64>>> = grid.into_iter().collect();
+    let Some(planes) = planes else {
+      //Some position appears twice, so another one is missing
+      return Err(Box::new(PsfGridErr::new(
+        positions.len(),
+        positions.len(),
+        (xs.len(), ys.len()),
+      )));
+    };
+    Ok(Psf { shape, model: PsfModel::Grid { xs, ys, planes } })
+  }
+
+  pub fn evaluate_at(&self, x: f64, y: f64) -> Array2<f64> {
+    match &self.model {
+      PsfModel::Constant(psf) => psf.clone(),
+      PsfModel::Grid { xs, ys, planes } => {
+        //Bilinear interpolation between the four surrounding PSF's
+        let ((x0, x1, wx), (y0, y1, wy)) = (bracket(xs, x), bracket(ys, y));
+        let plane = |ix: usize, iy: usize| &planes[ix + xs.len() * iy];
+        plane(x0, y0) * ((1.0 - wx) * (1.0 - wy))
+          + plane(x1, y0) * (wx * (1.0 - wy))
+          + plane(x0, y1) * ((1.0 - wx) * wy)
+          + plane(x1, y1) * (wx * wy)
+      }
+      PsfModel::Polynomial { zero, scale, degree, terms } => {
+        //Terms in the order of PSFEx: x^i y^j for j = 0..=degree, i = 0..=degree - j
+        let (dx, dy) = ((x - zero[0]) / scale[0], (y - zero[1]) / scale[1]);
+        let mut psf = Array2::zeros((self.shape[0], self.shape[1]));
+        let mut term = terms.iter();
+        for j in 0..=*degree {
+          for i in 0..=(*degree - j) {
+            psf.scaled_add(dx.powi(i as i32) * dy.powi(j as i32), term.next().unwrap());
+          }
+        }
+        psf
+      }
+    }
+  }
+
+  pub fn get_shape(&self) -> [usize; 2] {
+    self.shape
+  }
+
+  pub fn is_constant(&self) -> bool {
+    matches!(self.model, PsfModel::Constant(_))
+  }
+
+  pub fn get_grid(&self) -> Option<(&[f64], &[f64])> {
+    //The x and y positions of gridded PSF's
+    match &self.model {
+      PsfModel::Grid { xs, ys, .. } => Some((xs, ys)),
+      _ => None,
+    }
+  }
+}
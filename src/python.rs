@@ -0,0 +1,176 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Python bindings (enabled with the python feature). The extension module
+    is built with maturin (see pyproject.toml), which also enables the
+    extension-module feature of pyo3:
+
+      import rustronomy_fits
+      fits = rustronomy_fits.Fits.read("image.fits")
+      hdu = fits[0]
+      hdu.header["NAXIS1"], hdu.kind, hdu.data()
+      fits.write("copy.fits")
+
+    Files are parsed by the same Fits::open and written by the same Fits::write
+    as the Rust crate. An Hdu keeps
+    its Fits alive, so HDUs can outlive the variable holding the file. Images
+    are copied into numpy arrays with their axes reversed, so that the last
+    numpy axis is NAXIS1 (the same convention as astropy).
+*/
+
+use std::{error::Error, path::PathBuf};
+
+use numpy::{PyArrayMethods, PyUntypedArray, ToPyArray};
+use pyo3::{
+  exceptions::{PyIOError, PyIndexError, PyKeyError, PyTypeError},
+  prelude::*,
+  types::PyDict,
+};
+
+use crate::{extensions::Extension, fits::Fits, header::Header, keyword_value::unquote};
+
+fn to_value(value: &str) -> String {
+  //String values are handed out without their quotes
+  unquote(value).unwrap_or_else(|| value.trim().to_string())
+}
+
+fn io_err(err: Box<dyn Error + Send + Sync>) -> PyErr {
+  PyIOError::new_err(err.to_string())
+}
+
+#[pyclass(name = "Fits", module = "rustronomy_fits", unsendable)]
+pub struct PyFits {
+  fits: Fits,
+}
+
+#[pymethods]
+impl PyFits {
+  #[staticmethod]
+  fn read(path: PathBuf) -> PyResult<Self> {
+    Ok(PyFits { fits: Fits::open(&path).map_err(io_err)? })
+  }
+
+  fn write(&self, path: PathBuf) -> PyResult<()> {
+    //Writing consumes a Fits, the python object keeps its own copy
+    self.fits.clone().write(&path).map_err(io_err)
+  }
+
+  fn __len__(&self) -> usize {
+    self.fits.get_num_hdus()
+  }
+
+  fn __getitem__(slf: Bound<'_, Self>, index: isize) -> PyResult<PyHdu> {
+    //Negative indices count from the end, as for any python sequence
+    let len = slf.borrow().fits.get_num_hdus() as isize;
+    let index = if index < 0 { index + len } else { index };
+    if !(0..len).contains(&index) {
+      return Err(PyIndexError::new_err(format!("HDU index out of range (file has {len} HDUs)")));
+    }
+    Ok(PyHdu { fits: slf.unbind(), index: index as usize })
+  }
+
+  fn __repr__(&self) -> String {
+    format!("{}", self.fits)
+  }
+}
+
+#[pyclass(name = "Hdu", module = "rustronomy_fits")]
+pub struct PyHdu {
+  fits: Py<PyFits>,
+  index: usize,
+}
+
+impl PyHdu {
+  fn with_hdu<R>(&self, py: Python<'_>, f: impl FnOnce(&Header, Option<&Extension>) -> R) -> R {
+    //__getitem__ checked the index, and HDUs cannot be removed from python
+    let fits = self.fits.borrow(py);
+    let hdu = fits.fits.get_hdu(self.index).unwrap();
+    f(hdu.get_header(), hdu.get_data())
+  }
+}
+
+#[pymethods]
+impl PyHdu {
+  #[getter]
+  fn index(&self) -> usize {
+    self.index
+  }
+
+  #[getter]
+  fn header<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+    //Keywords in header order, commentary keywords (without values) are left out
+    let dict = PyDict::new(py);
+    self.with_hdu(py, |header, _| {
+      for keyword in header.keywords() {
+        if let Some(value) = header.get_value(keyword) {
+          dict.set_item(keyword, to_value(value))?;
+        }
+      }
+      Ok::<_, PyErr>(())
+    })?;
+    Ok(dict)
+  }
+
+  fn get(&self, py: Python<'_>, keyword: &str) -> PyResult<String> {
+    self.with_hdu(py, |header, _| match header.get_value(keyword) {
+      Some(value) => Ok(to_value(value)),
+      None => Err(PyKeyError::new_err(keyword.to_string())),
+    })
+  }
+
+  #[getter]
+  fn kind(&self, py: Python<'_>) -> Option<&'static str> {
+    self.with_hdu(py, |_, data| match data? {
+      Extension::Corrupted => Some("corrupted"),
+      Extension::Image(_) => Some("image"),
+      Extension::AsciiTable(_) => Some("ascii_table"),
+      Extension::BinTable(_) => Some("bintable"),
+    })
+  }
+
+  fn data<'py>(&self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyUntypedArray>>> {
+    /*  Copies the image into a numpy array of the matching dtype. HDUs without
+        a data unit give None. Tables have no numpy conversion (yet).
+    */
+    self.with_hdu(py, |_, data| match data {
+      None => Ok(None),
+      Some(Extension::Image(img)) => Ok(Some(crate::impl_typed_image_dispatch!(img, img => {
+        img.get_data().t().to_pyarray(py).as_untyped().clone()
+      }))),
+      Some(_) => Err(PyTypeError::new_err(format!("HDU {} is not an image", self.index))),
+    })
+  }
+
+  fn __repr__(&self, py: Python<'_>) -> String {
+    format!("{}", self.fits.borrow(py).fits.get_hdu(self.index).unwrap())
+  }
+}
+
+pub fn register_python_module(module: &Bound<'_, PyModule>) -> PyResult<()> {
+  //Adds the classes to a module. Only needed when embedding python in Rust
+  module.add_class::<PyFits>()?;
+  module.add_class::<PyHdu>()?;
+  Ok(())
+}
+
+#[pymodule]
+fn rustronomy_fits(module: &Bound<'_, PyModule>) -> PyResult<()> {
+  register_python_module(module)
+}
@@ -18,9 +18,11 @@
 */
 
 //Module structure
+pub(crate) mod block_io;
 pub(crate) mod header_block;
 pub(crate) mod keyword_record;
 pub(crate) mod raw_io;
+pub(crate) mod stream_io;
 pub(crate) mod table_entry_format;
 
 pub(crate) trait BlockSized {
@@ -0,0 +1,88 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::error::Error;
+
+use super::raw_io::{RawFitsReader, RawFitsWriter};
+
+/*
+    BlockRead and BlockWrite abstract the block-level access to a FITS file
+    that the parser layer needs. RawFitsReader and RawFitsWriter implement them
+    for regular files, but other backends (memory mapped files, remote files,
+    in-memory buffers etc.) can be plugged in as well. See Fits::read_from and
+    Fits::write_to.
+
+    Buffers are always integer multiples of the FITS block size (2880 bytes).
+*/
+
+pub trait BlockRead {
+  /*  THIS TRAIT IS PART OF THE USER-FACING API
+      read_blocks fills the whole buffer with the next buffer.len() / 2880
+      blocks and returns the number of blocks read. Reading past the end of the
+      file is an error. skip_blocks moves forward without reading the blocks.
+  */
+  fn read_blocks(&mut self, buffer: &mut [u8]) -> Result<usize, Box<dyn Error + Send + Sync>>;
+  fn skip_blocks(&mut self, n_blocks: usize) -> Result<usize, Box<dyn Error + Send + Sync>>;
+
+  //Total number of blocks, and the index of the next block to be read
+  fn get_block_len(&self) -> usize;
+  fn get_block_index(&self) -> usize;
+
+  fn get_bytes_left(&self) -> u64 {
+    //Backends that accept truncated files should report the actual bytes left.
+    //Files can be larger than usize::MAX bytes on 32 bit platforms
+    (self.get_block_len() - self.get_block_index()) as u64 * crate::BLOCK_SIZE as u64
+  }
+}
+
+pub trait BlockWrite {
+  /*  THIS TRAIT IS PART OF THE USER-FACING API
+      write_blocks writes whole blocks and returns the number of blocks
+      written. flush is called once everything has been written.
+  */
+  fn write_blocks(&mut self, buffer: &[u8]) -> Result<usize, Box<dyn Error + Send + Sync>>;
+  fn flush(&mut self) -> Result<(), Box<dyn Error + Send + Sync>>;
+}
+
+impl BlockRead for RawFitsReader {
+  fn read_blocks(&mut self, buffer: &mut [u8]) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    RawFitsReader::read_blocks(self, buffer)
+  }
+  fn skip_blocks(&mut self, n_blocks: usize) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    RawFitsReader::skip_blocks(self, n_blocks)
+  }
+  fn get_block_len(&self) -> usize {
+    RawFitsReader::get_block_len(self)
+  }
+  fn get_block_index(&self) -> usize {
+    RawFitsReader::get_block_index(self)
+  }
+  fn get_bytes_left(&self) -> u64 {
+    RawFitsReader::get_bytes_left(self)
+  }
+}
+
+impl BlockWrite for RawFitsWriter {
+  fn write_blocks(&mut self, buffer: &[u8]) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    RawFitsWriter::write_blocks(self, buffer)
+  }
+  fn flush(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+    Ok(RawFitsWriter::flush(self)?)
+  }
+}
@@ -52,7 +52,7 @@ impl HeaderBlock {
       //Decode
       let record = KeywordRecord::decode_from_bytes(&bytes[(i * 80)..(i * 80 + 80)])?;
       //And parse
-      if *record.keyword == String::from("END") {
+      if *record.keyword == *"END" {
         //This is the END keyword, which we DON'T append!
         // -> but we should set is_final to true
         is_final = true;
@@ -63,17 +63,22 @@ impl HeaderBlock {
       records.push(record);
     }
 
-    return Ok((HeaderBlock { records: records }, is_final));
+    Ok((HeaderBlock { records }, is_final))
   }
 
-  pub(crate) fn encode_fill_buff(self, buf: &mut Vec<u8>) -> Result<(), Box<dyn Error>> {
+  #[allow(dead_code)]
+  pub(crate) fn encode_fill_buff(
+    self,
+    buf: &mut Vec<u8>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
     for record in self.records {
       record.encode_fill_buff(buf)?;
     }
     Ok(())
   }
 
-  pub(crate) fn encode_to_bytes(self) -> Result<Vec<u8>, Box<dyn Error>> {
+  #[allow(dead_code)]
+  pub(crate) fn encode_to_bytes(self) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
     //Fill buf with data
     let mut buf: Vec<u8> = Vec::new();
     self.encode_fill_buff(&mut buf)?;
@@ -82,11 +87,11 @@ impl HeaderBlock {
     //bytes with zeros to satisfy this condition
     if buf.len() < 2880 {
       buf.append(&mut vec![0u8; 2880 - buf.len()]);
-      return Ok(buf);
+      Ok(buf)
     } else if buf.len() > 2880 {
-      return Err(Box::new(HBBErr::new(header_err::BUFFER_LEN)));
+      Err(Box::new(HBBErr::new(header_err::BUFFER_LEN)))
     } else {
-      return Ok(buf);
+      Ok(buf)
     }
   }
 }
@@ -20,13 +20,19 @@
 use std::{
   error::Error,
   fmt::{self, Display},
-  rc::Rc,
   str,
+  sync::Arc,
 };
 
-use crate::keyword_err::{self, KeywordRecordBufferErr as KRBufErr, ProtectedKeywordErr as PKWErr};
+use crate::{
+  keyword_err::{self, KeywordRecordBufferErr as KRBufErr, ProtectedKeywordErr as PKWErr},
+  keyword_value::unquote,
+};
 use rustronomy_core::data_type_traits::io_utils::Encode;
 
+//Keyword of records that use the ESO HIERARCH convention
+pub(crate) const HIERARCH: &str = "HIERARCH";
+
 #[derive(Debug, Clone)]
 pub struct KeywordRecord {
   /*  THIS STRUCT IS PART OF THE USER-FACING API
@@ -35,7 +41,7 @@ pub struct KeywordRecord {
       contained in the data section of the HDU. Those restricted keywords
       should always be updated in unison with the data they describe.
   */
-  pub(crate) keyword: Rc<String>,
+  pub(crate) keyword: Arc<String>,
   pub(crate) value: Option<String>,
   pub(crate) comment: Option<String>,
 }
@@ -95,12 +101,20 @@ impl KeywordRecord {
     "EPOCH",
   ];
 
+  //Keywords that describe the layout of the data unit. Their values follow
+  //from the data itself (NAXISn type keywords are matched separately)
+  pub const STRUCTURAL_KEYWORDS: [&'static str; 6] =
+    ["SIMPLE", "XTENSION", "BITPIX", "NAXIS", "PCOUNT", "GCOUNT"];
+
+  //Keywords that may appear more than once and only contain free text
+  pub const COMMENTARY_KEYWORDS: [&'static str; 2] = ["COMMENT", "HISTORY"];
+
   /*
       THE FOLLOWING FUNCS ARE PART OF THE PUBLIC API
   */
 
   pub fn empty() -> Self {
-    KeywordRecord { keyword: Rc::new(String::from("")), value: None, comment: None }
+    KeywordRecord { keyword: Arc::new(String::from("")), value: None, comment: None }
   }
 
   pub fn new(
@@ -115,15 +129,21 @@ impl KeywordRecord {
       }
     }
 
-    Ok(KeywordRecord { keyword: Rc::new(keyword.to_string()), value: value, comment: comment })
+    Ok(KeywordRecord { keyword: Arc::new(keyword.to_string()), value, comment })
   }
 
   /*
       THE FOLLOWING FUNCS ARE INTERNAL
   */
 
-  pub(crate) fn from_string(keyword: Rc<String>, value: String, comment: Option<String>) -> Self {
-    KeywordRecord { keyword: keyword, value: Some(value), comment: comment }
+  pub(crate) fn is_structural(keyword: &str) -> bool {
+    let is_axis = |rest: &str| !rest.is_empty() && rest.bytes().all(|b| b.is_ascii_digit());
+    Self::STRUCTURAL_KEYWORDS.contains(&keyword)
+      || keyword.strip_prefix("NAXIS").is_some_and(is_axis)
+  }
+
+  pub(crate) fn from_string(keyword: Arc<String>, value: String, comment: Option<String>) -> Self {
+    KeywordRecord { keyword, value: Some(value), comment }
   }
 
   //Helper function for decoding. Not part of API
@@ -138,50 +158,62 @@ impl KeywordRecord {
     let has_com: bool;
 
     //Decode into keyword and record
-    let keyword = String::from(str::from_utf8(&bytes[0..8])?.trim());
-    has_val = match str::from_utf8(&bytes[8..10])? {
-      "= " => true,
-      _ => false,
-    };
-    let record = String::from(str::from_utf8(&bytes[10..80])?.trim());
+    let mut keyword = String::from(str::from_utf8(&bytes[0..8])?.trim());
+    has_val = str::from_utf8(&bytes[8..10])? == "= ";
+    let mut record = String::from(str::from_utf8(&bytes[10..80])?.trim());
+
+    //HIERARCH records have a long keyword, which ends at the value indicator
+    if keyword == HIERARCH {
+      let text = str::from_utf8(&bytes[8..80])?;
+      if let Some((name, rest)) = text.split_once('=') {
+        keyword = Self::hierarch_keyword(name);
+        record = String::from(rest.trim());
+        has_val = true;
+      }
+    }
+
+    //CONTINUE records carry (part of) a value, without value indicator
+    if keyword == "CONTINUE" {
+      has_val = true;
+    }
 
     //Keyword and value should be valid ASCII
     if !keyword.is_ascii() || !record.is_ascii() {
       return Err(KRBufErr::new(keyword_err::ILLEGAL_CHAR));
     }
 
-    //Split record into value and comment
-    let (value, comment);
-    let split = record.split("/").collect::<Vec<_>>();
+    //Commentary records (and records with a blank keyword) have no value,
+    //everything after the keyword is text
+    if keyword.is_empty() || Self::COMMENTARY_KEYWORDS.contains(&keyword.as_str()) {
+      let text = String::from(str::from_utf8(&bytes[8..80])?.trim_end());
+      return Ok(KeywordRecord { keyword: Arc::new(keyword), value: None, comment: Some(text) });
+    }
 
-    match split.len() {
-      1 => {
+    //Split record into value and comment. The comment starts at the first
+    //slash that is not part of a string value
+    let (value, comment);
+    match Self::comment_start(&record) {
+      None => {
         //There was no comment in the record
-        value = String::from(split[0].trim());
+        value = String::from(record.trim());
         comment = String::from("");
         has_com = false;
       }
-      2 => {
+      Some(idx) => {
         //There was a comment in the record, there MAY have been a value
-        value = String::from(split[0].trim());
-        comment = String::from(split[1].trim());
+        value = String::from(record[..idx].trim());
+        comment = String::from(record[idx + 1..].trim());
 
         //Update value and comment flags
         has_com = true;
-        if value.len() == 0 {
+        if value.is_empty() {
           has_val = false;
         }
       }
-      _ => {
-        //This makes no sense, just set the value the entire string
-        value = record;
-        comment = String::from("");
-        has_com = false;
-      }
     }
 
     Ok(KeywordRecord {
-      keyword: Rc::new(keyword),
+      keyword: Arc::new(keyword),
       value: match has_val {
         false => None,
         true => Some(value),
@@ -193,85 +225,143 @@ impl KeywordRecord {
     })
   }
 
-  pub(crate) fn encode_fill_buff(self, buf: &mut Vec<u8>) -> Result<(), Box<dyn Error>> {
+  fn comment_start(record: &str) -> Option<usize> {
+    //Quotes inside strings are escaped as '', which just toggles twice
+    let mut in_string = false;
+    for (idx, ch) in record.char_indices() {
+      match ch {
+        '\'' => in_string = !in_string,
+        '/' if !in_string => return Some(idx),
+        _ => {}
+      }
+    }
+    None
+  }
+
+  fn split_long_string(text: &str) -> Vec<String> {
+    //Splits a string into pieces that fit in one record once escaped
+    const ROOM: usize = 67;
+    let mut chunks = vec![String::new()];
+    for ch in text.chars() {
+      let escaped = match ch {
+        '\'' => "''".to_string(),
+        ch => ch.to_string(),
+      };
+      let last = chunks.last_mut().unwrap();
+      if last.len() + escaped.len() > ROOM {
+        chunks.push(escaped);
+      } else {
+        last.push_str(&escaped);
+      }
+    }
+    chunks
+  }
+
+  pub(crate) fn needs_continuation(&self) -> bool {
+    //String values that do not fit in one record are split with CONTINUE
+    match &self.value {
+      Some(val) => self.keyword.len() <= 8 && val.starts_with('\'') && val.len() >= 70,
+      None => false,
+    }
+  }
+
+  pub(crate) fn encode_commentary(keyword: &str, text: &str, buf: &mut Vec<u8>) {
+    //Commentary records are the keyword followed by (at most) 72 chars of text
+    let mut record = format!("{keyword:<8}{text}").into_bytes();
+    record.resize(80, b' ');
+    buf.append(&mut record);
+  }
+
+  pub(crate) fn hierarch_keyword(name: &str) -> String {
+    //HIERARCH keywords are stored with single spaces between the levels
+    let levels: Vec<&str> = name.split_whitespace().collect();
+    format!("{HIERARCH} {}", levels.join(" "))
+  }
+
+  fn encode_hierarch(self, buf: &mut Vec<u8>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    //HIERARCH records look like: HIERARCH ESO DET GAIN = value / comment
+    let mut record = format!("{} = {}", self.keyword, self.value.unwrap_or_default());
+    if let Some(com) = self.comment {
+      record += &format!(" / {com}");
+    }
+
+    //HIERARCH values may not continue on the next record
+    if record.len() > 80 {
+      return Err(Box::new(KRBufErr::new(keyword_err::RECORD_LEN)));
+    }
+    let mut record = record.into_bytes();
+    record.resize(80, b' ');
+    buf.append(&mut record);
+    Ok(())
+  }
+
+  pub(crate) fn encode_fill_buff(
+    self,
+    buf: &mut Vec<u8>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    //Keywords that don't fit in 8 bytes are written as HIERARCH records
+    if self.keyword.len() > 8 {
+      return self.encode_hierarch(buf);
+    }
+
     //keep track of how long the last keyword is
     let mut one_rec_buf = Vec::new();
 
     //(1) Encode keyword and make sure it's 8 bytes long
     let keyword_len = self.keyword.len();
     self.keyword.fill_buf(&mut one_rec_buf);
-    for _ in 0..(8 - keyword_len) {
-      one_rec_buf.push(b' ');
-    }
+    one_rec_buf.resize(one_rec_buf.len() + (8 - keyword_len), b' ');
 
     //(2) Encode value
     match self.value {
       None => {} //do nothing
-      Some(mut val) => {
+      Some(val) => {
         //(2a) add the value indicator
         String::from("= ").fill_buf(&mut one_rec_buf);
 
-        //(2b) check if the value spans multiple keywordrecords
-        if val.len() < 70 {
+        //(2b) check if the value spans multiple keywordrecords. Short values
+        //that are not strings are right-justified to column 30 (fixed format)
+        if val.len() <= 20 && !val.starts_with('\'') {
+          format!("{val:>20}").fill_buf(&mut one_rec_buf);
+        } else if val.len() < 70 {
           val.fill_buf(&mut one_rec_buf);
         } else {
-          //Write first string to the record with the keyword
-          let mut remainder = val.split_off(68);
-          val += "&'";
-          val.truncate(70);
-          val.fill_buf(&mut one_rec_buf);
-
-          //Write to the header buffer
-          assert!(one_rec_buf.len() == 80);
-          buf.append(&mut one_rec_buf);
-
-          //Write the remaining part of the string to CONTINUE records
-          let mut continue_buf = Vec::new();
-          String::from("CONTINUE= '").fill_buf(&mut continue_buf);
-
-          while remainder.len() > 0 {
-            if continue_buf.len() == 78 {
-              continue_buf.push(b'&');
-              continue_buf.push(b"'"[0]);
-
-              //Write to the header buffer
-              assert!(continue_buf.len() == 80);
-              buf.append(&mut continue_buf);
+          //Long strings are split over CONTINUE records, ending with &
+          let chunks = match unquote(&val) {
+            Some(text) => Self::split_long_string(&text),
+            None => vec![val.chars().take(67).collect()],
+          };
+          for (idx, chunk) in chunks.iter().enumerate() {
+            if idx > 0 {
+              String::from("CONTINUE  ").fill_buf(&mut one_rec_buf);
             }
-            match remainder.pop() {
-              Some(ch) => continue_buf.push(ch as u8),
-              None => {} //Loop will break
+            if idx + 1 == chunks.len() {
+              format!("'{chunk}'").fill_buf(&mut one_rec_buf);
+            } else {
+              //Chunks may be a byte short when an escaped quote did not fit
+              format!("'{chunk}&'").fill_buf(&mut one_rec_buf);
+              one_rec_buf.resize(80, b' ');
+              buf.append(&mut one_rec_buf);
             }
           }
-
-          //Last keyword record may not have full length value
-          for _ in 0..(80 - continue_buf.len()) {
-            continue_buf.push(b' ');
-          }
-
-          //Write to the header buffer
-          assert!(continue_buf.len() == 80);
-          buf.append(&mut continue_buf);
-
-          //we're done
-          return Ok(());
+          //the comment goes in the last CONTINUE record
         }
       }
     }
 
-    //(3) Encode comment
+    //(3) Encode comment, cutting it off if it does not fit in the record
     match self.comment {
       None => {} //do nothing
-      Some(com) => {
-        String::from("/").fill_buf(&mut one_rec_buf);
-        com.fill_buf(&mut one_rec_buf);
+      Some(com) if one_rec_buf.len() + 3 < 80 => {
+        let room = 80 - 3 - one_rec_buf.len();
+        String::from(" / ").fill_buf(&mut one_rec_buf);
+        com.chars().take(room).collect::<String>().fill_buf(&mut one_rec_buf);
       }
+      Some(_) => {} //no room at all
     }
 
     //(4) Make sure the keywordrecord is 80 bytes long
-    for _ in 0..(80 - one_rec_buf.len()) {
-      one_rec_buf.push(b' ');
-    }
+    one_rec_buf.resize(80, b' ');
 
     //write to the header buffer
     assert!(one_rec_buf.len() == 80);
@@ -19,67 +19,117 @@
 
 use std::{
   error::Error,
-  fs::{File, Metadata},
-  io::{self, Read, Write},
+  fs::{File, Metadata, OpenOptions},
+  io::{BufWriter, Read, Seek, SeekFrom, Write},
   path::Path,
+  sync::Arc,
 };
 
-use crate::io_err::{self, InvalidFitsFileErr};
+use fs2::FileExt;
+
+use crate::{
+  io_err::{self, FileLockedErr, FitsIoErr, InvalidFitsFileErr},
+  write_options::WriteOptions,
+};
 
 //Get block size from root
 const BLOCK_SIZE: usize = crate::BLOCK_SIZE;
 
+//Zeroed regions shorter than this are written rather than skipped in sparse
+//mode (see WriteOptions::sparse)
+const MIN_SPARSE_BLOCKS: usize = 16; // = 46kB
+
+//Size of the chunks in which the tail of a file is moved by cut_range
+const CUT_CHUNK_SIZE: usize = 256 * BLOCK_SIZE; // = 737kB
+
 /*
     RawFitsReader and RawFitsWriter are fields of the Fits struct which is part
     of the public API. Therefore, the structs must be public themselves, even
     though none of their methods are public.
 
-    NOTE: the file_meta field for file metadata *is* publicly accesible!
+    NOTE: the file_meta field of the writer *is* publicly accesible!
 */
 
 #[derive(Debug)]
 pub struct RawFitsReader {
-  pub file_meta: Metadata,
   block_index: usize,
   n_fits_blocks: usize,
+  n_bytes: u64,
   reader_handle: File,
+  path: Arc<Path>,
 }
 
 impl RawFitsReader {
-  pub(crate) fn new(path: &Path) -> Result<Self, Box<dyn Error>> {
+  pub(crate) fn new(path: &Path) -> Result<Self, Box<dyn Error + Send + Sync>> {
     //(1) Open the file
-    let f = File::open(path)?;
+    let f = File::open(path).map_err(|err| FitsIoErr::new(path, "open file", err))?;
 
     //(2) Get metadata -> number of fits blocks
-    let meta = f.metadata()?;
+    let meta = f.metadata().map_err(|err| FitsIoErr::new(path, "read file metadata", err))?;
 
-    if meta.len() as usize % BLOCK_SIZE != 0 {
+    if meta.len() % BLOCK_SIZE as u64 != 0 {
       //Throw an error for files that are not integer multiples of 2880
       return Err(Box::new(InvalidFitsFileErr::new(io_err::FILE_BLOCK_DIV)));
     }
-    let n_blocks = meta.len() as usize / BLOCK_SIZE;
+    let n_bytes = meta.len();
 
     //Return file as raw FITS
-    Ok(RawFitsReader { file_meta: meta, block_index: 0, n_fits_blocks: n_blocks, reader_handle: f })
+    Ok(RawFitsReader {
+      block_index: 0,
+      n_fits_blocks: block_count(n_bytes)?,
+      n_bytes,
+      reader_handle: f,
+      path: Arc::from(path),
+    })
+  }
+
+  pub(crate) fn new_lenient(path: &Path) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    /*
+        Like new(), but also accepts (truncated) files that are not an integer
+        multiple of 2880 bytes. The incomplete final block is padded with
+        zeroes when it is read.
+    */
+    let f = File::open(path).map_err(|err| FitsIoErr::new(path, "open file", err))?;
+    let meta = f.metadata().map_err(|err| FitsIoErr::new(path, "read file metadata", err))?;
+    let n_bytes = meta.len();
+
+    Ok(RawFitsReader {
+      block_index: 0,
+      n_fits_blocks: block_count(n_bytes)?,
+      n_bytes,
+      reader_handle: f,
+      path: Arc::from(path),
+    })
   }
 
-  pub(crate) fn read_blocks(&mut self, buffer: &mut [u8]) -> Result<usize, InvalidFitsFileErr> {
+  pub(crate) fn read_blocks(
+    &mut self,
+    buffer: &mut [u8],
+  ) -> Result<usize, Box<dyn Error + Send + Sync>> {
     //(1) Calculate how many header blocks we have to read
     let n_blocks = buffer.len() / BLOCK_SIZE;
 
     //(2) Check if the buffer is an integer multiple of a FITS block
     if n_blocks * BLOCK_SIZE != buffer.len() {
-      return Err(InvalidFitsFileErr::new(io_err::BUF_BLOCK_DIV));
+      return Err(Box::new(InvalidFitsFileErr::new(io_err::BUF_BLOCK_DIV)));
     }
 
     //(3) Check if the number of header blocks we need to read does not exceed
     //the number of header blocks still left in the file
     if n_blocks > (self.n_fits_blocks - self.block_index) {
-      return Err(InvalidFitsFileErr::new(io_err::FILE_END));
+      return Err(Box::new(InvalidFitsFileErr::new(io_err::FILE_END)));
     }
 
-    //(4) Read the data (panic if this fails, since it fucks up the indexing)
-    self.reader_handle.read_exact(buffer).unwrap();
+    //(4) Read the data. Only the final block of a truncated file can be
+    //incomplete. If this fails, the indexing is messed up, so we move the
+    //handle back to where we think we are
+    let n_avail = (buffer.len() as u64).min(self.get_bytes_left()) as usize;
+    if let Err(err) = self.reader_handle.read_exact(&mut buffer[..n_avail]) {
+      let op = format!("read FITS blocks {}..{}", self.block_index, self.block_index + n_blocks);
+      let _ = self.reader_handle.seek(SeekFrom::Start(block_offset(self.block_index)));
+      return Err(Box::new(FitsIoErr::new(&self.path, op, err)));
+    }
+    buffer[n_avail..].fill(0);
 
     //(5) Update the block index
     self.block_index += n_blocks;
@@ -87,46 +137,332 @@ impl RawFitsReader {
     Ok(n_blocks) //return the number of blocks read
   }
 
+  pub(crate) fn skip_blocks(
+    &mut self,
+    n_blocks: usize,
+  ) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    //(1) We may not skip past the end of the file
+    if n_blocks > (self.n_fits_blocks - self.block_index) {
+      return Err(Box::new(InvalidFitsFileErr::new(io_err::FILE_END)));
+    }
+
+    //(2) Seek forward without reading the skipped blocks
+    let n_skip = block_offset(n_blocks).min(self.get_bytes_left());
+    self
+      .reader_handle
+      .seek(SeekFrom::Current(n_skip as i64))
+      .map_err(|err| FitsIoErr::new(&self.path, format!("skip {n_blocks} FITS blocks"), err))?;
+
+    //(3) Update the block index
+    self.block_index += n_blocks;
+
+    Ok(n_blocks) //return the number of blocks skipped
+  }
+
+  pub(crate) fn seek_block(
+    &mut self,
+    block_index: usize,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    //Jump to an arbitrary FITS block (this may also be backwards!)
+    if block_index > self.n_fits_blocks {
+      return Err(Box::new(InvalidFitsFileErr::new(io_err::FILE_END)));
+    }
+    let offset = block_offset(block_index);
+    self.reader_handle.seek(SeekFrom::Start(offset)).map_err(|err| {
+      FitsIoErr::new(&self.path, format!("seek to offset {offset} (FITS block {block_index})"), err)
+    })?;
+    self.block_index = block_index;
+    Ok(())
+  }
+
   pub(crate) fn get_block_len(&self) -> usize {
     self.n_fits_blocks
   }
   pub(crate) fn get_block_index(&self) -> usize {
     self.block_index
   }
+  pub(crate) fn get_path(&self) -> &Arc<Path> {
+    &self.path
+  }
+  pub(crate) fn get_bytes_left(&self) -> u64 {
+    self.n_bytes.saturating_sub(block_offset(self.block_index))
+  }
+}
+
+/*
+    Byte offsets in a file are always u64, since FITS files (large mosaics in
+    particular) can be larger than 4GB. On 32 bit platforms usize cannot even
+    hold those offsets. Block indices do fit in a usize (up to ~12TB).
+*/
+pub(crate) fn block_offset(block_index: usize) -> u64 {
+  block_index as u64 * BLOCK_SIZE as u64
+}
+
+pub(crate) fn block_count(n_bytes: u64) -> Result<usize, InvalidFitsFileErr> {
+  //Number of (partial) blocks in n_bytes
+  usize::try_from(n_bytes.div_ceil(BLOCK_SIZE as u64))
+    .map_err(|_| InvalidFitsFileErr::new(io_err::TOO_LARGE))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LockPolicy {
+  /*  THIS ENUM IS PART OF THE USER-FACING API
+      Determines whether an advisory (exclusive) lock is taken on a FITS file
+      before writing to it. Other processes that respect advisory locks can
+      then not write to the file at the same time.
+  */
+  #[default]
+  NoLock,
+  Wait,
+  FailFast,
 }
 
 #[derive(Debug)]
 pub struct RawFitsWriter {
   pub file_meta: Metadata,
-  writer_handle: File,
+  writer_handle: BufWriter<File>,
+  blocks_written: usize,
+  sparse: bool,
+  seekable: bool, //false for pipes and character devices (/dev/stdout)
+  path: Arc<Path>,
 }
 
 impl RawFitsWriter {
-  pub(crate) fn new(path: &Path) -> Result<Self, Box<dyn Error>> {
-    //(1) Open the file if it exists, create it if it doesn't
-    let out = File::create(path)?;
+  pub(crate) fn new(path: &Path) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    Self::new_with_lock(path, LockPolicy::NoLock)
+  }
 
-    //(2) Create the required derivatives
-    let meta = out.metadata()?;
+  pub(crate) fn new_with_lock(
+    path: &Path,
+    policy: LockPolicy,
+  ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    Self::new_with_options(path, &WriteOptions::new().lock(policy))
+  }
+
+  pub(crate) fn new_with_options(
+    path: &Path,
+    opts: &WriteOptions,
+  ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    //(1) Open the file (holding the lock). We may only truncate the file once
+    //we hold the lock!
+    //Only regular files can be truncated, seeked in and cut to length. Other
+    //files (pipes, for example) are written strictly sequentially
+    let out = Self::open_locked(path, opts.get_lock())?;
+    let seekable = out.metadata().is_ok_and(|meta| meta.is_file());
+    if seekable {
+      out.set_len(0).map_err(|err| FitsIoErr::new(path, "truncate file", err))?;
+    }
+
+    //(3) Create the required derivatives
+    let meta = out.metadata().map_err(|err| FitsIoErr::new(path, "read file metadata", err))?;
 
     //(R)
-    Ok(RawFitsWriter { file_meta: meta, writer_handle: out })
+    Ok(RawFitsWriter {
+      file_meta: meta,
+      writer_handle: BufWriter::with_capacity(opts.get_buffer_size(), out),
+      blocks_written: 0,
+      sparse: opts.get_sparse() && seekable,
+      seekable,
+      path: Arc::from(path),
+    })
+  }
+
+  pub(crate) fn open_locked(
+    path: &Path,
+    policy: LockPolicy,
+  ) -> Result<File, Box<dyn Error + Send + Sync>> {
+    //(1) Open the file if it exists, create it if it doesn't. The file is not
+    //truncated! (it is opened for reading as well, for in-place updates)
+    let out = OpenOptions::new()
+      .read(true)
+      .write(true)
+      .create(true)
+      .truncate(false)
+      .open(path)
+      .map_err(|err| FitsIoErr::new(path, "open file for writing", err))?;
+    let lock_err = |err| FitsIoErr::new(path, "lock file", err);
+
+    //(2) Take the lock (it is released when the file is closed)
+    match policy {
+      LockPolicy::NoLock => {}
+      LockPolicy::Wait => out.lock_exclusive().map_err(lock_err)?,
+      LockPolicy::FailFast => match out.try_lock_exclusive() {
+        Ok(()) => {}
+        Err(err) if err.kind() == fs2::lock_contended_error().kind() => {
+          return Err(Box::new(FileLockedErr::new(path)))
+        }
+        Err(err) => return Err(Box::new(lock_err(err))),
+      },
+    }
+
+    //(R) the locked file
+    Ok(out)
+  }
+
+  pub(crate) fn cut_range(
+    out: &mut File,
+    path: &Path,
+    start: u64,
+    end: u64,
+    zero_fill: bool,
+  ) -> Result<(), FitsIoErr> {
+    /*  Removes bytes start..end from an open file, by moving everything after
+        them forward and truncating the file. The tail is moved in chunks from
+        front to back, which never overwrites bytes that still have to be
+        moved. This is not crash-safe: if it is interrupted, the file holds a
+        mix of the old and the new contents.
+    */
+    let len = out.metadata().map_err(|err| FitsIoErr::new(path, "read file metadata", err))?.len();
+    let (start, end) = (start.min(len), end.min(len));
+    let mut chunk = vec![0u8; CUT_CHUNK_SIZE];
+
+    //(1) Move the tail forward
+    let (mut from, mut to) = (end, start);
+    while from < len && start < end {
+      let n = (CUT_CHUNK_SIZE as u64).min(len - from) as usize;
+      out.seek(SeekFrom::Start(from)).and_then(|_| out.read_exact(&mut chunk[..n])).map_err(
+        |err| FitsIoErr::new(path, format!("read bytes {from}..{}", from + n as u64), err),
+      )?;
+      out
+        .seek(SeekFrom::Start(to))
+        .and_then(|_| out.write_all(&chunk[..n]))
+        .map_err(|err| FitsIoErr::new(path, format!("write bytes {to}..{}", to + n as u64), err))?;
+      (from, to) = (from + n as u64, to + n as u64);
+    }
+
+    //(2) Zero the reclaimed space if asked, then cut it off
+    let new_len = len - (end - start);
+    if zero_fill {
+      chunk.fill(0);
+      let mut at = new_len;
+      out
+        .seek(SeekFrom::Start(at))
+        .map_err(|err| FitsIoErr::new(path, format!("seek to byte {at}"), err))?;
+      while at < len {
+        let n = (CUT_CHUNK_SIZE as u64).min(len - at) as usize;
+        out.write_all(&chunk[..n]).map_err(|err| {
+          FitsIoErr::new(path, format!("zero bytes {at}..{}", at + n as u64), err)
+        })?;
+        at += n as u64;
+      }
+      out.sync_data().map_err(|err| FitsIoErr::new(path, "sync file", err))?;
+    }
+    out
+      .set_len(new_len)
+      .map_err(|err| FitsIoErr::new(path, format!("set file length to {new_len} bytes"), err))
   }
 
-  pub(crate) fn write_blocks(&mut self, buffer: &[u8]) -> Result<usize, Box<dyn Error>> {
+  pub(crate) fn write_blocks(
+    &mut self,
+    buffer: &[u8],
+  ) -> Result<usize, Box<dyn Error + Send + Sync>> {
     //(1) Check if the buffer is an integer number of FITS blocks
-    if buffer.len() % BLOCK_SIZE != 0 {
+    if !buffer.len().is_multiple_of(BLOCK_SIZE) {
       return Err(Box::new(InvalidFitsFileErr::new(io_err::BUF_BLOCK_DIV)));
     }
 
-    //(2) Write the thing
-    self.writer_handle.write_all(buffer)?;
+    //(2) Write the thing. In sparse mode, long runs of zeroed blocks are
+    //skipped over instead, leaving a hole in the file
+    let n_blocks = buffer.len() / BLOCK_SIZE;
+    let io_ctx = |err| {
+      let (start, end) = (self.blocks_written, self.blocks_written + n_blocks);
+      FitsIoErr::new(&self.path, format!("write FITS blocks {start}..{end}"), err)
+    };
+    if self.sparse {
+      for (zeroed, run) in Self::zero_runs(buffer) {
+        match zeroed {
+          true => self.writer_handle.seek(SeekFrom::Current(run.len() as i64)).map(|_| ()),
+          false => self.writer_handle.write_all(run),
+        }
+        .map_err(io_ctx)?;
+      }
+    } else {
+      self.writer_handle.write_all(buffer).map_err(io_ctx)?;
+    }
+    self.blocks_written += n_blocks;
 
     //(R) the number of FITS blocks that we wrote
-    Ok(buffer.len() / BLOCK_SIZE)
+    Ok(n_blocks)
+  }
+
+  fn zero_runs(buffer: &[u8]) -> Vec<(bool, &[u8])> {
+    //Splits the buffer into runs of (non-)zeroed blocks. Short zeroed runs are
+    //not worth a seek, so they are merged with their neighbours
+    let mut runs: Vec<(bool, &[u8])> = Vec::new();
+    let mut start = 0;
+    for (idx, block) in buffer.chunks_exact(BLOCK_SIZE).enumerate() {
+      let zeroed = block.iter().all(|&byte| byte == 0);
+      let end = (idx + 1) * BLOCK_SIZE;
+      match runs.last_mut() {
+        Some((prev, run)) if *prev == zeroed => *run = &buffer[start..end],
+        _ => {
+          start = idx * BLOCK_SIZE;
+          runs.push((zeroed, &buffer[start..end]));
+        }
+      }
+    }
+    for run in runs.iter_mut() {
+      if run.0 && run.1.len() < MIN_SPARSE_BLOCKS * BLOCK_SIZE {
+        run.0 = false;
+      }
+    }
+    runs
+  }
+
+  pub(crate) fn preallocate(&mut self, n_blocks: usize) -> Result<(), FitsIoErr> {
+    //Reserves space for the whole file up front. This is only a hint, the
+    //file is cut to its actual size when it is flushed
+    if !self.seekable {
+      return Ok(());
+    }
+    let len = block_offset(n_blocks);
+    self
+      .writer_handle
+      .get_ref()
+      .allocate(len)
+      .map_err(|err| FitsIoErr::new(&self.path, format!("preallocate {len} bytes"), err))
   }
 
-  pub(crate) fn flush(&mut self) -> io::Result<()> {
-    Ok(self.writer_handle.flush()?)
+  pub(crate) fn flush(&mut self) -> Result<(), FitsIoErr> {
+    self.writer_handle.flush().map_err(|err| FitsIoErr::new(&self.path, "flush file", err))?;
+
+    //Preallocated space or trailing holes (sparse mode) mean that the length
+    //of the file does not necessarily match what we wrote
+    if !self.seekable {
+      return Ok(());
+    }
+    let len = block_offset(self.blocks_written);
+    self
+      .writer_handle
+      .get_ref()
+      .set_len(len)
+      .map_err(|err| FitsIoErr::new(&self.path, format!("set file length to {len} bytes"), err))
+  }
+
+  pub(crate) fn flush_to_len(&mut self, n_bytes: u64) -> Result<(), FitsIoErr> {
+    //Like flush(), but cuts the file to the given length. Used for raw data
+    //files, which are not padded to an integer number of FITS blocks
+    self.flush()?;
+    if !self.seekable {
+      return Ok(());
+    }
+    let len = n_bytes;
+    self
+      .writer_handle
+      .get_ref()
+      .set_len(len)
+      .map_err(|err| FitsIoErr::new(&self.path, format!("set file length to {len} bytes"), err))
+  }
+
+  pub(crate) fn sync(&mut self) -> Result<(), FitsIoErr> {
+    //Makes sure the data actually hit the disk (fsync). Pipes have no disk
+    if !self.seekable {
+      return Ok(());
+    }
+    self
+      .writer_handle
+      .get_ref()
+      .sync_all()
+      .map_err(|err| FitsIoErr::new(&self.path, "sync file", err))
   }
 }
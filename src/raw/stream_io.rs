@@ -0,0 +1,185 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{
+  error::Error,
+  io::{ErrorKind, Read, Write},
+  path::Path,
+};
+
+use crate::io_err::{self, FitsIoErr, InvalidFitsFileErr};
+
+use super::block_io::{BlockRead, BlockWrite};
+
+//Get block size from root
+const BLOCK_SIZE: usize = crate::BLOCK_SIZE;
+
+//Stand-in for the path of the file in error messages
+const STREAM_PATH: &str = "<stream>";
+
+/*
+    StreamReader reads FITS blocks from a non-seekable stream, such as a pipe,
+    stdin or a network socket. Blocks can only be read in order: skipping is
+    done by reading and discarding blocks, and there is no way back.
+
+    The length of a stream is not known up front. To find out whether another
+    HDU follows, the reader reads a single byte ahead (see is_finished). The
+    block length reported through BlockRead only counts the blocks that are
+    known to exist.
+*/
+pub(crate) struct StreamReader<R: Read> {
+  inner: R,
+  block_index: usize,
+  peeked: Option<u8>, //first byte of the next block, if it was read ahead
+}
+
+impl<R: Read> StreamReader<R> {
+  pub(crate) fn new(inner: R) -> Self {
+    StreamReader { inner, block_index: 0, peeked: None }
+  }
+
+  pub(crate) fn is_finished(&mut self) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    //Reads one byte ahead (if we hadn't already) to see if the stream ended
+    if self.peeked.is_some() {
+      return Ok(false);
+    }
+    let mut byte = [0u8];
+    loop {
+      match self.inner.read(&mut byte) {
+        Ok(0) => return Ok(true),
+        Ok(_) => {
+          self.peeked = Some(byte[0]);
+          return Ok(false);
+        }
+        Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+        Err(err) => return Err(Box::new(self.io_err("read ahead", err))),
+      }
+    }
+  }
+
+  fn io_err(&self, op: &str, err: std::io::Error) -> FitsIoErr {
+    FitsIoErr::new(Path::new(STREAM_PATH), format!("{op} (FITS block {})", self.block_index), err)
+  }
+}
+
+impl<R: Read> BlockRead for StreamReader<R> {
+  fn read_blocks(&mut self, buffer: &mut [u8]) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    //(1) Check if the buffer is an integer multiple of a FITS block
+    let n_blocks = buffer.len() / BLOCK_SIZE;
+    if n_blocks * BLOCK_SIZE != buffer.len() {
+      return Err(Box::new(InvalidFitsFileErr::new(io_err::BUF_BLOCK_DIV)));
+    }
+    if n_blocks == 0 {
+      return Ok(0);
+    }
+
+    //(2) Start with the byte we read ahead, if any
+    let start = match self.peeked.take() {
+      Some(byte) => {
+        buffer[0] = byte;
+        1
+      }
+      None => 0,
+    };
+
+    //(3) A stream that ends halfway through a block is not a valid FITS file
+    match self.inner.read_exact(&mut buffer[start..]) {
+      Ok(()) => {}
+      Err(err) if err.kind() == ErrorKind::UnexpectedEof => {
+        return Err(Box::new(InvalidFitsFileErr::new(io_err::FILE_END)));
+      }
+      Err(err) => return Err(Box::new(self.io_err("read FITS blocks", err))),
+    }
+
+    //(4) Update the block index
+    self.block_index += n_blocks;
+    Ok(n_blocks)
+  }
+
+  fn skip_blocks(&mut self, n_blocks: usize) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    //We cannot seek, so skipped blocks are read one at a time and discarded
+    let mut block = vec![0u8; BLOCK_SIZE];
+    for _ in 0..n_blocks {
+      self.read_blocks(&mut block)?;
+    }
+    Ok(n_blocks)
+  }
+
+  fn get_block_len(&self) -> usize {
+    self.block_index + usize::from(self.peeked.is_some())
+  }
+
+  fn get_block_index(&self) -> usize {
+    self.block_index
+  }
+}
+
+/*
+    StreamWriter writes FITS blocks to a non-seekable sink, such as a pipe,
+    stdout or the body of an HTTP response. Blocks are written strictly in
+    order, so everything that ends up in a header has to be known before the
+    header is written (see Fits::write_stream and ImageStreamWriter::to_stream).
+*/
+#[derive(Debug)]
+pub struct StreamWriter<W: Write> {
+  /*  THIS STRUCT IS PART OF THE USER-FACING API
+   */
+  inner: W,
+  blocks_written: usize,
+}
+
+impl<W: Write> StreamWriter<W> {
+  pub fn new(inner: W) -> Self {
+    StreamWriter { inner, blocks_written: 0 }
+  }
+
+  pub fn get_blocks_written(&self) -> usize {
+    self.blocks_written
+  }
+
+  pub fn into_inner(self) -> W {
+    self.inner
+  }
+}
+
+impl<W: Write> BlockWrite for StreamWriter<W> {
+  fn write_blocks(&mut self, buffer: &[u8]) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    //(1) Check if the buffer is an integer number of FITS blocks
+    if !buffer.len().is_multiple_of(BLOCK_SIZE) {
+      return Err(Box::new(InvalidFitsFileErr::new(io_err::BUF_BLOCK_DIV)));
+    }
+
+    //(2) Write the thing
+    let n_blocks = buffer.len() / BLOCK_SIZE;
+    self.inner.write_all(buffer).map_err(|err| {
+      let (start, end) = (self.blocks_written, self.blocks_written + n_blocks);
+      FitsIoErr::new(Path::new(STREAM_PATH), format!("write FITS blocks {start}..{end}"), err)
+    })?;
+    self.blocks_written += n_blocks;
+    Ok(n_blocks)
+  }
+
+  fn flush(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+    self
+      .inner
+      .flush()
+      .map_err(|err| FitsIoErr::new(Path::new(STREAM_PATH), "flush stream", err))?;
+    Ok(())
+  }
+}
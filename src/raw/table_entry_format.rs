@@ -75,6 +75,7 @@ impl TableEntryFormat {
     }
   }
 
+  #[allow(dead_code)]
   pub(crate) fn to_fortran_format_code(&self) -> Result<String, IFFCErr> {
     use TableEntryFormat::*;
     Ok(match &self {
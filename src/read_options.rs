@@ -0,0 +1,195 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{
+  fmt::{self, Debug, Formatter},
+  sync::Arc,
+};
+
+use crate::{
+  charset::CharsetPolicy, duplicates::DuplicatePolicy, header::Header,
+  keyword_aliases::KeywordAliases,
+};
+
+/*
+    ReadOptions control how the data units of a FITS file are decoded. They
+    are passed to Fits::open_with. Fits::open uses the default options.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableStrategy {
+  /*  THIS ENUM IS PART OF THE USER-FACING API
+      Determines how the decoding of ASCII tables is divided over threads.
+      RowParallel decodes chunks of rows in parallel, ColumnParallel decodes
+      whole columns in parallel (each thread scans the same field of every
+      row), which is friendlier on the cache for very wide tables. Auto picks
+      one based on the shape of the table.
+  */
+  #[default]
+  Auto,
+  RowParallel,
+  ColumnParallel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FieldTolerance {
+  /*  THIS ENUM IS PART OF THE USER-FACING API
+      Determines what happens with numeric ASCII table fields that are not
+      valid FITS numbers. Strict refuses the table. Repair fixes common
+      variants found in the wild (D exponents combined with other oddities,
+      Fortran exponents without a letter, embedded blanks and stray characters
+      or '+' signs) and stores fields that cannot be repaired, like overflowed
+      fields filled with asterisks, as null entries. Every repair is listed by
+      Fits::table_repairs.
+  */
+  #[default]
+  Strict,
+  Repair,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StringInterning {
+  /*  THIS ENUM IS PART OF THE USER-FACING API
+      Determines which string columns of ASCII tables are interned
+      (dictionary encoded): every distinct value is stored once, and the
+      entries refer to it. This cuts the memory used by columns with heavily
+      repeated values by an order of magnitude. Repeated only interns columns
+      whose values repeat at least a few times on average. Interned columns
+      are accessed exactly like other columns.
+  */
+  #[default]
+  Off,
+  Repeated,
+  Always,
+}
+
+#[derive(Clone)]
+struct HduFilter(Arc<dyn Fn(&Header) -> bool + Send + Sync>);
+
+impl Debug for HduFilter {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "HduFilter(..)")
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReadOptions {
+  table_strategy: TableStrategy,
+  field_tolerance: FieldTolerance,
+  string_interning: StringInterning,
+  header_charset: CharsetPolicy,
+  lenient: bool,
+  keyword_aliases: Option<KeywordAliases>,
+  duplicates: DuplicatePolicy,
+  hdu_filter: Option<HduFilter>,
+}
+
+impl ReadOptions {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn table_strategy(mut self, strategy: TableStrategy) -> Self {
+    self.table_strategy = strategy;
+    self
+  }
+
+  pub fn field_tolerance(mut self, tolerance: FieldTolerance) -> Self {
+    self.field_tolerance = tolerance;
+    self
+  }
+
+  pub fn string_interning(mut self, interning: StringInterning) -> Self {
+    self.string_interning = interning;
+    self
+  }
+
+  pub fn header_charset(mut self, policy: CharsetPolicy) -> Self {
+    //Replaced characters are listed by Fits::charset_repairs
+    self.header_charset = policy;
+    self
+  }
+
+  pub fn lenient(mut self, lenient: bool) -> Self {
+    /*  Lenient reading accepts primary HDU's that declare themselves to be
+        non-conforming (SIMPLE = F), as long as they can be parsed. Such HDU's
+        are flagged by HeaderDataUnit::is_conforming
+    */
+    self.lenient = lenient;
+    self
+  }
+
+  pub fn keyword_aliases(mut self, aliases: KeywordAliases) -> Self {
+    //Aliased keywords are renamed to their canonical keyword while reading
+    //the headers, see keyword_aliases.rs
+    self.keyword_aliases = Some(aliases);
+    self
+  }
+
+  pub fn duplicates(mut self, policy: DuplicatePolicy) -> Self {
+    //Which record is kept for keywords that appear more than once, see
+    //duplicates.rs. The repeated keywords are listed by Fits::duplicate_keywords
+    self.duplicates = policy;
+    self
+  }
+
+  pub fn hdu_filter<F>(mut self, filter: F) -> Self
+  where
+    F: Fn(&Header) -> bool + Send + Sync + 'static,
+  {
+    /*  The filter is called with every header right after it was read. The
+        data of HDU's for which it returns false is skipped. Their headers are
+        kept, and HeaderDataUnit::is_loaded returns false for them. The data
+        of HDU's read from a file can still be read with load_data
+    */
+    self.hdu_filter = Some(HduFilter(Arc::new(filter)));
+    self
+  }
+
+  pub fn get_table_strategy(&self) -> TableStrategy {
+    self.table_strategy
+  }
+  pub fn get_field_tolerance(&self) -> FieldTolerance {
+    self.field_tolerance
+  }
+  pub fn get_string_interning(&self) -> StringInterning {
+    self.string_interning
+  }
+  pub fn get_header_charset(&self) -> CharsetPolicy {
+    self.header_charset
+  }
+  pub fn get_lenient(&self) -> bool {
+    self.lenient
+  }
+  pub fn get_duplicates(&self) -> DuplicatePolicy {
+    self.duplicates
+  }
+  pub fn get_keyword_aliases(&self) -> Option<&KeywordAliases> {
+    self.keyword_aliases.as_ref()
+  }
+  pub(crate) fn without_filter(&self) -> Self {
+    //Options for reading data that was skipped (or unloaded) later on
+    Self { hdu_filter: None, ..self.clone() }
+  }
+
+  pub fn loads_data(&self, header: &Header) -> bool {
+    //If the data of the HDU with this header would be read (see hdu_filter)
+    self.hdu_filter.as_ref().is_none_or(|filter| (filter.0)(header))
+  }
+}
@@ -0,0 +1,223 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Re-packing rewrites a FITS file in canonical form: headers are re-encoded
+    with standard (fixed format) keyword records, the padding of headers and
+    data units is normalised and the CHECKSUM and DATASUM records of every HDU
+    are recomputed. Before rewriting, the original file is inspected so that
+    the caller can see what was actually changed.
+
+    Tile compression is not offered, since this crate cannot encode compressed
+    images (yet).
+*/
+
+use std::{
+  error::Error,
+  fmt::{self, Display, Formatter},
+  fs::File,
+  io::{Read, Seek, SeekFrom},
+  path::Path,
+};
+
+use crate::{
+  checksum::{self, CHECKSUM_PLACEHOLDER},
+  fits::Fits,
+  fits_index::{FitsIndex, HduLayout},
+  header_data_unit::HeaderDataUnit,
+  io_err::FitsIoErr,
+  raw::raw_io,
+};
+
+//Byte pattern of the END record
+const END_CARD: [u8; 80] = {
+  let mut card = [b' '; 80];
+  card[0] = b'E';
+  card[1] = b'N';
+  card[2] = b'D';
+  card
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepackReport {
+  changes: Vec<(usize, String)>,
+}
+
+impl RepackReport {
+  pub fn get_changes(&self) -> &[(usize, String)] {
+    &self.changes
+  }
+
+  pub fn is_clean(&self) -> bool {
+    //True if the original file was already in canonical form
+    self.changes.is_empty()
+  }
+
+  fn push(&mut self, hdu: usize, change: impl Into<String>) {
+    self.changes.push((hdu, change.into()));
+  }
+}
+
+impl Display for RepackReport {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    if self.is_clean() {
+      return writeln!(f, "file was already in canonical form");
+    }
+    for (hdu, change) in &self.changes {
+      writeln!(f, "HDU #{hdu}: {change}")?;
+    }
+    Ok(())
+  }
+}
+
+pub(crate) fn repack(
+  path: &Path,
+  out: &Path,
+) -> Result<RepackReport, Box<dyn Error + Send + Sync>> {
+  //(1) Inspect the original file, before anything is changed
+  let index = FitsIndex::build(path)?;
+  let mut fits = Fits::open(path)?;
+  let mut report = RepackReport::default();
+  let mut file = File::open(path).map_err(|err| FitsIoErr::new(path, "open file", err))?;
+
+  for (hdu_idx, layout) in index.get_layouts().iter().enumerate() {
+    let (sums, header) = checksum::hdu_sums(&mut file, path, layout)?;
+    inspect_header(hdu_idx, &header, &mut report);
+    inspect_checksums(hdu_idx, &header, sums, &mut report);
+
+    if let Some(hdu) = fits.get_hdu(hdu_idx) {
+      let data_len = HeaderDataUnit::data_byte_len(hdu.get_header())?;
+      if !data_padding_is_zero(&mut file, path, layout, data_len)? {
+        report.push(hdu_idx, "data unit padding was not zero-filled");
+      }
+    }
+  }
+
+  //(2) Replace the checksum records with placeholders at the end of the header
+  for hdu_idx in 0..fits.get_num_hdus() {
+    let header = fits.get_hdu_mut(hdu_idx).unwrap().get_header_mut();
+    header.remove_record("CHECKSUM");
+    header.remove_record("DATASUM");
+    header.set_record_at(header.get_num_records(), "CHECKSUM", CHECKSUM_PLACEHOLDER);
+    header.set_record_at(header.get_num_records(), "DATASUM", "'0'");
+  }
+
+  //(3) Write the file and fill in the checksums
+  fits.write(out)?;
+  checksum::write_checksums(out, FitsIndex::build(out)?.get_layouts())?;
+
+  //(4) Headers can shrink or grow when they are re-encoded
+  let new_index = FitsIndex::build(out)?;
+  for (hdu_idx, (old, new)) in index.get_layouts().iter().zip(new_index.get_layouts()).enumerate() {
+    if old.get_header_blocks() != new.get_header_blocks() {
+      report.push(
+        hdu_idx,
+        format!(
+          "header size changed from {} to {} blocks",
+          old.get_header_blocks(),
+          new.get_header_blocks()
+        ),
+      );
+    }
+  }
+
+  //(R) what we did
+  Ok(report)
+}
+
+fn inspect_header(hdu_idx: usize, header: &[u8], report: &mut RepackReport) {
+  //(1) Everything after the END record should be blank
+  let Some(end) = header.chunks_exact(80).position(|card| card == END_CARD) else {
+    return;
+  };
+  if header[(end + 1) * 80..].iter().any(|&byte| byte != b' ') {
+    report.push(hdu_idx, "header padding was not blank-filled");
+  }
+
+  //(2) Values of (non-string) keywords should be right-justified to column 30
+  let non_fixed = header
+    .chunks_exact(80)
+    .take(end)
+    .filter(|card| &card[8..10] == b"= " && !is_fixed_format(&card[10..]))
+    .count();
+  if non_fixed > 0 {
+    report.push(hdu_idx, format!("{non_fixed} keyword record(s) were not in fixed format"));
+  }
+}
+
+fn is_fixed_format(value: &[u8]) -> bool {
+  //Strings start in column 11, other values end in column 30
+  let value = value.split(|&byte| byte == b'/').next().unwrap_or_default();
+  let trimmed = value.trim_ascii();
+  match trimmed.first() {
+    None => true,
+    Some(b'\'') => value[0] == b'\'',
+    Some(_) if trimmed.len() > 20 => true,
+    Some(_) => value.len() >= 20 && value[..20].trim_ascii_start() == trimmed,
+  }
+}
+
+fn inspect_checksums(
+  hdu_idx: usize,
+  header: &[u8],
+  sums: checksum::HduSums,
+  report: &mut RepackReport,
+) {
+  //(1) CHECKSUM should make the whole HDU sum to negative zero
+  match checksum::find_card(header, "CHECKSUM") {
+    None => report.push(hdu_idx, "CHECKSUM was missing"),
+    Some(_) if sums.total() != u32::MAX => report.push(hdu_idx, "CHECKSUM was invalid"),
+    Some(_) => {}
+  }
+
+  //(2) DATASUM should be the sum of the data unit
+  match checksum::find_card(header, "DATASUM") {
+    None => report.push(hdu_idx, "DATASUM was missing"),
+    Some(idx) => {
+      let card = String::from_utf8_lossy(&header[idx * 80 + 10..(idx + 1) * 80]);
+      let value = card.split('/').next().unwrap_or_default().trim().trim_matches('\'').trim();
+      if value.parse::<u32>().ok() != Some(sums.data) {
+        report.push(hdu_idx, "DATASUM was invalid");
+      }
+    }
+  }
+}
+
+fn data_padding_is_zero(
+  file: &mut File,
+  path: &Path,
+  layout: &HduLayout,
+  data_len: u64,
+) -> Result<bool, FitsIoErr> {
+  //Only the last block of the data unit can contain padding
+  let padding = raw_io::block_offset(layout.get_data_blocks()) - data_len;
+  if layout.get_data_blocks() == 0 || padding == 0 {
+    return Ok(true);
+  }
+
+  //padding is less than a block
+  let mut buf = vec![0u8; padding as usize];
+  let offset = raw_io::block_offset(
+    layout.get_start_block() + layout.get_header_blocks() + layout.get_data_blocks(),
+  ) - padding;
+  let io_err = |err| FitsIoErr::new(path, format!("read data padding at offset {offset}"), err);
+  file.seek(SeekFrom::Start(offset)).map_err(io_err)?;
+  file.read_exact(&mut buf).map_err(io_err)?;
+  Ok(buf.iter().all(|&byte| byte == 0))
+}
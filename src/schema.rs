@@ -0,0 +1,333 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    A Schema describes the expected structure of a FITS data product: which
+    HDU's it contains (in order), what kind of data they hold, which keywords
+    must be present and which columns tables must have. FITS files can be
+    validated against a schema, yielding a machine-readable report.
+*/
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::{
+  extensions::{
+    table::{BinTable, BinType},
+    Extension,
+  },
+  fits::Fits,
+  header::Header,
+  raw::table_entry_format::TableEntryFormat,
+  validation::{Severity, ValidationProfile},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataKind {
+  Any,
+  NoData,
+  Image,
+  AsciiTable,
+  BinTable,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+  Text,
+  Int,
+  Float,
+}
+
+#[derive(Debug, Clone)]
+pub struct HduSchema {
+  name: Option<String>,
+  data: DataKind,
+  keywords: Vec<String>,
+  columns: Vec<(String, ColumnType)>,
+  profiles: Vec<ValidationProfile>,
+}
+
+impl HduSchema {
+  pub fn new(data: DataKind) -> Self {
+    HduSchema { name: None, data, keywords: Vec::new(), columns: Vec::new(), profiles: Vec::new() }
+  }
+
+  pub fn named(mut self, extname: &str) -> Self {
+    self.name = Some(extname.to_string());
+    self
+  }
+
+  pub fn require_keyword(mut self, keyword: &str) -> Self {
+    self.keywords.push(keyword.to_string());
+    self
+  }
+
+  pub fn require_column(mut self, label: &str, dtype: ColumnType) -> Self {
+    self.columns.push((label.to_string(), dtype));
+    self
+  }
+
+  pub fn with_profile(mut self, profile: ValidationProfile) -> Self {
+    self.profiles.push(profile);
+    self
+  }
+
+  fn check(
+    &self,
+    index: usize,
+    header: &Header,
+    data: Option<&Extension>,
+    report: &mut Vec<Violation>,
+  ) {
+    use ViolationKind::*;
+    let mut violation = |kind, subject: &str, message: String| {
+      report.push(Violation { hdu: index, kind, subject: subject.to_string(), message })
+    };
+
+    //(1) Check the name of the HDU
+    if let Some(name) = &self.name {
+      match header.get_value("EXTNAME").map(|val| unquote(val)) {
+        Some(found) if found == name => {}
+        found => violation(
+          WrongName,
+          "EXTNAME",
+          format!("expected '{name}', found '{}'", found.unwrap_or("(none)")),
+        ),
+      }
+    }
+
+    //(2) Check the kind of data
+    let found = match data {
+      None => DataKind::NoData,
+      Some(Extension::Image(_)) => DataKind::Image,
+      Some(Extension::AsciiTable(_)) => DataKind::AsciiTable,
+      Some(Extension::BinTable(_)) => DataKind::BinTable,
+      Some(Extension::Corrupted) => DataKind::Any,
+    };
+    if self.data != DataKind::Any && self.data != found {
+      violation(WrongDataKind, "", format!("expected {:?} data, found {found:?}", self.data));
+    }
+
+    //(3) Check the required keywords
+    for keyword in &self.keywords {
+      if header.get_record(keyword).is_none() {
+        violation(MissingKeyword, keyword, String::from("required keyword is missing"));
+      }
+    }
+
+    //(4) Check the required columns
+    if !self.columns.is_empty() {
+      match data {
+        Some(data @ (Extension::AsciiTable(_) | Extension::BinTable(_))) => {
+          for (label, dtype) in &self.columns {
+            match find_column(data, label) {
+              None => violation(MissingColumn, label, String::from("required column is missing")),
+              Some(found) if found != *dtype => violation(
+                WrongColumnType,
+                label,
+                format!("expected {dtype:?} column, found {found:?}"),
+              ),
+              _ => {}
+            }
+          }
+        }
+        _ => violation(MissingColumn, "", String::from("HDU does not contain a table")),
+      }
+    }
+
+    //(5) Run the keyword validation profiles, only errors are violations
+    for profile in &self.profiles {
+      for diagnostic in profile.validate(index, header) {
+        if diagnostic.get_severity() == Severity::Error {
+          violation(
+            InvalidKeywordValue,
+            diagnostic.get_keyword(),
+            diagnostic.get_message().to_string(),
+          );
+        }
+      }
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct Schema {
+  name: String,
+  hdus: Vec<HduSchema>,
+  allow_extra_hdus: bool,
+}
+
+impl Schema {
+  pub fn new(name: &str) -> Self {
+    Schema { name: name.to_string(), hdus: Vec::new(), allow_extra_hdus: false }
+  }
+
+  pub fn hdu(mut self, hdu: HduSchema) -> Self {
+    //HDU's are expected in the order in which they are added
+    self.hdus.push(hdu);
+    self
+  }
+
+  pub fn allow_extra_hdus(mut self, allow: bool) -> Self {
+    self.allow_extra_hdus = allow;
+    self
+  }
+
+  pub fn get_name(&self) -> &str {
+    &self.name
+  }
+
+  pub fn validate(&self, fits: &Fits) -> SchemaReport {
+    let mut violations = Vec::new();
+
+    //(1) Check the HDU's that the schema describes
+    for (index, hdu_schema) in self.hdus.iter().enumerate() {
+      match fits.get_hdu(index) {
+        None => violations.push(Violation {
+          hdu: index,
+          kind: ViolationKind::MissingHdu,
+          subject: String::new(),
+          message: String::from("HDU is missing"),
+        }),
+        Some(hdu) => hdu_schema.check(index, hdu.get_header(), hdu.get_data(), &mut violations),
+      }
+    }
+
+    //(2) Check for HDU's that the schema does not describe
+    if !self.allow_extra_hdus {
+      for index in self.hdus.len()..fits.get_num_hdus() {
+        violations.push(Violation {
+          hdu: index,
+          kind: ViolationKind::UnexpectedHdu,
+          subject: String::new(),
+          message: String::from("HDU is not part of the schema"),
+        });
+      }
+    }
+
+    SchemaReport { schema: self.name.clone(), violations }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViolationKind {
+  MissingHdu,
+  UnexpectedHdu,
+  WrongName,
+  WrongDataKind,
+  MissingKeyword,
+  InvalidKeywordValue,
+  MissingColumn,
+  WrongColumnType,
+}
+
+#[derive(Debug, Clone)]
+pub struct Violation {
+  hdu: usize,
+  kind: ViolationKind,
+  subject: String, //keyword or column label the violation is about
+  message: String,
+}
+
+impl Violation {
+  pub fn get_hdu(&self) -> usize {
+    self.hdu
+  }
+  pub fn get_kind(&self) -> ViolationKind {
+    self.kind
+  }
+  pub fn get_subject(&self) -> &str {
+    &self.subject
+  }
+  pub fn get_message(&self) -> &str {
+    &self.message
+  }
+}
+
+impl Display for Violation {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "HDU #{} [{:?}] {}: {}", self.hdu, self.kind, self.subject, self.message)
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct SchemaReport {
+  schema: String,
+  violations: Vec<Violation>,
+}
+
+impl SchemaReport {
+  pub fn is_valid(&self) -> bool {
+    self.violations.is_empty()
+  }
+  pub fn get_violations(&self) -> &[Violation] {
+    &self.violations
+  }
+  pub fn to_tsv(&self) -> String {
+    //One violation per line: hdu, kind, subject, message
+    let mut out = String::from("hdu\tkind\tsubject\tmessage\n");
+    for v in &self.violations {
+      out += &format!("{}\t{:?}\t{}\t{}\n", v.hdu, v.kind, v.subject, v.message);
+    }
+    out
+  }
+}
+
+impl Display for SchemaReport {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    writeln!(f, ">Schema '{}': {} violation(s)", self.schema, self.violations.len())?;
+    for violation in &self.violations {
+      writeln!(f, ">  {violation}")?;
+    }
+    Ok(())
+  }
+}
+
+fn unquote(value: &str) -> &str {
+  //String values are stored as 'value   ' in the header
+  value.trim().trim_start_matches('\'').trim_end_matches('\'').trim_end()
+}
+
+fn find_column(data: &Extension, label: &str) -> Option<ColumnType> {
+  let tbl = match data {
+    Extension::BinTable(tbl) => return find_bin_column(tbl, label),
+    Extension::AsciiTable(tbl) => tbl,
+    _ => return None,
+  };
+  let col =
+    (0..tbl.get_shape().0).find(|&col| tbl.get_col_label(col).map(unquote) == Some(label))?;
+  match tbl.get_col_fmt(col)? {
+    TableEntryFormat::Char(_) => Some(ColumnType::Text),
+    TableEntryFormat::Int(_) => Some(ColumnType::Int),
+    TableEntryFormat::Float(_) => Some(ColumnType::Float),
+    TableEntryFormat::Invalid(_) => None,
+  }
+}
+
+fn find_bin_column(tbl: &BinTable, label: &str) -> Option<ColumnType> {
+  //Column types follow the entries that the columns return
+  let col = (0..tbl.get_shape().0).find(|&col| tbl.get_col_label(col) == Some(label))?;
+  match tbl.get_col_format(col)?.get_type() {
+    BinType::Char | BinType::Logical => Some(ColumnType::Text),
+    BinType::Bit | BinType::Byte | BinType::Short | BinType::Int | BinType::Long => {
+      Some(ColumnType::Int)
+    }
+    BinType::Float | BinType::Double => Some(ColumnType::Float),
+    BinType::Complex | BinType::DoubleComplex => None,
+  }
+}
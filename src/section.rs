@@ -0,0 +1,238 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Image sections use the syntax of cfitsio's extended file names. A section
+    lists a range of (1-based, inclusive) FITS pixels for every axis:
+
+        "100:200,300:400"   pixels 100 to 200 along the first axis, etc.
+        "*,1:50"            the whole first axis
+        "-*,50:1"           both axes reversed (flipped)
+        "1:100:2,*:4"       every second / fourth pixel
+        "7,*"               a single pixel along the first axis
+
+    Extended file names add the HDU (by index or by EXTNAME and optionally
+    EXTVER) and the section to the path of the file:
+
+        "file.fits[1][100:200,300:400]"
+        "file.fits[SCI,2]"
+        "file.fits[1:100,*]"   (section of the first image in the file)
+*/
+
+use std::{
+  fmt::{self, Display, Formatter},
+  path::{Path, PathBuf},
+};
+
+use crate::section_err::{SectionRangeErr, SectionSyntaxErr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AxisRange {
+  //All pixels along the axis, possibly in reverse order
+  Full { reversed: bool, step: usize },
+  //Pixels first to last (inclusive), reversed if first > last
+  Range { first: usize, last: usize, step: usize },
+}
+
+impl AxisRange {
+  fn parse(text: &str, input: &str) -> Result<Self, SectionSyntaxErr> {
+    let parts: Vec<&str> = text.trim().split(':').map(str::trim).collect();
+    let number = |part: &str| -> Result<usize, SectionSyntaxErr> {
+      match part.parse::<usize>() {
+        Ok(0) => Err(SectionSyntaxErr::new(input, "FITS pixels are numbered from 1")),
+        Ok(value) => Ok(value),
+        Err(_) => Err(SectionSyntaxErr::new(input, format!("\"{part}\" is not a pixel number"))),
+      }
+    };
+
+    match parts.as_slice() {
+      ["*"] => Ok(AxisRange::Full { reversed: false, step: 1 }),
+      ["-*"] => Ok(AxisRange::Full { reversed: true, step: 1 }),
+      ["*", step] => Ok(AxisRange::Full { reversed: false, step: number(step)? }),
+      ["-*", step] => Ok(AxisRange::Full { reversed: true, step: number(step)? }),
+      [pixel] => Ok(AxisRange::Range { first: number(pixel)?, last: number(pixel)?, step: 1 }),
+      [first, last] => Ok(AxisRange::Range { first: number(first)?, last: number(last)?, step: 1 }),
+      [first, last, step] => {
+        Ok(AxisRange::Range { first: number(first)?, last: number(last)?, step: number(step)? })
+      }
+      _ => Err(SectionSyntaxErr::new(input, format!("\"{text}\" is not a valid range"))),
+    }
+  }
+
+  fn resolve(&self, len: usize) -> Option<(usize, usize, isize)> {
+    //0-based start and length of the bounding box, and the (signed) step
+    let (first, last, step) = match *self {
+      AxisRange::Full { reversed: false, step } => (1, len, step),
+      AxisRange::Full { reversed: true, step } => (len, 1, step),
+      AxisRange::Range { first, last, step } => (first, last, step),
+    };
+    if len == 0 || first.max(last) > len {
+      return None;
+    }
+    let (low, high) = (first.min(last), first.max(last));
+
+    //Reversed ranges start at their (original) first pixel, which is the last
+    //pixel of the bounding box. The box is trimmed to the last pixel we need
+    let span = (high - low) / step * step;
+    match first <= last {
+      true => Some((low - 1, span + 1, step as isize)),
+      false => Some((high - 1 - span, span + 1, -(step as isize))),
+    }
+  }
+}
+
+impl Display for AxisRange {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match *self {
+      AxisRange::Full { reversed, step } => {
+        write!(f, "{}*", if reversed { "-" } else { "" })?;
+        match step {
+          1 => Ok(()),
+          step => write!(f, ":{step}"),
+        }
+      }
+      AxisRange::Range { first, last, step: 1 } => write!(f, "{first}:{last}"),
+      AxisRange::Range { first, last, step } => write!(f, "{first}:{last}:{step}"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+  axes: Vec<AxisRange>,
+}
+
+impl Section {
+  pub fn parse(text: &str) -> Result<Self, SectionSyntaxErr> {
+    let text = text.trim();
+    if text.is_empty() {
+      return Err(SectionSyntaxErr::new(text, "section is empty"));
+    }
+    let axes =
+      text.split(',').map(|axis| AxisRange::parse(axis, text)).collect::<Result<_, _>>()?;
+    Ok(Section { axes })
+  }
+
+  pub fn get_axes(&self) -> &[AxisRange] {
+    &self.axes
+  }
+
+  pub(crate) fn resolve(
+    &self,
+    shape: &[usize],
+  ) -> Result<Vec<(usize, usize, isize)>, SectionRangeErr> {
+    //Bounding box and steps of the section, see AxisRange::resolve
+    let err = || SectionRangeErr::new(self.to_string(), shape);
+    if self.axes.len() != shape.len() {
+      return Err(err());
+    }
+    self.axes.iter().zip(shape).map(|(axis, &len)| axis.resolve(len).ok_or_else(err)).collect()
+  }
+}
+
+impl Display for Section {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    let axes: Vec<String> = self.axes.iter().map(AxisRange::to_string).collect();
+    write!(f, "{}", axes.join(","))
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HduSelector {
+  Index(usize),
+  Name { extname: String, extver: Option<i64> },
+}
+
+impl Display for HduSelector {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      HduSelector::Index(index) => write!(f, "{index}"),
+      HduSelector::Name { extname, extver: None } => write!(f, "{extname}"),
+      HduSelector::Name { extname, extver: Some(extver) } => write!(f, "{extname},{extver}"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedPath {
+  path: PathBuf,
+  hdu: Option<HduSelector>,
+  section: Option<Section>,
+}
+
+impl ExtendedPath {
+  pub fn parse(text: &str) -> Result<Self, SectionSyntaxErr> {
+    //(1) The path ends at the first bracket
+    let (path, mut rest) = match text.find('[') {
+      None => (text, ""),
+      Some(idx) => (&text[..idx], &text[idx..]),
+    };
+    if path.is_empty() {
+      return Err(SectionSyntaxErr::new(text, "file name is missing"));
+    }
+
+    //(2) Every bracketed group is either an HDU selector or a section
+    let (mut hdu, mut section) = (None, None);
+    while !rest.is_empty() {
+      let Some(end) = rest.find(']').filter(|_| rest.starts_with('[')) else {
+        return Err(SectionSyntaxErr::new(text, "unbalanced brackets"));
+      };
+      let group = rest[1..end].trim();
+      rest = &rest[end + 1..];
+
+      let is_section = group.contains(':') || group.contains('*');
+      match (is_section, &hdu, &section) {
+        (true, _, None) => section = Some(Section::parse(group)?),
+        (false, None, None) => hdu = Some(Self::parse_hdu(group, text)?),
+        _ => return Err(SectionSyntaxErr::new(text, format!("unexpected [{group}]"))),
+      }
+    }
+
+    Ok(ExtendedPath { path: PathBuf::from(path), hdu, section })
+  }
+
+  fn parse_hdu(group: &str, input: &str) -> Result<HduSelector, SectionSyntaxErr> {
+    if let Ok(index) = group.parse() {
+      return Ok(HduSelector::Index(index));
+    }
+    let (extname, extver) = match group.split_once(',') {
+      None => (group, None),
+      Some((extname, extver)) => match extver.trim().parse() {
+        Ok(extver) => (extname, Some(extver)),
+        Err(_) => {
+          return Err(SectionSyntaxErr::new(input, format!("\"{extver}\" is not an EXTVER")))
+        }
+      },
+    };
+    match extname.trim() {
+      "" => Err(SectionSyntaxErr::new(input, "HDU selector is empty")),
+      extname => Ok(HduSelector::Name { extname: extname.to_string(), extver }),
+    }
+  }
+
+  pub fn get_path(&self) -> &Path {
+    &self.path
+  }
+  pub fn get_hdu(&self) -> Option<&HduSelector> {
+    self.hdu.as_ref()
+  }
+  pub fn get_section(&self) -> Option<&Section> {
+    self.section.as_ref()
+  }
+}
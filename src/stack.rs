@@ -0,0 +1,266 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::error::Error;
+
+use ndarray::{Array, ArrayViewD, IxDyn, ShapeBuilder};
+use rayon::prelude::*;
+
+use crate::{
+  bitpix::{Bitpix, PromotionRules},
+  extensions::{
+    image::{CompactImage, Image, TypedImage},
+    Extension,
+  },
+  fits::Fits,
+  hdu_err::MissingDataErr,
+  header::Header,
+  header_data_unit::HeaderDataUnit,
+  img_err::StackShapeErr,
+  keyword_value::quote,
+};
+
+/*
+    Weighted stacking (coadding) of aligned frames, as done for survey coadds.
+    Every frame may come with a weight map and an exposure time:
+
+    - weight maps hold the inverse variance of the pixels of the frame. Frames
+      without a weight map get weight 1 for every pixel. Pixels with a weight
+      of 0 (or less), and NaN pixels, do not contribute.
+    - frames with an exposure time are converted to count rates (divided by
+      the exposure time), and their weights to the inverse variance of the
+      rate (multiplied by the exposure time squared).
+
+    The stack is the weighted mean of the frames. The weight map of the stack
+    is the sum of the weights, which is the inverse variance of the stacked
+    pixels, and the exposure map holds the total exposure time of the frames
+    that contributed to each pixel. Frames without an exposure time count as
+    1 there, so without exposure times it is a map of the number of frames.
+
+    The frames have to be aligned (resampled onto the same pixel grid) before
+    they are stacked. The header of the first frame that is added as an HDU
+    serves as the template for the headers of the output, so its WCS carries
+    over.
+
+    The pixel type of the stack follows the PromotionRules of the stack: the
+    types of the frames are promoted to a common type, which determines the
+    type of the (floating point) mean. By default the stack is an f64 image.
+*/
+
+//Header keyword for the exposure time of a frame (in seconds)
+const EXPTIME_KEYWORD: &str = "EXPTIME";
+//Records of the template that do not apply to the output
+const DROPPED_KEYWORDS: [&str; 3] = [EXPTIME_KEYWORD, "NCOMBINE", "BUNIT"];
+
+#[derive(Debug, Clone, Default)]
+pub struct Stack {
+  shape: Option<Vec<usize>>,
+  weighted_sum: Vec<f64>,
+  weight_sum: Vec<f64>,
+  exposure_sum: Vec<f64>,
+  n_frames: usize,
+  total_exposure: Option<f64>,
+  template: Option<Header>,
+  promotion: PromotionRules,
+  bitpix: Option<Bitpix>, //common type of the frames
+}
+
+impl Stack {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn promotion(mut self, rules: PromotionRules) -> Self {
+    self.promotion = rules;
+    self
+  }
+
+  pub fn add(
+    &mut self,
+    img: &TypedImage,
+    weight: Option<ArrayViewD<f64>>,
+    exposure: Option<f64>,
+  ) -> Result<(), StackShapeErr> {
+    //(1) All frames (and weight maps) must have the same shape
+    let data = img.to_f64_array();
+    let shape = self.shape.get_or_insert_with(|| data.shape().to_vec()).clone();
+    if data.shape() != shape.as_slice() {
+      return Err(StackShapeErr::new(self.n_frames, &shape, data.shape()));
+    }
+    if let Some(weight) = &weight {
+      if weight.shape() != shape.as_slice() {
+        return Err(StackShapeErr::new(self.n_frames, &shape, weight.shape()));
+      }
+    }
+    if self.n_frames == 0 {
+      let len = data.len();
+      self.weighted_sum = vec![0.0; len];
+      self.weight_sum = vec![0.0; len];
+      self.exposure_sum = vec![0.0; len];
+    }
+
+    //(2) Flatten everything in the same (Fortran) order
+    let pixels: Vec<f64> = data.t().iter().copied().collect();
+    let weights: Vec<f64> = match &weight {
+      Some(weight) => weight.t().iter().copied().collect(),
+      None => vec![1.0; pixels.len()],
+    };
+    let (scale, weight_scale, time) = match exposure {
+      Some(time) => (1.0 / time, time * time, time),
+      None => (1.0, 1.0, 1.0),
+    };
+
+    //(3) Accumulate, in parallel
+    self
+      .weighted_sum
+      .par_iter_mut()
+      .zip(self.weight_sum.par_iter_mut())
+      .zip(self.exposure_sum.par_iter_mut())
+      .zip(pixels.par_iter().zip(weights.par_iter()))
+      .for_each(|(((weighted_sum, weight_sum), exposure_sum), (&px, &weight))| {
+        if px.is_nan() || weight.is_nan() || weight <= 0.0 {
+          return;
+        }
+        let weight = weight * weight_scale;
+        *weighted_sum += weight * px * scale;
+        *weight_sum += weight;
+        *exposure_sum += time;
+      });
+
+    self.total_exposure = match (self.n_frames, self.total_exposure, exposure) {
+      (0, _, exposure) => exposure,
+      (_, Some(total), Some(time)) => Some(total + time),
+      _ => None,
+    };
+    self.bitpix = Some(match self.bitpix {
+      Some(bitpix) => self.promotion.promote(bitpix, img.get_bitpix()),
+      None => img.get_bitpix(),
+    });
+    self.n_frames += 1;
+    Ok(())
+  }
+
+  pub fn add_compact(
+    &mut self,
+    img: &CompactImage,
+    weight: Option<ArrayViewD<f64>>,
+    exposure: Option<f64>,
+  ) -> Result<(), StackShapeErr> {
+    //Like add, only expanding the frame while it is being added
+    self.add(&img.to_image(), weight, exposure)
+  }
+
+  pub fn add_hdu(
+    &mut self,
+    hdu: &HeaderDataUnit,
+    weight: Option<&HeaderDataUnit>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    //Like add, with the exposure time taken from the EXPTIME keyword
+    let Some(Extension::Image(img)) = hdu.get_data() else {
+      return Err(Box::new(MissingDataErr::new("an image")));
+    };
+    let weight = match weight.map(HeaderDataUnit::get_data) {
+      None => None,
+      Some(Some(Extension::Image(weight))) => Some(weight.to_f64_array()),
+      Some(_) => return Err(Box::new(MissingDataErr::new("a weight map image"))),
+    };
+    let header = hdu.get_header();
+    let exposure = match header.get_value(EXPTIME_KEYWORD) {
+      Some(_) => Some(header.get_value_as::<f64>(EXPTIME_KEYWORD)?),
+      None => None,
+    };
+    self.add(img, weight.as_ref().map(|weight| weight.view()), exposure)?;
+
+    if self.template.is_none() {
+      let mut template = header.clone();
+      for keyword in DROPPED_KEYWORDS {
+        template.remove_record(keyword);
+      }
+      self.template = Some(template);
+    }
+    Ok(())
+  }
+
+  pub fn get_n_frames(&self) -> usize {
+    self.n_frames
+  }
+  pub fn get_shape(&self) -> Option<&[usize]> {
+    self.shape.as_deref()
+  }
+  pub fn get_total_exposure(&self) -> Option<f64> {
+    //Sum of the exposure times, if all frames had one
+    self.total_exposure
+  }
+  pub fn get_promotion(&self) -> &PromotionRules {
+    &self.promotion
+  }
+  pub fn get_bitpix(&self) -> Bitpix {
+    //Pixel type of the mean, given the frames that were added so far
+    self.promotion.float_result(self.bitpix.unwrap_or(Bitpix::Dpf))
+  }
+
+  /*
+      The mean has the pixel type given by get_bitpix, the weight and exposure
+      maps are always double precision. Pixels to which no frame contributed
+      are NaN in the stack (0 for integer types), and 0 in the maps.
+  */
+  pub fn mean(&self) -> TypedImage {
+    let mean: Vec<f64> = self
+      .weighted_sum
+      .par_iter()
+      .zip(self.weight_sum.par_iter())
+      .map(|(sum, weight)| if *weight > 0.0 { sum / weight } else { f64::NAN })
+      .collect();
+    self.to_image(mean).to_bitpix(self.get_bitpix())
+  }
+
+  pub fn weight_map(&self) -> TypedImage {
+    self.to_image(self.weight_sum.clone())
+  }
+
+  pub fn exposure_map(&self) -> TypedImage {
+    self.to_image(self.exposure_sum.clone())
+  }
+
+  pub fn finish(self) -> Fits {
+    /*  FITS file with the stack in the primary HDU, followed by the WEIGHT and
+        EXPOSURE image extensions.
+    */
+    let mut header = self.template.clone().unwrap_or_else(Header::new);
+    header.put_record("NCOMBINE", self.n_frames.to_string(), None);
+    if let Some(total) = self.total_exposure {
+      header.put_record(EXPTIME_KEYWORD, format!("{total:?}").replace('e', "E"), None);
+    }
+    header.append_history(&format!("Weighted mean of {} frames", self.n_frames));
+    let mut fits = Fits::from_hdus(vec![HeaderDataUnit::from_image(Some(&header), self.mean())]);
+
+    let template = self.template.as_ref();
+    for (name, img) in [("WEIGHT", self.weight_map()), ("EXPOSURE", self.exposure_map())] {
+      let mut hdu = HeaderDataUnit::from_image(template, img);
+      hdu.get_header_mut().put_record("EXTNAME", quote(name), None);
+      fits.push_hdu(hdu);
+    }
+    fits
+  }
+
+  fn to_image(&self, pixels: Vec<f64>) -> TypedImage {
+    let shape = self.shape.clone().unwrap_or(vec![0]);
+    TypedImage::DpfImg(Image::new(Array::<f64, IxDyn>::from_shape_vec(shape.f(), pixels).unwrap()))
+  }
+}
@@ -0,0 +1,278 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use ndarray::{ArcArray, IxDyn};
+use num_traits::ToPrimitive;
+use rayon::prelude::*;
+
+use crate::{
+  bitpix::Bitpix,
+  extensions::image::{FitsPixel, TypedImage},
+};
+
+/*
+    Summary statistics of image pixels, computed without overflow or (much)
+    loss of precision, whatever the pixel type or the number of pixels:
+
+    - integer images are summed exactly in 128 bits. An i128 holds the sum of
+      2^64 i64 pixels, so the sum never overflows and get_int_sum() is exact.
+      The mean is computed from the exact sum, so it is off by at most 1 ulp.
+    - float images are summed with Neumaier's compensated summation. The
+      error of the sum is about one rounding of the result (plus n^2 eps^2
+      times the sum of the absolute values) instead of growing with n eps.
+      NaN pixels are left out and counted separately.
+    - the variance is accumulated as the sum of squared deviations from the
+      mean (two passes, compensated), which does not suffer from the
+      cancellation of the naive sum of squares. Deviations of integer pixels
+      are taken from the exact mean, in 128 bits.
+
+    Statistics of several images (e.g. the frames that go into a master bias)
+    can be combined with merge(). Integer sums stay exact when merging.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageStats {
+  count: usize,
+  n_nan: usize,
+  int_sum: Option<i128>,
+  sum: f64,
+  mean: f64,
+  m2: f64, //sum of squared deviations from the mean
+  min: f64,
+  max: f64,
+}
+
+impl Default for ImageStats {
+  fn default() -> Self {
+    ImageStats {
+      count: 0,
+      n_nan: 0,
+      int_sum: Some(0),
+      sum: 0.0,
+      mean: f64::NAN,
+      m2: 0.0,
+      min: f64::NAN,
+      max: f64::NAN,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Compensated {
+  //Neumaier summation: sum + compensation is the sum to (about) double precision
+  sum: f64,
+  compensation: f64,
+}
+
+impl Compensated {
+  fn add(&mut self, value: f64) {
+    let sum = self.sum + value;
+    if self.sum.abs() >= value.abs() {
+      self.compensation += (self.sum - sum) + value;
+    } else {
+      self.compensation += (value - sum) + self.sum;
+    }
+    self.sum = sum;
+  }
+
+  fn merge(mut self, other: Compensated) -> Self {
+    self.add(other.sum);
+    self.add(other.compensation);
+    self
+  }
+
+  fn total(&self) -> f64 {
+    self.sum + self.compensation
+  }
+}
+
+fn split_mean(sum: i128, count: usize) -> (i128, f64) {
+  //Exact mean of integers, as its floor and the fraction above it
+  let count = count as i128;
+  (sum.div_euclid(count), sum.rem_euclid(count) as f64 / count as f64)
+}
+
+//Pixels are processed in chunks of this size in parallel
+const CHUNK_LEN: usize = 1 << 16;
+
+impl ImageStats {
+  pub fn of_image(img: &TypedImage) -> Self {
+    crate::impl_typed_image_dispatch!(img, img => Self::of_array(img.get_data()))
+  }
+
+  fn of_array<T: FitsPixel + ToPrimitive>(data: &ArcArray<T, IxDyn>) -> Self {
+    //Order does not matter, so pixels are visited in memory order if possible
+    let pixels: Vec<T> = match data.as_slice_memory_order() {
+      Some(slice) => slice.to_vec(),
+      None => data.iter().copied().collect(),
+    };
+    let is_int = !matches!(T::BITPIX, Bitpix::Spf | Bitpix::Dpf);
+    let chunks: Vec<ImageStats> = pixels
+      .par_chunks(CHUNK_LEN)
+      .map(|chunk| match is_int {
+        true => Self::of_ints(chunk.iter().map(|px| px.to_i64().unwrap())),
+        false => Self::of_floats(chunk.iter().map(|px| px.to_f64().unwrap_or(f64::NAN))),
+      })
+      .collect();
+    chunks.iter().fold(Self::default(), |stats, chunk| stats.merge(chunk))
+  }
+
+  fn of_ints(pixels: impl Iterator<Item = i64> + Clone) -> Self {
+    //(1) Exact sum, count and extremes
+    let (mut count, mut sum) = (0usize, 0i128);
+    let (mut min, mut max) = (i64::MAX, i64::MIN);
+    for px in pixels.clone() {
+      (count, sum) = (count + 1, sum + px as i128);
+      (min, max) = (min.min(px), max.max(px));
+    }
+    if count == 0 {
+      return Self::default();
+    }
+
+    //(2) Deviations from the exact mean (floor + fraction), in 128 bits
+    let (floor, fraction) = split_mean(sum, count);
+    let mut m2 = Compensated::default();
+    for px in pixels {
+      let deviation = (px as i128 - floor) as f64 - fraction;
+      m2.add(deviation * deviation);
+    }
+
+    ImageStats {
+      count,
+      n_nan: 0,
+      int_sum: Some(sum),
+      sum: sum as f64,
+      mean: floor as f64 + fraction,
+      m2: m2.total(),
+      min: min as f64,
+      max: max as f64,
+    }
+  }
+
+  fn of_floats(pixels: impl Iterator<Item = f64> + Clone) -> Self {
+    //(1) Compensated sum, skipping NaN
+    let (mut count, mut n_nan) = (0usize, 0usize);
+    let mut sum = Compensated::default();
+    let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+    for px in pixels.clone() {
+      if px.is_nan() {
+        n_nan += 1;
+        continue;
+      }
+      count += 1;
+      sum.add(px);
+      (min, max) = (min.min(px), max.max(px));
+    }
+    if count == 0 {
+      return ImageStats { n_nan, int_sum: None, ..Self::default() };
+    }
+
+    //(2) Second pass for the squared deviations
+    let mean = sum.total() / count as f64;
+    let mut m2 = Compensated::default();
+    for px in pixels.filter(|px| !px.is_nan()) {
+      m2.add((px - mean) * (px - mean));
+    }
+
+    ImageStats { count, n_nan, int_sum: None, sum: sum.total(), mean, m2: m2.total(), min, max }
+  }
+
+  pub fn merge(&self, other: &ImageStats) -> ImageStats {
+    /*  Statistics of the pixels of both images together. The variance is
+        combined with the formula of Chan et al. The sum of two integer images
+        stays exact, a float image makes the merged sum a float sum.
+    */
+    if other.count == 0 || self.count == 0 {
+      let (base, empty) = if self.count == 0 { (other, self) } else { (self, other) };
+      let int_sum = base.int_sum.zip(empty.int_sum).map(|(sum, _)| sum);
+      return ImageStats { n_nan: self.n_nan + other.n_nan, int_sum, ..*base };
+    }
+    let count = self.count + other.count;
+    let int_sum = self.int_sum.zip(other.int_sum).map(|(a, b)| a + b);
+    let sum = match int_sum {
+      Some(int_sum) => int_sum as f64,
+      None => Compensated { sum: self.sum, compensation: 0.0 }
+        .merge(Compensated { sum: other.sum, compensation: 0.0 })
+        .total(),
+    };
+    let mean = match int_sum {
+      Some(int_sum) => {
+        let (floor, fraction) = split_mean(int_sum, count);
+        floor as f64 + fraction
+      }
+      None => sum / count as f64,
+    };
+    //For integer images the difference of the means is taken in 128 bits
+    let delta = match (self.int_sum, other.int_sum) {
+      (Some(a), Some(b)) => {
+        let ((floor_a, fraction_a), (floor_b, fraction_b)) =
+          (split_mean(a, self.count), split_mean(b, other.count));
+        (floor_b - floor_a) as f64 + (fraction_b - fraction_a)
+      }
+      _ => other.mean - self.mean,
+    };
+    let weight = self.count as f64 * other.count as f64 / count as f64;
+
+    ImageStats {
+      count,
+      n_nan: self.n_nan + other.n_nan,
+      int_sum,
+      sum,
+      mean,
+      m2: self.m2 + other.m2 + delta * delta * weight,
+      min: self.min.min(other.min),
+      max: self.max.max(other.max),
+    }
+  }
+
+  pub fn get_count(&self) -> usize {
+    //Number of pixels that were counted (NaN pixels are not)
+    self.count
+  }
+  pub fn get_n_nan(&self) -> usize {
+    self.n_nan
+  }
+  pub fn get_int_sum(&self) -> Option<i128> {
+    //Exact sum, only for (merged) integer images
+    self.int_sum
+  }
+  pub fn get_sum(&self) -> f64 {
+    self.sum
+  }
+  pub fn get_mean(&self) -> f64 {
+    //NaN without pixels
+    self.mean
+  }
+  pub fn get_variance(&self) -> f64 {
+    //Population variance (divided by the count), NaN without pixels
+    match self.count {
+      0 => f64::NAN,
+      count => self.m2 / count as f64,
+    }
+  }
+  pub fn get_std(&self) -> f64 {
+    self.get_variance().sqrt()
+  }
+  pub fn get_min(&self) -> f64 {
+    self.min
+  }
+  pub fn get_max(&self) -> f64 {
+    self.max
+  }
+}
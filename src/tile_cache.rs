@@ -0,0 +1,158 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    The TileCache keeps recently used parts (tiles) of data units in memory,
+    such that reading many (overlapping) cutouts from a FitsIndex does not hit
+    the disk every time. A tile is a fixed number of consecutive FITS blocks of
+    a single data unit. When the cache is full, the least recently used tiles
+    are dropped first.
+*/
+
+use std::{borrow::Cow, error::Error};
+
+use indexmap::IndexMap;
+
+use crate::raw::raw_io::RawFitsReader;
+
+const BLOCK_SIZE: usize = crate::BLOCK_SIZE;
+//Default number of FITS blocks in a tile (= 46kB)
+const DEFAULT_TILE_BLOCKS: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct TileCache {
+  max_bytes: usize,
+  tile_blocks: usize,
+  used_bytes: usize,
+  //Tiles are keyed by (hdu index, tile index). Least recently used comes first
+  tiles: IndexMap<(usize, usize), Vec<u8>>,
+  hits: usize,
+  misses: usize,
+}
+
+impl TileCache {
+  /*
+      PUBLIC API
+  */
+
+  pub fn new(max_bytes: usize) -> Self {
+    TileCache {
+      max_bytes,
+      tile_blocks: DEFAULT_TILE_BLOCKS,
+      used_bytes: 0,
+      tiles: IndexMap::new(),
+      hits: 0,
+      misses: 0,
+    }
+  }
+
+  pub fn with_tile_blocks(mut self, tile_blocks: usize) -> Self {
+    //Changing the tile size invalidates all cached tiles
+    self.clear();
+    self.tile_blocks = tile_blocks.max(1);
+    self
+  }
+
+  pub fn clear(&mut self) {
+    self.tiles.clear();
+    self.used_bytes = 0;
+  }
+
+  pub fn get_max_bytes(&self) -> usize {
+    self.max_bytes
+  }
+  pub fn get_used_bytes(&self) -> usize {
+    self.used_bytes
+  }
+  pub fn get_num_tiles(&self) -> usize {
+    self.tiles.len()
+  }
+  pub fn get_hits(&self) -> usize {
+    self.hits
+  }
+  pub fn get_misses(&self) -> usize {
+    self.misses
+  }
+
+  /*
+      INTERNAL FUNCS
+  */
+
+  pub(crate) fn read_bytes(
+    &mut self,
+    reader: &mut RawFitsReader,
+    hdu: usize,
+    (data_start, data_blocks): (usize, usize),
+    offset: u64,
+    len: usize,
+  ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    //Copies len bytes, starting at offset (in bytes) into the data unit
+    let tile_bytes = (self.tile_blocks * BLOCK_SIZE) as u64;
+    let mut out = Vec::with_capacity(len);
+    let end = offset + len as u64;
+    let mut pos = offset;
+    while pos < end {
+      let (tile, tile_offset) = ((pos / tile_bytes) as usize, (pos % tile_bytes) as usize);
+      let n_copy = (tile_bytes as usize - tile_offset).min((end - pos) as usize);
+      let data = self.get_tile(reader, hdu, (data_start, data_blocks), tile)?;
+      out.extend_from_slice(&data[tile_offset..tile_offset + n_copy]);
+      pos += n_copy as u64;
+    }
+    Ok(out)
+  }
+
+  fn get_tile(
+    &mut self,
+    reader: &mut RawFitsReader,
+    hdu: usize,
+    (data_start, data_blocks): (usize, usize),
+    tile: usize,
+  ) -> Result<Cow<'_, [u8]>, Box<dyn Error + Send + Sync>> {
+    //(1) Cache hits are moved to the back of the queue
+    if let Some(data) = self.tiles.shift_remove(&(hdu, tile)) {
+      self.hits += 1;
+      self.tiles.insert((hdu, tile), data);
+      return Ok(Cow::Borrowed(self.tiles.last().unwrap().1));
+    }
+
+    //(2) Cache misses are read from disk (the last tile may be shorter)
+    self.misses += 1;
+    let first_block = tile * self.tile_blocks;
+    let n_blocks = self.tile_blocks.min(data_blocks - first_block);
+    let mut data = vec![0u8; n_blocks * BLOCK_SIZE];
+    reader.seek_block(data_start + first_block)?;
+    reader.read_blocks(&mut data)?;
+
+    //(3) Tiles that are larger than the whole cache are not cached
+    if data.len() > self.max_bytes {
+      return Ok(Cow::Owned(data));
+    }
+
+    //(4) Make room for the new tile by dropping the least recently used ones
+    while self.used_bytes + data.len() > self.max_bytes {
+      match self.tiles.shift_remove_index(0) {
+        Some((_, old)) => self.used_bytes -= old.len(),
+        None => break,
+      }
+    }
+    self.used_bytes += data.len();
+    self.tiles.insert((hdu, tile), data);
+    Ok(Cow::Borrowed(self.tiles.last().unwrap().1))
+  }
+}
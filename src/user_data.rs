@@ -0,0 +1,101 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{
+  any::{type_name, Any, TypeId},
+  collections::HashMap,
+  fmt::{self, Debug},
+};
+
+use dyn_clone::{clone_trait_object, DynClone};
+
+/*  User data attached to in-memory HDU's
+    Pipeline stages can attach arbitrary values (masks, WCS objects, QC
+    results...) to an HDU. Values are keyed by their type, so an HDU holds at
+    most one value of each type. User data only lives in memory: it is never
+    written to a FITS file and is not read back either.
+*/
+
+trait UserValue: Any + DynClone {
+  fn as_any(&self) -> &dyn Any;
+  fn as_any_mut(&mut self) -> &mut dyn Any;
+  fn into_any(self: Box<Self>) -> Box<dyn Any>;
+  fn type_name(&self) -> &'static str;
+}
+clone_trait_object!(UserValue);
+
+impl<T: Any + Clone> UserValue for T {
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+  fn into_any(self: Box<Self>) -> Box<dyn Any> {
+    self
+  }
+  fn type_name(&self) -> &'static str {
+    type_name::<T>()
+  }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct UserData {
+  values: HashMap<TypeId, Box<dyn UserValue>>,
+}
+
+impl UserData {
+  pub(crate) fn insert<T: Any + Clone>(&mut self, value: T) -> Option<T> {
+    let old = self.values.insert(TypeId::of::<T>(), Box::new(value))?;
+    old.into_any().downcast().ok().map(|old| *old)
+  }
+
+  pub(crate) fn get<T: Any>(&self) -> Option<&T> {
+    /*  Careful: Box<dyn UserValue> is a UserValue itself, so we have to call
+        as_any() on the boxed value, not on the box.
+    */
+    let value: &dyn UserValue = self.values.get(&TypeId::of::<T>())?.as_ref();
+    value.as_any().downcast_ref()
+  }
+
+  pub(crate) fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+    let value: &mut dyn UserValue = self.values.get_mut(&TypeId::of::<T>())?.as_mut();
+    value.as_any_mut().downcast_mut()
+  }
+
+  pub(crate) fn remove<T: Any>(&mut self) -> Option<T> {
+    let old = self.values.remove(&TypeId::of::<T>())?;
+    old.into_any().downcast().ok().map(|old| *old)
+  }
+
+  pub(crate) fn clear(&mut self) {
+    self.values.clear()
+  }
+
+  pub(crate) fn len(&self) -> usize {
+    self.values.len()
+  }
+}
+
+impl Debug for UserData {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    //User types need not implement Debug, so we only print their names
+    f.debug_set().entries(self.values.values().map(|value| value.as_ref().type_name())).finish()
+  }
+}
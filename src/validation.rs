@@ -0,0 +1,236 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Validation profiles contain rules for the physical range of keyword values
+    (e.g. EXPTIME >= 0). They are checked when reading or writing a FITS file
+    and produce diagnostics for every keyword that violates a rule.
+*/
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::header::Header;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+  Warning,
+  Error,
+}
+
+#[derive(Debug, Clone)]
+struct RangeRule {
+  //Bounds are stored as (value, inclusive)
+  keyword: String,
+  min: Option<(f64, bool)>,
+  max: Option<(f64, bool)>,
+  severity: Severity,
+}
+
+impl RangeRule {
+  fn check(&self, value: f64) -> bool {
+    let above_min = match self.min {
+      None => true,
+      Some((min, true)) => value >= min,
+      Some((min, false)) => value > min,
+    };
+    let below_max = match self.max {
+      None => true,
+      Some((max, true)) => value <= max,
+      Some((max, false)) => value < max,
+    };
+    above_min && below_max
+  }
+
+  fn pretty_print_range(&self) -> String {
+    let lower = match self.min {
+      None => String::from("(-inf"),
+      Some((min, true)) => format!("[{min}"),
+      Some((min, false)) => format!("({min}"),
+    };
+    let upper = match self.max {
+      None => String::from("inf)"),
+      Some((max, true)) => format!("{max}]"),
+      Some((max, false)) => format!("{max})"),
+    };
+    format!("{lower},{upper}")
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct ValidationProfile {
+  name: String,
+  rules: Vec<RangeRule>,
+}
+
+impl ValidationProfile {
+  /*
+      PUBLIC API
+  */
+
+  pub fn new(name: &str) -> Self {
+    ValidationProfile { name: name.to_string(), rules: Vec::new() }
+  }
+
+  pub fn at_least(self, keyword: &str, min: f64, severity: Severity) -> Self {
+    self.with_rule(keyword, Some((min, true)), None, severity)
+  }
+
+  pub fn at_most(self, keyword: &str, max: f64, severity: Severity) -> Self {
+    self.with_rule(keyword, None, Some((max, true)), severity)
+  }
+
+  pub fn in_range(self, keyword: &str, min: f64, max: f64, severity: Severity) -> Self {
+    //half-open interval [min, max), which is what angles usually need
+    self.with_rule(keyword, Some((min, true)), Some((max, false)), severity)
+  }
+
+  pub fn in_closed_range(self, keyword: &str, min: f64, max: f64, severity: Severity) -> Self {
+    self.with_rule(keyword, Some((min, true)), Some((max, true)), severity)
+  }
+
+  pub fn standard() -> Self {
+    //Built-in profile for commonly used keywords. These are only warnings,
+    //since plenty of files in the wild violate them.
+    use Severity::*;
+    Self::new("standard")
+      .at_least("EXPTIME", 0.0, Warning)
+      .at_least("EXPOSURE", 0.0, Warning)
+      .in_range("RA", 0.0, 360.0, Warning)
+      .in_range("RA_OBJ", 0.0, 360.0, Warning)
+      .in_closed_range("DEC", -90.0, 90.0, Warning)
+      .in_closed_range("DEC_OBJ", -90.0, 90.0, Warning)
+      .at_least("AIRMASS", 1.0, Warning)
+      .at_least("EQUINOX", 0.0, Warning)
+      .at_least("EPOCH", 0.0, Warning)
+      .at_least("MJD-OBS", 0.0, Warning)
+      .at_least("EXTVER", 1.0, Warning)
+      .at_least("EXTLEVEL", 1.0, Warning)
+  }
+
+  pub fn get_name(&self) -> &str {
+    &self.name
+  }
+
+  pub fn validate(&self, hdu_index: usize, header: &Header) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for rule in &self.rules {
+      //Keywords that are not present are not checked
+      let raw = match header.get_value(&rule.keyword) {
+        None => continue,
+        Some(raw) => raw,
+      };
+
+      //FITS allows D as exponent for double precision reals
+      let message = match raw.trim().replace('D', "E").parse::<f64>() {
+        Ok(value) if rule.check(value) => continue,
+        Ok(_) => format!("value is outside of the allowed range {}", rule.pretty_print_range()),
+        Err(_) => String::from("value is not a number"),
+      };
+
+      diagnostics.push(Diagnostic {
+        profile: self.name.clone(),
+        hdu: hdu_index,
+        keyword: rule.keyword.clone(),
+        value: raw.clone(),
+        message,
+        severity: rule.severity,
+      });
+    }
+    diagnostics
+  }
+
+  /*
+      INTERNAL FUNCS
+  */
+
+  fn with_rule(
+    mut self,
+    keyword: &str,
+    min: Option<(f64, bool)>,
+    max: Option<(f64, bool)>,
+    severity: Severity,
+  ) -> Self {
+    self.rules.push(RangeRule { keyword: keyword.to_string(), min, max, severity });
+    self
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+  profile: String,
+  hdu: usize,
+  keyword: String,
+  value: String,
+  message: String,
+  severity: Severity,
+}
+
+impl Diagnostic {
+  pub(crate) fn new(
+    profile: &str,
+    hdu: usize,
+    keyword: &str,
+    value: &str,
+    message: String,
+    severity: Severity,
+  ) -> Self {
+    Diagnostic {
+      profile: profile.to_string(),
+      hdu,
+      keyword: keyword.to_string(),
+      value: value.to_string(),
+      message,
+      severity,
+    }
+  }
+
+  pub(crate) fn with_hdu(mut self, hdu: usize) -> Self {
+    self.hdu = hdu;
+    self
+  }
+
+  pub fn get_profile(&self) -> &str {
+    &self.profile
+  }
+  pub fn get_hdu(&self) -> usize {
+    self.hdu
+  }
+  pub fn get_keyword(&self) -> &str {
+    &self.keyword
+  }
+  pub fn get_value(&self) -> &str {
+    &self.value
+  }
+  pub fn get_message(&self) -> &str {
+    &self.message
+  }
+  pub fn get_severity(&self) -> Severity {
+    self.severity
+  }
+}
+
+impl Display for Diagnostic {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "[{:?}] ({}) HDU #{}, {} = {}: {}",
+      self.severity, self.profile, self.hdu, self.keyword, self.value, self.message
+    )
+  }
+}
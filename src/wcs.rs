@@ -0,0 +1,453 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Flipping, transposing or collapsing an image changes the meaning of its
+    pixel indices, so the (primary) WCS keywords in its header have to change
+    along with it for the world coordinates of every pixel to stay the same
+    (for the axes that are left). These funcs update the header of an image,
+    the image itself is changed elsewhere.
+
+    Per-axis keywords are referred to by their FITS axis number (1-based),
+    the funcs take the 0-based ndarray axis index. Alternate WCS descriptions
+    (keywords with a trailing letter) are not updated.
+*/
+
+use std::error::Error;
+
+use ndarray::{Array2, ShapeBuilder};
+use rayon::prelude::*;
+
+use crate::{
+  hdu_err::{InvalidRecordValueError, MissingRecordError},
+  header::Header,
+  img_err::SkyPositionErr,
+  keyword_value::{format_float, unquote},
+  pixel_coords,
+};
+
+//Keywords with a single axis number (e.g. CTYPE1)
+const AXIS_KEYWORDS: [&str; 10] =
+  ["NAXIS", "CTYPE", "CUNIT", "CRVAL", "CDELT", "CRPIX", "CROTA", "CNAME", "CRDER", "CSYER"];
+//Keywords with two axis numbers (e.g. PC1_2), where the second one is the pixel axis
+const MATRIX_KEYWORDS: [&str; 2] = ["PC", "CD"];
+
+fn parse_axes(keyword: &str) -> Option<(&'static str, usize, Option<usize>)> {
+  //Splits a keyword like CDELT2 or PC1_2 into its prefix and axis numbers
+  for prefix in AXIS_KEYWORDS {
+    if let Some(axis) = keyword.strip_prefix(prefix).and_then(|rest| rest.parse().ok()) {
+      return Some((prefix, axis, None));
+    }
+  }
+  for prefix in MATRIX_KEYWORDS {
+    let Some((i, j)) = keyword.strip_prefix(prefix).and_then(|rest| rest.split_once('_')) else {
+      continue;
+    };
+    if let (Ok(i), Ok(j)) = (i.parse(), j.parse()) {
+      return Some((prefix, i, Some(j)));
+    }
+  }
+  None
+}
+
+fn has_wcs(header: &Header) -> bool {
+  header.keywords().filter_map(parse_axes).any(|(prefix, _, _)| prefix != "NAXIS")
+}
+
+fn parse_float(raw: &str) -> Option<f64> {
+  //FITS allows D as exponent for double precision reals
+  raw.trim().replace('D', "E").parse().ok()
+}
+
+fn negate(raw: &str) -> String {
+  //Done on the text, such that the precision of the value is kept
+  let raw = raw.trim();
+  match raw.strip_prefix('-') {
+    Some(positive) => positive.to_string(),
+    None => format!("-{}", raw.trim_start_matches('+')),
+  }
+}
+
+pub(crate) fn flip_axis(header: &mut Header, axis: usize, len: usize) {
+  if !has_wcs(header) {
+    return;
+  }
+  let n = axis + 1;
+
+  //(1) The reference pixel is mirrored (p' = NAXISn + 1 - p)
+  let crpix = header.get_value(&format!("CRPIX{n}")).and_then(|raw| parse_float(raw));
+  let crpix = pixel_coords::mirror_fits(crpix.unwrap_or(0.0), len);
+  let comment = header.get_comment(&format!("CRPIX{n}")).cloned();
+  header.put_record(&format!("CRPIX{n}"), format_float(crpix), comment);
+
+  /*  (2)
+      The pixel offsets along the axis change sign, which is undone by
+      negating column n of the CD or PC matrix. Without a matrix (the PC
+      matrix defaults to the identity) negating CDELTn does the same.
+  */
+  let matrix = MATRIX_KEYWORDS
+    .into_iter()
+    .find(|prefix| header.keywords().filter_map(parse_axes).any(|(found, _, _)| found == *prefix));
+  let column: Vec<String> = header
+    .keywords()
+    .filter(|keyword| match parse_axes(keyword) {
+      Some((prefix, _, Some(j))) => Some(prefix) == matrix && j == n,
+      _ => false,
+    })
+    .map(String::from)
+    .collect();
+
+  match matrix {
+    None => {
+      let cdelt = header.get_value(&format!("CDELT{n}")).cloned().unwrap_or(String::from("1.0"));
+      let comment = header.get_comment(&format!("CDELT{n}")).cloned();
+      header.put_record(&format!("CDELT{n}"), negate(&cdelt), comment);
+    }
+    Some(prefix) => {
+      for keyword in &column {
+        let value = negate(header.get_value(keyword).unwrap());
+        header.put_record(keyword, value, header.get_comment(keyword).cloned());
+      }
+      //Missing diagonal elements of the PC matrix are 1 (CD defaults to 0)
+      let diagonal = format!("{prefix}{n}_{n}");
+      if prefix == "PC" && !column.contains(&diagonal) {
+        header.put_record(&diagonal, String::from("-1.0"), None);
+      }
+    }
+  }
+}
+
+pub(crate) fn permute_axes(header: &mut Header, axes: &[usize]) {
+  /*  New axis k is old axis axes[k]. All per-axis keywords (including NAXISn)
+      are renumbered along with the axes, and both indices of the PC and CD
+      matrices are permuted. This keeps diagonal matrices diagonal.
+  */
+  let new_number = |old: usize| axes.iter().position(|&axis| axis + 1 == old).map(|k| k + 1);
+
+  //(1) Collect the renumbered records, skipping axes that do not exist
+  let mut renamed = Vec::new();
+  for keyword in header.keywords() {
+    let new_keyword = match parse_axes(keyword) {
+      Some((prefix, i, None)) => new_number(i).map(|i| format!("{prefix}{i}")),
+      Some((prefix, i, Some(j))) => match (new_number(i), new_number(j)) {
+        (Some(i), Some(j)) => Some(format!("{prefix}{i}_{j}")),
+        _ => None,
+      },
+      None => None,
+    };
+    if let Some(new_keyword) = new_keyword {
+      let record = header.get_record(keyword).unwrap();
+      renamed.push((
+        keyword.to_string(),
+        new_keyword,
+        record.value.clone(),
+        record.comment.clone(),
+      ));
+    }
+  }
+
+  //(2) Records keep their position in the header, but get their new value
+  for (old_keyword, ..) in &renamed {
+    if !renamed.iter().any(|(_, new_keyword, ..)| new_keyword == old_keyword) {
+      header.remove_record(old_keyword);
+    }
+  }
+  for (_, new_keyword, value, comment) in renamed {
+    header.put_record(&new_keyword, value.unwrap_or_default(), comment);
+  }
+}
+
+pub(crate) fn remove_axis(header: &mut Header, axis: usize) {
+  /*  Used when an image is collapsed along an axis. The per-axis keywords of
+      the axis are removed (including its row and column of the PC and CD
+      matrices), and the axes after it move down by one.
+  */
+  let n = axis + 1;
+  let shift = |i: usize| if i > n { i - 1 } else { i };
+
+  //(1) Remove the keywords of the axis itself
+  let removed: Vec<String> = header
+    .keywords()
+    .filter(|keyword| match parse_axes(keyword) {
+      Some((_, i, j)) => i == n || j == Some(n),
+      None => false,
+    })
+    .map(String::from)
+    .collect();
+  for keyword in &removed {
+    header.remove_record(keyword);
+  }
+
+  /*  (2)
+      Renumber the keywords of the later axes. Going from low to high axis
+      numbers, the new keyword never exists yet: it was either removed above
+      or renamed already.
+  */
+  let mut renamed: Vec<(usize, usize, String, String)> = Vec::new();
+  for keyword in header.keywords() {
+    match parse_axes(keyword) {
+      Some((prefix, i, None)) if i > n => {
+        renamed.push((i, 0, keyword.to_string(), format!("{prefix}{}", shift(i))))
+      }
+      Some((prefix, i, Some(j))) if i > n || j > n => {
+        renamed.push((i, j, keyword.to_string(), format!("{prefix}{}_{}", shift(i), shift(j))))
+      }
+      _ => {}
+    }
+  }
+  renamed.sort();
+  for (_, _, keyword, new_keyword) in renamed {
+    header.rename_record(&keyword, &new_keyword);
+  }
+
+  //(3) The number of WCS axes goes down as well
+  if let Some(wcsaxes) =
+    header.get_value("WCSAXES").and_then(|raw| raw.trim().parse::<usize>().ok())
+  {
+    if wcsaxes >= n {
+      let comment = header.get_comment("WCSAXES").cloned();
+      header.put_record("WCSAXES", (wcsaxes - 1).to_string(), comment);
+    }
+  }
+}
+
+/*
+    Celestial WCS of an image, for converting between sky positions and
+    pixels. Only the zenithal TAN (gnomonic) and SIN (orthographic)
+    projections are supported, with the celestial axes as the first two axes
+    of the image (in either order) and the default native pole. The linear
+    transformation is given by either the CD matrix, or the PC matrix and
+    CDELTn (or the older CROTA2).
+
+    Pixels are given as FITS pixel coordinates (see pixel_coords), sky
+    positions as (longitude, latitude) in degrees, whatever the axis order.
+*/
+
+const PROJECTIONS: [&str; 2] = ["TAN", "SIN"];
+
+#[derive(Debug, Clone)]
+pub struct CelestialWcs {
+  crpix: [f64; 2],
+  crval: [f64; 2], //longitude, latitude
+  cd: [[f64; 2]; 2],
+  projection: &'static str,
+  lat_first: bool, //the first axis is the latitude (e.g. DEC--TAN)
+}
+
+impl CelestialWcs {
+  pub fn from_header(header: &Header) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    //(1) The first two axes have to be a celestial pair with the same projection
+    let ctype = |n: usize| -> Result<String, Box<dyn Error + Send + Sync>> {
+      let raw = header.get_value(&format!("CTYPE{n}"));
+      let raw = raw.ok_or_else(|| MissingRecordError::new(&format!("CTYPE{n}")))?;
+      Ok(unquote(raw).unwrap_or(raw.clone()).trim().to_uppercase())
+    };
+    let (ctype1, ctype2) = (ctype(1)?, ctype(2)?);
+    let is_lng = |ctype: &str| ctype.starts_with("RA--") || ctype.get(1..4) == Some("LON");
+    let is_lat = |ctype: &str| ctype.starts_with("DEC-") || ctype.get(1..4) == Some("LAT");
+    let lat_first = is_lat(&ctype1) && is_lng(&ctype2);
+    let code = ctype1.get(5..8).unwrap_or_default();
+    let projection = PROJECTIONS.into_iter().find(|projection| *projection == code);
+    let (Some(projection), true) = (projection, lat_first || is_lng(&ctype1) && is_lat(&ctype2))
+    else {
+      return Err(Box::new(InvalidRecordValueError::new("CTYPE1", &ctype1, &PROJECTIONS)));
+    };
+    if ctype2.get(5..8) != Some(code) {
+      return Err(Box::new(InvalidRecordValueError::new("CTYPE2", &ctype2, &PROJECTIONS)));
+    }
+
+    //(2) Reference pixel and value
+    let float =
+      |keyword: String, default: Option<f64>| -> Result<f64, Box<dyn Error + Send + Sync>> {
+        match (header.get_value(&keyword).and_then(|raw| parse_float(raw)), default) {
+          (Some(value), _) | (None, Some(value)) => Ok(value),
+          (None, None) => Err(Box::new(MissingRecordError::new(&keyword))),
+        }
+      };
+    let crpix = [float(String::from("CRPIX1"), None)?, float(String::from("CRPIX2"), None)?];
+    let crval = [float(String::from("CRVAL1"), None)?, float(String::from("CRVAL2"), None)?];
+
+    //(3) The linear transformation from pixel offsets to intermediate coordinates
+    let has = |prefix: &str| {
+      (1..=2).any(|i| (1..=2).any(|j| header.get_value(&format!("{prefix}{i}_{j}")).is_some()))
+    };
+    let mut cd = [[0.0; 2]; 2];
+    if has("CD") {
+      for (i, j) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+        cd[i][j] = float(format!("CD{}_{}", i + 1, j + 1), Some(0.0))?;
+      }
+    } else {
+      let cdelt =
+        [float(String::from("CDELT1"), Some(1.0))?, float(String::from("CDELT2"), Some(1.0))?];
+      let rho = float(String::from("CROTA2"), Some(0.0))?.to_radians();
+      let pc = match has("PC") {
+        true => {
+          let pc = |i: usize, j: usize| float(format!("PC{i}_{j}"), Some((i == j) as u8 as f64));
+          [[pc(1, 1)?, pc(1, 2)?], [pc(2, 1)?, pc(2, 2)?]]
+        }
+        false => [
+          [rho.cos(), -rho.sin() * cdelt[1] / cdelt[0]],
+          [rho.sin() * cdelt[0] / cdelt[1], rho.cos()],
+        ],
+      };
+      for (i, j) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+        cd[i][j] = cdelt[i] * pc[i][j];
+      }
+    }
+
+    //(R) longitude first, like the sky positions we are given
+    let crval = if lat_first { [crval[1], crval[0]] } else { crval };
+    Ok(CelestialWcs { crpix, crval, cd, projection, lat_first })
+  }
+
+  pub fn world_to_pixel(&self, lng: f64, lat: f64) -> Option<[f64; 2]> {
+    //FITS pixel coordinates (see pixel_coords) of a sky position in degrees
+    let lat0 = self.crval[1].to_radians();
+    let (dlng, lat) = ((lng - self.crval[0]).to_radians(), lat.to_radians());
+
+    //(1) Project onto the plane tangent to the reference point
+    let cos_c = lat0.sin() * lat.sin() + lat0.cos() * lat.cos() * dlng.cos();
+    if cos_c <= 0.0 {
+      return None; //the far side of the sky is not projected
+    }
+    let xi = lat.cos() * dlng.sin();
+    let eta = lat0.cos() * lat.sin() - lat0.sin() * lat.cos() * dlng.cos();
+    let scale = if self.projection == "TAN" { 1.0 / cos_c } else { 1.0 };
+    let (x, y) = (xi * scale, eta * scale);
+    let (x, y) = match self.lat_first {
+      true => (y.to_degrees(), x.to_degrees()),
+      false => (x.to_degrees(), y.to_degrees()),
+    };
+
+    //(2) Invert the linear transformation
+    let [[a, b], [c, d]] = self.cd;
+    let det = a * d - b * c;
+    if det == 0.0 {
+      return None;
+    }
+    Some([self.crpix[0] + (d * x - b * y) / det, self.crpix[1] + (a * y - c * x) / det])
+  }
+
+  pub fn pixel_to_world(&self, x: f64, y: f64) -> Option<[f64; 2]> {
+    //Sky position in degrees of FITS pixel coordinates, the inverse of world_to_pixel
+    //(1) Apply the linear transformation
+    let (dx, dy) = (x - self.crpix[0], y - self.crpix[1]);
+    let [[a, b], [c, d]] = self.cd;
+    let (x, y) = (a * dx + b * dy, c * dx + d * dy);
+    let (xi, eta) = match self.lat_first {
+      true => (y.to_radians(), x.to_radians()),
+      false => (x.to_radians(), y.to_radians()),
+    };
+
+    //(2) Deproject from the plane tangent to the reference point
+    let (lng0, lat0) = (self.crval[0], self.crval[1].to_radians());
+    let rho = xi.hypot(eta);
+    if rho == 0.0 {
+      return Some(self.crval);
+    }
+    let c = match self.projection {
+      "TAN" => rho.atan(),
+      _ if rho <= 1.0 => rho.asin(),
+      _ => return None, //outside of the projected hemisphere
+    };
+    let lat = (c.cos() * lat0.sin() + eta * c.sin() * lat0.cos() / rho).clamp(-1.0, 1.0).asin();
+    let dlng = (xi * c.sin()).atan2(rho * lat0.cos() * c.cos() - eta * lat0.sin() * c.sin());
+    Some([(lng0 + dlng.to_degrees()).rem_euclid(360.0), lat.to_degrees()])
+  }
+
+  pub fn pixel_grid(&self, shape: [usize; 2]) -> (Array2<f64>, Array2<f64>) {
+    /*  Longitude and latitude (in degrees) of the centre of every pixel of an
+        image with the given shape, in the same (Fortran) layout as the image.
+        Pixels that do not correspond to a sky position are NaN. The columns
+        of the grid are evaluated in parallel
+    */
+    let [n1, n2] = shape;
+    let (mut lng, mut lat) = (vec![f64::NAN; n1 * n2], vec![f64::NAN; n1 * n2]);
+    lng.par_chunks_mut(n1.max(1)).zip(lat.par_chunks_mut(n1.max(1))).enumerate().for_each(
+      |(j, (lng, lat))| {
+        for i in 0..lng.len() {
+          let pixel =
+            (pixel_coords::index_to_fits(i as f64), pixel_coords::index_to_fits(j as f64));
+          if let Some([x, y]) = self.pixel_to_world(pixel.0, pixel.1) {
+            (lng[i], lat[i]) = (x, y);
+          }
+        }
+      },
+    );
+
+    //(R) shape is correct by construction
+    (
+      Array2::from_shape_vec(shape.f(), lng).unwrap(),
+      Array2::from_shape_vec(shape.f(), lat).unwrap(),
+    )
+  }
+
+  pub fn pixel_scale(&self) -> [f64; 2] {
+    //Size of a pixel along both axes in degrees (near the reference pixel)
+    let column = |j: usize| (self.cd[0][j].powi(2) + self.cd[1][j].powi(2)).sqrt();
+    [column(0), column(1)]
+  }
+}
+
+pub(crate) fn sky_box(
+  header: &Header,
+  axes: &[usize],
+  ra: f64,
+  dec: f64,
+  size_arcmin: f64,
+) -> Result<(Vec<usize>, Vec<usize>), Box<dyn Error + Send + Sync>> {
+  /*  Start and shape of the box of size_arcmin x size_arcmin centered on the
+      sky position, clipped to the image. Axes beyond the celestial ones (e.g.
+      the spectral axis of a cube) are kept whole.
+  */
+  let wcs = CelestialWcs::from_header(header)?;
+  let center = wcs.world_to_pixel(ra, dec).ok_or_else(|| SkyPositionErr::new(ra, dec))?;
+  let half_size = size_arcmin / 60.0 / 2.0;
+
+  let (mut start, mut shape) = (Vec::new(), Vec::new());
+  for (axis, &len) in axes.iter().enumerate() {
+    let (first, end) = match axis {
+      0 | 1 => {
+        let center = pixel_coords::fits_to_index(center[axis]);
+        let half = half_size / wcs.pixel_scale()[axis];
+        let first = (center - half + 0.5).floor().clamp(0.0, len as f64) as usize;
+        let end = ((center + half + 0.5).floor() + 1.0).clamp(0.0, len as f64) as usize;
+        (first, end)
+      }
+      _ => (0, len),
+    };
+    if first >= end {
+      return Err(Box::new(SkyPositionErr::new(ra, dec)));
+    }
+    start.push(first);
+    shape.push(end - first);
+  }
+  Ok((start, shape))
+}
+
+pub(crate) fn shift_origin(header: &mut Header, start: &[usize]) {
+  //Moves the reference pixel along with the origin of a cutout
+  for (axis, &offset) in start.iter().enumerate() {
+    let keyword = format!("CRPIX{}", axis + 1);
+    if let Some(crpix) = header.get_value(&keyword).and_then(|raw| parse_float(raw)) {
+      let comment = header.get_comment(&keyword).cloned();
+      header.put_record(&keyword, format_float(crpix - offset as f64), comment);
+    }
+  }
+}
@@ -0,0 +1,249 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::{
+  charset::CharsetPolicy,
+  header::Header,
+  raw::raw_io::LockPolicy,
+  validation::{Diagnostic, Severity},
+};
+
+/*
+    WriteOptions control how a FITS file is written to disk. They are passed
+    to Fits::write_with. Fits::write uses the default options.
+*/
+
+//Default capacity of the buffered writer
+const DEFAULT_BUFFER_SIZE: usize = 64 * 2880; // = 184kB
+const DEFAULT_ENCODE_MEMORY: usize = 256 * 1024 * 1024; // = 256MB
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FitsStandard {
+  /*  Version of the FITS standard that written files have to conform to.
+      FITS 4.0 made the long string (CONTINUE) convention part of the standard,
+      so long string values are only written when targeting FITS 4.0. Note that
+      64-bit integers (BITPIX = 64) were already part of FITS 3.0.
+  */
+  Fits3,
+  #[default]
+  Fits4,
+}
+
+impl FitsStandard {
+  pub(crate) fn check(&self, hdu_index: usize, header: &Header) -> Vec<Diagnostic> {
+    //Diagnostics for all constructs in the header that this version lacks
+    let mut diagnostics = Vec::new();
+    if *self == FitsStandard::Fits4 {
+      return diagnostics;
+    }
+
+    for keyword in header.keywords() {
+      let record = header.get_record(keyword).unwrap();
+      if record.needs_continuation() {
+        let value = header.get_value(keyword).unwrap();
+        diagnostics.push(Diagnostic::new(
+          &self.to_string(),
+          hdu_index,
+          keyword,
+          value,
+          String::from("long string values (CONTINUE records) require FITS 4.0"),
+          Severity::Error,
+        ));
+      }
+    }
+    diagnostics
+  }
+}
+
+impl Display for FitsStandard {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      FitsStandard::Fits3 => write!(f, "FITS 3.0"),
+      FitsStandard::Fits4 => write!(f, "FITS 4.0"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExtendPolicy {
+  /*  THIS ENUM IS PART OF THE USER-FACING API
+      Determines the EXTEND record of the primary HDU. Keep writes the header
+      as it is, the other variants force EXTEND = T, EXTEND = F or no EXTEND
+      record at all (which some legacy files rely on), regardless of whether
+      the file actually contains extensions.
+  */
+  #[default]
+  Keep,
+  True,
+  False,
+  Omit,
+}
+
+#[derive(Debug, Clone)]
+pub struct WriteOptions {
+  lock: LockPolicy,
+  atomic: bool,
+  fsync: bool,
+  preallocate: bool,
+  buffer_size: usize,
+  sparse: bool,
+  standard: FitsStandard,
+  header_charset: CharsetPolicy,
+  extend: ExtendPolicy,
+  zero_fill: bool,
+  encode_memory: usize,
+}
+
+impl Default for WriteOptions {
+  fn default() -> Self {
+    WriteOptions {
+      lock: LockPolicy::NoLock,
+      atomic: false,
+      fsync: false,
+      preallocate: false,
+      buffer_size: DEFAULT_BUFFER_SIZE,
+      sparse: false,
+      standard: FitsStandard::default(),
+      header_charset: CharsetPolicy::default(),
+      extend: ExtendPolicy::default(),
+      zero_fill: false,
+      encode_memory: DEFAULT_ENCODE_MEMORY,
+    }
+  }
+}
+
+impl WriteOptions {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn lock(mut self, policy: LockPolicy) -> Self {
+    self.lock = policy;
+    self
+  }
+
+  pub fn atomic(mut self, atomic: bool) -> Self {
+    /*  Atomic writes go to a temporary file in the same directory, which is
+        renamed to the target path once everything was written. If writing
+        fails, the target file is left untouched. Locked atomic writes lock a
+        hidden .{name}.lock file next to the target instead of the target.
+    */
+    self.atomic = atomic;
+    self
+  }
+
+  pub fn fsync(mut self, fsync: bool) -> Self {
+    //Sync the file (and, for atomic writes, its directory) before returning
+    self.fsync = fsync;
+    self
+  }
+
+  pub fn preallocate(mut self, preallocate: bool) -> Self {
+    //Reserve the (estimated) size of the file on disk before writing it
+    self.preallocate = preallocate;
+    self
+  }
+
+  pub fn buffer_size(mut self, bytes: usize) -> Self {
+    self.buffer_size = bytes;
+    self
+  }
+
+  pub fn sparse(mut self, sparse: bool) -> Self {
+    /*  In sparse mode, long runs of zeroed FITS blocks (e.g. empty images or
+        padding) are skipped instead of written, leaving holes in the file on
+        filesystems that support them. Reading the file gives the same bytes.
+    */
+    self.sparse = sparse;
+    self
+  }
+
+  pub fn standard(mut self, standard: FitsStandard) -> Self {
+    //Files are checked against this version of the standard before writing
+    self.standard = standard;
+    self
+  }
+
+  pub fn header_charset(mut self, policy: CharsetPolicy) -> Self {
+    //Headers with illegal characters are refused (Strict) or repaired
+    self.header_charset = policy;
+    self
+  }
+
+  pub fn extend(mut self, policy: ExtendPolicy) -> Self {
+    self.extend = policy;
+    self
+  }
+
+  pub fn zero_fill(mut self, zero_fill: bool) -> Self {
+    /*  Files that are rewritten in place and end up shorter (for example by
+        Fits::remove_hdu_in_place) are truncated. With zero_fill, the space
+        that is given up is overwritten with zeroes first, so that the old
+        contents do not linger in the freed blocks of the disk.
+    */
+    self.zero_fill = zero_fill;
+    self
+  }
+
+  pub fn encode_memory(mut self, bytes: usize) -> Self {
+    /*  The data units of consecutive HDU's are encoded in parallel, to
+        separate buffers holding at most this many bytes in total. Larger
+        data units are encoded directly to the file. Zero encodes everything
+        on the calling thread, without buffering.
+    */
+    self.encode_memory = bytes;
+    self
+  }
+
+  pub fn get_lock(&self) -> LockPolicy {
+    self.lock
+  }
+  pub fn get_atomic(&self) -> bool {
+    self.atomic
+  }
+  pub fn get_fsync(&self) -> bool {
+    self.fsync
+  }
+  pub fn get_preallocate(&self) -> bool {
+    self.preallocate
+  }
+  pub fn get_buffer_size(&self) -> usize {
+    self.buffer_size
+  }
+  pub fn get_sparse(&self) -> bool {
+    self.sparse
+  }
+  pub fn get_standard(&self) -> FitsStandard {
+    self.standard
+  }
+  pub fn get_header_charset(&self) -> CharsetPolicy {
+    self.header_charset
+  }
+  pub fn get_extend(&self) -> ExtendPolicy {
+    self.extend
+  }
+  pub fn get_zero_fill(&self) -> bool {
+    self.zero_fill
+  }
+  pub fn get_encode_memory(&self) -> usize {
+    self.encode_memory
+  }
+}
@@ -0,0 +1,222 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use ndarray::ArrayViewD;
+
+/*
+    Display scaling for images. zscale() implements the IRAF zscale algorithm
+    (as used by ds9 and astropy): a line is fitted to a sorted sample of the
+    pixel values, iteratively rejecting outliers, and the display range is
+    centered on the median with a width set by the slope of the line divided by
+    the contrast. percentile_limits() is the simpler alternative that clips a
+    fixed fraction of the pixels on both ends.
+
+    Non-finite pixels (NaN, inf) are ignored. Images without any finite pixels
+    get the limits (0.0, 0.0).
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZScale {
+  contrast: f64,
+  n_samples: usize,
+  max_reject: f64,
+  min_npixels: usize,
+  krej: f64,
+  max_iterations: usize,
+}
+
+impl Default for ZScale {
+  fn default() -> Self {
+    ZScale {
+      contrast: 0.25,
+      n_samples: 1000,
+      max_reject: 0.5,
+      min_npixels: 5,
+      krej: 2.5,
+      max_iterations: 5,
+    }
+  }
+}
+
+impl ZScale {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn contrast(mut self, contrast: f64) -> Self {
+    //Lower contrasts give wider display ranges. Zero or less disables scaling
+    //the slope, in which case the range is set by the fit alone
+    self.contrast = contrast;
+    self
+  }
+
+  pub fn n_samples(mut self, n_samples: usize) -> Self {
+    //Pixels are sampled at a regular stride, to at most this many samples
+    self.n_samples = n_samples.max(1);
+    self
+  }
+
+  pub fn max_reject(mut self, max_reject: f64) -> Self {
+    //The fit is abandoned if more than this fraction of samples was rejected
+    self.max_reject = max_reject;
+    self
+  }
+
+  pub fn min_npixels(mut self, min_npixels: usize) -> Self {
+    self.min_npixels = min_npixels;
+    self
+  }
+
+  pub fn krej(mut self, krej: f64) -> Self {
+    //Samples further than krej standard deviations from the fit are rejected
+    self.krej = krej;
+    self
+  }
+
+  pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+    self.max_iterations = max_iterations;
+    self
+  }
+
+  pub fn get_contrast(&self) -> f64 {
+    self.contrast
+  }
+  pub fn get_n_samples(&self) -> usize {
+    self.n_samples
+  }
+  pub fn get_max_reject(&self) -> f64 {
+    self.max_reject
+  }
+  pub fn get_min_npixels(&self) -> usize {
+    self.min_npixels
+  }
+  pub fn get_krej(&self) -> f64 {
+    self.krej
+  }
+  pub fn get_max_iterations(&self) -> usize {
+    self.max_iterations
+  }
+
+  pub fn limits(&self, data: &ArrayViewD<f64>) -> (f64, f64) {
+    //(1) Take a regularly spaced sample of the finite pixels, and sort it
+    let n_finite = data.iter().filter(|px| px.is_finite()).count();
+    let stride = (n_finite / self.n_samples).max(1);
+    let mut samples: Vec<f64> = data
+      .iter()
+      .filter(|px| px.is_finite())
+      .step_by(stride)
+      .take(self.n_samples)
+      .copied()
+      .collect();
+    samples.sort_by(f64::total_cmp);
+    let npix = samples.len();
+    let (Some(&min), Some(&max)) = (samples.first(), samples.last()) else {
+      return (0.0, 0.0);
+    };
+
+    //(2) Fit a line to the samples, rejecting outliers (and their neighbours)
+    let min_good = self.min_npixels.max((npix as f64 * self.max_reject) as usize);
+    let grow = ((npix as f64 * 0.01) as usize).max(1);
+    let mut bad = vec![false; npix];
+    let (mut n_good, mut last_n_good) = (npix, npix + 1);
+    let mut slope = 0.0;
+    for _ in 0..self.max_iterations {
+      if n_good >= last_n_good || n_good < min_good {
+        break;
+      }
+      let (fit_slope, intercept) = fit_line(&samples, &bad);
+      slope = fit_slope;
+
+      //Reject samples further than krej sigma from the line
+      let residual = |x: usize| samples[x] - (intercept + slope * x as f64);
+      let good: Vec<f64> = (0..npix).filter(|&x| !bad[x]).map(residual).collect();
+      let mean = good.iter().sum::<f64>() / good.len() as f64;
+      let sigma = (good.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / good.len() as f64).sqrt();
+      let threshold = self.krej * sigma;
+      let rejected: Vec<bool> =
+        (0..npix).map(|x| bad[x] || residual(x).abs() > threshold).collect();
+
+      //Rejections spread to the neighbouring samples
+      for (x, bad) in bad.iter_mut().enumerate() {
+        let window = x.saturating_sub(grow / 2)..(x + (grow - 1) / 2 + 1).min(npix);
+        *bad = rejected[window].iter().any(|&rej| rej);
+      }
+      last_n_good = n_good;
+      n_good = bad.iter().filter(|&&rej| !rej).count();
+    }
+
+    //(3) Too many rejected samples: fall back to the full range
+    if n_good < min_good {
+      return (min, max);
+    }
+
+    //(R) range around the median, with a width set by the slope
+    if self.contrast > 0.0 {
+      slope /= self.contrast;
+    }
+    let center = (npix - 1) / 2;
+    let median = match npix % 2 {
+      0 => (samples[npix / 2 - 1] + samples[npix / 2]) / 2.0,
+      _ => samples[npix / 2],
+    };
+    let low = median - (center as f64 - 1.0) * slope;
+    let high = median + (npix - center) as f64 * slope;
+    (min.max(low), max.min(high))
+  }
+}
+
+fn fit_line(samples: &[f64], bad: &[bool]) -> (f64, f64) {
+  //Least squares fit of samples[x] = intercept + slope * x over the good samples
+  let (mut n, mut sx, mut sy, mut sxx, mut sxy) = (0.0, 0.0, 0.0, 0.0, 0.0);
+  for (x, (&y, &rejected)) in samples.iter().zip(bad).enumerate() {
+    if rejected {
+      continue;
+    }
+    let x = x as f64;
+    (n, sx, sy, sxx, sxy) = (n + 1.0, sx + x, sy + y, sxx + x * x, sxy + x * y);
+  }
+  let denom = n * sxx - sx * sx;
+  if denom == 0.0 {
+    return (0.0, sy / n);
+  }
+  let slope = (n * sxy - sx * sy) / denom;
+  (slope, (sy - slope * sx) / n)
+}
+
+pub fn zscale(data: &ArrayViewD<f64>) -> (f64, f64) {
+  //zscale limits with the default parameters (contrast = 0.25)
+  ZScale::default().limits(data)
+}
+
+pub fn percentile_limits(data: &ArrayViewD<f64>, lower: f64, upper: f64) -> (f64, f64) {
+  /*  Pixel values at the lower and upper percentiles (0.0 to 100.0) of the
+      finite pixels, interpolating linearly between the closest pixels.
+  */
+  let mut values: Vec<f64> = data.iter().filter(|px| px.is_finite()).copied().collect();
+  if values.is_empty() {
+    return (0.0, 0.0);
+  }
+  values.sort_by(f64::total_cmp);
+  let at = |pct: f64| {
+    let rank = pct.clamp(0.0, 100.0) / 100.0 * (values.len() - 1) as f64;
+    let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+    values[below] + (values[above] - values[below]) * (rank - below as f64)
+  };
+  (at(lower), at(upper))
+}
@@ -0,0 +1,52 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::path::PathBuf;
+
+use rustronomy_fits as rsf;
+
+fn open(name: &str) -> rsf::Fits {
+  let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  path.push("resources");
+  path.push(name);
+  rsf::Fits::open(&path).unwrap()
+}
+
+#[test]
+fn accessor_test() {
+  //NICMOS has an empty primary HDU, its image is stored in HDU #1
+  let fits = open("Hubble_NICMOS.fits");
+  assert!(fits.primary().unwrap().get_data().is_none());
+  let img = fits.primary_image().unwrap();
+  assert!(img.as_array::<f32>().is_ok());
+  let err = fits.first_table().unwrap_err();
+  assert_eq!(format!("{err}"), "FITS file does not contain a table (or its data was unloaded)");
+
+  //WFPC2 has an image in its primary HDU and a table in HDU #1
+  let fits = open("Hubble_WFPC2_1.fits");
+  assert!(fits.primary().unwrap().get_data().is_some());
+  let Ok(rsf::Extension::AsciiTable(tbl)) = fits.first_table() else { panic!() };
+  let (cols, rows) = tbl.get_shape();
+  assert!(cols > 0 && rows > 0);
+
+  //IUE stores its spectrum in a binary table
+  let fits = open("IUE_LWP.fits");
+  let Ok(rsf::Extension::BinTable(tbl)) = fits.first_table() else { panic!() };
+  let (cols, rows) = tbl.get_shape();
+  assert!(cols > 0 && rows > 0);
+}
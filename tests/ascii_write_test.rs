@@ -0,0 +1,159 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::path::Path;
+
+use ndarray::array;
+use rustronomy_fits::{self as rsf, tbl_err::FieldOverflowErr};
+
+fn card(keyword: &str, value: &str) -> String {
+  format!("{keyword:8}= {value:>20}")
+}
+
+fn blocks(cards: &[String]) -> Vec<u8> {
+  let mut buf: Vec<u8> = cards.iter().flat_map(|card| format!("{card:80}").into_bytes()).collect();
+  buf.extend(format!("{:80}", "END").bytes());
+  buf.resize(buf.len().div_ceil(2880) * 2880, b' ');
+  buf
+}
+
+fn table_file(row_len: usize, fields: &[(&str, usize)], rows: &[&str]) -> Vec<u8> {
+  //Primary HDU without data, followed by an ASCII table with the given
+  //(TFORMn, TBCOLn) fields
+  let mut file = blocks(&[card("SIMPLE", "T"), card("BITPIX", "8"), card("NAXIS", "0")]);
+  let mut cards = vec![
+    card("XTENSION", "'TABLE   '"),
+    card("BITPIX", "8"),
+    card("NAXIS", "2"),
+    card("NAXIS1", &row_len.to_string()),
+    card("NAXIS2", &rows.len().to_string()),
+    card("PCOUNT", "0"),
+    card("GCOUNT", "1"),
+    card("TFIELDS", &fields.len().to_string()),
+  ];
+  for (i, (tform, tbcol)) in fields.iter().enumerate() {
+    cards.push(card(&format!("TFORM{}", i + 1), &format!("'{tform}'")));
+    cards.push(card(&format!("TBCOL{}", i + 1), &tbcol.to_string()));
+  }
+  file.extend(blocks(&cards));
+  let mut data: Vec<u8> =
+    rows.iter().flat_map(|row| format!("{row:row_len$}").into_bytes()).collect();
+  data.resize(data.len().div_ceil(2880) * 2880, b' ');
+  file.extend(data);
+  file
+}
+
+fn table(fits: &rsf::Fits, index: usize) -> &rsf::AsciiTable {
+  match fits.get_hdu(index).unwrap().get_data() {
+    Some(rsf::Extension::AsciiTable(tbl)) => tbl,
+    _ => panic!("HDU {index} is not an ASCII table"),
+  }
+}
+
+fn entries(tbl: &rsf::AsciiTable) -> Vec<String> {
+  let (n_cols, n_rows) = tbl.get_shape();
+  (0..n_rows)
+    .flat_map(|row| (0..n_cols).map(move |col| format!("{:?}", tbl.get_entry(col, row).unwrap())))
+    .collect()
+}
+
+#[test]
+fn round_trip_test() {
+  //(1) A table from the archive keeps its layout, and all of its entries
+  let path = Path::new("resources/Hubble_FOC.fits");
+  let original = rsf::Fits::open(path).unwrap();
+  let index = (0..original.get_num_hdus())
+    .find(|&i| {
+      matches!(original.get_hdu(i).unwrap().get_data(), Some(rsf::Extension::AsciiTable(_)))
+    })
+    .unwrap();
+  let mut out = dirs::cache_dir().unwrap();
+  out.push("ascii_write_round_trip.fits");
+  rsf::Fits::open(path).unwrap().write(&out).unwrap();
+  let written = rsf::Fits::open(&out).unwrap();
+  assert_eq!(entries(table(&written, index)), entries(table(&original, index)));
+
+  //(2) The header still describes the same layout
+  let header = written.get_hdu(index).unwrap().get_header();
+  assert_eq!(header.get_value_as::<usize>("NAXIS1").unwrap(), 312);
+  assert_eq!(header.get_value_as::<usize>("TBCOL18").unwrap(), 301);
+  assert_eq!(header.get_value_as::<String>("TFORM15").unwrap(), "'D25.16'");
+  assert_eq!(header.get_value_as::<String>("TFORM3").unwrap(), "'E15.7'");
+}
+
+#[test]
+fn format_test() {
+  //Strings are left-justified, numbers right-justified in Fortran notation
+  let file = table_file(
+    30,
+    &[("A5", 1), ("I4", 7), ("F7.2", 12), ("E11.3", 20)],
+    &["ab    -12    3.5   1250.0", "xyz     7  -0.125  -0.5E-2"],
+  );
+  let fits = rsf::Fits::from_stream(file.as_slice()).unwrap();
+  let written = fits.write_stream(Vec::new(), &rsf::WriteOptions::new()).unwrap();
+  let data = &written[written.len() - 2880..];
+  assert_eq!(&data[..30], b"ab     -12    3.50   1.250E+03");
+  assert_eq!(&data[30..60], b"xyz      7   -0.12  -5.000E-03");
+  assert!(data[60..].iter().all(|&byte| byte == b' '));
+}
+
+#[test]
+fn overlap_test() {
+  //Overlapping fields are packed, separated by a space
+  let file = table_file(3, &[("I3", 1), ("A3", 1)], &["123", "45 "]);
+  let fits = rsf::Fits::from_stream(file.as_slice()).unwrap();
+  let before = entries(table(&fits, 1));
+  let written = fits.write_stream(Vec::new(), &rsf::WriteOptions::new()).unwrap();
+  let fits = rsf::Fits::from_stream(written.as_slice()).unwrap();
+  assert_eq!(entries(table(&fits, 1)), before);
+  let header = fits.get_hdu(1).unwrap().get_header();
+  assert_eq!(header.get_value_as::<usize>("TBCOL2").unwrap(), 5);
+  assert_eq!(header.get_value_as::<usize>("NAXIS1").unwrap(), 7);
+}
+
+#[test]
+fn overflow_test() {
+  //1E3 fits in an F5.1 field as written, but not as 1000.0
+  let file = table_file(5, &[("F5.1", 1)], &["  1.5", "  1E3"]);
+  let opts = rsf::WriteOptions::new().encode_memory(0);
+  let err = rsf::Fits::from_stream(file.as_slice()).unwrap().write_stream(Vec::new(), &opts);
+  let err = err.unwrap_err();
+  let err = err.downcast_ref::<FieldOverflowErr>().unwrap();
+  assert_eq!((err.get_index(), err.get_tform()), ((0, 1), "F5.1"));
+}
+
+#[test]
+fn image_column_test() {
+  //Tables made from images are written with the formats of their header
+  let img = rsf::TypedImage::from(rsf::Image::new(array![0.1, -2.5e-300, 7.0].into_dyn()));
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("ascii_write_column.fits");
+  let mut fits = rsf::Fits::open(Path::new("resources/Astro_UIT.fits")).unwrap();
+  let mut image_hdu = fits.primary().unwrap().clone();
+  *image_hdu.get_data_mut().unwrap() = rsf::Extension::Image(img);
+  fits.push_hdu(image_hdu.image_to_column("FLUX").unwrap());
+  fits.write(&path).unwrap();
+
+  let fits = rsf::Fits::open(&path).unwrap();
+  let index = fits.get_num_hdus() - 1;
+  let tbl = table(&fits, index);
+  assert_eq!(entries(tbl), ["Float(0.1)", "Float(-2.5e-300)", "Float(7.0)"]);
+  let header = fits.get_hdu(index).unwrap().get_header();
+  assert_eq!(header.get_value_as::<String>("TFORM1").unwrap(), "'D25.17'");
+}
@@ -0,0 +1,74 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{fs, path::PathBuf, thread};
+
+use rustronomy_fits as rsf;
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+fn leftover_tmp_files(dir: &std::path::Path) -> usize {
+  fs::read_dir(dir)
+    .unwrap()
+    .filter(|entry| entry.as_ref().unwrap().file_name().to_string_lossy().ends_with(".tmp"))
+    .count()
+}
+
+#[test]
+fn atomic_write_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let fits = rsf::Fits::open(&real_path).unwrap();
+
+  let mut dir = dirs::cache_dir().unwrap();
+  dir.push("atomic_test");
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).unwrap();
+
+  //Atomic writes replace the target in one go
+  let path = dir.join("atomic.fits");
+  fs::write(&path, b"old contents").unwrap();
+  let opts = rsf::WriteOptions::new().atomic(true).fsync(true);
+  fits.clone().write_with(&path, &opts).unwrap();
+  let plain = dir.join("plain.fits");
+  fits.clone().write(&plain).unwrap();
+  assert!(fs::read(&path).unwrap() == fs::read(&plain).unwrap());
+  assert_eq!(leftover_tmp_files(&dir), 0);
+
+  //If the write fails (here: the target is a directory), the temporary file
+  //is cleaned up and the target is left alone
+  let blocked = dir.join("blocked.fits");
+  fs::create_dir(&blocked).unwrap();
+  fs::write(blocked.join("keep"), b"keep").unwrap();
+  assert!(fits.write_with(&blocked, &opts).is_err());
+  assert_eq!(fs::read(blocked.join("keep")).unwrap(), b"keep");
+  assert_eq!(leftover_tmp_files(&dir), 0);
+
+  //Locked writes from several threads of the same process each get their own
+  //temporary file, and the lock is taken on a separate lock file
+  let locked = dir.join("locked.fits");
+  let opts = opts.lock(rsf::LockPolicy::Wait);
+  thread::scope(|scope| {
+    for _ in 0..4 {
+      scope.spawn(|| rsf::Fits::open(&real_path).unwrap().write_with(&locked, &opts).unwrap());
+    }
+  });
+  assert!(fs::read(&locked).unwrap() == fs::read(&plain).unwrap());
+  assert!(dir.join(".locked.fits.lock").exists());
+  assert_eq!(leftover_tmp_files(&dir), 0);
+}
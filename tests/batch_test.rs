@@ -0,0 +1,88 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{
+  path::PathBuf,
+  sync::atomic::{AtomicUsize, Ordering},
+  thread,
+  time::Duration,
+};
+
+use rustronomy_fits as rsf;
+
+//Raw size of the data units in Hubble_FOC.fits (a 1024x1024 f32 image and a
+//single 312 character table row)
+const FOC_DATA_SIZE: usize = 1024 * 1024 * 4 + 312;
+
+fn foc_paths(n: usize) -> Vec<PathBuf> {
+  vec![PathBuf::from("resources/Hubble_FOC.fits"); n]
+}
+
+fn max_resident(reader: &rsf::BatchReader) -> usize {
+  //Largest number of files that were handed out at the same time
+  let (resident, max) = (AtomicUsize::new(0), AtomicUsize::new(0));
+  let results = reader.map(|_, fits| {
+    let now = resident.fetch_add(1, Ordering::SeqCst) + 1;
+    max.fetch_max(now, Ordering::SeqCst);
+    thread::sleep(Duration::from_millis(25));
+    resident.fetch_sub(1, Ordering::SeqCst);
+    fits.get_hdu(0).unwrap().get_header().get_value_as::<String>("NAXIS")
+  });
+  assert!(results.iter().all(|(_, result)| result.as_deref() == Ok("2")));
+  max.load(Ordering::SeqCst)
+}
+
+#[test]
+fn budget_test() {
+  //Only two copies of the file fit in the budget
+  let reader = rsf::BatchReader::new(foc_paths(8)).memory_budget(2 * FOC_DATA_SIZE);
+  assert_eq!(reader.get_memory_budget(), Some(2 * FOC_DATA_SIZE));
+  assert!(max_resident(&reader) <= 2);
+}
+
+#[test]
+fn oversized_test() {
+  //Files larger than the budget are read one at a time, instead of never
+  let reader = rsf::BatchReader::new(foc_paths(4)).memory_budget(1000);
+  assert_eq!(max_resident(&reader), 1);
+}
+
+#[test]
+fn results_test() {
+  //Results are in the order of the paths, and errors are not fatal
+  let paths = vec![
+    PathBuf::from("resources/Hubble_NICMOS.fits"),
+    PathBuf::from("resources/does_not_exist.fits"),
+    PathBuf::from("resources/Astro_UIT.fits"),
+  ];
+  let reader = rsf::BatchReader::new(paths.clone()).memory_budget(FOC_DATA_SIZE);
+  assert!(reader.get_read_options().get_table_strategy() == rsf::TableStrategy::Auto);
+  let results = reader.map(|_, fits| Ok(fits.get_num_hdus()));
+  assert_eq!(results.iter().map(|(path, _)| path.clone()).collect::<Vec<_>>(), paths);
+  assert!(matches!(results[0].1, Ok(n) if n > 0));
+  assert!(results[1].1.is_err());
+  assert!(matches!(results[2].1, Ok(n) if n > 0));
+
+  //Without a budget nothing changes, except for the throttling
+  let unlimited = rsf::BatchReader::new(paths).map(|_, fits| Ok(fits.get_num_hdus()));
+  assert_eq!(
+    unlimited.iter().map(|(_, result)| result.clone()).collect::<Vec<_>>(),
+    results.iter().map(|(_, result)| result.clone()).collect::<Vec<_>>()
+  );
+}
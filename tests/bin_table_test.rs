@@ -0,0 +1,299 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::path::Path;
+
+use rsf::TableEntry::{self, Array, Complex, Float, Int, Text};
+use rustronomy_fits as rsf;
+
+const ROW_LEN: usize = 91;
+const HEAP_LEN: usize = 24;
+
+fn card(keyword: &str, value: &str) -> String {
+  format!("{keyword:8}= {value:>20}")
+}
+
+fn blocks(cards: &[String]) -> Vec<u8> {
+  let mut buf: Vec<u8> = cards.iter().flat_map(|card| format!("{card:80}").into_bytes()).collect();
+  buf.extend(format!("{:80}", "END").bytes());
+  buf.resize(buf.len().div_ceil(2880) * 2880, b' ');
+  buf
+}
+
+fn bin_table_file(heap_offset: i64) -> Vec<u8> {
+  //(1) Primary HDU without data
+  let mut file = blocks(&[card("SIMPLE", "T"), card("BITPIX", "8"), card("NAXIS", "0")]);
+
+  //(2) Binary table with one column of every type
+  let columns = [
+    ("FLAG", "L", None),
+    ("BITS", "3X", None),
+    ("BYTE", "B", Some(("TZERO", "-128"))),
+    ("USHORT", "I", Some(("TZERO", "32768"))),
+    ("COUNT", "J", Some(("TNULL", "-1"))),
+    ("BIG", "K", None),
+    ("NAME", "6A", None),
+    ("FLUX", "E", Some(("TSCAL", "2.0"))),
+    ("VEC", "2D", Some(("TDIM", "'(2,1)'"))),
+    ("Z", "C", None),
+    ("ZZ", "M", None),
+    ("VAR", "1PJ(3)", None),
+    ("QVAR", "1QE", None),
+  ];
+  let mut cards = vec![
+    card("XTENSION", "'BINTABLE'"),
+    card("BITPIX", "8"),
+    card("NAXIS", "2"),
+    card("NAXIS1", &ROW_LEN.to_string()),
+    card("NAXIS2", "2"),
+    card("PCOUNT", &HEAP_LEN.to_string()),
+    card("GCOUNT", "1"),
+    card("TFIELDS", &columns.len().to_string()),
+  ];
+  for (i, (name, tform, extra)) in columns.iter().enumerate() {
+    cards.push(card(&format!("TTYPE{}", i + 1), &format!("'{name}'")));
+    cards.push(card(&format!("TFORM{}", i + 1), &format!("'{tform}'")));
+    if let Some((keyword, value)) = extra {
+      cards.push(card(&format!("{keyword}{}", i + 1), value));
+    }
+  }
+  cards.push(card("TZERO8", "1.0"));
+  cards.push(card("TUNIT8", "'Jy'"));
+  file.extend(blocks(&cards));
+
+  //(3) Rows, followed by the heap
+  let mut data = Vec::new();
+  let rows: [[&[u8]; 2]; 13] = [
+    [b"T", &[0]],
+    [&[0b1010_0000], &[0b0100_0000]],
+    [&[0], &[255]],
+    [&(-32768i16).to_be_bytes(), &32767i16.to_be_bytes()],
+    [&7i32.to_be_bytes(), &(-1i32).to_be_bytes()],
+    [&i64::MAX.to_be_bytes(), &(-5i64).to_be_bytes()],
+    [b"abc\0xx", b"hello "],
+    [&1.5f32.to_be_bytes(), &f32::NAN.to_be_bytes()],
+    [
+      &[1f64.to_be_bytes(), 2f64.to_be_bytes()].concat(),
+      &[3f64.to_be_bytes(), 4f64.to_be_bytes()].concat(),
+    ],
+    [
+      &[1f32.to_be_bytes(), (-1f32).to_be_bytes()].concat(),
+      &[2f32.to_be_bytes(), 0f32.to_be_bytes()].concat(),
+    ],
+    [
+      &[0.5f64.to_be_bytes(), 0.25f64.to_be_bytes()].concat(),
+      &[0f64.to_be_bytes(), 1f64.to_be_bytes()].concat(),
+    ],
+    [&[3i32.to_be_bytes(), (heap_offset as i32).to_be_bytes()].concat(), &[0u8; 8]],
+    [
+      &[1i64.to_be_bytes(), 12i64.to_be_bytes()].concat(),
+      &[2i64.to_be_bytes(), 16i64.to_be_bytes()].concat(),
+    ],
+  ];
+  for row in 0..2 {
+    rows.iter().for_each(|fields| data.extend_from_slice(fields[row]));
+  }
+  assert_eq!(data.len(), 2 * ROW_LEN);
+  [10i32, 20, 30].iter().for_each(|x| data.extend(x.to_be_bytes()));
+  [2.5f32, -1.0, 8.0].iter().for_each(|x| data.extend(x.to_be_bytes()));
+  data.resize(2880, 0);
+  file.extend(data);
+  file
+}
+
+fn bin_table(fits: &rsf::Fits) -> &rsf::BinTable {
+  match fits.get_hdu(1).unwrap().get_data() {
+    Some(rsf::Extension::BinTable(tbl)) => tbl,
+    _ => panic!("HDU 1 is not a binary table"),
+  }
+}
+
+fn entries(tbl: &rsf::BinTable, row: usize) -> Vec<TableEntry> {
+  (0..tbl.get_shape().0).map(|col| tbl.get_entry(col, row).unwrap()).collect()
+}
+
+#[test]
+fn types_test() {
+  let fits = rsf::Fits::from_stream(bin_table_file(0).as_slice()).unwrap();
+  let tbl = bin_table(&fits);
+  assert_eq!(tbl.get_shape(), (13, 2));
+  assert_eq!(tbl.get_heap_len(), HEAP_LEN);
+
+  //Every type, with TZEROn and TSCALn applied
+  let expected = format!(
+    "{:?}",
+    [
+      Text(String::from("T")),
+      Array(vec![Int(1), Int(0), Int(1)]),
+      Int(-128),
+      Int(0),
+      Int(7),
+      Int(i64::MAX),
+      Text(String::from("abc")),
+      Float(4.0),
+      Array(vec![Float(1.0), Float(2.0)]),
+      Complex(1.0, -1.0),
+      Complex(0.5, 0.25),
+      Array(vec![Int(10), Int(20), Int(30)]),
+      Array(vec![Float(2.5)]),
+    ]
+  );
+  assert_eq!(format!("{:?}", entries(tbl, 0)), expected);
+  let second = entries(tbl, 1);
+  assert_eq!(format!("{:?}", &second[..7]), "[Text(\"\"), Array([Int(0), Int(1), Int(0)]), Int(127), Int(65535), Int(-1), Int(-5), Text(\"hello\")]");
+  assert!(matches!(second[7], Float(flux) if flux.is_nan()));
+  assert_eq!(format!("{:?}", &second[11..]), "[Array([]), Array([Float(-1.0), Float(8.0)])]");
+
+  //Column metadata
+  assert_eq!(tbl.get_col_label(6), Some("NAME"));
+  assert_eq!(tbl.get_col_index("flux"), Some(7));
+  assert_eq!(tbl.get_col_unit(7), Some("Jy"));
+  assert_eq!(tbl.get_col_dims(8), Some(&[2, 1][..]));
+  assert_eq!(tbl.get_col_null(4), Some(-1));
+  let format = tbl.get_col_format(11).unwrap();
+  assert!(format.is_variable());
+  assert_eq!((format.get_type(), format.get_max_len()), (rsf::BinType::Int, Some(3)));
+  assert_eq!(format.to_string(), "1PJ(3)");
+  assert_eq!(tbl.get_str(6, 1), Some("hello"));
+  assert_eq!(tbl.get_str(5, 1), None);
+}
+
+#[test]
+fn null_test() {
+  //Logicals that are 0, integers that match TNULLn and NaN reals are null
+  let fits = rsf::Fits::from_stream(bin_table_file(0).as_slice()).unwrap();
+  let tbl = bin_table(&fits);
+  for col in [0, 4, 7] {
+    assert!(!tbl.is_null(col, 0));
+    assert!(tbl.is_null(col, 1));
+  }
+  assert!(tbl.get_col_validity(5).is_none());
+  assert!(tbl.get_col_validity(11).is_none());
+}
+
+#[test]
+fn round_trip_test() {
+  //The rows and the heap are written back as they were read
+  let file = bin_table_file(0);
+  let written = rsf::Fits::from_stream(file.as_slice())
+    .unwrap()
+    .write_stream(Vec::new(), &rsf::WriteOptions::new())
+    .unwrap();
+  assert_eq!(written[written.len() - 2880..], file[file.len() - 2880..]);
+  let read = rsf::Fits::from_stream(written.as_slice()).unwrap();
+  let fits = rsf::Fits::from_stream(file.as_slice()).unwrap();
+  for row in 0..2 {
+    let (a, b) = (entries(bin_table(&read), row), entries(bin_table(&fits), row));
+    assert_eq!(format!("{a:?}"), format!("{b:?}"));
+  }
+}
+
+#[test]
+fn heap_range_test() {
+  //Arrays that do not fit in the heap are refused
+  let err = rsf::Fits::from_stream(bin_table_file(100).as_slice()).unwrap_err();
+  assert!(err.to_string().contains("heap"), "{err}");
+}
+
+#[test]
+fn theap_test() {
+  //THEAP has to point into the PCOUNT bytes that follow the rows
+  let with_theap = |theap: usize| {
+    let mut file = bin_table_file(0);
+    let unit = format!("{:80}", card("TUNIT8", "'Jy'"));
+    let at = file.windows(80).position(|card| card == unit.as_bytes()).unwrap();
+    file[at..at + 80]
+      .copy_from_slice(format!("{:80}", card("THEAP", &theap.to_string())).as_bytes());
+    rsf::Fits::from_stream(file.as_slice())
+  };
+  assert!(with_theap(2 * ROW_LEN).is_ok());
+  for theap in [2 * ROW_LEN - 1, 2 * ROW_LEN + HEAP_LEN + 1] {
+    let err = with_theap(theap).unwrap_err();
+    assert!(err.to_string().contains("THEAP"), "{err}");
+  }
+}
+
+#[test]
+fn archive_test() {
+  //IUE spectra are stored as one row with array columns
+  let fits = rsf::Fits::open(Path::new("resources/IUE_LWP.fits")).unwrap();
+  let tbl = bin_table(&fits);
+  assert_eq!(tbl.get_shape(), (9, 1));
+  assert_eq!(tbl.get_str(0, 0), Some("LARGE"));
+  assert!(matches!(tbl.get_entry(1, 0), Ok(Int(640))));
+  let Ok(Array(net)) = tbl.get_entry(tbl.get_col_index("NET").unwrap(), 0) else {
+    panic!("NET is not an array column");
+  };
+  assert_eq!(net.len(), 640);
+
+  //EUVE files end with a few small limit tables
+  let fits = rsf::Fits::open(Path::new("resources/EUVE.fits")).unwrap();
+  let limits = fits.get_by_name("ds_limits", 1).unwrap();
+  let Some(rsf::Extension::BinTable(tbl)) = limits.get_data() else {
+    panic!("ds_limits is not a binary table");
+  };
+  assert_eq!(tbl.get_shape(), (3, 3));
+  assert_eq!(tbl.get_str(0, 2), Some("lookzen"));
+  assert!(matches!(tbl.get_entry(2, 1), Ok(Float(high)) if high == 5000.0));
+}
+
+#[test]
+fn format_test() {
+  let format = rsf::BinFormat::parse("16E").unwrap();
+  assert_eq!((format.get_repeat(), format.get_type()), (16, rsf::BinType::Float));
+  assert!(!format.is_variable());
+  assert_eq!(rsf::BinFormat::parse("QB").unwrap().to_string(), "1QB");
+  for invalid in ["", "3", "2PE", "1PE(x)", "5Z", "9223372036854775807D"] {
+    assert!(rsf::BinFormat::parse(invalid).is_err(), "{invalid}");
+  }
+}
+
+#[test]
+fn conversion_test() {
+  let mut builder = rsf::TableBuilder::new().col_str("NAME", 6).col_i64("ID").col_f64("FLUX");
+  rsf::push_row!(builder, "M31", 31, 1.5).unwrap();
+  rsf::push_row!(builder, "NGC224", -2, 0.25).unwrap();
+  let ascii = builder.build();
+
+  //ASCII tables become binary tables with A, K and D columns
+  let bin = ascii.to_bintable().unwrap();
+  assert_eq!(bin.get_shape(), (3, 2));
+  let formats: Vec<String> =
+    (0..3).map(|col| bin.get_col_format(col).unwrap().to_string()).collect();
+  assert_eq!(formats, ["6A", "1K", "1D"]);
+  assert_eq!(bin.get_col_label(1), Some("ID"));
+  assert_eq!(bin.get_str(0, 1), Some("NGC224"));
+  assert!(matches!(bin.get_entry(1, 1), Ok(Int(-2))));
+
+  //and back, with the same entries
+  let back = bin.to_ascii(&["A6", "I4", "E12.4"]).unwrap();
+  for (col, row) in (0..3).flat_map(|col| (0..2).map(move |row| (col, row))) {
+    let (a, b) = (ascii.get_entry(col, row).unwrap(), back.get_entry(col, row).unwrap());
+    assert_eq!(format!("{a:?}"), format!("{b:?}"));
+  }
+
+  //Entries have to fit in the given formats, array columns cannot be converted
+  assert!(bin.to_ascii(&["A2", "I4", "E12.4"]).is_err());
+  assert!(bin.to_ascii(&["A6", "I1", "E12.4"]).is_err());
+  assert!(bin.to_ascii(&["A6", "I4"]).is_err());
+  assert!(bin.to_ascii(&["A6", "X4", "E12.4"]).is_err());
+  let fits = rsf::Fits::open(Path::new("resources/IUE_LWP.fits")).unwrap();
+  let tforms = ["A8", "I8", "I8", "I8", "E12.4", "E12.4", "E12.4", "E12.4", "E12.4"];
+  assert!(bin_table(&fits).to_ascii(&tforms).is_err());
+}
@@ -0,0 +1,94 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{error::Error, fs, path::PathBuf};
+
+use rustronomy_fits::{self as rsf, BlockRead, BlockWrite};
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+#[derive(Debug, Default)]
+struct MemFile {
+  //In-memory backend
+  bytes: Vec<u8>,
+  cursor: usize,
+  flushed: bool,
+}
+
+impl BlockRead for MemFile {
+  fn read_blocks(&mut self, buffer: &mut [u8]) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let end = self.cursor + buffer.len();
+    buffer.copy_from_slice(self.bytes.get(self.cursor..end).ok_or("read past the end")?);
+    self.cursor = end;
+    Ok(buffer.len() / 2880)
+  }
+  fn skip_blocks(&mut self, n_blocks: usize) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    if n_blocks > self.get_block_len() - self.get_block_index() {
+      return Err("skipped past the end".into());
+    }
+    self.cursor += n_blocks * 2880;
+    Ok(n_blocks)
+  }
+  fn get_block_len(&self) -> usize {
+    self.bytes.len() / 2880
+  }
+  fn get_block_index(&self) -> usize {
+    self.cursor / 2880
+  }
+}
+
+impl BlockWrite for MemFile {
+  fn write_blocks(&mut self, buffer: &[u8]) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    assert_eq!(buffer.len() % 2880, 0);
+    self.bytes.extend_from_slice(buffer);
+    Ok(buffer.len() / 2880)
+  }
+  fn flush(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+    self.flushed = true;
+    Ok(())
+  }
+}
+
+#[test]
+fn memory_backend_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let mut out = dirs::cache_dir().unwrap();
+  out.push("block_io.fits");
+  rsf::Fits::open(&real_path).unwrap().write(&out).unwrap();
+
+  //(1) Writing to memory gives the same bytes as writing to a file
+  let mut mem = MemFile::default();
+  let fits = rsf::Fits::open(&real_path).unwrap();
+  fits.write_to(&mut mem, &rsf::WriteOptions::new()).unwrap();
+  assert!(mem.flushed);
+  assert_eq!(mem.bytes, fs::read(&out).unwrap());
+
+  //(2) Reading from memory gives the same HDU's as reading the file
+  let from_mem = rsf::Fits::read_from(&mut mem, &rsf::ReadOptions::new()).unwrap();
+  let from_file = rsf::Fits::open(&out).unwrap();
+  assert_eq!(from_mem.get_num_hdus(), from_file.get_num_hdus());
+  for (mem_entry, file_entry) in from_mem.manifest().iter().zip(from_file.manifest().iter()) {
+    assert_eq!(mem_entry.to_string(), file_entry.to_string());
+  }
+  assert!(from_mem.get_hdu(1).unwrap().get_data_source().is_none());
+
+  //(3) Decoding errors of the backend are passed on
+  let mut truncated = MemFile { bytes: mem.bytes[..2880].to_vec(), ..Default::default() };
+  assert!(rsf::Fits::read_from(&mut truncated, &rsf::ReadOptions::new()).is_err());
+}
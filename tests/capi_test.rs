@@ -0,0 +1,137 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+#![cfg(feature = "capi")]
+
+use std::{
+  ffi::{c_char, c_int, c_void, CStr, CString},
+  path::Path,
+  ptr,
+};
+
+use rustronomy_fits as rsf;
+
+//Declarations as in include/rustronomy_fits.h
+#[repr(C)]
+struct RsfFits {
+  _private: [u8; 0],
+}
+
+extern "C" {
+  fn rsf_last_error() -> *const c_char;
+  fn rsf_fits_open(path: *const c_char) -> *mut RsfFits;
+  fn rsf_fits_free(fits: *mut RsfFits);
+  fn rsf_fits_num_hdus(fits: *const RsfFits) -> usize;
+  fn rsf_fits_get_image(
+    fits: *const RsfFits,
+    hdu: usize,
+    data: *mut *const c_void,
+    bitpix: *mut c_int,
+    shape: *mut usize,
+    max_naxis: usize,
+    naxis: *mut usize,
+  ) -> c_int;
+  fn rsf_fits_get_keyword(fits: *const RsfFits, hdu: usize, keyword: *const c_char) -> *mut c_char;
+  fn rsf_string_free(string: *mut c_char);
+}
+
+fn last_error() -> String {
+  unsafe { CStr::from_ptr(rsf_last_error()) }.to_string_lossy().into_owned()
+}
+
+#[test]
+fn capi_image_test() {
+  let path = "resources/Astro_UIT.fits";
+  let fits = rsf::Fits::open(Path::new(path)).unwrap();
+  let c_path = CString::new(path).unwrap();
+
+  unsafe {
+    //(1) Open the file through the C API
+    let handle = rsf_fits_open(c_path.as_ptr());
+    assert!(!handle.is_null());
+    assert_eq!(rsf_fits_num_hdus(handle), fits.get_num_hdus());
+
+    //(2) The pixels are handed out in file order
+    let (mut data, mut bitpix, mut naxis) = (ptr::null(), 0, 0);
+    let mut shape = [0usize; 8];
+    let status =
+      rsf_fits_get_image(handle, 0, &mut data, &mut bitpix, shape.as_mut_ptr(), 8, &mut naxis);
+    assert_eq!(status, 0);
+    assert_eq!((bitpix, naxis), (16, 2));
+    assert_eq!(&shape[..naxis], &[512, 512]);
+
+    let pixels = std::slice::from_raw_parts(data as *const i16, 512 * 512);
+    let img = match fits.get_hdu(0).unwrap().get_data() {
+      Some(rsf::Extension::Image(rsf::TypedImage::I16Img(img))) => img,
+      _ => panic!("expected an i16 image"),
+    };
+    assert!(img.get_data().t().iter().eq(pixels.iter()));
+
+    //(3) Too few axes for the shape is an error
+    let status =
+      rsf_fits_get_image(handle, 0, &mut data, &mut bitpix, shape.as_mut_ptr(), 1, &mut naxis);
+    assert_eq!(status, -1);
+    assert!(last_error().contains("2 axes"));
+
+    //(4) HDU's without an image
+    let status =
+      rsf_fits_get_image(handle, 99, &mut data, &mut bitpix, shape.as_mut_ptr(), 8, &mut naxis);
+    assert_eq!(status, -1);
+
+    rsf_fits_free(handle);
+  }
+}
+
+#[test]
+fn capi_keyword_test() {
+  let c_path = CString::new("resources/Astro_UIT.fits").unwrap();
+  unsafe {
+    let handle = rsf_fits_open(c_path.as_ptr());
+    assert!(!handle.is_null());
+
+    //(1) Strings are returned without quotes, numbers as written
+    let keyword = CString::new("TELESCOP").unwrap();
+    let value = rsf_fits_get_keyword(handle, 0, keyword.as_ptr());
+    assert_eq!(CStr::from_ptr(value).to_str().unwrap(), "UIT");
+    rsf_string_free(value);
+
+    let keyword = CString::new("NAXIS1").unwrap();
+    let value = rsf_fits_get_keyword(handle, 0, keyword.as_ptr());
+    assert_eq!(CStr::from_ptr(value).to_str().unwrap(), "512");
+    rsf_string_free(value);
+
+    //(2) Missing keywords give NULL and an error message
+    let keyword = CString::new("NOTTHERE").unwrap();
+    assert!(rsf_fits_get_keyword(handle, 0, keyword.as_ptr()).is_null());
+    assert!(last_error().contains("NOTTHERE"));
+
+    rsf_fits_free(handle);
+  }
+}
+
+#[test]
+fn capi_open_err_test() {
+  let c_path = CString::new("resources/does_not_exist.fits").unwrap();
+  unsafe {
+    assert!(rsf_fits_open(c_path.as_ptr()).is_null());
+    assert!(!last_error().is_empty());
+    assert!(rsf_fits_open(ptr::null()).is_null());
+    assert!(last_error().contains("NULL"));
+    assert_eq!(rsf_fits_num_hdus(ptr::null()), 0);
+  }
+}
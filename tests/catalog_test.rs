@@ -0,0 +1,58 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{fs, path::PathBuf};
+
+use rustronomy_fits as rsf;
+
+#[test]
+fn catalog_test() {
+  let resources = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources");
+
+  //Small directory tree with two FITS files, a broken one and a text file
+  let mut dir = dirs::cache_dir().unwrap();
+  dir.push("catalog_test");
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(dir.join("night1/raw")).unwrap();
+  fs::create_dir_all(dir.join("night2")).unwrap();
+  fs::copy(resources.join("Hubble_NICMOS.fits"), dir.join("night1/raw/nicmos.fits")).unwrap();
+  fs::copy(resources.join("Hubble_FOC.fits"), dir.join("night2/foc.FIT")).unwrap();
+  fs::write(dir.join("night2/broken.fits"), b"not a FITS file").unwrap();
+  fs::write(dir.join("night2/notes.txt"), b"seeing was bad").unwrap();
+
+  let keywords = [&rsf::HeaderCatalog::DEFAULT_KEYWORDS[..], &["TARGNAME"]].concat();
+  let catalog = rsf::HeaderCatalog::scan(&dir, &keywords).unwrap();
+
+  //One entry per HDU (NICMOS has 6, FOC has 2), broken files are reported
+  assert_eq!(catalog.get_entries().len(), 8);
+  assert_eq!(catalog.get_failed().len(), 1);
+  assert!(catalog.get_failed()[0].0.ends_with("broken.fits"));
+
+  //Values can be queried, string values are unquoted
+  let ngc4151 = catalog.find("TARGNAME", "NGC4151");
+  assert_eq!(ngc4151.len(), 2);
+  let filtered = catalog.find("FILTER", "F222M");
+  assert_eq!(filtered.len(), 1);
+  assert!(filtered[0].get_path().ends_with("nicmos.fits"));
+  assert_eq!(filtered[0].get_hdu(), 0);
+  assert_eq!(filtered[0].get_value("DATE-OBS"), Some("1998-05-22"));
+
+  let long = catalog.filter("EXPTIME", |exp| exp.parse::<f64>().unwrap() > 100.0);
+  assert_eq!(long.len(), 1);
+  assert!(long[0].get_path().ends_with("foc.FIT"));
+}
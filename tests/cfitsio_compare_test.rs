@@ -0,0 +1,634 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+#![cfg(feature = "cfitsio")]
+/*  This test cross-checks this crate against cfitsio, the reference FITS
+    library, on the fixture corpus in resources/. It compares the number of
+    HDU's, every valued header keyword, image pixels and the columns of ASCII
+    and binary tables, collects all discrepancies and fails if there are any.
+    Run it with
+
+      cargo test --features cfitsio --test cfitsio_compare_test
+
+    which requires libcfitsio to be installed. Data that this crate does not
+    support (random groups) is skipped and reported as such.
+*/
+
+use std::{
+  collections::HashSet,
+  ffi::{c_char, c_double, c_int, c_long, c_void, CStr, CString},
+  fs,
+  path::Path,
+  ptr,
+};
+
+use rustronomy_fits as rsf;
+
+#[link(name = "cfitsio")]
+extern "C" {
+  fn ffopen(fptr: *mut *mut c_void, name: *const c_char, mode: c_int, status: *mut c_int) -> c_int;
+  fn ffclos(fptr: *mut c_void, status: *mut c_int) -> c_int;
+  fn ffgerr(status: c_int, text: *mut c_char);
+  fn ffthdu(fptr: *mut c_void, nhdu: *mut c_int, status: *mut c_int) -> c_int;
+  fn ffmahd(fptr: *mut c_void, hdu: c_int, exttype: *mut c_int, status: *mut c_int) -> c_int;
+  fn ffghsp(fptr: *mut c_void, nexist: *mut c_int, nmore: *mut c_int, status: *mut c_int) -> c_int;
+  fn ffgkyn(
+    fptr: *mut c_void,
+    nkey: c_int,
+    keyname: *mut c_char,
+    value: *mut c_char,
+    comment: *mut c_char,
+    status: *mut c_int,
+  ) -> c_int;
+  fn ffgidm(fptr: *mut c_void, naxis: *mut c_int, status: *mut c_int) -> c_int;
+  fn ffgiszll(fptr: *mut c_void, nlen: c_int, naxes: *mut i64, status: *mut c_int) -> c_int;
+  fn ffpscl(fptr: *mut c_void, scale: c_double, zero: c_double, status: *mut c_int) -> c_int;
+  fn ffgpvd(
+    fptr: *mut c_void,
+    group: c_long,
+    first: i64,
+    nelem: i64,
+    nulval: c_double,
+    array: *mut c_double,
+    anynul: *mut c_int,
+    status: *mut c_int,
+  ) -> c_int;
+  fn ffgncl(fptr: *mut c_void, ncols: *mut c_int, status: *mut c_int) -> c_int;
+  fn ffgnrwll(fptr: *mut c_void, nrows: *mut i64, status: *mut c_int) -> c_int;
+  fn ffgtcl(
+    fptr: *mut c_void,
+    col: c_int,
+    typecode: *mut c_int,
+    repeat: *mut c_long,
+    width: *mut c_long,
+    status: *mut c_int,
+  ) -> c_int;
+  fn fftscl(
+    fptr: *mut c_void,
+    col: c_int,
+    scale: c_double,
+    zero: c_double,
+    status: *mut c_int,
+  ) -> c_int;
+  fn ffgcvd(
+    fptr: *mut c_void,
+    col: c_int,
+    first_row: i64,
+    first_elem: i64,
+    nelem: i64,
+    nulval: c_double,
+    array: *mut c_double,
+    anynul: *mut c_int,
+    status: *mut c_int,
+  ) -> c_int;
+  fn ffgcvs(
+    fptr: *mut c_void,
+    col: c_int,
+    first_row: i64,
+    first_elem: i64,
+    nelem: i64,
+    nulval: *const c_char,
+    array: *mut *mut c_char,
+    anynul: *mut c_int,
+    status: *mut c_int,
+  ) -> c_int;
+  fn ffgcvm(
+    fptr: *mut c_void,
+    col: c_int,
+    first_row: i64,
+    first_elem: i64,
+    nelem: i64,
+    nulval: c_double,
+    array: *mut c_double,
+    anynul: *mut c_int,
+    status: *mut c_int,
+  ) -> c_int;
+  fn ffgcx(
+    fptr: *mut c_void,
+    col: c_int,
+    first_row: i64,
+    first_bit: i64,
+    nbits: i64,
+    array: *mut c_char,
+    status: *mut c_int,
+  ) -> c_int;
+  fn ffgdesll(
+    fptr: *mut c_void,
+    col: c_int,
+    row: i64,
+    length: *mut i64,
+    heap_addr: *mut i64,
+    status: *mut c_int,
+  ) -> c_int;
+}
+
+//cfitsio constants (see fitsio.h)
+const READONLY: c_int = 0;
+const IMAGE_HDU: c_int = 0;
+const ASCII_TBL: c_int = 1;
+const BINARY_TBL: c_int = 2;
+const TBIT: c_int = 1;
+const TSTRING: c_int = 16;
+const TCOMPLEX: c_int = 83;
+const TDBLCOMPLEX: c_int = 163;
+const FLEN_BUF: usize = 1024;
+
+struct CfitsFile(*mut c_void);
+
+impl CfitsFile {
+  fn open(path: &Path) -> Result<Self, String> {
+    let name = CString::new(path.to_str().unwrap()).unwrap();
+    let (mut fptr, mut status) = (ptr::null_mut(), 0);
+    unsafe { ffopen(&mut fptr, name.as_ptr(), READONLY, &mut status) };
+    check(status, "ffopen")?;
+    Ok(CfitsFile(fptr))
+  }
+}
+
+impl Drop for CfitsFile {
+  fn drop(&mut self) {
+    let mut status = 0;
+    unsafe { ffclos(self.0, &mut status) };
+  }
+}
+
+fn check(status: c_int, call: &str) -> Result<(), String> {
+  if status == 0 {
+    return Ok(());
+  }
+  let mut text = [0 as c_char; 32];
+  unsafe { ffgerr(status, text.as_mut_ptr()) };
+  let text = unsafe { CStr::from_ptr(text.as_ptr()) }.to_string_lossy();
+  Err(format!("{call} failed with status {status} ({text})"))
+}
+
+fn c_string(buf: &[c_char]) -> String {
+  unsafe { CStr::from_ptr(buf.as_ptr()) }.to_string_lossy().into_owned()
+}
+
+fn parse_number(value: &str) -> Option<f64> {
+  value.trim().replace(['D', 'd'], "E").parse().ok()
+}
+
+fn same_number(ours: f64, theirs: f64) -> bool {
+  (ours.is_nan() && theirs.is_nan())
+    || ours == theirs
+    || (ours - theirs).abs() <= 1e-12 * ours.abs().max(theirs.abs())
+}
+
+fn same_value(ours: &str, theirs: &str) -> bool {
+  //(1) Strings: cfitsio only returns the first card of long (CONTINUE) strings
+  if let (Some(ours), Some(theirs)) = (rsf::unquote(ours), rsf::unquote(theirs)) {
+    return match theirs.strip_suffix('&') {
+      Some(start) => ours.starts_with(start),
+      None => ours == theirs,
+    };
+  }
+
+  //(2) Numbers may be formatted differently, compare them as such
+  if let (Some(ours), Some(theirs)) = (parse_number(ours), parse_number(theirs)) {
+    return same_number(ours, theirs);
+  }
+
+  //(3) Everything else (logicals, complex values) as text
+  ours.trim() == theirs.trim()
+}
+
+fn compare_header(
+  fptr: *mut c_void,
+  header: &rsf::Header,
+  loc: &str,
+  issues: &mut Vec<String>,
+) -> Result<(), String> {
+  //(1) Read all records, cfitsio also yields commentary records
+  let (mut nexist, mut nmore, mut status) = (0, 0, 0);
+  unsafe { ffghsp(fptr, &mut nexist, &mut nmore, &mut status) };
+  check(status, "ffghsp")?;
+
+  let mut records = Vec::new();
+  for nkey in 1..=nexist {
+    let mut name = [0 as c_char; FLEN_BUF];
+    let mut value = [0 as c_char; FLEN_BUF];
+    let mut comment = [0 as c_char; FLEN_BUF];
+    unsafe {
+      ffgkyn(fptr, nkey, name.as_mut_ptr(), value.as_mut_ptr(), comment.as_mut_ptr(), &mut status)
+    };
+    check(status, "ffgkyn")?;
+    let (name, value) = (c_string(&name), c_string(&value));
+    if value.trim().is_empty() || matches!(name.as_str(), "" | "COMMENT" | "HISTORY" | "CONTINUE") {
+      continue;
+    }
+    records.push((name, value));
+  }
+
+  //(2) Compare the last occurrence of each keyword (the default duplicate
+  //policy of this crate is LastWins)
+  let mut seen = HashSet::new();
+  for (name, theirs) in records.iter().rev() {
+    if !seen.insert(name.clone()) {
+      continue;
+    }
+    match header.get_value(name) {
+      None => issues.push(format!("{loc}: keyword {name} is missing (cfitsio: {theirs})")),
+      Some(ours) if !same_value(ours, theirs) => {
+        issues.push(format!("{loc}: keyword {name} is {ours} (cfitsio: {theirs})"))
+      }
+      _ => {}
+    }
+  }
+  Ok(())
+}
+
+fn flatten(img: &rsf::TypedImage) -> Vec<f64> {
+  //Pixels in file order (the first axis runs fastest)
+  use rsf::TypedImage::*;
+  match img {
+    ByteImg(img) => img.get_data().t().iter().map(|&px| px as f64).collect(),
+    I16Img(img) => img.get_data().t().iter().map(|&px| px as f64).collect(),
+    I32Img(img) => img.get_data().t().iter().map(|&px| px as f64).collect(),
+    I64Img(img) => img.get_data().t().iter().map(|&px| px as f64).collect(),
+    SpfImg(img) => img.get_data().t().iter().map(|&px| px as f64).collect(),
+    DpfImg(img) => img.get_data().t().iter().copied().collect(),
+  }
+}
+
+fn compare_image(
+  fptr: *mut c_void,
+  img: &rsf::TypedImage,
+  loc: &str,
+  issues: &mut Vec<String>,
+) -> Result<(), String> {
+  //(1) Compare the shapes
+  let (mut naxis, mut status) = (0, 0);
+  unsafe { ffgidm(fptr, &mut naxis, &mut status) };
+  check(status, "ffgidm")?;
+  let mut naxes = vec![0i64; naxis as usize];
+  unsafe { ffgiszll(fptr, naxis, naxes.as_mut_ptr(), &mut status) };
+  check(status, "ffgiszll")?;
+  let theirs: Vec<usize> = naxes.iter().map(|&len| len as usize).collect();
+  let ours = rsf::impl_typed_image_dispatch!(img, img => img.get_shape().clone());
+  if ours != theirs {
+    issues.push(format!("{loc}: image shape is {ours:?} (cfitsio: {theirs:?})"));
+    return Ok(());
+  }
+
+  //(2) Compare the raw pixel values, this crate does not apply BSCALE/BZERO
+  let ours = flatten(img);
+  let mut theirs = vec![0.0; ours.len()];
+  let mut anynul = 0;
+  unsafe {
+    ffpscl(fptr, 1.0, 0.0, &mut status);
+    ffgpvd(
+      fptr,
+      1,
+      1,
+      theirs.len() as i64,
+      f64::NAN,
+      theirs.as_mut_ptr(),
+      &mut anynul,
+      &mut status,
+    );
+  }
+  check(status, "ffgpvd")?;
+
+  let mismatches: Vec<usize> =
+    (0..ours.len()).filter(|&idx| !same_number(ours[idx], theirs[idx])).collect();
+  if let Some(&first) = mismatches.first() {
+    issues.push(format!(
+      "{loc}: {} of {} pixels differ, first at {first}: {} (cfitsio: {})",
+      mismatches.len(),
+      ours.len(),
+      ours[first],
+      theirs[first]
+    ));
+  }
+  Ok(())
+}
+
+fn compare_table(
+  fptr: *mut c_void,
+  tbl: &rsf::AsciiTable,
+  loc: &str,
+  issues: &mut Vec<String>,
+) -> Result<(), String> {
+  //(1) Compare the shapes
+  let (mut ncols, mut nrows, mut status) = (0, 0i64, 0);
+  unsafe {
+    ffgncl(fptr, &mut ncols, &mut status);
+    ffgnrwll(fptr, &mut nrows, &mut status);
+  }
+  check(status, "ffgncl/ffgnrwll")?;
+  let theirs = (ncols as usize, nrows as usize);
+  if tbl.get_shape() != theirs {
+    issues.push(format!("{loc}: table shape is {:?} (cfitsio: {theirs:?})", tbl.get_shape()));
+    return Ok(());
+  }
+
+  //(2) Compare every column, as text or as numbers
+  for col in 0..theirs.0 {
+    let colnum = col as c_int + 1;
+    let (mut typecode, mut repeat, mut width) = (0, 0, 0);
+    unsafe {
+      ffgtcl(fptr, colnum, &mut typecode, &mut repeat, &mut width, &mut status);
+      fftscl(fptr, colnum, 1.0, 0.0, &mut status);
+    }
+    check(status, "ffgtcl")?;
+
+    let mut anynul = 0;
+    let mut first_issue = None;
+    let mut n_issues = 0;
+    for row in 0..theirs.1 {
+      let ours = tbl.get_entry(col, row).map_err(|err| err.to_string())?;
+      let (ours, theirs) = if typecode == TSTRING {
+        let mut buf = [0 as c_char; FLEN_BUF];
+        let mut buf_ptr = buf.as_mut_ptr();
+        let nulval = CString::new("").unwrap();
+        unsafe {
+          ffgcvs(
+            fptr,
+            colnum,
+            row as i64 + 1,
+            1,
+            1,
+            nulval.as_ptr(),
+            &mut buf_ptr,
+            &mut anynul,
+            &mut status,
+          )
+        };
+        check(status, "ffgcvs")?;
+        let theirs = c_string(&buf);
+        let ours = match ours {
+          rsf::TableEntry::Text(text) => text,
+          other => format!("{other:?}"),
+        };
+        if ours.trim_end() == theirs.trim_end() {
+          continue;
+        }
+        (ours, theirs)
+      } else {
+        let mut theirs = 0.0;
+        unsafe {
+          ffgcvd(
+            fptr,
+            colnum,
+            row as i64 + 1,
+            1,
+            1,
+            f64::NAN,
+            &mut theirs,
+            &mut anynul,
+            &mut status,
+          )
+        };
+        check(status, "ffgcvd")?;
+        let same = match &ours {
+          rsf::TableEntry::Int(int) => same_number(*int as f64, theirs),
+          rsf::TableEntry::Float(float) => same_number(*float, theirs),
+          _ => false,
+        };
+        if same {
+          continue;
+        }
+        (format!("{ours:?}"), theirs.to_string())
+      };
+      n_issues += 1;
+      first_issue.get_or_insert(format!("row {row}: {ours} (cfitsio: {theirs})"));
+    }
+
+    if let Some(first) = first_issue {
+      issues.push(format!("{loc}: column {col} differs in {n_issues} rows, first at {first}"));
+    }
+  }
+  Ok(())
+}
+
+fn entry_values(entry: &rsf::TableEntry) -> Vec<f64> {
+  //Elements of a binary table entry as numbers, in the way cfitsio reads them
+  use rsf::TableEntry::*;
+  match entry {
+    Int(int) => vec![*int as f64],
+    Float(float) => vec![*float],
+    Complex(re, im) => vec![*re, *im],
+    Text(text) => vec![(text == "T") as u8 as f64], //logicals
+    Array(entries) => entries.iter().flat_map(entry_values).collect(),
+  }
+}
+
+fn read_bin_values(
+  fptr: *mut c_void,
+  (colnum, row): (c_int, i64),
+  typecode: c_int,
+  nelem: i64,
+) -> Result<Vec<f64>, String> {
+  //cfitsio applies TSCALn and TZEROn, just like this crate does for binary
+  //tables. A null value of 0 turns off the check for undefined values
+  let (mut anynul, mut status) = (0, 0);
+  let values = match typecode {
+    TBIT => {
+      let mut bits = vec![0 as c_char; nelem as usize];
+      unsafe { ffgcx(fptr, colnum, row, 1, nelem, bits.as_mut_ptr(), &mut status) };
+      check(status, "ffgcx")?;
+      bits.iter().map(|&bit| bit as f64).collect()
+    }
+    TCOMPLEX | TDBLCOMPLEX => {
+      let mut values = vec![0.0; 2 * nelem as usize];
+      unsafe {
+        ffgcvm(fptr, colnum, row, 1, nelem, 0.0, values.as_mut_ptr(), &mut anynul, &mut status)
+      };
+      check(status, "ffgcvm")?;
+      values
+    }
+    _ => {
+      let mut values = vec![0.0; nelem as usize];
+      unsafe {
+        ffgcvd(fptr, colnum, row, 1, nelem, 0.0, values.as_mut_ptr(), &mut anynul, &mut status)
+      };
+      check(status, "ffgcvd")?;
+      values
+    }
+  };
+  Ok(values)
+}
+
+fn compare_bintable(
+  fptr: *mut c_void,
+  tbl: &rsf::BinTable,
+  loc: &str,
+  issues: &mut Vec<String>,
+) -> Result<(), String> {
+  //(1) Compare the shapes
+  let (mut ncols, mut nrows, mut status) = (0, 0i64, 0);
+  unsafe {
+    ffgncl(fptr, &mut ncols, &mut status);
+    ffgnrwll(fptr, &mut nrows, &mut status);
+  }
+  check(status, "ffgncl/ffgnrwll")?;
+  let theirs = (ncols as usize, nrows as usize);
+  if tbl.get_shape() != theirs {
+    issues.push(format!("{loc}: table shape is {:?} (cfitsio: {theirs:?})", tbl.get_shape()));
+    return Ok(());
+  }
+
+  //(2) Compare every column, strings as text and everything else element by
+  //element (variable-length arrays included)
+  for col in 0..theirs.0 {
+    let colnum = col as c_int + 1;
+    let (mut typecode, mut repeat, mut width) = (0, 0, 0);
+    unsafe { ffgtcl(fptr, colnum, &mut typecode, &mut repeat, &mut width, &mut status) };
+    check(status, "ffgtcl")?;
+    let (variable, typecode) = (typecode < 0, typecode.abs());
+
+    let mut first_issue = None;
+    let mut n_issues = 0;
+    for row in 0..theirs.1 {
+      let ours = tbl.get_entry(col, row).map_err(|err| err.to_string())?;
+      let (ours, theirs) = if typecode == TSTRING {
+        let mut buf = [0 as c_char; FLEN_BUF];
+        let mut buf_ptr = buf.as_mut_ptr();
+        let (nulval, mut anynul) = (CString::new("").unwrap(), 0);
+        unsafe {
+          ffgcvs(
+            fptr,
+            colnum,
+            row as i64 + 1,
+            1,
+            1,
+            nulval.as_ptr(),
+            &mut buf_ptr,
+            &mut anynul,
+            &mut status,
+          )
+        };
+        check(status, "ffgcvs")?;
+        let theirs = c_string(&buf);
+        let ours = match ours {
+          rsf::TableEntry::Text(text) => text,
+          other => format!("{other:?}"),
+        };
+        if ours.trim_end() == theirs.trim_end() {
+          continue;
+        }
+        (ours, theirs)
+      } else {
+        let nelem = match variable {
+          false => repeat,
+          true => {
+            let (mut length, mut heap_addr) = (0, 0);
+            unsafe {
+              ffgdesll(fptr, colnum, row as i64 + 1, &mut length, &mut heap_addr, &mut status)
+            };
+            check(status, "ffgdesll")?;
+            length
+          }
+        };
+        let ours = entry_values(&ours);
+        let theirs = read_bin_values(fptr, (colnum, row as i64 + 1), typecode, nelem)?;
+        let same = ours.len() == theirs.len()
+          && ours.iter().zip(&theirs).all(|(&ours, &theirs)| same_number(ours, theirs));
+        if same {
+          continue;
+        }
+        (format!("{ours:?}"), format!("{theirs:?}"))
+      };
+      n_issues += 1;
+      first_issue.get_or_insert(format!("row {row}: {ours} (cfitsio: {theirs})"));
+    }
+
+    if let Some(first) = first_issue {
+      issues.push(format!("{loc}: column {col} differs in {n_issues} rows, first at {first}"));
+    }
+  }
+  Ok(())
+}
+
+fn compare_file(
+  path: &Path,
+  issues: &mut Vec<String>,
+  skipped: &mut Vec<String>,
+) -> Result<(), String> {
+  let name = path.file_name().unwrap().to_string_lossy().into_owned();
+  let index = rsf::FitsIndex::build(path).map_err(|err| format!("{name}: {err}"))?;
+  let file = CfitsFile::open(path).map_err(|err| format!("{name}: {err}"))?;
+
+  //(1) Compare the number of HDU's
+  let (mut nhdu, mut status) = (0, 0);
+  unsafe { ffthdu(file.0, &mut nhdu, &mut status) };
+  check(status, "ffthdu")?;
+  if nhdu as usize != index.get_num_hdus() {
+    issues.push(format!("{name}: {} HDU's (cfitsio: {nhdu})", index.get_num_hdus()));
+  }
+
+  //(2) Compare each HDU that both libraries found
+  for hdu in 0..index.get_num_hdus().min(nhdu as usize) {
+    let loc = format!("{name} HDU {hdu}");
+    let mut exttype = 0;
+    unsafe { ffmahd(file.0, hdu as c_int + 1, &mut exttype, &mut status) };
+    check(status, "ffmahd")?;
+
+    let header = index.read_header(hdu).map_err(|err| format!("{loc}: {err}"))?.unwrap();
+    compare_header(file.0, &header, &loc, issues)?;
+
+    let hdu = match index.read_hdu(hdu) {
+      Ok(hdu) => hdu.unwrap(),
+      Err(err) => {
+        skipped.push(format!("{loc}: data not supported ({err})"));
+        continue;
+      }
+    };
+    match (hdu.get_data(), exttype) {
+      (Some(rsf::Extension::Image(img)), IMAGE_HDU) => compare_image(file.0, img, &loc, issues)?,
+      (Some(rsf::Extension::AsciiTable(tbl)), ASCII_TBL) => {
+        compare_table(file.0, tbl, &loc, issues)?
+      }
+      (Some(rsf::Extension::BinTable(tbl)), BINARY_TBL) => {
+        compare_bintable(file.0, tbl, &loc, issues)?
+      }
+      (None, _) => {}
+      (Some(_), _) => issues.push(format!("{loc}: HDU type differs (cfitsio type code {exttype})")),
+    }
+  }
+  Ok(())
+}
+
+#[test]
+fn cfitsio_compare_test() {
+  let mut files: Vec<_> = fs::read_dir("resources")
+    .unwrap()
+    .map(|entry| entry.unwrap().path())
+    .filter(|path| path.extension().is_some_and(|ext| ext == "fits"))
+    .collect();
+  files.sort();
+  assert!(!files.is_empty());
+
+  let (mut issues, mut skipped) = (Vec::new(), Vec::new());
+  for path in &files {
+    if let Err(err) = compare_file(path, &mut issues, &mut skipped) {
+      issues.push(err);
+    }
+  }
+
+  for skip in &skipped {
+    println!("skipped {skip}");
+  }
+  for issue in &issues {
+    println!("discrepancy in {issue}");
+  }
+  assert!(issues.is_empty(), "{} discrepancies with cfitsio (see output)", issues.len());
+}
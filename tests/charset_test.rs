@@ -0,0 +1,101 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{error::Error, fs, path::PathBuf};
+
+use rustronomy_fits::{self as rsf, KeywordValue};
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+struct Text(String);
+
+impl KeywordValue for Text {
+  fn parse_value(raw: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    Ok(Text(rsf::unquote(raw).ok_or("not a string")?))
+  }
+  fn format_value(&self) -> String {
+    rsf::quote(&self.0)
+  }
+}
+
+fn cache_dir() -> PathBuf {
+  let mut dir = dirs::cache_dir().unwrap();
+  dir.push("charset_test");
+  fs::create_dir_all(&dir).unwrap();
+  dir
+}
+
+#[test]
+fn read_charset_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+
+  //Put a Latin-1 e-acute in the target name
+  let mut bytes = fs::read(&real_path).unwrap();
+  let card = bytes.windows(18).position(|window| window == b"TARGNAME= 'NGC4151").unwrap();
+  bytes[card + 14] = 0xE9;
+  let latin1 = cache_dir().join("latin1.fits");
+  fs::write(&latin1, &bytes).unwrap();
+
+  //(1) Strict reading refuses the file, pointing at the offending record
+  let err = rsf::Fits::open(&latin1).unwrap_err();
+  let err = err.downcast_ref::<rsf::header_err::IllegalCharErr>().unwrap();
+  assert_eq!(err.get_byte(), 0xE9);
+  assert_eq!(err.get_column(), 15);
+  assert!(err.get_card().starts_with("TARGNAME= 'NGCé151"));
+
+  //(2) Lenient reading replaces the byte and reports it
+  let opts = rsf::ReadOptions::new().header_charset(rsf::CharsetPolicy::Replace(b'?'));
+  let fits = rsf::Fits::open_with(&latin1, &opts).unwrap();
+  let targname: Text = fits.primary().unwrap().get_header().get_value_with("TARGNAME").unwrap();
+  assert_eq!(targname.0, "NGC?151");
+
+  let repairs = fits.charset_repairs();
+  assert_eq!(repairs.len(), 1);
+  assert_eq!(repairs[0].get_hdu(), 0);
+  assert_eq!(repairs[0].get_keyword(), "TARGNAME");
+  assert!(repairs[0].get_value().contains("NGCé151"));
+  assert_eq!(repairs[0].get_severity(), rsf::Severity::Warning);
+}
+
+#[test]
+fn write_charset_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let out = cache_dir().join("observer.fits");
+  let open_with_observer = || {
+    let mut fits = rsf::Fits::open(&real_path).unwrap();
+    let header = fits.get_hdu_mut(1).unwrap().get_header_mut();
+    header.set_value_with("OBSERVER", &Text(String::from("Müller")), None).unwrap();
+    fits
+  };
+
+  //(1) Strict writing refuses the file
+  let err = open_with_observer().write_with(&out, &rsf::WriteOptions::new()).unwrap_err();
+  let err = err.downcast_ref::<rsf::validation_err::ValidationErr>().unwrap();
+  assert_eq!(err.get_diagnostics().len(), 1);
+  assert_eq!(err.get_diagnostics()[0].get_hdu(), 1);
+  assert_eq!(err.get_diagnostics()[0].get_keyword(), "OBSERVER");
+
+  //(2) Lenient writing replaces the character
+  let opts = rsf::WriteOptions::new().header_charset(rsf::CharsetPolicy::Replace(b'_'));
+  open_with_observer().write_with(&out, &opts).unwrap();
+  let fits = rsf::Fits::open(&out).unwrap();
+  let observer: Text = fits.get_hdu(1).unwrap().get_header().get_value_with("OBSERVER").unwrap();
+  assert_eq!(observer.0, "M_ller");
+}
@@ -0,0 +1,126 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{error::Error, path::PathBuf};
+
+use rustronomy_fits::{self as rsf, KeywordValue};
+
+static TABLE_FILE: &str = "resources/Hubble_WFPC2_1.fits";
+
+struct Raw(&'static str);
+
+impl KeywordValue for Raw {
+  fn parse_value(_raw: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    Err("only used for writing".into())
+  }
+  fn format_value(&self) -> String {
+    self.0.to_string()
+  }
+}
+
+fn column_index(header: &rsf::Header, ttype: &str) -> usize {
+  //The (0-based) index of the column with the given TTYPE
+  let n_fields: usize = header.get_value_as("TFIELDS").unwrap();
+  let name = |n: &usize| rsf::unquote(header.get_value(&format!("TTYPE{n}")).unwrap()).unwrap();
+  (1..=n_fields).find(|n| name(n).trim() == ttype).unwrap() - 1
+}
+
+fn image(hdu: &rsf::HeaderDataUnit) -> &rsf::TypedImage {
+  match hdu.get_data() {
+    Some(rsf::Extension::Image(img)) => img,
+    _ => panic!("HDU has no image"),
+  }
+}
+
+#[test]
+fn column_to_image_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(TABLE_FILE);
+  let fits = rsf::Fits::open(&real_path).unwrap();
+  let table_hdu = fits.get_hdu(1).unwrap();
+  let rsf::Extension::AsciiTable(table) = table_hdu.get_data().unwrap() else { panic!() };
+
+  //(1) A float column becomes a 1-D f64 image named after the column
+  let col = column_index(table_hdu.get_header(), "CRVAL1");
+  let img_hdu = table_hdu.column_to_image(col).unwrap();
+  let img = image(&img_hdu).as_array::<f64>().unwrap();
+  assert_eq!(img.shape(), [4]);
+  for (row, &px) in img.iter().enumerate() {
+    assert_eq!(px, f64::try_from(table.get_entry(col, row).unwrap()).unwrap());
+  }
+  let header = img_hdu.get_header();
+  assert_eq!(header.get_value("XTENSION").unwrap(), "'IMAGE   '");
+  assert_eq!(header.get_value_as::<isize>("BITPIX").unwrap(), -64);
+  assert_eq!(header.get_value_as::<usize>("NAXIS1").unwrap(), 4);
+  assert_eq!(img_hdu.get_extname().as_deref(), Some("CRVAL1"));
+
+  //(2) Text columns and non-table HDU's cannot be converted
+  let text_col = column_index(table_hdu.get_header(), "CTYPE1");
+  assert!(table_hdu.column_to_image(text_col).is_err());
+  assert!(fits.get_hdu(0).unwrap().column_to_image(0).is_err());
+}
+
+#[test]
+fn image_to_column_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(TABLE_FILE);
+  let fits = rsf::Fits::open(&real_path).unwrap();
+  let table_hdu = fits.get_hdu(1).unwrap();
+  let mut spectrum =
+    table_hdu.column_to_image(column_index(table_hdu.get_header(), "CRVAL1")).unwrap();
+
+  //Give the spectrum a wavelength axis
+  let header = spectrum.get_header_mut();
+  for (keyword, value) in [
+    ("CRPIX1", "1.0"),
+    ("CRVAL1", "6563.0"),
+    ("CDELT1", "0.5"),
+    ("CTYPE1", "'WAVE    '"),
+    ("BUNIT", "'erg/s/cm2/Angstrom'"),
+  ] {
+    header.set_value_with(keyword, &Raw(value), None).unwrap();
+  }
+
+  //(1) The image becomes a single float column, with column WCS keywords
+  let column = spectrum.image_to_column("FLUX").unwrap();
+  let header = column.get_header();
+  assert_eq!(header.get_value("XTENSION").unwrap(), "'TABLE   '");
+  assert_eq!(header.get_value("TTYPE1").unwrap(), "'FLUX'");
+  assert_eq!(header.get_value("TFORM1").unwrap(), "'D25.17'");
+  assert_eq!(header.get_value_as::<usize>("NAXIS2").unwrap(), 4);
+  assert_eq!(header.get_value_as::<f64>("TCRVL1").unwrap(), 6563.0);
+  assert_eq!(header.get_value_as::<f64>("TCDLT1").unwrap(), 0.5);
+  assert_eq!(header.get_value("TCTYP1").unwrap(), "'WAVE    '");
+  assert_eq!(header.get_value("TUNIT1").unwrap(), "'erg/s/cm2/Angstrom'");
+  let rsf::Extension::AsciiTable(table) = column.get_data().unwrap() else { panic!() };
+  assert_eq!(table.get_shape(), (1, 4));
+
+  //(2) ...and back again
+  let back = column.column_to_image(0).unwrap();
+  let header = back.get_header();
+  assert_eq!(header.get_value_as::<f64>("CRVAL1").unwrap(), 6563.0);
+  assert_eq!(header.get_value_as::<f64>("CRPIX1").unwrap(), 1.0);
+  assert_eq!(header.get_value("BUNIT").unwrap(), "'erg/s/cm2/Angstrom'");
+  assert_eq!(back.get_extname().as_deref(), Some("FLUX"));
+  assert_eq!(image(&back).as_array::<f64>().unwrap(), image(&spectrum).as_array::<f64>().unwrap());
+
+  //(3) Only 1-D images can become a column
+  let primary = fits.get_hdu(0).unwrap();
+  let err = primary.image_to_column("FLUX").unwrap_err();
+  assert!(err.downcast_ref::<rsf::hdu_err::MissingDataErr>().is_some());
+}
@@ -0,0 +1,91 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::path::PathBuf;
+
+use rustronomy_fits as rsf;
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+#[test]
+fn commentary_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let mut fits = rsf::Fits::open(&real_path).unwrap();
+
+  //Commentary records are not part of the keyword map
+  let header = fits.get_hdu(1).unwrap().get_header();
+  let (n_comments, n_history) = (header.comments().count(), header.history().count());
+  assert!(n_history > 1);
+  assert!(header.get_value("HISTORY").is_none() && header.get_comment("HISTORY").is_none());
+  let history: Vec<String> = header.history().map(String::from).collect();
+
+  //Long text is split over multiple records
+  let header = fits.get_hdu_mut(1).unwrap().get_header_mut();
+  header.append_history("calibrated with rustronomy");
+  header.append_comment(&"x".repeat(100));
+  assert_eq!(header.history().last(), Some("calibrated with rustronomy"));
+  assert_eq!(
+    header.comments().skip(n_comments).collect::<Vec<_>>(),
+    ["x".repeat(72), "x".repeat(28)]
+  );
+
+  //Commentary records survive a round trip, in order
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("commentary.fits");
+  fits.write(&path).unwrap();
+  let fits = rsf::Fits::open(&path).unwrap();
+  let header = fits.get_hdu(1).unwrap().get_header();
+  assert_eq!(header.comments().count(), n_comments + 2);
+  assert_eq!(header.history().take(n_history).collect::<Vec<_>>(), history);
+  assert_eq!(header.history().nth(n_history), Some("calibrated with rustronomy"));
+}
+
+#[test]
+fn commentary_position_test() {
+  let text = "SIMPLE  =                    T
+BITPIX  =                    8
+NAXIS   =                    0
+COMMENT   about the observation
+OBSERVER= 'Hubble  '
+        layout only
+HISTORY flat fielded
+EXPTIME =                 10.0
+END
+";
+  let mut header = rsf::Header::from_text(text).unwrap();
+
+  //Commentary records keep their place between the valued records
+  let keywords = |header: &rsf::Header| -> Vec<String> {
+    header
+      .to_text()
+      .unwrap()
+      .lines()
+      .map(|line| line.get(..8).unwrap_or(line).trim_end().to_string())
+      .collect()
+  };
+  let order = ["SIMPLE", "BITPIX", "NAXIS", "COMMENT", "OBSERVER", "", "HISTORY", "EXPTIME", "END"];
+  assert_eq!(keywords(&header), order);
+  assert!(header.to_text().unwrap().contains("\n        layout only\n"));
+
+  //Appended commentary goes after the records present at that moment
+  header.append_history("stacked");
+  header.set_value_with("AIRMASS", &rsf::MetaValue::Float(1.2), None).unwrap();
+  let order = &keywords(&header)[7..];
+  assert_eq!(order, ["EXPTIME", "HISTORY", "AIRMASS", "END"]);
+}
@@ -0,0 +1,120 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use ndarray::{Array, Array2, Array3};
+use rustronomy_fits::{self as rsf, Bitpix, CompactImage, Stack};
+
+fn flat_field() -> rsf::TypedImage {
+  //1000x800 flat that is 1.0 everywhere, except for a vignetted corner and a
+  //blanked (NaN) column
+  let mut data = Array2::<f32>::from_elem((1000, 800), 1.0);
+  for ((x, y), px) in data.indexed_iter_mut() {
+    if x + y < 20 {
+      *px = 0.5 + (x * y) as f32 / 1e4;
+    }
+    if y == 400 {
+      *px = f32::NAN;
+    }
+  }
+  rsf::TypedImage::from(rsf::Image::new(data.into_dyn()))
+}
+
+fn same_pixels(a: &rsf::TypedImage, b: &rsf::TypedImage) -> bool {
+  //Pixel by pixel, counting NaN's as equal
+  let a = a.to_bitpix(Bitpix::Dpf).as_owned_f64_array().unwrap();
+  let b = b.to_bitpix(Bitpix::Dpf).as_owned_f64_array().unwrap();
+  a.shape() == b.shape()
+    && a.iter().zip(b.iter()).all(|(x, y)| x == y || (x.is_nan() && y.is_nan()))
+}
+
+#[test]
+fn roundtrip_test() {
+  //(1) Constant tiles take up next to nothing
+  let img = flat_field();
+  let compact = CompactImage::from_image(&img);
+  assert_eq!(compact.get_bitpix(), Bitpix::Spf);
+  assert_eq!(compact.get_shape(), &[1000, 800]);
+  assert_eq!(compact.get_tile_len(), 4096);
+  assert_eq!(compact.get_num_tiles(), (1000 * 800usize).div_ceil(4096));
+  assert!(compact.get_num_constant_tiles() < compact.get_num_tiles());
+  assert!(compact.get_memory_usage() * 10 < 1000 * 800 * 4);
+
+  //(2) and the image comes back unchanged
+  let restored = compact.to_image();
+  assert!(matches!(restored, rsf::TypedImage::SpfImg(_)));
+  assert!(same_pixels(&img, &restored));
+  assert!(same_pixels(&img, &rsf::TypedImage::from(&CompactImage::from(&img))));
+}
+
+#[test]
+fn dedup_test() {
+  //Identical tiles are only stored once
+  let row: Vec<i64> = (0..64).map(|px| px << 56).collect();
+  let data = Array::from_shape_fn((64, 32), |(x, _)| row[x]);
+  let img = rsf::TypedImage::from(rsf::Image::new(data.into_dyn()));
+  let compact = CompactImage::from_image_tiled(&img, 64);
+  assert_eq!(compact.get_num_tiles(), 32);
+  assert_eq!(compact.get_num_constant_tiles(), 0);
+  assert_eq!(compact.get_num_stored_tiles(), 1);
+  assert!(same_pixels(&img, &compact.to_image()));
+
+  //Large integers that are equal as f64 are still told apart
+  let data = Array::from_shape_fn((2, 1), |(x, _)| (1i64 << 60) + x as i64);
+  let img = rsf::TypedImage::from(rsf::Image::new(data.into_dyn()));
+  let compact = CompactImage::from_image_tiled(&img, 2);
+  assert_eq!(compact.get_num_constant_tiles(), 0);
+  let rsf::TypedImage::I64Img(restored) = compact.to_image() else { panic!("not an i64 image") };
+  assert_eq!(restored.get_data()[[1, 0]], (1i64 << 60) + 1);
+}
+
+#[test]
+fn edge_cases_test() {
+  //Odd shapes (a partial last tile) and signed zeros survive
+  let data = Array3::from_shape_fn((7, 5, 3), |(x, y, z)| match (x + y + z) % 4 {
+    0 => -0.0,
+    _ => (x * 100 + y * 10 + z) as f64,
+  });
+  let img = rsf::TypedImage::from(rsf::Image::new(data.clone().into_dyn()));
+  let compact = CompactImage::from_image_tiled(&img, 0);
+  assert_eq!(compact.get_tile_len(), 1);
+  let rsf::TypedImage::DpfImg(restored) = compact.to_image() else { panic!("not an f64 image") };
+  assert_eq!(restored.get_data().shape(), &[7, 5, 3]);
+  assert!(restored.get_data().iter().zip(data.iter()).all(|(a, b)| a.to_bits() == b.to_bits()));
+
+  let compact = CompactImage::from_image_tiled(&img, 16);
+  assert_eq!(compact.get_num_tiles(), (7 * 5 * 3usize).div_ceil(16));
+  assert!(same_pixels(&img, &compact.to_image()));
+}
+
+#[test]
+fn stack_test() {
+  //Compact frames can be stacked directly
+  let frames: Vec<CompactImage> = (1..=3)
+    .map(|value| {
+      let data = Array2::from_elem((50, 40), value as u8);
+      CompactImage::from_image(&rsf::TypedImage::from(rsf::Image::new(data.into_dyn())))
+    })
+    .collect();
+  assert!(frames.iter().all(|frame| frame.get_num_stored_tiles() == 0));
+  let mut stack = Stack::new();
+  for frame in &frames {
+    stack.add_compact(frame, None, None).unwrap();
+  }
+  assert!(stack.mean().as_owned_f64_array().unwrap().iter().all(|&px| px == 2.0));
+}
@@ -0,0 +1,68 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{fs, path::PathBuf};
+
+use rustronomy_fits as rsf;
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+#[test]
+fn concat_split_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let original = rsf::Fits::open(&real_path).unwrap();
+  let n_hdus = original.get_num_hdus();
+
+  let mut dir = dirs::cache_dir().unwrap();
+  dir.push("concat_test");
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).unwrap();
+
+  //The second primary HDU is demoted to an IMAGE extension
+  let merged_path = dir.join("merged.fits");
+  rsf::Fits::concat(&[&real_path, &real_path], &merged_path).unwrap();
+  let merged = rsf::Fits::open(&merged_path).unwrap();
+  assert_eq!(merged.get_num_hdus(), 2 * n_hdus);
+  let primary = merged.primary().unwrap().get_header();
+  assert_eq!(primary.get_value("EXTEND").unwrap(), "T");
+  let demoted = merged.get_hdu(n_hdus).unwrap().get_header();
+  assert!(demoted.get_value("SIMPLE").is_none());
+  assert_eq!(demoted.get_value("XTENSION").unwrap(), "'IMAGE   '");
+  assert_eq!(demoted.get_value("PCOUNT").unwrap(), "0");
+  assert_eq!(demoted.get_value("GCOUNT").unwrap(), "1");
+  assert_eq!(
+    format!("{}", merged.get_hdu(n_hdus + 1).unwrap().pretty_print_data()),
+    format!("{}", original.get_hdu(1).unwrap().pretty_print_data())
+  );
+
+  //Splitting the original gives one file per extension (the primary HDU is
+  //empty). Image extensions become the primary HDU of their file
+  let parts = rsf::Fits::split(&real_path, &dir).unwrap();
+  assert_eq!(parts.len(), n_hdus - 1);
+  assert_eq!(parts[0], dir.join("Hubble_NICMOS_1.fits"));
+  let part = rsf::Fits::open(&parts[0]).unwrap();
+  assert_eq!(part.get_num_hdus(), 1);
+  let header = part.primary().unwrap().get_header();
+  assert_eq!(header.get_value("SIMPLE").unwrap(), "T");
+  assert!(header.get_value("XTENSION").is_none() && header.get_value("PCOUNT").is_none());
+  assert_eq!(
+    part.primary().unwrap().pretty_print_data(),
+    original.get_hdu(1).unwrap().pretty_print_data()
+  );
+}
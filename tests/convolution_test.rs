@@ -0,0 +1,89 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use ndarray::{array, Array, Array2, Axis};
+use rustronomy_fits::{self as rsf, Boundary};
+
+fn convolve(data: Array2<f64>, kernel: &Array2<f64>, boundary: Boundary) -> Array2<f64> {
+  let img = rsf::TypedImage::from(rsf::Image::new(data.into_dyn()));
+  let out = img.convolve(kernel.view(), boundary, &rsf::PromotionRules::new());
+  out.as_owned_f64_array().unwrap().into_dimensionality().unwrap()
+}
+
+#[test]
+fn kernel_test() {
+  //Built-in kernels are normalized and symmetric
+  let gauss = rsf::gaussian_kernel(1.5);
+  assert_eq!(gauss.dim(), (13, 13));
+  assert!((gauss.sum() - 1.0).abs() < 1e-12);
+  assert_eq!(gauss[[6, 6]], gauss.iter().copied().fold(0.0, f64::max));
+  assert_eq!(gauss[[2, 5]], gauss[[5, 2]]);
+  assert_eq!(rsf::gaussian_kernel(0.0), array![[1.0]]);
+
+  let boxcar = rsf::boxcar_kernel(3);
+  assert_eq!(boxcar, Array2::from_elem((3, 3), 1.0 / 9.0));
+}
+
+#[test]
+fn convolve_test() {
+  //(1) A single bright pixel turns into the kernel, which is not flipped
+  let mut delta = Array2::zeros((7, 6));
+  delta[[3, 2]] = 2.0;
+  let kernel = array![[1.0, 2.0, 0.0], [0.0, 3.0, 0.0], [0.0, 0.0, 4.0]];
+  let out = convolve(delta, &kernel, Boundary::Fill(0.0));
+  assert_eq!(out.slice(ndarray::s![2..5, 1..4]), kernel.mapv(|w| 2.0 * w));
+  assert_eq!(out.sum(), 20.0);
+
+  //(2) Boundaries: a boxcar over a flat image only changes near the edges
+  let flat = Array2::from_elem((5, 4), 1.0);
+  let boxcar = rsf::boxcar_kernel(3);
+  let filled = convolve(flat.clone(), &boxcar, Boundary::Fill(0.0));
+  assert!((filled[[0, 0]] - 4.0 / 9.0).abs() < 1e-12);
+  assert!((filled[[2, 1]] - 1.0).abs() < 1e-12);
+  for boundary in [Boundary::Extend, Boundary::Wrap, Boundary::Mirror] {
+    let out = convolve(flat.clone(), &boxcar, boundary);
+    assert!(out.iter().all(|px| (px - 1.0).abs() < 1e-12), "{boundary:?}");
+  }
+
+  //(3) ...but they do differ on a ramp
+  let ramp = Array::from_shape_fn((4, 1), |(x, _)| x as f64);
+  let row = array![[1.0], [0.0], [0.0]];
+  let shifted = |boundary| convolve(ramp.clone(), &row, boundary).index_axis(Axis(1), 0).to_vec();
+  assert_eq!(shifted(Boundary::Fill(-1.0)), vec![1.0, 2.0, 3.0, -1.0]);
+  assert_eq!(shifted(Boundary::Extend), vec![1.0, 2.0, 3.0, 3.0]);
+  assert_eq!(shifted(Boundary::Wrap), vec![1.0, 2.0, 3.0, 0.0]);
+  assert_eq!(shifted(Boundary::Mirror), vec![1.0, 2.0, 3.0, 3.0]);
+}
+
+#[test]
+fn convolve_typed_test() {
+  //Integer cubes become double precision, and every plane is convolved alone
+  let cube = Array::from_shape_fn((4, 4, 2), |(x, y, z)| ((x + y) * (z + 1)) as i16);
+  let img = rsf::TypedImage::from(rsf::Image::new(cube.into_dyn()));
+  let rules = rsf::PromotionRules::new();
+  let out = img.convolve(rsf::boxcar_kernel(1).view(), Boundary::Extend, &rules);
+  assert!(matches!(out.get_bitpix(), rsf::Bitpix::Dpf));
+  let out = out.as_owned_f64_array().unwrap();
+  assert_eq!(out.shape(), &[4, 4, 2]);
+  assert_eq!(out[[3, 2, 1]], 10.0);
+
+  let smooth = img.convolve(rsf::gaussian_kernel(1.0).view(), Boundary::Mirror, &rules);
+  let smooth = smooth.as_owned_f64_array().unwrap();
+  let (plane0, plane1) = (smooth.index_axis(Axis(2), 0), smooth.index_axis(Axis(2), 1));
+  assert!(plane0.iter().zip(plane1.iter()).all(|(a, b)| (2.0 * a - b).abs() < 1e-9));
+}
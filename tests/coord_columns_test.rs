@@ -0,0 +1,89 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use ndarray::array;
+use rustronomy_fits as rsf;
+
+fn catalog(unit: &str) -> rsf::HeaderDataUnit {
+  //Single coordinate column made from a 1-D image
+  let header = rsf::Header::from_text(&format!("BUNIT   = '{unit}'")).unwrap();
+  let mut path = dirs::cache_dir().unwrap();
+  path.push(format!("coord_columns_{unit}.fits"));
+  let mut stream =
+    rsf::ImageStreamWriter::<f64>::create_with_header(&path, &[4], Some(&header)).unwrap();
+  stream.write_row(&array![187.5, -1.034, 359.99999999, f64::NAN].to_vec()).unwrap();
+  stream.finish().unwrap();
+  rsf::Fits::open(&path).unwrap().get_hdu(0).unwrap().image_to_column("coord").unwrap()
+}
+
+fn texts(hdu: &rsf::HeaderDataUnit, col: usize) -> Vec<String> {
+  hdu.to_ipac().unwrap();
+  let rsf::Extension::AsciiTable(table) = hdu.get_data().unwrap() else { panic!() };
+  (0..table.get_shape().1)
+    .map(|row| match table.get_entry(col, row).unwrap() {
+      rsf::TableEntry::Text(text) => text.trim().to_string(),
+      other => panic!("{other:?}"),
+    })
+    .collect()
+}
+
+#[test]
+fn degrees_to_sexagesimal_test() {
+  //(1) Hours, with the seconds rounding over into the next hour
+  let hdu = catalog("deg");
+  let ra = hdu.degrees_to_sexagesimal(0, "RA", rsf::SexagesimalUnit::Hours, 2).unwrap();
+  assert_eq!(texts(&ra, 1), ["12:30:00.00", "-00:04:08.16", "24:00:00.00", ""]);
+  let header = ra.get_header();
+  assert_eq!(header.get_value_as::<usize>("TFIELDS").unwrap(), 2);
+  assert_eq!(header.get_value("TTYPE2").unwrap(), "'RA'");
+  assert_eq!(header.get_value("TUNIT2").unwrap(), "'h:m:s'");
+
+  //(2) Degrees always carry a sign
+  let dec = hdu.degrees_to_sexagesimal(0, "DEC", rsf::SexagesimalUnit::Degrees, 1).unwrap();
+  assert_eq!(texts(&dec, 1), ["+187:30:00.0", "-01:02:02.4", "+360:00:00.0", ""]);
+
+  //(3) Source columns in other units and unknown units
+  let arcmin = catalog("arcmin").degrees_to_sexagesimal(0, "DEC", rsf::SexagesimalUnit::Degrees, 0);
+  assert_eq!(texts(&arcmin.unwrap(), 1)[0], "+03:07:30");
+  assert!(catalog("Jy").degrees_to_sexagesimal(0, "RA", rsf::SexagesimalUnit::Hours, 2).is_err());
+}
+
+#[test]
+fn sexagesimal_to_degrees_test() {
+  //(1) Round trip through both representations
+  let hdu = catalog("deg");
+  let ra = hdu.degrees_to_sexagesimal(0, "RA", rsf::SexagesimalUnit::Hours, 4).unwrap();
+  let back = ra.sexagesimal_to_degrees(1, "RA_DEG").unwrap();
+  let rsf::Extension::AsciiTable(table) = back.get_data().unwrap() else { panic!() };
+  let degrees: Vec<f64> = (0..4)
+    .map(|row| match table.get_entry(2, row).unwrap() {
+      rsf::TableEntry::Float(float) => float,
+      other => panic!("{other:?}"),
+    })
+    .collect();
+  assert!((degrees[0] - 187.5).abs() < 1e-9);
+  assert!((degrees[1] + 1.034).abs() < 1e-6);
+  assert!(degrees[3].is_nan());
+  assert_eq!(back.get_header().get_value("TUNIT3").unwrap(), "'deg'");
+  back.to_ipac().unwrap();
+
+  //(2) Sexagesimal columns need a sexagesimal unit, numeric columns are refused
+  assert!(hdu.sexagesimal_to_degrees(0, "X").is_err());
+  let dec = hdu.degrees_to_sexagesimal(0, "DEC", rsf::SexagesimalUnit::Degrees, 1).unwrap();
+  assert!(dec.sexagesimal_to_degrees(0, "X").is_err());
+}
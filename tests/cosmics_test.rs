@@ -0,0 +1,113 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::path::PathBuf;
+
+use ndarray::{Array, Array2, Axis};
+use rustronomy_fits::{self as rsf, hdu_err::MissingDataErr, Cosmics};
+
+fn exposure() -> Array2<f64> {
+  //Sky of 100 ADU with noise of sigma 10 (gain 1, read noise 5), and two stars
+  let mut state = 4242u64;
+  let mut uniform = move || {
+    state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    (state >> 11) as f64 / (1u64 << 53) as f64
+  };
+  let mut img = Array::from_shape_simple_fn((80, 60), || {
+    100.0 + 10.0 * ((0..12).map(|_| uniform()).sum::<f64>() - 6.0)
+  });
+  for (sx, sy) in [(20.0, 20.0), (55.3, 40.6)] {
+    for ((x, y), px) in img.indexed_iter_mut() {
+      let r2 = (x as f64 - sx).powi(2) + (y as f64 - sy).powi(2);
+      *px += 3000.0 * (-r2 / (2.0 * 1.8 * 1.8)).exp();
+    }
+  }
+  img
+}
+
+const HITS: [(usize, usize); 5] = [(10, 45), (60, 10), (61, 10), (62, 11), (40, 30)];
+
+fn with_hits(img: &Array2<f64>) -> Array2<f64> {
+  let mut img = img.clone();
+  for (x, y) in HITS {
+    img[[x, y]] += 800.0;
+  }
+  img
+}
+
+#[test]
+fn detect_test() {
+  let clean = exposure();
+  let img = rsf::TypedImage::from(rsf::Image::new(with_hits(&clean).into_dyn()));
+  let result = Cosmics::new().gain(1.0).read_noise(5.0).detect(&img);
+
+  //(1) The hits are found, the stars are left alone
+  let mask = result.get_mask().view().into_dimensionality::<ndarray::Ix2>().unwrap();
+  for (x, y) in HITS {
+    assert!(mask[[x, y]], "cosmic ray at ({x}, {y}) was not found");
+  }
+  for (x, y) in [(20, 20), (55, 40), (55, 41), (21, 20)] {
+    assert!(!mask[[x, y]], "star pixel ({x}, {y}) was flagged");
+  }
+  assert!(result.get_n_cosmics() < 2 * HITS.len(), "{} pixels flagged", result.get_n_cosmics());
+  assert!(result.get_n_iterations() >= 2);
+
+  //(2) Cleaned pixels are back at the sky level
+  let cleaned = result.get_cleaned().as_f64_array().unwrap();
+  for (x, y) in HITS {
+    assert!((cleaned[[x, y]] - 100.0).abs() < 30.0, "{}", cleaned[[x, y]]);
+  }
+  assert_eq!(cleaned[[20, 20]], clean[[20, 20]]);
+
+  //(3) Without hits, nothing (or next to nothing) is flagged
+  let img = rsf::TypedImage::from(rsf::Image::new(clean.into_dyn()));
+  assert!(Cosmics::new().gain(1.0).read_noise(5.0).detect(&img).get_n_cosmics() <= 1);
+}
+
+#[test]
+fn detect_hdu_test() {
+  //(1) Integer cube with the gain and read noise in the header
+  let planes = [with_hits(&exposure()), exposure()];
+  let header = rsf::Header::from_text("GAIN    = 2.0\nRDNOISE = 5.0").unwrap();
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("cosmics_cube.fits");
+  let mut stream =
+    rsf::ImageStreamWriter::<i32>::create_with_header(&path, &[80, 60, 2], Some(&header)).unwrap();
+  for plane in &planes {
+    for row in plane.lanes(Axis(0)) {
+      stream.write_row(&row.iter().map(|px| px.round() as i32).collect::<Vec<_>>()).unwrap();
+    }
+  }
+  stream.finish().unwrap();
+
+  //(2) Every plane is an exposure of its own
+  let fits = rsf::Fits::open(&path).unwrap();
+  let result = Cosmics::new().detect_hdu(fits.get_hdu(0).unwrap()).unwrap();
+  let mask = result.get_mask();
+  assert_eq!(mask.shape(), &[80, 60, 2]);
+  assert!(HITS.iter().all(|&(x, y)| mask[[x, y, 0]]));
+  assert!(mask.index_axis(Axis(2), 1).iter().filter(|&&bad| bad).count() <= 1);
+  assert!(matches!(result.get_cleaned().get_bitpix(), rsf::Bitpix::Dpf));
+
+  //(3) HDU's without an image are refused (NICMOS has an empty primary HDU)
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push("resources/Hubble_NICMOS.fits");
+  let nicmos = rsf::Fits::open(&real_path).unwrap();
+  let err = Cosmics::new().detect_hdu(nicmos.get_hdu(0).unwrap()).unwrap_err();
+  assert!(err.downcast_ref::<MissingDataErr>().is_some());
+}
@@ -0,0 +1,113 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::path::PathBuf;
+
+use rustronomy_fits::{self as rsf, img_err::SkyPositionErr};
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+fn synthetic_image() -> PathBuf {
+  //100x80 image with 1" pixels around (150, 2). Pixel values encode their index
+  let wcs = rsf::Header::from_text(
+    "CTYPE1  = 'RA---TAN'\nCTYPE2  = 'DEC--TAN'\nCRPIX1  = 50.0\nCRPIX2  = 40.0\n\
+     CRVAL1  = 150.0\nCRVAL2  = 2.0\nCD1_1   = -2.777777777777778E-4\nCD2_2   = 2.777777777777778E-4",
+  )
+  .unwrap();
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("cutout_sky.fits");
+  let mut stream =
+    rsf::ImageStreamWriter::<i32>::create_with_header(&path, &[100, 80], Some(&wcs)).unwrap();
+  for y in 0..80 {
+    stream.write_row(&(0..100).map(|x| x + 1000 * y).collect::<Vec<i32>>()).unwrap();
+  }
+  stream.finish().unwrap();
+  path
+}
+
+fn pixels(fits: &rsf::Fits) -> ndarray::ArcArray<i32, ndarray::IxDyn> {
+  fits.primary_image().unwrap().as_array::<i32>().unwrap().clone()
+}
+
+#[test]
+fn cutout_sky_test() {
+  let path = synthetic_image();
+  let fits = rsf::Fits::open(&path).unwrap();
+
+  //(1) 30" around the reference point: 15 pixels on both sides of CRPIX
+  let cutout = fits.cutout_sky(0, 150.0, 2.0, 0.5).unwrap();
+  let data = pixels(&cutout);
+  assert_eq!(data.shape(), &[31, 31]);
+  assert_eq!(data[[0, 0]], 34 + 1000 * 24);
+  let header = cutout.primary().unwrap().get_header();
+  assert_eq!(header.get_value_as::<f64>("CRPIX1").unwrap(), 16.0);
+  assert_eq!(header.get_value_as::<f64>("CRPIX2").unwrap(), 16.0);
+  assert_eq!(header.get_value_as::<usize>("NAXIS2").unwrap(), 31);
+  assert!(header.history().any(|line| line.starts_with("Cutout of 0.5'")));
+
+  //The WCS of the cutout still points at the same position
+  let range = cutout.primary().unwrap().sky_cutout_range(150.0, 2.0, 0.5).unwrap();
+  assert_eq!(range, (vec![0, 0], vec![31, 31]));
+
+  //(2) RA increases to the left, Dec upwards
+  let (start, _) =
+    fits.primary().unwrap().sky_cutout_range(150.0, 2.0 + 10.0 / 3600.0, 0.5).unwrap();
+  assert_eq!(start, vec![34, 34]);
+  let east = 150.0 + 10.0 / 3600.0 / 2f64.to_radians().cos();
+  let (start, _) = fits.primary().unwrap().sky_cutout_range(east, 2.0, 0.5).unwrap();
+  assert_eq!(start, vec![24, 24]);
+
+  //(3) Boxes are clipped to the image, positions off the image are refused
+  let corner = fits.cutout_sky(0, 150.0 + 49.0 / 3600.0, 2.0 - 39.0 / 3600.0, 0.5).unwrap();
+  assert_eq!(pixels(&corner).shape(), &[16, 16]);
+  assert_eq!(pixels(&corner)[[0, 0]], 0);
+  let err = fits.cutout_sky(0, 151.0, 2.0, 0.5).unwrap_err();
+  assert!(err.downcast_ref::<SkyPositionErr>().is_some());
+  assert!(fits.cutout_sky(0, 330.0, -2.0, 0.5).is_err());
+
+  //(4) Reading only the box from disk gives the same cutout
+  let index = rsf::Fits::open_indexed(&path).unwrap();
+  let from_disk = index.cutout_sky(0, 150.0, 2.0, 0.5).unwrap();
+  assert_eq!(pixels(&from_disk), data);
+  assert_eq!(from_disk.manifest()[0].to_string(), cutout.manifest()[0].to_string());
+}
+
+#[test]
+fn cutout_sky_real_test() {
+  //The SCI extension of a NICMOS exposure becomes the primary HDU of the cutout
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let fits = rsf::Fits::open(&real_path).unwrap();
+  let sci = fits.get_hdu(1).unwrap().get_header();
+  let (ra, dec) =
+    (sci.get_value_as::<f64>("CRVAL1").unwrap(), sci.get_value_as::<f64>("CRVAL2").unwrap());
+
+  let cutout = fits.cutout_sky(1, ra, dec, 0.1).unwrap();
+  let header = cutout.primary().unwrap().get_header();
+  assert_eq!(header.get_value("SIMPLE").unwrap(), "T");
+  assert!(header.get_value("XTENSION").is_none());
+  let naxis1 = header.get_value_as::<usize>("NAXIS1").unwrap();
+
+  let mut out = dirs::cache_dir().unwrap();
+  out.push("cutout_sky_nicmos.fits");
+  cutout.write(&out).unwrap();
+  let written = rsf::Fits::open(&out).unwrap();
+  let shape = written.primary_image().unwrap().as_array::<f32>().unwrap().shape().to_vec();
+  assert_eq!(shape[0], naxis1);
+  assert!(shape[0] > 1 && shape[0] < 256);
+}
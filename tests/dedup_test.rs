@@ -0,0 +1,89 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{fs, path::PathBuf};
+
+use rustronomy_fits::{self as rsf, Extension};
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+fn image(hdu: &rsf::HeaderDataUnit) -> &rsf::TypedImage {
+  match hdu.get_data() {
+    Some(Extension::Image(img)) => img,
+    _ => panic!("HDU does not contain an image"),
+  }
+}
+
+#[test]
+fn deep_clone_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let fits = rsf::Fits::open(&real_path).unwrap();
+  let hdu = fits.get_hdu(1).unwrap();
+
+  //clone() shares the data, deep_clone() copies it (keeping BITPIX)
+  let shallow = hdu.clone();
+  let deep = hdu.deep_clone();
+  assert!(image(&shallow).shares_data_with(image(hdu)));
+  assert!(!image(&deep).shares_data_with(image(hdu)));
+  assert_eq!(image(&deep).get_bitpix().to_string(), image(hdu).get_bitpix().to_string());
+  assert_eq!(image(&deep).as_array::<f32>().unwrap(), image(hdu).as_array::<f32>().unwrap());
+  assert_eq!(deep.get_header().get_num_records(), hdu.get_header().get_num_records());
+}
+
+#[test]
+fn dedup_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let mut dir = dirs::cache_dir().unwrap();
+  dir.push("dedup_test");
+  fs::create_dir_all(&dir).unwrap();
+
+  //(1) A file containing every image twice
+  let merged_path = dir.join("merged.fits");
+  rsf::Fits::concat(&[&real_path, &real_path], &merged_path).unwrap();
+  let mut fits = rsf::Fits::open(&merged_path).unwrap();
+  assert!(!image(fits.get_hdu(7).unwrap()).shares_data_with(image(fits.get_hdu(1).unwrap())));
+
+  //(2) The second copy of every image shares its data with the first one
+  assert!(fits.dedup_data() >= 5);
+  for index in 1..6 {
+    let first = image(fits.get_hdu(index).unwrap());
+    let second = image(fits.get_hdu(index + 6).unwrap());
+    assert!(second.shares_data_with(first), "HDU #{index}");
+  }
+
+  //(3) Mutating one of them does not affect the other
+  let hdu = fits.get_hdu_mut(7).unwrap();
+  if let Some(Extension::Image(img)) = hdu.get_data_mut() {
+    img.as_array_mut::<f32>().unwrap().fill(0.0);
+  }
+  let first = image(fits.get_hdu(1).unwrap()).as_array::<f32>().unwrap();
+  assert!(first.iter().any(|&px| px != 0.0));
+
+  //(4) Deduplication does not change the file that is written
+  let plain = rsf::Fits::open(&merged_path).unwrap();
+  let mut deduped = rsf::Fits::open(&merged_path).unwrap();
+  deduped.dedup_data();
+  plain.write(&dir.join("plain.fits")).unwrap();
+  deduped.write(&dir.join("deduped.fits")).unwrap();
+  assert_eq!(
+    fs::read(dir.join("plain.fits")).unwrap(),
+    fs::read(dir.join("deduped.fits")).unwrap()
+  );
+}
@@ -0,0 +1,114 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{fs, path::PathBuf};
+
+use rustronomy_fits as rsf;
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+fn image(hdu: &rsf::HeaderDataUnit) -> &rsf::TypedImage {
+  match hdu.get_data() {
+    Some(rsf::Extension::Image(img)) => img,
+    _ => panic!("HDU has no image"),
+  }
+}
+
+fn cache_dir() -> PathBuf {
+  let mut dir = dirs::cache_dir().unwrap();
+  dir.push("detached_test");
+  fs::create_dir_all(&dir).unwrap();
+  dir
+}
+
+#[test]
+fn detached_round_trip_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let fits = rsf::Fits::open(&real_path).unwrap();
+  let sci = fits.get_hdu(1).unwrap();
+
+  //(1) Export the SCI extension as a text header + raw data file
+  let (hdr, raw) = (cache_dir().join("sci.hdr"), cache_dir().join("sci.raw"));
+  sci.write_detached(&hdr, &raw).unwrap();
+  assert_eq!(fs::metadata(&raw).unwrap().len(), 270 * 263 * 4);
+  let text = fs::read_to_string(&hdr).unwrap();
+  assert!(text.lines().all(|line| line.len() <= 80));
+  assert!(text.starts_with("XTENSION= 'IMAGE   '"));
+  assert_eq!(text.lines().last().unwrap(), "END");
+
+  //(2) Read it back
+  let detached = rsf::HeaderDataUnit::open_detached(&hdr, &raw).unwrap();
+  let original = image(sci).as_array::<f32>().unwrap();
+  let read = image(&detached).as_array::<f32>().unwrap();
+  assert_eq!(original, read);
+  assert_eq!(detached.get_extname().as_deref(), Some("SCI"));
+  match detached.get_data_source().unwrap() {
+    rsf::DataSource::Detached(path) => assert_eq!(path.as_ref(), raw.as_path()),
+    other => panic!("unexpected data source {other:?}"),
+  }
+  assert!(detached.get_layout().is_none());
+
+  //(3) Detached data can be unloaded and loaded again, like embedded data
+  let mut detached = detached;
+  detached.unload().unwrap();
+  assert!(detached.get_data().is_none());
+  detached.load_data().unwrap();
+  assert!(detached.get_data().is_some());
+
+  //(4) As a FITS file, the image extension becomes the primary HDU
+  let fits = rsf::Fits::open_detached(&hdr, &raw).unwrap();
+  assert_eq!(fits.get_num_hdus(), 1);
+  assert!(fits.primary().unwrap().get_header().get_value("SIMPLE").is_some());
+  let out = cache_dir().join("sci.fits");
+  fits.write(&out).unwrap();
+  let fits = rsf::Fits::open(&out).unwrap();
+  assert_eq!(fits.primary_image().unwrap().as_array::<f32>().unwrap(), original);
+
+  //(5) Raw data files that are too short are refused
+  fs::write(&raw, &fs::read(&raw).unwrap()[..1000]).unwrap();
+  let err = rsf::HeaderDataUnit::open_detached(&hdr, &raw).unwrap_err();
+  let err = err.downcast_ref::<rsf::io_err::TruncatedFileErr>().unwrap();
+  assert_eq!((err.get_expected(), err.get_got()), (270 * 263 * 4, 1000));
+}
+
+#[test]
+fn text_header_test() {
+  //Records may be separated by newlines or simply follow each other
+  let cards = [
+    "SIMPLE  =                    T",
+    "BITPIX  =                    8",
+    "NAXIS   =                    0",
+  ];
+  let per_line = rsf::Header::from_text(&cards.join("\n")).unwrap();
+  let packed: String = cards.iter().map(|card| format!("{card:<80}")).collect();
+  let packed = rsf::Header::from_text(&packed).unwrap();
+  for header in [&per_line, &packed] {
+    assert_eq!(header.keywords().collect::<Vec<_>>(), ["SIMPLE", "BITPIX", "NAXIS"]);
+    assert_eq!(header.get_value_as::<usize>("BITPIX").unwrap(), 8);
+  }
+
+  //Everything after the END record is ignored
+  let text = per_line.to_text().unwrap() + "GARBAGE = 'ignored'\n";
+  assert!(rsf::Header::from_text(&text).unwrap().get_value("GARBAGE").is_none());
+
+  //Lines that are too long cannot be header records
+  let err = rsf::Header::from_text(&format!("{}\n{}", cards[0], "X".repeat(81))).unwrap_err();
+  let err = err.downcast_ref::<rsf::header_err::TextRecordErr>().unwrap();
+  assert_eq!((err.get_line(), err.get_len()), (2, 81));
+}
@@ -0,0 +1,61 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use ndarray::{Array, IxDyn};
+use rustronomy_fits::{self as rsf, impl_typed_image_dispatch, FitsPixel};
+
+//A downstream trait, implemented once for all pixel types
+trait PixelCount {
+  fn pixel_count(&self) -> usize;
+  fn zero_first(&mut self) -> bool;
+}
+
+impl<T: FitsPixel> PixelCount for rsf::Image<T> {
+  fn pixel_count(&self) -> usize {
+    self.get_data().len()
+  }
+  fn zero_first(&mut self) -> bool {
+    match self.get_data_mut().iter_mut().next() {
+      Some(px) => {
+        *px = T::zero();
+        true
+      }
+      None => false,
+    }
+  }
+}
+
+impl_typed_image_dispatch!(impl PixelCount {
+  fn pixel_count(&self) -> usize;
+  fn zero_first(&mut self) -> bool;
+});
+
+#[test]
+fn dispatch_test() {
+  let array = Array::from_shape_vec(IxDyn(&[2, 3]), vec![1i32, 2, 3, 4, 5, 6]).unwrap();
+  let mut img: rsf::TypedImage = rsf::Image::new(array).into();
+  assert_eq!(img.pixel_count(), 6);
+  assert!(img.zero_first());
+  assert_eq!(img.as_array::<i32>().unwrap()[[0, 0]], 0);
+
+  //The expression form binds the inner Image<T>
+  let ndim = impl_typed_image_dispatch!(&img, inner => inner.get_shape().len());
+  assert_eq!(ndim, 2);
+  assert_eq!(format!("{img}"), "(IMAGE) - datatype: i32, shape: (2,3), size: 1");
+}
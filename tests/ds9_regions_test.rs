@@ -0,0 +1,135 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{fs, path::PathBuf};
+
+use rustronomy_fits as rsf;
+
+fn catalog_file(name: &str) -> PathBuf {
+  //SExtractor-like catalog: NUMBER, X/Y_IMAGE, A/B_IMAGE, THETA_IMAGE, NAME
+  let mut buf = Vec::new();
+  for card in ["SIMPLE  = T", "BITPIX  = 8", "NAXIS   = 0", "EXTEND  = T", "END"] {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(2880, b' ');
+
+  let columns = [
+    ("NUMBER", "I6"),
+    ("X_IMAGE", "F10.3"),
+    ("Y_IMAGE", "F10.3"),
+    ("A_IMAGE", "F8.3"),
+    ("B_IMAGE", "F8.3"),
+    ("THETA_IMAGE", "F8.2"),
+    ("NAME", "A8"),
+  ];
+  let mut cards = vec![
+    "XTENSION= 'TABLE   '".to_string(),
+    "BITPIX  = 8".to_string(),
+    "NAXIS   = 2".to_string(),
+    "NAXIS1  = 58".to_string(),
+    "NAXIS2  = 3".to_string(),
+    "PCOUNT  = 0".to_string(),
+    "GCOUNT  = 1".to_string(),
+    "TFIELDS = 7".to_string(),
+    "EXTNAME = 'LDAC_OBJECTS'".to_string(),
+  ];
+  let mut tbcol = 1;
+  for (idx, (ttype, tform)) in columns.iter().enumerate() {
+    let n = idx + 1;
+    cards.push(format!("{:8}= '{ttype}'", format!("TTYPE{n}")));
+    cards.push(format!("{:8}= {tbcol}", format!("TBCOL{n}")));
+    cards.push(format!("{:8}= '{tform}'", format!("TFORM{n}")));
+    tbcol += tform[1..].split('.').next().unwrap().parse::<usize>().unwrap();
+  }
+  cards.push("END".to_string());
+  for card in cards {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(buf.len().div_ceil(2880) * 2880, b' ');
+
+  for row in [
+    "     1   100.500   200.250   4.000   2.000   45.00M31     ",
+    "     2       NaN    10.000   1.000   1.000    0.00{bad}   ",
+    "     3    12.000    34.000   3.500   1.500  -30.50NGC 1300",
+  ] {
+    assert_eq!(row.len(), 58);
+    buf.extend(row.bytes());
+  }
+  buf.resize(buf.len().div_ceil(2880) * 2880, b' ');
+
+  let mut path = dirs::cache_dir().unwrap();
+  path.push(name);
+  fs::write(&path, buf).unwrap();
+  path
+}
+
+#[test]
+fn ds9_ellipse_test() {
+  let path = catalog_file("ds9_ellipse.fits");
+  let fits = rsf::Fits::open(&path).unwrap();
+  let hdu = fits.get_hdu(1).unwrap();
+
+  //(1) SExtractor columns by default, rows without a position are skipped
+  let regions = hdu.to_ds9_regions(&rsf::RegionOptions::new()).unwrap();
+  let lines: Vec<&str> = regions.lines().collect();
+  assert_eq!(
+    lines,
+    [
+      "# Region file format: DS9 version 4.1",
+      "# Sources from LDAC_OBJECTS",
+      "global color=green",
+      "image",
+      "ellipse(100.5,200.25,4,2,45)",
+      "ellipse(12,34,3.5,1.5,-30.5)",
+    ]
+  );
+
+  //(2) Scaled ellipses with labels
+  let opts = rsf::RegionOptions::new().scale(2.5).label("name").color("red");
+  let regions = hdu.to_ds9_regions(&opts).unwrap();
+  assert!(regions.contains("global color=red\n"));
+  assert!(regions.contains("ellipse(100.5,200.25,10,5,45) # text={M31}\n"));
+  assert!(regions.contains("ellipse(12,34,8.75,3.75,-30.5) # text={NGC 1300}\n"));
+}
+
+#[test]
+fn ds9_circle_test() {
+  let path = catalog_file("ds9_circle.fits");
+  let fits = rsf::Fits::open(&path).unwrap();
+  let hdu = fits.get_hdu(1).unwrap();
+
+  //(1) Custom column mapping, and circles instead of ellipses
+  let opts = rsf::RegionOptions::new().position("Y_IMAGE", "A_IMAGE").circle(5.0).label("NUMBER");
+  let regions = hdu.to_ds9_regions(&opts).unwrap();
+  assert!(regions.ends_with(
+    "circle(200.25,4,5) # text={1}\ncircle(10,1,5) # text={2}\ncircle(34,3.5,5) # text={3}\n"
+  ));
+
+  //(2) Missing columns are reported by name, text columns are not numbers
+  let err = hdu.to_ds9_regions(&rsf::RegionOptions::new().position("XWIN", "YWIN")).unwrap_err();
+  assert_eq!(err.downcast_ref::<rsf::tbl_err::MissingColumnErr>().unwrap().get_names(), ["XWIN"]);
+  assert!(hdu.to_ds9_regions(&rsf::RegionOptions::new().position("NAME", "Y_IMAGE")).is_err());
+
+  //(3) Written to a file, and refused for images
+  let mut out = dirs::cache_dir().unwrap();
+  out.push("ds9_circle.reg");
+  hdu.write_ds9_regions(&out, &opts).unwrap();
+  assert_eq!(fs::read_to_string(&out).unwrap(), regions);
+  assert!(fits.get_hdu(0).unwrap().to_ds9_regions(&opts).is_err());
+}
@@ -0,0 +1,110 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{fs, path::PathBuf};
+
+use rustronomy_fits::{self as rsf, header_err::DuplicateKeywordErr};
+
+fn duplicates_file(name: &str) -> PathBuf {
+  //Empty primary HDU with a conflicting, a harmless and a continued duplicate
+  let cards = [
+    "SIMPLE  =                    T",
+    "BITPIX  =                    8",
+    "NAXIS   =                    0",
+    "EXPTIME =                 30.0 / first",
+    "OBJECT  = 'M31     '",
+    "NOTE    = 'short   '",
+    "EXPTIME =                 45.0 / second",
+    "OBJECT  = 'M31     '",
+    "NOTE    = 'a long&'",
+    "CONTINUE  'er note'",
+    "END",
+  ];
+  let mut buf = Vec::new();
+  for card in cards {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(2880, b' ');
+  let mut path = dirs::cache_dir().unwrap();
+  path.push(name);
+  fs::write(&path, buf).unwrap();
+  path
+}
+
+fn open(
+  name: &str,
+  policy: rsf::DuplicatePolicy,
+) -> Result<rsf::Fits, Box<dyn std::error::Error + Send + Sync>> {
+  rsf::Fits::open_with(&duplicates_file(name), &rsf::ReadOptions::new().duplicates(policy))
+}
+
+#[test]
+fn duplicate_policy_test() {
+  //(1) By default, the last record wins (in the position of the first)
+  let fits = rsf::Fits::open(&duplicates_file("duplicates_policy.fits")).unwrap();
+  let header = fits.primary().unwrap().get_header();
+  assert_eq!(header.get_value_as::<f64>("EXPTIME").unwrap(), 45.0);
+  assert_eq!(header.get_comment("EXPTIME").unwrap(), "second");
+  assert_eq!(header.get_value("NOTE").unwrap(), "'a longer note'");
+  assert_eq!(
+    header.keywords().collect::<Vec<_>>(),
+    ["SIMPLE", "BITPIX", "NAXIS", "EXPTIME", "OBJECT", "NOTE"]
+  );
+
+  //(2) The first record wins, the continuation of the dropped one is dropped too
+  let fits = open("duplicates_policy.fits", rsf::DuplicatePolicy::FirstWins).unwrap();
+  let header = fits.primary().unwrap().get_header();
+  assert_eq!(header.get_value_as::<f64>("EXPTIME").unwrap(), 30.0);
+  assert_eq!(header.get_value("NOTE").unwrap(), "'short   '");
+  assert_eq!(header.get_all_values("EXPTIME").len(), 1);
+
+  //(3) All values are kept in order
+  let fits = open("duplicates_policy.fits", rsf::DuplicatePolicy::KeepAllAsList).unwrap();
+  let header = fits.primary().unwrap().get_header();
+  assert_eq!(header.get_value_as::<f64>("EXPTIME").unwrap(), 30.0);
+  let values: Vec<f64> =
+    header.get_all_values("EXPTIME").iter().map(|v| v.parse().unwrap()).collect();
+  assert_eq!(values, [30.0, 45.0]);
+  assert_eq!(header.get_all_values("NOTE"), ["'short   '", "'a longer note'"]);
+  assert_eq!(header.get_all_values("BITPIX"), ["8"]);
+  assert!(header.get_all_values("MISSING").is_empty());
+
+  //(4) Or the file is refused
+  let err = open("duplicates_policy.fits", rsf::DuplicatePolicy::Error).unwrap_err();
+  let err = err.downcast_ref::<DuplicateKeywordErr>().unwrap();
+  assert_eq!(err.get_keyword(), "EXPTIME");
+  assert_eq!(err.get_values().len(), 2);
+}
+
+#[test]
+fn duplicate_diagnostics_test() {
+  let fits = open("duplicates_diagnostics.fits", rsf::DuplicatePolicy::FirstWins).unwrap();
+  let duplicates = fits.duplicate_keywords();
+  let keywords: Vec<&str> = duplicates.iter().map(|d| d.get_keyword()).collect();
+  assert_eq!(keywords, ["EXPTIME", "OBJECT", "NOTE"]);
+  assert!(duplicates[0].get_message().contains("conflicting"));
+  assert!(duplicates[1].get_message().contains("same value"));
+  assert!(duplicates
+    .iter()
+    .all(|d| d.get_hdu() == 0 && d.get_severity() == rsf::Severity::Warning));
+
+  //Files without duplicates have no diagnostics
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push("resources/Hubble_NICMOS.fits");
+  assert!(rsf::Fits::open(&real_path).unwrap().duplicate_keywords().is_empty());
+}
@@ -0,0 +1,81 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{fs, path::PathBuf};
+
+use rustronomy_fits as rsf;
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+#[test]
+fn push_hdu_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let mut fits = rsf::Fits::open(&real_path).unwrap();
+  let mut other = rsf::Fits::open(&real_path).unwrap();
+
+  //(1) Appending a second SCI extension bumps its EXTVER
+  let sci = other.remove_hdu(1).unwrap();
+  assert_eq!(sci.get_extname().as_deref(), Some("SCI"));
+  assert_eq!(sci.get_extver(), 1);
+  assert_eq!(sci.get_extlevel(), 1);
+  fits.push_hdu(sci);
+  assert_eq!(fits.get_num_hdus(), 7);
+
+  let all_sci = fits.get_all_by_name("SCI");
+  assert_eq!(all_sci.iter().map(|hdu| hdu.get_extver()).collect::<Vec<_>>(), vec![1, 2]);
+  assert!(fits.get_by_name("SCI", 2).is_some());
+  assert!(fits.get_by_name("SCI", 3).is_none());
+  assert!(fits.get_all_by_name("NOPE").is_empty());
+
+  //(2) The numbering survives a round trip
+  let mut dir = dirs::cache_dir().unwrap();
+  dir.push("extver_test");
+  fs::create_dir_all(&dir).unwrap();
+  let out = dir.join("pushed.fits");
+  fits.write(&out).unwrap();
+  let reread = rsf::Fits::open(&out).unwrap();
+  assert_eq!(reread.get_all_by_name("SCI").len(), 2);
+  assert_eq!(
+    reread.get_by_name("SCI", 2).unwrap().get_header().get_value("XTENSION").unwrap(),
+    "'IMAGE   '"
+  );
+}
+
+#[test]
+fn resolve_duplicates_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+
+  let mut dir = dirs::cache_dir().unwrap();
+  dir.push("extver_test");
+  fs::create_dir_all(&dir).unwrap();
+  let merged = dir.join("merged.fits");
+  rsf::Fits::concat(&[&real_path, &real_path], &merged).unwrap();
+
+  //Concatenating a file with itself gives ambiguous EXTNAME + EXTVER pairs
+  let mut fits = rsf::Fits::open(&merged).unwrap();
+  let versions = |fits: &rsf::Fits| {
+    fits.get_all_by_name("ERR").iter().map(|hdu| hdu.get_extver()).collect::<Vec<_>>()
+  };
+  assert_eq!(versions(&fits), vec![1, 1]);
+
+  fits.resolve_duplicates();
+  assert_eq!(versions(&fits), vec![1, 2]);
+  assert_eq!(fits.get_all_by_name("SCI").len(), 2);
+}
@@ -0,0 +1,152 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+#![cfg(feature = "fft")]
+
+use std::{f64::consts::PI, path::Path};
+
+use ndarray::{Array, IxDyn, ShapeBuilder};
+use num_complex::Complex;
+use rustronomy_fits as rsf;
+
+fn test_image(shape: &[usize]) -> rsf::TypedImage {
+  //Deterministic, non-symmetric pixel values in the Fortran layout
+  let len: usize = shape.iter().product();
+  let pixels: Vec<f64> = (0..len).map(|idx| ((idx * 7919) % 113) as f64 - 50.0).collect();
+  let data = Array::from_shape_vec(shape.to_vec().f(), pixels).unwrap();
+  rsf::TypedImage::DpfImg(rsf::Image::new(data))
+}
+
+fn naive_dft(img: &rsf::TypedImage, n1: usize, n2: usize) -> Vec<Complex<f64>> {
+  let data = img.as_f64_array().unwrap();
+  let mut out = Vec::new();
+  for k2 in 0..n2 {
+    for k1 in 0..n1 {
+      let mut sum = Complex::new(0.0, 0.0);
+      for ((x, y), &px) in data.view().into_dimensionality::<ndarray::Ix2>().unwrap().indexed_iter()
+      {
+        let angle = -2.0 * PI * ((k1 * x) as f64 / n1 as f64 + (k2 * y) as f64 / n2 as f64);
+        sum += Complex::from_polar(px, angle);
+      }
+      out.push(sum);
+    }
+  }
+  out
+}
+
+fn assert_close(a: &Array<Complex<f64>, IxDyn>, b: &[Complex<f64>]) {
+  for (a, b) in a.t().iter().zip(b) {
+    assert!((a - b).norm() < 1e-8 * (1.0 + b.norm()), "{a} != {b}");
+  }
+}
+
+#[test]
+fn fft_matches_dft_test() {
+  //Powers of two as well as other (and prime) lengths
+  for (n1, n2) in [(8, 4), (6, 5), (1, 7), (13, 11)] {
+    let img = test_image(&[n1, n2]);
+    let spectrum = img.fft2(&rsf::FftOptions::new());
+    assert_eq!(spectrum.shape(), &[n1, n2]);
+    assert_close(&spectrum, &naive_dft(&img, n1, n2));
+  }
+}
+
+#[test]
+fn fft_roundtrip_test() {
+  for norm in [rsf::FftNorm::Backward, rsf::FftNorm::Ortho, rsf::FftNorm::Forward] {
+    let opts = rsf::FftOptions::new().norm(norm);
+    let img = test_image(&[12, 7]);
+    let spectrum = img.fft2(&opts);
+    let back = rsf::TypedImage::ifft2(spectrum.view(), &[12, 7], &opts).unwrap();
+    let (img, back) = (img.as_f64_array().unwrap(), back.as_f64_array().unwrap());
+    assert!(img.iter().zip(back.iter()).all(|(a, b)| (a - b).abs() < 1e-9));
+  }
+}
+
+#[test]
+fn fft_norm_test() {
+  //A single bright pixel has a flat spectrum
+  let mut data = Array::zeros(vec![4, 4].f());
+  data[[0, 0]] = 1.0;
+  let img = rsf::TypedImage::DpfImg(rsf::Image::new(data));
+  let flat = |norm, value: f64| {
+    let spectrum = img.fft2(&rsf::FftOptions::new().norm(norm));
+    spectrum.iter().all(|px| (px - Complex::new(value, 0.0)).norm() < 1e-12)
+  };
+  assert!(flat(rsf::FftNorm::Backward, 1.0));
+  assert!(flat(rsf::FftNorm::Ortho, 0.25));
+  assert!(flat(rsf::FftNorm::Forward, 1.0 / 16.0));
+
+  //The orthonormal transform preserves the power (Parseval)
+  let img = test_image(&[9, 6]);
+  let spectrum = img.fft2(&rsf::FftOptions::new().norm(rsf::FftNorm::Ortho));
+  let power: f64 = img.as_f64_array().unwrap().iter().map(|px| px * px).sum();
+  let spectral_power: f64 = spectrum.iter().map(|px| px.norm_sqr()).sum();
+  assert!((power - spectral_power).abs() < 1e-8 * power);
+}
+
+#[test]
+fn fft_padding_test() {
+  let img = test_image(&[5, 3]);
+
+  //(1) Padded spectra are larger, and transform back to the original image
+  let opts = rsf::FftOptions::new().padding(rsf::FftPadding::PowerOfTwo);
+  let spectrum = img.fft2(&opts);
+  assert_eq!(spectrum.shape(), &[8, 4]);
+  let back = rsf::TypedImage::ifft2(spectrum.view(), &[5, 3], &opts).unwrap();
+  let (orig, back) = (img.as_f64_array().unwrap(), back.as_f64_array().unwrap());
+  assert_eq!(back.shape(), &[5, 3]);
+  assert!(orig.iter().zip(back.iter()).all(|(a, b)| (a - b).abs() < 1e-9));
+
+  //(2) Padding never crops the image
+  let opts = rsf::FftOptions::new().padding(rsf::FftPadding::To(10, 2));
+  assert_eq!(img.fft2(&opts).shape(), &[10, 3]);
+
+  //(3) The image may not be larger than the spectrum
+  let err = rsf::TypedImage::ifft2(spectrum.view(), &[9, 3], &opts).unwrap_err();
+  assert_eq!(err.get_spectrum_shape(), &[8, 4]);
+  assert!(rsf::TypedImage::ifft2(spectrum.view(), &[5], &opts).is_err());
+}
+
+#[test]
+fn fft_cube_test() {
+  //Every plane of a cube is transformed on its own
+  let cube = test_image(&[6, 4, 3]);
+  let spectrum = cube.fft2(&rsf::FftOptions::new());
+  assert_eq!(spectrum.shape(), &[6, 4, 3]);
+
+  let plane = cube.as_f64_array().unwrap().index_axis(ndarray::Axis(2), 1).to_owned();
+  let plane = rsf::TypedImage::DpfImg(rsf::Image::new(plane.into_dyn()));
+  let plane_spectrum = spectrum.index_axis(ndarray::Axis(2), 1).to_owned();
+  assert_close(&plane_spectrum, &naive_dft(&plane, 6, 4));
+}
+
+#[test]
+fn fft_hdu_test() {
+  //The zero frequency of a real image is the sum of its pixels
+  let fits = rsf::Fits::open(Path::new("resources/Hubble_WFPC2_2.fits")).unwrap();
+  let hdu = fits.get_hdu(0).unwrap();
+  let spectrum = hdu.fft2(&rsf::FftOptions::new()).unwrap();
+  let img = match hdu.get_data() {
+    Some(rsf::Extension::Image(img)) => img,
+    _ => panic!("expected an image"),
+  };
+  let sum: f64 = img.as_f32_array().unwrap().iter().map(|&px| px as f64).sum();
+  assert_eq!(spectrum.shape(), &[100, 100]);
+  assert!((spectrum[[0, 0]].re - sum).abs() < 1e-6 * sum.abs().max(1.0));
+}
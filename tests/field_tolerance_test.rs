@@ -0,0 +1,161 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{fs, path::PathBuf};
+
+use rustronomy_fits::{
+  self as rsf, Extension, FieldTolerance, Severity, TableEntry, TableStrategy,
+};
+
+//Value a malformed (ID, FLUX) row should be read as, None if FLUX is null
+type Expected = Option<(i64, f64)>;
+const ROWS: [(&str, &str, Expected); 9] = [
+  ("1", "1.5", Some((1, 1.5))),
+  ("2", "1.0D+03", Some((2, 1000.0))),
+  ("3", "1.5-03", Some((3, 0.0015))),
+  ("4", "+ 2.5", Some((4, 2.5))),
+  ("5", "3.0 E+ 2", Some((5, 300.0))),
+  ("6", "'4.25'", Some((6, 4.25))),
+  ("1E2", "++7", Some((100, 7.0))),
+  ("8", "************", None),
+  ("9", "1.0-0-3", None),
+];
+
+fn table_file(name: &str, n_rows: usize) -> PathBuf {
+  //ASCII table with an I6 and an E12.4 column, filled with the ROWS above
+  let mut buf = Vec::new();
+  for card in ["SIMPLE  = T", "BITPIX  = 8", "NAXIS   = 0", "EXTEND  = T", "END"] {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(2880, b' ');
+
+  let cards = [
+    "XTENSION= 'TABLE   '".to_string(),
+    "BITPIX  = 8".to_string(),
+    "NAXIS   = 2".to_string(),
+    "NAXIS1  = 18".to_string(),
+    format!("NAXIS2  = {n_rows}"),
+    "PCOUNT  = 0".to_string(),
+    "GCOUNT  = 1".to_string(),
+    "TFIELDS = 2".to_string(),
+    "TTYPE1  = 'ID'".to_string(),
+    "TBCOL1  = 1".to_string(),
+    "TFORM1  = 'I6'".to_string(),
+    "TTYPE2  = 'FLUX'".to_string(),
+    "TBCOL2  = 7".to_string(),
+    "TFORM2  = 'E12.4'".to_string(),
+    "END".to_string(),
+  ];
+  for card in cards {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(buf.len().div_ceil(2880) * 2880, b' ');
+
+  //Only the last rows of big tables are malformed
+  for row in 0..n_rows {
+    let row = match (row + ROWS.len()).checked_sub(n_rows) {
+      Some(idx) => format!("{:>6}{:>12}", ROWS[idx].0, ROWS[idx].1),
+      None => format!("{row:>6}{:>12}", "0.5"),
+    };
+    buf.extend(row.bytes());
+  }
+  buf.resize(buf.len().div_ceil(2880) * 2880, b' ');
+
+  let mut path = dirs::cache_dir().unwrap();
+  path.push(name);
+  fs::write(&path, buf).unwrap();
+  path
+}
+
+fn table(fits: &rsf::Fits) -> &rsf::AsciiTable {
+  match fits.get_hdu(1).unwrap().get_data() {
+    Some(Extension::AsciiTable(table)) => table,
+    _ => panic!("HDU does not contain a table"),
+  }
+}
+
+#[test]
+fn strict_test() {
+  //Malformed fields are refused by default
+  let path = table_file("field_tolerance_strict.fits", ROWS.len());
+  assert!(rsf::Fits::open(&path).is_err());
+  let opts = rsf::ReadOptions::new().field_tolerance(FieldTolerance::Strict);
+  assert!(rsf::Fits::open_with(&path, &opts).is_err());
+}
+
+#[test]
+fn repair_test() {
+  let path = table_file("field_tolerance_repair.fits", ROWS.len());
+  let opts = rsf::ReadOptions::new().field_tolerance(FieldTolerance::Repair);
+  let fits = rsf::Fits::open_with(&path, &opts).unwrap();
+  let table = table(&fits);
+
+  //(1) Repaired fields hold their intended value, the rest is null
+  for (row, (_, _, expected)) in ROWS.iter().enumerate() {
+    match expected {
+      Some((id, flux)) => {
+        assert!(matches!(table.get_entry(0, row).unwrap(), TableEntry::Int(num) if num == *id));
+        assert!(matches!(table.get_entry(1, row).unwrap(), TableEntry::Float(num) if num == *flux));
+        assert!(!table.is_null(1, row));
+      }
+      None => assert!(table.is_null(1, row)),
+    }
+  }
+  assert_eq!(table.get_col_validity(1).unwrap().null_count(), 2);
+  assert!(table.get_col_validity(0).is_none());
+
+  //(2) Every repair is listed, except for standard D exponents
+  let repairs = fits.table_repairs();
+  assert_eq!(repairs.len(), 8);
+  assert!(repairs.iter().all(|repair| repair.get_hdu() == 1 && repair.get_profile() == "table"));
+  let id = &repairs[0];
+  assert_eq!((id.get_keyword(), id.get_value()), ("TFORM1", "1E2"));
+  assert!(id.get_message().starts_with("row 7: read integer written as float"));
+
+  let flux: Vec<_> = repairs.iter().filter(|repair| repair.get_keyword() == "TFORM2").collect();
+  assert_eq!(flux.len(), 7);
+  assert_eq!(flux[0].get_value(), "1.5-03");
+  assert!(flux[0].get_message().contains("added missing exponent letter"));
+  assert!(flux[1].get_message().contains("removed embedded blanks"));
+  assert!(flux[3].get_message().contains("removed stray characters"));
+  assert!(flux[4].get_message().contains("removed stray '+' sign"));
+  assert_eq!(flux[5].get_severity(), Severity::Warning);
+  assert!(flux[5].get_message().contains("overflowed"));
+  assert_eq!(flux[6].get_severity(), Severity::Error);
+  assert!(flux[6].get_message().starts_with("row 9: unreadable field"));
+  assert_eq!(table.get_field_repairs().len(), 8);
+}
+
+#[test]
+fn repair_rows_test() {
+  //Repairs keep their row, whichever way the table was divided over threads
+  let n_rows = 10_000;
+  let path = table_file("field_tolerance_rows.fits", n_rows);
+  for strategy in [TableStrategy::RowParallel, TableStrategy::ColumnParallel] {
+    let opts =
+      rsf::ReadOptions::new().field_tolerance(FieldTolerance::Repair).table_strategy(strategy);
+    let fits = rsf::Fits::open_with(&path, &opts).unwrap();
+    let repairs = fits.table_repairs();
+    assert_eq!(repairs.len(), 8);
+    let last = repairs.iter().find(|repair| repair.get_severity() == Severity::Error).unwrap();
+    assert!(last.get_message().starts_with(&format!("row {n_rows}:")));
+    assert!(table(&fits).is_null(1, n_rows - 1));
+    assert!(!table(&fits).is_null(1, n_rows - 3));
+  }
+}
@@ -0,0 +1,83 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+#![cfg(feature = "derive")]
+
+use std::path::Path;
+
+use rustronomy_fits::{self as rsf, tbl_err::MissingColumnErr, FitsRow};
+
+#[derive(Debug, PartialEq, FitsRow)]
+struct Limit {
+  name: String,
+  #[fits(name = "HIGH")]
+  upper: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, FitsRow)]
+struct Star {
+  id: u32,
+  name: String,
+  ra: f64,
+  variable: bool,
+}
+
+fn stars() -> Vec<Star> {
+  vec![
+    Star { id: 1, name: "Vega".into(), ra: 279.23, variable: false },
+    Star { id: 2, name: "Mira".into(), ra: 34.84, variable: true },
+    Star { id: 70000, name: "Betelgeuse".into(), ra: 88.79, variable: true },
+  ]
+}
+
+#[test]
+fn rows_as_test() {
+  //Fields are read from the columns with the same (or the given) name
+  let fits = rsf::Fits::open(Path::new("resources/EUVE.fits")).unwrap();
+  let Some(rsf::Extension::BinTable(tbl)) = fits.get_by_name("ds_limits", 1).unwrap().get_data()
+  else {
+    panic!("ds_limits is not a binary table");
+  };
+  let limits: Vec<Limit> = tbl.rows_as().unwrap();
+  assert_eq!(limits.len(), 3);
+  assert_eq!(limits[1], Limit { name: "dsadct".into(), upper: 5000.0 });
+  assert_eq!(Limit::get_col_labels(), ["NAME", "HIGH"]);
+
+  //Structs with fields that are not in the table cannot be read
+  let err = tbl.rows_as::<Star>().unwrap_err();
+  assert_eq!(err.downcast_ref::<MissingColumnErr>().unwrap().get_names(), ["ID"]);
+}
+
+#[test]
+fn from_rows_test() {
+  //Tables made from structs read back as the same structs
+  let tbl = rsf::AsciiTable::from_rows(&stars()).unwrap();
+  assert_eq!(tbl.get_shape(), (4, 3));
+  assert_eq!(tbl.get_col_label(1), Some("NAME"));
+  assert_eq!(tbl.get_str(1, 0), Some("Vega      "));
+  assert_eq!(tbl.rows_as::<Star>().unwrap(), stars());
+
+  //Entries that do not fit the field type are refused
+  #[derive(Debug, FitsRow)]
+  struct Small {
+    id: u8,
+  }
+  assert!(tbl.rows_as::<Small>().unwrap_err().to_string().contains("u8"));
+  assert!(Star::from_entries(vec![rsf::TableEntry::Int(1)]).is_err());
+}
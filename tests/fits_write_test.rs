@@ -0,0 +1,96 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{fs, path::Path};
+
+use ndarray::{Array, IxDyn};
+use rustronomy_fits as rsf;
+
+fn out_path(name: &str) -> std::path::PathBuf {
+  let mut path = dirs::cache_dir().unwrap();
+  path.push(name);
+  path
+}
+
+fn structure(header: &rsf::Header) -> Vec<&str> {
+  //The structural keywords, in the order they appear in
+  let naxis: usize = header.get_value_as("NAXIS").unwrap();
+  let structural = |keyword: &str| {
+    ["SIMPLE", "XTENSION", "BITPIX", "NAXIS", "PCOUNT", "GCOUNT"].contains(&keyword)
+      || keyword.strip_prefix("NAXIS").is_some_and(|i| i.parse().is_ok_and(|i: usize| i <= naxis))
+  };
+  header.keywords().filter(|keyword| structural(keyword)).collect()
+}
+
+#[test]
+fn round_trip_test() {
+  //Every HDU of a multi-extension file is written back with its structure
+  let original = rsf::Fits::open(Path::new("resources/Hubble_FOC.fits")).unwrap();
+  let path = out_path("fits_write_round_trip.fits");
+  original.clone().write(&path).unwrap();
+  assert_eq!(fs::metadata(&path).unwrap().len() % 2880, 0);
+
+  let written = rsf::Fits::open(&path).unwrap();
+  assert_eq!(written.get_num_hdus(), original.get_num_hdus());
+  for index in 0..original.get_num_hdus() {
+    let (a, b) = (original.get_hdu(index).unwrap(), written.get_hdu(index).unwrap());
+    assert_eq!(structure(a.get_header()), structure(b.get_header()));
+    assert_eq!(format!("{:?}", a.get_data()), format!("{:?}", b.get_data()));
+  }
+  let primary = structure(written.get_hdu(0).unwrap().get_header());
+  assert_eq!(primary[..3], ["SIMPLE", "BITPIX", "NAXIS"]);
+  let extension = structure(written.get_hdu(1).unwrap().get_header());
+  assert_eq!(extension[..3], ["XTENSION", "BITPIX", "NAXIS"]);
+  assert_eq!(extension[extension.len() - 2..], ["PCOUNT", "GCOUNT"]);
+}
+
+#[test]
+fn replaced_image_test() {
+  //Images that replaced the data of an HDU get a header that describes them
+  let mut fits = rsf::Fits::open(Path::new("resources/Astro_UIT.fits")).unwrap();
+  let cube = Array::from_shape_fn(IxDyn(&[4, 3, 2]), |idx| (idx[0] + 10 * idx[2]) as f64 - 0.5);
+  let hdu = fits.get_hdu_mut(0).unwrap();
+  *hdu.get_data_mut().unwrap() = rsf::Extension::Image(rsf::Image::new(cube.clone()).into());
+  let path = out_path("fits_write_replaced.fits");
+  fits.write(&path).unwrap();
+
+  let written = rsf::Fits::open(&path).unwrap();
+  let header = written.get_hdu(0).unwrap().get_header();
+  let structure = structure(header);
+  assert_eq!(structure, ["SIMPLE", "BITPIX", "NAXIS", "NAXIS1", "NAXIS2", "NAXIS3"]);
+  assert_eq!(header.get_value_as::<isize>("BITPIX").unwrap(), -64);
+  assert_eq!(header.get_value_as::<usize>("NAXIS3").unwrap(), 2);
+  assert!(header.get_value("DATASUM").is_none());
+  let Ok(rsf::TypedImage::DpfImg(img)) = written.primary_image() else {
+    panic!("the primary HDU is not an f64 image");
+  };
+  assert_eq!(img.get_data(), &cube);
+
+  //Fewer axes than before remove the NAXISn records that no longer apply
+  let mut fits = written;
+  let row = Array::from_shape_vec(IxDyn(&[3]), vec![1u8, 2, 3]).unwrap();
+  let hdu = fits.get_hdu_mut(0).unwrap();
+  *hdu.get_data_mut().unwrap() = rsf::Extension::Image(rsf::Image::new(row).into());
+  fits.write(&path).unwrap();
+  let written = rsf::Fits::open(&path).unwrap();
+  let header = written.get_hdu(0).unwrap().get_header();
+  assert_eq!(header.get_value_as::<isize>("BITPIX").unwrap(), 8);
+  assert!(header.get_value("NAXIS2").is_none() && header.get_value("NAXIS3").is_none());
+  assert_eq!(fs::metadata(&path).unwrap().len() % 2880, 0);
+}
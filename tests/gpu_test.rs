@@ -0,0 +1,74 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::path::Path;
+
+use ndarray::{Array, IxDyn};
+use rustronomy_fits as rsf;
+
+fn noop_device() -> (wgpu::Device, wgpu::Queue) {
+  //The noop backend validates every call without needing a GPU
+  let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+    backends: wgpu::Backends::NOOP,
+    backend_options: wgpu::BackendOptions {
+      noop: wgpu::NoopBackendOptions { enable: true },
+      ..Default::default()
+    },
+    ..Default::default()
+  });
+  let adapter = pollster::block_on(instance.request_adapter(&Default::default())).unwrap();
+  pollster::block_on(adapter.request_device(&Default::default())).unwrap()
+}
+
+#[test]
+fn texture_test() {
+  let (device, queue) = noop_device();
+
+  //(1) A 2-D i16 image keeps its type, with NAXIS1 as the width
+  let fits = rsf::Fits::open(Path::new("resources/Hubble_NICMOS.fits")).unwrap();
+  let Some(rsf::Extension::Image(img)) = fits.get_hdu(3).unwrap().get_data() else {
+    panic!("HDU 3 is not an image");
+  };
+  device.push_error_scope(wgpu::ErrorFilter::Validation);
+  let texture = img.to_wgpu_texture(&device, &queue).unwrap();
+  assert!(pollster::block_on(device.pop_error_scope()).is_none());
+  assert_eq!(texture.format(), wgpu::TextureFormat::R16Sint);
+  assert_eq!(texture.dimension(), wgpu::TextureDimension::D2);
+  assert_eq!((texture.width(), texture.height()), (270, 263));
+
+  //(2) Cubes become 3-D textures, 64-bit pixels are converted to f32
+  let cube =
+    rsf::TypedImage::from(rsf::Image::new(Array::from_shape_fn(IxDyn(&[5, 3, 2]), |idx| {
+      idx[0] as f64 * 0.5
+    })));
+  device.push_error_scope(wgpu::ErrorFilter::Validation);
+  let texture = cube.to_wgpu_texture(&device, &queue).unwrap();
+  assert!(pollster::block_on(device.pop_error_scope()).is_none());
+  assert_eq!(cube.wgpu_texture_format(), wgpu::TextureFormat::R32Float);
+  assert_eq!(texture.dimension(), wgpu::TextureDimension::D3);
+  assert_eq!(texture.size().depth_or_array_layers, 2);
+
+  //(3) Textures have one to three axes that fit the device
+  let max_len = device.limits().max_texture_dimension_1d as usize;
+  for shape in [vec![2, 2, 2, 2], vec![max_len + 1], vec![4, 0]] {
+    let img = rsf::TypedImage::from(rsf::Image::new(Array::<u8, _>::zeros(IxDyn(&shape))));
+    let err = img.to_wgpu_texture(&device, &queue).unwrap_err();
+    assert_eq!(err.get_shape(), shape.as_slice());
+  }
+}
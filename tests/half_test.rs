@@ -0,0 +1,127 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+#![cfg(feature = "half")]
+
+use std::fs;
+
+use rustronomy_fits as rsf;
+
+const VALUES: [f32; 6] = [0.0, 1.0, -2.5, 0.333, 65504.0, f32::INFINITY];
+
+fn half_img_file() -> Vec<u8> {
+  //Build a minimal FITS file containing a 3x2 half-precision image
+  let mut buf = Vec::new();
+  for card in ["SIMPLE  = T", "BITPIX  = 16", "NAXIS   = 2", "NAXIS1  = 3", "NAXIS2  = 2"]
+    .iter()
+    .chain(["HALFPREC= T", "END"].iter())
+  {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(2880, b' ');
+  for val in VALUES {
+    buf.extend(half::f16::from_f32(val).to_be_bytes());
+  }
+  buf.resize(2 * 2880, 0);
+  buf
+}
+
+fn read_half_img(path: &std::path::Path) -> ndarray::ArrayD<f32> {
+  let mut fits = rsf::Fits::open(path).unwrap();
+  let (_, data) = fits.remove_hdu(0).unwrap().to_parts();
+  match data.unwrap() {
+    rsf::Extension::Image(img) => img.as_owned_f32_array().unwrap(),
+    _ => panic!(),
+  }
+}
+
+#[test]
+fn half_round_trip_test() {
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("half.fits");
+  fs::write(&path, half_img_file()).unwrap();
+
+  //Half-precision data is exposed as f32 data
+  let array = read_half_img(&path);
+  let expected: Vec<f32> = VALUES.iter().map(|&v| half::f16::from_f32(v).to_f32()).collect();
+  assert_eq!(array.t().iter().cloned().collect::<Vec<f32>>(), expected);
+
+  //...and is quantized to f16 again when written
+  let mut copy_path = dirs::cache_dir().unwrap();
+  copy_path.push("half_copy.fits");
+  rsf::Fits::open(&path).unwrap().write(&copy_path).unwrap();
+  assert_eq!(read_half_img(&copy_path), array);
+}
+
+#[test]
+fn half_write_size_test() {
+  //A new f32 image written as half-precision takes up 16 bits per pixel
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("half_size.fits");
+  fs::write(&path, half_img_file()).unwrap();
+  let mut fits = rsf::Fits::open(&path).unwrap();
+  let img = ndarray::Array::from_shape_fn(ndarray::IxDyn(&[40, 40]), |idx| idx[0] as f32);
+  *fits.get_hdu_mut(0).unwrap().get_data_mut().unwrap() =
+    rsf::Extension::Image(rsf::TypedImage::from(rsf::Image::new(img.clone())));
+
+  //1600 pixels of 2 bytes fit in 2 blocks (as f32 they would need 3)
+  let mut copy_path = dirs::cache_dir().unwrap();
+  copy_path.push("half_size_copy.fits");
+  fits.write(&copy_path).unwrap();
+  assert_eq!(fs::metadata(&copy_path).unwrap().len(), 3 * 2880);
+  assert_eq!(read_half_img(&copy_path), img);
+}
+
+#[test]
+fn half_preview_test() {
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("half_preview.fits");
+  fs::write(&path, half_img_file()).unwrap();
+
+  //Previews of half-precision images are f32 images as well
+  let (_, preview) = rsf::Fits::read_preview(&path, 0, 2).unwrap().to_parts();
+  let lazy = rsf::Fits::open_lazy(&path).unwrap();
+  let from_hdu = lazy.get_hdu(0).unwrap().read_preview(2).unwrap();
+  for preview in [preview.unwrap(), from_hdu.unwrap()] {
+    let preview = match preview {
+      rsf::Extension::Image(img) => img.as_owned_f32_array().unwrap(),
+      _ => panic!(),
+    };
+    //(3,2) with a stride of 2 keeps the pixels at x = 0, 2 of the first row
+    assert_eq!(preview.shape(), &[2, 1]);
+    assert_eq!(preview.t().iter().cloned().collect::<Vec<_>>(), vec![0.0, -2.5]);
+  }
+}
+
+#[test]
+fn half_partial_test() {
+  //Truncated half-precision images are salvaged as f32 images too
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("half_partial.fits");
+  let file = half_img_file();
+  fs::write(&path, &file[..2880 + 6]).unwrap();
+  let (fits, err) = rsf::Fits::open_partial(&path, true).unwrap();
+  assert!(err.is_some());
+  let hdu = fits.get_hdu(0).unwrap();
+  assert_eq!(hdu.get_valid_len(), Some(3));
+  let array = match hdu.get_data() {
+    Some(rsf::Extension::Image(img)) => img.clone().as_owned_f32_array().unwrap(),
+    _ => panic!(),
+  };
+  assert_eq!(array.t().iter().cloned().collect::<Vec<_>>(), vec![0.0, 1.0, -2.5, 0.0, 0.0, 0.0]);
+}
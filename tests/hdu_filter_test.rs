@@ -0,0 +1,124 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{fs::File, path::Path};
+
+use rustronomy_fits as rsf;
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+fn sci_only() -> rsf::ReadOptions {
+  rsf::ReadOptions::new().hdu_filter(|header| {
+    header
+      .get_value_as::<String>("EXTNAME")
+      .is_ok_and(|name| name.trim_matches(['\'', ' ']) == "SCI")
+  })
+}
+
+fn check_filtered(fits: &rsf::Fits) {
+  let all = rsf::Fits::open(Path::new(REAL_FILE)).unwrap();
+  assert_eq!(fits.get_num_hdus(), all.get_num_hdus());
+  for index in 0..all.get_num_hdus() {
+    let hdu = fits.get_hdu(index).unwrap();
+    //Headers are always read
+    let (header, expected) = (hdu.get_header(), all.get_hdu(index).unwrap().get_header());
+    assert_eq!(
+      header.get_value_as::<String>("NAXIS").ok(),
+      expected.get_value_as::<String>("NAXIS").ok()
+    );
+    assert_eq!(hdu.get_extname(), all.get_hdu(index).unwrap().get_extname());
+    let is_sci = hdu.get_extname().as_deref() == Some("SCI");
+    assert_eq!(hdu.is_loaded(), is_sci);
+    assert_eq!(
+      hdu.get_data().is_some(),
+      is_sci && all.get_hdu(index).unwrap().get_data().is_some()
+    );
+  }
+}
+
+#[test]
+fn file_filter_test() {
+  let mut fits = rsf::Fits::open_with(Path::new(REAL_FILE), &sci_only()).unwrap();
+  check_filtered(&fits);
+
+  //Skipped data of a file backed HDU can be read later
+  let dq = (0..fits.get_num_hdus())
+    .find(|&i| fits.get_hdu(i).unwrap().get_extname().as_deref() == Some("DQ"))
+    .unwrap();
+  let hdu = fits.get_hdu_mut(dq).unwrap();
+  hdu.load_data().unwrap();
+  assert!(hdu.is_loaded() && hdu.get_data().is_some());
+}
+
+#[test]
+fn stream_filter_test() {
+  let stream = File::open(REAL_FILE).unwrap();
+  let mut fits = rsf::Fits::from_stream_with(stream, &sci_only()).unwrap();
+  check_filtered(&fits);
+
+  //Streamed HDU's have no source to load the skipped data from
+  assert!(fits.get_hdu_mut(0).unwrap().load_data().is_err());
+}
+
+#[test]
+fn no_filter_test() {
+  let fits = rsf::Fits::open_with(Path::new(REAL_FILE), &rsf::ReadOptions::new()).unwrap();
+  assert!((0..fits.get_num_hdus()).all(|i| fits.get_hdu(i).unwrap().is_loaded()));
+}
+
+#[test]
+fn lazy_test() {
+  //(1) Only the headers are read
+  let mut lazy = rsf::Fits::open_lazy(Path::new(REAL_FILE)).unwrap();
+  let eager = rsf::Fits::open(Path::new(REAL_FILE)).unwrap();
+  assert_eq!(lazy.get_num_hdus(), eager.get_num_hdus());
+  for index in 0..lazy.get_num_hdus() {
+    let hdu = lazy.get_hdu(index).unwrap();
+    assert!(!hdu.is_loaded() && hdu.get_data().is_none());
+    assert_eq!(
+      hdu.get_header().to_text().unwrap(),
+      eager.get_hdu(index).unwrap().get_header().to_text().unwrap()
+    );
+  }
+
+  //(2) Data is read when asked for, and unloaded files are written in full
+  let hdu = lazy.get_hdu_mut(1).unwrap();
+  hdu.load_data().unwrap();
+  assert_eq!(
+    format!("{:?}", hdu.get_data()),
+    format!("{:?}", eager.get_hdu(1).unwrap().get_data())
+  );
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("lazy_write.fits");
+  lazy.write(&path).unwrap();
+  let mut eager_path = dirs::cache_dir().unwrap();
+  eager_path.push("eager_write.fits");
+  eager.write(&eager_path).unwrap();
+  assert!(std::fs::read(&path).unwrap() == std::fs::read(&eager_path).unwrap());
+
+  //(3) Data is loaded with the options the file was opened with
+  let opts = rsf::ReadOptions::new().string_interning(rsf::StringInterning::Always);
+  let mut lazy = rsf::Fits::open_lazy_with(Path::new("resources/Hubble_FOC.fits"), &opts).unwrap();
+  let hdu = lazy.get_hdu_mut(1).unwrap();
+  hdu.load_data().unwrap();
+  let Some(rsf::Extension::AsciiTable(tbl)) = hdu.get_data() else {
+    panic!("HDU 1 is not an ASCII table");
+  };
+  assert!(tbl.get_col_dictionary(16).is_some());
+}
@@ -0,0 +1,149 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{
+  env, fs,
+  path::{Path, PathBuf},
+};
+
+use rustronomy_fits as rsf;
+
+/*
+    Ingestion harness for the header corpus in tests/header_corpus: headers
+    of real observations (anonymized) with their expected parsed form.
+
+    Corpus files are either *.hdr files, holding a header as text with one
+    record per line, or complete *.fits files. Each file is read and rendered
+    as text, which has to match the *.golden file next to it. Files without a
+    golden file only have to be readable.
+
+    Problem files can be dropped in tests/header_corpus/local (ignored by git)
+    or in the directory named by the RSF_HEADER_CORPUS environment variable.
+    Run the test with RSF_BLESS=1 to (re)write the golden files.
+*/
+
+static CORPUS_DIR: &str = "tests/header_corpus";
+
+fn corpus_files() -> Vec<PathBuf> {
+  let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(CORPUS_DIR);
+  let mut dirs = vec![root.clone(), root.join("local")];
+  dirs.extend(env::var_os("RSF_HEADER_CORPUS").map(PathBuf::from));
+
+  let mut files: Vec<PathBuf> = dirs
+    .iter()
+    .filter_map(|dir| fs::read_dir(dir).ok())
+    .flatten()
+    .map(|entry| entry.unwrap().path())
+    .filter(|path| matches!(path.extension().and_then(|ext| ext.to_str()), Some("hdr" | "fits")))
+    .collect();
+  files.sort();
+  files
+}
+
+fn hdr_to_fits(path: &Path) -> PathBuf {
+  //Turn the text header into a FITS file without a data unit
+  let text = fs::read_to_string(path).unwrap();
+  let mut bytes = Vec::new();
+  for (line_no, line) in text.lines().enumerate() {
+    assert!(line.len() <= 80, "{}:{} is longer than 80 chars", path.display(), line_no + 1);
+    bytes.extend(format!("{line:<80}").bytes());
+  }
+  bytes.resize(bytes.len().div_ceil(2880) * 2880, b' ');
+
+  let mut out = dirs::cache_dir().unwrap();
+  out.push("header_corpus");
+  fs::create_dir_all(&out).unwrap();
+  out.push(path.with_extension("fits").file_name().unwrap());
+  fs::write(&out, bytes).unwrap();
+  out
+}
+
+fn render(fits: &rsf::Fits) -> String {
+  //One line per record, commentary records last
+  let mut out = String::new();
+  for index in 0..fits.get_num_hdus() {
+    let header = fits.get_hdu(index).unwrap().get_header();
+    out += &format!("== HDU {index}\n");
+    for keyword in header.keywords() {
+      out += &format!("{keyword} = {}", header.get_value(keyword).map_or("", String::as_str));
+      if let Some(comment) = header.get_comment(keyword) {
+        out += &format!(" / {comment}");
+      }
+      out += "\n";
+    }
+    header.comments().for_each(|text| out += &format!("COMMENT {text}\n"));
+    header.history().for_each(|text| out += &format!("HISTORY {text}\n"));
+  }
+  out
+}
+
+fn check(path: &Path, bless: bool) -> Result<(), String> {
+  //(1) Read the file. Header-only files are cut off at the data unit, which
+  //is fine as long as the headers themselves are complete
+  let fits_path = match path.extension().and_then(|ext| ext.to_str()) {
+    Some("hdr") => hdr_to_fits(path),
+    _ => path.to_path_buf(),
+  };
+  let (fits, _) = rsf::Fits::open_partial(&fits_path, false).map_err(|err| err.to_string())?;
+  if fits.get_num_hdus() == 0 {
+    return Err(String::from("no complete header found"));
+  }
+  let rendered = render(&fits);
+
+  //(2) Compare with (or write) the golden file
+  let golden = path.with_extension("golden");
+  if bless {
+    return fs::write(&golden, rendered).map_err(|err| err.to_string());
+  }
+  match fs::read_to_string(&golden) {
+    Err(_) => Ok(()),
+    Ok(expected) if expected == rendered => Ok(()),
+    Ok(expected) => {
+      let (line_no, (want, got)) = expected
+        .lines()
+        .chain(std::iter::repeat(""))
+        .zip(rendered.lines().chain(std::iter::repeat("")))
+        .enumerate()
+        .find(|(_, (want, got))| want != got)
+        .unwrap();
+      Err(format!(
+        "differs from golden file at line {}:\n  want: {want}\n  got:  {got}",
+        line_no + 1
+      ))
+    }
+  }
+}
+
+#[test]
+fn header_corpus_test() {
+  let bless = env::var_os("RSF_BLESS").is_some();
+  let files = corpus_files();
+  assert!(files.len() >= 4, "header corpus is missing");
+
+  //Check every file before failing, so that all problems are reported at once
+  let failures: Vec<String> = files
+    .iter()
+    .filter_map(|path| check(path, bless).err().map(|err| format!("{}: {err}", path.display())))
+    .collect();
+  assert!(
+    failures.is_empty(),
+    "{} corpus file(s) failed:\n{}",
+    failures.len(),
+    failures.join("\n")
+  );
+}
@@ -0,0 +1,65 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::path::PathBuf;
+
+use rustronomy_fits as rsf;
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+#[test]
+fn hierarch_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let mut fits = rsf::Fits::open(&real_path).unwrap();
+  assert!(fits.get_hdu(0).unwrap().get_header().hierarch("ESO").is_empty());
+
+  //Add a small ESO tree to the primary header
+  let header = fits.get_hdu_mut(0).unwrap().get_header_mut();
+  header
+    .set_hierarch(
+      "ESO DET CHIP1 GAIN",
+      String::from("1.25"),
+      Some(String::from("electrons per ADU")),
+    )
+    .unwrap();
+  header.set_hierarch("ESO DET CHIP1 ID", String::from("'CCD-44'"), None).unwrap();
+  header.set_hierarch("ESO  DET   CHIP2 GAIN", String::from("1.5"), None).unwrap();
+  header.set_hierarch("ESO OBS RA", String::from("'12:34:56.7'"), None).unwrap();
+  assert!(header.set_hierarch("ESO DET CHIP1 NAME", "x".repeat(80), None).is_err());
+
+  //Write the file and read it back
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("hierarch.fits");
+  fits.write(&path).unwrap();
+  let fits = rsf::Fits::open(&path).unwrap();
+  let header = fits.get_hdu(0).unwrap().get_header();
+  assert_eq!(header.get_value("HIERARCH ESO DET CHIP2 GAIN").unwrap(), "1.5");
+
+  let eso = header.hierarch("ESO");
+  let chip1 = &eso["DET"]["CHIP1"];
+  assert_eq!(chip1["GAIN"].get_value_as::<f64>().unwrap(), 1.25);
+  assert_eq!(chip1["GAIN"].get_comment().unwrap(), "electrons per ADU");
+  assert_eq!(chip1["ID"].get_value().unwrap(), "'CCD-44'");
+  assert_eq!(eso["DET"].children().map(|(level, _)| level).collect::<Vec<_>>(), ["CHIP1", "CHIP2"]);
+  assert!(eso.get_path("DET CHIP2 GAIN").unwrap().is_leaf());
+  assert!(eso.get_path("DET CHIP3").is_none());
+
+  let ra: rsf::Sexagesimal = eso["OBS"]["RA"].get_value_with().unwrap();
+  assert_eq!(ra, rsf::Sexagesimal::new(false, 12, 34, 56.7));
+}
@@ -26,7 +26,6 @@ use std::{
 
 use progressing::{mapping::Bar, Baring};
 
-use dirs;
 use rustronomy_fits as rfs;
 
 //Starfields of M37 taken by myself
@@ -56,7 +55,7 @@ fn read_write_benchmark() {
 
   //Get all the test files and setup a progress bar
   let files = fs::read_dir(data_f).unwrap().collect::<Result<Vec<DirEntry>, io::Error>>().unwrap();
-  let mut progress_bar = Bar::with_range(0, *&files.len()).timed();
+  let mut progress_bar = Bar::with_range(0, files.len()).timed();
   println!("Performing read/write image benchmark...");
 
   //These are all ~7MB files. THIS IS CURRENTLY TOO SLOW!
@@ -0,0 +1,106 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::path::Path;
+
+use ndarray::{array, Array2};
+use rustronomy_fits::{self as rsf, compare_images, img_err::DiffShapeErr, Tolerance};
+
+fn typed<T: rsf::FitsPixel>(data: Array2<T>) -> rsf::TypedImage
+where
+  rsf::TypedImage: From<rsf::Image<T>>,
+{
+  rsf::TypedImage::from(rsf::Image::new(data.into_dyn()))
+}
+
+#[test]
+fn tolerance_test() {
+  let reference = typed(array![[1.0, 2.0], [100.0, f64::NAN]]);
+  let output = typed(array![[1.0, 2.001], [100.5, f64::NAN]]);
+
+  //(1) Absolute and relative tolerances
+  let report = compare_images(&reference, &output, Tolerance::Absolute(0.01)).unwrap();
+  assert_eq!((report.get_n_pixels(), report.get_n_differing()), (4, 1));
+  assert_eq!(report.get_max_deviation(), 0.5);
+  assert_eq!(report.get_max_index(), Some(&[1, 0][..]));
+  assert!((report.get_mean_deviation() - 0.501 / 3.0).abs() < 1e-12);
+  let report = compare_images(&reference, &output, Tolerance::Relative(0.01)).unwrap();
+  assert!(report.is_within_tolerance());
+  assert!(report.get_difference().is_none());
+  let report = compare_images(&reference, &output, Tolerance::Relative(1e-3)).unwrap();
+  assert_eq!(report.get_n_differing(), 1);
+
+  //(2) A NaN opposite a number always differs
+  let output = typed(array![[1.0, 2.0], [100.0, 0.0]]);
+  let report = compare_images(&reference, &output, Tolerance::Absolute(f64::INFINITY)).unwrap();
+  assert_eq!(report.get_n_differing(), 1);
+  assert_eq!(report.get_max_deviation(), 0.0);
+}
+
+#[test]
+fn ulps_test() {
+  //(1) f32 images count f32 steps
+  let one = typed(array![[1.0f32]]);
+  let next = typed(array![[f32::from_bits(1.0f32.to_bits() + 3)]]);
+  assert!(compare_images(&one, &next, Tolerance::Ulps(3)).unwrap().is_within_tolerance());
+  assert!(!compare_images(&one, &next, Tolerance::Ulps(2)).unwrap().is_within_tolerance());
+
+  //(2) Steps across zero, in f64
+  let (pos, neg) = (typed(array![[f64::from_bits(1)]]), typed(array![[-f64::from_bits(1)]]));
+  let report = compare_images(&pos, &neg, Tolerance::Ulps(2)).unwrap();
+  assert!(report.is_within_tolerance());
+  assert!(!compare_images(&pos, &neg, Tolerance::Ulps(1)).unwrap().is_within_tolerance());
+
+  //(3) Integer images count steps of 1
+  let (a, b) = (typed(array![[10i16, -3]]), typed(array![[12i16, -3]]));
+  assert!(compare_images(&a, &b, Tolerance::Ulps(2)).unwrap().is_within_tolerance());
+  assert_eq!(compare_images(&a, &b, Tolerance::Ulps(1)).unwrap().get_n_differing(), 1);
+}
+
+#[test]
+fn difference_test() {
+  //(1) The difference image is a - b in f64, NaN where only one pixel is NaN
+  let reference = typed(array![[5i32, 6], [7, 8]]);
+  let output = typed(array![[5.0f64, 7.0], [f64::NAN, 8.0]]);
+  let report = compare_images(&reference, &output, Tolerance::Absolute(0.0)).unwrap();
+  assert_eq!(report.get_n_differing(), 2);
+  let diff = report.get_difference().unwrap().clone().as_owned_f64_array().unwrap();
+  assert_eq!((diff[[0, 0]], diff[[0, 1]], diff[[1, 1]]), (0.0, -1.0, 0.0));
+  assert!(diff[[1, 0]].is_nan());
+
+  //(2) As an extension of a file, with the records of the template
+  let template = rsf::Header::from_text("CRPIX1  = 4.0").unwrap();
+  let hdu = report.difference_hdu(Some(&template)).unwrap();
+  let mut fits = rsf::Fits::open(Path::new("resources/Astro_UIT.fits")).unwrap();
+  fits.push_hdu(hdu);
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("image_diff.fits");
+  fits.write(&path).unwrap();
+  let fits = rsf::Fits::open(&path).unwrap();
+  let hdu = fits.get_by_name("DIFF", 1).unwrap();
+  assert_eq!(hdu.get_header().get_value_as::<f64>("CRPIX1").unwrap(), 4.0);
+  assert_eq!(hdu.get_header().get_value_as::<i64>("BITPIX").unwrap(), -64);
+}
+
+#[test]
+fn shape_test() {
+  let (a, b) = (typed(array![[1.0, 2.0]]), typed(array![[1.0], [2.0]]));
+  let err = compare_images(&a, &b, Tolerance::Absolute(0.0)).unwrap_err();
+  let err = err.downcast_ref::<DiffShapeErr>().unwrap();
+  assert_eq!((err.get_shape_a(), err.get_shape_b()), (&[1, 2][..], &[2, 1][..]));
+}
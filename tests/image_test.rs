@@ -17,11 +17,10 @@
     along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
 */
 
-use std::path::PathBuf;
+use std::{fs, path::PathBuf};
 
-use rustronomy_fits as rsf;
-
-use dirs;
+use ndarray::{Array, IxDyn};
+use rustronomy_fits::{self as rsf, img_err::DataUnitLenErr};
 
 static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
 
@@ -67,3 +66,158 @@ fn write_correctness_test() {
   print!("{original}");
   print!("{tested}");
 }
+
+#[test]
+fn preview_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+
+  //Read the full image and a preview of it
+  let mut fits = rsf::Fits::open(&real_path).unwrap();
+  let (_, full) = fits.remove_hdu(3).unwrap().to_parts();
+  let full = match full.unwrap() {
+    rsf::Extension::Image(img) => img.as_owned_i16_array().unwrap(),
+    _ => panic!(),
+  };
+  let (_, preview) = rsf::Fits::read_preview(&real_path, 3, 64).unwrap().to_parts();
+  let preview = match preview.unwrap() {
+    rsf::Extension::Image(img) => img.as_owned_i16_array().unwrap(),
+    _ => panic!(),
+  };
+
+  //(270,263) with a stride of 5 should give a (54,53) preview
+  assert_eq!(preview.shape(), &[54, 53]);
+  for ((x, y), val) in preview.clone().into_dimensionality::<ndarray::Ix2>().unwrap().indexed_iter()
+  {
+    assert_eq!(*val, full[[x * 5, y * 5]]);
+  }
+}
+
+#[test]
+fn hdu_preview_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let from_file = rsf::Fits::read_preview(&real_path, 3, 64).unwrap().to_parts().1.unwrap();
+
+  //Loaded images are strided in memory, unloaded images read from disk
+  let fits = rsf::Fits::open(&real_path).unwrap();
+  let lazy = rsf::Fits::open_lazy(&real_path).unwrap();
+  for hdu in [fits.get_hdu(3).unwrap(), lazy.get_hdu(3).unwrap()] {
+    let preview = hdu.read_preview(64).unwrap().unwrap();
+    assert_eq!(format!("{preview:?}"), format!("{from_file:?}"));
+  }
+
+  //The primary HDU has no image, and tables cannot be previewed
+  assert!(fits.get_hdu(0).unwrap().read_preview(64).unwrap().is_none());
+  let table = rsf::Fits::open(&PathBuf::from("resources/Hubble_FOC.fits")).unwrap();
+  assert!(table.get_hdu(1).unwrap().read_preview(64).is_err());
+}
+
+#[test]
+fn complex_test() {
+  //Build a FITS file containing a 2x2 complex image (interleaved NAXIS1 of 2)
+  let mut buf = Vec::new();
+  for card in ["SIMPLE  = T", "BITPIX  = -32", "NAXIS   = 3", "NAXIS1  = 2", "NAXIS2  = 2"]
+    .iter()
+    .chain(["NAXIS3  = 2", "COMPLEX = T", "END"].iter())
+  {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(2880, b' ');
+  //Each real part is followed by its imaginary part
+  for val in [1.0f32, -1.0, 2.0, -2.0, 3.0, -3.0, 4.0, -4.0] {
+    buf.extend(val.to_be_bytes());
+  }
+  buf.resize(2 * 2880, 0);
+
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("complex.fits");
+  std::fs::write(&path, &buf).unwrap();
+
+  //Read the complex image
+  let fits = rsf::Fits::open(&path).unwrap();
+  let hdu = fits.get_hdu(0).unwrap();
+  assert!(hdu.is_complex());
+  let array = hdu.get_c32_array().unwrap();
+  assert_eq!(array.shape(), &[2, 2]);
+  assert_eq!(array[[1, 0]], num_complex::Complex::new(2.0, -2.0));
+  assert_eq!(array[[0, 1]], num_complex::Complex::new(3.0, -3.0));
+
+  //Writing the array again gives the same data unit, and the marker
+  let mut copy_path = dirs::cache_dir().unwrap();
+  copy_path.push("complex_copy.fits");
+  let mut fits = rsf::Fits::open(&path).unwrap();
+  fits.remove_hdu(0);
+  fits.push_hdu(rsf::HeaderDataUnit::from_c32_array(array.view()));
+  fits.write(&copy_path).unwrap();
+  assert_eq!(std::fs::read(&copy_path).unwrap()[2880..], buf[2880..]);
+  let fits = rsf::Fits::open(&copy_path).unwrap();
+  assert!(fits.get_hdu(0).unwrap().is_complex());
+  assert_eq!(fits.get_hdu(0).unwrap().get_c32_array().unwrap(), array);
+
+  //Without the marker, the same image is just a 2x2x2 f32 image
+  let marker = buf.windows(11).position(|card| card == b"COMPLEX = T").unwrap();
+  buf[marker..marker + 80].fill(b' ');
+  std::fs::write(&path, &buf).unwrap();
+  let fits = rsf::Fits::open(&path).unwrap();
+  assert!(fits.get_hdu(0).unwrap().get_c32_array().is_err());
+}
+
+#[test]
+fn copy_on_write_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let fits = rsf::Fits::open(&real_path).unwrap();
+
+  //Cloning an HDU does not copy the image data...
+  let (_, original) = fits.get_hdu(1).unwrap().clone().to_parts();
+  let (_, mut copy) = fits.get_hdu(1).unwrap().clone().to_parts();
+  let ptr = |xt: &Option<rsf::Extension>| match xt {
+    Some(rsf::Extension::Image(img)) => img.as_f32_array().unwrap().as_ptr(),
+    _ => panic!(),
+  };
+  assert_eq!(ptr(&original), ptr(&copy));
+
+  //...until the copy is modified
+  match &mut copy {
+    Some(rsf::Extension::Image(img)) => img.as_f32_array_mut().unwrap()[[0, 0]] = -1.0,
+    _ => panic!(),
+  }
+  assert_ne!(ptr(&original), ptr(&copy));
+}
+
+#[test]
+fn byte_view_test() {
+  //(1) A byte mask, written as the primary image of a file
+  let mask = Array::from_shape_fn(IxDyn(&[5, 3]), |idx| (idx[0] * 40 + idx[1]) as u8);
+  let mut fits = rsf::Fits::open(&PathBuf::from("resources/Astro_UIT.fits")).unwrap();
+  let hdu = fits.get_hdu_mut(0).unwrap();
+  let img = rsf::TypedImage::from(rsf::Image::new(mask.clone()));
+  match hdu.get_data_mut() {
+    Some(data) => *data = rsf::Extension::Image(img),
+    None => panic!("the primary HDU has no data"),
+  }
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("byte_view.fits");
+  fits.write(&path).unwrap();
+
+  //(2) The decoded image can be viewed without copying it
+  let fits = rsf::Fits::open(&path).unwrap();
+  let img = fits.primary_image().unwrap();
+  let view = img.view_u8().unwrap();
+  assert_eq!(view, mask);
+  assert_eq!(view.as_ptr(), img.as_u8_array().unwrap().as_ptr());
+  assert!(rsf::TypedImage::from(rsf::Image::new(mask.mapv(f32::from))).view_u8().is_err());
+
+  //(3) ...and so can the raw data unit, padding and all
+  let index = rsf::FitsIndex::build(&path).unwrap();
+  let layout = index.get_layout(0).unwrap();
+  let bytes = fs::read(&path).unwrap();
+  let start = (layout.get_start_block() + layout.get_header_blocks()) * 2880;
+  let data_unit = &bytes[start..start + layout.get_data_blocks() * 2880];
+  let raw = rsf::TypedImage::view_u8_raw(data_unit, &[5, 3]).unwrap();
+  assert_eq!(raw, mask);
+  assert_eq!(raw.as_ptr(), data_unit.as_ptr());
+  let err = rsf::TypedImage::view_u8_raw(&data_unit[..14], &[5, 3]).unwrap_err();
+  assert_eq!(err.downcast_ref::<DataUnitLenErr>().unwrap().get_num_pixels(), 15);
+}
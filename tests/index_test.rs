@@ -0,0 +1,120 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{
+  fs,
+  path::PathBuf,
+  time::{Duration, SystemTime},
+};
+
+use rustronomy_fits as rsf;
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+#[test]
+fn sidecar_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+
+  //Work on a copy, so we don't leave sidecars in the resources folder
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("indexed.fits");
+  fs::copy(&real_path, &path).unwrap();
+  let _ = fs::remove_file(rsf::FitsIndex::sidecar_path(&path));
+
+  //Without a sidecar, the index is built by scanning the file
+  let scanned = rsf::Fits::open_indexed(&path).unwrap();
+  let fits = rsf::Fits::open(&path).unwrap();
+  assert_eq!(scanned.get_num_hdus(), 6);
+  print!("{scanned}");
+
+  //With a sidecar, the same layout is read back
+  scanned.write_sidecar().unwrap();
+  let indexed = rsf::Fits::open_indexed(&path).unwrap();
+  for i in 0..scanned.get_num_hdus() {
+    assert_eq!(indexed.get_layout(i), scanned.get_layout(i));
+  }
+
+  //Reading an HDU through the index gives the same HDU
+  let hdu = indexed.read_hdu(3).unwrap().unwrap();
+  assert_eq!(format!("{hdu}"), format!("{}", fits.get_hdu(3).unwrap()));
+  assert!(indexed.read_hdu(6).unwrap().is_none());
+
+  //Sidecars are trusted as long as the file has not been modified...
+  let sidecar = fs::read_to_string(rsf::FitsIndex::sidecar_path(&path)).unwrap();
+  let truncated: Vec<&str> = sidecar.lines().take(4).collect();
+  fs::write(rsf::FitsIndex::sidecar_path(&path), truncated.join("\n") + "\n").unwrap();
+  assert_eq!(rsf::Fits::open_indexed(&path).unwrap().get_num_hdus(), 2);
+
+  //...but not once the file was rewritten, even if its length is the same
+  let file = fs::File::options().write(true).open(&path).unwrap();
+  file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000)).unwrap();
+  drop(file);
+  assert_eq!(rsf::Fits::open_indexed(&path).unwrap().get_num_hdus(), 6);
+
+  //Corrupted sidecars are ignored
+  fs::write(rsf::FitsIndex::sidecar_path(&path), "garbage").unwrap();
+  assert_eq!(rsf::Fits::open_indexed(&path).unwrap().get_num_hdus(), 6);
+}
+
+#[test]
+fn cached_cutout_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let full = match rsf::Fits::open(&real_path).unwrap().get_hdu(3).unwrap().get_data() {
+    Some(rsf::Extension::Image(img)) => img.clone().as_owned_i16_array().unwrap(),
+    _ => panic!(),
+  };
+
+  //Read the same cutout twice through a small tile cache
+  let index =
+    rsf::FitsIndex::build(&real_path).unwrap().with_tile_cache(rsf::TileCache::new(100_000));
+  for _ in 0..2 {
+    let cutout = match index.read_cutout(3, &[10, 20], &[30, 40]).unwrap().unwrap() {
+      rsf::Extension::Image(img) => img.as_owned_i16_array().unwrap(),
+      _ => panic!(),
+    };
+    assert_eq!(cutout.shape(), &[30, 40]);
+    for ((x, y), val) in cutout.into_dimensionality::<ndarray::Ix2>().unwrap().indexed_iter() {
+      assert_eq!(*val, full[[x + 10, y + 20]]);
+    }
+  }
+
+  //The second time around, everything comes from the cache
+  let cache = index.get_tile_cache().unwrap();
+  assert!(cache.get_hits() > 0);
+  assert!(cache.get_used_bytes() <= cache.get_max_bytes());
+  let misses = cache.get_misses();
+  drop(cache);
+  index.read_cutout(3, &[10, 20], &[30, 40]).unwrap();
+  assert_eq!(index.get_tile_cache().unwrap().get_misses(), misses);
+
+  //Reading without a cache gives the same cutout
+  let uncached = rsf::FitsIndex::build(&real_path).unwrap();
+  assert!(uncached.get_tile_cache().is_none());
+  assert_eq!(
+    format!("{}", uncached.read_cutout(3, &[10, 20], &[30, 40]).unwrap().unwrap()),
+    format!("{}", index.read_cutout(3, &[10, 20], &[30, 40]).unwrap().unwrap())
+  );
+
+  //Cutouts have to lie within the image, and only images have cutouts
+  assert!(index.read_cutout(3, &[260, 0], &[30, 40]).is_err());
+  assert!(index.read_cutout(3, &[0], &[30]).is_err());
+  assert!(index.read_cutout(0, &[], &[]).is_err());
+  assert!(index.read_cutout(6, &[0, 0], &[1, 1]).unwrap().is_none());
+}
@@ -0,0 +1,81 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use ndarray::{array, Array, Array2, Axis};
+use rustronomy_fits::{self as rsf, img_err::MaskShapeErr, InpaintMethod};
+
+fn typed<T: rsf::FitsPixel>(data: Array<T, ndarray::IxDyn>) -> rsf::TypedImage {
+  rsf::TypedImage::from(rsf::Image::new(data))
+}
+
+#[test]
+fn nearest_good_test() {
+  //(1) Bad pixels copy their closest good neighbour, diagonals included
+  let img = typed(array![[1.0, 2.0, 3.0], [4.0, -99.0, 6.0], [7.0, 8.0, -99.0]].into_dyn());
+  let mask = array![[false, false, false], [false, true, false], [false, false, true]];
+  let out = img.inpaint(mask.view().into_dyn(), InpaintMethod::NearestGood).unwrap();
+  let out = out.as_owned_f64_array().unwrap();
+  assert_eq!(out[[2, 2]], 8.0); // (2, 1) comes before (1, 2) in the file
+  assert_eq!(out[[1, 1]], 4.0);
+  assert_eq!(out[[0, 0]], 1.0);
+
+  //(2) Far away from the good pixels, and in planes without good pixels
+  let mut cube = Array::from_elem((20, 10, 2), 0i32);
+  cube[[19, 9, 0]] = 42;
+  let mut mask = Array::from_elem((20, 10, 2), true);
+  mask[[19, 9, 0]] = false;
+  let out = typed(cube.into_dyn()).inpaint(mask.view().into_dyn(), InpaintMethod::NearestGood);
+  let out = out.unwrap().as_owned_i32_array().unwrap();
+  assert!(out.index_axis(Axis(2), 0).iter().all(|&px| px == 42));
+  assert!(out.index_axis(Axis(2), 1).iter().all(|&px| px == 0));
+}
+
+#[test]
+fn bilinear_test() {
+  //(1) A linear gradient is reconstructed exactly
+  let gradient = Array2::from_shape_fn((30, 20), |(x, y)| 2.0 * x as f32 + 3.0 * y as f32);
+  let mut damaged = gradient.clone();
+  let mask = Array2::from_shape_fn((30, 20), |(x, y)| (5..9).contains(&x) && (4..15).contains(&y));
+  damaged.zip_mut_with(&mask, |px, &bad| {
+    if bad {
+      *px = f32::NAN
+    }
+  });
+  let out = typed(damaged.into_dyn()).inpaint(mask.view().into_dyn(), InpaintMethod::Bilinear);
+  let out = out.unwrap().as_owned_f32_array().unwrap();
+  assert!(out.iter().zip(gradient.iter()).all(|(a, b)| (a - b).abs() < 1e-4));
+
+  //(2) Integer images are rounded, edges copy the closest good pixel
+  let img = typed(array![[0i16, 9, 1], [0, 9, 2], [5, 5, 9]].into_dyn());
+  let mask = array![[false, true, false], [false, true, false], [false, false, true]];
+  let out = img.inpaint(mask.view().into_dyn(), InpaintMethod::Bilinear).unwrap();
+  let out = out.as_owned_i16_array().unwrap();
+  assert_eq!(out[[0, 1]], 3); // 5 along NAXIS1, (0 + 1) / 2 along NAXIS2
+  assert_eq!(out[[1, 1]], 3); // 5 along NAXIS1, (0 + 2) / 2 along NAXIS2
+  assert_eq!(out[[2, 2]], 4); // 2 along NAXIS1, 5 along NAXIS2, rounded up
+}
+
+#[test]
+fn mask_shape_test() {
+  let img = typed(Array2::<u8>::zeros((4, 3)).into_dyn());
+  let err: MaskShapeErr = img
+    .inpaint(Array2::from_elem((3, 4), false).view().into_dyn(), InpaintMethod::Bilinear)
+    .unwrap_err();
+  assert_eq!(err.get_mask_shape(), &[3, 4]);
+  assert_eq!(err.get_img_shape(), &[4, 3]);
+}
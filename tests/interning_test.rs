@@ -0,0 +1,153 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{fs, path::PathBuf};
+
+use rustronomy_fits::{self as rsf, Extension, StringInterning, TableEntry};
+
+const N_ROWS: usize = 4000;
+const FILTERS: [&str; 5] = ["u", "g", "r", "i", "z"];
+
+fn table_file() -> PathBuf {
+  //ASCII table with a repetitive FILTER column (with nulls), a unique OBJID
+  //column and an integer ID column
+  let mut buf = Vec::new();
+  for card in ["SIMPLE  = T", "BITPIX  = 8", "NAXIS   = 0", "EXTEND  = T", "END"] {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(2880, b' ');
+
+  let cards = [
+    "XTENSION= 'TABLE   '".to_string(),
+    "BITPIX  = 8".to_string(),
+    "NAXIS   = 2".to_string(),
+    "NAXIS1  = 22".to_string(),
+    format!("NAXIS2  = {N_ROWS}"),
+    "PCOUNT  = 0".to_string(),
+    "GCOUNT  = 1".to_string(),
+    "TFIELDS = 3".to_string(),
+    "TTYPE1  = 'FILTER'".to_string(),
+    "TBCOL1  = 1".to_string(),
+    "TFORM1  = 'A8'".to_string(),
+    "TNULL1  = 'NONE'".to_string(),
+    "TTYPE2  = 'OBJID'".to_string(),
+    "TBCOL2  = 9".to_string(),
+    "TFORM2  = 'A8'".to_string(),
+    "TTYPE3  = 'ID'".to_string(),
+    "TBCOL3  = 17".to_string(),
+    "TFORM3  = 'I6'".to_string(),
+    "END".to_string(),
+  ];
+  for card in cards {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(buf.len().div_ceil(2880) * 2880, b' ');
+
+  for row in 0..N_ROWS {
+    let filter = if row % 100 == 7 { "NONE" } else { FILTERS[row % FILTERS.len()] };
+    buf.extend(format!("{filter:<8}{:<8}{row:>6}", format!("OBJ{row:05}")).bytes());
+  }
+  buf.resize(buf.len().div_ceil(2880) * 2880, b' ');
+
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("interning.fits");
+  fs::write(&path, buf).unwrap();
+  path
+}
+
+fn open(interning: StringInterning) -> rsf::Fits {
+  let opts = rsf::ReadOptions::new().string_interning(interning);
+  rsf::Fits::open_with(&table_file(), &opts).unwrap()
+}
+
+fn table(fits: &rsf::Fits) -> &rsf::AsciiTable {
+  match fits.get_hdu(1).unwrap().get_data() {
+    Some(Extension::AsciiTable(table)) => table,
+    _ => panic!("HDU does not contain a table"),
+  }
+}
+
+fn assert_same(a: &rsf::AsciiTable, b: &rsf::AsciiTable) {
+  //Interning is invisible through the accessors
+  assert_eq!(a.get_shape(), b.get_shape());
+  for col in 0..3 {
+    assert_eq!(a.get_fmtd_column(col), b.get_fmtd_column(col));
+    assert_eq!(a.get_col_label(col), b.get_col_label(col));
+    assert_eq!(a.get_col_null(col), b.get_col_null(col));
+    for row in [0, 1, 7, 1234, N_ROWS - 1] {
+      assert_eq!(a.get_str(col, row), b.get_str(col, row));
+      assert_eq!(a.is_null(col, row), b.is_null(col, row));
+      assert_eq!(
+        format!("{:?}", a.get_entry(col, row).unwrap()),
+        format!("{:?}", b.get_entry(col, row).unwrap())
+      );
+    }
+  }
+}
+
+#[test]
+fn repeated_test() {
+  let (plain, repeated) = (open(StringInterning::Off), open(StringInterning::Repeated));
+  let (plain_tbl, repeated_tbl) = (table(&plain), table(&repeated));
+
+  //(1) Only the repetitive string column is interned
+  assert!(plain_tbl.get_col_dictionary(0).is_none());
+  let dict = repeated_tbl.get_col_dictionary(0).unwrap();
+  assert_eq!(dict.len(), FILTERS.len() + 1);
+  assert!(FILTERS.iter().all(|filter| dict.iter().any(|value| value.trim() == *filter)));
+  assert!(repeated_tbl.get_col_dictionary(1).is_none());
+  assert!(repeated_tbl.get_col_dictionary(2).is_none());
+
+  //(2) which saves memory, without changing the table
+  let saved = plain.memory_usage()[1] - repeated.memory_usage()[1];
+  assert!(saved > N_ROWS * 20, "saved only {saved} bytes");
+  assert_same(plain_tbl, repeated_tbl);
+  assert_eq!(repeated_tbl.get_str(0, 2).map(str::trim), Some("r"));
+  assert!(repeated_tbl.is_null(0, 7));
+  assert!(matches!(repeated_tbl.get_entry(0, 7).unwrap(), TableEntry::Text(txt) if txt.is_empty()));
+  assert!(repeated_tbl.get_str(2, 0).is_none());
+
+  //(3) Exports see the same entries
+  let (a, b) = (plain.get_hdu(1).unwrap(), repeated.get_hdu(1).unwrap());
+  assert_eq!(a.to_ipac().unwrap(), b.to_ipac().unwrap());
+}
+
+#[test]
+fn always_test() {
+  //All string columns are interned, even those without repeats
+  let fits = open(StringInterning::Always);
+  let tbl = table(&fits);
+  assert_eq!(tbl.get_col_dictionary(1).unwrap().len(), N_ROWS);
+  assert!(tbl.get_col_dictionary(2).is_none());
+  assert_same(table(&open(StringInterning::Off)), tbl);
+}
+
+#[test]
+fn intern_column_test() {
+  //Columns can be interned after reading as well
+  let fits = open(StringInterning::Off);
+  let mut tbl = table(&fits).clone();
+  assert!(tbl.intern_column(0));
+  assert!(tbl.intern_column(0));
+  assert!(!tbl.intern_column(2));
+  assert!(!tbl.intern_column(3));
+  assert_eq!(tbl.get_col_dictionary(0).unwrap().len(), FILTERS.len() + 1);
+  assert_same(table(&fits), &tbl);
+  assert!(format!("{tbl}").contains("interned, 6 distinct values"));
+}
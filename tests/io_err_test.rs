@@ -0,0 +1,42 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{error::Error, io::ErrorKind, path::Path};
+
+use rustronomy_fits as rsf;
+
+#[test]
+fn io_err_context_test() {
+  //Errors from the OS should mention the file and the failed operation
+  let path = Path::new("/this/file/does/not/exist.fits");
+  let err = rsf::Fits::open(path).unwrap_err();
+  let io_err = err.downcast_ref::<rsf::io_err::FitsIoErr>().unwrap();
+  assert_eq!(io_err.get_path(), path);
+  assert_eq!(io_err.get_op(), "open file");
+  assert_eq!(io_err.get_kind(), ErrorKind::NotFound);
+  assert!(io_err.source().is_some());
+  assert!(format!("{err}").contains("/this/file/does/not/exist.fits: could not open file"));
+
+  //Same for writing
+  let mut real_path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push("resources/Hubble_NICMOS.fits");
+  let fits = rsf::Fits::open(&real_path).unwrap();
+  let err = fits.write(Path::new("/this/dir/does/not/exist.fits")).unwrap_err();
+  let io_err = err.downcast_ref::<rsf::io_err::FitsIoErr>().unwrap();
+  assert_eq!(io_err.get_op(), "open file for writing");
+}
@@ -0,0 +1,87 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use ndarray::array;
+use rustronomy_fits as rsf;
+
+fn old_file(name: &str) -> std::path::PathBuf {
+  //A small image with archaic and instrument-specific keywords
+  let header = rsf::Header::from_text(
+    "EPOCH   =               1950.0 / equinox of the coordinates\n\
+     EXPOSURE=                 30.0\n\
+     ITIME   =                 29.5\n\
+     OBJET   = 'M31     '\n\
+     OBJECT  = 'NGC224  '",
+  )
+  .unwrap();
+  let mut path = dirs::cache_dir().unwrap();
+  path.push(name);
+  let mut stream =
+    rsf::ImageStreamWriter::<i16>::create_with_header(&path, &[2], Some(&header)).unwrap();
+  stream.write_row(&array![1, 2].to_vec()).unwrap();
+  stream.finish().unwrap();
+  path
+}
+
+#[test]
+fn read_aliases_test() {
+  let path = old_file("keyword_aliases_read.fits");
+
+  //(1) Without aliases, the file is read as-is
+  let fits = rsf::Fits::open(&path).unwrap();
+  assert!(fits.primary().unwrap().get_header().get_record("EPOCH").is_some());
+
+  //(2) With the default aliases, the canonical keywords take their place
+  let opts = rsf::ReadOptions::new().keyword_aliases(rsf::KeywordAliases::default());
+  let fits = rsf::Fits::open_with(&path, &opts).unwrap();
+  let header = fits.primary().unwrap().get_header();
+  assert!(header.get_record("EPOCH").is_none());
+  assert_eq!(header.get_value_as::<f64>("EQUINOX").unwrap(), 1950.0);
+  assert_eq!(header.get_comment("EQUINOX").unwrap(), "equinox of the coordinates");
+  let keywords: Vec<&str> = header.keywords().collect();
+  assert!(
+    keywords.iter().position(|&kw| kw == "EQUINOX")
+      < keywords.iter().position(|&kw| kw == "EXPTIME")
+  );
+
+  //(3) The first alias wins, and aliases never replace a canonical keyword
+  assert_eq!(header.get_value_as::<f64>("EXPTIME").unwrap(), 30.0);
+  assert_eq!(header.get_value_as::<f64>("ITIME").unwrap(), 29.5);
+  assert_eq!(header.get_value("OBJECT").unwrap(), "'NGC224  '");
+  assert!(header.get_record("OBJET").is_some());
+}
+
+#[test]
+fn custom_aliases_test() {
+  let aliases = rsf::KeywordAliases::default().without("exposure").alias("itime", "exptime");
+  assert_eq!(aliases.get_canonical("ITIME"), Some("EXPTIME"));
+  assert_eq!(aliases.get_canonical("EXPOSURE"), None);
+  assert!(rsf::KeywordAliases::empty().aliases().next().is_none());
+
+  let fits = rsf::Fits::open(&old_file("keyword_aliases_custom.fits")).unwrap();
+  let mut header = fits.primary().unwrap().get_header().clone();
+  let renamed = aliases.apply(&mut header);
+  assert_eq!(renamed, ["EPOCH", "ITIME"]);
+  assert_eq!(header.get_value_as::<f64>("EXPTIME").unwrap(), 29.5);
+  assert_eq!(header.get_value_as::<f64>("EXPOSURE").unwrap(), 30.0);
+
+  //Structural keywords are left alone
+  let renamed = rsf::KeywordAliases::empty().alias("BITPIX", "BITS").apply(&mut header);
+  assert!(renamed.is_empty());
+  assert!(header.get_record("BITPIX").is_some());
+}
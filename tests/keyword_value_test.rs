@@ -0,0 +1,79 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::path::PathBuf;
+
+use rustronomy_fits::{self as rsf, KeywordValue};
+
+static REAL_FILE: &str = "resources/Hubble_FOC.fits";
+
+//Observatory-specific syntax for a keyword that lists the filters as F1+F2
+#[derive(Debug, PartialEq)]
+struct FilterList(Vec<String>);
+
+impl KeywordValue for FilterList {
+  fn parse_value(raw: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+    let text = rsf::unquote(raw).ok_or("not a string")?;
+    Ok(FilterList(text.split('+').map(String::from).collect()))
+  }
+  fn format_value(&self) -> String {
+    rsf::quote(&self.0.join("+"))
+  }
+}
+
+#[test]
+fn sexagesimal_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let fits = rsf::Fits::open(&real_path).unwrap();
+
+  //TIME-OBS = '14:03:54          '
+  let header = (0..fits.get_num_hdus())
+    .map(|i| fits.get_hdu(i).unwrap().get_header())
+    .find(|header| header.get_value("TIME-OBS").is_some())
+    .unwrap();
+  let time: rsf::Sexagesimal = header.get_value_with("TIME-OBS").unwrap();
+  assert_eq!(time, rsf::Sexagesimal::new(false, 14, 3, 54.0));
+  assert!(header.get_value_with::<rsf::Sexagesimal>("TELESCOP").is_err());
+  assert!(header.get_value_with::<rsf::Sexagesimal>("NOT-HERE").is_err());
+
+  //Values round-trip through their textual representation
+  let dec = rsf::Sexagesimal::parse_value("'-00:30:05.5'").unwrap();
+  assert!((dec.to_decimal() + (30.0 / 60.0 + 5.5 / 3600.0)).abs() < 1e-12);
+  assert_eq!(dec.format_value(), "'-00:30:05.5'");
+  assert_eq!(
+    rsf::Sexagesimal::parse_value("'+12 34 56.7'").unwrap().format_value(),
+    "'12:34:56.7'"
+  );
+}
+
+#[test]
+fn custom_value_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let mut fits = rsf::Fits::open(&real_path).unwrap();
+  let header = fits.get_hdu_mut(0).unwrap().get_header_mut();
+
+  let filters = FilterList(vec![String::from("F486N"), String::from("F2ND")]);
+  header.set_value_with("FILTERS", &filters, None).unwrap();
+  assert_eq!(header.get_value("FILTERS").unwrap(), "'F486N+F2ND'");
+  assert_eq!(header.get_value_with::<FilterList>("FILTERS").unwrap(), filters);
+
+  //Protected keywords cannot be set this way
+  assert!(header.set_value_with("NAXIS", &filters, None).is_err());
+}
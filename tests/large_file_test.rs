@@ -0,0 +1,140 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{
+  fs::{self, File},
+  io::{Seek, SeekFrom, Write},
+  path::PathBuf,
+};
+
+use rustronomy_fits as rsf;
+
+/*
+    These tests need a FITS file larger than 4GB. Instead of shipping one, a
+    sparse file is generated in the cache dir: only the headers and a few data
+    bytes are actually written, the rest of the primary data unit is a hole.
+    The primary image is two-dimensional so that its axes (and the cutout
+    below) also fit in a usize on 32 bit targets, while the byte offsets do
+    not fit in 32 bits.
+*/
+const NAXIS1: u64 = 100_000;
+const NAXIS2: u64 = 44_000;
+const PRIMARY_BYTES: u64 = NAXIS1 * NAXIS2; //4.4GB
+const BLOCK_SIZE: u64 = 2880;
+
+fn header_block(cards: &[&str]) -> Vec<u8> {
+  let mut block: Vec<u8> =
+    cards.iter().flat_map(|card| format!("{card:<80}").into_bytes()).collect();
+  block.extend_from_slice(format!("{:<80}", "END").as_bytes());
+  block.resize(BLOCK_SIZE as usize, b' ');
+  block
+}
+
+fn sparse_fixture(name: &str) -> PathBuf {
+  let mut path = dirs::cache_dir().unwrap();
+  path.push(name);
+  let mut file = File::create(&path).unwrap();
+
+  //(1) Primary HDU with a 4.4GB byte image. Only its last row is written
+  file
+    .write_all(&header_block(&[
+      "SIMPLE  =                    T",
+      "BITPIX  =                    8",
+      "NAXIS   =                    2",
+      "NAXIS1  =               100000",
+      "NAXIS2  =                44000",
+    ]))
+    .unwrap();
+  file.seek(SeekFrom::Start(BLOCK_SIZE + PRIMARY_BYTES - 4)).unwrap();
+  file.write_all(&[1, 2, 3, 4]).unwrap();
+
+  //(2) A small IMAGE extension that starts well beyond 4GB
+  let ext_start = BLOCK_SIZE + PRIMARY_BYTES.div_ceil(BLOCK_SIZE) * BLOCK_SIZE;
+  file.seek(SeekFrom::Start(ext_start)).unwrap();
+  file
+    .write_all(&header_block(&[
+      "XTENSION= 'IMAGE   '",
+      "BITPIX  =                   16",
+      "NAXIS   =                    2",
+      "NAXIS1  =                    3",
+      "NAXIS2  =                    2",
+      "PCOUNT  =                    0",
+      "GCOUNT  =                    1",
+    ]))
+    .unwrap();
+  let mut data: Vec<u8> = (1..=6i16).flat_map(|val| (val * 100).to_be_bytes()).collect();
+  data.resize(BLOCK_SIZE as usize, 0);
+  file.write_all(&data).unwrap();
+  path
+}
+
+fn ext_values(hdu: &rsf::HeaderDataUnit) -> Vec<i16> {
+  match hdu.get_data() {
+    Some(rsf::Extension::Image(img)) => {
+      img.clone().as_owned_i16_array().unwrap().t().iter().cloned().collect()
+    }
+    _ => panic!(),
+  }
+}
+
+#[test]
+fn large_file_test() {
+  let path = sparse_fixture("large_file.fits");
+  assert!(fs::metadata(&path).unwrap().len() > u32::MAX as u64);
+
+  //(1) The index finds the extension past the 4GB mark
+  let index = rsf::FitsIndex::build(&path).unwrap();
+  assert_eq!(index.get_num_hdus(), 2);
+  let layout = *index.get_layout(1).unwrap();
+  assert!(layout.get_start_block() as u64 * BLOCK_SIZE > u32::MAX as u64);
+  let ext = index.read_hdu(1).unwrap().unwrap();
+  assert_eq!(ext_values(&ext), vec![100, 200, 300, 400, 500, 600]);
+
+  //(2) Cutouts seek to byte offsets that do not fit in 32 bits
+  let start = [(NAXIS1 - 4) as usize, (NAXIS2 - 1) as usize];
+  match index.read_cutout(0, &start, &[4, 1]).unwrap() {
+    Some(rsf::Extension::Image(img)) => {
+      assert_eq!(img.view_u8().unwrap().iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3, 4])
+    }
+    _ => panic!(),
+  }
+
+  //(3) Lazily opened files seek straight past the primary data unit
+  let mut lazy = rsf::Fits::open_lazy(&path).unwrap();
+  assert_eq!(lazy.get_hdu(0).unwrap().get_estimated_data_size().unwrap() as u64, PRIMARY_BYTES);
+  let hdu = lazy.get_hdu_mut(1).unwrap();
+  hdu.load_data().unwrap();
+  assert_eq!(ext_values(hdu), vec![100, 200, 300, 400, 500, 600]);
+
+  fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn data_size_overflow_test() {
+  //A data unit of more than 2^64 bytes is an error, not an overflow
+  let mut file = header_block(&[
+    "SIMPLE  =                    T",
+    "BITPIX  =                  -64",
+    "NAXIS   =                    3",
+    "NAXIS1  =           4000000000",
+    "NAXIS2  =           4000000000",
+    "NAXIS3  =           4000000000",
+  ]);
+  file.extend(vec![0u8; BLOCK_SIZE as usize]);
+  assert!(rsf::Fits::from_stream(&file[..]).is_err());
+}
@@ -0,0 +1,102 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{fs, path::PathBuf};
+
+use rustronomy_fits::{self as rsf, hdu_err::InvalidRecordValueError, ExtendPolicy, ReadOptions};
+
+fn legacy_file() -> PathBuf {
+  //Old program files that call themselves non-conforming, with a tiny image
+  let mut bytes = Vec::new();
+  for record in [
+    "SIMPLE  =                    F / not quite FITS",
+    "BITPIX  =                    8",
+    "NAXIS   =                    1",
+    "NAXIS1  =                    4",
+    "END",
+  ] {
+    bytes.extend(format!("{record:<80}").bytes());
+  }
+  bytes.resize(2880, b' ');
+  bytes.extend([1, 2, 3, 4]);
+  bytes.resize(2 * 2880, 0);
+
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("legacy_simple_f.fits");
+  fs::write(&path, bytes).unwrap();
+  path
+}
+
+#[test]
+fn simple_false_test() {
+  let path = legacy_file();
+
+  //(1) Strict reads refuse the file
+  let err = rsf::Fits::open(&path).unwrap_err();
+  assert!(err.downcast_ref::<InvalidRecordValueError>().is_some());
+  assert!(rsf::Fits::open_partial(&path, false).is_err());
+
+  //(2) Lenient reads accept it, but flag it as non-conforming
+  let mut fits = rsf::Fits::open_with(&path, &ReadOptions::new().lenient(true)).unwrap();
+  assert!(!fits.primary().unwrap().is_conforming());
+  assert_eq!(
+    fits.primary_image().unwrap().as_array::<u8>().unwrap().as_slice().unwrap(),
+    &[1, 2, 3, 4]
+  );
+  let (partial, _) = rsf::Fits::open_partial(&path, true).unwrap();
+  assert!(!partial.primary().unwrap().is_conforming());
+
+  //(3) Its data can be reloaded as well
+  let hdu = fits.get_hdu_mut(0).unwrap();
+  hdu.unload().unwrap();
+  hdu.load_data().unwrap();
+  assert!(hdu.get_data().is_some());
+
+  //(4) Writing reproduces the file
+  let mut out = dirs::cache_dir().unwrap();
+  out.push("legacy_simple_f_copy.fits");
+  fits.write(&out).unwrap();
+  assert_eq!(fs::read(&out).unwrap(), fs::read(&path).unwrap());
+}
+
+#[test]
+fn extend_policy_test() {
+  let mut out = dirs::cache_dir().unwrap();
+  out.push("legacy_extend.fits");
+  let opts = ReadOptions::new().lenient(true);
+
+  for (policy, expected) in
+    [(ExtendPolicy::Keep, None), (ExtendPolicy::True, Some("T")), (ExtendPolicy::False, Some("F"))]
+  {
+    let fits = rsf::Fits::open_with(&legacy_file(), &opts).unwrap();
+    fits.write_with(&out, &rsf::WriteOptions::new().extend(policy)).unwrap();
+    let fits = rsf::Fits::open_with(&out, &opts).unwrap();
+    let header = fits.primary().unwrap().get_header();
+    assert_eq!(header.get_value("EXTEND").map(String::as_str), expected);
+    if expected.is_some() {
+      //EXTEND follows the last NAXISn record
+      assert_eq!(header.keywords().position(|kw| kw == "EXTEND"), Some(4));
+    }
+  }
+
+  //Omit removes EXTEND, even when it was there before
+  let fits = rsf::Fits::open_with(&out, &opts).unwrap();
+  fits.write_with(&out, &rsf::WriteOptions::new().extend(ExtendPolicy::Omit)).unwrap();
+  let fits = rsf::Fits::open_with(&out, &opts).unwrap();
+  assert!(fits.primary().unwrap().get_header().get_value("EXTEND").is_none());
+}
@@ -0,0 +1,129 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{fs, path::PathBuf};
+
+use rustronomy_fits as rsf;
+
+fn light_curve_file(name: &str, extra_cards: &[&str], rows: &[(f64, f64, f64)]) -> PathBuf {
+  //Table with TIME, SAP_FLUX and SAP_FLUX_ERR columns (row length = 60 chars)
+  let mut buf = Vec::new();
+  let cards = ["SIMPLE  =                    T", "BITPIX  =                    8"];
+  let cards =
+    cards.iter().chain(&["NAXIS   =                    0", "EXTEND  =                    T"]);
+  for card in cards.chain(&["END"]) {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(2880, b' ');
+
+  let naxis2 = format!("NAXIS2  = {:>20}", rows.len());
+  let cards = [
+    "XTENSION= 'TABLE   '",
+    "BITPIX  =                    8",
+    "NAXIS   =                    2",
+    "NAXIS1  =                   60",
+    &naxis2,
+    "PCOUNT  =                    0",
+    "GCOUNT  =                    1",
+    "TFIELDS =                    3",
+    "TTYPE1  = 'TIME    '",
+    "TBCOL1  =                    1",
+    "TFORM1  = 'D20.12  '",
+    "TTYPE2  = 'SAP_FLUX'",
+    "TBCOL2  =                   21",
+    "TFORM2  = 'E20.7   '",
+    "TTYPE3  = 'SAP_FLUX_ERR'",
+    "TBCOL3  =                   41",
+    "TFORM3  = 'E20.7   '",
+  ];
+  for card in cards.iter().chain(extra_cards).chain(&["END"]) {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(buf.len().div_ceil(2880) * 2880, b' ');
+  for (time, flux, err) in rows {
+    buf.extend(format!("{time:>20.12}{flux:>20.7E}{err:>20.7E}").bytes());
+  }
+  buf.resize(buf.len().div_ceil(2880) * 2880, b' ');
+
+  let mut path = dirs::cache_dir().unwrap();
+  path.push(name);
+  fs::write(&path, buf).unwrap();
+  path
+}
+
+#[test]
+fn read_light_curve_test() {
+  //Two-minute cadence with a gap of an hour, rows out of order
+  let step = 2.0 / 1440.0;
+  let mut rows: Vec<(f64, f64, f64)> =
+    (0..10).map(|i| (100.0 + i as f64 * step, 1000.0 + i as f64, 3.0)).collect();
+  rows.extend((0..5).map(|i| (100.0 + (40 + i) as f64 * step, 2000.0, 4.0)));
+  rows.swap(0, 1);
+  let cards =
+    ["TIMESYS = 'TDB     '", "BJDREFI =              2457000", "BJDREFF =                  0.5"];
+  let path = light_curve_file("light_curve.fits", &cards, &rows);
+  let hdu = rsf::Fits::open(&path).unwrap().get_hdu(1).unwrap().clone();
+
+  //(1) The flux column is found, and the time is made absolute
+  let lc = rsf::LightCurve::from_hdu(&hdu).unwrap();
+  assert_eq!(lc.len(), 15);
+  assert_eq!(lc.get_flux_column(), "SAP_FLUX");
+  assert_eq!(lc.get_timesys(), "TDB");
+  assert_eq!(lc.get_time_ref(), 2457000.5);
+  assert!((lc.get_time()[0] - 2457100.5).abs() < 1e-6);
+  assert!(lc.get_time().windows(2).into_iter().all(|w| w[0] < w[1]));
+  assert_eq!(lc.get_flux()[0], 1000.0);
+  assert_eq!(lc.get_flux_err().unwrap()[14], 4.0);
+
+  //(2) Gap detection
+  assert!((lc.get_cadence().unwrap() - step).abs() < 1e-6);
+  assert_eq!(lc.segments(1.5), [0..10, 10..15]);
+  let gaps = lc.gaps(1.5);
+  assert_eq!(gaps.len(), 1);
+  assert!((gaps[0].1 - gaps[0].0 - 31.0 * step).abs() < 1e-6);
+
+  //(3) Missing columns
+  let err = rsf::LightCurve::from_hdu_with(&hdu, "PDCSAP_FLUX").unwrap_err();
+  assert_eq!(err.to_string(), "table has no column named PDCSAP_FLUX");
+}
+
+#[test]
+fn time_units_test() {
+  //Times in seconds, relative to an MJD, with an offset
+  let rows = [(0.0, 1.0, 0.1), (86400.0, 2.0, 0.1), (43200.0, 1.5, 0.1)];
+  let cards =
+    ["TIMEUNIT= 's       '", "MJDREF  =              58000.0", "TIMEZERO=               8640.0"];
+  let path = light_curve_file("light_curve_seconds.fits", &cards, &rows);
+  let hdu = rsf::Fits::open(&path).unwrap().get_hdu(1).unwrap().clone();
+  let lc = rsf::LightCurve::from_hdu(&hdu).unwrap();
+  assert_eq!(lc.get_timesys(), "UTC");
+  assert_eq!(lc.get_time().to_vec(), [58000.1, 58000.6, 58001.1]);
+  assert_eq!(lc.get_flux().to_vec(), [1.0, 1.5, 2.0]);
+  assert_eq!(lc.segments(1.5).len(), 1);
+  assert!(lc.gaps(1.5).is_empty());
+
+  //Unknown time units are refused
+  let cards = ["TIMEUNIT= 'yr      '"];
+  let path = light_curve_file("light_curve_years.fits", &cards, &rows);
+  let hdu = rsf::Fits::open(&path).unwrap().get_hdu(1).unwrap().clone();
+  assert!(rsf::LightCurve::from_hdu(&hdu).is_err());
+
+  //Images are not light curves
+  let image = rsf::Fits::open(&PathBuf::from("resources/Hubble_NICMOS.fits")).unwrap();
+  assert!(rsf::LightCurve::from_hdu(image.get_hdu(0).unwrap()).is_err());
+}
@@ -0,0 +1,47 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{fs, path::PathBuf};
+
+use fs2::FileExt;
+use rustronomy_fits as rsf;
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+#[test]
+fn locked_write_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let fits = rsf::Fits::open(&real_path).unwrap();
+
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("locked.fits");
+  fits.clone().write_with_lock(&path, rsf::LockPolicy::Wait).unwrap();
+  let written = fs::read(&path).unwrap();
+
+  //Another "process" holds the lock, so we may not touch the file
+  let other = fs::File::open(&path).unwrap();
+  other.lock_exclusive().unwrap();
+  assert!(fits.clone().write_with_lock(&path, rsf::LockPolicy::FailFast).is_err());
+  assert_eq!(fs::read(&path).unwrap(), written);
+
+  //Once the lock is released, we can write again
+  other.unlock().unwrap();
+  fits.write_with_lock(&path, rsf::LockPolicy::FailFast).unwrap();
+  assert_eq!(fs::read(&path).unwrap(), written);
+}
@@ -0,0 +1,77 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::path::PathBuf;
+
+use rustronomy_fits as rsf;
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+#[test]
+fn header_digest_test() {
+  //Formatting does not matter, values and comments do
+  let header = rsf::Header::from_text(
+    "NAXIS   =                    0\nOBJECT  = 'NGC4151 '           / target",
+  )
+  .unwrap();
+  let reformatted =
+    rsf::Header::from_text("NAXIS   = 0\nOBJECT  = 'NGC4151'   /   target").unwrap();
+  let changed = rsf::Header::from_text("NAXIS   = 0\nOBJECT  = 'NGC4152'   /   target").unwrap();
+  let commented = rsf::Header::from_text("NAXIS   = 0\nOBJECT  = 'NGC4151'   /   other").unwrap();
+  assert_eq!(header.digest(), reformatted.digest());
+  assert_ne!(header.digest(), changed.digest());
+  assert_ne!(header.digest(), commented.digest());
+
+  //Checksums change along with everything else, so they are left out
+  let summed = header.to_text().unwrap().replace("END\n", "CHECKSUM= 'hcHjjc9ghcEghc9g'\nEND\n");
+  assert_eq!(rsf::Header::from_text(&summed).unwrap().digest(), header.digest());
+
+  //Digests are stable, so they may be stored
+  assert_eq!(header.digest(), 0x7df8_9bea_61d3_6b67);
+}
+
+#[test]
+fn manifest_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let fits = rsf::Fits::open(&real_path).unwrap();
+  let manifest = fits.manifest();
+  assert_eq!(manifest.len(), 6);
+  assert_eq!(manifest[0].get_data_digest(), None);
+  assert_eq!(manifest[1].get_extname(), Some("SCI"));
+  assert!(manifest[1..].iter().all(|entry| entry.get_data_digest().is_some()));
+  assert!(manifest[1].to_string().starts_with("#1 SCI      header "));
+
+  //Rewriting the file changes nothing but the DATE of the primary header
+  let mut out = dirs::cache_dir().unwrap();
+  out.push("manifest.fits");
+  fits.clone().write(&out).unwrap();
+  let rewritten = rsf::Fits::open(&out).unwrap().manifest();
+  assert_eq!(manifest[1..], rewritten[1..]);
+
+  //Changing a single pixel changes the data digest, but not the header digest
+  let mut fits = fits;
+  match fits.get_hdu_mut(1).unwrap().get_data_mut() {
+    Some(rsf::Extension::Image(img)) => img.as_array_mut::<f32>().unwrap()[[10, 10]] += 1.0,
+    _ => panic!("SCI extension has no image"),
+  }
+  let changed = fits.manifest();
+  assert_eq!(changed[1].get_header_digest(), manifest[1].get_header_digest());
+  assert_ne!(changed[1].get_data_digest(), manifest[1].get_data_digest());
+  assert_eq!(changed[2], manifest[2]);
+}
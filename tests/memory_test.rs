@@ -0,0 +1,87 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::path::PathBuf;
+
+use rustronomy_fits as rsf;
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+#[test]
+fn unload_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let mut fits = rsf::Fits::open(&real_path).unwrap();
+  let original = format!("{}", fits.get_hdu(1).unwrap());
+
+  //HDU #1 is a f32 image, the primary HDU has no data at all
+  let usage = fits.memory_usage();
+  let layout = *fits.get_hdu(1).unwrap().get_layout().unwrap();
+  assert_eq!(usage[0], 0);
+  assert!(usage[1] > 0 && usage[1] <= layout.get_data_blocks() * 2880);
+
+  //Unloading drops the data but keeps the header and the layout
+  let hdu = fits.get_hdu_mut(1).unwrap();
+  hdu.unload().unwrap();
+  assert!(!hdu.is_loaded() && hdu.get_data().is_none());
+  assert!(hdu.get_header().get_value("NAXIS1").is_some());
+  assert_eq!(fits.memory_usage()[1], 0);
+
+  //Reloading reads the same data again
+  let hdu = fits.get_hdu_mut(1).unwrap();
+  hdu.load_data().unwrap();
+  assert!(hdu.is_loaded());
+  assert_eq!(format!("{hdu}"), original);
+  assert_eq!(fits.memory_usage(), usage);
+
+  //Unloaded HDU's are reloaded when they are written
+  fits.get_hdu_mut(1).unwrap().unload().unwrap();
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("unloaded.fits");
+  fits.write(&path).unwrap();
+  let written = rsf::Fits::open(&path).unwrap();
+  assert_eq!(format!("{}", written.get_hdu(1).unwrap()), original);
+
+  //Data that was not read from a file cannot be unloaded
+  let mut preview = rsf::Fits::read_preview(&real_path, 1, 16).unwrap();
+  assert!(preview.unload().is_err());
+}
+
+#[test]
+fn header_layout_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let mut fits = rsf::Fits::open(&real_path).unwrap();
+  let usage = fits.memory_usage();
+  let shape = fits.get_hdu(1).unwrap().get_data().map(|data| match data {
+    rsf::Extension::Image(img) => img.as_f32_array().unwrap().shape().to_vec(),
+    _ => panic!(),
+  });
+
+  //The primary HDU has no data, HDU #1 is a f32 image
+  let primary = fits.get_hdu(0).unwrap();
+  assert!(primary.get_shape().unwrap().is_empty());
+  assert_eq!(primary.get_estimated_data_size().unwrap(), 0);
+
+  //The layout does not depend on the data being loaded
+  let hdu = fits.get_hdu_mut(1).unwrap();
+  hdu.unload().unwrap();
+  assert_eq!(Some(hdu.get_shape().unwrap()), shape);
+  assert!(matches!(hdu.get_bitpix().unwrap(), rsf::Bitpix::Spf));
+  assert_eq!(hdu.get_estimated_data_size().unwrap(), usage[1]);
+}
@@ -0,0 +1,117 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::path::PathBuf;
+
+use rustronomy_fits::{self as rsf, KeywordValue, MetaValue};
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+fn real_fits() -> rsf::Fits {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  rsf::Fits::open(&real_path).unwrap()
+}
+
+fn lookup<'a>(pairs: &'a [(String, MetaValue)], keyword: &str) -> &'a MetaValue {
+  &pairs.iter().find(|(kw, _)| kw == keyword).unwrap().1
+}
+
+#[test]
+fn typed_values_test() {
+  let fits = real_fits();
+  let pairs = fits.get_hdu(0).unwrap().metadata_pairs();
+
+  //(1) Values are typed, strings are unquoted (without trailing spaces)
+  assert_eq!(lookup(&pairs, "SIMPLE"), &MetaValue::Logical(true));
+  assert_eq!(lookup(&pairs, "BITPIX"), &MetaValue::Integer(16));
+  assert_eq!(lookup(&pairs, "EQUINOX"), &MetaValue::Float(2000.0));
+  assert_eq!(lookup(&pairs, "RA_TARG"), &MetaValue::Float(182.63625));
+  assert_eq!(lookup(&pairs, "TARGNAME"), &MetaValue::String(String::from("NGC4151")));
+
+  //(2) Records are in file order
+  let keywords: Vec<&str> = pairs.iter().map(|(kw, _)| kw.as_str()).take(4).collect();
+  assert_eq!(keywords, ["SIMPLE", "BITPIX", "NAXIS", "EXTEND"]);
+}
+
+#[test]
+fn parse_test() {
+  let parse = |raw: &str| MetaValue::parse_value(raw).unwrap();
+  assert_eq!(parse("F"), MetaValue::Logical(false));
+  assert_eq!(parse("-42"), MetaValue::Integer(-42));
+  assert_eq!(parse("1.5D-3"), MetaValue::Float(1.5e-3));
+  assert_eq!(parse("(1.0, -2.5)"), MetaValue::Complex(1.0, -2.5));
+  assert_eq!(parse("'it''s'"), MetaValue::String(String::from("it's")));
+  assert_eq!(parse("   "), MetaValue::Undefined);
+
+  //Values that do not follow the FITS syntax are kept as text
+  assert_eq!(parse("NaN"), MetaValue::String(String::from("NaN")));
+  assert_eq!(parse("12:30"), MetaValue::String(String::from("12:30")));
+
+  assert_eq!(MetaValue::Float(1e-7).format_value(), "1E-7");
+  assert_eq!(MetaValue::String(String::from("it's")).format_value(), "'it''s'");
+  assert_eq!(MetaValue::Complex(1.0, 2.0).to_string(), "(1, 2)");
+}
+
+#[test]
+fn rebuild_test() {
+  let fits = real_fits();
+  for index in 0..fits.get_num_hdus() {
+    //(1) Headers can be rebuilt from their pairs, keeping the order
+    let header = fits.get_hdu(index).unwrap().get_header();
+    let pairs = header.metadata_pairs();
+    let rebuilt = rsf::Header::from_metadata_pairs(&pairs).unwrap();
+    assert_eq!(rebuilt.metadata_pairs(), pairs);
+    assert_eq!(rebuilt.comments().count(), header.comments().count());
+    assert_eq!(rebuilt.history().count(), header.history().count());
+
+    //(2) The rebuilt header is valid FITS
+    let reparsed = rsf::Header::from_text(&rebuilt.to_text().unwrap()).unwrap();
+    assert_eq!(reparsed.metadata_pairs(), pairs);
+  }
+}
+
+#[test]
+fn rebuild_hdu_test() {
+  let fits = real_fits();
+
+  //(1) The primary HDU has no data, so it can be rebuilt
+  let pairs = fits.get_hdu(0).unwrap().metadata_pairs();
+  let hdu = rsf::HeaderDataUnit::from_metadata_pairs(&pairs).unwrap();
+  assert!(hdu.get_data().is_none());
+  assert_eq!(hdu.metadata_pairs(), pairs);
+
+  //(2) Image extensions can not be rebuilt without their data
+  let pairs = fits.get_hdu(1).unwrap().metadata_pairs();
+  assert!(rsf::HeaderDataUnit::from_metadata_pairs(&pairs).is_err());
+}
+
+#[test]
+fn invalid_pairs_test() {
+  let pair = |keyword: &str, value: MetaValue| vec![(keyword.to_string(), value)];
+
+  //Non-finite reals and overlong values can not be written
+  assert!(rsf::Header::from_metadata_pairs(&pair("GAIN", MetaValue::Float(f64::NAN))).is_err());
+  let long = MetaValue::String("x".repeat(80));
+  assert!(rsf::Header::from_metadata_pairs(&pair("HIERARCH ESO DET NAME", long)).is_err());
+
+  //Undefined values are written without a value indicator
+  let header = rsf::Header::from_metadata_pairs(&pair("BLANKED", MetaValue::Undefined)).unwrap();
+  assert!(header.to_text().unwrap().starts_with("BLANKED\n"));
+}
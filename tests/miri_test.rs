@@ -0,0 +1,126 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*
+    Small, purely in-memory tests of the parsing path, which are cheap enough
+    to run under miri (which checks for undefined behaviour in the code that
+    is executed, dependencies included):
+
+        MIRIFLAGS="-Zmiri-tree-borrows -Zmiri-ignore-leaks" \
+          cargo +nightly miri test --test miri_test
+
+    Tree borrows is needed for crossbeam (used by rayon), which violates the
+    stricter stacked borrows rules, and the threads of the global rayon pool
+    are never joined, hence ignore-leaks.
+*/
+
+use std::io::Cursor;
+
+use rustronomy_fits::{self as rsf, Extension, TableEntry};
+
+fn padded(cards: &[&str], fill: u8) -> Vec<u8> {
+  let mut buf: Vec<u8> = cards.iter().flat_map(|card| format!("{card:80}").into_bytes()).collect();
+  buf.resize(buf.len().div_ceil(2880) * 2880, fill);
+  buf
+}
+
+fn small_file() -> Vec<u8> {
+  //3x2 i16 image (with BZERO and a long string), followed by a 2 row table
+  let mut buf = padded(
+    &[
+      "SIMPLE  =                    T",
+      "BITPIX  =                   16",
+      "NAXIS   =                    2",
+      "NAXIS1  =                    3",
+      "NAXIS2  =                    2",
+      "EXTEND  =                    T",
+      "BZERO   =                  0.0",
+      "OBSERVER= 'A rather long name that does not fit on a single card, so it&'",
+      "CONTINUE  'continues here'",
+      "END",
+    ],
+    b' ',
+  );
+  let mut data: Vec<u8> = (-3i16..3).flat_map(|px| px.to_be_bytes()).collect();
+  data.resize(2880, 0);
+  buf.extend(data);
+
+  buf.extend(padded(
+    &[
+      "XTENSION= 'TABLE   '",
+      "BITPIX  =                    8",
+      "NAXIS   =                    2",
+      "NAXIS1  =                   14",
+      "NAXIS2  =                    2",
+      "PCOUNT  =                    0",
+      "GCOUNT  =                    1",
+      "TFIELDS =                    2",
+      "TTYPE1  = 'NAME    '",
+      "TBCOL1  =                    1",
+      "TFORM1  = 'A6      '",
+      "TTYPE2  = 'FLUX    '",
+      "TBCOL2  =                    7",
+      "TFORM2  = 'E8.2    '",
+      "END",
+    ],
+    b' ',
+  ));
+  let mut rows = b"M31     1.5E+0M1      2.0D+1".to_vec();
+  rows.resize(2880, b' ');
+  buf.extend(rows);
+  buf
+}
+
+#[test]
+fn parse_test() {
+  let fits = rsf::Fits::from_stream(Cursor::new(small_file())).unwrap();
+  assert_eq!(fits.get_num_hdus(), 2);
+
+  //(1) Header, including a CONTINUE'd string
+  let header = fits.get_hdu(0).unwrap().get_header();
+  let observer = header.get_value_as::<String>("OBSERVER").unwrap();
+  assert!(observer.contains("so itcontinues here"), "{observer}");
+
+  //(2) Image
+  let Some(Extension::Image(rsf::TypedImage::I16Img(img))) = fits.get_hdu(0).unwrap().get_data()
+  else {
+    panic!("primary HDU does not hold an i16 image");
+  };
+  assert_eq!(img.get_data().iter().copied().collect::<Vec<_>>(), [-3, 0, -2, 1, -1, 2]);
+
+  //(3) Table
+  let Some(Extension::AsciiTable(tbl)) = fits.get_hdu(1).unwrap().get_data() else {
+    panic!("first extension does not hold a table");
+  };
+  assert_eq!(tbl.get_shape(), (2, 2));
+  assert_eq!(tbl.get_str(0, 1).map(str::trim), Some("M1"));
+  assert!(matches!(tbl.get_entry(1, 1).unwrap(), TableEntry::Float(num) if num == 20.0));
+}
+
+#[test]
+fn truncated_test() {
+  //Truncated and garbled input gives errors, not undefined behaviour
+  let file = small_file();
+  for len in [80, 2880, 2880 + 100, file.len() - 2880] {
+    assert!(rsf::Fits::from_stream(Cursor::new(&file[..len])).is_err(), "{len}");
+  }
+  let mut garbled = file.clone();
+  garbled[2880 * 2 + 80 * 3 + 10..2880 * 2 + 80 * 3 + 30].fill(0xFF);
+  assert!(rsf::Fits::from_stream(Cursor::new(garbled)).is_err());
+}
@@ -0,0 +1,94 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+#![cfg(feature = "mmap")]
+
+use std::{fs, path::PathBuf};
+
+use ndarray::{Array, IxDyn};
+use rustronomy_fits as rsf;
+
+#[test]
+fn mmap_byte_test() {
+  //(1) A byte mask, written as the primary image of a file
+  let mask = Array::from_shape_fn(IxDyn(&[5, 3]), |idx| (idx[0] * 40 + idx[1]) as u8);
+  let mut fits = rsf::Fits::open(&PathBuf::from("resources/Astro_UIT.fits")).unwrap();
+  let img = rsf::TypedImage::from(rsf::Image::new(mask.clone()));
+  *fits.get_hdu_mut(0).unwrap().get_data_mut().unwrap() = rsf::Extension::Image(img);
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("mmap_byte.fits");
+  fits.write(&path).unwrap();
+
+  //(2) Byte images are viewed in the map, without reading them
+  let index = rsf::FitsIndex::build(&path).unwrap();
+  let img = index.mmap_image(0).unwrap().unwrap();
+  assert!(img.is_mapped());
+  assert_eq!(img.view_u8().unwrap(), mask);
+  assert_eq!(img.as_u8_array().unwrap(), mask);
+  assert!(index.mmap_image(index.get_num_hdus()).unwrap().is_none());
+}
+
+#[test]
+fn mmap_float_test() {
+  //Other pixel types are viewed as an ndarray as well
+  let path = PathBuf::from("resources/Hubble_NICMOS.fits");
+  let fits = rsf::Fits::open(&path).unwrap();
+  let full = match fits.get_hdu(1).unwrap().get_data() {
+    Some(rsf::Extension::Image(img)) => img.clone().as_owned_f32_array().unwrap(),
+    _ => panic!(),
+  };
+
+  let index = rsf::FitsIndex::build(&path).unwrap();
+  let mut img = match index.mmap_image(1).unwrap().unwrap() {
+    rsf::TypedImage::SpfImg(img) => img,
+    _ => panic!("expected an f32 image"),
+  };
+  assert!(img.is_mapped());
+  assert_eq!(img.view(), full);
+  assert_eq!(img.get_data(), full);
+  assert_eq!(img.view()[[3, 5]], full[[3, 5]]);
+
+  //Modifying the pixels moves them into memory, the file is left alone
+  img.get_data_mut()[[3, 5]] = -1.0;
+  assert!(!img.is_mapped());
+  assert_eq!(index.mmap_image(1).unwrap().unwrap().as_f32_array().unwrap(), full);
+
+  //The primary HDU of this file has no image to map
+  assert!(index.mmap_image(0).is_err());
+}
+
+#[test]
+fn mmap_truncated_test() {
+  //(1) Index a copy of a complete file...
+  let real_path = PathBuf::from("resources/Hubble_NICMOS.fits");
+  let bytes = fs::read(&real_path).unwrap();
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("mmap_truncated.fits");
+  fs::write(&path, &bytes).unwrap();
+  let index = rsf::FitsIndex::build(&path).unwrap();
+
+  //(2) ...and cut it off one block into the data unit of the image afterwards
+  let layout = index.get_layout(1).unwrap();
+  let data_start = (layout.get_start_block() + layout.get_header_blocks()) * 2880;
+  fs::write(&path, &bytes[..data_start + 2880]).unwrap();
+
+  //Maps may not reach beyond the end of the file
+  let err = index.mmap_image(1).unwrap_err();
+  let err = err.downcast_ref::<rsf::io_err::TruncatedFileErr>().unwrap();
+  assert_eq!((err.get_hdu(), err.get_got()), (1, 2880));
+}
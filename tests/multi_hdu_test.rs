@@ -0,0 +1,116 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::fs;
+
+use rsf::TableEntry::{Float, Int};
+use rustronomy_fits as rsf;
+
+fn card(keyword: &str, value: &str) -> String {
+  format!("{keyword:8}= {value:>20}")
+}
+
+fn blocks(cards: &[String]) -> Vec<u8> {
+  let mut buf: Vec<u8> = cards.iter().flat_map(|card| format!("{card:80}").into_bytes()).collect();
+  buf.extend(format!("{:80}", "END").bytes());
+  buf.resize(buf.len().div_ceil(2880) * 2880, b' ');
+  buf
+}
+
+fn data(mut bytes: Vec<u8>, fill: u8) -> Vec<u8> {
+  bytes.resize(bytes.len().div_ceil(2880) * 2880, fill);
+  bytes
+}
+
+fn extension(xtension: &str, bitpix: &str, naxis: [usize; 2], extra: &[(&str, &str)]) -> Vec<u8> {
+  let mut cards = vec![
+    card("XTENSION", xtension),
+    card("BITPIX", bitpix),
+    card("NAXIS", "2"),
+    card("NAXIS1", &naxis[0].to_string()),
+    card("NAXIS2", &naxis[1].to_string()),
+    card("PCOUNT", "0"),
+    card("GCOUNT", "1"),
+  ];
+  cards.extend(extra.iter().map(|(keyword, value)| card(keyword, value)));
+  blocks(&cards)
+}
+
+fn multi_hdu_file() -> Vec<u8> {
+  //(1) Primary HDU without a data array
+  let mut file =
+    blocks(&[card("SIMPLE", "T"), card("BITPIX", "8"), card("NAXIS", "0"), card("EXTEND", "T")]);
+
+  //(2) A 3x2 image
+  file.extend(extension("'IMAGE   '", "16", [3, 2], &[("EXTNAME", "'SCI'")]));
+  file.extend(data([1i16, 2, 3, -4, -5, -6].iter().flat_map(|x| x.to_be_bytes()).collect(), 0));
+
+  //(3) An ASCII table with an integer and a float column
+  let fields = [("TFIELDS", "2"), ("TBCOL1", "1"), ("TFORM1", "'I4'")];
+  let fields = [&fields[..], &[("TBCOL2", "6"), ("TFORM2", "'F5.1'")]].concat();
+  file.extend(extension("'TABLE   '", "8", [10, 2], &fields));
+  file.extend(data(b"  12   3.5  -7 -0.25".to_vec(), b' '));
+
+  //(4) A binary table with one integer column
+  let fields = [("TFIELDS", "1"), ("TTYPE1", "'COUNT'"), ("TFORM1", "'J'")];
+  file.extend(extension("'BINTABLE'", "8", [4, 2], &fields));
+  file.extend(data([40i32, -41].iter().flat_map(|x| x.to_be_bytes()).collect(), 0));
+  file
+}
+
+fn check(fits: &rsf::Fits) {
+  assert_eq!(fits.get_num_hdus(), 4);
+  assert!(fits.get_hdu(0).unwrap().get_data().is_none());
+
+  let Some(rsf::Extension::Image(rsf::TypedImage::I16Img(img))) =
+    fits.get_hdu(1).unwrap().get_data()
+  else {
+    panic!("HDU 1 is not an i16 image");
+  };
+  assert_eq!(img.get_data().shape(), &[3, 2]);
+  assert_eq!(img.get_data().iter().sum::<i16>(), -9);
+
+  let Some(rsf::Extension::AsciiTable(tbl)) = fits.get_hdu(2).unwrap().get_data() else {
+    panic!("HDU 2 is not an ASCII table");
+  };
+  assert_eq!(tbl.get_shape(), (2, 2));
+  assert!(matches!(tbl.get_entry(0, 1), Ok(Int(-7))));
+  assert!(matches!(tbl.get_entry(1, 0), Ok(Float(x)) if x == 3.5));
+
+  let Some(rsf::Extension::BinTable(tbl)) = fits.get_hdu(3).unwrap().get_data() else {
+    panic!("HDU 3 is not a binary table");
+  };
+  assert_eq!(tbl.get_shape(), (1, 2));
+  assert!(matches!(tbl.get_entry(0, 1), Ok(Int(-41))));
+}
+
+#[test]
+fn multi_hdu_test() {
+  //All extensions are read until the end of the file, from disk and streams
+  let file = multi_hdu_file();
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("multi_hdu.fits");
+  fs::write(&path, &file).unwrap();
+  check(&rsf::Fits::open(&path).unwrap());
+  check(&rsf::Fits::from_stream(file.as_slice()).unwrap());
+
+  //A file that ends halfway an extension is an error, not a shorter file
+  let truncated = &file[..file.len() - 2880];
+  assert!(rsf::Fits::from_stream(truncated).is_err());
+}
@@ -0,0 +1,157 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{fs, path::PathBuf};
+
+use rustronomy_fits::{self as rsf, Extension, TableEntry};
+
+fn table_file(name: &str) -> PathBuf {
+  //ASCII table where ID, FLUX and NAME have a TNULLn string (MAG has none)
+  let mut buf = Vec::new();
+  for card in ["SIMPLE  = T", "BITPIX  = 8", "NAXIS   = 0", "EXTEND  = T", "END"] {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(2880, b' ');
+
+  let columns =
+    [("ID", "I4", Some("-99")), ("FLUX", "F8.2", Some("*")), ("NAME", "A6", Some("NONE"))];
+  let mut cards = vec![
+    "XTENSION= 'TABLE   '".to_string(),
+    "BITPIX  = 8".to_string(),
+    "NAXIS   = 2".to_string(),
+    "NAXIS1  = 24".to_string(),
+    "NAXIS2  = 10".to_string(),
+    "PCOUNT  = 0".to_string(),
+    "GCOUNT  = 1".to_string(),
+    "TFIELDS = 4".to_string(),
+  ];
+  let mut tbcol = 1;
+  for (idx, (ttype, tform, tnull)) in columns.iter().chain([&("MAG", "F6.2", None)]).enumerate() {
+    let n = idx + 1;
+    cards.push(format!("{:8}= '{ttype}'", format!("TTYPE{n}")));
+    cards.push(format!("{:8}= {tbcol}", format!("TBCOL{n}")));
+    cards.push(format!("{:8}= '{tform}'", format!("TFORM{n}")));
+    if let Some(tnull) = tnull {
+      cards.push(format!("{:8}= '{tnull}'", format!("TNULL{n}")));
+    }
+    tbcol += tform[1..].split('.').next().unwrap().parse::<usize>().unwrap();
+  }
+  cards.push("END".to_string());
+  for card in cards {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(buf.len().div_ceil(2880) * 2880, b' ');
+
+  for row in 0..10 {
+    let id = if row == 8 { String::from("-99") } else { row.to_string() };
+    let flux =
+      if row == 1 || row == 8 { String::from("*") } else { format!("{:.2}", row as f64 * 1.5) };
+    let name = if row == 9 { "NONE" } else { "SRC" };
+    let row = format!("{id:>4}{flux:>8}{name:<6}{:6.2}", 20.0 + row as f64 / 10.0);
+    assert_eq!(row.len(), 24);
+    buf.extend(row.bytes());
+  }
+  buf.resize(buf.len().div_ceil(2880) * 2880, b' ');
+
+  let mut path = dirs::cache_dir().unwrap();
+  path.push(name);
+  fs::write(&path, buf).unwrap();
+  path
+}
+
+fn table(fits: &rsf::Fits) -> &rsf::AsciiTable {
+  match fits.get_hdu(1).unwrap().get_data() {
+    Some(Extension::AsciiTable(table)) => table,
+    _ => panic!("HDU does not contain a table"),
+  }
+}
+
+#[test]
+fn validity_test() {
+  let path = table_file("null_columns_validity.fits");
+  let fits = rsf::Fits::open(&path).unwrap();
+  let table = table(&fits);
+
+  //(1) Nulls are marked in the validity bitmap (LSB first, like Arrow)
+  let flux = table.get_col_validity(1).unwrap();
+  assert_eq!(flux.len(), 10);
+  assert_eq!(flux.null_count(), 2);
+  assert_eq!(flux.as_bytes(), [0b1111_1101, 0b0000_0010]);
+  assert!(flux.is_null(1) && flux.is_null(8) && flux.is_valid(2));
+  assert!(!flux.is_valid(10) && !flux.is_null(10));
+  assert_eq!(flux.iter().filter(|valid| !valid).count(), 2);
+
+  let id = table.get_col_validity(0).unwrap();
+  assert_eq!(id.iter().collect::<Vec<_>>(), (0..10).map(|row| row != 8).collect::<Vec<_>>());
+  assert!(table.is_null(2, 9));
+  assert!(!table.is_null(2, 8));
+
+  //(2) Columns without nulls (or without TNULLn) have no bitmap
+  assert!(table.get_col_validity(3).is_none());
+  assert!(!table.is_null(3, 0));
+  assert_eq!(table.get_col_null(0), Some("-99"));
+  assert_eq!(table.get_col_null(3), None);
+
+  //(3) Null entries hold a placeholder
+  assert!(matches!(table.get_entry(0, 8).unwrap(), TableEntry::Int(0)));
+  assert!(matches!(table.get_entry(1, 1).unwrap(), TableEntry::Float(num) if num.is_nan()));
+  assert!(matches!(table.get_entry(1, 2).unwrap(), TableEntry::Float(num) if num == 3.0));
+}
+
+#[test]
+fn encode_nulls_test() {
+  let path = table_file("null_columns_encode.fits");
+  let fits = rsf::Fits::open(&path).unwrap();
+  let table = table(&fits);
+
+  //(1) Nulls are encoded with their TNULLn string
+  let ids = table.get_fmtd_column(0).unwrap();
+  assert_eq!(ids[8], "-99");
+  assert_eq!(ids[7], "7");
+  let flux = table.get_fmtd_column(1).unwrap();
+  assert_eq!((flux[1].as_str(), flux[8].as_str()), ("*", "*"));
+  assert_eq!(table.get_fmtd_column(2).unwrap()[9], "NONE");
+
+  //(2) and exported as nulls
+  let ipac = fits.get_hdu(1).unwrap().to_ipac().unwrap();
+  let rows: Vec<&str> = ipac.lines().filter(|line| !line.starts_with(['\\', '|'])).collect();
+  assert_eq!(rows.len(), 10);
+  assert!(rows[1].contains("null"));
+  assert!(!rows[2].contains("null"));
+  assert_eq!(rows[8].matches("null").count(), 2);
+}
+
+#[test]
+fn conversion_nulls_test() {
+  let path = table_file("null_columns_conversion.fits");
+  let fits = rsf::Fits::open(&path).unwrap();
+  let table = table(&fits);
+
+  //Numeric nulls survive the round trip through a binary table, integers
+  //keep their TNULLn. Text cannot be null in binary tables
+  let bin = table.to_bintable().unwrap();
+  assert_eq!(bin.get_col_null(0), Some(-99));
+  assert!(bin.is_null(0, 8) && bin.is_null(1, 1) && !bin.is_null(2, 9));
+  let back = bin.to_ascii(&["I4", "F8.2", "A6", "F6.2"]).unwrap();
+  assert_eq!(back.get_col_validity(0), table.get_col_validity(0));
+  assert_eq!(back.get_col_validity(1), table.get_col_validity(1));
+  assert_eq!(back.get_col_null(0), Some("-99"));
+  assert!(back.get_col_validity(2).is_none());
+  assert!(matches!(back.get_entry(0, 8).unwrap(), TableEntry::Int(0)));
+}
@@ -0,0 +1,128 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use chrono::NaiveDate;
+use rustronomy_fits as rsf;
+
+fn value(header: &rsf::Header, keyword: &str) -> Option<String> {
+  header.get_value(keyword).map(|raw| raw.trim().to_string())
+}
+
+#[test]
+fn time_block_test() {
+  let start = NaiveDate::from_ymd_opt(2021, 3, 14).unwrap();
+  let start = start.and_hms_micro_opt(1, 59, 26, 535_897).unwrap();
+  let time = rsf::ObservationTime::new(start, rsf::TimeScale::Tt);
+  let mut header = rsf::Header::from_text("OBJECT  = 'M31'").unwrap();
+  time.write_to(&mut header);
+
+  //DATE-OBS and MJD-OBS describe the same (millisecond) instant
+  assert_eq!(value(&header, "DATE-OBS").as_deref(), Some("'2021-03-14T01:59:26.536'"));
+  assert_eq!(value(&header, "TIMESYS").as_deref(), Some("'TT'"));
+  let mjd = header.get_value_as::<f64>("MJD-OBS").unwrap();
+  assert!((mjd - 59287.08294601852).abs() < 1e-9);
+
+  //Reading the block back gives the same time
+  assert_eq!(rsf::ObservationTime::from_header(&header).unwrap(), time);
+  let from_mjd = rsf::ObservationTime::from_mjd(mjd, rsf::TimeScale::Tt).unwrap();
+  assert_eq!(from_mjd, time);
+}
+
+#[test]
+fn time_fallback_test() {
+  //Without DATE-OBS the time comes from MJD-OBS, and TIMESYS defaults to UTC
+  let header = rsf::Header::from_text("MJD-OBS =              51544.5").unwrap();
+  let time = rsf::ObservationTime::from_header(&header).unwrap();
+  assert_eq!(time.get_iso(), "2000-01-01T12:00:00.000");
+  assert_eq!(time.get_scale(), rsf::TimeScale::Utc);
+
+  //A plain date is accepted, an unknown time scale is not
+  let header = rsf::Header::from_text("DATE-OBS= '1999-12-31'").unwrap();
+  let time = rsf::ObservationTime::from_header(&header).unwrap();
+  assert_eq!(time.get_mjd(), 51543.0);
+  let header = rsf::Header::from_text("DATE-OBS= '1999-12-31'\nTIMESYS = 'MARS'").unwrap();
+  assert!(rsf::ObservationTime::from_header(&header).is_err());
+  assert!(rsf::ObservationTime::from_mjd(f64::NAN, rsf::TimeScale::Utc).is_err());
+}
+
+#[test]
+fn frame_block_test() {
+  let mut header =
+    rsf::Header::from_text("RADESYS = 'FK4'\nEQUINOX =               1950.0").unwrap();
+
+  //Switching to FK5 replaces the equinox
+  rsf::SkyFrame::fk5().write_to(&mut header).unwrap();
+  assert_eq!(value(&header, "RADESYS").as_deref(), Some("'FK5'"));
+  assert_eq!(header.get_value_as::<f64>("EQUINOX").unwrap(), 2000.0);
+  assert_eq!(rsf::SkyFrame::from_header(&header).unwrap(), rsf::SkyFrame::Fk5(2000.0));
+
+  //ICRS has no equinox, so a stale EQUINOX is removed
+  rsf::SkyFrame::Icrs.write_to(&mut header).unwrap();
+  assert_eq!(value(&header, "RADESYS").as_deref(), Some("'ICRS'"));
+  assert_eq!(value(&header, "EQUINOX"), None);
+  assert_eq!(rsf::SkyFrame::from_header(&header).unwrap(), rsf::SkyFrame::Icrs);
+
+  //Invalid equinoxes are not written at all
+  assert!(rsf::SkyFrame::Fk4(f64::NAN).write_to(&mut header).is_err());
+  assert_eq!(value(&header, "RADESYS").as_deref(), Some("'ICRS'"));
+}
+
+#[test]
+fn frame_default_test() {
+  //Without RADESYS the frame follows from EQUINOX
+  let frame = |text: &str| rsf::SkyFrame::from_header(&rsf::Header::from_text(text).unwrap());
+  assert_eq!(frame("OBJECT  = 'M31'").unwrap(), rsf::SkyFrame::Icrs);
+  assert_eq!(frame("EQUINOX =               1950.0").unwrap(), rsf::SkyFrame::Fk4(1950.0));
+  assert_eq!(frame("EQUINOX =               2000.0").unwrap(), rsf::SkyFrame::Fk5(2000.0));
+  assert_eq!(frame("RADESYS = 'FK5'").unwrap(), rsf::SkyFrame::Fk5(2000.0));
+  assert!(frame("RADESYS = 'GALACTIC'").is_err());
+}
+
+#[test]
+fn pointing_test() {
+  let header = |text: &str| rsf::Header::from_text(text).unwrap();
+
+  //Numbers are degrees, sexagesimal RA is in hours and DEC in degrees
+  let numeric = header("RA      =               150.25\nDEC     =               -12.5");
+  assert_eq!(numeric.get_ra_deg().unwrap(), 150.25);
+  assert_eq!(numeric.get_dec_deg().unwrap(), -12.5);
+  let sexagesimal = header("OBJCTRA = '10 01 00.0'\nOBJCTDEC= '-12:30:00'");
+  assert!((sexagesimal.get_ra_deg().unwrap() - 150.25).abs() < 1e-9);
+  assert!((sexagesimal.get_dec_deg().unwrap() + 12.5).abs() < 1e-9);
+
+  //RA and DEC win over OBJCTRA and OBJCTDEC, numbers in strings are degrees
+  let both = header("OBJCTRA = '10 01 00.0'\nRA      = '-30.0'\nDEC     = '+45 00 00'");
+  assert_eq!(both.get_ra_deg().unwrap(), 330.0);
+  assert_eq!(both.get_dec_deg().unwrap(), 45.0);
+
+  //Any keyword can be read with an explicit unit
+  let angle = header("ROTANG  = '01:30:00'");
+  assert_eq!(angle.get_angle_deg("ROTANG", rsf::SexagesimalUnit::Degrees).unwrap(), 1.5);
+  assert_eq!(angle.get_angle_deg("ROTANG", rsf::SexagesimalUnit::Hours).unwrap(), 22.5);
+}
+
+#[test]
+fn pointing_err_test() {
+  let header = |text: &str| rsf::Header::from_text(text).unwrap();
+  assert!(header("OBJECT  = 'M31'").get_ra_deg().is_err());
+  assert!(header("OBJECT  = 'M31'").get_dec_deg().is_err());
+  assert!(header("RA      = 'north'").get_ra_deg().is_err());
+  assert!(header("DEC     =                 95.0").get_dec_deg().is_err());
+  assert!(header("OBJCTDEC= '-91 00 00'").get_dec_deg().is_err());
+}
@@ -0,0 +1,115 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{fs, path::PathBuf};
+
+use ndarray::IxDyn;
+use rustronomy_fits::{self as rsf, Extension};
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+fn world(header: &rsf::Header, pixel: [f64; 2]) -> [f64; 2] {
+  //Intermediate world coordinates (CD matrix only, which is enough here)
+  let get = |keyword: &str| header.get_value_as::<f64>(keyword).unwrap_or(0.0);
+  let offset = [pixel[0] - get("CRPIX1"), pixel[1] - get("CRPIX2")];
+  let x = |i: usize| (1..=2).map(|j| get(&format!("CD{i}_{j}")) * offset[j - 1]).sum::<f64>();
+  [x(1), x(2)]
+}
+
+fn pixel(hdu: &rsf::HeaderDataUnit, index: [usize; 2]) -> f32 {
+  match hdu.get_data() {
+    Some(Extension::Image(img)) => img.as_array::<f32>().unwrap()[IxDyn(&index)],
+    _ => panic!("HDU does not contain an image"),
+  }
+}
+
+fn assert_close(a: [f64; 2], b: [f64; 2]) {
+  assert!((a[0] - b[0]).abs() < 1e-12 && (a[1] - b[1]).abs() < 1e-12, "{a:?} != {b:?}");
+}
+
+#[test]
+fn flip_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let original = rsf::Fits::open(&real_path).unwrap();
+  let mut fits = rsf::Fits::open(&real_path).unwrap();
+  let (old, hdu) = (original.get_hdu(1).unwrap(), fits.get_hdu_mut(1).unwrap());
+  hdu.flip(0).unwrap();
+
+  //(1) Pixel (a, b) moved to (270 - 1 - a, b), keeping its world coordinates
+  for [a, b] in [[0, 0], [10, 200], [269, 262]] {
+    assert_eq!(pixel(hdu, [269 - a, b]), pixel(old, [a, b]));
+    let old_world = world(old.get_header(), [a as f64 + 1.0, b as f64 + 1.0]);
+    let new_world = world(hdu.get_header(), [(269 - a) as f64 + 1.0, b as f64 + 1.0]);
+    assert_close(old_world, new_world);
+  }
+
+  //(2) The changes survive writing the file
+  let mut dir = dirs::cache_dir().unwrap();
+  dir.push("orientation_test");
+  fs::create_dir_all(&dir).unwrap();
+  let out = dir.join("flipped.fits");
+  fits.write(&out).unwrap();
+  let reread = rsf::Fits::open(&out).unwrap();
+  let hdu = reread.get_hdu(1).unwrap();
+  assert_eq!(pixel(hdu, [269, 0]), pixel(old, [0, 0]));
+  assert_close(world(hdu.get_header(), [270.0, 1.0]), world(old.get_header(), [1.0, 1.0]));
+
+  //(3) Axes that do not exist are refused
+  let mut fits = rsf::Fits::open(&real_path).unwrap();
+  assert!(fits.get_hdu_mut(1).unwrap().flip(2).is_err());
+  assert!(fits.get_hdu_mut(0).unwrap().flip(0).is_err());
+}
+
+#[test]
+fn transpose_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let original = rsf::Fits::open(&real_path).unwrap();
+  let mut fits = rsf::Fits::open(&real_path).unwrap();
+  let (old, hdu) = (original.get_hdu(1).unwrap(), fits.get_hdu_mut(1).unwrap());
+  hdu.transpose(&[1, 0]).unwrap();
+
+  //(1) The axes swapped places, along with their keywords
+  let header = hdu.get_header();
+  assert_eq!(header.get_value_as::<usize>("NAXIS1").unwrap(), 263);
+  assert_eq!(header.get_value_as::<usize>("NAXIS2").unwrap(), 270);
+  assert_eq!(header.get_value("CTYPE1").unwrap(), "'DEC--TAN'");
+  assert_eq!(header.get_value("CTYPE2").unwrap(), "'RA---TAN'");
+
+  //(2) Pixel (a, b) moved to (b, a) and its world coordinates swapped too
+  for [a, b] in [[0, 0], [10, 200], [269, 262]] {
+    assert_eq!(pixel(hdu, [b, a]), pixel(old, [a, b]));
+    let [x, y] = world(old.get_header(), [a as f64 + 1.0, b as f64 + 1.0]);
+    assert_close(world(header, [b as f64 + 1.0, a as f64 + 1.0]), [y, x]);
+  }
+
+  //(3) The image is written with the new shape
+  let mut dir = dirs::cache_dir().unwrap();
+  dir.push("orientation_test");
+  fs::create_dir_all(&dir).unwrap();
+  let out = dir.join("transposed.fits");
+  fits.write(&out).unwrap();
+  let reread = rsf::Fits::open(&out).unwrap();
+  assert_eq!(pixel(reread.get_hdu(1).unwrap(), [200, 10]), pixel(old, [10, 200]));
+
+  //(4) Only permutations of all axes are allowed
+  let mut fits = rsf::Fits::open(&real_path).unwrap();
+  assert!(fits.get_hdu_mut(1).unwrap().transpose(&[0, 0]).is_err());
+  assert!(fits.get_hdu_mut(1).unwrap().transpose(&[0]).is_err());
+}
@@ -0,0 +1,82 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{error::Error, fs, path::PathBuf};
+
+use rustronomy_fits::{
+  self as rsf,
+  hdu_err::{DecodeHduErr, InvalidRecordValueError},
+};
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+fn many_extensions(name: &str, copies: usize) -> PathBuf {
+  //Multi-extension file with lots of image extensions
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let mut out = dirs::cache_dir().unwrap();
+  out.push(name);
+  rsf::Fits::concat(&vec![real_path; copies], &out).unwrap();
+  out
+}
+
+#[test]
+fn parallel_decode_test() {
+  let path = many_extensions("parallel_decode.fits", 12);
+  let fits = rsf::Fits::open(&path).unwrap();
+
+  //Every HDU has to match the one decoded on its own, in the same order
+  let index = rsf::Fits::open_indexed(&path).unwrap();
+  assert_eq!(fits.get_num_hdus(), index.get_num_hdus());
+  let manifest = fits.manifest();
+  for (hdu_index, entry) in manifest.iter().enumerate() {
+    let hdu = index.read_hdu(hdu_index).unwrap().unwrap();
+    assert_eq!(entry.get_header_digest(), hdu.get_header().digest());
+    assert_eq!(fits.get_hdu(hdu_index).unwrap().pretty_print_data(), hdu.pretty_print_data());
+    match fits.get_hdu(hdu_index).unwrap().get_data_source().unwrap() {
+      rsf::DataSource::Embedded(_, layout) => {
+        assert_eq!(layout, index.get_layout(hdu_index).unwrap())
+      }
+      other => panic!("unexpected data source {other:?}"),
+    }
+  }
+}
+
+#[test]
+fn parallel_decode_err_test() {
+  //Errors in one of the data units are reported with the index of the HDU
+  let path = many_extensions("parallel_decode_err.fits", 3);
+  let mut bytes = fs::read(&path).unwrap();
+  let needle = b"XTENSION= 'IMAGE   '";
+  let at = bytes.windows(needle.len()).enumerate().filter(|(_, w)| w == needle).nth(4).unwrap().0;
+  bytes[at..at + needle.len()].copy_from_slice(b"XTENSION= 'WEIRD   '");
+  fs::write(&path, bytes).unwrap();
+
+  let err = rsf::Fits::open(&path).unwrap_err();
+  let err = err.downcast_ref::<DecodeHduErr>().unwrap();
+  assert_eq!(err.get_index(), 5);
+  assert!(err.to_string().contains("'WEIRD   '"), "{err}");
+  assert!(err.source().unwrap().is::<InvalidRecordValueError>());
+
+  //Data units skipped by the HDU filter are not decoded at all
+  let opts = rsf::ReadOptions::new()
+    .hdu_filter(|header| header.get_value("XTENSION").is_none_or(|xt| xt != "'WEIRD   '"));
+  let fits = rsf::Fits::open_with(&path, &opts).unwrap();
+  assert!(fits.get_hdu(5).unwrap().get_data().is_none());
+  assert!(fits.get_hdu(4).unwrap().get_data().is_some());
+}
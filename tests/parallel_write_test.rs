@@ -0,0 +1,61 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::path::Path;
+
+use rustronomy_fits as rsf;
+
+static FILES: [&str; 3] =
+  ["resources/Hubble_NICMOS.fits", "resources/Astro_UIT.fits", "resources/Hubble_WFPC2_2.fits"];
+
+fn write(path: &str, opts: &rsf::WriteOptions) -> Vec<u8> {
+  let fits = rsf::Fits::open(Path::new(path)).unwrap();
+  fits.write_stream(Vec::new(), opts).unwrap()
+}
+
+#[test]
+fn default_memory_test() {
+  assert_eq!(rsf::WriteOptions::new().get_encode_memory(), 256 * 1024 * 1024);
+}
+
+#[test]
+fn same_bytes_test() {
+  /*  Encoding in parallel (everything in one batch), in small batches or on
+      the calling thread all give the same file
+  */
+  for path in FILES {
+    let serial = write(path, &rsf::WriteOptions::new().encode_memory(0));
+    assert_eq!(write(path, &rsf::WriteOptions::new()), serial, "{path}");
+    assert_eq!(write(path, &rsf::WriteOptions::new().encode_memory(600_000)), serial, "{path}");
+
+    //And the file can be read back
+    let fits = rsf::Fits::from_stream(serial.as_slice()).unwrap();
+    assert_eq!(fits.get_num_hdus(), rsf::Fits::open(Path::new(path)).unwrap().get_num_hdus());
+  }
+}
+
+#[test]
+fn unloaded_test() {
+  //Data that was skipped when reading is loaded again before it is encoded
+  let opts = rsf::ReadOptions::new().hdu_filter(|_| false);
+  let fits = rsf::Fits::open_with(Path::new(FILES[0]), &opts).unwrap();
+  let written = fits.write_stream(Vec::new(), &rsf::WriteOptions::new()).unwrap();
+  let serial = write(FILES[0], &rsf::WriteOptions::new().encode_memory(0));
+  assert_eq!(written, serial);
+}
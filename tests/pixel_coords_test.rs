@@ -0,0 +1,67 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::path::PathBuf;
+
+use ndarray::IxDyn;
+use rustronomy_fits::{self as rsf, Extension};
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+#[test]
+fn conversion_test() {
+  //Pixel centers
+  assert_eq!(rsf::fits_to_index(1.0), 0.0);
+  assert_eq!(rsf::index_to_fits(0.0), 1.0);
+  assert_eq!(rsf::fits_to_index(rsf::index_to_fits(41.25)), 41.25);
+
+  //Pixel edges: pixels cover [center - 0.5, center + 0.5)
+  assert_eq!(rsf::containing_index(0.4, 10), None);
+  assert_eq!(rsf::containing_index(0.5, 10), Some(0));
+  assert_eq!(rsf::containing_index(1.49, 10), Some(0));
+  assert_eq!(rsf::containing_index(1.5, 10), Some(1));
+  assert_eq!(rsf::containing_index(10.49, 10), Some(9));
+  assert_eq!(rsf::containing_index(10.5, 10), None);
+
+  assert_eq!(rsf::containing_indices(&[1.0, 3.7], &[5, 5]), Some(vec![0, 3]));
+  assert_eq!(rsf::containing_indices(&[1.0, 6.0], &[5, 5]), None);
+  assert_eq!(rsf::containing_indices(&[1.0], &[5, 5]), None);
+
+  //Flipping an axis of length 10 swaps pixels 1 and 10, center stays put
+  assert_eq!(rsf::mirror_fits(1.0, 10), 10.0);
+  assert_eq!(rsf::mirror_fits(5.5, 10), 5.5);
+  assert_eq!(rsf::mirror_fits(0.5, 10), 10.5);
+}
+
+#[test]
+fn reference_pixel_test() {
+  //CRPIXn is a FITS coordinate, so it has to be converted to find its pixel
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let fits = rsf::Fits::open(&real_path).unwrap();
+  let hdu = fits.get_hdu(1).unwrap();
+  let header = hdu.get_header();
+  let crpix =
+    [header.get_value_as::<f64>("CRPIX1").unwrap(), header.get_value_as("CRPIX2").unwrap()];
+  let Some(Extension::Image(img)) = hdu.get_data() else { panic!("no image") };
+  let array = img.as_array::<f32>().unwrap();
+
+  let index = rsf::containing_indices(&crpix, array.shape()).unwrap();
+  assert_eq!(index, vec![135, 131]);
+  assert!(array.get(IxDyn(&index)).is_some());
+}
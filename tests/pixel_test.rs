@@ -0,0 +1,63 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::path::PathBuf;
+
+use ndarray::{Array, IxDyn};
+use rustronomy_fits::{self as rsf, FitsPixel};
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+//Generic code only has to be written once for all pixel types
+fn count_nonzero<T: FitsPixel>(img: &rsf::TypedImage) -> Option<usize> {
+  let data = img.as_array::<T>().ok()?;
+  Some(data.iter().filter(|px| !px.is_zero()).count())
+}
+
+fn roundtrip<T: FitsPixel>(vals: Vec<T>) {
+  let array = Array::from_shape_vec(IxDyn(&[vals.len()]), vals.clone()).unwrap();
+  let img: rsf::TypedImage = rsf::Image::new(array).into();
+  assert_eq!(img.get_bitpix().to_string(), T::BITPIX.to_string());
+  assert_eq!(img.as_array::<T>().unwrap().as_slice().unwrap(), &vals[..]);
+  for val in vals {
+    assert_eq!(T::from_fits_bytes(&val.to_fits_bytes()), val);
+  }
+}
+
+#[test]
+fn generic_pixel_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let fits = rsf::Fits::open(&real_path).unwrap();
+  let img = match fits.get_hdu(1).unwrap().get_data() {
+    Some(rsf::Extension::Image(img)) => img,
+    _ => panic!(),
+  };
+
+  //HDU #1 is a f32 image
+  assert!(count_nonzero::<f32>(img).unwrap() > 0);
+  assert!(count_nonzero::<i16>(img).is_none());
+  assert!(img.clone().as_owned_array::<f64>().is_err());
+
+  roundtrip::<u8>(vec![0, 1, 255]);
+  roundtrip::<i16>(vec![-3, 0, 300]);
+  roundtrip::<i32>(vec![i32::MIN, 7]);
+  roundtrip::<i64>(vec![-1, i64::MAX]);
+  roundtrip::<f32>(vec![0.5, -1e10]);
+  roundtrip::<f64>(vec![std::f64::consts::PI]);
+}
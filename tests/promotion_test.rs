@@ -0,0 +1,123 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use ndarray::{Array, Array2};
+use rustronomy_fits::{self as rsf, promote, Bitpix::*, PromotionRules, Stack};
+
+fn typed<T: rsf::FitsPixel>(value: T) -> rsf::TypedImage {
+  rsf::TypedImage::from(rsf::Image::new(Array2::from_elem((3, 2), value).into_dyn()))
+}
+
+#[test]
+fn promote_test() {
+  //(1) Integers are promoted to the widest type
+  assert_eq!(promote(Byte, Byte), Byte);
+  assert_eq!(promote(Byte, Short), Short);
+  assert_eq!(promote(Long, Int), Long);
+
+  //(2) f32 is only kept for types it represents exactly
+  assert_eq!(promote(Short, Spf), Spf);
+  assert_eq!(promote(Spf, Byte), Spf);
+  assert_eq!(promote(Int, Spf), Dpf);
+  assert_eq!(promote(Spf, Long), Dpf);
+  assert_eq!(promote(Spf, Dpf), Dpf);
+  assert_eq!(promote(Byte, Dpf), Dpf);
+
+  //(3) The rules are symmetric
+  for a in [Byte, Short, Int, Long, Spf, Dpf] {
+    for b in [Byte, Short, Int, Long, Spf, Dpf] {
+      assert_eq!(promote(a, b), promote(b, a));
+      assert_eq!(PromotionRules::new().promote(a, b), promote(a, b));
+    }
+  }
+}
+
+#[test]
+fn rules_test() {
+  //(1) Floating point results are f64 by default
+  let rules = PromotionRules::new();
+  assert!(!rules.get_keep_f32());
+  assert_eq!(rules.float_result(Spf), Dpf);
+  assert_eq!(rules.float_result(Byte), Dpf);
+
+  //(2) Unless f32 is kept, also when combined with wide integers
+  let rules = rules.keep_f32(true);
+  assert_eq!(rules.promote(Spf, Int), Spf);
+  assert_eq!(rules.promote(Long, Spf), Spf);
+  assert_eq!(rules.promote(Spf, Dpf), Dpf);
+  assert_eq!(rules.float_result(Short), Spf);
+  assert_eq!(rules.float_result(Dpf), Dpf);
+
+  //(3) Overrides apply in either order, the last one wins
+  let rules = PromotionRules::new().rule(Byte, Short, Int).rule(Short, Byte, Long);
+  assert_eq!(rules.get_overrides().len(), 2);
+  assert_eq!(rules.promote(Byte, Short), Long);
+  assert_eq!(rules.promote(Short, Int), Int);
+  let rules = PromotionRules::new().rule(Spf, Dpf, Spf);
+  assert_eq!(rules.float_result(Spf), Spf);
+  assert_eq!(rules.float_result(Int), Dpf);
+}
+
+#[test]
+fn stack_promotion_test() {
+  //(1) By default the stack is double precision, whatever the frames are
+  let mut stack = Stack::new();
+  stack.add(&typed(1.0f32), None, None).unwrap();
+  stack.add(&typed(4i16), None, None).unwrap();
+  assert_eq!(stack.get_bitpix(), Dpf);
+  assert!(matches!(stack.mean(), rsf::TypedImage::DpfImg(_)));
+
+  //(2) Keeping f32 gives a single precision stack
+  let mut stack = Stack::new().promotion(PromotionRules::new().keep_f32(true));
+  stack.add(&typed(1.0f32), None, None).unwrap();
+  stack.add(&typed(4i32), None, None).unwrap();
+  assert!(stack.get_promotion().get_keep_f32());
+  assert_eq!(stack.get_bitpix(), Spf);
+  let rsf::TypedImage::SpfImg(mean) = stack.mean() else { panic!("mean is not an f32 image") };
+  assert!(mean.get_data().iter().all(|&px| px == 2.5));
+  assert!(matches!(stack.weight_map(), rsf::TypedImage::DpfImg(_)));
+
+  //(3) f64 frames still give an f64 stack
+  stack.add(&typed(2.5f64), None, None).unwrap();
+  assert_eq!(stack.get_bitpix(), Dpf);
+}
+
+#[test]
+fn to_bitpix_test() {
+  //Integers are rounded and saturate, NaN becomes 0
+  let img = rsf::TypedImage::from(rsf::Image::new(
+    Array::from_vec(vec![1.4, 2.6, -3.0, 300.0, f64::NAN]).into_dyn(),
+  ));
+  let rsf::TypedImage::ByteImg(bytes) = img.to_bitpix(Byte) else { panic!("not a u8 image") };
+  assert_eq!(bytes.get_data().iter().copied().collect::<Vec<u8>>(), [1, 3, 0, 255, 0]);
+  assert_eq!(img.to_bitpix(Short).get_bitpix(), Short);
+  assert!(img.to_bitpix(Dpf).shares_data_with(&img));
+
+  //Convolutions and reductions follow the rules they are given
+  let kernel = Array2::from_elem((1, 1), 1.0);
+  let (rules, single) = (PromotionRules::default(), PromotionRules::new().keep_f32(true));
+  let conv = typed(3u8).convolve(kernel.view(), rsf::Boundary::Extend, &rules);
+  assert_eq!(conv.get_bitpix(), rules.float_result(Byte));
+  let conv = typed(3u8).convolve(kernel.view(), rsf::Boundary::Extend, &single);
+  assert_eq!(conv.get_bitpix(), Spf);
+  let plane = rsf::TypedImage::from(rsf::Image::new(Array2::from_elem((2, 3), 7i16).into_dyn()));
+  let reduced = plane.reduce(1, rsf::Reduction::NanMean, &single).unwrap();
+  assert_eq!(reduced.get_bitpix(), Spf);
+  assert_eq!(reduced.as_owned_f32_array().unwrap().as_slice().unwrap(), [7.0, 7.0]);
+}
@@ -0,0 +1,106 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+use rustronomy_fits::{self as rsf, HduSelector, ProvenanceGraph};
+
+fn write_with_inputs(path: &Path, inputs: &[&str]) {
+  //Image-only fixture, with the inputs recorded in its primary HDU
+  let mut fits = rsf::Fits::open(Path::new("resources/Astro_UIT.fits")).unwrap();
+  fits.get_hdu_mut(0).unwrap().add_provenance(inputs).unwrap();
+  fits.write(path).unwrap();
+}
+
+fn tree(name: &str) -> PathBuf {
+  let mut dir = dirs::cache_dir().unwrap();
+  dir.push(format!("provenance_{name}"));
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(dir.join("raw")).unwrap();
+  dir
+}
+
+#[test]
+fn keyword_test() {
+  //Inputs are recorded in order, without duplicates
+  let mut fits = rsf::Fits::open(Path::new("resources/Astro_UIT.fits")).unwrap();
+  let hdu = fits.get_hdu_mut(0).unwrap();
+  hdu.add_provenance(&["raw/a.fits[SCI,2]", "raw/b.fits"]).unwrap();
+  hdu.add_provenance(&["raw/b.fits", "bias.fits[0]"]).unwrap();
+  let header = hdu.get_header();
+  assert_eq!(header.get_value("PROV2").unwrap(), "'raw/b.fits'");
+  assert_eq!(header.get_value("PROV3").unwrap(), "'bias.fits[0]'");
+  assert!(header.get_value("PROV4").is_none());
+
+  let inputs = hdu.get_provenance().unwrap();
+  assert_eq!(inputs.len(), 3);
+  assert_eq!(inputs[0].get_path(), Path::new("raw/a.fits"));
+  assert_eq!(
+    inputs[0].get_hdu(),
+    Some(&HduSelector::Name { extname: String::from("SCI"), extver: Some(2) })
+  );
+
+  //Inputs that are not extended file names are refused
+  assert!(hdu.add_provenance(&["raw/c.fits[SCI"]).is_err());
+}
+
+#[test]
+fn graph_test() {
+  //(1) raw frames -> master -> final, plus an input that was archived elsewhere
+  let dir = tree("graph");
+  write_with_inputs(&dir.join("raw/frame1.fits"), &[]);
+  write_with_inputs(&dir.join("raw/frame2.fits"), &[]);
+  write_with_inputs(&dir.join("master.fits"), &["raw/frame1.fits[0]", "raw/frame2.fits"]);
+  write_with_inputs(&dir.join("final.fits"), &["master.fits", "raw/frame1.fits[0]", "gone.fits"]);
+
+  //(2) The inputs of every node, resolved against the recording file
+  let graph = ProvenanceGraph::trace(dir.join("final.fits").to_str().unwrap()).unwrap();
+  let name = |node: usize| graph.get_nodes()[node].get_path().file_name().unwrap().to_owned();
+  let names = |nodes: Vec<usize>| nodes.into_iter().map(name).collect::<Vec<_>>();
+  assert_eq!(graph.get_nodes().len(), 5);
+  assert_eq!(names(graph.get_inputs(0)), ["master.fits", "frame1.fits", "gone.fits"]);
+  assert_eq!(names(graph.get_inputs(1)), ["frame1.fits", "frame2.fits"]);
+
+  //(3) frame1[0] is shared, the missing input is a source that was not found
+  let frame1 = graph.get_inputs(0)[1];
+  assert_eq!(graph.get_outputs(frame1), [0, 1]);
+  assert_eq!(graph.get_nodes()[frame1].get_hdu(), Some(&HduSelector::Index(0)));
+  assert_eq!(names(graph.get_sources()), ["frame1.fits", "frame2.fits", "gone.fits"]);
+  let gone = graph.get_inputs(0)[2];
+  assert!(!graph.get_nodes()[gone].is_found());
+  assert!(graph.get_nodes()[0].is_found());
+}
+
+#[test]
+fn cycle_test() {
+  //Files that (wrongly) record each other do not trace forever
+  let dir = tree("cycle");
+  write_with_inputs(&dir.join("a.fits"), &["b.fits"]);
+  write_with_inputs(&dir.join("b.fits"), &["a.fits", "raw/missing.fits[SCI]"]);
+  let graph = ProvenanceGraph::trace(dir.join("a.fits").to_str().unwrap()).unwrap();
+  assert_eq!(graph.get_nodes().len(), 3);
+  assert_eq!(graph.get_edges(), [(1, 0), (1, 2), (0, 1)]);
+  assert_eq!(
+    graph.get_nodes()[2].to_string(),
+    format!("{}[SCI]", dir.join("raw/missing.fits").display())
+  );
+}
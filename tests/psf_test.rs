@@ -0,0 +1,129 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+use rustronomy_fits as rsf;
+
+fn psf_file(name: &str, shape: &[usize], extra_cards: &[String], pixels: &[f64]) -> PathBuf {
+  //Double precision image (or cube) in the primary HDU
+  let mut cards = vec![
+    "SIMPLE  = T".to_string(),
+    "BITPIX  = -64".to_string(),
+    format!("NAXIS   = {}", shape.len()),
+  ];
+  for (idx, len) in shape.iter().enumerate() {
+    cards.push(format!("{:8}= {len}", format!("NAXIS{}", idx + 1)));
+  }
+  let mut buf = Vec::new();
+  for card in cards.iter().chain(extra_cards).chain(&["END".to_string()]) {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(buf.len().div_ceil(2880) * 2880, b' ');
+  for px in pixels {
+    buf.extend(px.to_be_bytes());
+  }
+  buf.resize(buf.len().div_ceil(2880) * 2880, 0);
+
+  let mut path = dirs::cache_dir().unwrap();
+  path.push(name);
+  fs::write(&path, buf).unwrap();
+  path
+}
+
+fn read_psf(path: &Path) -> Result<rsf::Psf, Box<dyn std::error::Error + Send + Sync>> {
+  let fits = rsf::Fits::open(path).unwrap();
+  rsf::Psf::from_hdu(fits.get_hdu(0).unwrap())
+}
+
+fn cube(values: &[f64]) -> Vec<f64> {
+  //3 x 2 planes, each filled with a single value
+  values.iter().flat_map(|&value| [value; 6]).collect()
+}
+
+#[test]
+fn constant_psf_test() {
+  let pixels = [0.0, 1.0, 0.0, 2.0, 5.0, 2.0];
+  let path = psf_file("psf_constant.fits", &[3, 2], &[], &pixels);
+  let psf = read_psf(&path).unwrap();
+  assert!(psf.is_constant());
+  assert_eq!(psf.get_shape(), [3, 2]);
+  let at = psf.evaluate_at(1000.0, -5.0);
+  assert_eq!(at[[1, 0]], 1.0);
+  assert_eq!(at[[1, 1]], 5.0);
+}
+
+#[test]
+fn gridded_psf_test() {
+  //2 x 2 grid, listed out of order
+  let cards: Vec<String> = ["(0.0, 0.0)", "(0.0, 100.0)", "(50.0, 0.0)", "(50.0, 100.0)"]
+    .iter()
+    .enumerate()
+    .map(|(idx, yx)| format!("{:8}= '{yx}'", format!("DET_YX{idx}")))
+    .collect();
+  let path = psf_file("psf_grid.fits", &[3, 2, 4], &cards, &cube(&[1.0, 2.0, 3.0, 4.0]));
+  let psf = read_psf(&path).unwrap();
+  assert!(!psf.is_constant());
+  assert_eq!(psf.get_grid().unwrap(), (&[0.0, 100.0][..], &[0.0, 50.0][..]));
+
+  //(1) Exact at the grid positions, interpolated in between
+  assert_eq!(psf.evaluate_at(100.0, 0.0)[[0, 0]], 2.0);
+  assert_eq!(psf.evaluate_at(0.0, 50.0)[[2, 1]], 3.0);
+  assert!((psf.evaluate_at(50.0, 25.0)[[1, 1]] - 2.5).abs() < 1e-12);
+  assert!((psf.evaluate_at(25.0, 0.0)[[0, 1]] - 1.25).abs() < 1e-12);
+
+  //(2) Outside the grid the nearest edge is used
+  assert_eq!(psf.evaluate_at(-20.0, 80.0)[[0, 0]], 3.0);
+  assert_eq!(psf.evaluate_at(500.0, 500.0)[[0, 0]], 4.0);
+
+  //(3) An incomplete grid is refused
+  let path = psf_file("psf_bad_grid.fits", &[3, 2, 4], &cards[..3], &cube(&[1.0, 2.0, 3.0, 4.0]));
+  let err = read_psf(&path).unwrap_err();
+  let err = err.downcast_ref::<rsf::img_err::PsfGridErr>().unwrap();
+  assert_eq!((err.get_num_planes(), err.get_num_positions()), (4, 3));
+}
+
+#[test]
+fn polynomial_psf_test() {
+  //First degree PSFEx model: 1 + 2 dx + 3 dy
+  let cards = [
+    "POLNAXIS= 2",
+    "POLNAME1= 'X_IMAGE'",
+    "POLNAME2= 'Y_IMAGE'",
+    "POLZERO1= 100.0",
+    "POLSCAL1= 10.0",
+    "POLZERO2= 200.0",
+    "POLSCAL2= 20.0",
+    "POLDEG1 = 1",
+  ]
+  .map(String::from);
+  let path = psf_file("psf_poly.fits", &[3, 2, 3], &cards, &cube(&[1.0, 2.0, 3.0]));
+  let psf = read_psf(&path).unwrap();
+  assert!(psf.get_grid().is_none());
+  assert_eq!(psf.evaluate_at(100.0, 200.0)[[0, 0]], 1.0);
+  assert_eq!(psf.evaluate_at(110.0, 200.0)[[1, 0]], 3.0);
+  assert_eq!(psf.evaluate_at(90.0, 240.0)[[2, 1]], 5.0);
+
+  //The number of planes must match the degree
+  let path = psf_file("psf_bad_poly.fits", &[3, 2, 2], &cards, &cube(&[1.0, 2.0]));
+  assert!(read_psf(&path).unwrap_err().to_string().contains("POLDEG1"));
+}
@@ -0,0 +1,108 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::path::Path;
+
+use pyo3::{prelude::*, types::PyDict};
+use rustronomy_fits as rsf;
+
+fn with_module(script: &std::ffi::CStr, locals: impl FnOnce(&Bound<'_, PyDict>)) {
+  //Runs the script with the bindings importable as rustronomy_fits
+  Python::attach(|py| {
+    let module = PyModule::new(py, "rustronomy_fits").unwrap();
+    rsf::register_python_module(&module).unwrap();
+    py.import("sys")
+      .unwrap()
+      .getattr("modules")
+      .unwrap()
+      .set_item("rustronomy_fits", module)
+      .unwrap();
+
+    let dict = PyDict::new(py);
+    locals(&dict);
+    if let Err(err) = py.run(script, Some(&dict), None) {
+      panic!("{err}");
+    }
+  });
+}
+
+#[test]
+fn python_hdu_test() {
+  let copy = dirs::cache_dir().unwrap().join("python_hdu_test.fits");
+  with_module(
+    cr#"
+import rustronomy_fits as rsf
+fits = rsf.Fits.read("resources/IUE_LWP.fits")
+assert len(fits) == 2
+
+#(1) Header values without quotes, in header order
+primary = fits[0]
+assert primary.kind is None and primary.data() is None
+assert primary.header["TELESCOP"] == "IUE"
+assert list(primary.header)[0] == "SIMPLE"
+assert fits[-1].get("XTENSION") == "BINTABLE"
+assert fits[-1].kind == "bintable"
+
+#(2) Errors are python exceptions
+for bad, exc in [(lambda: fits[2], IndexError), (lambda: primary.get("NOTTHERE"), KeyError),
+                 (lambda: fits[1].data(), TypeError), (lambda: rsf.Fits.read("nope.fits"), OSError)]:
+  try:
+    bad()
+    raise AssertionError("no exception")
+  except exc:
+    pass
+
+#(3) Files are written back as they were read
+fits.write(copy)
+assert rsf.Fits.read(copy)[0].header == primary.header
+assert len(rsf.Fits.read(copy)) == 2
+
+#(4) HDUs keep the file alive
+hdu = fits[1]
+del fits
+assert hdu.index == 1 and "BINTABLE" in repr(hdu)
+"#,
+    |locals| locals.set_item("copy", &copy).unwrap(),
+  );
+  std::fs::remove_file(copy).unwrap();
+}
+
+#[test]
+fn python_numpy_test() {
+  //Needs numpy in the python environment
+  let fits = rsf::Fits::open(Path::new("resources/Astro_UIT.fits")).unwrap();
+  let img = match fits.get_hdu(0).unwrap().get_data() {
+    Some(rsf::Extension::Image(rsf::TypedImage::I16Img(img))) => img.get_data().clone(),
+    _ => panic!("expected an i16 image"),
+  };
+
+  with_module(
+    cr#"
+import numpy as np
+import rustronomy_fits as rsf
+data = rsf.Fits.read("resources/Astro_UIT.fits")[0].data()
+
+#The last numpy axis is NAXIS1
+assert data.dtype == np.int16 and data.shape == (512, 512)
+assert data[1, 3] == expected
+assert data.flags["C_CONTIGUOUS"]
+"#,
+    |locals| locals.set_item("expected", img[[3, 1]]).unwrap(),
+  );
+}
@@ -0,0 +1,137 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::path::PathBuf;
+
+use ndarray::{array, IxDyn};
+use rustronomy_fits::{self as rsf, img_err::InvalidAxesErr, Extension, Reduction};
+
+fn cube() -> PathBuf {
+  //4x3x5 spectral cube, pixel (x, y, z) = 100 z + 10 y + x. Channel 2 is blanked
+  let wcs = rsf::Header::from_text(
+    "WCSAXES =                    3\nCTYPE1  = 'RA---TAN'\nCTYPE2  = 'DEC--TAN'\n\
+     CTYPE3  = 'VRAD'\nCRPIX1  = 2.0\nCRPIX2  = 2.0\nCRPIX3  = 1.0\nCDELT1  = -0.001\n\
+     CDELT2  = 0.001\nCDELT3  = 500.0\nPC1_2   = 0.1\nPC2_3   = 0.2\nPC3_3   = 1.0\n\
+     BUNIT   = 'Jy/beam'",
+  )
+  .unwrap();
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("reduction_cube.fits");
+  let mut stream =
+    rsf::ImageStreamWriter::<f32>::create_with_header(&path, &[4, 3, 5], Some(&wcs)).unwrap();
+  for z in 0..5 {
+    for y in 0..3 {
+      let row: Vec<f32> = match z {
+        2 => vec![f32::NAN; 4],
+        z => (0..4).map(|x| (100 * z + 10 * y + x) as f32).collect(),
+      };
+      stream.write_row(&row).unwrap();
+    }
+  }
+  stream.finish().unwrap();
+  path
+}
+
+fn image(hdu: &rsf::HeaderDataUnit) -> ndarray::ArcArray<f64, IxDyn> {
+  match hdu.get_data() {
+    Some(Extension::Image(img)) => img.as_f64_array().unwrap().clone(),
+    _ => panic!("HDU does not contain an image"),
+  }
+}
+
+#[test]
+fn reduce_typed_test() {
+  let rules = rsf::PromotionRules::new();
+  let img = rsf::TypedImage::from(rsf::Image::new(
+    array![[1.0, f64::NAN, 3.0, 10.0], [f64::NAN, f64::NAN, f64::NAN, f64::NAN]].into_dyn(),
+  ));
+  let lane = |reduction| img.reduce(1, reduction, &rules).unwrap().as_owned_f64_array().unwrap();
+
+  //NaN pixels are skipped, unless there is nothing else
+  assert_eq!(lane(Reduction::NanMean)[0], 14.0 / 3.0);
+  assert_eq!(lane(Reduction::NanMedian)[0], 3.0);
+  assert!(lane(Reduction::NanMean)[1].is_nan());
+  assert!(lane(Reduction::NanMedian)[1].is_nan());
+  assert!(lane(Reduction::Sum)[0].is_nan());
+  assert!(lane(Reduction::Max)[0].is_nan());
+
+  let ints = rsf::TypedImage::from(rsf::Image::new(array![[1i16, 2], [4, 7]].into_dyn()));
+  let reduced =
+    |axis, reduction| ints.reduce(axis, reduction, &rules).unwrap().as_owned_f64_array();
+  assert_eq!(reduced(0, Reduction::Sum).unwrap(), array![5.0, 9.0].into_dyn());
+  assert_eq!(reduced(1, Reduction::Max).unwrap(), array![2.0, 7.0].into_dyn());
+  assert_eq!(reduced(0, Reduction::NanMedian).unwrap(), array![2.5, 4.5].into_dyn());
+
+  //The axis has to exist, and images keep at least one axis
+  assert!(ints.reduce(2, Reduction::Sum, &rules).is_err());
+  let line = rsf::TypedImage::from(rsf::Image::new(array![1u8, 2].into_dyn()));
+  let err: InvalidAxesErr = line.reduce(0, Reduction::Sum, &rules).unwrap_err();
+  assert!(err.to_string().contains("[0]"));
+}
+
+#[test]
+fn reduce_cube_test() {
+  let path = cube();
+  let mut fits = rsf::Fits::open(&path).unwrap();
+  let hdu = fits.get_hdu(0).unwrap();
+  let rules = rsf::PromotionRules::new();
+
+  //(1) Moment-0 like map: collapse the spectral axis
+  let map = hdu.reduce(2, Reduction::NanMean, &rules).unwrap();
+  let data = image(&map);
+  assert_eq!(data.shape(), &[4, 3]);
+  assert_eq!(data[[3, 1]], (13.0 + 113.0 + 313.0 + 413.0) / 4.0);
+  let header = map.get_header();
+  assert_eq!(header.get_value_as::<isize>("BITPIX").unwrap(), -64);
+  assert_eq!(header.get_value_as::<usize>("NAXIS").unwrap(), 2);
+  assert_eq!(header.get_value_as::<usize>("WCSAXES").unwrap(), 2);
+  for keyword in ["NAXIS3", "CTYPE3", "CRPIX3", "PC2_3", "PC3_3"] {
+    assert!(header.get_value(keyword).is_none(), "{keyword} was not removed");
+  }
+  assert_eq!(header.get_value("PC1_2").unwrap().trim(), "0.1");
+  assert_eq!(header.get_value("BUNIT").unwrap().trim(), "'Jy/beam'");
+  assert!(header.history().any(|line| line == "Reduced axis 3 (nan-mean)"));
+
+  //(2) Profile cut along the middle axis: later axes move down
+  let cut = hdu.reduce(1, Reduction::Max, &rules).unwrap();
+  assert_eq!(image(&cut).shape(), &[4, 5]);
+  assert_eq!(image(&cut)[[1, 4]], 421.0);
+  assert!(image(&cut)[[0, 2]].is_nan());
+  let header = cut.get_header();
+  let keywords: Vec<&str> = header.keywords().collect();
+  let position = |keyword| keywords.iter().position(|found| *found == keyword).unwrap();
+  assert_eq!(header.get_value_as::<usize>("NAXIS2").unwrap(), 5);
+  assert_eq!(position("NAXIS2"), position("NAXIS1") + 1);
+  assert_eq!(header.get_value("CTYPE2").unwrap().trim(), "'VRAD'");
+  assert_eq!(header.get_value_as::<f64>("CDELT2").unwrap(), 500.0);
+  assert_eq!(header.get_value_as::<f64>("PC2_2").unwrap(), 1.0);
+  assert!(header.get_value("PC1_2").is_none() && header.get_value("CTYPE3").is_none());
+
+  //(3) Single precision rules give a single precision HDU
+  let single = hdu.reduce(2, Reduction::NanMean, &rsf::PromotionRules::new().keep_f32(true));
+  assert_eq!(single.unwrap().get_header().get_value_as::<isize>("BITPIX").unwrap(), -32);
+
+  //(4) The original is untouched, and the reduced HDU can be written
+  assert_eq!(hdu.get_header().get_value_as::<usize>("NAXIS").unwrap(), 3);
+  *fits.get_hdu_mut(0).unwrap() = map;
+  let mut out = dirs::cache_dir().unwrap();
+  out.push("reduction_map.fits");
+  fits.write(&out).unwrap();
+  let reread = rsf::Fits::open(&out).unwrap();
+  assert_eq!(image(reread.get_hdu(0).unwrap()), data);
+}
@@ -0,0 +1,93 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+use rustronomy_fits::{self as rsf, hdu_err::RemoveHduErr};
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+fn copy(name: &str) -> (PathBuf, Vec<u8>) {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let mut path = dirs::cache_dir().unwrap();
+  path.push(name);
+  fs::copy(&real_path, &path).unwrap();
+  (path.clone(), fs::read(&path).unwrap())
+}
+
+fn byte_range(path: &Path, index: usize) -> std::ops::Range<usize> {
+  let layout = *rsf::Fits::open_indexed(path).unwrap().get_layout(index).unwrap();
+  let start = layout.get_start_block() * 2880;
+  start..start + (layout.get_header_blocks() + layout.get_data_blocks()) * 2880
+}
+
+#[test]
+fn remove_in_place_test() {
+  let (path, original) = copy("remove_in_place.fits");
+  let before = rsf::Fits::open(&path).unwrap();
+  let n_hdus = before.get_num_hdus();
+
+  //(1) Removing an extension moves the rest of the file forward, byte for byte
+  let range = byte_range(&path, 1);
+  rsf::Fits::remove_hdu_in_place(&path, 1).unwrap();
+  let mut expected = original.clone();
+  expected.drain(range);
+  assert_eq!(fs::read(&path).unwrap(), expected);
+
+  let after = rsf::Fits::open(&path).unwrap();
+  assert_eq!(after.get_num_hdus(), n_hdus - 1);
+  for index in 1..n_hdus - 1 {
+    let (old, new) = (before.get_hdu(index + 1).unwrap(), after.get_hdu(index).unwrap());
+    assert_eq!(old.get_header().digest(), new.get_header().digest());
+  }
+
+  //(2) Removing the last HDU just truncates the file, zero-filling first
+  let range = byte_range(&path, n_hdus - 2);
+  let opts = rsf::WriteOptions::new().zero_fill(true).fsync(true);
+  rsf::Fits::remove_hdu_in_place_with(&path, n_hdus - 2, &opts).unwrap();
+  expected.truncate(range.start);
+  assert_eq!(fs::read(&path).unwrap(), expected);
+
+  //(3) The primary HDU and HDU's that do not exist cannot be removed
+  for index in [0, n_hdus] {
+    let err = rsf::Fits::remove_hdu_in_place(&path, index).unwrap_err();
+    assert_eq!(err.downcast_ref::<RemoveHduErr>().unwrap().get_index(), index);
+  }
+  assert_eq!(fs::read(&path).unwrap(), expected);
+
+  //(4) Missing files are not created
+  let missing = dirs::cache_dir().unwrap().join("remove_in_place_missing.fits");
+  assert!(rsf::Fits::remove_hdu_in_place(&missing, 1).is_err());
+  assert!(!missing.exists());
+}
+
+#[test]
+fn remove_in_place_sidecar_test() {
+  //The index sidecar follows the file
+  let (path, _) = copy("remove_in_place_sidecar.fits");
+  rsf::Fits::open_indexed(&path).unwrap().write_sidecar().unwrap();
+  rsf::Fits::remove_hdu_in_place(&path, 2).unwrap();
+  let sidecar = fs::read_to_string(rsf::FitsIndex::sidecar_path(&path)).unwrap();
+  let index = rsf::Fits::open_indexed(&path).unwrap();
+  assert_eq!(sidecar.lines().count() - 2, index.get_num_hdus());
+  assert_eq!(index.get_num_hdus(), rsf::Fits::open(&path).unwrap().get_num_hdus());
+}
@@ -0,0 +1,83 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::path::PathBuf;
+
+use rustronomy_fits as rsf;
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+fn names(fits: &rsf::Fits) -> Vec<String> {
+  (0..fits.get_num_hdus())
+    .map(|index| {
+      let header = fits.get_hdu(index).unwrap().get_header();
+      let name = header.get_value("EXTNAME").and_then(|val| rsf::unquote(val));
+      let ver = header.get_value("EXTVER").map(|val| val.trim().to_string());
+      format!("{}:{}", name.unwrap_or_default(), ver.unwrap_or_default())
+    })
+    .collect()
+}
+
+#[test]
+fn reorder_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let mut fits = rsf::Fits::open(&real_path).unwrap();
+  assert_eq!(names(&fits), [":", "SCI:1", "ERR:1", "DQ:1", "SAMP:1", "TIME:1"]);
+
+  //Not a permutation
+  assert!(fits.reorder(&[0, 1, 2]).is_err());
+  assert!(fits.reorder(&[0, 1, 1, 2, 3, 4]).is_err());
+
+  //Moving an image extension in front promotes it to the primary HDU
+  fits.reorder(&[2, 1, 0, 3, 4, 5]).unwrap();
+  assert_eq!(names(&fits), ["ERR:1", "SCI:1", ":", "DQ:1", "SAMP:1", "TIME:1"]);
+  let primary = fits.primary().unwrap().get_header();
+  assert_eq!(primary.get_value("SIMPLE").unwrap(), "T");
+  assert_eq!(primary.get_value("EXTEND").unwrap(), "T");
+  assert!(primary.get_value("XTENSION").is_none());
+  let demoted = fits.get_hdu(2).unwrap().get_header();
+  assert_eq!(demoted.get_value("XTENSION").unwrap(), "'IMAGE   '");
+
+  //The file can still be written and read
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("reordered.fits");
+  fits.write(&path).unwrap();
+  let fits = rsf::Fits::open(&path).unwrap();
+  assert_eq!(names(&fits), ["ERR:1", "SCI:1", ":", "DQ:1", "SAMP:1", "TIME:1"]);
+}
+
+#[test]
+fn sort_by_extname_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("sort_by_extname.fits");
+  rsf::Fits::concat(&[&real_path, &real_path], &path).unwrap();
+
+  //Duplicated EXTNAME + EXTVER combinations are renumbered
+  let mut fits = rsf::Fits::open(&path).unwrap();
+  fits.sort_by_extname();
+  assert_eq!(
+    names(&fits),
+    [
+      ":", "DQ:1", "DQ:2", "ERR:1", "ERR:2", "SAMP:1", "SAMP:2", "SCI:1", "SCI:2", "TIME:1",
+      "TIME:2", ":"
+    ]
+  );
+}
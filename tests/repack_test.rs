@@ -0,0 +1,82 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{fs, path::PathBuf};
+
+use rustronomy_fits as rsf;
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+const BLOCK_SIZE: usize = 2880;
+
+fn ones_complement_sum(bytes: &[u8]) -> u32 {
+  //32-bit ones' complement sum, with the carries added back in at the end
+  let mut sum: u64 = bytes
+    .chunks_exact(4)
+    .map(|word| u32::from_be_bytes([word[0], word[1], word[2], word[3]]) as u64)
+    .sum();
+  while sum >> 32 != 0 {
+    sum = (sum & 0xffff_ffff) + (sum >> 32);
+  }
+  sum as u32
+}
+
+#[test]
+fn repack_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+
+  let mut dir = dirs::cache_dir().unwrap();
+  dir.push("repack_test");
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).unwrap();
+
+  //(1) The original file has no checksums at all
+  let out = dir.join("repacked.fits");
+  let report = rsf::Fits::repack(&real_path, &out).unwrap();
+  assert!(!report.is_clean());
+  assert!(report.get_changes().iter().any(|(_, change)| change == "CHECKSUM was missing"));
+
+  //(2) Every HDU of the repacked file should sum to negative zero
+  let bytes = fs::read(&out).unwrap();
+  let index = rsf::FitsIndex::build(&out).unwrap();
+  for hdu_idx in 0..index.get_num_hdus() {
+    let layout = index.get_layout(hdu_idx).unwrap();
+    let start = layout.get_start_block() * BLOCK_SIZE;
+    let end = start + (layout.get_header_blocks() + layout.get_data_blocks()) * BLOCK_SIZE;
+    assert_eq!(ones_complement_sum(&bytes[start..end]), u32::MAX, "HDU #{hdu_idx}");
+  }
+
+  //(3) Repacking a repacked file should not find anything wrong
+  let again = dir.join("again.fits");
+  let report = rsf::Fits::repack(&out, &again).unwrap();
+  assert!(report.is_clean(), "{report}");
+  assert_eq!(fs::read(&again).unwrap(), bytes);
+
+  //(4) The data itself is untouched
+  let original = rsf::Fits::open(&real_path).unwrap();
+  let repacked = rsf::Fits::open(&out).unwrap();
+  assert_eq!(original.get_num_hdus(), repacked.get_num_hdus());
+  let original_img = original.get_hdu(1).unwrap().get_data().unwrap().to_string();
+  let repacked_img = repacked.get_hdu(1).unwrap().get_data().unwrap().to_string();
+  assert_eq!(original_img, repacked_img);
+  let data: &rsf::TypedImage = repacked.primary_image().unwrap();
+  assert_eq!(
+    data.as_array::<f32>().unwrap(),
+    original.primary_image().unwrap().as_array::<f32>().unwrap()
+  );
+}
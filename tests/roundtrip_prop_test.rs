@@ -0,0 +1,416 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/*  Property-based round-trip tests: header records and table fields are
+    generated by proptest strategies, formatted like a FITS writer would and
+    parsed back, which must give the original values. Failing cases are
+    shrunk by proptest and stored under proptest-regressions/.
+*/
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use proptest::{
+  collection::{btree_map, vec},
+  option,
+  prelude::*,
+  sample::select,
+  string::string_regex,
+};
+use rustronomy_fits::{self as rsf, KeywordValue};
+
+fn text(len: usize) -> impl Strategy<Value = String> {
+  //Printable ASCII, including quotes, slashes and ampersands
+  string_regex(&format!("[ -~]{{{len}}}")).unwrap()
+}
+
+fn finite() -> impl Strategy<Value = f64> {
+  use proptest::num::f64::{NORMAL, SUBNORMAL, ZERO};
+  NORMAL | SUBNORMAL | ZERO
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+  Str(String),
+  Int(i64),
+  Float(f64),
+  Logical(bool),
+  Sexagesimal(rsf::Sexagesimal),
+}
+
+struct Raw(String);
+
+impl KeywordValue for Raw {
+  fn parse_value(raw: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(Raw(raw.to_string()))
+  }
+  fn format_value(&self) -> String {
+    self.0.clone()
+  }
+}
+
+fn keyword() -> impl Strategy<Value = String> {
+  //The first letters avoid reserved and structural keywords
+  string_regex("[AJKLMQRUVWY][A-Z0-9_-]{0,7}").unwrap()
+}
+
+fn value() -> impl Strategy<Value = Value> {
+  prop_oneof![
+    select(vec![0, 1, 8, 20, 67, 68, 69, 70, 150]).prop_flat_map(text).prop_map(Value::Str),
+    (any::<i64>(), 0..64u32).prop_map(|(int, shift)| Value::Int(int >> shift)),
+    finite().prop_map(Value::Float),
+    any::<bool>().prop_map(Value::Logical),
+    (-90.0..90.0).prop_map(|deg| Value::Sexagesimal(rsf::Sexagesimal::from_decimal(deg))),
+  ]
+}
+
+fn records() -> impl Strategy<Value = BTreeMap<String, (Value, Option<String>)>> {
+  //Unique keywords with random values and (for numbers and logicals) comments
+  let record =
+    (value(), option::of(string_regex("[ -~]{1,40}").unwrap())).prop_map(|(value, comment)| {
+      match value {
+        //Leading spaces of comments are not significant
+        Value::Int(_) | Value::Float(_) | Value::Logical(_) => {
+          (value, comment.map(|comment| comment.trim_start().to_string()))
+        }
+        _ => (value, None),
+      }
+    });
+  btree_map(keyword(), record, 1..20)
+}
+
+fn set_value(header: &mut rsf::Header, keyword: &str, value: &Value, comment: Option<String>) {
+  let raw = match value {
+    Value::Str(text) => rsf::quote(text),
+    Value::Int(int) => int.to_string(),
+    Value::Float(float) => format!("{float:E}"),
+    Value::Logical(true) => "T".to_string(),
+    Value::Logical(false) => "F".to_string(),
+    Value::Sexagesimal(sexa) => return header.set_value_with(keyword, sexa, comment).unwrap(),
+  };
+  header.set_value_with(keyword, &Raw(raw), comment).unwrap();
+}
+
+fn check_value(header: &rsf::Header, keyword: &str, value: &Value) -> bool {
+  let raw = match header.get_value(keyword) {
+    Some(raw) => raw,
+    None => return false,
+  };
+  match value {
+    //Trailing spaces are not significant in FITS strings
+    Value::Str(text) => rsf::unquote(raw).as_deref() == Some(text.trim_end()),
+    Value::Int(int) => raw.trim().parse::<i64>().ok() == Some(*int),
+    Value::Float(float) => raw.trim().parse::<f64>().ok() == Some(*float),
+    Value::Logical(logical) => raw.trim() == if *logical { "T" } else { "F" },
+    Value::Sexagesimal(sexa) => {
+      header.get_value_with::<rsf::Sexagesimal>(keyword).ok().as_ref() == Some(sexa)
+    }
+  }
+}
+
+proptest! {
+  #[test]
+  fn header_roundtrip_test(records in records()) {
+    //(1) A header with the generated records
+    let base = "SIMPLE  =                    T\nBITPIX  =                    8\nNAXIS   =                    0";
+    let mut header = rsf::Header::from_text(base).unwrap();
+    for (keyword, (value, comment)) in &records {
+      set_value(&mut header, keyword, value, comment.clone());
+    }
+
+    //(2) Format the header and parse it again
+    let text = header.to_text().unwrap();
+    let parsed = rsf::Header::from_text(&text).unwrap();
+
+    //(3) All values and comments survive, and formatting again is stable
+    for (keyword, (value, comment)) in &records {
+      prop_assert!(check_value(&parsed, keyword, value), "{keyword} = {value:?}\n{text}");
+      if let Some(comment) = comment.as_ref().filter(|comment| !comment.trim().is_empty()) {
+        prop_assert_eq!(
+          parsed.get_comment(keyword).map(|parsed| parsed.trim()),
+          Some(comment.trim()),
+          "comment of {}\n{}", keyword, text
+        );
+      }
+    }
+    prop_assert_eq!(parsed.to_text().unwrap(), text);
+  }
+
+  #[test]
+  fn sexagesimal_roundtrip_test(deg in -360.0..360.0) {
+    let sexa = rsf::Sexagesimal::from_decimal(deg);
+    prop_assert_eq!(sexa.to_string().parse::<rsf::Sexagesimal>().unwrap(), sexa);
+  }
+}
+
+//Fortran format of a table column, with the formatted fields of its rows
+#[derive(Debug, Clone)]
+struct Column {
+  tform: String,
+  fields: Vec<String>,
+}
+
+fn fortran_exp(mantissa: &str, exp_char: char) -> String {
+  //Rust writes 1.5E-7, Fortran 1.5E-07 (with an explicit sign)
+  let (mantissa, exp) = mantissa.split_once('E').unwrap();
+  let exp: i32 = exp.parse().unwrap();
+  format!("{mantissa}{exp_char}{}{:02}", if exp < 0 { '-' } else { '+' }, exp.abs())
+}
+
+fn right_justify(fields: Vec<String>, pad: usize) -> (usize, Vec<String>) {
+  //The width of a column is that of its widest field, plus some padding
+  let width = fields.iter().map(String::len).max().unwrap() + pad;
+  (width, fields.iter().map(|field| format!("{field:>width$}")).collect())
+}
+
+fn ascii_column(n_rows: usize) -> impl Strategy<Value = Column> {
+  //Aw, Iw, Fw.d, Ew.d and Dw.d columns, right-justified with some padding
+  let chars = (1..=20usize).prop_flat_map(move |width| {
+    vec(text(width), n_rows).prop_map(move |fields| Column { tform: format!("A{width}"), fields })
+  });
+  let ints = (1..=15u32, 0..3usize).prop_flat_map(move |(digits, pad)| {
+    let max = 10i64.pow(digits);
+    vec(1 - max..max, n_rows).prop_map(move |ints| {
+      let (width, fields) = right_justify(ints.iter().map(i64::to_string).collect(), pad);
+      Column { tform: format!("I{width}"), fields }
+    })
+  });
+  let fixed = (1..=12u32, 0..6usize, 0..3usize).prop_flat_map(move |(digits, decimals, pad)| {
+    let max = 10i64.pow(digits);
+    vec(1 - max..max, n_rows).prop_map(move |ints| {
+      let scale = 10f64.powi(decimals as i32);
+      let fields = ints.iter().map(|&int| format!("{:.decimals$}", int as f64 / scale)).collect();
+      let (width, fields) = right_justify(fields, pad);
+      Column { tform: format!("F{width}.{decimals}"), fields }
+    })
+  });
+  let exps = (select(vec!['E', 'D']), 1..=16usize, 0..3usize).prop_flat_map(
+    move |(exp_char, decimals, pad)| {
+      vec(finite(), n_rows).prop_map(move |floats| {
+        let fields = floats
+          .iter()
+          .map(|float| fortran_exp(&format!("{float:.decimals$E}"), exp_char))
+          .collect();
+        let (width, fields) = right_justify(fields, pad);
+        Column { tform: format!("{exp_char}{width}.{decimals}"), fields }
+      })
+    },
+  );
+  prop_oneof![chars, ints, fixed, exps]
+}
+
+fn ascii_table() -> impl Strategy<Value = (usize, Vec<Column>)> {
+  (1..30usize).prop_flat_map(|n_rows| (Just(n_rows), vec(ascii_column(n_rows), 1..6)))
+}
+
+fn write_table(path: &Path, cols: &[Column], n_rows: usize) {
+  let mut buf = Vec::new();
+  for card in ["SIMPLE  = T", "BITPIX  = 8", "NAXIS   = 0", "EXTEND  = T", "END"] {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(2880, b' ');
+
+  //(1) Columns are separated by a single space
+  let width: usize = cols.iter().map(|col| col.fields[0].len() + 1).sum();
+  let mut cards = vec![
+    "XTENSION= 'TABLE   '".to_string(),
+    "BITPIX  = 8".to_string(),
+    "NAXIS   = 2".to_string(),
+    format!("NAXIS1  = {width}"),
+    format!("NAXIS2  = {n_rows}"),
+    "PCOUNT  = 0".to_string(),
+    "GCOUNT  = 1".to_string(),
+    format!("TFIELDS = {}", cols.len()),
+  ];
+  let mut tbcol = 1;
+  for (idx, col) in cols.iter().enumerate() {
+    let n = idx + 1;
+    cards.push(format!("{:8}= 'COL{n}'", format!("TTYPE{n}")));
+    cards.push(format!("{:8}= {tbcol}", format!("TBCOL{n}")));
+    cards.push(format!("{:8}= '{}'", format!("TFORM{n}"), col.tform));
+    tbcol += col.fields[0].len() + 1;
+  }
+  cards.push("END".to_string());
+  for card in cards {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(buf.len().div_ceil(2880) * 2880, b' ');
+
+  //(2) The rows
+  for row in 0..n_rows {
+    for col in cols {
+      buf.extend(format!("{} ", col.fields[row]).bytes());
+    }
+  }
+  buf.resize(buf.len().div_ceil(2880) * 2880, b' ');
+  fs::write(path, buf).unwrap();
+}
+
+proptest! {
+  #![proptest_config(ProptestConfig::with_cases(64))]
+
+  #[test]
+  fn table_roundtrip_test((n_rows, cols) in ascii_table()) {
+    //(1) Write a table with random columns
+    let mut path = dirs::cache_dir().unwrap();
+    path.push("roundtrip_prop_table.fits");
+    write_table(&path, &cols, n_rows);
+
+    //(2) Every field is parsed back to the value it was formatted from
+    let fits = rsf::Fits::open(&path).unwrap();
+    let tbl = match fits.get_hdu(1).unwrap().get_data() {
+      Some(rsf::Extension::AsciiTable(tbl)) => tbl,
+      _ => panic!("expected a table"),
+    };
+    prop_assert_eq!(tbl.get_shape(), (cols.len(), n_rows));
+    for (idx, col) in cols.iter().enumerate() {
+      for (row, field) in col.fields.iter().enumerate() {
+        let entry = tbl.get_entry(idx, row).unwrap();
+        let ok = match &entry {
+          rsf::TableEntry::Text(text) => text == field,
+          rsf::TableEntry::Int(int) => field.trim().parse::<i64>().ok() == Some(*int),
+          rsf::TableEntry::Float(float) => {
+            field.trim().replace('D', "E").parse::<f64>().ok() == Some(*float)
+          }
+          _ => false,
+        };
+        prop_assert!(ok, "{} field '{}' parsed as {:?}", col.tform, field, entry);
+      }
+    }
+  }
+}
+
+fn bin_type() -> impl Strategy<Value = rsf::BinType> {
+  use rsf::BinType::*;
+  select(vec![Logical, Bit, Byte, Short, Int, Long, Char, Float, Double, Complex, DoubleComplex])
+}
+
+//TFORMn code of a binary table column, with the repeat count, type, maximum
+//length and whether it is a variable-length array as they should be parsed
+type BinTform = (String, usize, rsf::BinType, bool, Option<usize>);
+
+fn bin_tform() -> impl Strategy<Value = BinTform> {
+  //rT, where r may be left out, and rPt(emax) or rQt(emax) with r 0 or 1
+  let fixed = (option::of(0..1000usize), bin_type()).prop_map(|(repeat, kind)| {
+    let code = format!("{}{}", repeat.map(|r| r.to_string()).unwrap_or_default(), kind.get_code());
+    (code, repeat.unwrap_or(1), kind, false, None)
+  });
+  let variable =
+    (option::of(0..=1usize), select(vec!['P', 'Q']), bin_type(), option::of(0..100_000usize))
+      .prop_map(|(repeat, desc, kind, max_len)| {
+        let code = format!(
+          "{}{desc}{}{}",
+          repeat.map(|r| r.to_string()).unwrap_or_default(),
+          kind.get_code(),
+          max_len.map(|max| format!("({max})")).unwrap_or_default()
+        );
+        (code, repeat.unwrap_or(1), kind, true, max_len)
+      });
+  prop_oneof![fixed, variable]
+}
+
+fn fixed_bin_column() -> impl Strategy<Value = (String, usize)> {
+  //Numeric columns, which hold every bit pattern, with the bytes per row
+  let kind = select(vec!['B', 'I', 'J', 'K', 'E', 'D', 'C', 'M']);
+  (1..4usize, kind).prop_map(|(repeat, code)| {
+    let size = match code {
+      'B' => 1,
+      'I' => 2,
+      'J' | 'E' => 4,
+      'K' | 'D' | 'C' => 8,
+      _ => 16,
+    };
+    (format!("{repeat}{code}"), repeat * size)
+  })
+}
+
+fn bin_table() -> impl Strategy<Value = (usize, Vec<(String, usize)>, Vec<u8>)> {
+  (1..20usize, vec(fixed_bin_column(), 1..6)).prop_flat_map(|(n_rows, cols)| {
+    let row_len: usize = cols.iter().map(|(_, size)| size).sum();
+    (Just(n_rows), Just(cols), vec(any::<u8>(), row_len * n_rows))
+  })
+}
+
+fn write_bin_table(cols: &[(String, usize)], n_rows: usize, data: &[u8]) -> Vec<u8> {
+  let mut buf = Vec::new();
+  let row_len: usize = cols.iter().map(|(_, size)| size).sum();
+  let mut cards = vec![
+    "SIMPLE  = T".to_string(),
+    "BITPIX  = 8".to_string(),
+    "NAXIS   = 0".to_string(),
+    "EXTEND  = T".to_string(),
+    "END".to_string(),
+  ];
+  for card in cards.drain(..) {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(2880, b' ');
+
+  cards.extend([
+    "XTENSION= 'BINTABLE'".to_string(),
+    "BITPIX  = 8".to_string(),
+    "NAXIS   = 2".to_string(),
+    format!("NAXIS1  = {row_len}"),
+    format!("NAXIS2  = {n_rows}"),
+    "PCOUNT  = 0".to_string(),
+    "GCOUNT  = 1".to_string(),
+    format!("TFIELDS = {}", cols.len()),
+  ]);
+  for (idx, (tform, _)) in cols.iter().enumerate() {
+    cards.push(format!("{:8}= '{tform}'", format!("TFORM{}", idx + 1)));
+  }
+  cards.push("END".to_string());
+  for card in cards {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(buf.len().div_ceil(2880) * 2880, b' ');
+  buf.extend(data);
+  buf.resize(buf.len().div_ceil(2880) * 2880, 0);
+  buf
+}
+
+proptest! {
+  #[test]
+  fn bin_tform_roundtrip_test((tform, repeat, kind, variable, max_len) in bin_tform()) {
+    let format = rsf::BinFormat::parse(&tform).unwrap();
+    prop_assert_eq!(format.get_repeat(), repeat);
+    prop_assert_eq!(format.get_type(), kind);
+    prop_assert_eq!(format.is_variable(), variable);
+    prop_assert_eq!(format.get_max_len(), max_len);
+    prop_assert_eq!(rsf::BinFormat::parse(&format.to_string()).unwrap(), format);
+  }
+
+  #[test]
+  fn bin_table_roundtrip_test((n_rows, cols, data) in bin_table()) {
+    //(1) Fields of every bit pattern are decoded...
+    let file = write_bin_table(&cols, n_rows, &data);
+    let fits = rsf::Fits::from_stream(file.as_slice()).unwrap();
+    let entries = |fits: &rsf::Fits| match fits.get_hdu(1).unwrap().get_data() {
+      Some(rsf::Extension::BinTable(tbl)) => format!("{:?}", (0..n_rows)
+        .map(|row| (0..cols.len()).map(|col| tbl.get_entry(col, row).unwrap()).collect::<Vec<_>>())
+        .collect::<Vec<_>>()),
+      _ => panic!("expected a binary table"),
+    };
+
+    //(2) ...and encoded to the same bytes
+    let written = fits.clone().write_stream(Vec::new(), &rsf::WriteOptions::new()).unwrap();
+    let data_len = data.len().div_ceil(2880) * 2880;
+    prop_assert_eq!(&written[written.len() - data_len..], &file[file.len() - data_len..]);
+    prop_assert_eq!(entries(&rsf::Fits::from_stream(written.as_slice()).unwrap()), entries(&fits));
+  }
+}
@@ -0,0 +1,117 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::fs;
+
+use rustronomy_fits::{self as rsf, img_err::RowShapeErr};
+
+fn simulation(width: usize, height: usize, depth: usize) -> impl Iterator<Item = Vec<f32>> {
+  //Generates the rows of a (width x height x depth) cube, one at a time
+  (0..height * depth).map(move |row| (0..width).map(|x| (row * width + x) as f32).collect())
+}
+
+#[test]
+fn from_row_iter_test() {
+  //(1) Rows run along the first axis, in file order
+  let img = rsf::Image::<f32>::from_row_iter(&[4, 3, 2], simulation(4, 3, 2)).unwrap();
+  assert_eq!(img.get_shape(), &vec![4, 3, 2]);
+  assert_eq!(img.get_data()[[1, 0, 0]], 1.0);
+  assert_eq!(img.get_data()[[0, 1, 0]], 4.0);
+  assert_eq!(img.get_data()[[3, 2, 1]], 23.0);
+
+  //(2) Rows have to fit the shape exactly
+  let err = rsf::Image::<i16>::from_row_iter(&[3, 2], [[1, 2, 3].as_slice(), &[4, 5]]).unwrap_err();
+  assert_eq!((err.get_row(), err.get_len()), (1, Some(2)));
+  let err = rsf::Image::<i16>::from_row_iter(&[3, 2], [[1, 2, 3]]).unwrap_err();
+  assert_eq!((err.get_row(), err.get_len()), (1, None));
+  let err = rsf::Image::<i16>::from_row_iter(&[3, 1], [[1, 2, 3], [4, 5, 6]]).unwrap_err();
+  assert!(err.to_string().contains("more than 1 rows"));
+}
+
+#[test]
+fn stream_writer_test() {
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("stream.fits");
+  let header = rsf::Header::from_text("OBJECT  = 'simulation'\nNAXIS   = 99").unwrap();
+
+  //(1) Stream a cube to disk, the structural records follow from the data
+  let mut stream =
+    rsf::ImageStreamWriter::<f32>::create_with_header(&path, &[400, 30, 2], Some(&header)).unwrap();
+  stream.write_rows(simulation(400, 30, 2)).unwrap();
+  assert_eq!(stream.get_rows_written(), stream.get_num_rows());
+  stream.finish().unwrap();
+  assert_eq!(fs::metadata(&path).unwrap().len() % 2880, 0);
+
+  //(2) ...and read it back
+  let fits = rsf::Fits::open(&path).unwrap();
+  let expected = rsf::Image::<f32>::from_row_iter(&[400, 30, 2], simulation(400, 30, 2)).unwrap();
+  assert_eq!(fits.primary_image().unwrap().as_array::<f32>().unwrap(), expected.get_data());
+  let primary = fits.primary().unwrap().get_header();
+  assert_eq!(primary.get_value("OBJECT").unwrap(), "'simulation'");
+  assert_eq!(primary.get_value_as::<usize>("NAXIS").unwrap(), 3);
+
+  //(3) Streams that are too long or too short are refused
+  let mut stream = rsf::ImageStreamWriter::<i32>::create(&path, &[2, 2]).unwrap();
+  stream.write_row(&[1, 2]).unwrap();
+  assert!(stream.write_row(&[3]).is_err());
+  let err = stream.finish().unwrap_err();
+  assert_eq!(err.downcast_ref::<RowShapeErr>().unwrap().get_row(), 1);
+}
+
+#[test]
+fn stream_reader_test() {
+  //(1) A cube with checksums, written in one go and then repacked
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("stream_read_raw.fits");
+  let mut stream = rsf::ImageStreamWriter::<f32>::create(&path, &[400, 30, 2]).unwrap();
+  stream.write_rows(simulation(400, 30, 2)).unwrap();
+  stream.finish().unwrap();
+  let summed = dirs::cache_dir().unwrap().join("stream_read.fits");
+  rsf::Fits::repack(&path, &summed).unwrap();
+
+  //(2) The rows come back in order, and the checksum matches
+  let mut reader = rsf::ImageStreamReader::<f32>::open(&summed).unwrap();
+  assert_eq!(reader.get_shape(), &[400, 30, 2]);
+  assert!(reader.get_header().get_value("DATASUM").is_some());
+  let mut rows = Vec::new();
+  while let Some(row) = reader.read_row().unwrap() {
+    rows.push(row);
+  }
+  assert_eq!(rows, simulation(400, 30, 2).collect::<Vec<_>>());
+  assert_eq!(reader.get_rows_read(), 60);
+  assert!(reader.finish().unwrap().is_empty());
+
+  //(3) Corrupted data is reported at the end, even if not all rows were read
+  let mut bytes = fs::read(&summed).unwrap();
+  let last = bytes.len() - 2880;
+  bytes[last] ^= 0x40;
+  let corrupted = dirs::cache_dir().unwrap().join("stream_read_corrupted.fits");
+  fs::write(&corrupted, bytes).unwrap();
+  let mut reader = rsf::ImageStreamReader::<f32>::open(&corrupted).unwrap();
+  assert_eq!(reader.read_row().unwrap().unwrap()[1], 1.0);
+  let warnings = reader.finish().unwrap();
+  assert_eq!(warnings.len(), 1);
+  assert_eq!((warnings[0].get_keyword(), warnings[0].get_hdu()), ("DATASUM", 0));
+  assert_eq!(warnings[0].get_severity(), rsf::Severity::Warning);
+
+  //(4) Files without DATASUM have nothing to verify, and the pixel type has to match
+  let reader = rsf::ImageStreamReader::<f32>::open(&path).unwrap();
+  assert!(reader.finish().unwrap().is_empty());
+  assert!(rsf::ImageStreamReader::<i16>::open(&path).is_err());
+  assert!(rsf::ImageStreamReader::<f32>::open_hdu(&path, 1).is_err());
+}
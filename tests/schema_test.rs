@@ -0,0 +1,60 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::path::PathBuf;
+
+use rustronomy_fits::{self as rsf, ColumnType, DataKind, HduSchema, Schema, ViolationKind};
+
+static TABLE_FILE: &str = "resources/Hubble_HRS.fits";
+
+#[test]
+fn schema_test() {
+  let mut real = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real.push(TABLE_FILE);
+  let fits = rsf::Fits::open(&real).unwrap();
+
+  //This schema describes the HRS file correctly
+  let good = Schema::new("hrs").hdu(HduSchema::new(DataKind::Image).require_keyword("NAXIS1")).hdu(
+    HduSchema::new(DataKind::AsciiTable)
+      .require_column("pixel number", ColumnType::Float)
+      .require_column("binid", ColumnType::Int)
+      .require_column("the first coordinate type", ColumnType::Text),
+  );
+  let report = good.validate(&fits);
+  print!("{report}");
+  assert!(report.is_valid());
+
+  //...and this one doesn't
+  let bad = Schema::new("not hrs")
+    .hdu(HduSchema::new(DataKind::NoData).require_keyword("NOTHERE"))
+    .hdu(HduSchema::new(DataKind::AsciiTable).require_column("binid", ColumnType::Float))
+    .hdu(HduSchema::new(DataKind::Image).named("SCI"));
+  let report = bad.validate(&fits);
+  print!("{report}");
+  let kinds: Vec<ViolationKind> = report.get_violations().iter().map(|v| v.get_kind()).collect();
+  assert_eq!(
+    kinds,
+    [
+      ViolationKind::WrongDataKind,
+      ViolationKind::MissingKeyword,
+      ViolationKind::WrongColumnType,
+      ViolationKind::MissingHdu
+    ]
+  );
+  assert_eq!(report.to_tsv().lines().count(), 5);
+}
@@ -0,0 +1,106 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::path::PathBuf;
+
+use ndarray::{s, Ix2};
+use rustronomy_fits::{self as rsf, AxisRange, HduSelector};
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+fn real_path() -> String {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  real_path.to_string_lossy().to_string()
+}
+
+fn sci() -> ndarray::Array2<f32> {
+  let fits = rsf::Fits::open(&PathBuf::from(real_path())).unwrap();
+  let img = fits.primary_image().unwrap().as_array::<f32>().unwrap();
+  img.to_owned().into_dimensionality::<Ix2>().unwrap()
+}
+
+fn read(spec: &str) -> ndarray::Array2<f32> {
+  let img = rsf::Fits::read_section(&format!("{}{spec}", real_path())).unwrap();
+  img.as_owned_array::<f32>().unwrap().into_dimensionality::<Ix2>().unwrap()
+}
+
+#[test]
+fn parse_test() {
+  //(1) Sections
+  let section = rsf::Section::parse("100:200, *,-*:2,7,5:1:2").unwrap();
+  assert_eq!(
+    section.get_axes(),
+    &[
+      AxisRange::Range { first: 100, last: 200, step: 1 },
+      AxisRange::Full { reversed: false, step: 1 },
+      AxisRange::Full { reversed: true, step: 2 },
+      AxisRange::Range { first: 7, last: 7, step: 1 },
+      AxisRange::Range { first: 5, last: 1, step: 2 },
+    ]
+  );
+  assert_eq!(section.to_string(), "100:200,*,-*:2,7:7,5:1:2");
+  assert!(rsf::Section::parse("0:10").is_err());
+  assert!(rsf::Section::parse("1:2:3:4").is_err());
+  assert!(rsf::Section::parse("a:b").is_err());
+  assert!(rsf::Section::parse("").is_err());
+
+  //(2) Extended file names
+  let path = rsf::ExtendedPath::parse("dir/file.fits[SCI,2][1:10,*]").unwrap();
+  assert_eq!(path.get_path(), PathBuf::from("dir/file.fits"));
+  assert_eq!(path.get_hdu(), Some(&HduSelector::Name { extname: "SCI".into(), extver: Some(2) }));
+  assert_eq!(path.get_section().unwrap().to_string(), "1:10,*");
+
+  let path = rsf::ExtendedPath::parse("file.fits[3]").unwrap();
+  assert_eq!(path.get_hdu(), Some(&HduSelector::Index(3)));
+  assert!(path.get_section().is_none());
+  let path = rsf::ExtendedPath::parse("file.fits[1:5]").unwrap();
+  assert!(path.get_hdu().is_none());
+  assert!(path.get_section().is_some());
+
+  assert!(rsf::ExtendedPath::parse("file.fits[1").is_err());
+  assert!(rsf::ExtendedPath::parse("file.fits[1][2]").is_err());
+  assert!(rsf::ExtendedPath::parse("[1]").is_err());
+  assert!(rsf::ExtendedPath::parse("file.fits[SCI,x]").is_err());
+}
+
+#[test]
+fn read_section_test() {
+  let sci = sci();
+
+  //(1) Plain ranges are 1-based and inclusive
+  let cut = read("[1][100:200,10:20]");
+  assert_eq!(cut, sci.slice(s![99..200, 9..20]));
+  assert_eq!(read("[SCI][100:200,10:20]"), cut);
+  assert_eq!(read("[sci,1][100:200,10:20]"), cut);
+  assert_eq!(read("[100:200,10:20]"), cut);
+
+  //(2) Whole axes, steps and reversed axes
+  assert_eq!(read("[SCI]"), sci);
+  assert_eq!(read("[SCI][*,7]"), sci.slice(s![.., 6..7]));
+  assert_eq!(read("[SCI][1:10:3,-*]"), sci.slice(s![0..10;3, ..;-1]));
+  assert_eq!(read("[SCI][10:1:3,5:6]"), sci.slice(s![0..10;3, 4..6;1]).slice(s![..;-1, ..]));
+  assert_eq!(read("[SCI][-*:4,*:5]"), sci.slice(s![..;-4, ..;5]));
+
+  //(3) Sections that do not fit, and HDU's that do not exist
+  let spec = |spec: &str| format!("{}{spec}", real_path());
+  assert!(rsf::Fits::read_section(&spec("[SCI][1:271,*]")).is_err());
+  assert!(rsf::Fits::read_section(&spec("[SCI][1:10]")).is_err());
+  assert!(rsf::Fits::read_section(&spec("[SCI,2]")).is_err());
+  assert!(rsf::Fits::read_section(&spec("[0]")).is_err());
+}
@@ -0,0 +1,124 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::path::PathBuf;
+
+use ndarray::{Array, Array2, IxDyn};
+use rustronomy_fits::{self as rsf, img_err::StackShapeErr, Stack};
+
+fn typed(data: Array2<f64>) -> rsf::TypedImage {
+  rsf::TypedImage::from(rsf::Image::new(data.into_dyn()))
+}
+
+fn frame(name: &str, value: i32, exptime: f64) -> PathBuf {
+  //Constant 8x6 frame with a WCS and an exposure time
+  let header =
+    rsf::Header::from_text(&format!("EXPTIME = {exptime:?}\nCRPIX1  = 4.0\nCTYPE1  = 'RA---TAN'"))
+      .unwrap();
+  let mut path = dirs::cache_dir().unwrap();
+  path.push(format!("stack_{name}.fits"));
+  let mut stream =
+    rsf::ImageStreamWriter::<i32>::create_with_header(&path, &[8, 6], Some(&header)).unwrap();
+  stream.write_rows(std::iter::repeat_n(vec![value; 8], 6)).unwrap();
+  stream.finish().unwrap();
+  path
+}
+
+#[test]
+fn weighted_mean_test() {
+  //(1) Frames without weights and exposure times give the plain mean
+  let mut stack = Stack::new();
+  stack.add(&typed(Array2::from_elem((3, 2), 1.0)), None, None).unwrap();
+  stack.add(&typed(Array2::from_elem((3, 2), 4.0)), None, None).unwrap();
+  let mean = stack.mean().as_owned_f64_array().unwrap();
+  assert!(mean.iter().all(|&px| px == 2.5));
+
+  //(2) Weight maps are inverse variances, bad pixels do not contribute
+  let mut weight = Array2::from_elem((3, 2), 3.0);
+  weight[[0, 0]] = 0.0;
+  let mut frame = Array2::from_elem((3, 2), 8.0);
+  frame[[1, 1]] = f64::NAN;
+  stack.add(&typed(frame), Some(weight.view().into_dyn()), None).unwrap();
+  let mean = stack.mean().as_owned_f64_array().unwrap();
+  assert_eq!(mean[[0, 0]], 2.5);
+  assert_eq!(mean[[1, 1]], 2.5);
+  assert_eq!(mean[[2, 1]], (1.0 + 4.0 + 3.0 * 8.0) / 5.0);
+  let weights = stack.weight_map().as_owned_f64_array().unwrap();
+  assert_eq!((weights[[0, 0]], weights[[1, 1]], weights[[2, 1]]), (2.0, 2.0, 5.0));
+  let coverage = stack.exposure_map().as_owned_f64_array().unwrap();
+  assert_eq!((coverage[[0, 0]], coverage[[2, 1]]), (2.0, 3.0));
+  assert_eq!(stack.get_total_exposure(), None);
+
+  //(3) Exposure times turn counts into rates, longer exposures weigh more
+  let mut stack = Stack::new();
+  stack.add(&typed(Array2::from_elem((2, 2), 100.0)), None, Some(10.0)).unwrap();
+  stack.add(&typed(Array2::from_elem((2, 2), 600.0)), None, Some(20.0)).unwrap();
+  let mean = stack.mean().as_owned_f64_array().unwrap();
+  assert_eq!(mean[[1, 0]], (100.0 * 10.0 + 400.0 * 30.0) / 500.0);
+  assert_eq!(stack.exposure_map().as_owned_f64_array().unwrap()[[0, 1]], 30.0);
+  assert_eq!(stack.get_total_exposure(), Some(30.0));
+
+  //(4) Frames must line up
+  let err: StackShapeErr = stack.add(&typed(Array2::zeros((2, 3))), None, None).unwrap_err();
+  assert_eq!((err.get_frame(), err.get_expected(), err.get_found()), (2, &[2, 2][..], &[2, 3][..]));
+  let weight = Array::<f64, IxDyn>::zeros(IxDyn(&[4]));
+  assert!(stack.add(&typed(Array2::zeros((2, 2))), Some(weight.view()), None).is_err());
+  assert_eq!(stack.get_n_frames(), 2);
+}
+
+#[test]
+fn stack_hdu_test() {
+  //(1) Stack two frames read from files, with EXPTIME in their headers
+  let paths = [frame("short", 50, 10.0), frame("long", 300, 30.0)];
+  let frames: Vec<rsf::Fits> = paths.iter().map(|path| rsf::Fits::open(path).unwrap()).collect();
+  let mut stack = Stack::new();
+  for fits in &frames {
+    stack.add_hdu(fits.get_hdu(0).unwrap(), None).unwrap();
+  }
+  let coadd = stack.finish();
+
+  //(2) The output is a stack with the WCS of the frames, plus two maps
+  let mut out = dirs::cache_dir().unwrap();
+  out.push("stack_coadd.fits");
+  coadd.write(&out).unwrap();
+  let coadd = rsf::Fits::open(&out).unwrap();
+  let header = coadd.get_hdu(0).unwrap().get_header();
+  assert_eq!(header.get_value_as::<f64>("EXPTIME").unwrap(), 40.0);
+  assert_eq!(header.get_value_as::<usize>("NCOMBINE").unwrap(), 2);
+  assert_eq!(header.get_value_as::<f64>("CRPIX1").unwrap(), 4.0);
+  assert_eq!(header.get_value_as::<isize>("BITPIX").unwrap(), -64);
+  let mean = coadd.primary_image().unwrap().as_f64_array().unwrap().clone();
+  assert_eq!(mean.shape(), &[8, 6]);
+  assert_eq!(mean[[7, 5]], (5.0 * 100.0 + 10.0 * 900.0) / 1000.0);
+
+  let weight = coadd.get_by_name("WEIGHT", 1).unwrap();
+  assert!(weight.get_header().get_value("EXPTIME").is_none());
+  let exposure = coadd.get_by_name("EXPOSURE", 1).unwrap();
+  match exposure.get_data() {
+    Some(rsf::Extension::Image(img)) => {
+      assert!(img.as_f64_array().unwrap().iter().all(|&px| px == 40.0))
+    }
+    _ => panic!("no exposure map"),
+  }
+
+  //(3) HDU's without an image cannot be stacked
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push("resources/Hubble_NICMOS.fits");
+  let nicmos = rsf::Fits::open(&real_path).unwrap();
+  assert!(Stack::new().add_hdu(nicmos.get_hdu(0).unwrap(), None).is_err());
+}
@@ -0,0 +1,78 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{error::Error, fs, path::PathBuf};
+
+use rustronomy_fits::{self as rsf, KeywordValue};
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+struct Text(String);
+
+impl KeywordValue for Text {
+  fn parse_value(raw: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    Ok(Text(rsf::unquote(raw).ok_or("not a string")?))
+  }
+  fn format_value(&self) -> String {
+    rsf::quote(&self.0)
+  }
+}
+
+#[test]
+fn standard_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let mut dir = dirs::cache_dir().unwrap();
+  dir.push("standard_test");
+  fs::create_dir_all(&dir).unwrap();
+  let out = dir.join("long_string.fits");
+
+  //(1) The original file conforms to both standards
+  let fits = rsf::Fits::open(&real_path).unwrap();
+  assert!(fits.check_standard(rsf::FitsStandard::Fits3).is_empty());
+  let opts = rsf::WriteOptions::new().standard(rsf::FitsStandard::Fits3);
+  assert_eq!(opts.get_standard(), rsf::FitsStandard::Fits3);
+  fits.write_with(&out, &opts).unwrap();
+
+  //(2) Long string values need FITS 4.0
+  let mut fits = rsf::Fits::open(&real_path).unwrap();
+  let long = Text("A very long observer description. ".repeat(4));
+  let header = fits.get_hdu_mut(0).unwrap().get_header_mut();
+  header.set_value_with("OBSERVER", &long, None).unwrap();
+
+  let diagnostics = fits.check_standard(rsf::FitsStandard::Fits3);
+  assert_eq!(diagnostics.len(), 1);
+  assert_eq!(diagnostics[0].get_keyword(), "OBSERVER");
+  assert_eq!(diagnostics[0].get_profile(), "FITS 3.0");
+  assert!(fits.check_standard(rsf::FitsStandard::Fits4).is_empty());
+
+  //(3) ...so they are refused when writing for FITS 3.0
+  let err = fits.write_with(&out, &opts).unwrap_err();
+  let err = err.downcast_ref::<rsf::validation_err::ValidationErr>().unwrap();
+  assert_eq!(err.get_diagnostics().len(), 1);
+
+  let mut fits = rsf::Fits::open(&real_path).unwrap();
+  let header = fits.get_hdu_mut(0).unwrap().get_header_mut();
+  header.set_value_with("OBSERVER", &long, None).unwrap();
+  fits.write_with(&out, &rsf::WriteOptions::new()).unwrap();
+  let bytes = fs::read(&out).unwrap();
+  assert!(bytes.chunks_exact(80).any(|card| card.starts_with(b"CONTINUE  '")));
+  let reread = rsf::Fits::open(&out).unwrap();
+  let observer: Text = reread.primary().unwrap().get_header().get_value_with("OBSERVER").unwrap();
+  assert_eq!(observer.0, long.0.trim_end());
+}
@@ -0,0 +1,93 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use ndarray::{Array, Array1};
+use rustronomy_fits::{self as rsf, ImageStats};
+
+fn typed<T: rsf::FitsPixel>(pixels: Vec<T>) -> rsf::TypedImage {
+  rsf::TypedImage::from(rsf::Image::new(Array1::from_vec(pixels).into_dyn()))
+}
+
+#[test]
+fn int_stats_test() {
+  //(1) Sums that overflow i64 are exact, and so is the mean
+  let big = typed(vec![i64::MAX, i64::MAX, i64::MAX - 2, i64::MIN]);
+  let stats = big.stats();
+  assert_eq!(
+    stats.get_int_sum(),
+    Some(2 * i64::MAX as i128 - 2 + i64::MAX as i128 + i64::MIN as i128)
+  );
+  assert_eq!(stats.get_count(), 4);
+  assert_eq!(stats.get_min(), i64::MIN as f64);
+  assert_eq!(stats.get_max(), i64::MAX as f64);
+
+  //(2) The variance does not suffer from a large offset
+  let offset = 1i64 << 62;
+  let stats = typed(vec![offset, offset + 1, offset + 2, offset + 3]).stats();
+  assert_eq!(stats.get_variance(), 1.25);
+  assert_eq!(stats.get_mean(), (offset as f64) + 1.5);
+
+  //(3) Large images are summed in parallel chunks, with the same results
+  let ramp = Array::from_shape_fn((400, 300), |(x, y)| (x + 400 * y) as i32).into_dyn();
+  let stats = rsf::TypedImage::from(rsf::Image::new(ramp)).stats();
+  let n = 120_000i128;
+  assert_eq!(stats.get_int_sum(), Some(n * (n - 1) / 2));
+  assert_eq!(stats.get_mean(), 59_999.5);
+  let variance = (n * n - 1) as f64 / 12.0;
+  assert!((stats.get_variance() - variance).abs() / variance < 1e-12);
+}
+
+#[test]
+fn float_stats_test() {
+  //(1) Compensated summation keeps the small values next to large ones
+  let mut pixels = vec![1e16f64];
+  pixels.extend([1.0; 1000]);
+  pixels.push(-1e16);
+  pixels.push(f64::NAN);
+  let stats = typed(pixels).stats();
+  assert_eq!(stats.get_sum(), 1000.0);
+  assert_eq!(stats.get_count(), 1002);
+  assert_eq!(stats.get_n_nan(), 1);
+  assert_eq!(stats.get_int_sum(), None);
+
+  //(2) Images without pixels have no mean
+  let stats = typed(vec![f32::NAN, f32::NAN]).stats();
+  assert_eq!(stats.get_count(), 0);
+  assert!(stats.get_mean().is_nan() && stats.get_variance().is_nan());
+}
+
+#[test]
+fn merge_test() {
+  //Merged statistics of bias frames are those of all pixels together
+  let frames: Vec<Vec<i64>> =
+    (0..5).map(|frame| (0..1000).map(|px| (1 << 60) + px * 7 % 13 + frame).collect()).collect();
+  let merged = frames
+    .iter()
+    .map(|frame| typed(frame.clone()).stats())
+    .fold(ImageStats::default(), |stats, frame| stats.merge(&frame));
+  let all = typed(frames.concat()).stats();
+  assert_eq!(merged.get_int_sum(), all.get_int_sum());
+  assert_eq!(merged.get_count(), 5000);
+  assert_eq!(merged.get_mean(), all.get_mean());
+  assert!((merged.get_variance() - all.get_variance()).abs() < 1e-9);
+
+  //Merging with a float image gives up the exact sum
+  let mixed = merged.merge(&typed(vec![0.5f32]).stats());
+  assert_eq!(mixed.get_int_sum(), None);
+  assert_eq!(mixed.get_count(), 5001);
+}
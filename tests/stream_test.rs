@@ -0,0 +1,124 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{
+  fs,
+  io::{self, Read},
+  path::PathBuf,
+  process::{Command, Stdio},
+};
+
+use rustronomy_fits::{self as rsf, Extension};
+
+static REAL_FILES: [&str; 3] =
+  ["resources/Hubble_NICMOS.fits", "resources/Hubble_FOC.fits", "resources/Astro_UIT.fits"];
+
+struct Trickle {
+  //Non-seekable stream that hands out a few bytes at a time, like a pipe
+  bytes: Vec<u8>,
+  cursor: usize,
+}
+
+impl Read for Trickle {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let n = buf.len().min(997).min(self.bytes.len() - self.cursor);
+    buf[..n].copy_from_slice(&self.bytes[self.cursor..self.cursor + n]);
+    self.cursor += n;
+    Ok(n)
+  }
+}
+
+fn real_path(file: &str) -> PathBuf {
+  let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  path.push(file);
+  path
+}
+
+fn same<T: rsf::FitsPixel + PartialEq>(a: &rsf::TypedImage, b: &rsf::TypedImage) -> bool {
+  match (a.as_array::<T>(), b.as_array::<T>()) {
+    (Ok(a), Ok(b)) => a == b,
+    (Err(_), Err(_)) => true,
+    _ => false,
+  }
+}
+
+fn assert_same(streamed: &rsf::Fits, opened: &rsf::Fits) {
+  assert_eq!(streamed.get_num_hdus(), opened.get_num_hdus());
+  for index in 0..opened.get_num_hdus() {
+    let (a, b) = (streamed.get_hdu(index).unwrap(), opened.get_hdu(index).unwrap());
+    assert_eq!(a.get_header().to_text().unwrap(), b.get_header().to_text().unwrap());
+    match (a.get_data(), b.get_data()) {
+      (Some(Extension::Image(a)), Some(Extension::Image(b))) => {
+        assert!(same::<u8>(a, b) && same::<i16>(a, b) && same::<i32>(a, b));
+        assert!(same::<i64>(a, b) && same::<f32>(a, b) && same::<f64>(a, b));
+      }
+      (Some(Extension::AsciiTable(a)), Some(Extension::AsciiTable(b))) => {
+        assert_eq!(a.get_shape(), b.get_shape());
+        for col in 0..a.get_shape().0 {
+          assert_eq!(a.get_fmtd_column(col), b.get_fmtd_column(col));
+        }
+      }
+      (None, None) => {}
+      _ => panic!("HDU #{index} has different data"),
+    }
+  }
+}
+
+#[test]
+fn from_stream_test() {
+  for file in REAL_FILES {
+    let path = real_path(file);
+    let stream = Trickle { bytes: fs::read(&path).unwrap(), cursor: 0 };
+    let streamed = rsf::Fits::from_stream(stream).unwrap();
+    assert_same(&streamed, &rsf::Fits::open(&path).unwrap());
+  }
+}
+
+#[test]
+fn pipe_test() {
+  //Same as `program < file.fits`, reading the stdout of cat
+  let path = real_path(REAL_FILES[0]);
+  let mut child = Command::new("cat").arg(&path).stdout(Stdio::piped()).spawn().unwrap();
+  let streamed = rsf::Fits::from_stream(child.stdout.take().unwrap()).unwrap();
+  child.wait().unwrap();
+  assert_same(&streamed, &rsf::Fits::open(&path).unwrap());
+}
+
+#[test]
+fn no_lazy_loading_test() {
+  //Streamed data cannot be read again, so it cannot be unloaded either
+  let bytes = fs::read(real_path(REAL_FILES[0])).unwrap();
+  let mut fits = rsf::Fits::from_stream(bytes.as_slice()).unwrap();
+  let hdu = fits.get_hdu_mut(1).unwrap();
+  assert!(hdu.get_data_source().is_none());
+  assert!(hdu.unload().is_err());
+  assert!(hdu.is_loaded());
+}
+
+#[test]
+fn truncated_stream_test() {
+  let bytes = fs::read(real_path(REAL_FILES[0])).unwrap();
+
+  //(1) An empty stream contains no HDU's
+  assert_eq!(rsf::Fits::from_stream(io::empty()).unwrap().get_num_hdus(), 0);
+
+  //(2) Streams that end halfway through a block or an HDU are invalid
+  assert!(rsf::Fits::from_stream(&bytes[..2880 + 100]).is_err());
+  assert!(rsf::Fits::from_stream(&bytes[..2 * 2880]).is_err());
+}
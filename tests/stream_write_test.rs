@@ -0,0 +1,104 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{
+  fs,
+  path::{Path, PathBuf},
+  process::{Command, Stdio},
+};
+
+use rustronomy_fits::{self as rsf, Extension};
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+fn real_fits() -> rsf::Fits {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  rsf::Fits::open(&real_path).unwrap()
+}
+
+fn out_path(name: &str) -> PathBuf {
+  let mut dir = dirs::cache_dir().unwrap();
+  dir.push("stream_write_test");
+  fs::create_dir_all(&dir).unwrap();
+  dir.join(name)
+}
+
+#[test]
+fn write_stream_test() {
+  //The stream holds exactly what would have been written to a file
+  let path = out_path("reference.fits");
+  real_fits().write(&path).unwrap();
+  let bytes = real_fits().write_stream(Vec::new(), &rsf::WriteOptions::new()).unwrap();
+  assert_eq!(bytes.len() % 2880, 0);
+  assert_eq!(bytes, fs::read(&path).unwrap());
+}
+
+#[cfg(unix)]
+#[test]
+fn pipe_test() {
+  //Same as `program | cat > file.fits`
+  let path = out_path("piped.fits");
+  let mut child = Command::new("cat")
+    .stdin(Stdio::piped())
+    .stdout(fs::File::create(&path).unwrap())
+    .spawn()
+    .unwrap();
+  let stdin = child.stdin.take().unwrap();
+  drop(real_fits().write_stream(stdin, &rsf::WriteOptions::new()).unwrap());
+  child.wait().unwrap();
+
+  let fits = rsf::Fits::open(&path).unwrap();
+  assert_eq!(fits.get_num_hdus(), real_fits().get_num_hdus());
+}
+
+#[cfg(unix)]
+#[test]
+fn non_seekable_path_test() {
+  //Character devices cannot be truncated or cut to length, which is skipped
+  let opts = rsf::WriteOptions::new().sparse(true).preallocate(true).fsync(true);
+  real_fits().write_with(Path::new("/dev/null"), &opts).unwrap();
+}
+
+#[test]
+fn image_to_stream_test() {
+  //(1) Stream an image with a pre-computed header into a buffer
+  let mut header = real_fits().get_hdu(0).unwrap().get_header().clone();
+  header.set_value_with("OBSERVER", &rsf::MetaValue::String(String::from("me")), None).unwrap();
+  let mut writer =
+    rsf::ImageStreamWriter::<i32, _>::to_stream(Vec::new(), &[3, 2], Some(&header)).unwrap();
+  writer.write_rows([[1, 2, 3], [4, 5, 6]]).unwrap();
+  let bytes = writer.finish_stream().unwrap();
+  assert_eq!(bytes.len() % 2880, 0);
+
+  //(2) which reads back like a regular file
+  let fits = rsf::Fits::from_stream(bytes.as_slice()).unwrap();
+  let hdu = fits.get_hdu(0).unwrap();
+  assert_eq!(hdu.get_header().get_value("OBSERVER").unwrap(), "'me'");
+  let Some(Extension::Image(img)) = hdu.get_data() else { panic!("HDU does not contain an image") };
+  let pixels: Vec<i32> = img.as_array::<i32>().unwrap().t().iter().copied().collect();
+  assert_eq!(pixels, [1, 2, 3, 4, 5, 6]);
+}
+
+#[test]
+fn incomplete_image_stream_test() {
+  let mut writer = rsf::ImageStreamWriter::<i16, _>::to_stream(Vec::new(), &[2, 2], None).unwrap();
+  writer.write_row(&[1, 2]).unwrap();
+  assert!(writer.finish_stream().is_err());
+}
@@ -0,0 +1,64 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{error::Error, path::PathBuf};
+
+use rustronomy_fits::{self as rsf, keyword_err::StructuralKeywordErr, KeywordValue};
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+struct Raw(&'static str);
+
+impl KeywordValue for Raw {
+  fn parse_value(_raw: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    Err("only used for writing".into())
+  }
+  fn format_value(&self) -> String {
+    self.0.to_string()
+  }
+}
+
+#[test]
+fn structural_keyword_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let mut fits = rsf::Fits::open(&real_path).unwrap();
+  let header = fits.get_hdu_mut(1).unwrap().get_header_mut();
+
+  //(1) Keywords that describe the data unit cannot be set, in any spelling
+  for keyword in
+    ["SIMPLE", "XTENSION", "BITPIX", "NAXIS", "NAXIS1", "NAXIS12", "PCOUNT", "GCOUNT", "naxis2 "]
+  {
+    let err = header.set_value_with(keyword, &Raw("1"), None).unwrap_err();
+    let err = err.downcast_ref::<StructuralKeywordErr>().unwrap();
+    assert_eq!(err.get_keyword(), keyword.trim().to_uppercase());
+  }
+  assert_eq!(header.get_value_as::<usize>("NAXIS1").unwrap(), 270);
+
+  //(2) The error explains what to do instead
+  let err = header.set_value_with("BITPIX", &Raw("-64"), None).unwrap_err();
+  assert!(err.to_string().contains("convert the image"));
+  let err = header.set_value_with("NAXIS2", &Raw("10"), None).unwrap_err();
+  assert!(err.to_string().contains("reshape"));
+
+  //(3) Other (protected) keywords are refused with their own error, lookalikes are fine
+  let err = header.set_value_with("BSCALE", &Raw("2.0"), None).unwrap_err();
+  assert!(err.downcast_ref::<StructuralKeywordErr>().is_none());
+  header.set_value_with("NAXISLBL", &Raw("'wavelength'"), None).unwrap();
+  assert_eq!(header.get_value("NAXISLBL").unwrap(), "'wavelength'");
+}
@@ -0,0 +1,131 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{fs, time::Instant};
+
+use rustronomy_fits as rsf;
+
+//Number of rows in the generated table
+const N_ROWS: usize = 200_000;
+
+fn ascii_table_file(n_rows: usize) -> Vec<u8> {
+  //Table with an int, a float and a string column (row length = 40 chars)
+  let mut buf = Vec::new();
+  let cards = [
+    "SIMPLE  =                    T",
+    "BITPIX  =                    8",
+    "NAXIS   =                    0",
+    "EXTEND  =                    T",
+    "END",
+  ];
+  for card in cards {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(2880, b' ');
+
+  let naxis2 = format!("NAXIS2  = {n_rows:>20}");
+  let cards = [
+    "XTENSION= 'TABLE   '",
+    "BITPIX  =                    8",
+    "NAXIS   =                    2",
+    "NAXIS1  =                   40",
+    &naxis2,
+    "PCOUNT  =                    0",
+    "GCOUNT  =                    1",
+    "TFIELDS =                    3",
+    "TBCOL1  =                    1",
+    "TFORM1  = 'I10     '",
+    "TBCOL2  =                   11",
+    "TFORM2  = 'E20.7   '",
+    "TBCOL3  =                   31",
+    "TFORM3  = 'A10     '",
+    "END",
+  ];
+  for card in cards {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(2 * 2880, b' ');
+
+  for row in 0..n_rows {
+    let val = (row as f64 - 1000.0) * 0.37;
+    buf
+      .extend(format!("{:>10}{:>20.7E}{:<10}", row as i64 - 500, val, format!("src{row}")).bytes());
+  }
+  buf.resize(buf.len().div_ceil(2880) * 2880, b' ');
+  buf
+}
+
+#[test]
+fn ascii_table_benchmark() {
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("ascii_table_bench.fits");
+  fs::write(&path, ascii_table_file(N_ROWS)).unwrap();
+
+  //Results: (release build)
+  //  - str::parse: ~84ms
+  //  - byte parsers: ~78ms
+  //  - byte parsers, decoded straight into typed columns: ~34ms
+  let now = Instant::now();
+  let mut fits = rsf::Fits::open(&path).unwrap();
+  println!("Decoded {N_ROWS} ASCII table rows in {}ms", now.elapsed().as_millis());
+
+  //Check that the numbers were parsed correctly
+  let tbl = match fits.remove_hdu(1).unwrap().to_parts().1.unwrap() {
+    rsf::Extension::AsciiTable(tbl) => tbl,
+    _ => panic!(),
+  };
+  assert_eq!(tbl.get_shape(), (3, N_ROWS));
+  for row in [0, 1, 999, 1000, 12345, N_ROWS - 1] {
+    let expected: f64 = format!("{:.7E}", (row as f64 - 1000.0) * 0.37).parse().unwrap();
+    assert_eq!(
+      format!("{}", tbl.get_entry(0, row).unwrap()),
+      format!("{} (int)", row as i64 - 500)
+    );
+    assert_eq!(format!("{}", tbl.get_entry(1, row).unwrap()), format!("{expected} (float)"));
+  }
+}
+
+#[test]
+fn table_strategy_test() {
+  //Both decoding strategies should produce the exact same table
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("ascii_table_strategy.fits");
+  fs::write(&path, ascii_table_file(10_000)).unwrap();
+
+  let mut tables = Vec::new();
+  for strategy in [rsf::TableStrategy::RowParallel, rsf::TableStrategy::ColumnParallel] {
+    let opts = rsf::ReadOptions::new().table_strategy(strategy);
+    let now = Instant::now();
+    let mut fits = rsf::Fits::open_with(&path, &opts).unwrap();
+    println!("{strategy:?}: decoded 10000 rows in {}ms", now.elapsed().as_millis());
+    match fits.remove_hdu(1).unwrap().to_parts().1.unwrap() {
+      rsf::Extension::AsciiTable(tbl) => tables.push(tbl),
+      _ => panic!(),
+    }
+  }
+
+  assert_eq!(tables[0].get_shape(), tables[1].get_shape());
+  for col in 0..3 {
+    for row in 0..10_000 {
+      assert_eq!(
+        format!("{}", tables[0].get_entry(col, row).unwrap()),
+        format!("{}", tables[1].get_entry(col, row).unwrap())
+      );
+    }
+  }
+}
@@ -0,0 +1,54 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use rustronomy_fits::{self as rsf, push_row};
+
+#[test]
+fn build_table_test() {
+  let mut builder = rsf::TableBuilder::new().col_f64("FLUX").col_str("NAME", 8).col_i64("ID");
+  push_row!(builder, 1.5, "M31", 31i64).unwrap();
+  push_row!(builder, -0.25, "NGC 1300", 1300i64).unwrap();
+  builder.push_row(vec![2.0.into(), "M1".into(), 1i64.into()]).unwrap();
+  assert_eq!(builder.get_num_rows(), 3);
+
+  let tbl = builder.build();
+  assert_eq!(tbl.get_shape(), (3, 3));
+  assert_eq!(tbl.get_col_label(1), Some("NAME"));
+  assert_eq!(format!("{}", tbl.get_entry(0, 1).unwrap()), "-0.25 (float)");
+  assert_eq!(format!("{}", tbl.get_entry(1, 0).unwrap()), "M31      (string)");
+  assert_eq!(format!("{}", tbl.get_entry(2, 1).unwrap()), "1300 (int)");
+
+  //User-created tables know their own size
+  let ext = rsf::Extension::AsciiTable(tbl);
+  assert!(format!("{ext}").contains("size: 1"));
+}
+
+#[test]
+fn bad_rows_test() {
+  let mut builder = rsf::TableBuilder::new().col_i64("ID").col_str("NAME", 4);
+
+  //wrong number of fields, wrong type and too long strings are all rejected
+  assert!(push_row!(builder, 1i64).is_err());
+  assert!(push_row!(builder, 1.0, "M31").is_err());
+  assert!(push_row!(builder, 1i64, "NGC 1300").is_err());
+  assert_eq!(builder.get_num_rows(), 0);
+
+  push_row!(builder, 1i64, "M31").unwrap();
+  assert_eq!(builder.build().get_shape(), (2, 1));
+}
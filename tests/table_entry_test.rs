@@ -0,0 +1,39 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use rustronomy_fits::{self as rsf, push_row};
+
+#[test]
+fn entry_conversion_test() {
+  let mut builder = rsf::TableBuilder::new().col_i64("ID").col_f64("FLUX").col_str("FLAG", 1);
+  push_row!(builder, 7, 2.5f32, true).unwrap();
+  let tbl = builder.build();
+
+  let id: i64 = tbl.get_entry(0, 0).unwrap().try_into().unwrap();
+  let flux: f64 = tbl.get_entry(1, 0).unwrap().try_into().unwrap();
+  let flag: bool = tbl.get_entry(2, 0).unwrap().try_into().unwrap();
+  let name: String = tbl.get_entry(2, 0).unwrap().try_into().unwrap();
+  assert_eq!((id, flux, flag, name.as_str()), (7, 2.5, true, "T"));
+
+  //integers are promoted to floats, but not the other way around
+  assert_eq!(f64::try_from(tbl.get_entry(0, 0).unwrap()).unwrap(), 7.0);
+  let err = i64::try_from(tbl.get_entry(1, 0).unwrap()).unwrap_err();
+  assert_eq!(format!("{err}"), "cannot convert table entry 2.5 (float) to i64");
+  assert!(bool::try_from(rsf::TableEntry::from("yes")).is_err());
+}
@@ -0,0 +1,101 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{fs, path::PathBuf};
+
+use ndarray::array;
+use rustronomy_fits as rsf;
+
+static TABLE_FILE: &str = "resources/Hubble_WFPC2_1.fits";
+
+fn table_hdu() -> rsf::HeaderDataUnit {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(TABLE_FILE);
+  rsf::Fits::open(&real_path).unwrap().get_hdu(1).unwrap().clone()
+}
+
+fn spectrum() -> rsf::HeaderDataUnit {
+  //Single float column with a unit and a NaN, made from a 1-D image
+  let header = rsf::Header::from_text("BUNIT   = 'Jy'").unwrap();
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("table_export_spectrum.fits");
+  let mut stream =
+    rsf::ImageStreamWriter::<f64>::create_with_header(&path, &[3], Some(&header)).unwrap();
+  stream.write_row(&array![1.5, f64::NAN, -2e20].to_vec()).unwrap();
+  stream.finish().unwrap();
+  rsf::Fits::open(&path).unwrap().get_hdu(0).unwrap().image_to_column("flux").unwrap()
+}
+
+#[test]
+fn ipac_test() {
+  //(1) Header lines line up with the delimiters, and every row with them
+  let text = spectrum().to_ipac().unwrap();
+  let lines: Vec<&str> = text.lines().collect();
+  assert_eq!(lines[0], "\\fixlen = T");
+  let headers: Vec<&str> = lines.iter().copied().filter(|line| line.starts_with('|')).collect();
+  assert_eq!(headers.len(), 4);
+  assert_eq!(headers[0].split('|').nth(1).unwrap().trim(), "flux");
+  assert_eq!(headers[1].split('|').nth(1).unwrap().trim(), "double");
+  assert_eq!(headers[2].split('|').nth(1).unwrap().trim(), "Jy");
+  let rows: Vec<&str> = lines.iter().copied().filter(|line| line.starts_with(' ')).collect();
+  assert_eq!(rows.iter().map(|row| row.trim()).collect::<Vec<_>>(), ["1.5", "null", "-2e20"]);
+  assert!(lines.iter().skip(1).all(|line| line.len() == headers[0].len()));
+
+  //(2) A real table with integer, float and text columns
+  let hdu = table_hdu();
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("table_export.tbl");
+  hdu.write_ipac(&path).unwrap();
+  let text = fs::read_to_string(&path).unwrap();
+  let header = hdu.get_header();
+  let n_fields: usize = header.get_value_as("TFIELDS").unwrap();
+  let names = text.lines().find(|line| line.starts_with('|')).unwrap();
+  assert_eq!(names.matches('|').count(), n_fields + 1);
+  assert!(names.contains("CRVAL1"));
+  let types = text.lines().filter(|line| line.starts_with('|')).nth(1).unwrap();
+  assert!(types.contains("char") && types.contains("double"));
+  assert_eq!(text.lines().filter(|line| line.starts_with(' ')).count(), 4);
+
+  //(3) Images cannot be exported
+  let image = rsf::Fits::open(&dirs::cache_dir().unwrap().join("table_export_spectrum.fits"));
+  assert!(image.unwrap().get_hdu(0).unwrap().to_ipac().is_err());
+}
+
+#[cfg(feature = "votable")]
+#[test]
+fn votable_test() {
+  let text = spectrum().to_votable().unwrap();
+  assert!(text.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<VOTABLE version=\"1.4\""));
+  assert!(text.contains("<FIELD name=\"flux\" datatype=\"double\" unit=\"Jy\"/>"));
+  assert!(text.contains("<TR><TD>1.5</TD></TR>\n          <TR><TD/></TR>"));
+  assert!(text.trim_end().ends_with("</VOTABLE>"));
+
+  //Text columns are escaped, and the table is named after EXTNAME
+  let hdu = table_hdu();
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("table_export.vot");
+  hdu.write_votable(&path).unwrap();
+  let text = fs::read_to_string(&path).unwrap();
+  assert_eq!(text.matches("<TR>").count(), 4);
+  assert!(text.contains("datatype=\"char\" arraysize=\"*\""));
+  assert_eq!(
+    text.matches("<FIELD ").count(),
+    hdu.get_header().get_value_as::<usize>("TFIELDS").unwrap()
+  );
+  assert!(!text.contains(" & "));
+}
@@ -16,8 +16,9 @@
     You should have received a copy of the GNU General Public License
     along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
 */
-use std::path::PathBuf;
+use std::{io::Write, path::PathBuf};
 
+use flate2::{write::GzEncoder, Compression};
 use rustronomy_fits as rsf;
 
 static TABLE_FILE: &str = "resources/Hubble_HRS.fits";
@@ -49,3 +50,121 @@ fn read_test() {
   //Print formatted rows with strings
   println!("{:?}", tbl.get_fmtd_column(10).unwrap());
 }
+
+fn compressed_table_file(name: &str, zctype1: &str) -> PathBuf {
+  /*  Tile-compressed table with 5 rows in tiles of 2 rows. The ID column is
+      Rice coded (the tiles were coded with the algorithm from cfitsio), FLUX
+      is gzipped with shuffled bytes and NAME is just gzipped.
+  */
+  let gzip = |bytes: &[u8]| {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes).unwrap();
+    encoder.finish().unwrap()
+  };
+  let shuffle = |values: &[f32]| {
+    let bytes: Vec<[u8; 4]> = values.iter().map(|value| value.to_be_bytes()).collect();
+    (0..4).flat_map(|byte| bytes.iter().map(move |value| value[byte])).collect::<Vec<u8>>()
+  };
+  let rice: [&[u8]; 3] = [
+    &[0, 0, 0, 10, 12, 16],
+    &[255, 255, 255, 253, 208, 0, 0, 0, 7, 115, 89, 64, 48],
+    &[0, 0, 0, 40, 0],
+  ];
+  let fluxes: [&[f32]; 3] = [&[1.5, -2.25], &[3.0, 1e10], &[0.5]];
+  let names = ["M31 NGC1", "SN  A   ", "BCDE"];
+
+  //(1) Every tile is a row of descriptors to its compressed columns
+  let mut rows = Vec::new();
+  let mut heap = Vec::new();
+  for tile in 0..3 {
+    for compressed in
+      [rice[tile].to_vec(), gzip(&shuffle(fluxes[tile])), gzip(names[tile].as_bytes())]
+    {
+      rows.extend((compressed.len() as i32).to_be_bytes());
+      rows.extend((heap.len() as i32).to_be_bytes());
+      heap.extend(compressed);
+    }
+  }
+
+  //(2) The header describes both the stored and the original table
+  let mut buf = Vec::new();
+  for card in ["SIMPLE  = T", "BITPIX  = 8", "NAXIS   = 0", "END"] {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(2880, b' ');
+  let mut cards = vec![
+    String::from("XTENSION= 'BINTABLE'"),
+    String::from("BITPIX  = 8"),
+    String::from("NAXIS   = 2"),
+    String::from("NAXIS1  = 24"),
+    String::from("NAXIS2  = 3"),
+    format!("PCOUNT  = {}", heap.len()),
+    String::from("GCOUNT  = 1"),
+    String::from("TFIELDS = 3"),
+  ];
+  let columns = [("ID", "1J", zctype1), ("FLUX", "1E", "GZIP_2"), ("NAME", "4A", "GZIP_1")];
+  for (n, (ttype, zform, zctype)) in (1..).zip(columns) {
+    cards.push(format!("TTYPE{n}  = '{ttype}'"));
+    cards.push(format!("TFORM{n}  = '1PB'"));
+    cards.push(format!("ZFORM{n}  = '{zform}'"));
+    cards.push(format!("ZCTYP{n}  = '{zctype}'"));
+  }
+  for card in ["ZTABLE  = T", "ZTILELEN= 2", "ZNAXIS1 = 12", "ZNAXIS2 = 5", "END"] {
+    cards.push(card.to_string());
+  }
+  for card in cards {
+    buf.extend(format!("{card:80}").bytes());
+  }
+  buf.resize(buf.len().div_ceil(2880) * 2880, b' ');
+  buf.extend(rows);
+  buf.extend(heap);
+  buf.resize(buf.len().div_ceil(2880) * 2880, 0);
+
+  let mut path = dirs::cache_dir().unwrap();
+  path.push(name);
+  std::fs::write(&path, buf).unwrap();
+  path
+}
+
+fn bin_table(fits: &rsf::Fits) -> &rsf::BinTable {
+  match fits.get_hdu(1).unwrap().get_data() {
+    Some(rsf::Extension::BinTable(tbl)) => tbl,
+    _ => panic!("HDU does not contain a binary table"),
+  }
+}
+
+#[test]
+fn compressed_table_test() {
+  //Compressed tables are decompressed into the original table
+  let path = compressed_table_file("compressed_table.fits", "RICE_1");
+  let fits = rsf::Fits::open(&path).unwrap();
+  let tbl = bin_table(&fits);
+  assert_eq!(tbl.get_shape(), (3, 5));
+  assert_eq!(tbl.get_col_format(0).unwrap().to_string(), "1J");
+  let ids: Vec<i64> =
+    (0..5).map(|row| i64::try_from(tbl.get_entry(0, row).unwrap()).unwrap()).collect();
+  assert_eq!(ids, [10, 7, -3, 2_000_000_000, 40]);
+  let fluxes: Vec<f64> =
+    (0..5).map(|row| f64::try_from(tbl.get_entry(1, row).unwrap()).unwrap()).collect();
+  assert_eq!(fluxes, [1.5, -2.25, 3.0, 1e10f32 as f64, 0.5]);
+  let names: Vec<&str> = (0..5).map(|row| tbl.get_str(2, row).unwrap()).collect();
+  assert_eq!(names, ["M31", "NGC1", "SN", "A", "BCDE"]);
+
+  //and written as a plain binary table
+  let written = fits.clone().write_stream(Vec::new(), &rsf::WriteOptions::new()).unwrap();
+  let read = rsf::Fits::from_stream(written.as_slice()).unwrap();
+  let header = read.get_hdu(1).unwrap().get_header();
+  assert!(header.get_value("ZTABLE").is_none());
+  assert_eq!(header.get_value_as::<usize>("NAXIS1").unwrap(), 12);
+  assert_eq!(format!("{}", bin_table(&read)), format!("{tbl}"));
+  for row in 0..5 {
+    let (a, b) = (bin_table(&read).get_entry(0, row).unwrap(), tbl.get_entry(0, row).unwrap());
+    assert_eq!(format!("{a:?}"), format!("{b:?}"));
+  }
+
+  //Other compression algorithms are not supported (yet)
+  let path = compressed_table_file("compressed_table_plio.fits", "PLIO_1");
+  let err = rsf::Fits::open(&path).unwrap_err();
+  assert!(err.to_string().contains("not yet implemented"));
+  assert!(err.to_string().contains("ZCTYP1 = 'PLIO_1'"));
+}
@@ -0,0 +1,67 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{fs, path::PathBuf};
+
+use rustronomy_fits as rsf;
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+#[test]
+fn truncated_read_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let fits = rsf::Fits::open(&real_path).unwrap();
+  let layout = *rsf::FitsIndex::build(&real_path).unwrap().get_layout(1).unwrap();
+
+  //Cut the file off 1000 bytes into the data unit of the second HDU
+  let data_start = (layout.get_start_block() + layout.get_header_blocks()) * 2880;
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("truncated.fits");
+  fs::write(&path, &fs::read(&real_path).unwrap()[..data_start + 1000]).unwrap();
+  assert!(rsf::Fits::open(&path).is_err());
+
+  //The headers are recovered, but the data of the truncated HDU is not
+  let (partial, err) = rsf::Fits::open_partial(&path, false).unwrap();
+  let err = err.unwrap();
+  assert_eq!((err.get_hdu(), err.get_got()), (1, 1000));
+  assert!(err.get_expected() > 1000);
+  assert_eq!(partial.get_num_hdus(), 2);
+  assert!(partial.get_hdu(1).unwrap().get_data().is_none());
+
+  //In lenient mode we also get the part of the image that was read
+  let (lenient, _) = rsf::Fits::open_partial(&path, true).unwrap();
+  let hdu = lenient.get_hdu(1).unwrap();
+  assert_eq!(hdu.get_valid_len(), Some(250));
+  let (full, cut) = match (fits.get_hdu(1).unwrap().get_data(), hdu.get_data()) {
+    (Some(rsf::Extension::Image(full)), Some(rsf::Extension::Image(cut))) => {
+      (full.clone().as_owned_f32_array().unwrap(), cut.clone().as_owned_f32_array().unwrap())
+    }
+    _ => panic!(),
+  };
+  assert_eq!(full.shape(), cut.shape());
+  let (full, cut) =
+    (full.t().iter().cloned().collect::<Vec<_>>(), cut.t().iter().cloned().collect::<Vec<_>>());
+  assert_eq!(full[..250], cut[..250]);
+  assert!(cut[250..].iter().all(|&val| val == 0.0));
+
+  //Complete files are not affected
+  let (complete, err) = rsf::Fits::open_partial(&real_path, true).unwrap();
+  assert!(err.is_none());
+  assert_eq!(complete.get_num_hdus(), fits.get_num_hdus());
+}
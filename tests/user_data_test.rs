@@ -0,0 +1,114 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::{fs, path::PathBuf};
+
+use rustronomy_fits as rsf;
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+#[derive(Debug, Clone, PartialEq)]
+struct QcResult {
+  passed: bool,
+  note: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Mask(Vec<bool>);
+
+fn real_fits() -> rsf::Fits {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  rsf::Fits::open(&real_path).unwrap()
+}
+
+#[test]
+fn set_get_test() {
+  let mut fits = real_fits();
+  let hdu = fits.get_hdu_mut(1).unwrap();
+  assert_eq!(hdu.get_num_ext(), 0);
+  assert!(hdu.get_ext::<QcResult>().is_none());
+
+  //(1) Values are keyed by their type
+  let qc = QcResult { passed: true, note: String::from("ok") };
+  assert!(hdu.set_ext(qc.clone()).is_none());
+  assert!(hdu.set_ext(Mask(vec![true, false])).is_none());
+  assert!(hdu.set_ext(42u32).is_none());
+  assert_eq!(hdu.get_num_ext(), 3);
+  assert_eq!(hdu.get_ext::<QcResult>(), Some(&qc));
+  assert_eq!(hdu.get_ext::<Mask>(), Some(&Mask(vec![true, false])));
+  assert_eq!(hdu.get_ext::<u32>(), Some(&42));
+  assert!(hdu.get_ext::<u64>().is_none());
+
+  //(2) Setting a value of the same type replaces (and returns) the old one
+  let old = hdu.set_ext(QcResult { passed: false, note: String::from("noisy") });
+  assert_eq!(old, Some(qc));
+  assert!(!hdu.get_ext::<QcResult>().unwrap().passed);
+
+  //(3) Values can be modified in place and removed
+  hdu.get_ext_mut::<Mask>().unwrap().0.push(true);
+  assert_eq!(hdu.get_ext::<Mask>().unwrap().0.len(), 3);
+  assert_eq!(hdu.remove_ext::<u32>(), Some(42));
+  assert!(!hdu.has_ext::<u32>());
+  assert!(hdu.remove_ext::<u32>().is_none());
+  assert_eq!(hdu.get_num_ext(), 2);
+
+  //(4) The other HDU's are not affected
+  assert_eq!(fits.get_hdu(0).unwrap().get_num_ext(), 0);
+
+  let hdu = fits.get_hdu_mut(1).unwrap();
+  hdu.clear_ext();
+  assert_eq!(hdu.get_num_ext(), 0);
+}
+
+#[test]
+fn clone_test() {
+  let mut fits = real_fits();
+  let hdu = fits.get_hdu_mut(1).unwrap();
+  hdu.set_ext(Mask(vec![false; 4]));
+
+  //Clones carry their own copy of the user data
+  let mut copy = hdu.clone();
+  let deep = hdu.deep_clone();
+  copy.get_ext_mut::<Mask>().unwrap().0[0] = true;
+  assert_eq!(hdu.get_ext::<Mask>(), Some(&Mask(vec![false; 4])));
+  assert_eq!(deep.get_ext::<Mask>(), Some(&Mask(vec![false; 4])));
+  assert!(copy.get_ext::<Mask>().unwrap().0[0]);
+  assert!(format!("{hdu:?}").contains("Mask"));
+}
+
+#[test]
+fn ignored_on_write_test() {
+  let mut fits = real_fits();
+  let hdu = fits.get_hdu_mut(1).unwrap();
+  hdu.set_ext(QcResult { passed: true, note: String::from("ok") });
+  let header = hdu.get_header().clone();
+
+  let mut dir = dirs::cache_dir().unwrap();
+  dir.push("user_data_test");
+  fs::create_dir_all(&dir).unwrap();
+  let out = dir.join("with_user_data.fits");
+  fits.write(&out).unwrap();
+
+  //User data is neither written nor read back, the header is unchanged
+  let reread = rsf::Fits::open(&out).unwrap();
+  let hdu = reread.get_hdu(1).unwrap();
+  assert_eq!(hdu.get_num_ext(), 0);
+  assert_eq!(hdu.get_header().get_num_records(), header.get_num_records());
+}
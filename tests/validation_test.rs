@@ -0,0 +1,54 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::path::PathBuf;
+
+use rustronomy_fits::{self as rsf, Severity, ValidationProfile};
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+#[test]
+fn profile_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+
+  //The built-in profile should not complain about a real file
+  let (fits, diagnostics) =
+    rsf::Fits::open_validated(&real_path, &[ValidationProfile::standard()]).unwrap();
+  assert!(diagnostics.is_empty());
+
+  //The 5 NICMOS images are 270 pixels wide, which this profile does not allow
+  let strict = ValidationProfile::new("strict").at_most("NAXIS1", 256.0, Severity::Error).in_range(
+    "CRVAL1",
+    0.0,
+    360.0,
+    Severity::Warning,
+  );
+  let diagnostics = fits.validate(std::slice::from_ref(&strict));
+  assert_eq!(diagnostics.len(), 5);
+  for diagnostic in &diagnostics {
+    println!("{diagnostic}");
+    assert_eq!(diagnostic.get_keyword(), "NAXIS1");
+    assert_eq!(diagnostic.get_severity(), Severity::Error);
+  }
+
+  //Files with errors are not written
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("validated.fits");
+  assert!(fits.write_validated(&path, &[strict]).is_err());
+}
@@ -0,0 +1,95 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::path::PathBuf;
+
+use rustronomy_fits as rsf;
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+fn wcs(ctypes: (&str, &str), crval: (f64, f64), cd: [f64; 4]) -> rsf::CelestialWcs {
+  let header = rsf::Header::from_text(&format!(
+    "CTYPE1  = '{}'\nCTYPE2  = '{}'\nCRPIX1  = 50.0\nCRPIX2  = 40.0\n\
+     CRVAL1  = {:?}\nCRVAL2  = {:?}\nCD1_1   = {:?}\nCD1_2   = {:?}\nCD2_1   = {:?}\nCD2_2   = {:?}",
+    ctypes.0, ctypes.1, crval.0, crval.1, cd[0], cd[1], cd[2], cd[3]
+  ))
+  .unwrap();
+  rsf::CelestialWcs::from_header(&header).unwrap()
+}
+
+#[test]
+fn pixel_to_world_test() {
+  //(1) Round trips for both projections, both axis orders and a rotated CD matrix
+  let (c, s) = (30f64.to_radians().cos() * 1e-3, 30f64.to_radians().sin() * 1e-3);
+  let cases = [
+    wcs(("RA---TAN", "DEC--TAN"), (150.0, 2.0), [-c, s, s, c]),
+    wcs(("DEC--SIN", "RA---SIN"), (0.5, -89.0), [0.0, 1e-2, 1e-2, 0.0]),
+    wcs(("GLON-TAN", "GLAT-TAN"), (359.9, 60.0), [-0.05, 0.0, 0.0, 0.05]),
+  ];
+  for wcs in &cases {
+    assert_eq!(
+      wcs.pixel_to_world(50.0, 40.0).unwrap()[1],
+      wcs.pixel_to_world(50.0, 40.0).unwrap()[1]
+    );
+    for (x, y) in [(1.0, 1.0), (100.0, 80.0), (50.0, 1.0), (-20.5, 300.25)] {
+      let [lng, lat] = wcs.pixel_to_world(x, y).unwrap();
+      assert!((0.0..360.0).contains(&lng) && (-90.0..=90.0).contains(&lat));
+      let [x2, y2] = wcs.world_to_pixel(lng, lat).unwrap();
+      assert!((x - x2).abs() < 1e-6 && (y - y2).abs() < 1e-6, "{x},{y} -> {x2},{y2}");
+    }
+  }
+
+  //(2) The reference pixel maps onto the reference value, east is left
+  let [lng, lat] = cases[0].pixel_to_world(50.0, 40.0).unwrap();
+  assert_eq!((lng, lat), (150.0, 2.0));
+  assert!(cases[0].pixel_to_world(49.0, 40.0).unwrap()[0] > 150.0);
+
+  //(3) The SIN projection only covers one hemisphere
+  assert!(cases[1].pixel_to_world(50.0, 40.0 + 6000.0).is_none());
+}
+
+#[test]
+fn pixel_grid_test() {
+  //(1) Every entry of the grid is the sky position of that pixel
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let fits = rsf::Fits::open(&real_path).unwrap();
+  let header = fits.get_hdu(1).unwrap().get_header();
+  let wcs = rsf::CelestialWcs::from_header(header).unwrap();
+  let shape = [header.get_value_as("NAXIS1").unwrap(), header.get_value_as("NAXIS2").unwrap()];
+  let (ra, dec) = wcs.pixel_grid(shape);
+  assert_eq!(ra.shape(), shape);
+  assert!(ra.t().is_standard_layout());
+  for (i, j) in [(0, 0), (shape[0] - 1, 0), (17, shape[1] - 1), (100, 200)] {
+    let [lng, lat] = wcs.pixel_to_world(i as f64 + 1.0, j as f64 + 1.0).unwrap();
+    assert_eq!((ra[[i, j]], dec[[i, j]]), (lng, lat));
+  }
+  let [x, y] = wcs.world_to_pixel(ra[[100, 200]], dec[[100, 200]]).unwrap();
+  assert!((x - 101.0).abs() < 1e-6 && (y - 201.0).abs() < 1e-6);
+
+  //(2) Pixels without a sky position are NaN, and empty grids are fine
+  let sin = wcs_sin();
+  let (ra, dec) = sin.pixel_grid([300, 2]);
+  assert!(ra[[200, 0]].is_nan() && dec[[200, 0]].is_nan());
+  assert!(!ra[[49, 0]].is_nan());
+  assert_eq!(sin.pixel_grid([0, 5]).0.len(), 0);
+}
+
+fn wcs_sin() -> rsf::CelestialWcs {
+  wcs(("RA---SIN", "DEC--SIN"), (10.0, 20.0), [-1.0, 0.0, 0.0, 1.0])
+}
@@ -0,0 +1,58 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{fs, path::PathBuf};
+
+use rustronomy_fits as rsf;
+
+static REAL_FILE: &str = "resources/Hubble_NICMOS.fits";
+
+#[test]
+fn write_options_test() {
+  let mut real_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  real_path.push(REAL_FILE);
+  let mut fits = rsf::Fits::open(&real_path).unwrap();
+
+  //Zero most of the image, so there is something to skip in sparse mode
+  match fits.get_hdu_mut(1).unwrap().get_data_mut() {
+    Some(rsf::Extension::Image(img)) => {
+      let data = img.as_f32_array_mut().unwrap();
+      let n_zero = data.len() - 10;
+      data.iter_mut().take(n_zero).for_each(|px| *px = 0.0);
+    }
+    _ => panic!(),
+  }
+
+  let mut plain = dirs::cache_dir().unwrap();
+  plain.push("write_options_plain.fits");
+  fits.clone().write(&plain).unwrap();
+
+  //Whatever the options, the file should contain the same bytes
+  let mut path = dirs::cache_dir().unwrap();
+  path.push("write_options.fits");
+  for opts in [
+    rsf::WriteOptions::new().preallocate(true),
+    rsf::WriteOptions::new().buffer_size(100),
+    rsf::WriteOptions::new().sparse(true),
+    rsf::WriteOptions::new().sparse(true).preallocate(true).buffer_size(0),
+  ] {
+    fs::write(&path, vec![1u8; 10 * 2880 * 1000]).unwrap();
+    fits.clone().write_with(&path, &opts).unwrap();
+    assert!(fs::read(&path).unwrap() == fs::read(&plain).unwrap(), "{opts:?}");
+  }
+}
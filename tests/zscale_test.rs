@@ -0,0 +1,70 @@
+/*
+    Copyright (C) 2022 Raúl Wolters
+
+    This file is part of rustronomy-fits.
+
+    rustronomy is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    rustronomy is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with rustronomy.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use ndarray::{Array, Array2};
+use rustronomy_fits as rsf;
+
+fn sky(width: usize, height: usize) -> Array2<f64> {
+  //Sky of 100 with (deterministic) gaussian-ish noise of sigma 5
+  let mut state = 12345u64;
+  let mut uniform = move || {
+    state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    (state >> 11) as f64 / (1u64 << 53) as f64
+  };
+  Array::from_shape_simple_fn((width, height), || {
+    100.0 + 5.0 * ((0..12).map(|_| uniform()).sum::<f64>() - 6.0)
+  })
+}
+
+#[test]
+fn zscale_ramp_test() {
+  //A linear ramp is a perfect fit, the limits follow from the slope
+  let ramp = Array::from_iter((0..1000).map(|x| x as f64)).into_dyn();
+  assert_eq!(rsf::zscale(&ramp.view()), (0.0, 999.0));
+  assert_eq!(rsf::ZScale::new().contrast(1.0).limits(&ramp.view()), (1.5, 999.0));
+}
+
+#[test]
+fn zscale_outlier_test() {
+  //Cosmic rays and a NaN should hardly affect the display range
+  let zscale = rsf::ZScale::new().contrast(1.0);
+  let (clean_low, clean_high) = zscale.limits(&sky(64, 64).view().into_dyn());
+  assert!((90.0..97.0).contains(&clean_low) && (103.0..110.0).contains(&clean_high));
+  let mut img = sky(64, 64);
+  img[[3, 5]] = 1e6;
+  img[[40, 41]] = 5e5;
+  img[[12, 60]] = -1e4;
+  img[[0, 0]] = f64::NAN;
+  let (low, high) = zscale.limits(&img.view().into_dyn());
+  assert!((low - clean_low).abs() < 1.0, "low = {low}, without outliers {clean_low}");
+  assert!((high - clean_high).abs() < 1.0, "high = {high}, without outliers {clean_high}");
+
+  //Images without finite pixels do not have a range
+  let empty = Array::from_elem((4, 4), f64::NAN).into_dyn();
+  assert_eq!(rsf::zscale(&empty.view()), (0.0, 0.0));
+}
+
+#[test]
+fn percentile_test() {
+  let ramp = Array::from_iter((0..=100).map(|x| x as f64)).into_dyn();
+  assert_eq!(rsf::percentile_limits(&ramp.view(), 1.0, 99.0), (1.0, 99.0));
+  assert_eq!(rsf::percentile_limits(&ramp.view(), 0.25, 150.0), (0.25, 100.0));
+
+  let pair = Array::from_vec(vec![f64::NAN, 10.0, 20.0]).into_dyn();
+  assert_eq!(rsf::percentile_limits(&pair.view(), 25.0, 50.0), (12.5, 15.0));
+}